@@ -0,0 +1,174 @@
+//! `streamline-cli`: add and list tasks and events from the terminal and from scripts.
+//! Records are encrypted and decrypted locally with the key derived from the password,
+//! exactly as the web app does, so both see the same data.
+
+mod records;
+mod session;
+
+use std::io::{self, BufRead, Read, Write};
+
+use anyhow::{anyhow, Result};
+use clap::{Parser, Subcommand};
+use serde_json::Value;
+use streamline_client::{
+    crypto::{auth_password, ClientKey, EncryptedFields},
+    models::auth::LoginRequest,
+    Client, ClientError,
+};
+
+use records::{EventAdd, EventList, TaskAdd, TaskList};
+use session::Session;
+
+#[derive(Debug, Parser)]
+#[command(name = "streamline-cli", version, about)]
+struct Cli {
+    /// Print records as JSON instead of a table
+    #[arg(long, global = true)]
+    json: bool,
+
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Debug, Subcommand)]
+enum Command {
+    /// Sign in and save the session; the password is read from STREAMLINE_PASSWORD or stdin
+    Login {
+        /// Server URL, e.g. https://streamline.example.com
+        #[arg(long, env = "STREAMLINE_SERVER", default_value = "http://localhost:3001")]
+        server: String,
+        #[arg(long)]
+        email: String,
+    },
+    /// Forget the saved session
+    Logout,
+    /// List and add tasks
+    Tasks {
+        #[command(subcommand)]
+        command: TaskCommand,
+    },
+    /// List and add calendar events
+    Events {
+        #[command(subcommand)]
+        command: EventCommand,
+    },
+    /// Encrypt the JSON on stdin for the signed-in user, printing `encrypted_data`, `iv` and `salt`
+    Encrypt,
+    /// Decrypt records (objects with `encrypted_data` and `salt`, or an array of them) read from stdin
+    Decrypt,
+}
+
+#[derive(Debug, Subcommand)]
+enum TaskCommand {
+    List(TaskList),
+    Add(TaskAdd),
+}
+
+#[derive(Debug, Subcommand)]
+enum EventCommand {
+    List(EventList),
+    Add(EventAdd),
+}
+
+#[tokio::main]
+async fn main() {
+    let cli = Cli::parse();
+    if let Err(err) = run(cli).await {
+        if err.downcast_ref::<io::Error>().is_some_and(|err| err.kind() == io::ErrorKind::BrokenPipe) {
+            return;
+        }
+        eprintln!("error: {}", err);
+        if let Some(ClientError::Api { status, .. }) = err.downcast_ref::<ClientError>()
+            && status.as_u16() == 401
+        {
+            eprintln!("The session may have expired; run `streamline-cli login` again");
+        }
+        std::process::exit(1);
+    }
+}
+
+async fn run(cli: Cli) -> Result<()> {
+    match cli.command {
+        Command::Login { server, email } => login(server, email).await,
+        Command::Logout => {
+            if Session::remove()? {
+                println!("Logged out");
+            }
+            Ok(())
+        }
+        Command::Tasks { command } => {
+            let session = Session::load()?;
+            match command {
+                TaskCommand::List(args) => records::list_tasks(&session, args, cli.json).await,
+                TaskCommand::Add(args) => records::add_task(&session, args, cli.json).await,
+            }
+        }
+        Command::Events { command } => {
+            let session = Session::load()?;
+            match command {
+                EventCommand::List(args) => records::list_events(&session, args, cli.json).await,
+                EventCommand::Add(args) => records::add_event(&session, args, cli.json).await,
+            }
+        }
+        Command::Encrypt => {
+            let key = Session::load()?.key();
+            let data: Value = serde_json::from_str(&read_stdin()?)?;
+            print_json(&key.encrypt(&mut rand::rng(), &data)?)
+        }
+        Command::Decrypt => decrypt(&Session::load()?.key()),
+    }
+}
+
+async fn login(server: String, email: String) -> Result<()> {
+    let password = match std::env::var("STREAMLINE_PASSWORD") {
+        Ok(password) => password,
+        Err(_) => {
+            eprint!("Password: ");
+            io::stderr().flush()?;
+            let mut line = String::new();
+            io::stdin().lock().read_line(&mut line)?;
+            line.trim_end_matches(['\r', '\n']).to_string()
+        }
+    };
+
+    // The server only ever sees the hashed password, as with the web app
+    let request = LoginRequest { email: email.clone(), password: auth_password(&password) };
+    let auth = Client::new(&server).login(&request).await?;
+    let session = Session {
+        server,
+        email,
+        access_token: auth.access_token,
+        encryption_key: ClientKey::from_password(&password).as_hex().to_string(),
+    };
+    let path = session.save()?;
+    println!("Logged in as {}; session saved to {}", auth.user.email, path.display());
+    Ok(())
+}
+
+fn decrypt(key: &ClientKey) -> Result<()> {
+    let decrypt_one = |record: Value| -> Result<Value> {
+        let fields: EncryptedFields =
+            serde_json::from_value(record).map_err(|_| anyhow!("Expected objects with encrypted_data, iv and salt"))?;
+        Ok(key.decrypt(&fields.encrypted_data, &fields.salt)?)
+    };
+
+    match serde_json::from_str(&read_stdin()?)? {
+        Value::Array(records) => {
+            let decrypted = records.into_iter().map(decrypt_one).collect::<Result<Vec<_>>>()?;
+            print_json(&decrypted)
+        }
+        record => print_json(&decrypt_one(record)?),
+    }
+}
+
+fn read_stdin() -> Result<String> {
+    let mut input = String::new();
+    io::stdin().read_to_string(&mut input)?;
+    Ok(input)
+}
+
+/// Like `println!`, but a closed pipe (e.g. `| head`) is an error instead of a panic
+fn print_json<T: serde::Serialize>(value: &T) -> Result<()> {
+    writeln!(io::stdout().lock(), "{}", serde_json::to_string_pretty(value)?)?;
+    Ok(())
+}
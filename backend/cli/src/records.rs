@@ -0,0 +1,282 @@
+use anyhow::{anyhow, Result};
+use chrono::{DateTime, Duration, Local, NaiveDate, NaiveDateTime, SecondsFormat, TimeZone, Utc};
+use clap::Args;
+use serde::Serialize;
+use serde_json::{json, Map, Value};
+use streamline_client::{
+    crypto::ClientKey,
+    models::{
+        calendar::CalendarResponse,
+        calendar_event::{CalendarEventResponse, CreateCalendarEventRequest},
+        can_do_list::{CanDoItemResponse, CreateCanDoItemRequest},
+    },
+    routes,
+};
+use uuid::Uuid;
+
+use crate::session::Session;
+
+#[derive(Debug, Args)]
+pub struct TaskList {
+    /// Include completed tasks
+    #[arg(long)]
+    all: bool,
+    /// Only tasks in this project
+    #[arg(long)]
+    project: Option<Uuid>,
+}
+
+#[derive(Debug, Args)]
+pub struct TaskAdd {
+    /// What to do
+    #[arg(required = true)]
+    content: Vec<String>,
+    /// Project to add the task to; defaults to the inbox
+    #[arg(long)]
+    project: Option<Uuid>,
+    /// Due date, e.g. 2025-09-15 or 2025-09-15T17:00 (local time) or RFC 3339
+    #[arg(long, value_parser = parse_time)]
+    due: Option<DateTime<Utc>>,
+    /// Tag; repeat for several
+    #[arg(long = "tag")]
+    tags: Vec<String>,
+    /// Estimated duration
+    #[arg(long)]
+    minutes: Option<i32>,
+}
+
+#[derive(Debug, Args)]
+pub struct EventList {
+    /// Start of the range; defaults to now
+    #[arg(long, value_parser = parse_time)]
+    from: Option<DateTime<Utc>>,
+    /// End of the range; defaults to a week after the start
+    #[arg(long, value_parser = parse_time)]
+    to: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Args)]
+pub struct EventAdd {
+    #[arg(required = true)]
+    title: Vec<String>,
+    /// e.g. 2025-09-15T10:00 (local time) or RFC 3339; a date for all-day events
+    #[arg(long, value_parser = parse_time)]
+    start: DateTime<Utc>,
+    /// Defaults to an hour after the start, or a day for all-day events
+    #[arg(long, value_parser = parse_time)]
+    end: Option<DateTime<Utc>>,
+    /// Length of the event instead of an end
+    #[arg(long, conflicts_with = "end")]
+    minutes: Option<i64>,
+    #[arg(long)]
+    all_day: bool,
+    #[arg(long)]
+    location: Option<String>,
+    /// Calendar to add the event to; defaults to the default calendar
+    #[arg(long)]
+    calendar: Option<Uuid>,
+}
+
+/// A date or date and time in local time, or an RFC 3339 timestamp
+fn parse_time(value: &str) -> Result<DateTime<Utc>, String> {
+    if let Ok(time) = DateTime::parse_from_rfc3339(value) {
+        return Ok(time.with_timezone(&Utc));
+    }
+    let local = ["%Y-%m-%dT%H:%M", "%Y-%m-%d %H:%M"]
+        .iter()
+        .find_map(|format| NaiveDateTime::parse_from_str(value, format).ok())
+        .or_else(|| NaiveDate::parse_from_str(value, "%Y-%m-%d").ok().and_then(|date| date.and_hms_opt(0, 0, 0)))
+        .ok_or_else(|| format!("'{}' is not a date like 2025-09-15 or 2025-09-15T10:00", value))?;
+    Local
+        .from_local_datetime(&local)
+        .earliest()
+        .map(|time| time.with_timezone(&Utc))
+        .ok_or_else(|| format!("{} doesn't exist in the local time zone", value))
+}
+
+fn rfc3339(time: DateTime<Utc>) -> String {
+    time.to_rfc3339_opts(SecondsFormat::Secs, true)
+}
+
+fn local(time: DateTime<Utc>) -> String {
+    time.with_timezone(&Local).format("%Y-%m-%d %H:%M").to_string()
+}
+
+/// The record with its encrypted fields replaced by the decrypted `data`, `null` if this key
+/// can't decrypt it (e.g. a record shared by another user)
+fn decrypted<T: Serialize>(key: &ClientKey, record: &T) -> Result<Value> {
+    let mut fields: Map<String, Value> = serde_json::from_value(serde_json::to_value(record)?)?;
+    let encrypted_data = fields.remove("encrypted_data");
+    fields.remove("iv");
+    let salt = fields.remove("salt");
+    let data = match (encrypted_data.as_ref().and_then(Value::as_str), salt.as_ref().and_then(Value::as_str)) {
+        (Some(encrypted_data), Some(salt)) => key.decrypt(encrypted_data, salt).unwrap_or(Value::Null),
+        _ => Value::Null,
+    };
+    fields.insert("data".to_string(), data);
+    Ok(Value::Object(fields))
+}
+
+fn text<'a>(record: &'a Value, field: &str) -> &'a str {
+    record["data"][field].as_str().unwrap_or("")
+}
+
+pub async fn list_tasks(session: &Session, args: TaskList, json: bool) -> Result<()> {
+    let path = match args.project {
+        Some(project_id) => format!("{}?project_id={}", routes::CAN_DO_LIST, project_id),
+        None => routes::CAN_DO_LIST.to_string(),
+    };
+    let items: Vec<CanDoItemResponse> = session.client().get(&path).await?.data;
+
+    let key = session.key();
+    let mut tasks = Vec::with_capacity(items.len());
+    for item in &items {
+        let task = decrypted(&key, item)?;
+        let completed = item.completed_at.is_some() || task["data"]["completed"].as_bool().unwrap_or(false);
+        if args.all || !completed {
+            tasks.push((task, completed));
+        }
+    }
+
+    if json {
+        return crate::print_json(&tasks.into_iter().map(|(task, _)| task).collect::<Vec<_>>());
+    }
+    for (task, completed) in &tasks {
+        let due = task["due_at"].as_str().and_then(|due| due.parse().ok()).map(local).unwrap_or_default();
+        let tags = task["data"]["tags"]
+            .as_array()
+            .map(|tags| tags.iter().filter_map(Value::as_str).map(|tag| format!(" #{}", tag)).collect::<String>())
+            .unwrap_or_default();
+        let content = if task["data"].is_null() { "<encrypted with another key>" } else { text(task, "content") };
+        println!(
+            "{}  [{}]  {:<16}  {}{}",
+            task["id"].as_str().unwrap_or(""),
+            if *completed { "x" } else { " " },
+            due,
+            content,
+            tags
+        );
+    }
+    Ok(())
+}
+
+pub async fn add_task(session: &Session, args: TaskAdd, json: bool) -> Result<()> {
+    let content = args.content.join(" ");
+    let mut data = json!({ "content": content, "completed": false, "my_day": false });
+    if let Some(due) = args.due {
+        data["due_date"] = json!(rfc3339(due));
+    }
+    if !args.tags.is_empty() {
+        data["tags"] = json!(args.tags);
+    }
+    if let Some(minutes) = args.minutes {
+        data["duration_minutes"] = json!(minutes);
+    }
+
+    let key = session.key();
+    let fields = key.encrypt(&mut rand::rng(), &data)?;
+    let request = CreateCanDoItemRequest {
+        project_id: args.project,
+        encrypted_data: fields.encrypted_data,
+        iv: fields.iv,
+        salt: fields.salt,
+        due_at: args.due,
+        estimated_minutes: args.minutes,
+        search_tokens: Some(key.search_tokens(&format!("{} {}", content, args.tags.join(" ")))),
+        ..Default::default()
+    };
+    let item = session.client().create_can_do_item(&request).await?;
+
+    if json {
+        return crate::print_json(&decrypted(&key, &item)?);
+    }
+    println!("Added task {}", item.id);
+    Ok(())
+}
+
+pub async fn list_events(session: &Session, args: EventList, json: bool) -> Result<()> {
+    let from = args.from.unwrap_or_else(Utc::now);
+    let to = args.to.unwrap_or(from + Duration::days(7));
+    let path = format!("{}?start={}&end={}", routes::CALENDAR_EVENTS, rfc3339(from), rfc3339(to));
+    let mut events: Vec<CalendarEventResponse> = session.client().get(&path).await?.data;
+    events.sort_by_key(|event| event.start_time);
+
+    let key = session.key();
+    let events = events.iter().map(|event| decrypted(&key, event)).collect::<Result<Vec<_>>>()?;
+    if json {
+        return crate::print_json(&events);
+    }
+    for event in &events {
+        let time = |field: &str| event[field].as_str().and_then(|time| time.parse().ok()).map(local).unwrap_or_default();
+        let title = if event["data"].is_null() { "<encrypted with another key>" } else { text(event, "title") };
+        let location = match text(event, "location") {
+            "" => String::new(),
+            location => format!(" @ {}", location),
+        };
+        println!("{:<16} - {:<16}  {}{}", time("start_time"), time("end_time"), title, location);
+    }
+    Ok(())
+}
+
+pub async fn add_event(session: &Session, args: EventAdd, json: bool) -> Result<()> {
+    let client = session.client();
+    let calendar_id = match args.calendar {
+        Some(calendar_id) => calendar_id,
+        None => default_calendar(&client.list_calendars().await?)?,
+    };
+    let end = match (args.end, args.minutes) {
+        (Some(end), _) => end,
+        (None, Some(minutes)) => args.start + Duration::minutes(minutes),
+        (None, None) if args.all_day => args.start + Duration::days(1),
+        (None, None) => args.start + Duration::hours(1),
+    };
+    if end <= args.start {
+        return Err(anyhow!("The event must end after it starts"));
+    }
+
+    // All-day events span whole local days, the end being the day after the last one
+    let first_day = args.start.with_timezone(&Local).date_naive();
+    let last_day = (end - Duration::days(1)).with_timezone(&Local).date_naive().max(first_day);
+
+    let title = args.title.join(" ");
+    let key = session.key();
+    let search_tokens = key.search_tokens(&format!("{} {}", title, args.location.as_deref().unwrap_or("")));
+    let data = json!({
+        "title": title,
+        "location": args.location,
+        "start_time": rfc3339(args.start),
+        "end_time": rfc3339(end),
+        "all_day": args.all_day,
+        "calendar_id": calendar_id,
+    });
+    let fields = key.encrypt(&mut rand::rng(), &data)?;
+    let request = CreateCalendarEventRequest {
+        calendar_id: Some(calendar_id),
+        encrypted_data: fields.encrypted_data,
+        iv: fields.iv,
+        salt: fields.salt,
+        start_time: Some(args.start),
+        end_time: Some(end),
+        is_all_day: args.all_day,
+        start_date: args.all_day.then_some(first_day),
+        end_date: args.all_day.then_some(last_day),
+        search_tokens: Some(search_tokens),
+        ..Default::default()
+    };
+    let event = client.create_calendar_event(&request).await?;
+
+    if json {
+        return crate::print_json(&decrypted(&key, &event)?);
+    }
+    println!("Added event {}", event.id);
+    Ok(())
+}
+
+fn default_calendar(calendars: &[CalendarResponse]) -> Result<Uuid> {
+    calendars
+        .iter()
+        .filter(|calendar| !calendar.is_read_only)
+        .max_by_key(|calendar| calendar.is_default)
+        .map(|calendar| calendar.id)
+        .ok_or_else(|| anyhow!("No calendar to add the event to; create one in the app or pass --calendar"))
+}
@@ -0,0 +1,67 @@
+use std::{env, fs, path::PathBuf};
+
+use anyhow::{anyhow, Context, Result};
+use serde::{Deserialize, Serialize};
+use streamline_client::{crypto::ClientKey, Client};
+
+/// What `login` saves: the server, an access token and the encryption key derived from the
+/// password, which the web app keeps in its `encKey` cookie. The password itself isn't stored.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Session {
+    pub server: String,
+    pub email: String,
+    pub access_token: String,
+    pub encryption_key: String,
+}
+
+impl Session {
+    /// `$STREAMLINE_CONFIG_DIR`, else `$XDG_CONFIG_HOME/streamline`, else `~/.config/streamline`
+    fn path() -> Result<PathBuf> {
+        let dir = match (env::var_os("STREAMLINE_CONFIG_DIR"), env::var_os("XDG_CONFIG_HOME"), env::var_os("HOME")) {
+            (Some(dir), _, _) => PathBuf::from(dir),
+            (None, Some(config), _) => PathBuf::from(config).join("streamline"),
+            (None, None, Some(home)) => PathBuf::from(home).join(".config").join("streamline"),
+            (None, None, None) => return Err(anyhow!("Set STREAMLINE_CONFIG_DIR or HOME to store the session")),
+        };
+        Ok(dir.join("session.json"))
+    }
+
+    pub fn load() -> Result<Self> {
+        let path = Self::path()?;
+        let contents = fs::read_to_string(&path)
+            .map_err(|_| anyhow!("Not logged in; run `streamline-cli login --email <email>` first"))?;
+        serde_json::from_str(&contents).with_context(|| format!("{} is not a valid session", path.display()))
+    }
+
+    pub fn save(&self) -> Result<PathBuf> {
+        let path = Self::path()?;
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir).with_context(|| format!("Creating {}", dir.display()))?;
+        }
+        fs::write(&path, serde_json::to_string_pretty(self)?).with_context(|| format!("Writing {}", path.display()))?;
+        // The key decrypts every record of the account, so only the user may read it
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            fs::set_permissions(&path, fs::Permissions::from_mode(0o600))?;
+        }
+        Ok(path)
+    }
+
+    /// Forget the saved session; true if there was one
+    pub fn remove() -> Result<bool> {
+        match fs::remove_file(Self::path()?) {
+            Ok(()) => Ok(true),
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => Ok(false),
+            Err(err) => Err(err.into()),
+        }
+    }
+
+    pub fn client(&self) -> Client {
+        Client::new(&self.server).with_token(&self.access_token)
+    }
+
+    pub fn key(&self) -> ClientKey {
+        ClientKey::from_hex(&self.encryption_key)
+    }
+}
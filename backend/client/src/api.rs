@@ -0,0 +1,134 @@
+//! Typed wrappers for the core endpoints; anything not covered here is reachable
+//! through [`Client::get`] and friends with the paths in [`crate::routes`].
+
+use uuid::Uuid;
+
+use crate::models::{
+    auth::{AuthResponse, CreateUserRequest, LoginRequest, UserResponse},
+    calendar::{CalendarResponse, CreateCalendarRequest, UpdateCalendarRequest},
+    calendar_event::{CalendarEventResponse, CreateCalendarEventRequest, UpdateCalendarEventRequest},
+    can_do_list::{CanDoItemResponse, CreateCanDoItemRequest, UpdateCanDoItemRequest},
+    note::{CreateNoteRequest, NoteResponse, UpdateNoteRequest},
+    project::{CreateProjectRequest, ProjectResponse, UpdateProjectRequest},
+};
+use crate::{routes, Client, Result};
+
+impl Client {
+    pub async fn register(&self, request: &CreateUserRequest) -> Result<AuthResponse> {
+        Ok(self.post(routes::AUTH_REGISTER, request).await?.data)
+    }
+
+    /// Sign in; pass the returned `access_token` to [`Client::with_token`]
+    pub async fn login(&self, request: &LoginRequest) -> Result<AuthResponse> {
+        Ok(self.post(routes::AUTH_LOGIN, request).await?.data)
+    }
+
+    pub async fn me(&self) -> Result<UserResponse> {
+        Ok(self.get(routes::AUTH_ME).await?.data)
+    }
+
+    pub async fn list_projects(&self) -> Result<Vec<ProjectResponse>> {
+        Ok(self.get(routes::PROJECTS).await?.data)
+    }
+
+    pub async fn get_project(&self, id: Uuid) -> Result<ProjectResponse> {
+        Ok(self.get(&routes::project(id)).await?.data)
+    }
+
+    pub async fn create_project(&self, request: &CreateProjectRequest) -> Result<ProjectResponse> {
+        Ok(self.post(routes::PROJECTS, request).await?.data)
+    }
+
+    pub async fn update_project(&self, id: Uuid, request: &UpdateProjectRequest) -> Result<ProjectResponse> {
+        Ok(self.put(&routes::project(id), request).await?.data)
+    }
+
+    /// Move a project and its subtree to the trash
+    pub async fn delete_project(&self, id: Uuid) -> Result<()> {
+        self.delete::<()>(&routes::project(id)).await.map(|_| ())
+    }
+
+    pub async fn list_can_do_items(&self) -> Result<Vec<CanDoItemResponse>> {
+        Ok(self.get(routes::CAN_DO_LIST).await?.data)
+    }
+
+    pub async fn get_can_do_item(&self, id: Uuid) -> Result<CanDoItemResponse> {
+        Ok(self.get(&routes::can_do_item(id)).await?.data)
+    }
+
+    pub async fn create_can_do_item(&self, request: &CreateCanDoItemRequest) -> Result<CanDoItemResponse> {
+        Ok(self.post(routes::CAN_DO_LIST, request).await?.data)
+    }
+
+    pub async fn update_can_do_item(&self, id: Uuid, request: &UpdateCanDoItemRequest) -> Result<CanDoItemResponse> {
+        Ok(self.put(&routes::can_do_item(id), request).await?.data)
+    }
+
+    pub async fn delete_can_do_item(&self, id: Uuid) -> Result<()> {
+        self.delete::<()>(&routes::can_do_item(id)).await.map(|_| ())
+    }
+
+    pub async fn list_calendars(&self) -> Result<Vec<CalendarResponse>> {
+        Ok(self.get(routes::CALENDARS).await?.data)
+    }
+
+    pub async fn get_calendar(&self, id: Uuid) -> Result<CalendarResponse> {
+        Ok(self.get(&routes::calendar(id)).await?.data)
+    }
+
+    pub async fn create_calendar(&self, request: &CreateCalendarRequest) -> Result<CalendarResponse> {
+        Ok(self.post(routes::CALENDARS, request).await?.data)
+    }
+
+    pub async fn update_calendar(&self, id: Uuid, request: &UpdateCalendarRequest) -> Result<CalendarResponse> {
+        Ok(self.put(&routes::calendar(id), request).await?.data)
+    }
+
+    pub async fn delete_calendar(&self, id: Uuid) -> Result<()> {
+        self.delete::<()>(&routes::calendar(id)).await.map(|_| ())
+    }
+
+    pub async fn list_calendar_events(&self) -> Result<Vec<CalendarEventResponse>> {
+        Ok(self.get(routes::CALENDAR_EVENTS).await?.data)
+    }
+
+    pub async fn get_calendar_event(&self, id: Uuid) -> Result<CalendarEventResponse> {
+        Ok(self.get(&routes::calendar_event(id)).await?.data)
+    }
+
+    pub async fn create_calendar_event(&self, request: &CreateCalendarEventRequest) -> Result<CalendarEventResponse> {
+        Ok(self.post(routes::CALENDAR_EVENTS, request).await?.data)
+    }
+
+    pub async fn update_calendar_event(
+        &self,
+        id: Uuid,
+        request: &UpdateCalendarEventRequest,
+    ) -> Result<CalendarEventResponse> {
+        Ok(self.put(&routes::calendar_event(id), request).await?.data)
+    }
+
+    pub async fn delete_calendar_event(&self, id: Uuid) -> Result<()> {
+        self.delete::<()>(&routes::calendar_event(id)).await.map(|_| ())
+    }
+
+    pub async fn list_notes(&self) -> Result<Vec<NoteResponse>> {
+        Ok(self.get(routes::NOTES).await?.data)
+    }
+
+    pub async fn get_note(&self, id: Uuid) -> Result<NoteResponse> {
+        Ok(self.get(&routes::note(id)).await?.data)
+    }
+
+    pub async fn create_note(&self, request: &CreateNoteRequest) -> Result<NoteResponse> {
+        Ok(self.post(routes::NOTES, request).await?.data)
+    }
+
+    pub async fn update_note(&self, id: Uuid, request: &UpdateNoteRequest) -> Result<NoteResponse> {
+        Ok(self.put(&routes::note(id), request).await?.data)
+    }
+
+    pub async fn delete_note(&self, id: Uuid) -> Result<()> {
+        self.delete::<()>(&routes::note(id)).await.map(|_| ())
+    }
+}
@@ -0,0 +1,132 @@
+use reqwest::{Method, StatusCode};
+use serde::{Deserialize, Serialize, de::DeserializeOwned};
+use uuid::Uuid;
+
+pub use streamline_models::ApiResponse;
+
+/// Header the server uses to suppress echoing a change back to its origin socket
+const CONNECTION_ID_HEADER: &str = "x-connection-id";
+/// Pins the REST API version the request and response types were written for
+const API_VERSION_HEADER: &str = "api-version";
+const API_VERSION: &str = "1";
+
+/// Error body returned for non-2xx responses
+#[derive(Debug, Clone, Deserialize)]
+pub struct ErrorResponse {
+    pub error: String,
+    /// Stable machine-readable code, e.g. `AUTH_INVALID_CREDENTIALS`
+    #[serde(default)]
+    pub code: Option<String>,
+    pub details: Option<String>,
+    /// One entry per invalid request field, for validation errors tied to fields
+    #[serde(default)]
+    pub fields: Vec<FieldError>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct FieldError {
+    pub field: String,
+    pub message: String,
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum ClientError {
+    #[error("HTTP error: {0}")]
+    Http(#[from] reqwest::Error),
+
+    #[error("API error ({status}): {}", .body.details.as_deref().unwrap_or(&.body.error))]
+    Api { status: StatusCode, body: ErrorResponse },
+}
+
+pub type Result<T> = std::result::Result<T, ClientError>;
+
+#[derive(Debug, Clone)]
+pub struct Client {
+    http: reqwest::Client,
+    base_url: String,
+    token: Option<String>,
+    connection_id: Option<Uuid>,
+}
+
+impl Client {
+    pub fn new(base_url: impl Into<String>) -> Self {
+        Self {
+            http: reqwest::Client::new(),
+            base_url: base_url.into().trim_end_matches('/').to_string(),
+            token: None,
+            connection_id: None,
+        }
+    }
+
+    /// Authenticate subsequent requests with a bearer token
+    pub fn with_token(mut self, token: impl Into<String>) -> Self {
+        self.token = Some(token.into());
+        self
+    }
+
+    /// Tag subsequent requests with the id of this client's WebSocket connection
+    pub fn with_connection_id(mut self, connection_id: Uuid) -> Self {
+        self.connection_id = Some(connection_id);
+        self
+    }
+
+    /// Open a WebSocket subscription authenticated with this client's token
+    pub fn subscribe(&self) -> crate::Subscriber {
+        crate::Subscriber::new(&self.base_url, self.token.clone().unwrap_or_default())
+    }
+
+    pub async fn get<T: DeserializeOwned>(&self, path: &str) -> Result<ApiResponse<T>> {
+        self.send(Method::GET, path, None::<&()>).await
+    }
+
+    pub async fn post<B: Serialize, T: DeserializeOwned>(
+        &self,
+        path: &str,
+        body: &B,
+    ) -> Result<ApiResponse<T>> {
+        self.send(Method::POST, path, Some(body)).await
+    }
+
+    pub async fn put<B: Serialize, T: DeserializeOwned>(
+        &self,
+        path: &str,
+        body: &B,
+    ) -> Result<ApiResponse<T>> {
+        self.send(Method::PUT, path, Some(body)).await
+    }
+
+    pub async fn delete<T: DeserializeOwned>(&self, path: &str) -> Result<ApiResponse<T>> {
+        self.send(Method::DELETE, path, None::<&()>).await
+    }
+
+    async fn send<B: Serialize, T: DeserializeOwned>(
+        &self,
+        method: Method,
+        path: &str,
+        body: Option<&B>,
+    ) -> Result<ApiResponse<T>> {
+        let mut request = self
+            .http
+            .request(method, format!("{}{}", self.base_url, path))
+            .header(API_VERSION_HEADER, API_VERSION);
+
+        if let Some(token) = &self.token {
+            request = request.bearer_auth(token);
+        }
+        if let Some(connection_id) = self.connection_id {
+            request = request.header(CONNECTION_ID_HEADER, connection_id.to_string());
+        }
+        if let Some(body) = body {
+            request = request.json(body);
+        }
+
+        let response = request.send().await?;
+        let status = response.status();
+        if !status.is_success() {
+            let body = response.json::<ErrorResponse>().await?;
+            return Err(ClientError::Api { status, body });
+        }
+
+        Ok(response.json().await?)
+    }
+}
@@ -0,0 +1,163 @@
+//! The web client's record encryption (`frontend/utils/cryptography/encryption.ts`), so Rust
+//! clients read and write records the web app can read. The web app builds it from CryptoJS:
+//!
+//! - its sign-in password is PBKDF2-SHA256 of the typed password with a fixed salt
+//! - its encryption key is PBKDF2-SHA256 of the typed password with another fixed salt
+//! - each record gets a random `salt`; PBKDF2 of the key with it gives a per-record passphrase
+//! - the payload JSON is encrypted with CryptoJS' passphrase mode: AES-256-CBC with key and IV
+//!   from OpenSSL's `EVP_BytesToKey` (MD5), stored as base64 of `Salted__`, an 8-byte salt and
+//!   the ciphertext. The record's `iv` is stored but not used by this mode.
+//!
+//! Every PBKDF2 input and output is hex text, used as UTF-8 bytes.
+//!
+//! Records can also carry `search_tokens`, a blind index the server matches searches against:
+//! each distinct lowercased word becomes the first 16 bytes, in hex, of HMAC-SHA256 keyed with
+//! HMAC-SHA256(key, `"streamline_search_tokens"`). See [`ClientKey::search_tokens`].
+
+use aes::cipher::{block_padding::Pkcs7, BlockDecryptMut, BlockEncryptMut, KeyIvInit};
+use base64::{engine::general_purpose::STANDARD, Engine as _};
+use hmac::{Hmac, Mac};
+use md5::{Digest, Md5};
+use rand::Rng;
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use sha2::Sha256;
+
+const AUTH_SALT: &str = "streamline_auth_salt_2024";
+const ENCRYPTION_SALT: &str = "streamline_encryption_salt_2024";
+const PASSWORD_ITERATIONS: u32 = 10_000;
+const RECORD_ITERATIONS: u32 = 1_000;
+const OPENSSL_MAGIC: &[u8] = b"Salted__";
+const SEARCH_KEY_CONTEXT: &str = "streamline_search_tokens";
+const SEARCH_TOKEN_BYTES: usize = 16;
+
+type Aes256CbcEnc = cbc::Encryptor<aes::Aes256>;
+type Aes256CbcDec = cbc::Decryptor<aes::Aes256>;
+
+#[derive(Debug, thiserror::Error)]
+pub enum CryptoError {
+    #[error("encrypted_data is not a CryptoJS payload")]
+    Format,
+
+    /// Wrong key, or the record was encrypted by someone else
+    #[error("decryption failed")]
+    Decrypt,
+
+    #[error("decrypted payload is not the expected JSON: {0}")]
+    Json(#[from] serde_json::Error),
+}
+
+fn pbkdf2_hex(password: &str, salt: &str, iterations: u32) -> String {
+    let mut key = [0u8; 32];
+    pbkdf2::pbkdf2_hmac::<Sha256>(password.as_bytes(), salt.as_bytes(), iterations, &mut key);
+    hex::encode(key)
+}
+
+/// What the client sends as the password when signing up or in with `password`
+pub fn auth_password(password: &str) -> String {
+    pbkdf2_hex(password, AUTH_SALT, PASSWORD_ITERATIONS)
+}
+
+/// An encrypted record payload, as stored in `encrypted_data`, `iv` and `salt`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EncryptedFields {
+    pub encrypted_data: String,
+    pub iv: String,
+    pub salt: String,
+}
+
+/// The key the client derives from a user's password
+#[derive(Clone)]
+pub struct ClientKey(String);
+
+impl ClientKey {
+    pub fn from_password(password: &str) -> Self {
+        Self(pbkdf2_hex(password, ENCRYPTION_SALT, PASSWORD_ITERATIONS))
+    }
+
+    /// A key saved with [`ClientKey::as_hex`], like the web app's `encKey` cookie
+    pub fn from_hex(key: impl Into<String>) -> Self {
+        Self(key.into())
+    }
+
+    pub fn as_hex(&self) -> &str {
+        &self.0
+    }
+
+    pub fn encrypt<T: Serialize>(&self, rng: &mut impl Rng, data: &T) -> serde_json::Result<EncryptedFields> {
+        let salt = hex::encode(rng.random::<[u8; 16]>());
+        let iv = hex::encode(rng.random::<[u8; 16]>());
+        let passphrase = pbkdf2_hex(&self.0, &salt, RECORD_ITERATIONS);
+
+        let mut openssl_salt = [0u8; 8];
+        rng.fill_bytes(&mut openssl_salt);
+        let (key, cbc_iv) = evp_bytes_to_key(passphrase.as_bytes(), &openssl_salt);
+        let ciphertext = Aes256CbcEnc::new(&key.into(), &cbc_iv.into())
+            .encrypt_padded_vec_mut::<Pkcs7>(&serde_json::to_vec(data)?);
+
+        let mut payload = Vec::with_capacity(16 + ciphertext.len());
+        payload.extend_from_slice(OPENSSL_MAGIC);
+        payload.extend_from_slice(&openssl_salt);
+        payload.extend_from_slice(&ciphertext);
+        Ok(EncryptedFields {
+            encrypted_data: STANDARD.encode(payload),
+            iv,
+            salt,
+        })
+    }
+
+    /// Decrypt a record's `encrypted_data` with its `salt`
+    pub fn decrypt<T: DeserializeOwned>(&self, encrypted_data: &str, salt: &str) -> Result<T, CryptoError> {
+        let payload = STANDARD.decode(encrypted_data).map_err(|_| CryptoError::Format)?;
+        let (openssl_salt, ciphertext) = payload
+            .strip_prefix(OPENSSL_MAGIC)
+            .and_then(|rest| rest.split_first_chunk::<8>())
+            .ok_or(CryptoError::Format)?;
+
+        let passphrase = pbkdf2_hex(&self.0, salt, RECORD_ITERATIONS);
+        let (key, cbc_iv) = evp_bytes_to_key(passphrase.as_bytes(), openssl_salt);
+        let plaintext = Aes256CbcDec::new(&key.into(), &cbc_iv.into())
+            .decrypt_padded_vec_mut::<Pkcs7>(ciphertext)
+            .map_err(|_| CryptoError::Decrypt)?;
+        Ok(serde_json::from_slice(&plaintext)?)
+    }
+
+    /// Blind-index tokens of the words in `text`, for a record's `search_tokens` and for
+    /// `GET /api/search?q_tokens=`. Equal words give equal tokens, but only with this key.
+    pub fn search_tokens(&self, text: &str) -> Vec<String> {
+        let search_key = hmac_sha256(self.0.as_bytes(), SEARCH_KEY_CONTEXT.as_bytes());
+        let mut tokens: Vec<String> = text
+            .split(|c: char| !c.is_alphanumeric())
+            .filter(|word| !word.is_empty())
+            .map(|word| hex::encode(&hmac_sha256(&search_key, word.to_lowercase().as_bytes())[..SEARCH_TOKEN_BYTES]))
+            .collect();
+        tokens.sort_unstable();
+        tokens.dedup();
+        tokens
+    }
+}
+
+fn hmac_sha256(key: &[u8], message: &[u8]) -> [u8; 32] {
+    let mut mac = Hmac::<Sha256>::new_from_slice(key).expect("HMAC accepts keys of any length");
+    mac.update(message);
+    mac.finalize().into_bytes().into()
+}
+
+/// OpenSSL's `EVP_BytesToKey` with MD5 and one iteration: a 32-byte key and a 16-byte IV
+fn evp_bytes_to_key(passphrase: &[u8], salt: &[u8; 8]) -> ([u8; 32], [u8; 16]) {
+    let mut derived = Vec::with_capacity(48);
+    let mut block: Vec<u8> = Vec::new();
+    while derived.len() < 48 {
+        let mut hasher = Md5::new();
+        hasher.update(&block);
+        hasher.update(passphrase);
+        hasher.update(salt);
+        block = hasher.finalize().to_vec();
+        derived.extend_from_slice(&block);
+    }
+
+    let mut key = [0u8; 32];
+    let mut iv = [0u8; 16];
+    key.copy_from_slice(&derived[..32]);
+    iv.copy_from_slice(&derived[32..48]);
+    (key, iv)
+}
@@ -0,0 +1,36 @@
+//! Typed client for the Streamline Scheduler API.
+//!
+//! The route table in [`routes`] is the single source of truth for API paths:
+//! the server mounts its handlers on these constants, so a renamed route breaks
+//! the build of every consumer instead of failing at runtime. Request and
+//! response bodies are the server's own types from `streamline-models`,
+//! re-exported as [`models`], and [`crypto`] encrypts and decrypts record
+//! contents the way the web app does.
+//!
+//! ```no_run
+//! # async fn run() -> streamline_client::Result<()> {
+//! use streamline_client::{models::auth::LoginRequest, Client, SubscriberEvent};
+//!
+//! let login = LoginRequest { email: "bot@example.com".into(), password: "secret".into() };
+//! let auth = Client::new("https://streamline.example.com").login(&login).await?;
+//! let client = Client::new("https://streamline.example.com").with_token(auth.access_token);
+//!
+//! let mut subscriber = client.subscribe();
+//! while let Some(event) = subscriber.next().await {
+//!     if let SubscriberEvent::Change(change) = event {
+//!         println!("{} {} {:?}", change.event_type, change.table, change.record_id);
+//!     }
+//! }
+//! # Ok(())
+//! # }
+//! ```
+
+mod api;
+mod client;
+pub mod crypto;
+pub mod routes;
+mod subscriber;
+
+pub use client::{ApiResponse, Client, ClientError, ErrorResponse, FieldError, Result};
+pub use streamline_models as models;
+pub use subscriber::{Subscriber, SubscriberEvent};
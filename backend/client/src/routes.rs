@@ -0,0 +1,481 @@
+//! API route patterns and helpers for building concrete paths.
+//!
+//! Constants use axum's `{id}` placeholder syntax so the server can mount them
+//! directly; the functions below fill in the placeholder for clients.
+
+use uuid::Uuid;
+
+pub const HEALTH: &str = "/health";
+pub const HEALTH_LIVE: &str = "/health/live";
+pub const HEALTH_READY: &str = "/health/ready";
+pub const WEBSOCKET: &str = "/ws";
+pub const META: &str = "/api/meta";
+
+pub const AUTH_REGISTER: &str = "/api/auth/register";
+pub const AUTH_LOGIN: &str = "/api/auth/login";
+pub const AUTH_ME: &str = "/api/auth/me";
+pub const AUTH_SESSIONS: &str = "/api/auth/sessions";
+pub const AUTH_SESSION: &str = "/api/auth/sessions/{id}";
+
+pub const ACTIVITY: &str = "/api/activity";
+pub const ADMIN_IMPERSONATE: &str = "/api/admin/impersonate";
+pub const ADMIN_IMPERSONATION: &str = "/api/admin/impersonate/{id}";
+pub const ADMIN_BACKUP: &str = "/api/admin/backup";
+pub const ADMIN_RESTORE: &str = "/api/admin/restore";
+pub const ADMIN_USER_FEATURE: &str = "/api/admin/users/{id}/features/{feature}";
+pub const ADMIN_BROADCAST: &str = "/api/admin/broadcast";
+pub const ANNOUNCEMENTS: &str = "/api/announcements";
+
+pub const PROJECTS: &str = "/api/projects";
+pub const PROJECTS_REORDER: &str = "/api/projects/reorder";
+pub const PROJECTS_DEFAULT: &str = "/api/projects/default";
+pub const PROJECTS_SHARED: &str = "/api/projects/shared";
+pub const PROJECT: &str = "/api/projects/{id}";
+pub const PROJECT_RESTORE: &str = "/api/projects/{id}/restore";
+pub const PROJECT_DUPLICATE: &str = "/api/projects/{id}/duplicate";
+pub const PROJECT_MOVE: &str = "/api/projects/{id}/move";
+pub const PROJECT_PIN: &str = "/api/projects/{id}/pin";
+pub const PROJECT_REVISIONS: &str = "/api/projects/{id}/revisions";
+pub const PROJECT_REVERT: &str = "/api/projects/{id}/revert/{revision}";
+pub const PROJECT_MEMBERS: &str = "/api/projects/{id}/members";
+pub const PROJECT_MEMBER: &str = "/api/projects/{id}/members/{user_id}";
+
+pub const ORGANIZATIONS: &str = "/api/organizations";
+pub const ORGANIZATION: &str = "/api/organizations/{id}";
+pub const ORGANIZATION_MEMBERS: &str = "/api/organizations/{id}/members";
+pub const ORGANIZATION_MEMBER: &str = "/api/organizations/{id}/members/{user_id}";
+pub const ORGANIZATION_EXPORT: &str = "/api/organizations/{id}/export";
+
+pub const CAN_DO_LIST: &str = "/api/can-do-list";
+pub const CAN_DO_LIST_REORDER: &str = "/api/can-do-list/reorder";
+pub const CAN_DO_ITEM: &str = "/api/can-do-list/{id}";
+pub const CAN_DO_ITEM_RESTORE: &str = "/api/can-do-list/{id}/restore";
+pub const CAN_DO_ITEM_PIN: &str = "/api/can-do-list/{id}/pin";
+pub const CAN_DO_ITEM_TIMER_START: &str = "/api/can-do-list/{id}/timer/start";
+pub const CAN_DO_ITEM_TIMER_STOP: &str = "/api/can-do-list/{id}/timer/stop";
+pub const CAN_DO_ITEM_REVISIONS: &str = "/api/can-do-list/{id}/revisions";
+pub const CAN_DO_ITEM_REVERT: &str = "/api/can-do-list/{id}/revert/{revision}";
+pub const CAN_DO_ITEM_LINKED_EVENTS: &str = "/api/can-do-list/{id}/linked-events";
+
+pub const CALENDARS: &str = "/api/calendars";
+pub const CALENDARS_REORDER: &str = "/api/calendars/reorder";
+pub const CALENDAR: &str = "/api/calendars/{id}";
+pub const CALENDAR_RESTORE: &str = "/api/calendars/{id}/restore";
+pub const CALENDAR_PIN: &str = "/api/calendars/{id}/pin";
+pub const CALENDAR_REVISIONS: &str = "/api/calendars/{id}/revisions";
+pub const CALENDAR_REVERT: &str = "/api/calendars/{id}/revert/{revision}";
+
+pub const CALENDAR_EVENTS: &str = "/api/calendar-events";
+pub const CALENDAR_EVENTS_BATCH: &str = "/api/calendar-events/batch";
+pub const CALENDAR_EVENT: &str = "/api/calendar-events/{id}";
+pub const CALENDAR_EVENT_RESTORE: &str = "/api/calendar-events/{id}/restore";
+pub const CALENDAR_EVENT_DUPLICATE: &str = "/api/calendar-events/{id}/duplicate";
+pub const CALENDAR_EVENT_OCCURRENCES: &str = "/api/calendar-events/occurrences";
+pub const CALENDAR_EVENT_SERIES_OCCURRENCES: &str = "/api/calendar-events/{id}/occurrences";
+pub const CALENDAR_EVENT_REVISIONS: &str = "/api/calendar-events/{id}/revisions";
+pub const CALENDAR_EVENT_REVERT: &str = "/api/calendar-events/{id}/revert/{revision}";
+pub const CALENDAR_EVENT_LINK: &str = "/api/calendar-events/{id}/link";
+pub const CALENDAR_EVENT_TIME: &str = "/api/calendar-events/{id}/time";
+pub const REMINDERS: &str = "/api/reminders";
+pub const REMINDER: &str = "/api/reminders/{id}";
+pub const EVENT_ATTENDEES: &str = "/api/event-attendees";
+pub const EVENT_ATTENDEE: &str = "/api/event-attendees/{id}";
+pub const EVENT_ATTENDEE_RSVP: &str = "/api/event-attendees/{id}/rsvp";
+pub const TIME_ENTRIES: &str = "/api/time-entries";
+pub const TIME_ENTRY_TOTALS: &str = "/api/time-entries/totals";
+pub const TIME_ENTRY: &str = "/api/time-entries/{id}";
+pub const FOCUS_SESSIONS: &str = "/api/focus-sessions";
+pub const FOCUS_SESSION_CURRENT: &str = "/api/focus-sessions/current";
+pub const FOCUS_SESSION_STOP: &str = "/api/focus-sessions/{id}/stop";
+pub const FOCUS_SESSION_CANCEL: &str = "/api/focus-sessions/{id}/cancel";
+pub const HABITS: &str = "/api/habits";
+pub const HABIT: &str = "/api/habits/{id}";
+pub const HABIT_CHECKIN: &str = "/api/habits/{id}/checkin";
+pub const HABIT_CHECKINS: &str = "/api/habits/{id}/checkins";
+pub const GOALS: &str = "/api/goals";
+pub const GOAL: &str = "/api/goals/{id}";
+pub const GOAL_PROJECT: &str = "/api/goals/{id}/projects/{project_id}";
+pub const GOAL_TASK: &str = "/api/goals/{id}/tasks/{task_id}";
+pub const TEMPLATES: &str = "/api/templates";
+pub const TEMPLATE: &str = "/api/templates/{id}";
+pub const TEMPLATE_INSTANTIATE: &str = "/api/templates/{id}/instantiate";
+
+pub const NOTES: &str = "/api/notes";
+pub const NOTE: &str = "/api/notes/{id}";
+pub const NOTE_RESTORE: &str = "/api/notes/{id}/restore";
+pub const NOTE_REVISIONS: &str = "/api/notes/{id}/revisions";
+pub const NOTE_REVERT: &str = "/api/notes/{id}/revert/{revision}";
+
+pub const POLLS: &str = "/api/polls";
+pub const POLL: &str = "/api/polls/{id}";
+pub const POLL_FINALIZE: &str = "/api/polls/{id}/finalize";
+pub const POLL_VOTE: &str = "/api/polls/{id}/votes/{vote_id}";
+pub const PUBLIC_POLL: &str = "/api/polls/public/{token}";
+pub const PUBLIC_POLL_VOTES: &str = "/api/polls/public/{token}/votes";
+
+pub const BOOKING_PAGES: &str = "/api/booking-pages";
+pub const BOOKING_PAGE: &str = "/api/booking-pages/{id}";
+pub const BOOKING_PAGE_BOOKINGS: &str = "/api/booking-pages/{id}/bookings";
+pub const PUBLIC_BOOKING_PAGE: &str = "/api/booking-pages/public/{token}";
+pub const PUBLIC_BOOKING_PAGE_SLOTS: &str = "/api/booking-pages/public/{token}/slots";
+pub const PUBLIC_BOOKING_PAGE_BOOKINGS: &str = "/api/booking-pages/public/{token}/bookings";
+
+pub const CALENDAR_SUBSCRIPTIONS: &str = "/api/calendar-subscriptions";
+pub const CALENDAR_SUBSCRIPTION: &str = "/api/calendar-subscriptions/{id}";
+pub const CALENDAR_SUBSCRIPTION_REFRESH: &str = "/api/calendar-subscriptions/{id}/refresh";
+
+pub const WEBHOOKS: &str = "/api/webhooks";
+pub const WEBHOOK: &str = "/api/webhooks/{id}";
+pub const WEBHOOK_DELIVERIES: &str = "/api/webhooks/{id}/deliveries";
+pub const WEBHOOK_REDELIVER: &str = "/api/webhooks/{id}/deliveries/{delivery_id}/redeliver";
+pub const WEBHOOK_ROTATE_SECRET: &str = "/api/webhooks/{id}/rotate-secret";
+pub const WEBHOOK_VERIFY_SIGNATURE: &str = "/api/webhooks/{id}/verify-signature";
+
+pub const INBOUND_EMAIL: &str = "/api/inbound-email";
+pub const INBOUND_EMAIL_ROTATE: &str = "/api/inbound-email/rotate";
+pub const INBOUND_EMAIL_RECEIVE: &str = "/api/inbound-email/receive";
+
+pub const USER_SETTINGS: &str = "/api/user-settings";
+pub const USER_SETTINGS_NOTIFICATIONS: &str = "/api/user-settings/notifications";
+pub const SETTINGS: &str = "/api/settings";
+pub const SETTING: &str = "/api/settings/{namespace}";
+pub const KV: &str = "/api/kv";
+pub const KV_ENTRY: &str = "/api/kv/{key}";
+
+pub const SYNC: &str = "/api/sync";
+pub const SYNC_PUSH: &str = "/api/sync/push";
+pub const CONFLICTS: &str = "/api/conflicts";
+pub const CONFLICT: &str = "/api/conflicts/{id}";
+pub const CONFLICT_RESOLVE: &str = "/api/conflicts/{id}/resolve";
+
+pub const SEARCH: &str = "/api/search";
+pub const SEARCH_PREFILTER: &str = "/api/search/prefilter";
+pub const QUICK_ADD: &str = "/api/quick-add";
+pub const FEATURES: &str = "/api/features";
+
+pub const TRASH: &str = "/api/trash";
+
+pub const USAGE: &str = "/api/usage";
+
+pub const REPORTS_SUMMARY: &str = "/api/reports/summary";
+
+pub const SCHEDULE_PLAN: &str = "/api/schedule/plan";
+pub const SCHEDULE_APPLY: &str = "/api/schedule/apply";
+pub const SCHEDULE_CONSTRAINTS: &str = "/api/schedule/constraints";
+
+pub const EXPORT: &str = "/api/export";
+pub const IMPORT: &str = "/api/import";
+pub const IMPORT_TODOIST: &str = "/api/import/todoist";
+pub const IMPORT_TODOIST_COMMIT: &str = "/api/import/todoist/commit";
+
+/// Substitute the `{id}` placeholder of a route pattern
+pub fn with_id(pattern: &str, id: Uuid) -> String {
+    pattern.replace("{id}", &id.to_string())
+}
+
+/// `feature` is the feature's key, e.g. `auto_scheduling`
+pub fn admin_user_feature(id: Uuid, feature: &str) -> String {
+    with_id(ADMIN_USER_FEATURE, id).replace("{feature}", feature)
+}
+
+/// `namespace` is the settings namespace, e.g. `general`
+pub fn setting(namespace: &str) -> String {
+    SETTING.replace("{namespace}", namespace)
+}
+
+/// `key` is the entry's client-chosen name, e.g. `calendar.last_view`
+pub fn kv_entry(key: &str) -> String {
+    KV_ENTRY.replace("{key}", key)
+}
+
+pub fn auth_session(id: Uuid) -> String {
+    with_id(AUTH_SESSION, id)
+}
+
+pub fn admin_impersonation(id: Uuid) -> String {
+    with_id(ADMIN_IMPERSONATION, id)
+}
+
+pub fn project(id: Uuid) -> String {
+    with_id(PROJECT, id)
+}
+
+pub fn project_restore(id: Uuid) -> String {
+    with_id(PROJECT_RESTORE, id)
+}
+
+pub fn project_duplicate(id: Uuid) -> String {
+    with_id(PROJECT_DUPLICATE, id)
+}
+
+pub fn project_move(id: Uuid) -> String {
+    with_id(PROJECT_MOVE, id)
+}
+
+pub fn project_pin(id: Uuid) -> String {
+    with_id(PROJECT_PIN, id)
+}
+
+pub fn project_revisions(id: Uuid) -> String {
+    with_id(PROJECT_REVISIONS, id)
+}
+
+pub fn project_revert(id: Uuid, revision: Uuid) -> String {
+    with_id(PROJECT_REVERT, id).replace("{revision}", &revision.to_string())
+}
+
+pub fn project_members(id: Uuid) -> String {
+    with_id(PROJECT_MEMBERS, id)
+}
+
+pub fn project_member(id: Uuid, user_id: Uuid) -> String {
+    with_id(PROJECT_MEMBER, id).replace("{user_id}", &user_id.to_string())
+}
+
+pub fn organization(id: Uuid) -> String {
+    with_id(ORGANIZATION, id)
+}
+
+pub fn organization_members(id: Uuid) -> String {
+    with_id(ORGANIZATION_MEMBERS, id)
+}
+
+pub fn organization_member(id: Uuid, user_id: Uuid) -> String {
+    with_id(ORGANIZATION_MEMBER, id).replace("{user_id}", &user_id.to_string())
+}
+
+pub fn organization_export(id: Uuid) -> String {
+    with_id(ORGANIZATION_EXPORT, id)
+}
+
+pub fn can_do_item(id: Uuid) -> String {
+    with_id(CAN_DO_ITEM, id)
+}
+
+pub fn can_do_item_restore(id: Uuid) -> String {
+    with_id(CAN_DO_ITEM_RESTORE, id)
+}
+
+pub fn can_do_item_pin(id: Uuid) -> String {
+    with_id(CAN_DO_ITEM_PIN, id)
+}
+
+pub fn can_do_item_timer_start(id: Uuid) -> String {
+    with_id(CAN_DO_ITEM_TIMER_START, id)
+}
+
+pub fn can_do_item_timer_stop(id: Uuid) -> String {
+    with_id(CAN_DO_ITEM_TIMER_STOP, id)
+}
+
+pub fn can_do_item_revisions(id: Uuid) -> String {
+    with_id(CAN_DO_ITEM_REVISIONS, id)
+}
+
+pub fn can_do_item_revert(id: Uuid, revision: Uuid) -> String {
+    with_id(CAN_DO_ITEM_REVERT, id).replace("{revision}", &revision.to_string())
+}
+
+pub fn can_do_item_linked_events(id: Uuid) -> String {
+    with_id(CAN_DO_ITEM_LINKED_EVENTS, id)
+}
+
+pub fn calendar(id: Uuid) -> String {
+    with_id(CALENDAR, id)
+}
+
+pub fn calendar_restore(id: Uuid) -> String {
+    with_id(CALENDAR_RESTORE, id)
+}
+
+pub fn calendar_pin(id: Uuid) -> String {
+    with_id(CALENDAR_PIN, id)
+}
+
+pub fn calendar_revisions(id: Uuid) -> String {
+    with_id(CALENDAR_REVISIONS, id)
+}
+
+pub fn calendar_revert(id: Uuid, revision: Uuid) -> String {
+    with_id(CALENDAR_REVERT, id).replace("{revision}", &revision.to_string())
+}
+
+pub fn calendar_event(id: Uuid) -> String {
+    with_id(CALENDAR_EVENT, id)
+}
+
+pub fn calendar_event_restore(id: Uuid) -> String {
+    with_id(CALENDAR_EVENT_RESTORE, id)
+}
+
+pub fn calendar_event_duplicate(id: Uuid) -> String {
+    with_id(CALENDAR_EVENT_DUPLICATE, id)
+}
+
+pub fn calendar_event_series_occurrences(id: Uuid) -> String {
+    with_id(CALENDAR_EVENT_SERIES_OCCURRENCES, id)
+}
+
+pub fn calendar_event_revisions(id: Uuid) -> String {
+    with_id(CALENDAR_EVENT_REVISIONS, id)
+}
+
+pub fn calendar_event_revert(id: Uuid, revision: Uuid) -> String {
+    with_id(CALENDAR_EVENT_REVERT, id).replace("{revision}", &revision.to_string())
+}
+
+pub fn calendar_event_link(id: Uuid) -> String {
+    with_id(CALENDAR_EVENT_LINK, id)
+}
+
+pub fn calendar_event_time(id: Uuid) -> String {
+    with_id(CALENDAR_EVENT_TIME, id)
+}
+
+pub fn reminder(id: Uuid) -> String {
+    with_id(REMINDER, id)
+}
+
+pub fn event_attendee(id: Uuid) -> String {
+    with_id(EVENT_ATTENDEE, id)
+}
+
+pub fn event_attendee_rsvp(id: Uuid) -> String {
+    with_id(EVENT_ATTENDEE_RSVP, id)
+}
+
+pub fn time_entry(id: Uuid) -> String {
+    with_id(TIME_ENTRY, id)
+}
+
+pub fn focus_session_stop(id: Uuid) -> String {
+    with_id(FOCUS_SESSION_STOP, id)
+}
+
+pub fn focus_session_cancel(id: Uuid) -> String {
+    with_id(FOCUS_SESSION_CANCEL, id)
+}
+
+pub fn habit(id: Uuid) -> String {
+    with_id(HABIT, id)
+}
+
+pub fn habit_checkin(id: Uuid) -> String {
+    with_id(HABIT_CHECKIN, id)
+}
+
+pub fn habit_checkins(id: Uuid) -> String {
+    with_id(HABIT_CHECKINS, id)
+}
+
+pub fn goal(id: Uuid) -> String {
+    with_id(GOAL, id)
+}
+
+pub fn goal_project(id: Uuid, project_id: Uuid) -> String {
+    with_id(GOAL_PROJECT, id).replace("{project_id}", &project_id.to_string())
+}
+
+pub fn goal_task(id: Uuid, task_id: Uuid) -> String {
+    with_id(GOAL_TASK, id).replace("{task_id}", &task_id.to_string())
+}
+
+pub fn template(id: Uuid) -> String {
+    with_id(TEMPLATE, id)
+}
+
+pub fn template_instantiate(id: Uuid) -> String {
+    with_id(TEMPLATE_INSTANTIATE, id)
+}
+
+pub fn note(id: Uuid) -> String {
+    with_id(NOTE, id)
+}
+
+pub fn note_restore(id: Uuid) -> String {
+    with_id(NOTE_RESTORE, id)
+}
+
+pub fn note_revisions(id: Uuid) -> String {
+    with_id(NOTE_REVISIONS, id)
+}
+
+pub fn note_revert(id: Uuid, revision: Uuid) -> String {
+    with_id(NOTE_REVERT, id).replace("{revision}", &revision.to_string())
+}
+
+pub fn poll(id: Uuid) -> String {
+    with_id(POLL, id)
+}
+
+pub fn poll_finalize(id: Uuid) -> String {
+    with_id(POLL_FINALIZE, id)
+}
+
+pub fn poll_vote(id: Uuid, vote_id: Uuid) -> String {
+    with_id(POLL_VOTE, id).replace("{vote_id}", &vote_id.to_string())
+}
+
+pub fn public_poll(token: &str) -> String {
+    PUBLIC_POLL.replace("{token}", token)
+}
+
+pub fn public_poll_votes(token: &str) -> String {
+    PUBLIC_POLL_VOTES.replace("{token}", token)
+}
+
+pub fn booking_page(id: Uuid) -> String {
+    with_id(BOOKING_PAGE, id)
+}
+
+pub fn booking_page_bookings(id: Uuid) -> String {
+    with_id(BOOKING_PAGE_BOOKINGS, id)
+}
+
+pub fn public_booking_page(token: &str) -> String {
+    PUBLIC_BOOKING_PAGE.replace("{token}", token)
+}
+
+pub fn public_booking_page_slots(token: &str) -> String {
+    PUBLIC_BOOKING_PAGE_SLOTS.replace("{token}", token)
+}
+
+pub fn public_booking_page_bookings(token: &str) -> String {
+    PUBLIC_BOOKING_PAGE_BOOKINGS.replace("{token}", token)
+}
+
+pub fn calendar_subscription(id: Uuid) -> String {
+    with_id(CALENDAR_SUBSCRIPTION, id)
+}
+
+pub fn calendar_subscription_refresh(id: Uuid) -> String {
+    with_id(CALENDAR_SUBSCRIPTION_REFRESH, id)
+}
+
+pub fn webhook(id: Uuid) -> String {
+    with_id(WEBHOOK, id)
+}
+
+pub fn webhook_deliveries(id: Uuid) -> String {
+    with_id(WEBHOOK_DELIVERIES, id)
+}
+
+pub fn webhook_redeliver(id: Uuid, delivery_id: Uuid) -> String {
+    with_id(WEBHOOK_REDELIVER, id).replace("{delivery_id}", &delivery_id.to_string())
+}
+
+pub fn webhook_rotate_secret(id: Uuid) -> String {
+    with_id(WEBHOOK_ROTATE_SECRET, id)
+}
+
+pub fn webhook_verify_signature(id: Uuid) -> String {
+    with_id(WEBHOOK_VERIFY_SIGNATURE, id)
+}
+
+pub fn conflict(id: Uuid) -> String {
+    with_id(CONFLICT, id)
+}
+
+pub fn conflict_resolve(id: Uuid) -> String {
+    with_id(CONFLICT_RESOLVE, id)
+}
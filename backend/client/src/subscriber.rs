@@ -0,0 +1,142 @@
+use std::time::Duration;
+
+use futures_util::{SinkExt, StreamExt};
+use serde_json::{json, Value};
+use tokio::{sync::mpsc, task::JoinHandle};
+use tokio_tungstenite::{connect_async, tungstenite::Message};
+use uuid::Uuid;
+
+use crate::{models::websocket::WebSocketMessage, routes};
+
+/// Protocol version the subscriber speaks; change events carry `"type": "change"`
+const PROTOCOL_VERSION: u32 = 2;
+/// First delay before reconnecting after a dropped connection, doubled on every failed attempt
+const INITIAL_BACKOFF: Duration = Duration::from_secs(1);
+const MAX_BACKOFF: Duration = Duration::from_secs(30);
+/// Events buffered while the consumer is busy; the socket isn't read while the buffer is full
+const EVENT_BUFFER: usize = 256;
+
+/// What a [`Subscriber`] yields
+#[derive(Debug, Clone)]
+pub enum SubscriberEvent {
+    /// Authenticated on a new connection. Tag REST writes with this id through
+    /// [`Client::with_connection_id`](crate::Client::with_connection_id) to skip their echo.
+    /// Changes made while disconnected aren't replayed; fetch them from `/api/sync`.
+    Connected { connection_id: Uuid },
+    Change(WebSocketMessage),
+    /// The connection dropped; the subscriber reconnects by itself
+    Disconnected,
+    /// The server rejected the token; the subscriber stops, since retrying won't help
+    AuthFailed { message: String },
+}
+
+/// A `/ws` subscription that reconnects by itself, with exponential backoff after failures
+/// and after the delay the server asks for when it restarts. Dropping it closes the socket.
+pub struct Subscriber {
+    events: mpsc::Receiver<SubscriberEvent>,
+    task: JoinHandle<()>,
+}
+
+impl Subscriber {
+    /// Connect to the server at `base_url` (`http(s)://…`) in the background.
+    /// Must be called from within a Tokio runtime.
+    pub fn new(base_url: &str, token: impl Into<String>) -> Self {
+        let url = format!("{}{}", base_url.trim_end_matches('/').replacen("http", "ws", 1), routes::WEBSOCKET);
+        let (sender, events) = mpsc::channel(EVENT_BUFFER);
+        let task = tokio::spawn(run(url, token.into(), sender));
+        Self { events, task }
+    }
+
+    /// The next event, or `None` once the subscriber stopped after [`SubscriberEvent::AuthFailed`]
+    pub async fn next(&mut self) -> Option<SubscriberEvent> {
+        self.events.recv().await
+    }
+}
+
+impl Drop for Subscriber {
+    fn drop(&mut self) {
+        self.task.abort();
+    }
+}
+
+/// How a connection ended
+enum Outcome {
+    /// Reconnect after this delay
+    Reconnect(Option<Duration>),
+    /// Stop for good: the token was rejected or the subscriber was dropped
+    Stop,
+}
+
+async fn run(url: String, token: String, events: mpsc::Sender<SubscriberEvent>) {
+    let mut backoff = INITIAL_BACKOFF;
+    loop {
+        let delay = match connect(&url, &token, &events, &mut backoff).await {
+            Outcome::Stop => return,
+            Outcome::Reconnect(delay) => delay,
+        };
+        if events.send(SubscriberEvent::Disconnected).await.is_err() {
+            return;
+        }
+
+        let delay = delay.unwrap_or_else(|| {
+            let delay = backoff;
+            backoff = (backoff * 2).min(MAX_BACKOFF);
+            delay
+        });
+        tokio::time::sleep(delay).await;
+    }
+}
+
+async fn connect(
+    url: &str,
+    token: &str,
+    events: &mpsc::Sender<SubscriberEvent>,
+    backoff: &mut Duration,
+) -> Outcome {
+    let Ok((mut socket, _)) = connect_async(url).await else {
+        return Outcome::Reconnect(None);
+    };
+    let auth = json!({ "token": token, "version": PROTOCOL_VERSION });
+    if socket.send(Message::text(auth.to_string())).await.is_err() {
+        return Outcome::Reconnect(None);
+    }
+
+    // Pings are answered while reading, so the loop keeps the connection alive
+    while let Some(Ok(frame)) = socket.next().await {
+        let Message::Text(text) = frame else {
+            continue;
+        };
+        let Ok(frame) = serde_json::from_str::<Value>(&text) else {
+            continue;
+        };
+
+        let event = match frame["type"].as_str() {
+            Some("auth_success") => {
+                let Some(connection_id) = frame["connection_id"].as_str().and_then(|id| id.parse().ok()) else {
+                    return Outcome::Reconnect(None);
+                };
+                *backoff = INITIAL_BACKOFF;
+                SubscriberEvent::Connected { connection_id }
+            }
+            Some("auth_error") => {
+                let message = frame["message"].as_str().unwrap_or("Authentication failed").to_string();
+                let _ = events.send(SubscriberEvent::AuthFailed { message }).await;
+                return Outcome::Stop;
+            }
+            Some("server_restarting") => {
+                let delay = frame["reconnect_after_ms"].as_u64().map(Duration::from_millis);
+                return Outcome::Reconnect(delay);
+            }
+            Some("change") => match serde_json::from_value(frame) {
+                Ok(change) => SubscriberEvent::Change(change),
+                Err(_) => continue,
+            },
+            // Frames added in newer protocol versions
+            _ => continue,
+        };
+        if events.send(event).await.is_err() {
+            return Outcome::Stop;
+        }
+    }
+    Outcome::Reconnect(None)
+}
@@ -0,0 +1,70 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::double_option;
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct CreateCalendarRequest {
+    /// Optional client-generated id so offline-created records keep their identity
+    pub id: Option<Uuid>,
+    pub encrypted_data: String,
+    pub iv: String,
+    pub salt: String,
+    /// Format version of `encrypted_data`; 1 when omitted
+    pub schema_version: Option<i16>,
+    pub is_pinned: Option<bool>,
+    /// `#rrggbb`; kept in plaintext so people the calendar is shared with see it too
+    pub color: Option<String>,
+    pub display_order: Option<i32>,
+    /// Share the calendar with an organization the user belongs to
+    pub organization_id: Option<Uuid>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct UpdateCalendarRequest {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub encrypted_data: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub iv: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub salt: Option<String>,
+    /// Format version of `encrypted_data`; a new `encrypted_data` without it is taken to be version 1
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub schema_version: Option<i16>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub is_default: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub is_pinned: Option<bool>,
+    /// `null` removes the color
+    #[serde(default, deserialize_with = "double_option", skip_serializing_if = "Option::is_none")]
+    pub color: Option<Option<String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub display_order: Option<i32>,
+    /// `null` makes the calendar private again
+    #[serde(default, deserialize_with = "double_option", skip_serializing_if = "Option::is_none")]
+    pub organization_id: Option<Option<Uuid>>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CalendarResponse {
+    pub id: Uuid,
+    pub user_id: Uuid,
+    pub encrypted_data: String,
+    pub iv: String,
+    pub salt: String,
+    /// Format version of `encrypted_data`
+    #[serde(default = "crate::default_schema_version")]
+    pub schema_version: i16,
+    pub is_default: bool,
+    pub is_pinned: bool,
+    pub is_read_only: bool,
+    #[serde(default)]
+    pub color: Option<String>,
+    #[serde(default)]
+    pub display_order: i32,
+    pub organization_id: Option<Uuid>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+    pub deleted_at: Option<DateTime<Utc>>,
+}
@@ -0,0 +1,130 @@
+use chrono::{DateTime, NaiveDate, Utc};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::{double_option, event_attendee::AttendeeSummary};
+
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[cfg_attr(feature = "sea-orm", derive(sea_orm::EnumIter, sea_orm::DeriveActiveEnum))]
+#[cfg_attr(feature = "sea-orm", sea_orm(rs_type = "String", db_type = "Text"))]
+#[serde(rename_all = "snake_case")]
+pub enum EventStatus {
+    #[default]
+    #[cfg_attr(feature = "sea-orm", sea_orm(string_value = "confirmed"))]
+    Confirmed,
+    #[cfg_attr(feature = "sea-orm", sea_orm(string_value = "tentative"))]
+    Tentative,
+    /// Kept on the calendar but not occupying time
+    #[cfg_attr(feature = "sea-orm", sea_orm(string_value = "cancelled"))]
+    Cancelled,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct CreateCalendarEventRequest {
+    /// Optional client-generated id so offline-created records keep their identity
+    pub id: Option<Uuid>,
+    pub calendar_id: Option<Uuid>,
+    pub encrypted_data: String,
+    pub iv: String,
+    pub salt: String,
+    /// Format version of `encrypted_data`; 1 when omitted
+    pub schema_version: Option<i16>,
+    pub start_time: Option<DateTime<Utc>>,
+    pub end_time: Option<DateTime<Utc>>,
+    /// Spans whole days from `start_date` to `end_date`; the server sets the times from them
+    #[serde(default)]
+    pub is_all_day: bool,
+    /// First day of an all-day event; required for those
+    pub start_date: Option<NaiveDate>,
+    /// Last day of an all-day event; defaults to `start_date`
+    pub end_date: Option<NaiveDate>,
+    /// Base64 bloom filter over the client's hashed search tokens
+    pub search_bloom: Option<String>,
+    /// Blind-index tokens: keyed hashes of the record's words, computed client-side
+    pub search_tokens: Option<Vec<String>>,
+    /// RFC 5545 RRULE (without the `RRULE:` prefix); requires a start_time
+    pub rrule: Option<String>,
+    /// Start times of occurrences removed from the series
+    pub recurrence_exceptions: Option<Vec<DateTime<Utc>>>,
+    /// Defaults to `confirmed`
+    pub status: Option<EventStatus>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct UpdateCalendarEventRequest {
+    #[serde(default, deserialize_with = "double_option", skip_serializing_if = "Option::is_none")]
+    pub calendar_id: Option<Option<Uuid>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub encrypted_data: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub iv: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub salt: Option<String>,
+    /// Format version of `encrypted_data`; a new `encrypted_data` without it is taken to be version 1
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub schema_version: Option<i16>,
+    #[serde(default, deserialize_with = "double_option", skip_serializing_if = "Option::is_none")]
+    pub start_time: Option<Option<DateTime<Utc>>>,
+    #[serde(default, deserialize_with = "double_option", skip_serializing_if = "Option::is_none")]
+    pub end_time: Option<Option<DateTime<Utc>>>,
+    /// Turning this off clears the dates
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub is_all_day: Option<bool>,
+    #[serde(default, deserialize_with = "double_option", skip_serializing_if = "Option::is_none")]
+    pub start_date: Option<Option<NaiveDate>>,
+    #[serde(default, deserialize_with = "double_option", skip_serializing_if = "Option::is_none")]
+    pub end_date: Option<Option<NaiveDate>>,
+    /// Base64 bloom filter over the client's hashed search tokens
+    #[serde(default, deserialize_with = "double_option", skip_serializing_if = "Option::is_none")]
+    pub search_bloom: Option<Option<String>>,
+    /// Blind-index tokens: keyed hashes of the record's words, computed client-side
+    #[serde(default, deserialize_with = "double_option", skip_serializing_if = "Option::is_none")]
+    pub search_tokens: Option<Option<Vec<String>>>,
+    #[serde(default, deserialize_with = "double_option", skip_serializing_if = "Option::is_none")]
+    pub rrule: Option<Option<String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub recurrence_exceptions: Option<Vec<DateTime<Utc>>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub status: Option<EventStatus>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CalendarEventResponse {
+    pub id: Uuid,
+    pub user_id: Uuid,
+    pub calendar_id: Option<Uuid>,
+    pub encrypted_data: String,
+    pub iv: String,
+    pub salt: String,
+    /// Format version of `encrypted_data`
+    #[serde(default = "crate::default_schema_version")]
+    pub schema_version: i16,
+    pub start_time: Option<DateTime<Utc>>,
+    pub end_time: Option<DateTime<Utc>>,
+    #[serde(default)]
+    pub is_all_day: bool,
+    /// First and last day of an all-day event
+    #[serde(default)]
+    pub start_date: Option<NaiveDate>,
+    #[serde(default)]
+    pub end_date: Option<NaiveDate>,
+    pub rrule: Option<String>,
+    pub recurrence_exceptions: Vec<DateTime<Utc>>,
+    pub recurring_event_id: Option<Uuid>,
+    pub original_start_time: Option<DateTime<Utc>>,
+    pub status: EventStatus,
+    /// Set on read-only events imported from a calendar subscription
+    pub subscription_id: Option<Uuid>,
+    pub external_uid: Option<String>,
+    /// Unencrypted `summary`, `description` and `location` of a subscribed event
+    pub external_data: Option<serde_json::Value>,
+    /// Task this event blocks out time for
+    #[serde(default)]
+    pub linked_task_id: Option<Uuid>,
+    /// Attendee counts by response; only included when listing or fetching events
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub attendees: Option<AttendeeSummary>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+    pub deleted_at: Option<DateTime<Utc>>,
+}
@@ -0,0 +1,114 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::double_option;
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[cfg_attr(feature = "sea-orm", derive(sea_orm::EnumIter, sea_orm::DeriveActiveEnum))]
+#[cfg_attr(feature = "sea-orm", sea_orm(rs_type = "i16", db_type = "SmallInteger"))]
+#[serde(rename_all = "snake_case")]
+pub enum TaskPriority {
+    #[cfg_attr(feature = "sea-orm", sea_orm(num_value = 1))]
+    Low,
+    #[cfg_attr(feature = "sea-orm", sea_orm(num_value = 2))]
+    Medium,
+    #[cfg_attr(feature = "sea-orm", sea_orm(num_value = 3))]
+    High,
+    #[cfg_attr(feature = "sea-orm", sea_orm(num_value = 4))]
+    Urgent,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct CreateCanDoItemRequest {
+    /// Optional client-generated id so offline-created records keep their identity
+    pub id: Option<Uuid>,
+    pub project_id: Option<Uuid>,
+    pub encrypted_data: String,
+    pub iv: String,
+    pub salt: String,
+    /// Format version of `encrypted_data`; 1 when omitted
+    pub schema_version: Option<i16>,
+    pub display_order: Option<i32>,
+    /// Base64 bloom filter over the client's hashed search tokens
+    pub search_bloom: Option<String>,
+    /// Blind-index tokens: keyed hashes of the record's words, computed client-side
+    pub search_tokens: Option<Vec<String>>,
+    pub due_at: Option<DateTime<Utc>>,
+    pub scheduled_at: Option<DateTime<Utc>>,
+    /// Task this one is a subtask of
+    pub parent_task_id: Option<Uuid>,
+    pub completed_at: Option<DateTime<Utc>>,
+    /// RFC 5545 rule for a repeating task, anchored on `due_at` (or `scheduled_at`)
+    pub rrule: Option<String>,
+    pub priority: Option<TaskPriority>,
+    pub estimated_minutes: Option<i32>,
+    pub is_pinned: Option<bool>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct UpdateCanDoItemRequest {
+    #[serde(default, deserialize_with = "double_option", skip_serializing_if = "Option::is_none")]
+    pub project_id: Option<Option<Uuid>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub encrypted_data: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub iv: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub salt: Option<String>,
+    /// Format version of `encrypted_data`; a new `encrypted_data` without it is taken to be version 1
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub schema_version: Option<i16>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub display_order: Option<i32>,
+    /// Base64 bloom filter over the client's hashed search tokens
+    #[serde(default, deserialize_with = "double_option", skip_serializing_if = "Option::is_none")]
+    pub search_bloom: Option<Option<String>>,
+    /// Blind-index tokens: keyed hashes of the record's words, computed client-side
+    #[serde(default, deserialize_with = "double_option", skip_serializing_if = "Option::is_none")]
+    pub search_tokens: Option<Option<Vec<String>>>,
+    #[serde(default, deserialize_with = "double_option", skip_serializing_if = "Option::is_none")]
+    pub due_at: Option<Option<DateTime<Utc>>>,
+    #[serde(default, deserialize_with = "double_option", skip_serializing_if = "Option::is_none")]
+    pub scheduled_at: Option<Option<DateTime<Utc>>>,
+    #[serde(default, deserialize_with = "double_option", skip_serializing_if = "Option::is_none")]
+    pub parent_task_id: Option<Option<Uuid>>,
+    #[serde(default, deserialize_with = "double_option", skip_serializing_if = "Option::is_none")]
+    pub completed_at: Option<Option<DateTime<Utc>>>,
+    #[serde(default, deserialize_with = "double_option", skip_serializing_if = "Option::is_none")]
+    pub rrule: Option<Option<String>>,
+    #[serde(default, deserialize_with = "double_option", skip_serializing_if = "Option::is_none")]
+    pub priority: Option<Option<TaskPriority>>,
+    #[serde(default, deserialize_with = "double_option", skip_serializing_if = "Option::is_none")]
+    pub estimated_minutes: Option<Option<i32>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub is_pinned: Option<bool>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CanDoItemResponse {
+    pub id: Uuid,
+    pub user_id: Uuid,
+    pub project_id: Option<Uuid>,
+    pub encrypted_data: String,
+    pub iv: String,
+    pub salt: String,
+    /// Format version of `encrypted_data`
+    #[serde(default = "crate::default_schema_version")]
+    pub schema_version: i16,
+    pub display_order: i32,
+    pub due_at: Option<DateTime<Utc>>,
+    pub scheduled_at: Option<DateTime<Utc>>,
+    pub parent_task_id: Option<Uuid>,
+    pub completed_at: Option<DateTime<Utc>>,
+    pub rrule: Option<String>,
+    pub priority: Option<TaskPriority>,
+    pub estimated_minutes: Option<i32>,
+    pub is_pinned: bool,
+    /// Email the task was created from, `{ "subject", "body", "from", "received_at" }`, until a client
+    /// encrypts it into `encrypted_data`
+    pub pending_plaintext: Option<serde_json::Value>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+    pub deleted_at: Option<DateTime<Utc>>,
+}
@@ -0,0 +1,41 @@
+use serde::{Deserialize, Serialize};
+
+/// iCalendar PARTSTAT of an attendee (RFC 5545 section 3.2.12)
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[cfg_attr(feature = "sea-orm", derive(sea_orm::EnumIter, sea_orm::DeriveActiveEnum))]
+#[cfg_attr(feature = "sea-orm", sea_orm(rs_type = "String", db_type = "Text"))]
+#[serde(rename_all = "snake_case")]
+pub enum RsvpStatus {
+    /// Invited but hasn't responded yet
+    #[default]
+    #[cfg_attr(feature = "sea-orm", sea_orm(string_value = "needs_action"))]
+    NeedsAction,
+    #[cfg_attr(feature = "sea-orm", sea_orm(string_value = "accepted"))]
+    Accepted,
+    #[cfg_attr(feature = "sea-orm", sea_orm(string_value = "declined"))]
+    Declined,
+    #[cfg_attr(feature = "sea-orm", sea_orm(string_value = "tentative"))]
+    Tentative,
+}
+
+/// Attendee counts by response, included with events
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+pub struct AttendeeSummary {
+    pub total: i64,
+    pub accepted: i64,
+    pub declined: i64,
+    pub tentative: i64,
+    pub needs_action: i64,
+}
+
+impl AttendeeSummary {
+    pub fn add(&mut self, status: RsvpStatus, count: i64) {
+        self.total += count;
+        match status {
+            RsvpStatus::Accepted => self.accepted += count,
+            RsvpStatus::Declined => self.declined += count,
+            RsvpStatus::Tentative => self.tentative += count,
+            RsvpStatus::NeedsAction => self.needs_action += count,
+        }
+    }
+}
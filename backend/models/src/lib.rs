@@ -0,0 +1,91 @@
+//! Request and response types of the Streamline Scheduler API.
+//!
+//! The server deserializes requests into and serializes responses from these
+//! types, and `streamline-client` uses the same ones, so a field added or
+//! renamed on one side can't silently drift from the other. Record contents
+//! are end-to-end encrypted: `encrypted_data`, `iv` and `salt` are opaque to
+//! the server.
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Deserializer, Serialize};
+
+pub mod auth;
+pub mod calendar;
+pub mod calendar_event;
+pub mod can_do_list;
+pub mod event_attendee;
+pub mod note;
+pub mod project;
+pub mod websocket;
+
+/// Success envelope returned by every API endpoint
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ApiResponse<T> {
+    pub data: T,
+    pub message: Option<String>,
+    /// Present on list responses
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub meta: Option<ListMeta>,
+}
+
+impl<T> ApiResponse<T> {
+    pub fn new(data: T) -> Self {
+        Self {
+            data,
+            message: None,
+            meta: None,
+        }
+    }
+
+    pub fn with_message(data: T, message: impl Into<String>) -> Self {
+        Self {
+            data,
+            message: Some(message.into()),
+            meta: None,
+        }
+    }
+}
+
+impl<T> ApiResponse<Vec<T>> {
+    pub fn list(data: Vec<T>, meta: ListMeta) -> Self {
+        Self {
+            data,
+            message: None,
+            meta: Some(meta),
+        }
+    }
+}
+
+/// Paging details of a list response
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ListMeta {
+    /// Records on all pages together
+    pub total_count: u64,
+    /// Page size that was applied; `None` when the whole list was returned
+    pub limit: Option<u64>,
+    /// Pass as `?cursor=` to get the next page; `None` on the last one
+    pub next_cursor: Option<String>,
+    /// When the server started reading the list
+    pub generated_at: DateTime<Utc>,
+}
+
+/// Deserialize a nullable update field: absent leaves it unchanged (`None`),
+/// `null` clears it (`Some(None)`) and a value sets it (`Some(Some(value))`).
+/// Use with `#[serde(default, deserialize_with = "double_option")]`; clients
+/// add `skip_serializing_if = "Option::is_none"` so unset fields stay absent.
+pub fn double_option<'de, T, D>(deserializer: D) -> Result<Option<Option<T>>, D::Error>
+where
+    T: Deserialize<'de>,
+    D: Deserializer<'de>,
+{
+    Option::<T>::deserialize(deserializer).map(Some)
+}
+
+/// `schema_version` of records whose client didn't send one, including every record
+/// written before versioning. Clients bump it when they change an encrypted payload's format.
+pub const DEFAULT_SCHEMA_VERSION: i16 = 1;
+
+/// For `#[serde(default = "default_schema_version")]`
+pub fn default_schema_version() -> i16 {
+    DEFAULT_SCHEMA_VERSION
+}
@@ -0,0 +1,58 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::double_option;
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct CreateNoteRequest {
+    /// Optional client-generated id so offline-created records keep their identity
+    pub id: Option<Uuid>,
+    pub project_id: Option<Uuid>,
+    pub event_id: Option<Uuid>,
+    pub encrypted_data: String,
+    pub iv: String,
+    pub salt: String,
+    /// Format version of `encrypted_data`; 1 when omitted
+    pub schema_version: Option<i16>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct UpdateNoteRequest {
+    #[serde(default, deserialize_with = "double_option", skip_serializing_if = "Option::is_none")]
+    pub project_id: Option<Option<Uuid>>,
+    #[serde(default, deserialize_with = "double_option", skip_serializing_if = "Option::is_none")]
+    pub event_id: Option<Option<Uuid>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub encrypted_data: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub iv: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub salt: Option<String>,
+    /// Format version of `encrypted_data`; a new `encrypted_data` without it is taken to be version 1
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub schema_version: Option<i16>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct NoteQuery {
+    pub project_id: Option<Uuid>,
+    pub event_id: Option<Uuid>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct NoteResponse {
+    pub id: Uuid,
+    pub user_id: Uuid,
+    pub project_id: Option<Uuid>,
+    pub event_id: Option<Uuid>,
+    pub encrypted_data: String,
+    pub iv: String,
+    pub salt: String,
+    /// Format version of `encrypted_data`
+    #[serde(default = "crate::default_schema_version")]
+    pub schema_version: i16,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+    pub deleted_at: Option<DateTime<Utc>>,
+}
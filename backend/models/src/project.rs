@@ -0,0 +1,89 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::{can_do_list::CanDoItemResponse, double_option};
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct CreateProjectRequest {
+    /// Optional client-generated id so offline-created records keep their identity
+    pub id: Option<Uuid>,
+    pub encrypted_data: String,
+    pub iv: String,
+    pub salt: String,
+    /// Format version of `encrypted_data`; 1 when omitted
+    pub schema_version: Option<i16>,
+    pub parent_id: Option<Uuid>,
+    pub display_order: Option<i32>,
+    pub is_collapsed: Option<bool>,
+    pub is_pinned: Option<bool>,
+    /// Share the project with an organization the user belongs to
+    pub organization_id: Option<Uuid>,
+    /// Blind-index tokens: keyed hashes of the record's words, computed client-side
+    pub search_tokens: Option<Vec<String>>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct UpdateProjectRequest {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub encrypted_data: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub iv: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub salt: Option<String>,
+    /// Format version of `encrypted_data`; a new `encrypted_data` without it is taken to be version 1
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub schema_version: Option<i16>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub is_default: Option<bool>,
+    #[serde(default, deserialize_with = "double_option", skip_serializing_if = "Option::is_none")]
+    pub parent_id: Option<Option<Uuid>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub display_order: Option<i32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub is_collapsed: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub is_pinned: Option<bool>,
+    /// `null` makes the project private again
+    #[serde(default, deserialize_with = "double_option", skip_serializing_if = "Option::is_none")]
+    pub organization_id: Option<Option<Uuid>>,
+    /// Blind-index tokens: keyed hashes of the record's words, computed client-side
+    #[serde(default, deserialize_with = "double_option", skip_serializing_if = "Option::is_none")]
+    pub search_tokens: Option<Option<Vec<String>>>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ProjectResponse {
+    pub id: Uuid,
+    pub user_id: Uuid,
+    pub encrypted_data: String,
+    pub iv: String,
+    pub salt: String,
+    /// Format version of `encrypted_data`
+    #[serde(default = "crate::default_schema_version")]
+    pub schema_version: i16,
+    pub is_default: bool,
+    pub parent_id: Option<Uuid>,
+    pub display_order: i32,
+    pub is_collapsed: bool,
+    pub is_pinned: bool,
+    pub organization_id: Option<Uuid>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+    pub deleted_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct MoveProjectRequest {
+    /// New parent; `null` or omitted moves the project to the root level
+    pub parent_id: Option<Uuid>,
+    /// Zero-based index among the new siblings; defaults to the end
+    pub position: Option<usize>,
+}
+
+/// Copies created by duplicating a project subtree
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DuplicateProjectResponse {
+    pub projects: Vec<ProjectResponse>,
+    pub can_do_list: Vec<CanDoItemResponse>,
+}
@@ -0,0 +1,15 @@
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+/// A change to a record, pushed over `/ws` to every connection of the users who can see it.
+/// `event_type` is `INSERT`, `UPDATE` or `DELETE` (or `NOTIFICATION` for fired reminders) and
+/// `data` is the record as the REST API returns it; it is `null` for deletes and for payloads
+/// over the connection's `max_payload_size`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WebSocketMessage {
+    pub event_type: String,
+    pub table: String,
+    pub user_id: Uuid,
+    pub record_id: Option<Uuid>,
+    pub data: Option<serde_json::Value>,
+}
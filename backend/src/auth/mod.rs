@@ -1,3 +1,5 @@
+pub mod sessions;
+
 use jsonwebtoken::{decode, encode, Algorithm, DecodingKey, EncodingKey, Header, Validation};
 use serde::{Deserialize, Serialize};
 use std::env;
@@ -7,10 +9,18 @@ use argon2::{Argon2, PasswordHash, PasswordHasher, PasswordVerifier};
 use argon2::password_hash::{rand_core::OsRng, SaltString};
 
 use sea_orm::*;
-use crate::errors::{AppError, Result};
+use crate::errors::{AppError, ErrorCode, Result};
 use crate::models::user::{CreateUserRequest, LoginRequest, AuthResponse};
-use crate::db::Database;
-use crate::entities::{prelude::*, users};
+use crate::db::{begin_write, Database};
+use crate::entities::{prelude::*, sessions as session_entity, users};
+use crate::handlers::projects::ensure_default_project;
+use sessions::SessionActivity;
+
+/// Default tolerance for clock differences between token issuer and verifier
+const DEFAULT_LEEWAY_SECS: u64 = 60;
+
+/// Shown for impersonation sessions in the impersonated user's session list
+const IMPERSONATION_USER_AGENT: &str = "Support impersonation";
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct Claims {
@@ -18,8 +28,25 @@ pub struct Claims {
     pub email: String,
     pub exp: i64,     // Expiration time
     pub iat: i64,     // Issued at
+    /// Not valid before; absent on tokens issued before it was introduced
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub nbf: Option<i64>,
     pub aud: String,  // Audience
     pub iss: String,  // Issuer
+    /// Super admin acting as `sub` in a read-only impersonation session
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub act: Option<String>,
+    /// Session this token belongs to; absent on tokens issued before sessions were tracked
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sid: Option<String>,
+}
+
+/// A verified token's user, with the impersonating super admin and session it belongs to
+#[derive(Debug, Clone)]
+pub struct Authenticated {
+    pub user: users::Model,
+    pub impersonator: Option<Uuid>,
+    pub session_id: Option<Uuid>,
 }
 
 #[derive(Clone)]
@@ -27,6 +54,9 @@ pub struct AuthService {
     db: Database,
     jwt_secret: String,
     jwt_expiry_hours: i64,
+    /// Seconds of clock skew tolerated when checking `exp`, `nbf` and `iat`
+    jwt_leeway_secs: u64,
+    session_activity: SessionActivity,
 }
 
 impl AuthService {
@@ -37,15 +67,25 @@ impl AuthService {
             .unwrap_or_else(|_| "24".to_string())
             .parse()
             .unwrap_or(24);
+        let jwt_leeway_secs = env::var("JWT_LEEWAY_SECS")
+            .ok()
+            .and_then(|v| v.parse().ok())
+            .unwrap_or(DEFAULT_LEEWAY_SECS);
 
         Self {
             db,
             jwt_secret,
             jwt_expiry_hours,
+            jwt_leeway_secs,
+            session_activity: SessionActivity::from_env(),
         }
     }
 
-    pub async fn register(&self, request: CreateUserRequest) -> Result<AuthResponse> {
+    pub fn session_activity(&self) -> &SessionActivity {
+        &self.session_activity
+    }
+
+    pub async fn register(&self, request: CreateUserRequest, user_agent: Option<String>) -> Result<AuthResponse> {
         // Check if user already exists
         let existing_user = Users::find()
             .filter(users::Column::Email.eq(&request.email))
@@ -54,7 +94,7 @@ impl AuthService {
             .map_err(|e| AppError::Database(e.into()))?;
 
         if existing_user.is_some() {
-            return Err(AppError::Validation("User already exists".to_string()));
+            return Err(AppError::Coded(ErrorCode::AuthEmailTaken, "User already exists".to_string()));
         }
 
         // Hash password
@@ -66,11 +106,17 @@ impl AuthService {
         user_active.encrypted_password = Set(Some(password_hash));
         user_active.email_confirmed_at = Set(Some(chrono::Utc::now().into()));
 
-        let user = user_active.insert(&self.db.connection).await
+        // Every user starts with the default project that takes tasks without a project
+        let txn = begin_write(&self.db.connection).await?;
+        let user = user_active.insert(&txn).await
+            .map_err(|e| AppError::Database(e.into()))?;
+        ensure_default_project(&txn, user.id).await?;
+        txn.commit().await
             .map_err(|e| AppError::Database(e.into()))?;
 
-        // Generate JWT token
-        let token = self.generate_token(&user)?;
+        // Generate JWT token for a new session
+        let session = self.start_session(&user, user_agent).await?;
+        let token = self.generate_token(&user, &session)?;
 
         Ok(AuthResponse {
             access_token: token,
@@ -80,26 +126,27 @@ impl AuthService {
         })
     }
 
-    pub async fn login(&self, request: LoginRequest) -> Result<AuthResponse> {
+    pub async fn login(&self, request: LoginRequest, user_agent: Option<String>) -> Result<AuthResponse> {
         // Find user by email
         let user = Users::find()
             .filter(users::Column::Email.eq(&request.email))
             .one(&self.db.connection)
             .await
             .map_err(|e| AppError::Database(e.into()))?
-            .ok_or_else(|| AppError::Auth("Invalid credentials".to_string()))?;
+            .ok_or_else(|| AppError::Coded(ErrorCode::AuthInvalidCredentials, "Invalid credentials".to_string()))?;
 
         // Verify password
         if let Some(encrypted_password) = &user.encrypted_password {
             if !self.verify_password(&request.password, encrypted_password)? {
-                return Err(AppError::Auth("Invalid credentials".to_string()));
+                return Err(AppError::Coded(ErrorCode::AuthInvalidCredentials, "Invalid credentials".to_string()));
             }
         } else {
-            return Err(AppError::Auth("Invalid credentials".to_string()));
+            return Err(AppError::Coded(ErrorCode::AuthInvalidCredentials, "Invalid credentials".to_string()));
         }
 
-        // Generate JWT token
-        let token = self.generate_token(&user)?;
+        // Generate JWT token for a new session
+        let session = self.start_session(&user, user_agent).await?;
+        let token = self.generate_token(&user, &session)?;
 
         Ok(AuthResponse {
             access_token: token,
@@ -109,31 +156,159 @@ impl AuthService {
         })
     }
 
+    /// Resolve a token to its user; impersonation tokens are rejected
     pub async fn get_user_from_token(&self, token: &str) -> Result<users::Model> {
+        let authenticated = self.authenticate(token).await?;
+        if authenticated.impersonator.is_some() {
+            return Err(AppError::Auth("Impersonation tokens are not accepted here".to_string()));
+        }
+
+        Ok(authenticated.user)
+    }
+
+    /// Resolve a token to its user and, for impersonation tokens, the acting super admin;
+    /// tokens belonging to a session record activity on it
+    pub async fn authenticate(&self, token: &str) -> Result<Authenticated> {
         let claims = self.verify_token(token)?;
         let user_id = Uuid::parse_str(&claims.sub)
             .map_err(|_| AppError::Auth("Invalid user ID in token".to_string()))?;
 
-        let user = Users::find_by_id(user_id)
+        let user = self.find_user(user_id).await?;
+
+        let impersonator = match claims.act {
+            Some(act) => {
+                let actor_id = Uuid::parse_str(&act)
+                    .map_err(|_| AppError::Auth("Invalid actor ID in token".to_string()))?;
+                // Revoking admin rights ends any impersonation sessions still running
+                if !self.find_user(actor_id).await?.is_super_admin {
+                    return Err(AppError::Auth("Impersonating user is no longer a super admin".to_string()));
+                }
+                Some(actor_id)
+            }
+            None => None,
+        };
+
+        let session_id = match claims.sid {
+            Some(sid) => {
+                let session_id = Uuid::parse_str(&sid)
+                    .map_err(|_| AppError::Auth("Invalid session ID in token".to_string()))?;
+                self.check_session(session_id, user.id, impersonator).await?;
+                Some(session_id)
+            }
+            // Impersonation has to be revocable, so its tokens always belong to a session
+            None if impersonator.is_some() => {
+                return Err(AppError::Auth("Impersonation token has no session".to_string()));
+            }
+            None => None,
+        };
+
+        Ok(Authenticated { user, impersonator, session_id })
+    }
+
+    async fn start_session(&self, user: &users::Model, user_agent: Option<String>) -> Result<session_entity::Model> {
+        let mut session_active = session_entity::ActiveModel::new();
+        session_active.user_id = Set(user.id);
+        session_active.user_agent = Set(user_agent);
+        session_active.expires_at = Set((Utc::now() + Duration::hours(self.jwt_expiry_hours)).into());
+
+        session_active.insert(&self.db.connection).await
+            .map_err(|e| AppError::Database(e.into()))
+    }
+
+    /// Reject revoked, expired and idle sessions, otherwise record that the session was used.
+    /// Impersonation tokens only match the session opened for their actor, and other tokens only
+    /// match ordinary sessions.
+    async fn check_session(&self, session_id: Uuid, user_id: Uuid, impersonator: Option<Uuid>) -> Result<()> {
+        let session = Sessions::find_by_id(session_id)
+            .filter(session_entity::Column::UserId.eq(user_id))
+            .filter(match impersonator {
+                Some(actor_id) => session_entity::Column::ImpersonatorId.eq(actor_id),
+                None => session_entity::Column::ImpersonatorId.is_null(),
+            })
+            .one(&self.db.connection)
+            .await
+            .map_err(|e| AppError::Database(e.into()))?
+            .ok_or_else(|| AppError::Auth("Session not found".to_string()))?;
+
+        if session.revoked_at.is_some() {
+            return Err(AppError::Coded(ErrorCode::AuthSessionRevoked, "Session has been revoked".to_string()));
+        }
+
+        let now = Utc::now();
+        if session.expires_at.naive_utc().and_utc() <= now {
+            return Err(AppError::Coded(ErrorCode::AuthSessionExpired, "Session has expired".to_string()));
+        }
+
+        let last_active_at = self.last_active_at(&session).await;
+        if self.session_activity.idle_expires_at(last_active_at).is_some_and(|expires_at| expires_at <= now) {
+            return Err(AppError::Coded(ErrorCode::AuthSessionExpired, "Session expired due to inactivity".to_string()));
+        }
+
+        self.session_activity.touch(session_id, now).await;
+        Ok(())
+    }
+
+    /// Last use of a session, including activity not yet flushed to the database
+    pub async fn last_active_at(&self, session: &session_entity::Model) -> chrono::DateTime<Utc> {
+        let stored = session.last_active_at.naive_utc().and_utc();
+        match self.session_activity.pending_activity(session.id).await {
+            Some(pending) => stored.max(pending),
+            None => stored,
+        }
+    }
+
+    /// Open a short-lived session letting `actor` view `target`'s account read-only; its token
+    /// stops working once the session is revoked
+    pub async fn start_impersonation(
+        &self,
+        actor: &users::Model,
+        target: &users::Model,
+        ttl: Duration,
+    ) -> Result<(String, session_entity::Model)> {
+        let mut session_active = session_entity::ActiveModel::new();
+        session_active.user_id = Set(target.id);
+        session_active.user_agent = Set(Some(IMPERSONATION_USER_AGENT.to_string()));
+        session_active.expires_at = Set((Utc::now() + ttl).into());
+        session_active.impersonator_id = Set(Some(actor.id));
+
+        let session = session_active.insert(&self.db.connection).await
+            .map_err(|e| AppError::Database(e.into()))?;
+        let token = self.encode_token(target, ttl, Some(actor.id), Some(session.id))?;
+        Ok((token, session))
+    }
+
+    async fn find_user(&self, user_id: Uuid) -> Result<users::Model> {
+        Users::find_by_id(user_id)
             .one(&self.db.connection)
             .await
             .map_err(|e| AppError::Database(e.into()))?
-            .ok_or_else(|| AppError::Auth("User not found".to_string()))?;
+            .ok_or_else(|| AppError::Auth("User not found".to_string()))
+    }
 
-        Ok(user)
+    fn generate_token(&self, user: &users::Model, session: &session_entity::Model) -> Result<String> {
+        self.encode_token(user, Duration::hours(self.jwt_expiry_hours), None, Some(session.id))
     }
 
-    fn generate_token(&self, user: &users::Model) -> Result<String> {
+    fn encode_token(
+        &self,
+        user: &users::Model,
+        ttl: Duration,
+        actor_id: Option<Uuid>,
+        session_id: Option<Uuid>,
+    ) -> Result<String> {
         let now = Utc::now();
-        let expiry = now + Duration::hours(self.jwt_expiry_hours);
+        let expiry = now + ttl;
 
         let claims = Claims {
             sub: user.id.to_string(),
             email: user.email.clone(),
             exp: expiry.timestamp(),
             iat: now.timestamp(),
+            nbf: Some(now.timestamp()),
             aud: "streamline-scheduler".to_string(),
             iss: "streamline-scheduler".to_string(),
+            act: actor_id.map(|id| id.to_string()),
+            sid: session_id.map(|id| id.to_string()),
         };
 
         let token = encode(
@@ -149,6 +324,8 @@ impl AuthService {
         let mut validation = Validation::new(Algorithm::HS256);
         validation.set_audience(&["streamline-scheduler"]);
         validation.set_issuer(&["streamline-scheduler"]);
+        validation.leeway = self.jwt_leeway_secs;
+        validation.validate_nbf = true;
 
         let token_data = decode::<Claims>(
             token,
@@ -156,6 +333,11 @@ impl AuthService {
             &validation,
         )?;
 
+        // The library doesn't check `iat`; a token from the future means a badly skewed issuer
+        if token_data.claims.iat > Utc::now().timestamp() + self.jwt_leeway_secs as i64 {
+            return Err(AppError::Auth("Token issued in the future".to_string()));
+        }
+
         Ok(token_data.claims)
     }
 
@@ -0,0 +1,70 @@
+use chrono::{DateTime, Duration, Utc};
+use std::collections::HashMap;
+use std::env;
+use std::sync::Arc;
+use tokio::sync::Mutex;
+use uuid::Uuid;
+
+const DEFAULT_FLUSH_INTERVAL_SECS: u64 = 30;
+
+/// Buffers when each session was last used so requests don't write to the database;
+/// the buffer is flushed periodically by the session activity job
+#[derive(Clone)]
+pub struct SessionActivity {
+    pending: Arc<Mutex<HashMap<Uuid, DateTime<Utc>>>>,
+    /// Idle time after which a session expires (`SESSION_INACTIVITY_TIMEOUT_MINUTES`); off when unset or 0
+    pub inactivity_timeout: Option<Duration>,
+    pub flush_interval: std::time::Duration,
+}
+
+impl SessionActivity {
+    pub fn from_env() -> Self {
+        let inactivity_timeout = env::var("SESSION_INACTIVITY_TIMEOUT_MINUTES")
+            .ok()
+            .and_then(|v| v.parse::<i64>().ok())
+            .filter(|&minutes| minutes > 0)
+            .map(Duration::minutes);
+        let flush_interval = std::time::Duration::from_secs(
+            env::var("SESSION_ACTIVITY_FLUSH_SECS")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .filter(|&secs: &u64| secs > 0)
+                .unwrap_or(DEFAULT_FLUSH_INTERVAL_SECS),
+        );
+
+        Self {
+            pending: Arc::new(Mutex::new(HashMap::new())),
+            inactivity_timeout,
+            flush_interval,
+        }
+    }
+
+    pub async fn touch(&self, session_id: Uuid, at: DateTime<Utc>) {
+        let mut pending = self.pending.lock().await;
+        let last = pending.entry(session_id).or_insert(at);
+        *last = (*last).max(at);
+    }
+
+    /// Activity recorded since the last flush
+    pub async fn pending_activity(&self, session_id: Uuid) -> Option<DateTime<Utc>> {
+        self.pending.lock().await.get(&session_id).copied()
+    }
+
+    pub async fn take_pending(&self) -> HashMap<Uuid, DateTime<Utc>> {
+        std::mem::take(&mut *self.pending.lock().await)
+    }
+
+    /// Put back activity whose flush failed, keeping anything newer recorded meanwhile
+    pub async fn restore(&self, activity: HashMap<Uuid, DateTime<Utc>>) {
+        let mut pending = self.pending.lock().await;
+        for (session_id, at) in activity {
+            let last = pending.entry(session_id).or_insert(at);
+            *last = (*last).max(at);
+        }
+    }
+
+    /// When a session last active at `last_active_at` expires for inactivity, if that's enabled
+    pub fn idle_expires_at(&self, last_active_at: DateTime<Utc>) -> Option<DateTime<Utc>> {
+        self.inactivity_timeout.map(|timeout| last_active_at + timeout)
+    }
+}
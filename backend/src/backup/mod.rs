@@ -0,0 +1,557 @@
+//! Whole-database backups for administrators.
+//!
+//! A backup is a ZIP holding `manifest.json` and the tables, grouped by schema. Postgres
+//! tables are dumped as `<schema>/<table>.jsonl` with one row per line, read in a single
+//! snapshot. SQLite databases are copied as `<schema>.sqlite` files, which open directly
+//! with `sqlite3`. A backup restores only into a database of the same kind with exactly
+//! the migrations it was taken with; the restore replaces every row.
+
+use async_zip::{base::read::mem::ZipFileReader, tokio::write::ZipFileWriter, Compression, ZipEntryBuilder};
+use chrono::Utc;
+use futures_util::{AsyncReadExt, AsyncWriteExt};
+use sea_orm::*;
+use serde_json::Value;
+use std::collections::{BTreeMap, HashSet};
+use sqlx::sqlite::SqliteConnectOptions;
+use std::path::{Path, PathBuf};
+use tokio::io::AsyncWrite;
+use uuid::Uuid;
+
+use crate::{
+    errors::{AppError, Result},
+    migrator::runner::applied_versions,
+    models::backup::{
+        BackupFormat, BackupManifest, RestoreSummary, BACKUP_FORMAT_VERSION, BACKUP_MANIFEST_ENTRY,
+        MAX_RESTORE_ENTRY_BYTES,
+    },
+};
+
+/// Rows fetched from the dump cursor at a time
+const FETCH_SIZE: usize = 1000;
+/// Rows per INSERT statement when restoring
+const INSERT_CHUNK_SIZE: usize = 500;
+
+/// A table in the backup
+pub struct BackupTable {
+    pub schema: &'static str,
+    pub name: &'static str,
+    /// Column linking to another row of the same table, so rows can be restored parents first
+    pub parent_column: Option<&'static str>,
+}
+
+const fn table(schema: &'static str, name: &'static str) -> BackupTable {
+    BackupTable { schema, name, parent_column: None }
+}
+
+const fn tree(schema: &'static str, name: &'static str, parent_column: &'static str) -> BackupTable {
+    BackupTable { schema, name, parent_column: Some(parent_column) }
+}
+
+/// Every table with user data, each after the tables it references
+pub const TABLES: &[BackupTable] = &[
+    table("auth", "users"),
+    table("auth", "sessions"),
+    table("public", "organizations"),
+    table("public", "organization_members"),
+    tree("public", "projects", "parent_id"),
+    table("public", "calendars"),
+    table("public", "calendar_subscriptions"),
+    tree("public", "calendar_events", "recurring_event_id"),
+    tree("public", "can_do_list", "parent_task_id"),
+    table("public", "notes"),
+    table("public", "user_settings"),
+    table("public", "settings"),
+    table("public", "kv_entries"),
+    table("public", "conflicts"),
+    table("public", "notification_preferences"),
+    table("public", "scheduling_constraints"),
+    table("public", "deleted_records"),
+    table("public", "audit_log"),
+    table("public", "record_revisions"),
+    table("public", "reminders"),
+    table("public", "time_entries"),
+    table("public", "focus_sessions"),
+    table("public", "habits"),
+    table("public", "habit_checkins"),
+    table("public", "goals"),
+    table("public", "goal_links"),
+    table("public", "templates"),
+    table("public", "feature_flag_overrides"),
+    table("public", "announcements"),
+    table("public", "event_attendees"),
+    table("public", "project_members"),
+    table("public", "scheduling_polls"),
+    table("public", "poll_slots"),
+    table("public", "poll_votes"),
+    table("public", "booking_pages"),
+    table("public", "bookings"),
+    table("public", "webhooks"),
+    table("public", "webhook_deliveries"),
+    table("public", "inbound_email_addresses"),
+];
+
+/// Bookkeeping tables the migrations own; a backup restores into an identically migrated schema
+const SCHEMA_TABLES: &[&str] = &["seaql_migrations", "schema_backfills"];
+
+/// A consistent view of the database, ready to be written out as a backup
+pub enum Snapshot {
+    Postgres(DatabaseTransaction),
+    /// Copies of the SQLite files, taken while writers were held off
+    Sqlite(TempFiles),
+}
+
+impl Snapshot {
+    /// Start a backup. Fails if the database has a table the backup doesn't know about, so a
+    /// table added without updating [`TABLES`] can't silently go missing from backups.
+    pub async fn begin(db: &DatabaseConnection) -> Result<Self> {
+        ensure_tables_covered(db).await?;
+        match db.get_database_backend() {
+            DbBackend::Sqlite => Ok(Snapshot::Sqlite(copy_sqlite(db).await?)),
+            _ => {
+                let txn = db
+                    .begin_with_config(Some(IsolationLevel::RepeatableRead), Some(AccessMode::ReadOnly))
+                    .await
+                    .map_err(|e| AppError::Database(e.into()))?;
+                Ok(Snapshot::Postgres(txn))
+            }
+        }
+    }
+
+    /// Take a transaction-scoped advisory lock, so replicas don't run the same scheduled
+    /// backup side by side; false if another one holds it
+    pub async fn try_lock(&self, key: i64) -> Result<bool> {
+        let Snapshot::Postgres(txn) = self else {
+            return Ok(true);
+        };
+        let row = txn
+            .query_one(Statement::from_sql_and_values(
+                DbBackend::Postgres,
+                "SELECT pg_try_advisory_xact_lock($1) AS locked",
+                [key.into()],
+            ))
+            .await
+            .map_err(|e| AppError::Database(e.into()))?;
+        Ok(row.and_then(|row| row.try_get::<bool>("", "locked").ok()).unwrap_or(false))
+    }
+
+    /// Write the backup archive to `writer`
+    pub async fn write<W: AsyncWrite + Unpin>(self, writer: W) -> Result<BackupManifest> {
+        let mut zip = ZipFileWriter::with_tokio(writer);
+
+        let (format, migrations, counts) = match self {
+            Snapshot::Postgres(txn) => {
+                let mut counts = BTreeMap::new();
+                for table in TABLES {
+                    let count = dump_table(&txn, &mut zip, table).await?;
+                    counts.insert(format!("{}.{}", table.schema, table.name), count);
+                }
+                let migrations = sorted(applied_versions(&txn).await?);
+                txn.commit().await.map_err(|e| AppError::Database(e.into()))?;
+                (BackupFormat::Postgres, migrations, counts)
+            }
+            Snapshot::Sqlite(files) => {
+                let (counts, migrations) = files.describe().await?;
+                for (schema, path) in &files.paths {
+                    let data = tokio::fs::read(path)
+                        .await
+                        .map_err(|e| AppError::Internal(format!("Reading the database copy failed: {}", e)))?;
+                    write_entry(&mut zip, &format!("{}.sqlite", schema), &data).await?;
+                }
+                (BackupFormat::Sqlite, migrations, counts)
+            }
+        };
+
+        let manifest = BackupManifest {
+            format_version: BACKUP_FORMAT_VERSION,
+            format,
+            created_at: Utc::now(),
+            migrations,
+            counts,
+        };
+        write_entry(&mut zip, BACKUP_MANIFEST_ENTRY, &serde_json::to_vec_pretty(&manifest)?).await?;
+        zip.close()
+            .await
+            .map_err(|e| AppError::Internal(format!("Writing the backup archive failed: {}", e)))?;
+        Ok(manifest)
+    }
+}
+
+fn sorted(versions: HashSet<String>) -> Vec<String> {
+    let mut versions: Vec<String> = versions.into_iter().collect();
+    versions.sort();
+    versions
+}
+
+async fn ensure_tables_covered(db: &DatabaseConnection) -> Result<()> {
+    let backend = db.get_database_backend();
+    let sql = match backend {
+        DbBackend::Sqlite => {
+            "SELECT 'public' AS table_schema, name AS table_name FROM main.sqlite_master \
+                 WHERE type = 'table' AND name NOT LIKE 'sqlite_%' \
+             UNION ALL \
+             SELECT 'auth', name FROM auth.sqlite_master WHERE type = 'table' AND name NOT LIKE 'sqlite_%'"
+        }
+        _ => {
+            "SELECT table_schema, table_name FROM information_schema.tables \
+             WHERE table_schema IN ('auth', 'public') AND table_type = 'BASE TABLE'"
+        }
+    };
+    let rows = db
+        .query_all(Statement::from_string(backend, sql))
+        .await
+        .map_err(|e| AppError::Database(e.into()))?;
+
+    let known: HashSet<(&str, &str)> = TABLES.iter().map(|table| (table.schema, table.name)).collect();
+    let mut missing = Vec::new();
+    for row in rows {
+        let schema: String = row.try_get("", "table_schema").map_err(|e| AppError::Database(e.into()))?;
+        let name: String = row.try_get("", "table_name").map_err(|e| AppError::Database(e.into()))?;
+        if !known.contains(&(schema.as_str(), name.as_str())) && !SCHEMA_TABLES.contains(&name.as_str()) {
+            missing.push(format!("{}.{}", schema, name));
+        }
+    }
+    if !missing.is_empty() {
+        missing.sort();
+        return Err(AppError::Internal(format!("Tables missing from the backup table list: {}", missing.join(", "))));
+    }
+    Ok(())
+}
+
+/// Stream one table into `<schema>/<table>.jsonl` through a cursor, so it never sits in memory whole
+async fn dump_table<W: AsyncWrite + Unpin>(
+    txn: &DatabaseTransaction,
+    zip: &mut ZipFileWriter<W>,
+    table: &BackupTable,
+) -> Result<u64> {
+    let failed = |e: async_zip::error::ZipError| AppError::Internal(format!("Writing the backup archive failed: {}", e));
+    let io_failed = |e: std::io::Error| AppError::Internal(format!("Writing the backup archive failed: {}", e));
+    let db_failed = |e: DbErr| AppError::Database(e.into());
+
+    txn.execute_unprepared(&format!(
+        "DECLARE backup_rows NO SCROLL CURSOR FOR SELECT row_to_json(t)::text AS row FROM \"{}\".\"{}\" t",
+        table.schema, table.name
+    ))
+    .await
+    .map_err(db_failed)?;
+
+    let entry = ZipEntryBuilder::new(format!("{}/{}.jsonl", table.schema, table.name).into(), Compression::Deflate);
+    let mut writer = zip.write_entry_stream(entry).await.map_err(failed)?;
+    let mut count = 0;
+    loop {
+        let rows = txn
+            .query_all(Statement::from_string(
+                DbBackend::Postgres,
+                format!("FETCH {} FROM backup_rows", FETCH_SIZE),
+            ))
+            .await
+            .map_err(db_failed)?;
+        for row in &rows {
+            let line: String = row.try_get("", "row").map_err(db_failed)?;
+            writer.write_all(line.as_bytes()).await.map_err(io_failed)?;
+            writer.write_all(b"\n").await.map_err(io_failed)?;
+        }
+        count += rows.len() as u64;
+        if rows.len() < FETCH_SIZE {
+            break;
+        }
+    }
+    writer.close().await.map_err(failed)?;
+
+    txn.execute_unprepared("CLOSE backup_rows").await.map_err(db_failed)?;
+    Ok(count)
+}
+
+async fn write_entry<W: AsyncWrite + Unpin>(zip: &mut ZipFileWriter<W>, name: &str, data: &[u8]) -> Result<()> {
+    let entry = ZipEntryBuilder::new(name.to_string().into(), Compression::Deflate);
+    zip.write_entry_whole(entry, data)
+        .await
+        .map_err(|e| AppError::Internal(format!("Writing the backup archive failed: {}", e)))
+}
+
+/// Database files in the temp directory, removed when dropped
+pub struct TempFiles {
+    /// Schema and file, in the order of [`SQLITE_SCHEMAS`]
+    paths: Vec<(&'static str, PathBuf)>,
+}
+
+/// Where each backup schema lives on a SQLite connection
+const SQLITE_SCHEMAS: &[(&str, &str)] = &[("auth", "auth"), ("public", "main")];
+
+impl TempFiles {
+    fn new(purpose: &str) -> Self {
+        let id = Uuid::new_v4();
+        let paths = SQLITE_SCHEMAS
+            .iter()
+            .map(|(schema, _)| (*schema, std::env::temp_dir().join(format!("streamline-{}-{}-{}.sqlite", purpose, id, schema))))
+            .collect();
+        Self { paths }
+    }
+
+    fn path(&self, schema: &str) -> &Path {
+        let (_, path) = self.paths.iter().find(|(name, _)| *name == schema).expect("unknown backup schema");
+        path
+    }
+
+    /// Row counts and applied migrations of the copied files
+    async fn describe(&self) -> Result<(BTreeMap<String, u64>, Vec<String>)> {
+        let options = SqliteConnectOptions::new().filename(self.path("public")).read_only(true);
+        let mut conn = sqlx::ConnectOptions::connect(&options).await.map_err(sqlx_failed)?;
+        sqlx::query(&format!("ATTACH DATABASE {} AS auth", sql_path(self.path("auth"))))
+            .execute(&mut conn)
+            .await
+            .map_err(sqlx_failed)?;
+
+        let mut counts = BTreeMap::new();
+        for table in TABLES {
+            let count: i64 = sqlx::query_scalar(&format!("SELECT COUNT(*) FROM {}", sqlite_table(table)))
+                .fetch_one(&mut conn)
+                .await
+                .map_err(sqlx_failed)?;
+            counts.insert(format!("{}.{}", table.schema, table.name), count as u64);
+        }
+        let migrations: Vec<String> = sqlx::query_scalar("SELECT version FROM seaql_migrations ORDER BY version")
+            .fetch_all(&mut conn)
+            .await
+            .map_err(sqlx_failed)?;
+        sqlx::Connection::close(conn).await.map_err(sqlx_failed)?;
+        Ok((counts, migrations))
+    }
+}
+
+impl Drop for TempFiles {
+    fn drop(&mut self) {
+        for (_, path) in &self.paths {
+            let _ = std::fs::remove_file(path);
+        }
+    }
+}
+
+/// A file path as a SQL string literal
+fn sql_path(path: &Path) -> String {
+    format!("'{}'", path.to_string_lossy().replace('\'', "''"))
+}
+
+fn sqlx_failed(e: sqlx::Error) -> AppError {
+    AppError::Database(e.into())
+}
+
+fn sqlite_table(table: &BackupTable) -> String {
+    let schema = SQLITE_SCHEMAS.iter().find(|(schema, _)| *schema == table.schema).map(|(_, attached)| *attached).unwrap_or("main");
+    format!("\"{}\".\"{}\"", schema, table.name)
+}
+
+/// Copy both SQLite files with `VACUUM INTO`. A second connection holds the write lock on
+/// both meanwhile, so the copies agree with each other.
+async fn copy_sqlite(db: &DatabaseConnection) -> Result<TempFiles> {
+    let pool = db.get_sqlite_connection_pool();
+    let mut writer_lock = pool.acquire().await.map_err(sqlx_failed)?;
+    let mut conn = pool.acquire().await.map_err(sqlx_failed)?;
+    let files = TempFiles::new("backup");
+
+    sqlx::query("BEGIN IMMEDIATE").execute(&mut *writer_lock).await.map_err(sqlx_failed)?;
+    let mut copied = Ok(());
+    for (schema, attached) in SQLITE_SCHEMAS {
+        copied = sqlx::query(&format!("VACUUM \"{}\" INTO {}", attached, sql_path(files.path(schema))))
+            .execute(&mut *conn)
+            .await
+            .map(|_| ());
+        if copied.is_err() {
+            break;
+        }
+    }
+    sqlx::query("ROLLBACK").execute(&mut *writer_lock).await.map_err(sqlx_failed)?;
+    copied.map_err(sqlx_failed)?;
+    Ok(files)
+}
+
+/// Replace the contents of the database with a backup archive
+pub async fn restore(db: &DatabaseConnection, data: Vec<u8>) -> Result<RestoreSummary> {
+    let zip = ZipFileReader::new(data)
+        .await
+        .map_err(|e| AppError::Validation(format!("Not a valid backup archive: {}", e)))?;
+    let manifest: BackupManifest = serde_json::from_slice(
+        &read_entry(&zip, BACKUP_MANIFEST_ENTRY)
+            .await?
+            .ok_or_else(|| AppError::Validation(format!("The archive has no {}", BACKUP_MANIFEST_ENTRY)))?,
+    )
+    .map_err(|e| AppError::Validation(format!("{} is not a valid manifest: {}", BACKUP_MANIFEST_ENTRY, e)))?;
+
+    if manifest.format_version != BACKUP_FORMAT_VERSION {
+        return Err(AppError::Validation(format!(
+            "Unsupported backup format version {}; this server reads version {}",
+            manifest.format_version, BACKUP_FORMAT_VERSION
+        )));
+    }
+    let backend = db.get_database_backend();
+    let expected_format = match backend {
+        DbBackend::Sqlite => BackupFormat::Sqlite,
+        _ => BackupFormat::Postgres,
+    };
+    if manifest.format != expected_format {
+        return Err(AppError::Validation(format!(
+            "This is a {:?} backup, but the server runs on {:?}",
+            manifest.format, expected_format
+        )));
+    }
+    if manifest.migrations != sorted(applied_versions(db).await?) {
+        return Err(AppError::Conflict(
+            "The backup was taken with different migrations; restore it with the release that made it".to_string(),
+        ));
+    }
+
+    let restored = match backend {
+        DbBackend::Sqlite => restore_sqlite(db, &zip).await?,
+        _ => restore_postgres(db, &zip).await?,
+    };
+    Ok(RestoreSummary { created_at: manifest.created_at, restored })
+}
+
+/// Delete every row and insert the archived ones in one transaction, so a failed restore
+/// leaves the database as it was
+async fn restore_postgres(db: &DatabaseConnection, zip: &ZipFileReader) -> Result<BTreeMap<String, u64>> {
+    let db_failed = |e: DbErr| AppError::Database(e.into());
+    let txn = db.begin().await.map_err(db_failed)?;
+
+    let tables: Vec<String> = TABLES.iter().map(|table| format!("\"{}\".\"{}\"", table.schema, table.name)).collect();
+    txn.execute_unprepared(&format!("TRUNCATE {}", tables.join(", "))).await.map_err(db_failed)?;
+
+    let mut restored = BTreeMap::new();
+    for (table, qualified) in TABLES.iter().zip(&tables) {
+        let name = format!("{}/{}.jsonl", table.schema, table.name);
+        let data = read_entry(zip, &name).await?.unwrap_or_default();
+        let mut rows = data
+            .split(|byte| *byte == b'\n')
+            .filter(|line| !line.is_empty())
+            .map(serde_json::from_slice::<Value>)
+            .collect::<std::result::Result<Vec<_>, _>>()
+            .map_err(|e| AppError::Validation(format!("{} is not a valid table dump: {}", name, e)))?;
+        if let Some(parent_column) = table.parent_column {
+            rows = parents_first(rows, parent_column);
+        }
+
+        for chunk in rows.chunks(INSERT_CHUNK_SIZE) {
+            txn.execute(Statement::from_sql_and_values(
+                DbBackend::Postgres,
+                format!("INSERT INTO {0} SELECT * FROM json_populate_recordset(NULL::{0}, $1::json)", qualified),
+                [Value::Array(chunk.to_vec()).to_string().into()],
+            ))
+            .await
+            .map_err(db_failed)?;
+        }
+        restored.insert(format!("{}.{}", table.schema, table.name), rows.len() as u64);
+    }
+
+    txn.commit().await.map_err(db_failed)?;
+    Ok(restored)
+}
+
+/// Order rows so each comes after the row its `parent_column` points at
+fn parents_first(rows: Vec<Value>, parent_column: &str) -> Vec<Value> {
+    let id = |row: &Value| row.get("id").and_then(Value::as_str).map(str::to_string);
+    let parent = |row: &Value| row.get(parent_column).and_then(Value::as_str).map(str::to_string);
+    let ids: HashSet<String> = rows.iter().filter_map(id).collect();
+    let mut placed = HashSet::with_capacity(ids.len());
+    let mut ordered = Vec::with_capacity(rows.len());
+    let mut pending = rows;
+
+    loop {
+        let (ready, rest): (Vec<Value>, Vec<Value>) = pending
+            .into_iter()
+            .partition(|row| parent(row).is_none_or(|parent_id| !ids.contains(&parent_id) || placed.contains(&parent_id)));
+        pending = rest;
+        if ready.is_empty() {
+            break;
+        }
+        placed.extend(ready.iter().filter_map(id));
+        ordered.extend(ready);
+    }
+    // Rows caught in a cycle keep their order; the foreign key check rejects them
+    ordered.extend(pending);
+    ordered
+}
+
+/// Attach the archived files and copy their rows over on one connection, in one transaction
+async fn restore_sqlite(db: &DatabaseConnection, zip: &ZipFileReader) -> Result<BTreeMap<String, u64>> {
+    let files = TempFiles::new("restore");
+    for (schema, _) in SQLITE_SCHEMAS {
+        let name = format!("{}.sqlite", schema);
+        let data = read_entry(zip, &name)
+            .await?
+            .ok_or_else(|| AppError::Validation(format!("The archive has no {}", name)))?;
+        tokio::fs::write(files.path(schema), data)
+            .await
+            .map_err(|e| AppError::Internal(format!("Writing the restored database failed: {}", e)))?;
+    }
+
+    let mut conn = db.get_sqlite_connection_pool().acquire().await.map_err(sqlx_failed)?;
+    for (schema, _) in SQLITE_SCHEMAS {
+        sqlx::query(&format!("ATTACH DATABASE {} AS \"restore_{}\"", sql_path(files.path(schema)), schema))
+            .execute(&mut *conn)
+            .await
+            .map_err(sqlx_failed)?;
+    }
+
+    let copied = copy_restored_rows(&mut conn).await;
+    if copied.is_err() {
+        let _ = sqlx::query("ROLLBACK").execute(&mut *conn).await;
+    }
+    for (schema, _) in SQLITE_SCHEMAS {
+        sqlx::query(&format!("DETACH DATABASE \"restore_{}\"", schema))
+            .execute(&mut *conn)
+            .await
+            .map_err(sqlx_failed)?;
+    }
+    copied
+}
+
+async fn copy_restored_rows(conn: &mut sqlx::SqliteConnection) -> Result<BTreeMap<String, u64>> {
+    sqlx::query("BEGIN IMMEDIATE").execute(&mut *conn).await.map_err(sqlx_failed)?;
+    // Checked at commit, once every table is back
+    sqlx::query("PRAGMA defer_foreign_keys = ON").execute(&mut *conn).await.map_err(sqlx_failed)?;
+    for table in TABLES.iter().rev() {
+        sqlx::query(&format!("DELETE FROM {}", sqlite_table(table)))
+            .execute(&mut *conn)
+            .await
+            .map_err(sqlx_failed)?;
+    }
+
+    let mut restored = BTreeMap::new();
+    for table in TABLES {
+        let result = sqlx::query(&format!(
+            "INSERT INTO {} SELECT * FROM \"restore_{}\".\"{}\"",
+            sqlite_table(table),
+            table.schema,
+            table.name
+        ))
+        .execute(&mut *conn)
+        .await
+        .map_err(sqlx_failed)?;
+        restored.insert(format!("{}.{}", table.schema, table.name), result.rows_affected());
+    }
+    sqlx::query("COMMIT").execute(&mut *conn).await.map_err(sqlx_failed)?;
+    Ok(restored)
+}
+
+async fn read_entry(zip: &ZipFileReader, name: &str) -> Result<Option<Vec<u8>>> {
+    let entries = zip.file().entries();
+    let Some(index) = entries.iter().position(|entry| entry.filename().as_str().is_ok_and(|filename| filename == name)) else {
+        return Ok(None);
+    };
+    let invalid = |e: async_zip::error::ZipError| AppError::Validation(format!("Can't read {} from the archive: {}", name, e));
+
+    let mut reader = zip.reader_with_entry(index).await.map_err(invalid)?;
+    // The declared size can't be trusted, so stop inflating once the limit is passed
+    let mut data = Vec::new();
+    (&mut reader)
+        .take(MAX_RESTORE_ENTRY_BYTES + 1)
+        .read_to_end(&mut data)
+        .await
+        .map_err(|e| AppError::Validation(format!("Can't read {} from the archive: {}", name, e)))?;
+    if data.len() as u64 > MAX_RESTORE_ENTRY_BYTES {
+        return Err(AppError::Validation(format!("{} is larger than {} bytes", name, MAX_RESTORE_ENTRY_BYTES)));
+    }
+    if reader.compute_hash() != entries[index].crc32() {
+        return Err(AppError::Validation(format!("{} is corrupted", name)));
+    }
+    Ok(Some(data))
+}
@@ -0,0 +1,123 @@
+use clap::{Parser, Subcommand};
+use sea_orm::DatabaseConnection;
+
+use crate::{
+    db::Database,
+    errors::{AppError, Result},
+    migrator::runner::{self, MigrationState},
+    seed::{self, DemoOptions},
+};
+
+/// Streamline Scheduler backend; serves the API unless told otherwise
+#[derive(Debug, Parser)]
+#[command(name = "streamline-backend", version)]
+pub struct Cli {
+    #[command(subcommand)]
+    pub command: Option<Command>,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum Command {
+    /// Serve the API, migrating according to MIGRATION_MODE (the default)
+    Serve,
+    /// Manage the database schema without starting the server
+    Migrate {
+        #[command(subcommand)]
+        command: MigrateCommand,
+    },
+    /// Create a demo user with nested projects, hundreds of tasks and a few months of events
+    SeedDemo {
+        /// Email of the demo user; must not be registered yet
+        #[arg(long, default_value = "demo@streamline.local")]
+        email: String,
+        /// Password to sign in with; it also encrypts the demo records
+        #[arg(long, default_value = "demo-password")]
+        password: String,
+        /// How many tasks to create
+        #[arg(long, default_value_t = 300)]
+        tasks: usize,
+        /// Months of past events; one more month of upcoming ones is added
+        #[arg(long, default_value_t = 3)]
+        months: u32,
+        /// Seed for the generator; the same seed creates the same content
+        #[arg(long)]
+        seed: Option<u64>,
+    },
+}
+
+#[derive(Debug, Subcommand)]
+pub enum MigrateCommand {
+    /// Apply pending migrations
+    Up {
+        /// Apply at most this many
+        #[arg(short = 'n', long)]
+        steps: Option<u32>,
+    },
+    /// Roll back the most recently applied migrations
+    Down {
+        /// How many to roll back
+        #[arg(short = 'n', long, default_value_t = 1)]
+        steps: u32,
+    },
+    /// List every migration and whether it is applied
+    Status,
+    /// Drop every table and apply all migrations to the empty database, deleting all data
+    Fresh {
+        /// Confirm that all data may be deleted
+        #[arg(long)]
+        yes: bool,
+    },
+}
+
+pub async fn migrate(db: &DatabaseConnection, command: MigrateCommand) -> Result<()> {
+    match command {
+        MigrateCommand::Up { steps } => {
+            runner::up(db, steps).await?;
+            print_status(db).await
+        }
+        MigrateCommand::Down { steps } => {
+            runner::down(db, steps).await?;
+            print_status(db).await
+        }
+        MigrateCommand::Status => print_status(db).await,
+        MigrateCommand::Fresh { yes: false } => Err(AppError::Validation(
+            "migrate fresh deletes all data; pass --yes to confirm".to_string(),
+        )),
+        MigrateCommand::Fresh { yes: true } => {
+            runner::fresh(db).await?;
+            print_status(db).await
+        }
+    }
+}
+
+async fn print_status(db: &DatabaseConnection) -> Result<()> {
+    let migrations = runner::status(db).await?;
+    for (version, state) in &migrations {
+        let state = match state {
+            MigrationState::Applied => "applied",
+            MigrationState::Pending => "pending",
+            MigrationState::Unknown => "unknown",
+        };
+        println!("{:<8} {}", state, version);
+    }
+
+    let pending = migrations.iter().filter(|(_, state)| *state == MigrationState::Pending).count();
+    let unknown = migrations.iter().filter(|(_, state)| *state == MigrationState::Unknown).count();
+    println!("{} migrations, {} pending", migrations.len() - unknown, pending);
+    if unknown > 0 {
+        println!("{} applied by a newer release", unknown);
+    }
+    Ok(())
+}
+
+pub async fn seed_demo(db: &Database, options: DemoOptions) -> Result<()> {
+    runner::run(&db.connection).await?;
+    let summary = seed::seed_demo(db, &options).await?;
+
+    println!("Created demo user {} ({})", options.email, summary.user_id);
+    println!("Password: {}", options.password);
+    for (table, count) in &summary.created {
+        println!("{:<16} {}", table, count);
+    }
+    Ok(())
+}
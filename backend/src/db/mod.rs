@@ -1,25 +1,331 @@
-use sea_orm::{Database as SeaDatabase, DatabaseConnection, ConnectOptions};
+use chrono::{DateTime, Utc};
+use sea_orm::{
+    Database as SeaDatabase, DatabaseConnection, DatabaseTransaction, ConnectOptions, ConnectionTrait, DbBackend, DbErr,
+    SqlxSqliteConnector, Statement, TransactionTrait,
+};
+use serde::Serialize;
+use sqlx::{sqlite::SqliteConnectOptions, Executor};
 use std::env;
-use crate::errors::Result;
+use std::future::Future;
+use std::path::{Path, PathBuf};
+use std::pin::Pin;
+use std::str::FromStr;
+use std::sync::{atomic::{AtomicUsize, Ordering}, Arc};
+use std::time::Duration;
+use uuid::Uuid;
+use crate::errors::{AppError, Result};
+
+/// Pool and startup settings, read from `DB_*` environment variables
+#[derive(Debug, Clone)]
+pub struct DatabaseConfig {
+    pub url: String,
+    /// Read replicas of `url`; list and get queries are spread across them
+    pub replica_urls: Vec<String>,
+    pub max_connections: u32,
+    pub min_connections: u32,
+    pub connect_timeout: Duration,
+    pub acquire_timeout: Duration,
+    pub idle_timeout: Duration,
+    pub max_lifetime: Duration,
+    pub sqlx_logging: bool,
+    /// Attempts to reach the database at startup before giving up
+    pub connect_attempts: u32,
+    /// Delay after the first failed attempt; doubles after each further one
+    pub connect_backoff: Duration,
+    pub connect_backoff_max: Duration,
+}
+
+impl DatabaseConfig {
+    pub fn from_env() -> Self {
+        fn number(key: &str, default: u64) -> u64 {
+            env::var(key).ok().and_then(|v| v.trim().parse().ok()).unwrap_or(default)
+        }
+
+        Self {
+            url: env::var("DATABASE_URL").expect("DATABASE_URL environment variable must be set"),
+            replica_urls: env::var("DATABASE_REPLICA_URLS")
+                .map(|urls| {
+                    urls.split(',')
+                        .map(|url| url.trim().to_string())
+                        .filter(|url| !url.is_empty())
+                        .collect()
+                })
+                .unwrap_or_default(),
+            max_connections: number("DB_MAX_CONNECTIONS", 10) as u32,
+            min_connections: number("DB_MIN_CONNECTIONS", 5) as u32,
+            connect_timeout: Duration::from_secs(number("DB_CONNECT_TIMEOUT_SECS", 8)),
+            acquire_timeout: Duration::from_secs(number("DB_ACQUIRE_TIMEOUT_SECS", 8)),
+            idle_timeout: Duration::from_secs(number("DB_IDLE_TIMEOUT_SECS", 600)),
+            max_lifetime: Duration::from_secs(number("DB_MAX_LIFETIME_SECS", 1800)),
+            sqlx_logging: env::var("DB_SQLX_LOGGING")
+                .map(|v| matches!(v.trim(), "1" | "true"))
+                .unwrap_or(true),
+            connect_attempts: number("DB_CONNECT_ATTEMPTS", 10).max(1) as u32,
+            connect_backoff: Duration::from_millis(number("DB_CONNECT_BACKOFF_MS", 500)),
+            connect_backoff_max: Duration::from_secs(number("DB_CONNECT_BACKOFF_MAX_SECS", 30)),
+        }
+    }
+
+    /// Whether `DATABASE_URL` points at a SQLite file rather than a Postgres server
+    pub fn is_sqlite(&self) -> bool {
+        self.url.starts_with("sqlite:")
+    }
+
+    fn connect_options(&self, url: &str) -> ConnectOptions {
+        let mut opt = ConnectOptions::new(url);
+        opt.max_connections(self.max_connections)
+            .min_connections(self.min_connections.min(self.max_connections))
+            .connect_timeout(self.connect_timeout)
+            .acquire_timeout(self.acquire_timeout)
+            .idle_timeout(self.idle_timeout)
+            .max_lifetime(self.max_lifetime)
+            .sqlx_logging(self.sqlx_logging);
+        opt
+    }
+}
+
+/// Snapshot of the connection pool, e.g. for the readiness probe
+#[derive(Debug, Clone, Serialize)]
+pub struct PoolStats {
+    /// Open connections, idle or in use
+    pub size: u32,
+    pub idle: usize,
+    pub in_use: usize,
+    pub max_connections: u32,
+}
 
 #[derive(Clone)]
 pub struct Database {
+    /// The primary; every write and every read that must see the latest writes goes here
     pub connection: DatabaseConnection,
+    replicas: Arc<Vec<DatabaseConnection>>,
+    next_replica: Arc<AtomicUsize>,
+    max_connections: u32,
 }
 
 impl Database {
     pub async fn new() -> Result<Self> {
-        let database_url = env::var("DATABASE_URL")
-            .expect("DATABASE_URL environment variable must be set");
-        
-        let mut opt = ConnectOptions::new(database_url);
-        opt.max_connections(10)
-            .min_connections(5)
-            .sqlx_logging(true);
-        
-        let connection = SeaDatabase::connect(opt).await
-            .map_err(|e| crate::errors::AppError::Internal(format!("Database connection failed: {}", e)))?;
-        
-        Ok(Self { connection })
+        Self::connect(&DatabaseConfig::from_env()).await
+    }
+
+    /// Connect to the primary and every replica
+    pub async fn connect(config: &DatabaseConfig) -> Result<Self> {
+        if config.is_sqlite() && !config.replica_urls.is_empty() {
+            return Err(AppError::Internal("DATABASE_REPLICA_URLS is not supported with SQLite".to_string()));
+        }
+        let connection = Self::connect_with_retry(config, &config.url).await?;
+        let mut replicas = Vec::with_capacity(config.replica_urls.len());
+        for url in &config.replica_urls {
+            replicas.push(Self::connect_with_retry(config, url).await?);
+        }
+        if !replicas.is_empty() {
+            tracing::info!("Routing reads to {} database replicas", replicas.len());
+        }
+
+        Ok(Self {
+            connection,
+            replicas: Arc::new(replicas),
+            next_replica: Arc::new(AtomicUsize::new(0)),
+            max_connections: config.max_connections,
+        })
+    }
+
+    /// Retry with exponential backoff, so a server started alongside its database
+    /// (e.g. in the same compose file) waits for it instead of crashing
+    async fn connect_with_retry(config: &DatabaseConfig, url: &str) -> Result<DatabaseConnection> {
+        let mut backoff = config.connect_backoff;
+        let mut attempt = 1;
+        loop {
+            let connected = if config.is_sqlite() {
+                connect_sqlite(config, url).await
+            } else {
+                SeaDatabase::connect(config.connect_options(url)).await
+            };
+            match connected {
+                Ok(connection) => return Ok(connection),
+                Err(e) if attempt < config.connect_attempts => {
+                    tracing::warn!(
+                        "Database connection attempt {}/{} failed, retrying in {:?}: {}",
+                        attempt, config.connect_attempts, backoff, e
+                    );
+                    tokio::time::sleep(backoff).await;
+                    backoff = (backoff * 2).min(config.connect_backoff_max);
+                    attempt += 1;
+                }
+                Err(e) => {
+                    return Err(AppError::Internal(format!(
+                        "Database connection failed after {} attempts: {}",
+                        attempt, e
+                    )));
+                }
+            }
+        }
+    }
+
+    /// Connection for read-only list and get queries: the next replica in turn, or the
+    /// primary without replicas. Replicas may lag behind, so reads that must see a write
+    /// made earlier in the same request stay on `connection`.
+    pub fn reader(&self) -> &DatabaseConnection {
+        if self.replicas.is_empty() {
+            return &self.connection;
+        }
+        let index = self.next_replica.fetch_add(1, Ordering::Relaxed) % self.replicas.len();
+        &self.replicas[index]
+    }
+
+    /// Run `f` in a transaction on the primary: committed when it returns `Ok`, rolled back when it
+    /// returns an error. Handlers that read, check and then write wrap those steps in it, so a
+    /// failure halfway leaves nothing half-written and the rows they read can't change underneath.
+    /// Broadcasts belong after it returns, once the changes are committed.
+    ///
+    /// ```ignore
+    /// let updated = app_state.db.with_txn(|txn| Box::pin(async move {
+    ///     let note = find_note(txn, user_id, id).await?;
+    ///     apply(note).update(txn).await.map_err(|e| AppError::Database(e.into()))
+    /// })).await?;
+    /// ```
+    pub async fn with_txn<F, T>(&self, f: F) -> Result<T>
+    where
+        F: for<'c> FnOnce(&'c DatabaseTransaction) -> Pin<Box<dyn Future<Output = Result<T>> + Send + 'c>> + Send,
+        T: Send,
+    {
+        let txn = begin_write(&self.connection).await?;
+        match f(&txn).await {
+            Ok(value) => {
+                txn.commit().await
+                    .map_err(|e| AppError::Database(e.into()))?;
+                Ok(value)
+            }
+            Err(e) => {
+                if let Err(rollback_error) = txn.rollback().await {
+                    tracing::warn!("Failed to roll back transaction: {}", rollback_error);
+                }
+                Err(e)
+            }
+        }
     }
+
+    pub fn has_replicas(&self) -> bool {
+        !self.replicas.is_empty()
+    }
+
+    pub fn pool_stats(&self) -> PoolStats {
+        let (size, idle) = match self.connection.get_database_backend() {
+            DbBackend::Sqlite => {
+                let pool = self.connection.get_sqlite_connection_pool();
+                (pool.size(), pool.num_idle())
+            }
+            _ => {
+                let pool = self.connection.get_postgres_connection_pool();
+                (pool.size(), pool.num_idle())
+            }
+        };
+        PoolStats {
+            size,
+            idle,
+            in_use: (size as usize).saturating_sub(idle),
+            max_connections: self.max_connections,
+        }
+    }
+}
+
+/// SQLite has no schemas, so the tables Postgres keeps in `auth` live in a companion file
+/// next to the main one, e.g. `streamline-auth.db` for `streamline.db`
+pub fn sqlite_auth_path(main: &Path) -> PathBuf {
+    let stem = main.file_stem().and_then(|stem| stem.to_str()).unwrap_or("streamline");
+    let file_name = match main.extension().and_then(|ext| ext.to_str()) {
+        Some(ext) => format!("{}-auth.{}", stem, ext),
+        None => format!("{}-auth", stem),
+    };
+    main.with_file_name(file_name)
+}
+
+/// Open a SQLite pool whose connections all have the auth file attached as the `auth` schema
+async fn connect_sqlite(config: &DatabaseConfig, url: &str) -> std::result::Result<DatabaseConnection, DbErr> {
+    let options = SqliteConnectOptions::from_str(url)
+        .map_err(|e| DbErr::Conn(sea_orm::RuntimeErr::Internal(e.to_string())))?
+        .create_if_missing(true)
+        .foreign_keys(true)
+        .busy_timeout(config.acquire_timeout);
+    let options = if config.sqlx_logging { options } else { sqlx::ConnectOptions::disable_statement_logging(options) };
+    if let Some(dir) = options.get_filename().parent().filter(|dir| !dir.as_os_str().is_empty()) {
+        std::fs::create_dir_all(dir).map_err(|e| DbErr::Conn(sea_orm::RuntimeErr::Internal(e.to_string())))?;
+    }
+    let auth_path = sqlite_auth_path(options.get_filename()).to_string_lossy().into_owned();
+
+    let pool = config
+        .connect_options(url)
+        .sqlx_pool_options::<sqlx::Sqlite>()
+        .after_connect(move |conn, _| {
+            let attach = format!("ATTACH DATABASE '{}' AS auth", auth_path.replace('\'', "''"));
+            Box::pin(async move {
+                conn.execute(attach.as_str()).await?;
+                Ok(())
+            })
+        })
+        .connect_with(options)
+        .await
+        .map_err(|e| DbErr::Conn(sea_orm::RuntimeErr::Internal(e.to_string())))?;
+
+    Ok(SqlxSqliteConnector::from_sqlx_sqlite_pool(pool))
+}
+
+/// Begin a transaction that reads rows and then rewrites them. SQLite transactions start out
+/// reading, and two of them can't both upgrade to writing, so one would fail with "database is
+/// locked"; a no-op write as the first statement takes the write lock up front and makes
+/// concurrent writers wait for it instead.
+pub async fn begin_write(db: &DatabaseConnection) -> Result<DatabaseTransaction> {
+    let txn = db.begin().await
+        .map_err(|e| AppError::Database(e.into()))?;
+    if txn.get_database_backend() == DbBackend::Sqlite {
+        txn.execute_unprepared("UPDATE seaql_migrations SET version = version WHERE 0")
+            .await
+            .map_err(|e| AppError::Database(e.into()))?;
+    }
+    Ok(txn)
+}
+
+/// Serialize `display_order` rewrites among the same siblings: takes a transaction-scoped advisory
+/// lock per `(owner, parent)` group of `table`. Groups are locked in a fixed order, so transactions
+/// locking several can't deadlock. A SQLite file's transactions already lock the whole database.
+pub async fn lock_siblings<C: ConnectionTrait>(
+    db: &C,
+    table: &str,
+    groups: impl IntoIterator<Item = (Uuid, Option<Uuid>)>,
+) -> Result<()> {
+    if db.get_database_backend() != DbBackend::Postgres {
+        return Ok(());
+    }
+
+    let mut groups: Vec<(Uuid, Option<Uuid>)> = groups.into_iter().collect();
+    groups.sort();
+    groups.dedup();
+    for (owner_id, parent_id) in groups {
+        let key = format!("{}:{}:{}", table, owner_id, parent_id.map(|id| id.to_string()).unwrap_or_default());
+        db.execute(Statement::from_sql_and_values(
+            DbBackend::Postgres,
+            "SELECT pg_advisory_xact_lock(hashtextextended($1, 0))",
+            [key.into()],
+        ))
+        .await
+        .map_err(|e| AppError::Database(e.into()))?;
+    }
+    Ok(())
+}
+
+/// Point in time up to which `db` has seen every committed write: now on the primary or a
+/// caught-up replica, otherwise the commit time of the last transaction the replica replayed.
+/// Sync watermarks must not run ahead of it, or changes still in flight would be skipped.
+pub async fn consistent_as_of<C: ConnectionTrait>(db: &C) -> Result<DateTime<Utc>> {
+    db.query_one(Statement::from_string(
+        DbBackend::Postgres,
+        "SELECT CASE \
+             WHEN NOT pg_is_in_recovery() OR pg_last_wal_receive_lsn() = pg_last_wal_replay_lsn() THEN now() \
+             ELSE COALESCE(pg_last_xact_replay_timestamp(), now()) \
+         END AS as_of",
+    ))
+    .await
+    .map_err(|e| AppError::Database(e.into()))?
+    .and_then(|row| row.try_get::<DateTime<Utc>>("", "as_of").ok())
+    .ok_or_else(|| AppError::Internal("Replication position query returned no rows".to_string()))
 }
@@ -0,0 +1,62 @@
+use sea_orm::{entity::prelude::*, Set};
+use serde::{Deserialize, Serialize};
+
+#[derive(Clone, Debug, PartialEq, DeriveEntityModel, Eq, Serialize, Deserialize)]
+#[sea_orm(table_name = "announcements")]
+pub struct Model {
+    #[sea_orm(primary_key, auto_increment = false)]
+    pub id: Uuid,
+    pub kind: AnnouncementKind,
+    pub message: String,
+    /// Start of the window the announcement is about, e.g. planned downtime
+    pub starts_at: Option<DateTimeWithTimeZone>,
+    /// After this the announcement is no longer listed
+    pub ends_at: Option<DateTimeWithTimeZone>,
+    /// The super admin who sent it; cleared when their account is deleted
+    pub created_by: Option<Uuid>,
+    pub created_at: DateTimeWithTimeZone,
+}
+
+/// What an announcement is about, so clients can pick how to show it
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, EnumIter, DeriveActiveEnum, Serialize, Deserialize)]
+#[sea_orm(rs_type = "String", db_type = "Text")]
+#[serde(rename_all = "snake_case")]
+pub enum AnnouncementKind {
+    #[default]
+    #[sea_orm(string_value = "info")]
+    Info,
+    /// Planned downtime or degraded service
+    #[sea_orm(string_value = "maintenance")]
+    Maintenance,
+    /// A new version is out; clients may want to reload
+    #[sea_orm(string_value = "upgrade")]
+    Upgrade,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {
+    #[sea_orm(
+        belongs_to = "super::users::Entity",
+        from = "Column::CreatedBy",
+        to = "super::users::Column::Id",
+        on_update = "Cascade",
+        on_delete = "SetNull"
+    )]
+    User,
+}
+
+impl Related<super::users::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::User.def()
+    }
+}
+
+impl ActiveModelBehavior for ActiveModel {
+    fn new() -> Self {
+        Self {
+            id: Set(Uuid::now_v7()),
+            created_at: Set(chrono::Utc::now().into()),
+            ..ActiveModelTrait::default()
+        }
+    }
+}
@@ -0,0 +1,101 @@
+use sea_orm::{entity::prelude::*, Set};
+use serde::{Deserialize, Serialize};
+
+#[derive(Clone, Debug, PartialEq, DeriveEntityModel, Eq, Serialize, Deserialize)]
+#[sea_orm(table_name = "booking_pages")]
+pub struct Model {
+    #[sea_orm(primary_key, auto_increment = false)]
+    pub id: Uuid,
+    pub user_id: Uuid,
+    /// Calendar bookings land in; `None` leaves them without a calendar
+    pub calendar_id: Option<Uuid>,
+    pub encrypted_data: String,
+    pub iv: String,
+    pub salt: String,
+    /// Secret part of the booking link
+    pub public_token: String,
+    /// IANA time zone the availability windows are given in
+    pub time_zone: String,
+    /// Weekly availability windows, see `AvailabilityWindow`
+    #[sea_orm(column_type = "JsonBinary")]
+    pub availability: Json,
+    pub slot_minutes: i32,
+    /// Free time required before a slot
+    pub buffer_before_minutes: i32,
+    /// Free time required after a slot
+    pub buffer_after_minutes: i32,
+    /// How far ahead of its start a slot can still be booked
+    pub min_notice_minutes: i32,
+    /// How many days into the future slots are offered
+    pub max_days_ahead: i32,
+    pub is_active: bool,
+    /// Format version of the encrypted payload
+    #[serde(default = "streamline_models::default_schema_version")]
+    pub schema_version: i16,
+    pub created_at: DateTimeWithTimeZone,
+    pub updated_at: DateTimeWithTimeZone,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {
+    #[sea_orm(
+        belongs_to = "super::users::Entity",
+        from = "Column::UserId",
+        to = "super::users::Column::Id",
+        on_update = "Cascade",
+        on_delete = "Cascade"
+    )]
+    User,
+    #[sea_orm(
+        belongs_to = "super::calendars::Entity",
+        from = "Column::CalendarId",
+        to = "super::calendars::Column::Id",
+        on_update = "Cascade",
+        on_delete = "SetNull"
+    )]
+    Calendar,
+    #[sea_orm(has_many = "super::bookings::Entity")]
+    Bookings,
+}
+
+impl Related<super::users::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::User.def()
+    }
+}
+
+impl Related<super::bookings::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::Bookings.def()
+    }
+}
+
+#[async_trait::async_trait]
+impl ActiveModelBehavior for ActiveModel {
+    fn new() -> Self {
+        Self {
+            id: Set(Uuid::now_v7()),
+            schema_version: Set(streamline_models::DEFAULT_SCHEMA_VERSION),
+            time_zone: Set("UTC".to_string()),
+            availability: Set(serde_json::json!([])),
+            buffer_before_minutes: Set(0),
+            buffer_after_minutes: Set(0),
+            min_notice_minutes: Set(0),
+            max_days_ahead: Set(60),
+            is_active: Set(true),
+            created_at: Set(chrono::Utc::now().into()),
+            updated_at: Set(chrono::Utc::now().into()),
+            ..ActiveModelTrait::default()
+        }
+    }
+
+    async fn before_save<C>(mut self, _db: &C, insert: bool) -> Result<Self, DbErr>
+    where
+        C: ConnectionTrait,
+    {
+        if !insert {
+            self.updated_at = Set(chrono::Utc::now().into());
+        }
+        Ok(self)
+    }
+}
@@ -0,0 +1,68 @@
+use sea_orm::{entity::prelude::*, Set};
+use serde::{Deserialize, Serialize};
+
+#[derive(Clone, Debug, PartialEq, DeriveEntityModel, Eq, Serialize, Deserialize)]
+#[sea_orm(table_name = "bookings")]
+pub struct Model {
+    #[sea_orm(primary_key, auto_increment = false)]
+    pub id: Uuid,
+    pub booking_page_id: Uuid,
+    /// Owner of the booking page
+    pub user_id: Uuid,
+    /// Event created for the booking; cleared when the owner deletes it
+    pub event_id: Option<Uuid>,
+    pub start_time: DateTimeWithTimeZone,
+    pub end_time: DateTimeWithTimeZone,
+    pub created_at: DateTimeWithTimeZone,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {
+    #[sea_orm(
+        belongs_to = "super::booking_pages::Entity",
+        from = "Column::BookingPageId",
+        to = "super::booking_pages::Column::Id",
+        on_update = "Cascade",
+        on_delete = "Cascade"
+    )]
+    BookingPage,
+    #[sea_orm(
+        belongs_to = "super::users::Entity",
+        from = "Column::UserId",
+        to = "super::users::Column::Id",
+        on_update = "Cascade",
+        on_delete = "Cascade"
+    )]
+    User,
+    #[sea_orm(
+        belongs_to = "super::calendar_events::Entity",
+        from = "Column::EventId",
+        to = "super::calendar_events::Column::Id",
+        on_update = "Cascade",
+        on_delete = "SetNull"
+    )]
+    Event,
+}
+
+impl Related<super::booking_pages::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::BookingPage.def()
+    }
+}
+
+impl Related<super::users::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::User.def()
+    }
+}
+
+#[async_trait::async_trait]
+impl ActiveModelBehavior for ActiveModel {
+    fn new() -> Self {
+        Self {
+            id: Set(Uuid::now_v7()),
+            created_at: Set(chrono::Utc::now().into()),
+            ..ActiveModelTrait::default()
+        }
+    }
+}
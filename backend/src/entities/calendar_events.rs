@@ -7,13 +7,46 @@ pub struct Model {
     #[sea_orm(primary_key, auto_increment = false)]
     pub id: Uuid,
     pub user_id: Uuid,
+    pub calendar_id: Option<Uuid>,
     pub encrypted_data: String,
     pub iv: String,
     pub salt: String,
+    pub start_time: Option<DateTimeWithTimeZone>,
+    pub end_time: Option<DateTimeWithTimeZone>,
+    /// Spans whole days; `start_time` and `end_time` are then derived from the dates
+    #[serde(default)]
+    pub is_all_day: bool,
+    /// First and last day of an all-day event, in its owner's timezone
+    pub start_date: Option<Date>,
+    pub end_date: Option<Date>,
+    pub search_bloom: Option<String>,
+    /// Space-separated blind-index tokens, see `models::search`
+    pub search_tokens: Option<String>,
+    pub rrule: Option<String>,
+    #[sea_orm(column_type = "JsonBinary")]
+    pub recurrence_exceptions: Json,
+    pub recurring_event_id: Option<Uuid>,
+    pub original_start_time: Option<DateTimeWithTimeZone>,
+    pub status: EventStatus,
+    /// Set on events imported from a calendar subscription
+    pub subscription_id: Option<Uuid>,
+    /// UID of the VEVENT in the subscribed feed
+    pub external_uid: Option<String>,
+    /// Plaintext summary, description and location from the subscribed feed
+    #[sea_orm(column_type = "JsonBinary", nullable)]
+    pub external_data: Option<Json>,
+    /// Task this event blocks out time for
+    pub linked_task_id: Option<Uuid>,
+    /// Format version of the encrypted payload
+    #[serde(default = "streamline_models::default_schema_version")]
+    pub schema_version: i16,
     pub created_at: DateTimeWithTimeZone,
     pub updated_at: DateTimeWithTimeZone,
+    pub deleted_at: Option<DateTimeWithTimeZone>,
 }
 
+pub use streamline_models::calendar_event::EventStatus;
+
 #[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
 pub enum Relation {
     #[sea_orm(
@@ -24,6 +57,14 @@ pub enum Relation {
         on_delete = "Cascade"
     )]
     User,
+    #[sea_orm(
+        belongs_to = "super::calendars::Entity",
+        from = "Column::CalendarId",
+        to = "super::calendars::Column::Id",
+        on_update = "Cascade",
+        on_delete = "Cascade"
+    )]
+    Calendar,
 }
 
 impl Related<super::users::Entity> for Entity {
@@ -32,11 +73,20 @@ impl Related<super::users::Entity> for Entity {
     }
 }
 
+impl Related<super::calendars::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::Calendar.def()
+    }
+}
+
 #[async_trait::async_trait]
 impl ActiveModelBehavior for ActiveModel {
     fn new() -> Self {
         Self {
-            id: Set(Uuid::new_v4()),
+            id: Set(Uuid::now_v7()),
+            schema_version: Set(streamline_models::DEFAULT_SCHEMA_VERSION),
+            recurrence_exceptions: Set(serde_json::json!([])),
+            status: Set(EventStatus::Confirmed),
             created_at: Set(chrono::Utc::now().into()),
             updated_at: Set(chrono::Utc::now().into()),
             ..ActiveModelTrait::default()
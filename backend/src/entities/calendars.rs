@@ -11,8 +11,20 @@ pub struct Model {
     pub iv: String,
     pub salt: String,
     pub is_default: bool,
+    pub is_pinned: bool,
+    /// Filled by a calendar subscription; events can't be added or edited by hand
+    pub is_read_only: bool,
+    /// `#rrggbb`, readable by everyone the calendar is shared with
+    pub color: Option<String>,
+    pub display_order: i32,
+    /// Organization whose members share the calendar; it stays with its user either way
+    pub organization_id: Option<Uuid>,
+    /// Format version of the encrypted payload
+    #[serde(default = "streamline_models::default_schema_version")]
+    pub schema_version: i16,
     pub created_at: DateTimeWithTimeZone,
     pub updated_at: DateTimeWithTimeZone,
+    pub deleted_at: Option<DateTimeWithTimeZone>,
 }
 
 #[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
@@ -25,6 +37,16 @@ pub enum Relation {
         on_delete = "Cascade"
     )]
     User,
+    #[sea_orm(
+        belongs_to = "super::organizations::Entity",
+        from = "Column::OrganizationId",
+        to = "super::organizations::Column::Id",
+        on_update = "Cascade",
+        on_delete = "SetNull"
+    )]
+    Organization,
+    #[sea_orm(has_many = "super::calendar_events::Entity")]
+    CalendarEvents,
 }
 
 impl Related<super::users::Entity> for Entity {
@@ -33,12 +55,27 @@ impl Related<super::users::Entity> for Entity {
     }
 }
 
+impl Related<super::calendar_events::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::CalendarEvents.def()
+    }
+}
+
+impl Related<super::organizations::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::Organization.def()
+    }
+}
+
 #[async_trait::async_trait]
 impl ActiveModelBehavior for ActiveModel {
     fn new() -> Self {
         Self {
-            id: Set(Uuid::new_v4()),
+            id: Set(Uuid::now_v7()),
+            schema_version: Set(streamline_models::DEFAULT_SCHEMA_VERSION),
             is_default: Set(false),
+            is_pinned: Set(false),
+            is_read_only: Set(false),
             created_at: Set(chrono::Utc::now().into()),
             updated_at: Set(chrono::Utc::now().into()),
             ..ActiveModelTrait::default()
@@ -12,10 +12,29 @@ pub struct Model {
     pub iv: String,
     pub salt: String,
     pub display_order: i32,
+    pub search_bloom: Option<String>,
+    /// Space-separated blind-index tokens, see `models::search`
+    pub search_tokens: Option<String>,
+    pub due_at: Option<DateTimeWithTimeZone>,
+    pub scheduled_at: Option<DateTimeWithTimeZone>,
+    pub parent_task_id: Option<Uuid>,
+    pub completed_at: Option<DateTimeWithTimeZone>,
+    pub rrule: Option<String>,
+    pub priority: Option<TaskPriority>,
+    pub estimated_minutes: Option<i32>,
+    pub is_pinned: bool,
+    /// Email a task was created from, kept in plaintext until a client encrypts it into `encrypted_data`
+    pub pending_plaintext: Option<Json>,
+    /// Format version of the encrypted payload
+    #[serde(default = "streamline_models::default_schema_version")]
+    pub schema_version: i16,
     pub created_at: DateTimeWithTimeZone,
     pub updated_at: DateTimeWithTimeZone,
+    pub deleted_at: Option<DateTimeWithTimeZone>,
 }
 
+pub use streamline_models::can_do_list::TaskPriority;
+
 #[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
 pub enum Relation {
     #[sea_orm(
@@ -52,8 +71,10 @@ impl Related<super::projects::Entity> for Entity {
 impl ActiveModelBehavior for ActiveModel {
     fn new() -> Self {
         Self {
-            id: Set(Uuid::new_v4()),
+            id: Set(Uuid::now_v7()),
+            schema_version: Set(streamline_models::DEFAULT_SCHEMA_VERSION),
             display_order: Set(0),
+            is_pinned: Set(false),
             created_at: Set(chrono::Utc::now().into()),
             updated_at: Set(chrono::Utc::now().into()),
             ..ActiveModelTrait::default()
@@ -0,0 +1,82 @@
+use sea_orm::{entity::prelude::*, Set};
+use serde::{Deserialize, Serialize};
+
+#[derive(Clone, Debug, PartialEq, DeriveEntityModel, Eq, Serialize, Deserialize)]
+#[sea_orm(table_name = "conflicts")]
+pub struct Model {
+    #[sea_orm(primary_key, auto_increment = false)]
+    pub id: Uuid,
+    pub user_id: Uuid,
+    /// Table of the record, as named in sync operations
+    pub table_name: String,
+    pub record_id: Uuid,
+    /// What the client tried to do
+    pub action: ConflictAction,
+    /// `updated_at` of the version the client edited
+    pub base_updated_at: Option<DateTimeWithTimeZone>,
+    /// The operation's `data`, as the client sent it
+    #[sea_orm(column_type = "JsonBinary")]
+    pub client_data: Json,
+    /// The record as stored on the server when the operation was rejected
+    #[sea_orm(column_type = "JsonBinary")]
+    pub server_data: Json,
+    /// How the conflict was settled; unset while it is open
+    pub resolution: Option<ConflictResolution>,
+    pub resolved_at: Option<DateTimeWithTimeZone>,
+    pub created_at: DateTimeWithTimeZone,
+}
+
+/// Operations that can conflict; a create either finds its own record or fails
+#[derive(Copy, Clone, Debug, PartialEq, Eq, EnumIter, DeriveActiveEnum, Serialize, Deserialize)]
+#[sea_orm(rs_type = "String", db_type = "Text")]
+#[serde(rename_all = "snake_case")]
+pub enum ConflictAction {
+    #[sea_orm(string_value = "update")]
+    Update,
+    #[sea_orm(string_value = "delete")]
+    Delete,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, EnumIter, DeriveActiveEnum, Serialize, Deserialize)]
+#[sea_orm(rs_type = "String", db_type = "Text")]
+#[serde(rename_all = "snake_case")]
+pub enum ConflictResolution {
+    /// The client's operation was applied over the server version
+    #[sea_orm(string_value = "keep_mine")]
+    KeepMine,
+    /// The server version was kept and the operation dropped
+    #[sea_orm(string_value = "keep_theirs")]
+    KeepTheirs,
+    /// A payload combining both versions was saved
+    #[sea_orm(string_value = "merged")]
+    Merged,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {
+    #[sea_orm(
+        belongs_to = "super::users::Entity",
+        from = "Column::UserId",
+        to = "super::users::Column::Id",
+        on_update = "Cascade",
+        on_delete = "Cascade"
+    )]
+    User,
+}
+
+impl Related<super::users::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::User.def()
+    }
+}
+
+#[async_trait::async_trait]
+impl ActiveModelBehavior for ActiveModel {
+    fn new() -> Self {
+        Self {
+            id: Set(Uuid::now_v7()),
+            created_at: Set(chrono::Utc::now().into()),
+            ..ActiveModelTrait::default()
+        }
+    }
+}
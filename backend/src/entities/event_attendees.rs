@@ -0,0 +1,86 @@
+use sea_orm::{entity::prelude::*, Set};
+use serde::{Deserialize, Serialize};
+
+#[derive(Clone, Debug, PartialEq, DeriveEntityModel, Eq, Serialize, Deserialize)]
+#[sea_orm(table_name = "event_attendees")]
+pub struct Model {
+    #[sea_orm(primary_key, auto_increment = false)]
+    pub id: Uuid,
+    pub user_id: Uuid,
+    pub event_id: Uuid,
+    pub encrypted_data: String,
+    pub iv: String,
+    pub salt: String,
+    pub rsvp_status: RsvpStatus,
+    pub responded_at: Option<DateTimeWithTimeZone>,
+    /// Address an invitation was emailed to, lowercase; replies are matched against it
+    pub email: Option<String>,
+    /// Secret part of the address the attendee's replies go to, `rsvp-<token>@<INBOUND_EMAIL_DOMAIN>`;
+    /// left out of exports, so an imported copy doesn't answer for the original
+    #[serde(skip_serializing)]
+    pub invite_token: Option<String>,
+    pub invited_at: Option<DateTimeWithTimeZone>,
+    /// Format version of the encrypted payload
+    #[serde(default = "streamline_models::default_schema_version")]
+    pub schema_version: i16,
+    pub created_at: DateTimeWithTimeZone,
+    pub updated_at: DateTimeWithTimeZone,
+}
+
+pub use streamline_models::event_attendee::RsvpStatus;
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {
+    #[sea_orm(
+        belongs_to = "super::users::Entity",
+        from = "Column::UserId",
+        to = "super::users::Column::Id",
+        on_update = "Cascade",
+        on_delete = "Cascade"
+    )]
+    User,
+    #[sea_orm(
+        belongs_to = "super::calendar_events::Entity",
+        from = "Column::EventId",
+        to = "super::calendar_events::Column::Id",
+        on_update = "Cascade",
+        on_delete = "Cascade"
+    )]
+    CalendarEvent,
+}
+
+impl Related<super::users::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::User.def()
+    }
+}
+
+impl Related<super::calendar_events::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::CalendarEvent.def()
+    }
+}
+
+#[async_trait::async_trait]
+impl ActiveModelBehavior for ActiveModel {
+    fn new() -> Self {
+        Self {
+            id: Set(Uuid::now_v7()),
+            schema_version: Set(streamline_models::DEFAULT_SCHEMA_VERSION),
+            rsvp_status: Set(RsvpStatus::NeedsAction),
+            created_at: Set(chrono::Utc::now().into()),
+            updated_at: Set(chrono::Utc::now().into()),
+            ..ActiveModelTrait::default()
+        }
+    }
+
+    async fn before_save<C>(mut self, _db: &C, insert: bool) -> Result<Self, DbErr>
+    where
+        C: ConnectionTrait,
+    {
+        if !insert {
+            self.updated_at = Set(chrono::Utc::now().into());
+        }
+        Ok(self)
+    }
+}
@@ -0,0 +1,81 @@
+use sea_orm::{entity::prelude::*, Set};
+use serde::{Deserialize, Serialize};
+
+#[derive(Clone, Debug, PartialEq, DeriveEntityModel, Eq, Serialize, Deserialize)]
+#[sea_orm(table_name = "habits")]
+pub struct Model {
+    #[sea_orm(primary_key, auto_increment = false)]
+    pub id: Uuid,
+    pub user_id: Uuid,
+    pub encrypted_data: String,
+    pub iv: String,
+    pub salt: String,
+    /// Format version of the encrypted payload
+    #[serde(default = "streamline_models::default_schema_version")]
+    pub schema_version: i16,
+    /// When the habit is due, such as `FREQ=DAILY` or `FREQ=WEEKLY;BYDAY=MO,TH`
+    pub rrule: String,
+    /// First local date the habit is due on
+    pub starts_on: Date,
+    /// Periods in a row done, as of the last check-in change
+    pub current_streak: i32,
+    pub longest_streak: i32,
+    /// Local date from which `current_streak` is broken unless another check-in comes first
+    pub streak_expires_on: Option<Date>,
+    pub last_checkin_on: Option<Date>,
+    pub created_at: DateTimeWithTimeZone,
+    pub updated_at: DateTimeWithTimeZone,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {
+    #[sea_orm(
+        belongs_to = "super::users::Entity",
+        from = "Column::UserId",
+        to = "super::users::Column::Id",
+        on_update = "Cascade",
+        on_delete = "Cascade"
+    )]
+    User,
+    #[sea_orm(has_many = "super::habit_checkins::Entity")]
+    Checkins,
+}
+
+impl Related<super::users::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::User.def()
+    }
+}
+
+impl Related<super::habit_checkins::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::Checkins.def()
+    }
+}
+
+#[async_trait::async_trait]
+impl ActiveModelBehavior for ActiveModel {
+    fn new() -> Self {
+        Self {
+            id: Set(Uuid::now_v7()),
+            schema_version: Set(streamline_models::DEFAULT_SCHEMA_VERSION),
+            current_streak: Set(0),
+            longest_streak: Set(0),
+            streak_expires_on: Set(None),
+            last_checkin_on: Set(None),
+            created_at: Set(chrono::Utc::now().into()),
+            updated_at: Set(chrono::Utc::now().into()),
+            ..ActiveModelTrait::default()
+        }
+    }
+
+    async fn before_save<C>(mut self, _db: &C, insert: bool) -> Result<Self, DbErr>
+    where
+        C: ConnectionTrait,
+    {
+        if !insert {
+            self.updated_at = Set(chrono::Utc::now().into());
+        }
+        Ok(self)
+    }
+}
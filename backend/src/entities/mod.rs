@@ -5,3 +5,36 @@ pub mod projects;
 pub mod can_do_list;
 pub mod calendars;
 pub mod calendar_events;
+pub mod deleted_records;
+pub mod audit_log;
+pub mod reminders;
+pub mod sessions;
+pub mod time_entries;
+pub mod event_attendees;
+pub mod notes;
+pub mod project_members;
+pub mod scheduling_polls;
+pub mod poll_slots;
+pub mod poll_votes;
+pub mod booking_pages;
+pub mod bookings;
+pub mod calendar_subscriptions;
+pub mod webhooks;
+pub mod webhook_deliveries;
+pub mod inbound_email_addresses;
+pub mod notification_preferences;
+pub mod organizations;
+pub mod organization_members;
+pub mod record_revisions;
+pub mod scheduling_constraints;
+pub mod focus_sessions;
+pub mod habits;
+pub mod habit_checkins;
+pub mod goals;
+pub mod goal_links;
+pub mod templates;
+pub mod feature_flag_overrides;
+pub mod settings;
+pub mod announcements;
+pub mod kv_entries;
+pub mod conflicts;
@@ -0,0 +1,81 @@
+use sea_orm::{entity::prelude::*, Set};
+use serde::{Deserialize, Serialize};
+
+/// How a user wants to be emailed. Kept in plaintext beside the encrypted settings,
+/// since the server has to read it to send digests.
+#[derive(Clone, Debug, PartialEq, DeriveEntityModel, Eq, Serialize, Deserialize)]
+#[sea_orm(table_name = "notification_preferences")]
+pub struct Model {
+    #[sea_orm(primary_key, auto_increment = false)]
+    pub user_id: Uuid,
+    /// Superseded by `user_settings.timezone` and kept in step with it until replicas of the
+    /// release before it are gone
+    pub timezone: String,
+    pub digest_frequency: DigestFrequency,
+    /// Local hour the digest is sent at
+    pub digest_hour: i16,
+    /// ISO weekday of weekly digests, 1 (Monday) to 7 (Sunday)
+    pub digest_weekday: i16,
+    /// NULL while digests are off
+    pub next_digest_at: Option<DateTimeWithTimeZone>,
+    pub last_digest_sent_at: Option<DateTimeWithTimeZone>,
+    pub created_at: DateTimeWithTimeZone,
+    pub updated_at: DateTimeWithTimeZone,
+}
+
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Hash, EnumIter, DeriveActiveEnum, Serialize, Deserialize)]
+#[sea_orm(rs_type = "String", db_type = "Text")]
+#[serde(rename_all = "snake_case")]
+pub enum DigestFrequency {
+    #[default]
+    #[sea_orm(string_value = "off")]
+    Off,
+    #[sea_orm(string_value = "daily")]
+    Daily,
+    #[sea_orm(string_value = "weekly")]
+    Weekly,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {
+    #[sea_orm(
+        belongs_to = "super::users::Entity",
+        from = "Column::UserId",
+        to = "super::users::Column::Id",
+        on_update = "Cascade",
+        on_delete = "Cascade"
+    )]
+    User,
+}
+
+impl Related<super::users::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::User.def()
+    }
+}
+
+#[async_trait::async_trait]
+impl ActiveModelBehavior for ActiveModel {
+    fn new() -> Self {
+        Self {
+            timezone: Set("UTC".to_string()),
+            digest_frequency: Set(DigestFrequency::Off),
+            digest_hour: Set(7),
+            digest_weekday: Set(1),
+            next_digest_at: Set(None),
+            created_at: Set(chrono::Utc::now().into()),
+            updated_at: Set(chrono::Utc::now().into()),
+            ..ActiveModelTrait::default()
+        }
+    }
+
+    async fn before_save<C>(mut self, _db: &C, insert: bool) -> Result<Self, DbErr>
+    where
+        C: ConnectionTrait,
+    {
+        if !insert {
+            self.updated_at = Set(chrono::Utc::now().into());
+        }
+        Ok(self)
+    }
+}
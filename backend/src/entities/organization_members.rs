@@ -0,0 +1,106 @@
+use sea_orm::{entity::prelude::*, Set};
+use serde::{Deserialize, Serialize};
+
+use super::project_members::ProjectRole;
+
+#[derive(Clone, Debug, PartialEq, DeriveEntityModel, Eq, Serialize, Deserialize)]
+#[sea_orm(table_name = "organization_members")]
+pub struct Model {
+    #[sea_orm(primary_key, auto_increment = false)]
+    pub id: Uuid,
+    pub organization_id: Uuid,
+    pub user_id: Uuid,
+    pub role: OrganizationRole,
+    pub created_at: DateTimeWithTimeZone,
+    pub updated_at: DateTimeWithTimeZone,
+}
+
+/// What a member may do in an organization, in increasing order of rights
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash, EnumIter, DeriveActiveEnum, Serialize, Deserialize)]
+#[sea_orm(rs_type = "String", db_type = "Text")]
+#[serde(rename_all = "snake_case")]
+pub enum OrganizationRole {
+    /// Edits the organization's projects, tasks, calendars and events
+    #[default]
+    #[sea_orm(string_value = "member")]
+    Member,
+    /// Also renames the organization, manages members and the organization's projects and calendars
+    #[sea_orm(string_value = "admin")]
+    Admin,
+    /// Also manages admins and owners and deletes the organization
+    #[sea_orm(string_value = "owner")]
+    Owner,
+}
+
+impl OrganizationRole {
+    /// The rights the role gives in the organization's projects and calendars
+    pub fn project_role(self) -> ProjectRole {
+        match self {
+            OrganizationRole::Member => ProjectRole::Editor,
+            OrganizationRole::Admin | OrganizationRole::Owner => ProjectRole::Owner,
+        }
+    }
+
+    /// Roles that give at least `min_role` in the organization's projects and calendars
+    pub fn with_project_role(min_role: ProjectRole) -> Vec<OrganizationRole> {
+        [OrganizationRole::Member, OrganizationRole::Admin, OrganizationRole::Owner]
+            .into_iter()
+            .filter(|role| role.project_role() >= min_role)
+            .collect()
+    }
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {
+    #[sea_orm(
+        belongs_to = "super::organizations::Entity",
+        from = "Column::OrganizationId",
+        to = "super::organizations::Column::Id",
+        on_update = "Cascade",
+        on_delete = "Cascade"
+    )]
+    Organization,
+    #[sea_orm(
+        belongs_to = "super::users::Entity",
+        from = "Column::UserId",
+        to = "super::users::Column::Id",
+        on_update = "Cascade",
+        on_delete = "Cascade"
+    )]
+    User,
+}
+
+impl Related<super::organizations::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::Organization.def()
+    }
+}
+
+impl Related<super::users::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::User.def()
+    }
+}
+
+#[async_trait::async_trait]
+impl ActiveModelBehavior for ActiveModel {
+    fn new() -> Self {
+        Self {
+            id: Set(Uuid::now_v7()),
+            role: Set(OrganizationRole::Member),
+            created_at: Set(chrono::Utc::now().into()),
+            updated_at: Set(chrono::Utc::now().into()),
+            ..ActiveModelTrait::default()
+        }
+    }
+
+    async fn before_save<C>(mut self, _db: &C, insert: bool) -> Result<Self, DbErr>
+    where
+        C: ConnectionTrait,
+    {
+        if !insert {
+            self.updated_at = Set(chrono::Utc::now().into());
+        }
+        Ok(self)
+    }
+}
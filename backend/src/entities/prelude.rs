@@ -5,4 +5,37 @@ pub use super::{
     can_do_list::Entity as CanDoList,
     calendars::Entity as Calendars,
     calendar_events::Entity as CalendarEvents,
+    deleted_records::Entity as DeletedRecords,
+    audit_log::Entity as AuditLog,
+    reminders::Entity as Reminders,
+    sessions::Entity as Sessions,
+    time_entries::Entity as TimeEntries,
+    event_attendees::Entity as EventAttendees,
+    notes::Entity as Notes,
+    project_members::Entity as ProjectMembers,
+    scheduling_polls::Entity as SchedulingPolls,
+    poll_slots::Entity as PollSlots,
+    poll_votes::Entity as PollVotes,
+    booking_pages::Entity as BookingPages,
+    bookings::Entity as Bookings,
+    calendar_subscriptions::Entity as CalendarSubscriptions,
+    webhooks::Entity as Webhooks,
+    webhook_deliveries::Entity as WebhookDeliveries,
+    inbound_email_addresses::Entity as InboundEmailAddresses,
+    notification_preferences::Entity as NotificationPreferences,
+    organizations::Entity as Organizations,
+    organization_members::Entity as OrganizationMembers,
+    record_revisions::Entity as RecordRevisions,
+    scheduling_constraints::Entity as SchedulingConstraints,
+    focus_sessions::Entity as FocusSessions,
+    habits::Entity as Habits,
+    habit_checkins::Entity as HabitCheckins,
+    goals::Entity as Goals,
+    goal_links::Entity as GoalLinks,
+    templates::Entity as Templates,
+    feature_flag_overrides::Entity as FeatureFlagOverrides,
+    settings::Entity as Settings,
+    announcements::Entity as Announcements,
+    kv_entries::Entity as KvEntries,
+    conflicts::Entity as Conflicts,
 };
@@ -14,8 +14,17 @@ pub struct Model {
     pub parent_id: Option<Uuid>,
     pub display_order: i32,
     pub is_collapsed: bool,
+    pub is_pinned: bool,
+    /// Organization whose members share the project; it stays with its user either way
+    pub organization_id: Option<Uuid>,
+    /// Space-separated blind-index tokens, see `models::search`
+    pub search_tokens: Option<String>,
+    /// Format version of the encrypted payload
+    #[serde(default = "streamline_models::default_schema_version")]
+    pub schema_version: i16,
     pub created_at: DateTimeWithTimeZone,
     pub updated_at: DateTimeWithTimeZone,
+    pub deleted_at: Option<DateTimeWithTimeZone>,
 }
 
 #[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
@@ -28,6 +37,14 @@ pub enum Relation {
         on_delete = "Cascade"
     )]
     User,
+    #[sea_orm(
+        belongs_to = "super::organizations::Entity",
+        from = "Column::OrganizationId",
+        to = "super::organizations::Column::Id",
+        on_update = "Cascade",
+        on_delete = "SetNull"
+    )]
+    Organization,
     #[sea_orm(
         belongs_to = "Entity",
         from = "Column::ParentId",
@@ -52,14 +69,22 @@ impl Related<super::can_do_list::Entity> for Entity {
     }
 }
 
+impl Related<super::organizations::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::Organization.def()
+    }
+}
+
 #[async_trait::async_trait]
 impl ActiveModelBehavior for ActiveModel {
     fn new() -> Self {
         Self {
-            id: Set(Uuid::new_v4()),
+            id: Set(Uuid::now_v7()),
+            schema_version: Set(streamline_models::DEFAULT_SCHEMA_VERSION),
             is_default: Set(false),
             display_order: Set(0),
             is_collapsed: Set(false),
+            is_pinned: Set(false),
             created_at: Set(chrono::Utc::now().into()),
             updated_at: Set(chrono::Utc::now().into()),
             ..ActiveModelTrait::default()
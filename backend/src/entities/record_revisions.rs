@@ -0,0 +1,64 @@
+use sea_orm::{entity::prelude::*, Set};
+use serde::{Deserialize, Serialize};
+
+#[derive(Clone, Debug, PartialEq, DeriveEntityModel, Eq, Serialize, Deserialize)]
+#[sea_orm(table_name = "record_revisions")]
+pub struct Model {
+    #[sea_orm(primary_key, auto_increment = false)]
+    pub id: Uuid,
+    /// Owner of the record
+    pub user_id: Uuid,
+    /// Who made the change; differs from the owner in shared projects and calendars
+    pub actor_id: Option<Uuid>,
+    pub table_name: String,
+    pub record_id: Uuid,
+    pub action: RevisionAction,
+    /// The record as stored before the change
+    #[sea_orm(column_type = "JsonBinary")]
+    pub snapshot: Json,
+    pub created_at: DateTimeWithTimeZone,
+}
+
+/// The change that replaced a revision
+#[derive(Copy, Clone, Debug, PartialEq, Eq, EnumIter, DeriveActiveEnum, Serialize, Deserialize)]
+#[sea_orm(rs_type = "String", db_type = "Text")]
+#[serde(rename_all = "snake_case")]
+pub enum RevisionAction {
+    #[sea_orm(string_value = "update")]
+    Update,
+    /// Moved to the trash
+    #[sea_orm(string_value = "delete")]
+    Delete,
+    /// Reverted to an earlier revision
+    #[sea_orm(string_value = "revert")]
+    Revert,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {
+    #[sea_orm(
+        belongs_to = "super::users::Entity",
+        from = "Column::UserId",
+        to = "super::users::Column::Id",
+        on_update = "Cascade",
+        on_delete = "Cascade"
+    )]
+    User,
+}
+
+impl Related<super::users::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::User.def()
+    }
+}
+
+#[async_trait::async_trait]
+impl ActiveModelBehavior for ActiveModel {
+    fn new() -> Self {
+        Self {
+            id: Set(Uuid::now_v7()),
+            created_at: Set(chrono::Utc::now().into()),
+            ..ActiveModelTrait::default()
+        }
+    }
+}
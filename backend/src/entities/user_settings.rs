@@ -11,6 +11,15 @@ pub struct Model {
     pub encrypted_data: String,
     pub iv: String,
     pub salt: String,
+    /// Format version of the encrypted payload
+    #[serde(default = "streamline_models::default_schema_version")]
+    pub schema_version: i16,
+    /// IANA name, e.g. `Europe/Berlin`; recurring events, reminders and emails follow it
+    #[serde(default = "crate::models::user_settings::default_timezone")]
+    pub timezone: String,
+    /// ISO weekday weeks start on, 1 (Monday) to 7 (Sunday)
+    #[serde(default = "crate::models::user_settings::default_week_start")]
+    pub week_start: i16,
     
     pub created_at: DateTimeWithTimeZone,
     pub updated_at: DateTimeWithTimeZone,
@@ -3,66 +3,244 @@ use axum::{
     response::{IntoResponse, Response},
     Json,
 };
-use serde_json::json;
+use serde::Serialize;
 use thiserror::Error;
 
+use crate::models::ErrorResponse;
+
+/// Stable, machine-readable error codes; clients branch on these rather than on messages,
+/// which may be reworded at any time
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum ErrorCode {
+    AuthFailed,
+    AuthInvalidCredentials,
+    AuthTokenInvalid,
+    AuthTokenExpired,
+    AuthSessionExpired,
+    AuthSessionRevoked,
+    /// Registering with an email that already has an account
+    AuthEmailTaken,
+    ValidationFailed,
+    /// The request body isn't valid JSON or doesn't match the expected shape
+    ValidationInvalidFormat,
+    /// A project or task would become its own ancestor
+    ValidationParentCycle,
+    /// The request refers to a record that doesn't exist, caught by a foreign key
+    ValidationReferenceNotFound,
+    /// The record's `schema_version` is known to hold corrupt payloads; the client needs updating
+    ValidationSchemaVersionRejected,
+    NotFound,
+    Conflict,
+    /// A record with the same unique values already exists
+    ConflictDuplicate,
+    Forbidden,
+    /// The endpoint belongs to a feature that isn't rolled out to the user yet
+    FeatureDisabled,
+    QuotaExceeded,
+    RateLimited,
+    /// The route exists but doesn't support the request's method; `Allow` lists the ones it does
+    MethodNotAllowed,
+    /// The record changed since the client read it, per `If-Match` or `If-Unmodified-Since`
+    PreconditionFailed,
+    /// The version in the path or `API-Version` header is outside the supported range
+    UnsupportedApiVersion,
+    DatabaseError,
+    InternalError,
+}
+
+impl ErrorCode {
+    pub fn status(self) -> StatusCode {
+        match self {
+            ErrorCode::AuthFailed
+            | ErrorCode::AuthInvalidCredentials
+            | ErrorCode::AuthTokenInvalid
+            | ErrorCode::AuthTokenExpired
+            | ErrorCode::AuthSessionExpired
+            | ErrorCode::AuthSessionRevoked => StatusCode::UNAUTHORIZED,
+            ErrorCode::ValidationFailed
+            | ErrorCode::ValidationInvalidFormat
+            | ErrorCode::ValidationParentCycle
+            | ErrorCode::ValidationSchemaVersionRejected
+            | ErrorCode::UnsupportedApiVersion => StatusCode::BAD_REQUEST,
+            ErrorCode::ValidationReferenceNotFound => StatusCode::UNPROCESSABLE_ENTITY,
+            ErrorCode::NotFound => StatusCode::NOT_FOUND,
+            ErrorCode::Conflict | ErrorCode::ConflictDuplicate | ErrorCode::AuthEmailTaken => StatusCode::CONFLICT,
+            ErrorCode::Forbidden | ErrorCode::FeatureDisabled | ErrorCode::QuotaExceeded => StatusCode::FORBIDDEN,
+            ErrorCode::RateLimited => StatusCode::TOO_MANY_REQUESTS,
+            ErrorCode::MethodNotAllowed => StatusCode::METHOD_NOT_ALLOWED,
+            ErrorCode::PreconditionFailed => StatusCode::PRECONDITION_FAILED,
+            ErrorCode::DatabaseError | ErrorCode::InternalError => StatusCode::INTERNAL_SERVER_ERROR,
+        }
+    }
+
+    /// Short summary for the `error` field, shared by every code of a kind
+    fn title(self) -> &'static str {
+        match self {
+            ErrorCode::AuthTokenInvalid | ErrorCode::AuthTokenExpired => "Invalid token",
+            ErrorCode::AuthFailed
+            | ErrorCode::AuthInvalidCredentials
+            | ErrorCode::AuthSessionExpired
+            | ErrorCode::AuthSessionRevoked => "Authentication failed",
+            ErrorCode::ValidationInvalidFormat => "Invalid data format",
+            ErrorCode::ValidationFailed
+            | ErrorCode::ValidationParentCycle
+            | ErrorCode::ValidationReferenceNotFound
+            | ErrorCode::ValidationSchemaVersionRejected => "Validation failed",
+            ErrorCode::NotFound => "Resource not found",
+            ErrorCode::Conflict | ErrorCode::ConflictDuplicate | ErrorCode::AuthEmailTaken => "Resource conflict",
+            ErrorCode::Forbidden | ErrorCode::FeatureDisabled => "Forbidden",
+            ErrorCode::QuotaExceeded => "Quota exceeded",
+            ErrorCode::RateLimited => "Too many requests",
+            ErrorCode::MethodNotAllowed => "Method not allowed",
+            ErrorCode::PreconditionFailed => "Precondition failed",
+            ErrorCode::UnsupportedApiVersion => "Unsupported API version",
+            ErrorCode::DatabaseError => "Database error occurred",
+            ErrorCode::InternalError => "Internal server error",
+        }
+    }
+}
+
+/// One invalid field of a request body
+#[derive(Debug, Clone, Serialize)]
+pub struct FieldError {
+    pub field: String,
+    pub message: String,
+}
+
 #[derive(Error, Debug)]
 pub enum AppError {
     #[error("Database error: {0}")]
     Database(Box<dyn std::error::Error + Send + Sync>),
-    
+
     #[error("Authentication error: {0}")]
     Auth(String),
-    
+
     #[error("Validation error: {0}")]
     Validation(String),
-    
+
+    /// Validation failures tied to request fields, reported as one entry per field
+    #[error("Validation error: {}", .0.iter().map(|e| e.message.as_str()).collect::<Vec<_>>().join("; "))]
+    InvalidFields(Vec<FieldError>),
+
     #[error("Not found: {0}")]
     NotFound(String),
-    
-    
+
+    #[error("Conflict: {0}")]
+    Conflict(String),
+
+    #[error("Forbidden: {0}")]
+    Forbidden(String),
+
+    #[error("Quota exceeded: {0}")]
+    QuotaExceeded(String),
+
+    #[error("Rate limited: {0}")]
+    RateLimited(String),
+
+    /// A failure with a more specific code than its kind's default; the code decides the status
+    #[error("{1}")]
+    Coded(ErrorCode, String),
+
     #[error("JWT error: {0}")]
     Jwt(#[from] jsonwebtoken::errors::Error),
-    
+
     #[error("Serialization error: {0}")]
     Serialization(#[from] serde_json::Error),
-    
+
     #[error("SeaORM error: {0}")]
     SeaOrm(#[from] sea_orm::DbErr),
-    
+
     #[error("Internal server error: {0}")]
     Internal(String),
 }
 
 impl IntoResponse for AppError {
     fn into_response(self) -> Response {
-        let (status, error_message) = match self {
-            AppError::Database(ref err) => {
-                tracing::error!("Database error: {:?}", err);
-                (StatusCode::INTERNAL_SERVER_ERROR, "Database error occurred")
-            }
-            AppError::Auth(_) => (StatusCode::UNAUTHORIZED, "Authentication failed"),
-            AppError::Validation(_) => (StatusCode::BAD_REQUEST, "Validation failed"),
-            AppError::NotFound(_) => (StatusCode::NOT_FOUND, "Resource not found"),
-            AppError::Jwt(_) => (StatusCode::UNAUTHORIZED, "Invalid token"),
-            AppError::Serialization(_) => (StatusCode::BAD_REQUEST, "Invalid data format"),
-            AppError::SeaOrm(ref err) => {
-                tracing::error!("SeaORM error: {:?}", err);
-                (StatusCode::INTERNAL_SERVER_ERROR, "Database operation failed")
-            }
-            AppError::Internal(ref err) => {
-                tracing::error!("Internal error: {:?}", err);
-                (StatusCode::INTERNAL_SERVER_ERROR, "Internal server error")
+        let code = self.code();
+        let status = self.status();
+        let details = match code {
+            ErrorCode::ConflictDuplicate => "A record with the same values already exists".to_string(),
+            ErrorCode::ValidationReferenceNotFound => "A referenced record does not exist".to_string(),
+            ErrorCode::DatabaseError | ErrorCode::InternalError => {
+                tracing::error!("{:?}", self);
+                self.to_string()
             }
+            _ => self.to_string(),
         };
 
-        let body = Json(json!({
-            "error": error_message,
-            "details": self.to_string()
-        }));
+        let body = Json(ErrorResponse {
+            error: code.title().to_string(),
+            code,
+            details: Some(details),
+            fields: match self {
+                AppError::InvalidFields(fields) => Some(fields),
+                _ => None,
+            },
+        });
 
         (status, body).into_response()
     }
 }
 
+impl AppError {
+    /// A validation failure of a single request field
+    pub fn invalid_field(field: impl Into<String>, message: impl Into<String>) -> Self {
+        AppError::InvalidFields(vec![FieldError { field: field.into(), message: message.into() }])
+    }
+
+    /// The code's status, except that failures tied to request fields are `422`: the body was
+    /// understood but its values can't be used
+    pub fn status(&self) -> StatusCode {
+        match self {
+            AppError::InvalidFields(_) => StatusCode::UNPROCESSABLE_ENTITY,
+            _ => self.code().status(),
+        }
+    }
+
+    pub fn code(&self) -> ErrorCode {
+        match self {
+            AppError::Database(err) => match err.downcast_ref::<sea_orm::DbErr>() {
+                Some(err) => db_error_code(err),
+                None => ErrorCode::DatabaseError,
+            },
+            AppError::SeaOrm(err) => db_error_code(err),
+            AppError::Auth(_) => ErrorCode::AuthFailed,
+            AppError::Validation(_) | AppError::InvalidFields(_) => ErrorCode::ValidationFailed,
+            AppError::NotFound(_) => ErrorCode::NotFound,
+            AppError::Conflict(_) => ErrorCode::Conflict,
+            AppError::Forbidden(_) => ErrorCode::Forbidden,
+            AppError::QuotaExceeded(_) => ErrorCode::QuotaExceeded,
+            AppError::RateLimited(_) => ErrorCode::RateLimited,
+            AppError::Coded(code, _) => *code,
+            AppError::Jwt(err) => match err.kind() {
+                jsonwebtoken::errors::ErrorKind::ExpiredSignature => ErrorCode::AuthTokenExpired,
+                _ => ErrorCode::AuthTokenInvalid,
+            },
+            AppError::Serialization(_) => ErrorCode::ValidationInvalidFormat,
+            AppError::Internal(_) => ErrorCode::InternalError,
+        }
+    }
+
+    /// Map a failed insert; a duplicate key is only the caller's fault when the id came from the client
+    pub fn from_insert(err: sea_orm::DbErr, client_id: Option<uuid::Uuid>) -> Self {
+        match (err.sql_err(), client_id) {
+            (Some(sea_orm::SqlErr::UniqueConstraintViolation(_)), Some(id)) => {
+                AppError::Conflict(format!("A record with id {} already exists", id))
+            }
+            _ => AppError::Database(err.into()),
+        }
+    }
+}
+
+/// Constraint violations are the request's fault, e.g. two requests creating the same record at
+/// once or linking to one deleted in the meantime; anything else is the server's
+fn db_error_code(err: &sea_orm::DbErr) -> ErrorCode {
+    match err.sql_err() {
+        Some(sea_orm::SqlErr::UniqueConstraintViolation(_)) => ErrorCode::ConflictDuplicate,
+        Some(sea_orm::SqlErr::ForeignKeyConstraintViolation(_)) => ErrorCode::ValidationReferenceNotFound,
+        _ => ErrorCode::DatabaseError,
+    }
+}
+
 pub type Result<T> = std::result::Result<T, AppError>;
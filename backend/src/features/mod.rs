@@ -0,0 +1,185 @@
+//! Feature flags for rolling experimental subsystems out gradually. Each feature has an
+//! instance-wide rollout from `FEATURE_<NAME>` (`on`, `off` or a percentage of users, picked
+//! stably per user), and super admins can switch it on or off for single users.
+
+use std::{collections::BTreeMap, env};
+
+use sea_orm::*;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use uuid::Uuid;
+
+use crate::{
+    entities::{feature_flag_overrides, prelude::*},
+    errors::{AppError, ErrorCode, Result},
+};
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Feature {
+    /// Planning and applying schedules for open tasks
+    AutoScheduling,
+    /// Parsing natural language quick-add lines
+    QuickAdd,
+    /// Saving and instantiating project and event templates
+    Templates,
+}
+
+impl Feature {
+    pub const ALL: [Feature; 3] = [Feature::AutoScheduling, Feature::QuickAdd, Feature::Templates];
+
+    /// Name in the API and in `feature_flag_overrides`
+    pub fn key(self) -> &'static str {
+        match self {
+            Feature::AutoScheduling => "auto_scheduling",
+            Feature::QuickAdd => "quick_add",
+            Feature::Templates => "templates",
+        }
+    }
+
+    fn env_key(self) -> &'static str {
+        match self {
+            Feature::AutoScheduling => "FEATURE_AUTO_SCHEDULING",
+            Feature::QuickAdd => "FEATURE_QUICK_ADD",
+            Feature::Templates => "FEATURE_TEMPLATES",
+        }
+    }
+
+    fn from_key(key: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|feature| feature.key() == key)
+    }
+}
+
+/// Who a feature is on for when the user has no override
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Rollout {
+    Off,
+    On,
+    /// This share of users, 0 to 100
+    Percent(u8),
+}
+
+impl Rollout {
+    /// `on`/`true`, `off`/`false`, or a percentage such as `25` or `25%`
+    fn parse(value: &str) -> Option<Self> {
+        match value.trim().to_lowercase().as_str() {
+            "on" | "true" => Some(Rollout::On),
+            "off" | "false" => Some(Rollout::Off),
+            value => value
+                .trim_end_matches('%')
+                .parse::<u8>()
+                .ok()
+                .filter(|percent| *percent <= 100)
+                .map(Rollout::Percent),
+        }
+    }
+
+    fn includes(self, feature: Feature, user_id: Uuid) -> bool {
+        match self {
+            Rollout::Off => false,
+            Rollout::On => true,
+            // Hashing the feature in as well spreads early adopters across features
+            Rollout::Percent(percent) => {
+                let digest = Sha256::new()
+                    .chain_update(feature.key())
+                    .chain_update(user_id.as_bytes())
+                    .finalize();
+                let bucket = u16::from_be_bytes([digest[0], digest[1]]) % 100;
+                bucket < u16::from(percent)
+            }
+        }
+    }
+}
+
+/// Written as in `FEATURE_<NAME>`: `on`, `off` or a percentage such as `25%`
+impl Serialize for Rollout {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error> {
+        match self {
+            Rollout::Off => serializer.serialize_str("off"),
+            Rollout::On => serializer.serialize_str("on"),
+            Rollout::Percent(percent) => serializer.collect_str(&format_args!("{}%", percent)),
+        }
+    }
+}
+
+/// Instance-wide rollouts; every feature is on unless configured otherwise
+#[derive(Debug, Clone)]
+pub struct FeatureFlags {
+    rollouts: BTreeMap<Feature, Rollout>,
+}
+
+impl Default for FeatureFlags {
+    fn default() -> Self {
+        Self { rollouts: Feature::ALL.into_iter().map(|feature| (feature, Rollout::On)).collect() }
+    }
+}
+
+impl FeatureFlags {
+    pub fn from_env() -> Self {
+        let mut flags = Self::default();
+        for feature in Feature::ALL {
+            let Ok(value) = env::var(feature.env_key()) else {
+                continue;
+            };
+            match Rollout::parse(&value) {
+                Some(rollout) => {
+                    flags.rollouts.insert(feature, rollout);
+                }
+                None => tracing::warn!("Ignoring {}={:?}: expected on, off or a percentage", feature.env_key(), value),
+            }
+        }
+        flags
+    }
+
+    pub fn rollout(&self, feature: Feature) -> Rollout {
+        self.rollouts.get(&feature).copied().unwrap_or(Rollout::On)
+    }
+
+    /// Every feature's instance-wide rollout, before per-user overrides
+    pub fn rollouts(&self) -> BTreeMap<Feature, Rollout> {
+        Feature::ALL.into_iter().map(|feature| (feature, self.rollout(feature))).collect()
+    }
+
+    /// Whether each feature is on for the user, overrides taken into account
+    pub async fn for_user<C: ConnectionTrait>(&self, db: &C, user_id: Uuid) -> Result<BTreeMap<Feature, bool>> {
+        let overrides = FeatureFlagOverrides::find()
+            .filter(feature_flag_overrides::Column::UserId.eq(user_id))
+            .all(db)
+            .await
+            .map_err(|e| AppError::Database(e.into()))?;
+        let mut enabled: BTreeMap<Feature, bool> = Feature::ALL
+            .into_iter()
+            .map(|feature| (feature, self.rollout(feature).includes(feature, user_id)))
+            .collect();
+        for row in overrides {
+            if let Some(feature) = Feature::from_key(&row.feature) {
+                enabled.insert(feature, row.enabled);
+            }
+        }
+        Ok(enabled)
+    }
+
+    pub async fn is_enabled<C: ConnectionTrait>(&self, db: &C, user_id: Uuid, feature: Feature) -> Result<bool> {
+        let row = FeatureFlagOverrides::find()
+            .filter(feature_flag_overrides::Column::UserId.eq(user_id))
+            .filter(feature_flag_overrides::Column::Feature.eq(feature.key()))
+            .one(db)
+            .await
+            .map_err(|e| AppError::Database(e.into()))?;
+        Ok(match row {
+            Some(row) => row.enabled,
+            None => self.rollout(feature).includes(feature, user_id),
+        })
+    }
+
+    /// Fail with `FeatureDisabled` unless the feature is on for the user
+    pub async fn require<C: ConnectionTrait>(&self, db: &C, user_id: Uuid, feature: Feature) -> Result<()> {
+        if !self.is_enabled(db, user_id, feature).await? {
+            return Err(AppError::Coded(
+                ErrorCode::FeatureDisabled,
+                format!("The {} feature is not enabled for this account", feature.key()),
+            ));
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,56 @@
+use axum::{
+    extract::{Query, State},
+    response::Json,
+};
+use sea_orm::*;
+use uuid::Uuid;
+
+use crate::{
+    entities::{prelude::*, audit_log},
+    errors::Result,
+    middleware::auth::AuthUser,
+    models::{audit::ActivityResponse, ApiResponse, PageQuery},
+    state::AppState,
+};
+
+/// Number of entries returned by the activity feed
+const ACTIVITY_FEED_LIMIT: u64 = 100;
+
+/// Append an entry to a user's audit log
+pub async fn record_audit<C: ConnectionTrait>(
+    db: &C,
+    user_id: Uuid,
+    actor_id: Option<Uuid>,
+    action: &str,
+    details: serde_json::Value,
+) -> Result<()> {
+    let mut entry = audit_log::ActiveModel::new();
+    entry.user_id = Set(user_id);
+    entry.actor_id = Set(actor_id);
+    entry.action = Set(action.to_string());
+    entry.details = Set(details);
+
+    entry.insert(db).await
+        .map_err(|e| crate::errors::AppError::Database(e.into()))?;
+
+    Ok(())
+}
+
+/// Most recent audit log entries concerning the authenticated user's account
+pub async fn list_activity(
+    State(app_state): State<AppState>,
+    auth_user: AuthUser,
+    Query(paging): Query<PageQuery>,
+) -> Result<Json<ApiResponse<Vec<ActivityResponse>>>> {
+    let entries = AuditLog::find()
+        .filter(audit_log::Column::UserId.eq(auth_user.0.id))
+        .order_by_desc(audit_log::Column::CreatedAt)
+        .limit(ACTIVITY_FEED_LIMIT)
+        .all(&app_state.db.connection)
+        .await
+        .map_err(|e| crate::errors::AppError::Database(e.into()))?;
+
+    let response: Vec<ActivityResponse> = entries.into_iter().map(|entry| entry.into()).collect();
+    let (response, meta) = paging.page(response)?;
+    Ok(Json(ApiResponse::list(response, meta)))
+}
@@ -0,0 +1,294 @@
+use std::collections::BTreeMap;
+
+use axum::{
+    body::{Body, Bytes},
+    extract::{Path, State},
+    http::header,
+    response::{IntoResponse, Json, Response},
+};
+use chrono::{Duration, Utc};
+use sea_orm::{sea_query::{Expr, OnConflict}, *};
+use tokio_util::io::ReaderStream;
+use uuid::Uuid;
+
+use crate::{
+    backup::Snapshot,
+    entities::{announcements, feature_flag_overrides, prelude::*, sessions},
+    errors::Result,
+    features::Feature,
+    handlers::activity::record_audit,
+    middleware::{auth::AuthUser, validation::ValidatedJson},
+    models::{
+        announcement::{AnnouncementResponse, CreateAnnouncementRequest},
+        audit::{ImpersonateRequest, ImpersonationResponse, DEFAULT_IMPERSONATION_MINUTES},
+        backup::RestoreSummary,
+        feature::SetFeatureOverrideRequest,
+        user::AuthResponse,
+        ApiResponse,
+    },
+    state::AppState,
+    websocket::OutgoingFrame,
+};
+
+/// Buffer between the archive writer and the response body
+const BACKUP_BUFFER_BYTES: usize = 64 * 1024;
+
+/// Mint a short-lived, read-only token for viewing another user's account metadata.
+/// Only super admins may call this; every token issued is recorded in the target's audit log.
+pub async fn impersonate(
+    State(app_state): State<AppState>,
+    auth_user: AuthUser,
+    ValidatedJson(request): ValidatedJson<ImpersonateRequest>,
+) -> Result<Json<ApiResponse<ImpersonationResponse>>> {
+    if !auth_user.0.is_super_admin {
+        return Err(crate::errors::AppError::Forbidden("Only super admins can impersonate users".to_string()));
+    }
+
+    let reason = request.reason.trim();
+    let ttl_minutes = request.ttl_minutes.unwrap_or(DEFAULT_IMPERSONATION_MINUTES);
+
+    let target = Users::find_by_id(request.user_id)
+        .one(&app_state.db.connection)
+        .await
+        .map_err(|e| crate::errors::AppError::Database(e.into()))?
+        .ok_or_else(|| crate::errors::AppError::NotFound("User not found".to_string()))?;
+
+    let ttl = Duration::minutes(ttl_minutes);
+    let (access_token, session) = app_state.auth_service.start_impersonation(&auth_user.0, &target, ttl).await?;
+
+    record_audit(
+        &app_state.db.connection,
+        target.id,
+        Some(auth_user.0.id),
+        "impersonation_started",
+        serde_json::json!({
+            "actor_email": auth_user.0.email,
+            "reason": reason,
+            "session_id": session.id,
+            "expires_at": session.expires_at,
+        }),
+    )
+    .await?;
+
+    tracing::warn!("Super admin {} started impersonating user {} for {} minutes: {}", auth_user.0.id, target.id, ttl_minutes, reason);
+
+    Ok(Json(ApiResponse::with_message(
+        ImpersonationResponse {
+            session_id: session.id,
+            auth: AuthResponse {
+                access_token,
+                token_type: "Bearer".to_string(),
+                expires_in: ttl.num_seconds(),
+                user: target.into(),
+            },
+        },
+        "Impersonation token issued",
+    )))
+}
+
+/// End an impersonation session the caller started; its token is rejected from then on
+pub async fn end_impersonation(
+    State(app_state): State<AppState>,
+    auth_user: AuthUser,
+    Path(id): Path<Uuid>,
+) -> Result<Json<ApiResponse<()>>> {
+    if !auth_user.0.is_super_admin {
+        return Err(crate::errors::AppError::Forbidden("Only super admins can end impersonation sessions".to_string()));
+    }
+
+    let session = Sessions::find_by_id(id)
+        .filter(sessions::Column::ImpersonatorId.eq(auth_user.0.id))
+        .filter(sessions::Column::RevokedAt.is_null())
+        .one(&app_state.db.connection)
+        .await
+        .map_err(|e| crate::errors::AppError::Database(e.into()))?
+        .ok_or_else(|| crate::errors::AppError::NotFound("Impersonation session not found".to_string()))?;
+
+    Sessions::update_many()
+        .col_expr(sessions::Column::RevokedAt, Expr::value(Utc::now()))
+        .filter(sessions::Column::Id.eq(session.id))
+        .exec(&app_state.db.connection)
+        .await
+        .map_err(|e| crate::errors::AppError::Database(e.into()))?;
+
+    record_audit(
+        &app_state.db.connection,
+        session.user_id,
+        Some(auth_user.0.id),
+        "impersonation_ended",
+        serde_json::json!({
+            "actor_email": auth_user.0.email,
+            "session_id": session.id,
+        }),
+    )
+    .await?;
+
+    tracing::warn!("Super admin {} stopped impersonating user {}", auth_user.0.id, session.user_id);
+
+    Ok(Json(ApiResponse::with_message((), "Impersonation session ended")))
+}
+
+/// Download a backup of the whole database, taken from a single snapshot; see `crate::backup`
+/// for the archive layout. Only super admins may call this.
+pub async fn backup(
+    State(app_state): State<AppState>,
+    auth_user: AuthUser,
+) -> Result<Response> {
+    if !auth_user.0.is_super_admin {
+        return Err(crate::errors::AppError::Forbidden("Only super admins can back up the database".to_string()));
+    }
+
+    let snapshot = Snapshot::begin(&app_state.db.connection).await?;
+    tracing::warn!("Super admin {} started a database backup", auth_user.0.id);
+
+    // As with account exports, a failure once the download has started can only cut it short
+    let (writer, reader) = tokio::io::duplex(BACKUP_BUFFER_BYTES);
+    tokio::spawn(async move {
+        if let Err(e) = snapshot.write(writer).await {
+            tracing::error!("Writing the database backup failed: {}", e);
+        }
+    });
+
+    let filename = format!("streamline-backup-{}.zip", Utc::now().format("%Y-%m-%dT%H%M%SZ"));
+    Ok((
+        [
+            (header::CONTENT_TYPE, "application/zip".to_string()),
+            (header::CONTENT_DISPOSITION, format!("attachment; filename=\"{}\"", filename)),
+        ],
+        Body::from_stream(ReaderStream::new(reader)),
+    )
+        .into_response())
+}
+
+/// Replace every row in the database with the contents of a backup. The backup must come from
+/// the same kind of database with the same migrations applied. Sessions are restored too, so
+/// everyone, the caller included, is signed in as they were when the backup was taken.
+pub async fn restore(
+    State(app_state): State<AppState>,
+    auth_user: AuthUser,
+    body: Bytes,
+) -> Result<Json<ApiResponse<RestoreSummary>>> {
+    if !auth_user.0.is_super_admin {
+        return Err(crate::errors::AppError::Forbidden("Only super admins can restore the database".to_string()));
+    }
+
+    tracing::warn!("Super admin {} started restoring a database backup", auth_user.0.id);
+    let summary = crate::backup::restore(&app_state.db.connection, body.into()).await?;
+    tracing::warn!("Restored the database backup taken at {}", summary.created_at);
+
+    Ok(Json(ApiResponse::with_message(summary, "Backup restored")))
+}
+
+async fn find_user<C: ConnectionTrait>(db: &C, user_id: Uuid) -> Result<()> {
+    Users::find_by_id(user_id)
+        .one(db)
+        .await
+        .map_err(|e| crate::errors::AppError::Database(e.into()))?
+        .ok_or_else(|| crate::errors::AppError::NotFound("User not found".to_string()))?;
+    Ok(())
+}
+
+/// Switch a feature on or off for one user, whatever the rollout says; returns the user's features
+pub async fn set_feature_override(
+    State(app_state): State<AppState>,
+    auth_user: AuthUser,
+    Path((user_id, feature)): Path<(Uuid, Feature)>,
+    ValidatedJson(request): ValidatedJson<SetFeatureOverrideRequest>,
+) -> Result<Json<ApiResponse<BTreeMap<Feature, bool>>>> {
+    if !auth_user.0.is_super_admin {
+        return Err(crate::errors::AppError::Forbidden("Only super admins can change feature flags".to_string()));
+    }
+    let db = &app_state.db.connection;
+    find_user(db, user_id).await?;
+
+    let mut override_active = feature_flag_overrides::ActiveModel::new();
+    override_active.user_id = Set(user_id);
+    override_active.feature = Set(feature.key().to_string());
+    override_active.enabled = Set(request.enabled);
+    FeatureFlagOverrides::insert(override_active)
+        .on_conflict(
+            OnConflict::columns([feature_flag_overrides::Column::UserId, feature_flag_overrides::Column::Feature])
+                .update_columns([feature_flag_overrides::Column::Enabled, feature_flag_overrides::Column::UpdatedAt])
+                .to_owned(),
+        )
+        .exec_without_returning(db)
+        .await
+        .map_err(|e| crate::errors::AppError::Database(e.into()))?;
+
+    record_audit(
+        db,
+        user_id,
+        Some(auth_user.0.id),
+        "feature_override_set",
+        serde_json::json!({ "feature": feature.key(), "enabled": request.enabled }),
+    )
+    .await?;
+    tracing::warn!("Super admin {} turned {} {} for user {}", auth_user.0.id, feature.key(), if request.enabled { "on" } else { "off" }, user_id);
+
+    let features = app_state.features.for_user(db, user_id).await?;
+    Ok(Json(ApiResponse::with_message(features, "Feature override saved")))
+}
+
+/// Drop a user's override so the rollout decides again; returns the user's features
+pub async fn clear_feature_override(
+    State(app_state): State<AppState>,
+    auth_user: AuthUser,
+    Path((user_id, feature)): Path<(Uuid, Feature)>,
+) -> Result<Json<ApiResponse<BTreeMap<Feature, bool>>>> {
+    if !auth_user.0.is_super_admin {
+        return Err(crate::errors::AppError::Forbidden("Only super admins can change feature flags".to_string()));
+    }
+    let db = &app_state.db.connection;
+    find_user(db, user_id).await?;
+
+    FeatureFlagOverrides::delete_many()
+        .filter(feature_flag_overrides::Column::UserId.eq(user_id))
+        .filter(feature_flag_overrides::Column::Feature.eq(feature.key()))
+        .exec(db)
+        .await
+        .map_err(|e| crate::errors::AppError::Database(e.into()))?;
+
+    record_audit(
+        db,
+        user_id,
+        Some(auth_user.0.id),
+        "feature_override_cleared",
+        serde_json::json!({ "feature": feature.key() }),
+    )
+    .await?;
+
+    let features = app_state.features.for_user(db, user_id).await?;
+    Ok(Json(ApiResponse::with_message(features, "Feature override removed")))
+}
+
+/// Send an announcement, e.g. of a maintenance window or a new version, to everyone connected
+/// over WebSocket. It's also stored, so users who weren't connected see it at `GET /api/announcements`.
+pub async fn broadcast(
+    State(app_state): State<AppState>,
+    auth_user: AuthUser,
+    ValidatedJson(request): ValidatedJson<CreateAnnouncementRequest>,
+) -> Result<Json<ApiResponse<AnnouncementResponse>>> {
+    if !auth_user.0.is_super_admin {
+        return Err(crate::errors::AppError::Forbidden("Only super admins can broadcast announcements".to_string()));
+    }
+
+    let mut announcement_active = announcements::ActiveModel::new();
+    announcement_active.kind = Set(request.kind.unwrap_or_default());
+    announcement_active.message = Set(request.message.trim().to_string());
+    announcement_active.starts_at = Set(request.starts_at.map(Into::into));
+    announcement_active.ends_at = Set(request.ends_at.map(Into::into));
+    announcement_active.created_by = Set(Some(auth_user.0.id));
+    let announcement: AnnouncementResponse = announcement_active
+        .insert(&app_state.db.connection)
+        .await
+        .map_err(|e| crate::errors::AppError::Database(e.into()))?
+        .into();
+
+    let reached = app_state.ws_state.send_to_all(OutgoingFrame::Announcement(announcement.clone())).await;
+    tracing::warn!("Super admin {} broadcast announcement {} to {} connections", auth_user.0.id, announcement.id, reached);
+
+    Ok(Json(ApiResponse::with_message(
+        announcement,
+        format!("Announcement sent to {} connections", reached),
+    )))
+}
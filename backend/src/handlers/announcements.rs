@@ -0,0 +1,40 @@
+use axum::{
+    extract::{Query, State},
+    response::Json,
+};
+use chrono::Utc;
+use sea_orm::*;
+
+use crate::{
+    entities::{announcements, prelude::*},
+    errors::Result,
+    middleware::auth::AuthUser,
+    models::{
+        announcement::{AnnouncementQuery, AnnouncementResponse},
+        ApiResponse, PageQuery,
+    },
+    state::AppState,
+};
+
+/// Announcements that haven't ended yet, newest first, so clients that were offline when one
+/// was broadcast can still show it
+pub async fn list_announcements(
+    State(app_state): State<AppState>,
+    _auth_user: AuthUser,
+    Query(query): Query<AnnouncementQuery>,
+    Query(paging): Query<PageQuery>,
+) -> Result<Json<ApiResponse<Vec<AnnouncementResponse>>>> {
+    let mut find = Announcements::find()
+        .filter(
+            Condition::any()
+                .add(announcements::Column::EndsAt.is_null())
+                .add(announcements::Column::EndsAt.gt(Utc::now())),
+        )
+        .order_by_desc(announcements::Column::CreatedAt);
+    if let Some(since) = query.since {
+        find = find.filter(announcements::Column::CreatedAt.gt(since));
+    }
+    let (announcements, meta) = paging.fetch(app_state.db.reader(), find).await?;
+
+    Ok(Json(ApiResponse::list(announcements.into_iter().map(Into::into).collect(), meta)))
+}
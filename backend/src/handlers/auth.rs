@@ -1,31 +1,48 @@
 use axum::{
-    extract::State,
+    extract::{Path, Query, State},
+    http::{header, HeaderMap},
     response::Json,
 };
+use chrono::Utc;
+use sea_orm::{sea_query::Expr, *};
+use uuid::Uuid;
 
 use crate::{
-    errors::Result,
+    entities::{prelude::*, sessions},
+    errors::{AppError, Result},
     models::{
-        user::{CreateUserRequest, LoginRequest, AuthResponse, UserResponse},
-        ApiResponse,
+        user::{CreateUserRequest, LoginRequest, AuthResponse, SessionResponse, UserResponse},
+        ApiResponse, PageQuery,
+    },
+    middleware::{
+        auth::{AuthUser, CurrentSession},
+        validation::ValidatedJson,
     },
-    middleware::auth::AuthUser,
     state::AppState,
 };
 
+fn user_agent(headers: &HeaderMap) -> Option<String> {
+    headers
+        .get(header::USER_AGENT)
+        .and_then(|value| value.to_str().ok())
+        .map(str::to_string)
+}
+
 pub async fn register(
     State(app_state): State<AppState>,
-    Json(request): Json<CreateUserRequest>,
+    headers: HeaderMap,
+    ValidatedJson(request): ValidatedJson<CreateUserRequest>,
 ) -> Result<Json<ApiResponse<AuthResponse>>> {
-    let response = app_state.auth_service.register(request).await?;
+    let response = app_state.auth_service.register(request, user_agent(&headers)).await?;
     Ok(Json(ApiResponse::with_message(response, "User registered successfully")))
 }
 
 pub async fn login(
     State(app_state): State<AppState>,
-    Json(request): Json<LoginRequest>,
+    headers: HeaderMap,
+    ValidatedJson(request): ValidatedJson<LoginRequest>,
 ) -> Result<Json<ApiResponse<AuthResponse>>> {
-    let response = app_state.auth_service.login(request).await?;
+    let response = app_state.auth_service.login(request, user_agent(&headers)).await?;
     Ok(Json(ApiResponse::with_message(response, "Login successful")))
 }
 
@@ -36,3 +53,66 @@ pub async fn me(
     let user_response = auth_user.0.into();
     Ok(Json(ApiResponse::new(user_response)))
 }
+
+/// Sessions of the authenticated user that are still usable
+pub async fn list_sessions(
+    State(app_state): State<AppState>,
+    auth_user: AuthUser,
+    CurrentSession(current_session): CurrentSession,
+    Query(paging): Query<PageQuery>,
+) -> Result<Json<ApiResponse<Vec<SessionResponse>>>> {
+    let now = Utc::now();
+    let user_sessions = Sessions::find()
+        .filter(sessions::Column::UserId.eq(auth_user.0.id))
+        .filter(sessions::Column::RevokedAt.is_null())
+        .filter(sessions::Column::ExpiresAt.gt(now))
+        .order_by_desc(sessions::Column::CreatedAt)
+        .all(&app_state.db.connection)
+        .await
+        .map_err(|e| AppError::Database(e.into()))?;
+
+    let activity = app_state.auth_service.session_activity();
+    let mut responses = Vec::with_capacity(user_sessions.len());
+    for session in user_sessions {
+        let last_active_at = app_state.auth_service.last_active_at(&session).await;
+        let idle_expires_at = activity.idle_expires_at(last_active_at);
+        if idle_expires_at.is_some_and(|expires_at| expires_at <= now) {
+            continue;
+        }
+
+        responses.push(SessionResponse {
+            id: session.id,
+            user_agent: session.user_agent,
+            created_at: session.created_at.naive_utc().and_utc(),
+            last_active_at,
+            expires_at: session.expires_at.naive_utc().and_utc(),
+            idle_expires_at,
+            current: current_session == Some(session.id),
+        });
+    }
+
+    let (response, meta) = paging.page(responses)?;
+    Ok(Json(ApiResponse::list(response, meta)))
+}
+
+/// Sign a session out; its tokens are rejected from then on
+pub async fn revoke_session(
+    State(app_state): State<AppState>,
+    auth_user: AuthUser,
+    Path(id): Path<Uuid>,
+) -> Result<Json<ApiResponse<()>>> {
+    let result = Sessions::update_many()
+        .col_expr(sessions::Column::RevokedAt, Expr::value(Utc::now()))
+        .filter(sessions::Column::Id.eq(id))
+        .filter(sessions::Column::UserId.eq(auth_user.0.id))
+        .filter(sessions::Column::RevokedAt.is_null())
+        .exec(&app_state.db.connection)
+        .await
+        .map_err(|e| AppError::Database(e.into()))?;
+
+    if result.rows_affected == 0 {
+        return Err(AppError::NotFound("Session not found".to_string()));
+    }
+
+    Ok(Json(ApiResponse::with_message((), "Session revoked successfully")))
+}
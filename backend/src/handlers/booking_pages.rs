@@ -0,0 +1,408 @@
+use axum::{
+    extract::{Path, Query, State},
+    response::Json,
+};
+use chrono::{DateTime, Datelike, Duration, TimeZone, Utc};
+use sea_orm::*;
+use serde::Deserialize;
+use streamline_models::DEFAULT_SCHEMA_VERSION;
+use uuid::Uuid;
+
+use crate::{
+    entities::{prelude::*, booking_pages, bookings, calendar_events::{self, EventStatus}},
+    errors::{AppError, Result},
+    handlers::{
+        calendar_events::calendar_is_active, recurrence::occurrences_in,
+        scheduling_constraints::scheduling_constraints, user_settings::time_preferences,
+    },
+    middleware::{auth::AuthUser, connection::OriginConnection, preconditions::Preconditions, validation::ValidatedJson},
+    models::{
+        booking::{
+            parse_time_zone, AvailabilityWindow, BookingPageResponse, BookingResponse, BookingSlot,
+            CreateBookingPageRequest, CreateBookingRequest, PublicBookingPageResponse, PublicBookingResponse,
+            UpdateBookingPageRequest,
+        },
+        calendar_event::CalendarEventResponse,
+        ApiResponse, PageQuery, generate_public_token, validate_schema_version,
+    },
+    notifications::{BookingPayload, Notification},
+    quota::QuotaTable,
+    scheduler::weekly_intervals,
+    state::AppState,
+    websocket::WebSocketMessage,
+};
+
+/// Default and widest range of the public slots endpoint
+const DEFAULT_SLOT_WINDOW_DAYS: i64 = 7;
+const MAX_SLOT_WINDOW_DAYS: i64 = 31;
+
+#[derive(Debug, Deserialize)]
+pub struct SlotQuery {
+    /// Defaults to now
+    pub start: Option<DateTime<Utc>>,
+    /// Defaults to a week after `start`
+    pub end: Option<DateTime<Utc>>,
+}
+
+fn page_message(event_type: &str, page: &booking_pages::Model) -> WebSocketMessage {
+    WebSocketMessage {
+        event_type: event_type.to_string(),
+        table: "booking_pages".to_string(),
+        user_id: page.user_id,
+        record_id: Some(page.id),
+        data: Some(serde_json::to_value(BookingPageResponse::from(page.clone())).unwrap_or_default()),
+    }
+}
+
+async fn find_page<C: ConnectionTrait>(db: &C, user_id: Uuid, id: Uuid) -> Result<booking_pages::Model> {
+    BookingPages::find_by_id(id)
+        .filter(booking_pages::Column::UserId.eq(user_id))
+        .one(db)
+        .await
+        .map_err(|e| AppError::Database(e.into()))?
+        .ok_or_else(|| AppError::NotFound("Booking page not found".to_string()))
+}
+
+/// Slots the weekly windows offer within `[from, to)`, before busy time is taken out
+fn candidate_slots(page: &booking_pages::Model, from: DateTime<Utc>, to: DateTime<Utc>) -> Result<Vec<BookingSlot>> {
+    let tz = parse_time_zone(&page.time_zone)?;
+    let availability: Vec<AvailabilityWindow> = serde_json::from_value(page.availability.clone()).unwrap_or_default();
+    let length = Duration::minutes(page.slot_minutes as i64);
+
+    let mut slots = Vec::new();
+    let mut date = from.with_timezone(&tz).date_naive();
+    let last_date = to.with_timezone(&tz).date_naive();
+    while date <= last_date {
+        let weekday = date.weekday().number_from_monday();
+        for window in availability.iter().filter(|window| window.weekday == weekday) {
+            // Local times skipped by a DST change have no slots
+            let (Some(window_start), Some(window_end)) = (
+                tz.from_local_datetime(&date.and_time(window.start)).earliest(),
+                tz.from_local_datetime(&date.and_time(window.end)).earliest(),
+            ) else {
+                continue;
+            };
+            let window_end = window_end.with_timezone(&Utc);
+            let mut start_time = window_start.with_timezone(&Utc);
+            while start_time + length <= window_end {
+                if start_time >= from && start_time + length <= to {
+                    slots.push(BookingSlot { start_time, end_time: start_time + length });
+                }
+                start_time += length;
+            }
+        }
+        let Some(next) = date.succ_opt() else {
+            break;
+        };
+        date = next;
+    }
+
+    slots.sort_by_key(|slot| slot.start_time);
+    slots.dedup();
+    Ok(slots)
+}
+
+/// Bookable slots within `[from, to)`: inside the page's notice and horizon, clear of the
+/// owner's confirmed and tentative events including the page's buffers, and outside the
+/// protected windows of the owner's scheduling constraints
+async fn available_slots<C: ConnectionTrait>(
+    db: &C,
+    page: &booking_pages::Model,
+    from: DateTime<Utc>,
+    to: DateTime<Utc>,
+) -> Result<Vec<BookingSlot>> {
+    let now = Utc::now();
+    let from = from.max(now + Duration::minutes(page.min_notice_minutes as i64));
+    let to = to.min(now + Duration::days(page.max_days_ahead as i64));
+    if from >= to {
+        return Ok(Vec::new());
+    }
+
+    let buffer_before = Duration::minutes(page.buffer_before_minutes as i64);
+    let buffer_after = Duration::minutes(page.buffer_after_minutes as i64);
+    let busy = occurrences_in(
+        db,
+        CalendarEvents::find()
+            .filter(calendar_events::Column::UserId.eq(page.user_id))
+            .filter(calendar_events::Column::Status.ne(EventStatus::Cancelled)),
+        from - buffer_before,
+        to + buffer_after,
+    )
+    .await?;
+    let timezone = time_preferences(db, page.user_id).await?.timezone;
+    let protected = weekly_intervals(&scheduling_constraints(db, page.user_id).await?.protected_windows, timezone, from, to);
+
+    Ok(candidate_slots(page, from, to)?
+        .into_iter()
+        .filter(|slot| !protected.iter().any(|window| window.start < slot.end_time && window.end > slot.start_time))
+        .filter(|slot| {
+            let (blocked_from, blocked_to) = (slot.start_time - buffer_before, slot.end_time + buffer_after);
+            !busy.iter().any(|occurrence| {
+                let end_time = occurrence.end_time.unwrap_or(occurrence.start_time);
+                // Events without an end block the moment they start
+                occurrence.start_time < blocked_to && (end_time > blocked_from || occurrence.start_time >= blocked_from)
+            })
+        })
+        .collect())
+}
+
+pub async fn list_booking_pages(
+    State(app_state): State<AppState>,
+    auth_user: AuthUser,
+    Query(paging): Query<PageQuery>,
+) -> Result<Json<ApiResponse<Vec<BookingPageResponse>>>> {
+    let find = BookingPages::find()
+        .filter(booking_pages::Column::UserId.eq(auth_user.0.id))
+        .order_by_asc(booking_pages::Column::CreatedAt);
+    let (pages, meta) = paging.fetch(&app_state.db.connection, find).await?;
+
+    Ok(Json(ApiResponse::list(pages.into_iter().map(Into::into).collect(), meta)))
+}
+
+pub async fn get_booking_page(
+    State(app_state): State<AppState>,
+    auth_user: AuthUser,
+    Path(id): Path<Uuid>,
+) -> Result<Json<ApiResponse<BookingPageResponse>>> {
+    let page = find_page(&app_state.db.connection, auth_user.0.id, id).await?;
+    Ok(Json(ApiResponse::new(page.into())))
+}
+
+pub async fn create_booking_page(
+    State(app_state): State<AppState>,
+    auth_user: AuthUser,
+    OriginConnection(connection_id): OriginConnection,
+    ValidatedJson(request): ValidatedJson<CreateBookingPageRequest>,
+) -> Result<Json<ApiResponse<BookingPageResponse>>> {
+    if let Some(calendar_id) = request.calendar_id
+        && !calendar_is_active(&app_state.db.connection, auth_user.0.id, calendar_id).await?
+    {
+        return Err(AppError::NotFound("Calendar not found".to_string()));
+    }
+
+    let page = request
+        .into_active_model(auth_user.0.id, generate_public_token())?
+        .insert(&app_state.db.connection)
+        .await
+        .map_err(|e| AppError::Database(e.into()))?;
+
+    // Broadcast websocket message for booking page creation
+    tracing::info!("Booking page created, broadcasting websocket message for user {} (excluding connection {:?})", auth_user.0.id, connection_id);
+    app_state.ws_state
+        .broadcast_to_user(&auth_user.0.id, page_message("INSERT", &page), connection_id)
+        .await;
+
+    Ok(Json(ApiResponse::with_message(page.into(), "Booking page created successfully")))
+}
+
+pub async fn update_booking_page(
+    State(app_state): State<AppState>,
+    auth_user: AuthUser,
+    OriginConnection(connection_id): OriginConnection,
+    Path(id): Path<Uuid>,
+    preconditions: Preconditions,
+    ValidatedJson(request): ValidatedJson<UpdateBookingPageRequest>,
+) -> Result<Json<ApiResponse<BookingPageResponse>>> {
+    let user_id = auth_user.0.id;
+    let page = app_state.db.with_txn(|txn| Box::pin(async move {
+        let page = find_page(txn, user_id, id).await?;
+        preconditions.check(&page.updated_at)?;
+
+        if let Some(Some(calendar_id)) = request.calendar_id
+            && !calendar_is_active(txn, user_id, calendar_id).await?
+        {
+            return Err(AppError::NotFound("Calendar not found".to_string()));
+        }
+
+        let mut page_active: booking_pages::ActiveModel = page.into();
+        request.apply_to(&mut page_active)?;
+        page_active.update(txn).await
+            .map_err(|e| AppError::Database(e.into()))
+    })).await?;
+
+    // Broadcast websocket message for booking page update
+    tracing::info!("Booking page updated, broadcasting websocket message for user {} (excluding connection {:?})", auth_user.0.id, connection_id);
+    app_state.ws_state
+        .broadcast_to_user(&auth_user.0.id, page_message("UPDATE", &page), connection_id)
+        .await;
+
+    Ok(Json(ApiResponse::with_message(page.into(), "Booking page updated successfully")))
+}
+
+/// Delete a booking page and its booking records; events created by bookings stay
+pub async fn delete_booking_page(
+    State(app_state): State<AppState>,
+    auth_user: AuthUser,
+    OriginConnection(connection_id): OriginConnection,
+    Path(id): Path<Uuid>,
+    preconditions: Preconditions,
+) -> Result<Json<ApiResponse<()>>> {
+    let user_id = auth_user.0.id;
+    app_state.db.with_txn(|txn| Box::pin(async move {
+        let page = find_page(txn, user_id, id).await?;
+        preconditions.check(&page.updated_at)?;
+        BookingPages::delete_by_id(page.id)
+            .exec(txn)
+            .await
+            .map_err(|e| AppError::Database(e.into()))?;
+        Ok(())
+    })).await?;
+
+    // Broadcast websocket message for booking page deletion
+    tracing::info!("Booking page deleted, broadcasting websocket message for user {} (excluding connection {:?})", auth_user.0.id, connection_id);
+    let ws_message = WebSocketMessage {
+        event_type: "DELETE".to_string(),
+        table: "booking_pages".to_string(),
+        user_id: auth_user.0.id,
+        record_id: Some(id),
+        data: None,
+    };
+    app_state.ws_state.broadcast_to_user(&auth_user.0.id, ws_message, connection_id).await;
+
+    Ok(Json(ApiResponse::with_message((), "Booking page deleted successfully")))
+}
+
+pub async fn list_bookings(
+    State(app_state): State<AppState>,
+    auth_user: AuthUser,
+    Path(id): Path<Uuid>,
+    Query(paging): Query<PageQuery>,
+) -> Result<Json<ApiResponse<Vec<BookingResponse>>>> {
+    let page = find_page(&app_state.db.connection, auth_user.0.id, id).await?;
+
+    let find = Bookings::find()
+        .filter(bookings::Column::BookingPageId.eq(page.id))
+        .order_by_asc(bookings::Column::StartTime);
+    let (bookings, meta) = paging.fetch(&app_state.db.connection, find).await?;
+
+    Ok(Json(ApiResponse::list(bookings.into_iter().map(Into::into).collect(), meta)))
+}
+
+async fn find_public_page<C: ConnectionTrait>(db: &C, token: &str, lock: bool) -> Result<booking_pages::Model> {
+    let mut find = BookingPages::find()
+        .filter(booking_pages::Column::PublicToken.eq(token))
+        .filter(booking_pages::Column::IsActive.eq(true));
+    if lock {
+        find = find.lock_exclusive();
+    }
+    find.one(db)
+        .await
+        .map_err(|e| AppError::Database(e.into()))?
+        .ok_or_else(|| AppError::NotFound("Booking page not found".to_string()))
+}
+
+/// The booking page behind a booking link; no account needed
+pub async fn get_public_booking_page(
+    State(app_state): State<AppState>,
+    Path(token): Path<String>,
+) -> Result<Json<ApiResponse<PublicBookingPageResponse>>> {
+    let page = find_public_page(&app_state.db.connection, &token, false).await?;
+    Ok(Json(ApiResponse::new(page.into())))
+}
+
+pub async fn list_public_slots(
+    State(app_state): State<AppState>,
+    Path(token): Path<String>,
+    Query(query): Query<SlotQuery>,
+    Query(paging): Query<PageQuery>,
+) -> Result<Json<ApiResponse<Vec<BookingSlot>>>> {
+    let start = query.start.unwrap_or_else(Utc::now);
+    let end = query.end.unwrap_or(start + Duration::days(DEFAULT_SLOT_WINDOW_DAYS));
+    if end <= start {
+        return Err(AppError::Validation("end must be after start".to_string()));
+    }
+    if end - start > Duration::days(MAX_SLOT_WINDOW_DAYS) {
+        return Err(AppError::Validation(format!("The slot window must not exceed {} days", MAX_SLOT_WINDOW_DAYS)));
+    }
+
+    let page = find_public_page(&app_state.db.connection, &token, false).await?;
+    let slots = available_slots(&app_state.db.connection, &page, start, end).await?;
+    let (response, meta) = paging.page(slots)?;
+    Ok(Json(ApiResponse::list(response, meta)))
+}
+
+/// Book a slot through a booking link: creates the event in the owner's calendar and
+/// notifies the owner over WebSocket and, when configured, email
+pub async fn create_booking(
+    State(app_state): State<AppState>,
+    Path(token): Path<String>,
+    ValidatedJson(request): ValidatedJson<CreateBookingRequest>,
+) -> Result<Json<ApiResponse<PublicBookingResponse>>> {
+    validate_schema_version("calendar_events", request.schema_version)?;
+    let txn = app_state.db.connection.begin().await
+        .map_err(|e| AppError::Database(e.into()))?;
+
+    // Lock the page so two visitors can't book the same slot at once
+    let page = find_public_page(&txn, &token, true).await?;
+    let end_time = request.start_time + Duration::minutes(page.slot_minutes as i64);
+    let slots = available_slots(&txn, &page, request.start_time, end_time).await?;
+    if !slots.iter().any(|slot| slot.start_time == request.start_time) {
+        return Err(AppError::Conflict("This slot is not available".to_string()));
+    }
+    app_state.quotas.enforce(&txn, page.user_id, QuotaTable::CalendarEvents, 1).await?;
+
+    let calendar_id = match page.calendar_id {
+        Some(calendar_id) if calendar_is_active(&txn, page.user_id, calendar_id).await? => Some(calendar_id),
+        _ => None,
+    };
+    let mut event_active = calendar_events::ActiveModel::new();
+    event_active.user_id = Set(page.user_id);
+    event_active.calendar_id = Set(calendar_id);
+    event_active.encrypted_data = Set(request.encrypted_data);
+    event_active.iv = Set(request.iv);
+    event_active.salt = Set(request.salt);
+    event_active.schema_version = Set(request.schema_version.unwrap_or(DEFAULT_SCHEMA_VERSION));
+    event_active.start_time = Set(Some(request.start_time.into()));
+    event_active.end_time = Set(Some(end_time.into()));
+    let event = event_active.insert(&txn).await
+        .map_err(|e| AppError::Database(e.into()))?;
+
+    let mut booking_active = bookings::ActiveModel::new();
+    booking_active.booking_page_id = Set(page.id);
+    booking_active.user_id = Set(page.user_id);
+    booking_active.event_id = Set(Some(event.id));
+    booking_active.start_time = Set(request.start_time.into());
+    booking_active.end_time = Set(end_time.into());
+    let booking = booking_active.insert(&txn).await
+        .map_err(|e| AppError::Database(e.into()))?;
+
+    txn.commit().await
+        .map_err(|e| AppError::Database(e.into()))?;
+
+    // Broadcast websocket messages for the new event and booking to the owner
+    tracing::info!("Booking created, broadcasting websocket message for user {}", page.user_id);
+    let messages = vec![
+        WebSocketMessage {
+            event_type: "INSERT".to_string(),
+            table: "calendar_events".to_string(),
+            user_id: page.user_id,
+            record_id: Some(event.id),
+            data: Some(serde_json::to_value(CalendarEventResponse::from(event.clone())).unwrap_or_default()),
+        },
+        WebSocketMessage {
+            event_type: "INSERT".to_string(),
+            table: "bookings".to_string(),
+            user_id: page.user_id,
+            record_id: Some(booking.id),
+            data: Some(serde_json::to_value(BookingResponse::from(booking.clone())).unwrap_or_default()),
+        },
+    ];
+    app_state.ws_state.broadcast_many_to_user(&page.user_id, messages, None).await;
+
+    // Email can be slow; don't keep the visitor waiting for it
+    let notification = Notification {
+        user_id: page.user_id,
+        table: "bookings",
+        record_id: booking.id,
+        payload: serde_json::to_value(BookingPayload {
+            booking_page_id: page.id,
+            event_id: event.id,
+            start_time: request.start_time,
+            end_time,
+        })?,
+    };
+    let notifier = app_state.notifier.clone();
+    tokio::spawn(async move { notifier.notify(&notification).await });
+
+    Ok(Json(ApiResponse::with_message(booking.into(), "Booking confirmed")))
+}
@@ -1,43 +1,257 @@
+use std::collections::{HashMap, HashSet};
+
 use axum::{
-    extract::{Path, State},
-    http::HeaderMap,
+    extract::{Path, Query, State},
     response::Json,
 };
-use sea_orm::*;
+use chrono::{DateTime, Utc};
+use sea_orm::{sea_query::Query as SelectQuery, *};
+use serde::Deserialize;
 use uuid::Uuid;
 
 use crate::{
-    entities::{prelude::*, calendar_events},
+    entities::{prelude::*, calendar_events, calendars, project_members::ProjectRole, record_revisions::RevisionAction},
     errors::Result,
-    middleware::auth::AuthUser,
+    handlers::{
+        calendars::accessible_calendar,
+        can_do_list::find_task,
+        event_attendees::attendee_summaries,
+        organizations::{organization_audience, organization_ids},
+        recurrence::{restore_overrides, trash_overrides},
+        reminders::reschedule_event_reminders,
+        revisions::{find_revision, list_revisions, record_revision},
+        user_settings::time_preferences,
+        sync::record_deletion,
+        trash::DeleteQuery,
+    },
+    middleware::{auth::AuthUser, connection::OriginConnection, preconditions::Preconditions, validation::ValidatedJson},
     models::{
-        calendar_event::{CreateCalendarEventRequest, UpdateCalendarEventRequest, CalendarEventResponse},
-        ApiResponse,
+        calendar_event::{
+            all_day_bounds, parse_status_filter, validate_all_day, BatchCreateCalendarEventsRequest, CreateCalendarEventRequest,
+            UpdateCalendarEventRequest, UpdateEventTimeRequest, CalendarEventResponse, LinkTaskRequest,
+        },
+        revision::{RevisionResponse, Revisioned},
+        validation::field_error,
+        ApiResponse, PageQuery, ApplyTo, IntoRecord, FieldsQuery, validate_schema_version,
     },
+    quota::QuotaTable,
+    recurrence,
     state::AppState,
     websocket::WebSocketMessage,
 };
 
-fn extract_connection_id(headers: &HeaderMap) -> Option<Uuid> {
-    headers
-        .get("x-connection-id")
-        .and_then(|v| v.to_str().ok())
-        .and_then(|s| Uuid::parse_str(s).ok())
+pub(crate) fn validate_time_range(start_time: Option<DateTime<Utc>>, end_time: Option<DateTime<Utc>>) -> Result<()> {
+    if let (Some(start_time), Some(end_time)) = (start_time, end_time)
+        && end_time < start_time
+    {
+        return Err(crate::errors::AppError::invalid_field("end_time", "end_time must not be before start_time"));
+    }
+    Ok(())
+}
+
+/// Set an all-day event's times from its dates, taken as whole days in the owner's timezone, so
+/// range queries and availability see the days the owner means. The dates must be valid already.
+pub(crate) async fn resolve_all_day<C: ConnectionTrait>(
+    db: &C,
+    owner_id: Uuid,
+    request: &mut CreateCalendarEventRequest,
+) -> Result<()> {
+    let Some(start_date) = request.start_date.filter(|_| request.is_all_day) else {
+        return Ok(());
+    };
+    let end_date = request.end_date.unwrap_or(start_date);
+    let (start_time, end_time) = all_day_bounds(&time_preferences(db, owner_id).await?, start_date, end_date);
+    request.end_date = Some(end_date);
+    request.start_time = Some(start_time);
+    request.end_time = Some(end_time);
+    Ok(())
+}
+
+/// Check an update's dates against what the event ends up as, then derive its times like
+/// [`resolve_all_day`]; an event that stops being all-day loses its dates
+pub(crate) async fn resolve_all_day_update<C: ConnectionTrait>(
+    db: &C,
+    event: &calendar_events::Model,
+    request: &mut UpdateCalendarEventRequest,
+) -> Result<()> {
+    let is_all_day = request.is_all_day.unwrap_or(event.is_all_day);
+    let (start_date, end_date) = if is_all_day {
+        (request.start_date.unwrap_or(event.start_date), request.end_date.unwrap_or(event.end_date))
+    } else {
+        (request.start_date.flatten(), request.end_date.flatten())
+    };
+    let mut errors = Vec::new();
+    validate_all_day(&mut errors, is_all_day, start_date, end_date);
+    if !errors.is_empty() {
+        return Err(crate::errors::AppError::InvalidFields(errors));
+    }
+
+    let Some(start_date) = start_date.filter(|_| is_all_day) else {
+        if event.is_all_day {
+            request.start_date = Some(None);
+            request.end_date = Some(None);
+        }
+        return Ok(());
+    };
+    let end_date = end_date.unwrap_or(start_date);
+    let (start_time, end_time) = all_day_bounds(&time_preferences(db, event.user_id).await?, start_date, end_date);
+    request.start_date = Some(Some(start_date));
+    request.end_date = Some(Some(end_date));
+    request.start_time = Some(Some(start_time));
+    request.end_time = Some(Some(end_time));
+    Ok(())
+}
+
+/// Whether the calendar exists, belongs to the user, is not in the trash and takes
+/// events; subscription calendars are read-only
+pub(crate) async fn calendar_is_active<C: ConnectionTrait>(db: &C, user_id: Uuid, calendar_id: Uuid) -> Result<bool> {
+    let calendar = Calendars::find_by_id(calendar_id)
+        .filter(calendars::Column::UserId.eq(user_id))
+        .filter(calendars::Column::DeletedAt.is_null())
+        .filter(calendars::Column::IsReadOnly.eq(false))
+        .one(db)
+        .await
+        .map_err(|e| crate::errors::AppError::Database(e.into()))?;
+    Ok(calendar.is_some())
+}
+
+/// Events the user may access with at least `min_role`: their own and those in calendars shared
+/// with them through an organization
+pub(crate) async fn event_scope<C: ConnectionTrait>(db: &C, user_id: Uuid, min_role: ProjectRole) -> Result<Condition> {
+    let organization_ids = organization_ids(db, user_id, min_role).await?;
+    let shared_calendars = SelectQuery::select()
+        .column(calendars::Column::Id)
+        .from(Calendars)
+        .and_where(calendars::Column::OrganizationId.is_in(organization_ids))
+        .and_where(calendars::Column::DeletedAt.is_null())
+        .to_owned();
+    Ok(Condition::any()
+        .add(calendar_events::Column::UserId.eq(user_id))
+        .add(calendar_events::Column::CalendarId.in_subquery(shared_calendars)))
+}
+
+/// Whose events a new or moved event in `calendar_id` belongs to. Events of a shared calendar stay
+/// with the calendar's own user, so the user needs at least `editor` rights there.
+pub(crate) async fn event_calendar_owner<C: ConnectionTrait>(db: &C, user_id: Uuid, calendar_id: Uuid) -> Result<Uuid> {
+    let (calendar, role) = accessible_calendar(db, user_id, calendar_id).await?;
+    if calendar.is_read_only {
+        return Err(crate::errors::AppError::NotFound("Calendar not found".to_string()));
+    }
+    if role < ProjectRole::Editor {
+        return Err(crate::errors::AppError::Forbidden("Viewers cannot change the events of a shared calendar".to_string()));
+    }
+    Ok(calendar.user_id)
+}
+
+/// Users who see changes to events of `owner_id` in the given calendars
+pub(crate) async fn event_audience<C: ConnectionTrait>(
+    db: &C,
+    owner_id: Uuid,
+    calendar_ids: impl IntoIterator<Item = Option<Uuid>>,
+) -> Result<Vec<Uuid>> {
+    let calendar_ids: Vec<Uuid> = calendar_ids.into_iter().flatten().collect();
+    if calendar_ids.is_empty() {
+        return Ok(vec![owner_id]);
+    }
+    let organization_ids: Vec<Option<Uuid>> = Calendars::find()
+        .select_only()
+        .column(calendars::Column::OrganizationId)
+        .filter(calendars::Column::Id.is_in(calendar_ids))
+        .into_tuple()
+        .all(db)
+        .await
+        .map_err(|e| crate::errors::AppError::Database(e.into()))?;
+    organization_audience(db, owner_id, organization_ids).await
+}
+
+/// An event moves to `calendar_id` only if the user may add events there and the calendar belongs
+/// to the event's user, whose event it stays
+pub(crate) async fn ensure_same_owner<C: ConnectionTrait>(
+    db: &C,
+    user_id: Uuid,
+    calendar_id: Uuid,
+    owner_id: Uuid,
+) -> Result<()> {
+    if event_calendar_owner(db, user_id, calendar_id).await? != owner_id {
+        return Err(crate::errors::AppError::Validation(
+            "An event can only move between calendars of the same user".to_string(),
+        ));
+    }
+    Ok(())
+}
+
+/// Events imported from a calendar subscription are only changed by their feed
+pub(crate) fn ensure_editable(event: &calendar_events::Model) -> Result<()> {
+    if event.subscription_id.is_some() {
+        return Err(crate::errors::AppError::Forbidden("Events from calendar subscriptions are read-only".to_string()));
+    }
+    Ok(())
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CalendarEventQuery {
+    pub calendar_id: Option<Uuid>,
+    pub start: Option<DateTime<Utc>>,
+    pub end: Option<DateTime<Utc>>,
+    /// Comma-separated statuses to include, e.g. `confirmed,tentative`
+    pub status: Option<String>,
 }
 
 pub async fn list_events(
     State(app_state): State<AppState>,
     auth_user: AuthUser,
-) -> Result<Json<ApiResponse<Vec<CalendarEventResponse>>>> {
-    let events = CalendarEvents::find()
-        .filter(calendar_events::Column::UserId.eq(auth_user.0.id))
-        .order_by_asc(calendar_events::Column::CreatedAt)
-        .all(&app_state.db.connection)
-        .await
-        .map_err(|e| crate::errors::AppError::Database(e.into()))?;
+    Query(query): Query<CalendarEventQuery>,
+    Query(fields): Query<FieldsQuery>,
+    Query(paging): Query<PageQuery>,
+) -> Result<Json<ApiResponse<Vec<serde_json::Value>>>> {
+    validate_time_range(query.start, query.end)?;
+    let db = app_state.db.reader();
+
+    let mut find = CalendarEvents::find()
+        .filter(event_scope(db, auth_user.0.id, ProjectRole::Viewer).await?)
+        .filter(calendar_events::Column::DeletedAt.is_null());
+
+    if let Some(calendar_id) = query.calendar_id {
+        find = find.filter(calendar_events::Column::CalendarId.eq(calendar_id));
+    }
+    if let Some(statuses) = parse_status_filter(query.status.as_deref())? {
+        find = find.filter(calendar_events::Column::Status.is_in(statuses));
+    }
+
+    // Events without plaintext times can't be placed on the timeline, so they always match
+    if let Some(start) = query.start {
+        find = find.filter(
+            Condition::any()
+                .add(calendar_events::Column::StartTime.is_null())
+                .add(calendar_events::Column::EndTime.gt(start))
+                .add(
+                    Condition::all()
+                        .add(calendar_events::Column::EndTime.is_null())
+                        .add(calendar_events::Column::StartTime.gte(start)),
+                ),
+        );
+    }
+    if let Some(end) = query.end {
+        find = find.filter(
+            Condition::any()
+                .add(calendar_events::Column::StartTime.is_null())
+                .add(calendar_events::Column::StartTime.lt(end)),
+        );
+    }
 
-    let response: Vec<CalendarEventResponse> = events.into_iter().map(|event| event.into()).collect();
-    Ok(Json(ApiResponse::new(response)))
+    let find = find
+        .order_by_asc(calendar_events::Column::CreatedAt);
+    let (events, meta) = paging.fetch(db, find).await?;
+
+    let mut summaries = attendee_summaries(db, events.iter().map(|event| event.id).collect()).await?;
+    let response: Vec<CalendarEventResponse> = events
+        .into_iter()
+        .map(|event| {
+            let attendees = summaries.remove(&event.id).unwrap_or_default();
+            CalendarEventResponse { attendees: Some(attendees), ..event.into() }
+        })
+        .collect();
+    Ok(Json(ApiResponse::list(fields.select(response)?, meta)))
 }
 
 pub async fn get_event(
@@ -45,120 +259,641 @@ pub async fn get_event(
     auth_user: AuthUser,
     Path(id): Path<Uuid>,
 ) -> Result<Json<ApiResponse<CalendarEventResponse>>> {
+    let db = app_state.db.reader();
     let event = CalendarEvents::find_by_id(id)
-        .filter(calendar_events::Column::UserId.eq(auth_user.0.id))
-        .one(&app_state.db.connection)
+        .filter(event_scope(db, auth_user.0.id, ProjectRole::Viewer).await?)
+        .filter(calendar_events::Column::DeletedAt.is_null())
+        .one(db)
         .await
         .map_err(|e| crate::errors::AppError::Database(e.into()))?
         .ok_or_else(|| crate::errors::AppError::NotFound("Calendar event not found".to_string()))?;
 
-    Ok(Json(ApiResponse::new(event.into())))
+    let attendees = attendee_summaries(db, vec![event.id]).await?
+        .remove(&event.id)
+        .unwrap_or_default();
+    Ok(Json(ApiResponse::new(CalendarEventResponse { attendees: Some(attendees), ..event.into() })))
 }
 
 pub async fn create_event(
     State(app_state): State<AppState>,
     auth_user: AuthUser,
-    headers: HeaderMap,
-    Json(request): Json<CreateCalendarEventRequest>,
+    OriginConnection(connection_id): OriginConnection,
+    ValidatedJson(mut request): ValidatedJson<CreateCalendarEventRequest>,
 ) -> Result<Json<ApiResponse<CalendarEventResponse>>> {
-    let connection_id = extract_connection_id(&headers);
-    
-    let mut event_active = calendar_events::ActiveModel::new();
-    event_active.user_id = Set(auth_user.0.id);
-    event_active.encrypted_data = Set(request.encrypted_data);
-    event_active.iv = Set(request.iv);
-    event_active.salt = Set(request.salt);
+    // Events in a shared calendar belong to the calendar's user and count against their quota
+    let owner_id = match request.calendar_id {
+        Some(calendar_id) => event_calendar_owner(&app_state.db.connection, auth_user.0.id, calendar_id).await?,
+        None => auth_user.0.id,
+    };
+    resolve_all_day(&app_state.db.connection, owner_id, &mut request).await?;
+    validate_time_range(request.start_time, request.end_time)?;
+    recurrence::validate(request.rrule.as_deref(), request.start_time)?;
+
+    validate_schema_version("calendar_events", request.schema_version)?;
+    app_state.quotas.enforce(&app_state.db.connection, owner_id, QuotaTable::CalendarEvents, 1).await?;
+    let client_id = request.id;
+    let event_active = request.into_active_model(owner_id);
 
     let event = event_active.insert(&app_state.db.connection).await
-        .map_err(|e| crate::errors::AppError::Database(e.into()))?;
+        .map_err(|e| crate::errors::AppError::from_insert(e, client_id))?;
 
-    // Broadcast websocket message for calendar event creation
-    tracing::info!("Calendar event created, broadcasting websocket message for user {} (excluding connection {:?})", auth_user.0.id, connection_id);
+    // Broadcast websocket message for calendar event creation to everyone who shares the calendar
+    tracing::info!("Calendar event created, broadcasting websocket message for user {} (excluding connection {:?})", owner_id, connection_id);
     let ws_message = WebSocketMessage {
         event_type: "INSERT".to_string(),
         table: "calendar_events".to_string(),
-        user_id: auth_user.0.id,
+        user_id: owner_id,
         record_id: Some(event.id),
-        data: Some(serde_json::to_value(&CalendarEventResponse::from(event.clone())).unwrap_or_default()),
+        data: Some(serde_json::to_value(CalendarEventResponse::from(event.clone())).unwrap_or_default()),
     };
-    app_state.ws_state.broadcast_to_user(&auth_user.0.id, ws_message, connection_id).await;
+    let audience = event_audience(&app_state.db.connection, owner_id, [event.calendar_id]).await?;
+    app_state.ws_state.broadcast_many_to_users(&audience, vec![ws_message], connection_id).await;
 
     Ok(Json(ApiResponse::with_message(event.into(), "Calendar event created successfully")))
 }
 
+/// Create many events in one transaction: either all of them or, if any is rejected, none. The
+/// problems are reported per event, as fields like `events[3].calendar_id`.
+pub async fn batch_create_events(
+    State(app_state): State<AppState>,
+    auth_user: AuthUser,
+    OriginConnection(connection_id): OriginConnection,
+    ValidatedJson(mut request): ValidatedJson<BatchCreateCalendarEventsRequest>,
+) -> Result<Json<ApiResponse<Vec<CalendarEventResponse>>>> {
+    let user_id = auth_user.0.id;
+    let quotas = app_state.quotas.clone();
+    let events = app_state.db.with_txn(|txn| Box::pin(async move {
+        let mut errors = Vec::new();
+        // Batches usually target a handful of calendars, so each is only looked up once
+        let mut calendar_owners: HashMap<Uuid, std::result::Result<Uuid, String>> = HashMap::new();
+        let mut owners = Vec::with_capacity(request.events.len());
+        for (index, event) in request.events.iter().enumerate() {
+            let Some(calendar_id) = event.calendar_id else {
+                owners.push(user_id);
+                continue;
+            };
+            let owner = match calendar_owners.get(&calendar_id) {
+                Some(owner) => owner.clone(),
+                None => {
+                    let owner = match event_calendar_owner(txn, user_id, calendar_id).await {
+                        Ok(owner_id) => Ok(owner_id),
+                        Err(crate::errors::AppError::NotFound(message) | crate::errors::AppError::Forbidden(message)) => {
+                            Err(message)
+                        }
+                        Err(error) => return Err(error),
+                    };
+                    calendar_owners.insert(calendar_id, owner.clone());
+                    owner
+                }
+            };
+            match owner {
+                Ok(owner_id) => owners.push(owner_id),
+                Err(message) => {
+                    field_error(&mut errors, &format!("events[{}].calendar_id", index), message);
+                    owners.push(user_id);
+                }
+            }
+        }
+
+        let client_ids: Vec<Uuid> = request.events.iter().filter_map(|event| event.id).collect();
+        if !client_ids.is_empty() {
+            let taken: HashSet<Uuid> = CalendarEvents::find()
+                .select_only()
+                .column(calendar_events::Column::Id)
+                .filter(calendar_events::Column::Id.is_in(client_ids))
+                .into_tuple::<Uuid>()
+                .all(txn)
+                .await
+                .map_err(|e| crate::errors::AppError::Database(e.into()))?
+                .into_iter()
+                .collect();
+            for (index, event) in request.events.iter().enumerate() {
+                if let Some(id) = event.id.filter(|id| taken.contains(id)) {
+                    field_error(&mut errors, &format!("events[{}].id", index), format!("A record with id {} already exists", id));
+                }
+            }
+        }
+        if !errors.is_empty() {
+            return Err(crate::errors::AppError::InvalidFields(errors));
+        }
+
+        for (event, owner_id) in request.events.iter_mut().zip(&owners) {
+            resolve_all_day(txn, *owner_id, event).await?;
+        }
+
+        // Events in a shared calendar count against the quota of the calendar's user
+        let mut counts: HashMap<Uuid, u64> = HashMap::new();
+        for owner_id in &owners {
+            *counts.entry(*owner_id).or_default() += 1;
+        }
+        for (owner_id, count) in counts {
+            quotas.enforce(txn, owner_id, QuotaTable::CalendarEvents, count).await?;
+        }
+
+        let mut events = Vec::with_capacity(request.events.len());
+        for (event, owner_id) in request.events.into_iter().zip(owners) {
+            let client_id = event.id;
+            let event = event.into_active_model(owner_id).insert(txn).await
+                .map_err(|e| crate::errors::AppError::from_insert(e, client_id))?;
+            events.push(event);
+        }
+        Ok(events)
+    })).await?;
+
+    // Broadcast the new events of each calendar together, to everyone who shares the calendar
+    tracing::info!("{} calendar events created, broadcasting websocket messages for user {} (excluding connection {:?})", events.len(), user_id, connection_id);
+    let mut changes: HashMap<(Uuid, Option<Uuid>), Vec<WebSocketMessage>> = HashMap::new();
+    for event in &events {
+        changes.entry((event.user_id, event.calendar_id)).or_default().push(WebSocketMessage {
+            event_type: "INSERT".to_string(),
+            table: "calendar_events".to_string(),
+            user_id: event.user_id,
+            record_id: Some(event.id),
+            data: Some(serde_json::to_value(CalendarEventResponse::from(event.clone())).unwrap_or_default()),
+        });
+    }
+    for ((owner_id, calendar_id), messages) in changes {
+        let audience = event_audience(&app_state.db.connection, owner_id, [calendar_id]).await?;
+        app_state.ws_state.broadcast_many_to_users(&audience, messages, connection_id).await;
+    }
+
+    let events = events.into_iter().map(Into::into).collect();
+    Ok(Json(ApiResponse::with_message(events, "Calendar events created successfully")))
+}
+
 pub async fn update_event(
     State(app_state): State<AppState>,
     auth_user: AuthUser,
-    headers: HeaderMap,
+    OriginConnection(connection_id): OriginConnection,
+    Path(id): Path<Uuid>,
+    preconditions: Preconditions,
+    ValidatedJson(mut request): ValidatedJson<UpdateCalendarEventRequest>,
+) -> Result<Json<ApiResponse<CalendarEventResponse>>> {
+    validate_schema_version("calendar_events", request.schema_version)?;
+    let user_id = auth_user.0.id;
+    let (updated_event, previous_calendar_id) = app_state.db.with_txn(|txn| Box::pin(async move {
+        let event = CalendarEvents::find_by_id(id)
+            .filter(event_scope(txn, user_id, ProjectRole::Editor).await?)
+            .filter(calendar_events::Column::DeletedAt.is_null())
+            .one(txn)
+            .await
+            .map_err(|e| crate::errors::AppError::Database(e.into()))?
+            .ok_or_else(|| crate::errors::AppError::NotFound("Calendar event not found".to_string()))?;
+        preconditions.check(&event.updated_at)?;
+        ensure_editable(&event)?;
+        let previous_calendar_id = event.calendar_id;
+
+        resolve_all_day_update(txn, &event, &mut request).await?;
+        let start_time = request.start_time.unwrap_or(event.start_time.map(|dt| dt.naive_utc().and_utc()));
+        validate_time_range(
+            start_time,
+            request.end_time.unwrap_or(event.end_time.map(|dt| dt.naive_utc().and_utc())),
+        )?;
+        recurrence::validate(request.rrule.clone().unwrap_or(event.rrule.clone()).as_deref(), start_time)?;
+
+        if let Some(Some(calendar_id)) = request.calendar_id {
+            ensure_same_owner(txn, user_id, calendar_id, event.user_id).await?;
+        }
+
+        record_revision(txn, user_id, RevisionAction::Update, &event).await?;
+        let mut event_active: calendar_events::ActiveModel = event.into();
+        request.apply_to(&mut event_active);
+
+        let updated_event = event_active.update(txn).await
+            .map_err(|e| crate::errors::AppError::Database(e.into()))?;
+        reschedule_event_reminders(txn, &updated_event).await?;
+        Ok((updated_event, previous_calendar_id))
+    })).await?;
+    let owner_id = updated_event.user_id;
+
+    // Broadcast websocket message for calendar event update to everyone who shares either calendar
+    tracing::info!("Calendar event updated, broadcasting websocket message for user {} (excluding connection {:?})", owner_id, connection_id);
+    let ws_message = WebSocketMessage {
+        event_type: "UPDATE".to_string(),
+        table: "calendar_events".to_string(),
+        user_id: owner_id,
+        record_id: Some(updated_event.id),
+        data: Some(serde_json::to_value(CalendarEventResponse::from(updated_event.clone())).unwrap_or_default()),
+    };
+    let audience = event_audience(
+        &app_state.db.connection,
+        owner_id,
+        [updated_event.calendar_id, previous_calendar_id],
+    )
+    .await?;
+    app_state.ws_state.broadcast_many_to_users(&audience, vec![ws_message], connection_id).await;
+
+    Ok(Json(ApiResponse::with_message(updated_event.into(), "Calendar event updated successfully")))
+}
+
+/// Change only an event's start and end, e.g. while it is dragged or resized in the calendar
+pub async fn update_event_time(
+    State(app_state): State<AppState>,
+    auth_user: AuthUser,
+    OriginConnection(connection_id): OriginConnection,
     Path(id): Path<Uuid>,
-    Json(request): Json<UpdateCalendarEventRequest>,
+    preconditions: Preconditions,
+    ValidatedJson(request): ValidatedJson<UpdateEventTimeRequest>,
 ) -> Result<Json<ApiResponse<CalendarEventResponse>>> {
-    let connection_id = extract_connection_id(&headers);
-    
+    let user_id = auth_user.0.id;
+    let updated_event = app_state.db.with_txn(|txn| Box::pin(async move {
+        let event = CalendarEvents::find_by_id(id)
+            .filter(event_scope(txn, user_id, ProjectRole::Editor).await?)
+            .filter(calendar_events::Column::DeletedAt.is_null())
+            .one(txn)
+            .await
+            .map_err(|e| crate::errors::AppError::Database(e.into()))?
+            .ok_or_else(|| crate::errors::AppError::NotFound("Calendar event not found".to_string()))?;
+        preconditions.check(&event.updated_at)?;
+        ensure_editable(&event)?;
+
+        let mut change = if event.is_all_day {
+            if request.start_time.is_some() || request.end_time.is_some() {
+                return Err(crate::errors::AppError::invalid_field(
+                    "start_time",
+                    "All-day events are moved by start_date and end_date",
+                ));
+            }
+            UpdateCalendarEventRequest {
+                start_date: request.start_date.map(Some),
+                end_date: request.end_date.map(Some),
+                ..Default::default()
+            }
+        } else {
+            UpdateCalendarEventRequest {
+                start_time: request.start_time.map(Some),
+                end_time: request.end_time.map(Some),
+                start_date: request.start_date.map(Some),
+                end_date: request.end_date.map(Some),
+                ..Default::default()
+            }
+        };
+        resolve_all_day_update(txn, &event, &mut change).await?;
+        let start_time = change.start_time.unwrap_or(event.start_time.map(|dt| dt.naive_utc().and_utc()));
+        let end_time = change.end_time.unwrap_or(event.end_time.map(|dt| dt.naive_utc().and_utc()));
+        validate_time_range(start_time, end_time)?;
+        recurrence::validate(event.rrule.as_deref(), start_time)?;
+
+        record_revision(txn, user_id, RevisionAction::Update, &event).await?;
+        let mut event_active: calendar_events::ActiveModel = event.into();
+        change.apply_to(&mut event_active);
+        let updated_event = event_active.update(txn).await
+            .map_err(|e| crate::errors::AppError::Database(e.into()))?;
+        reschedule_event_reminders(txn, &updated_event).await?;
+        Ok(updated_event)
+    })).await?;
+    let owner_id = updated_event.user_id;
+
+    // Broadcast websocket message for the moved event to everyone who shares the calendar
+    tracing::info!("Calendar event moved, broadcasting websocket message for user {} (excluding connection {:?})", owner_id, connection_id);
+    let ws_message = WebSocketMessage {
+        event_type: "UPDATE".to_string(),
+        table: "calendar_events".to_string(),
+        user_id: owner_id,
+        record_id: Some(updated_event.id),
+        data: Some(serde_json::to_value(CalendarEventResponse::from(updated_event.clone())).unwrap_or_default()),
+    };
+    let audience = event_audience(&app_state.db.connection, owner_id, [updated_event.calendar_id]).await?;
+    app_state.ws_state.broadcast_many_to_users(&audience, vec![ws_message], connection_id).await;
+
+    Ok(Json(ApiResponse::with_message(updated_event.into(), "Calendar event moved successfully")))
+}
+
+/// Prior versions of a calendar event, newest first
+pub async fn list_event_revisions(
+    State(app_state): State<AppState>,
+    auth_user: AuthUser,
+    Path(id): Path<Uuid>,
+    Query(paging): Query<PageQuery>,
+) -> Result<Json<ApiResponse<Vec<RevisionResponse<CalendarEventResponse>>>>> {
+    let db = app_state.db.reader();
     let event = CalendarEvents::find_by_id(id)
-        .filter(calendar_events::Column::UserId.eq(auth_user.0.id))
-        .one(&app_state.db.connection)
+        .filter(event_scope(db, auth_user.0.id, ProjectRole::Viewer).await?)
+        .filter(calendar_events::Column::DeletedAt.is_null())
+        .one(db)
         .await
         .map_err(|e| crate::errors::AppError::Database(e.into()))?
         .ok_or_else(|| crate::errors::AppError::NotFound("Calendar event not found".to_string()))?;
 
-    let mut event_active: calendar_events::ActiveModel = event.into();
-    
-    if let Some(encrypted_data) = request.encrypted_data {
-        event_active.encrypted_data = Set(encrypted_data);
-    }
-    if let Some(iv) = request.iv {
-        event_active.iv = Set(iv);
-    }
-    if let Some(salt) = request.salt {
-        event_active.salt = Set(salt);
-    }
+    let (response, meta) = paging.page(list_revisions::<_, calendar_events::Model, _>(db, event.id).await?)?;
+    Ok(Json(ApiResponse::list(response, meta)))
+}
 
-    let updated_event = event_active.update(&app_state.db.connection).await
-        .map_err(|e| crate::errors::AppError::Database(e.into()))?;
+/// Bring back a calendar event's content as of one of its revisions; the version replaced becomes a revision itself
+pub async fn revert_event(
+    State(app_state): State<AppState>,
+    auth_user: AuthUser,
+    OriginConnection(connection_id): OriginConnection,
+    Path((id, revision_id)): Path<(Uuid, Uuid)>,
+) -> Result<Json<ApiResponse<CalendarEventResponse>>> {
+    let user_id = auth_user.0.id;
+    let reverted_event = app_state.db.with_txn(|txn| Box::pin(async move {
+        let event = CalendarEvents::find_by_id(id)
+            .filter(event_scope(txn, user_id, ProjectRole::Editor).await?)
+            .filter(calendar_events::Column::DeletedAt.is_null())
+            .one(txn)
+            .await
+            .map_err(|e| crate::errors::AppError::Database(e.into()))?
+            .ok_or_else(|| crate::errors::AppError::NotFound("Calendar event not found".to_string()))?;
+        ensure_editable(&event)?;
+        let prior: calendar_events::Model = find_revision(txn, id, revision_id).await?;
+
+        record_revision(txn, user_id, RevisionAction::Revert, &event).await?;
+        let mut event_active: calendar_events::ActiveModel = event.into();
+        calendar_events::Model::revert(&mut event_active, prior);
+
+        let reverted_event = event_active.update(txn).await
+            .map_err(|e| crate::errors::AppError::Database(e.into()))?;
+        reschedule_event_reminders(txn, &reverted_event).await?;
+        Ok(reverted_event)
+    })).await?;
+    let owner_id = reverted_event.user_id;
+
+    // Broadcast websocket message for calendar event update to everyone who shares the calendar
+    tracing::info!("Calendar event reverted, broadcasting websocket message for user {} (excluding connection {:?})", owner_id, connection_id);
+    let ws_message = WebSocketMessage {
+        event_type: "UPDATE".to_string(),
+        table: "calendar_events".to_string(),
+        user_id: owner_id,
+        record_id: Some(reverted_event.id),
+        data: Some(serde_json::to_value(CalendarEventResponse::from(reverted_event.clone())).unwrap_or_default()),
+    };
+    let audience = event_audience(&app_state.db.connection, owner_id, [reverted_event.calendar_id]).await?;
+    app_state.ws_state.broadcast_many_to_users(&audience, vec![ws_message], connection_id).await;
 
-    // Broadcast websocket message for calendar event update
-    tracing::info!("Calendar event updated, broadcasting websocket message for user {} (excluding connection {:?})", auth_user.0.id, connection_id);
+    Ok(Json(ApiResponse::with_message(reverted_event.into(), "Calendar event reverted successfully")))
+}
+
+/// Copy an event, including its calendar and times, under a new id
+pub async fn duplicate_event(
+    State(app_state): State<AppState>,
+    auth_user: AuthUser,
+    OriginConnection(connection_id): OriginConnection,
+    Path(id): Path<Uuid>,
+) -> Result<Json<ApiResponse<CalendarEventResponse>>> {
+    let user_id = auth_user.0.id;
+    let quotas = app_state.quotas.clone();
+    let event = app_state.db.with_txn(|txn| Box::pin(async move {
+        let source = CalendarEvents::find_by_id(id)
+            .filter(event_scope(txn, user_id, ProjectRole::Editor).await?)
+            .filter(calendar_events::Column::DeletedAt.is_null())
+            .one(txn)
+            .await
+            .map_err(|e| crate::errors::AppError::Database(e.into()))?
+            .ok_or_else(|| crate::errors::AppError::NotFound("Calendar event not found".to_string()))?;
+        ensure_editable(&source)?;
+        // The copy stays in the source's calendar, so it belongs to the same user
+        quotas.enforce(txn, source.user_id, QuotaTable::CalendarEvents, 1).await?;
+
+        let mut event_active = calendar_events::ActiveModel::new();
+        event_active.user_id = Set(source.user_id);
+        event_active.calendar_id = Set(source.calendar_id);
+        event_active.encrypted_data = Set(source.encrypted_data);
+        event_active.iv = Set(source.iv);
+        event_active.salt = Set(source.salt);
+        event_active.schema_version = Set(source.schema_version);
+        event_active.start_time = Set(source.start_time);
+        event_active.end_time = Set(source.end_time);
+        event_active.is_all_day = Set(source.is_all_day);
+        event_active.start_date = Set(source.start_date);
+        event_active.end_date = Set(source.end_date);
+        event_active.search_bloom = Set(source.search_bloom);
+        event_active.search_tokens = Set(source.search_tokens);
+        event_active.rrule = Set(source.rrule);
+        event_active.status = Set(source.status);
+        event_active.recurrence_exceptions = Set(source.recurrence_exceptions);
+        event_active.linked_task_id = Set(source.linked_task_id);
+
+        event_active.insert(txn).await
+            .map_err(|e| crate::errors::AppError::Database(e.into()))
+    })).await?;
+    let owner_id = event.user_id;
+
+    // Broadcast websocket message for the copied calendar event
+    tracing::info!("Calendar event duplicated, broadcasting websocket message for user {} (excluding connection {:?})", owner_id, connection_id);
+    let ws_message = WebSocketMessage {
+        event_type: "INSERT".to_string(),
+        table: "calendar_events".to_string(),
+        user_id: owner_id,
+        record_id: Some(event.id),
+        data: Some(serde_json::to_value(CalendarEventResponse::from(event.clone())).unwrap_or_default()),
+    };
+    let audience = event_audience(&app_state.db.connection, owner_id, [event.calendar_id]).await?;
+    app_state.ws_state.broadcast_many_to_users(&audience, vec![ws_message], connection_id).await;
+
+    Ok(Json(ApiResponse::with_message(event.into(), "Calendar event duplicated successfully")))
+}
+
+/// Link the event to a task of the same user, or unlink it when `task_id` is `None`
+async fn set_linked_task(
+    app_state: AppState,
+    auth_user: AuthUser,
+    connection_id: Option<Uuid>,
+    id: Uuid,
+    task_id: Option<Uuid>,
+) -> Result<Json<ApiResponse<CalendarEventResponse>>> {
+    let user_id = auth_user.0.id;
+    let updated_event = app_state.db.with_txn(|txn| Box::pin(async move {
+        let event = CalendarEvents::find_by_id(id)
+            .filter(event_scope(txn, user_id, ProjectRole::Editor).await?)
+            .filter(calendar_events::Column::DeletedAt.is_null())
+            .one(txn)
+            .await
+            .map_err(|e| crate::errors::AppError::Database(e.into()))?
+            .ok_or_else(|| crate::errors::AppError::NotFound("Calendar event not found".to_string()))?;
+        ensure_editable(&event)?;
+        if let Some(task_id) = task_id
+            && find_task(txn, user_id, task_id, Some(false), ProjectRole::Viewer).await?.user_id != event.user_id
+        {
+            return Err(crate::errors::AppError::Validation(
+                "An event can only be linked to a task of the same user".to_string(),
+            ));
+        }
+
+        let mut event_active: calendar_events::ActiveModel = event.into();
+        event_active.linked_task_id = Set(task_id);
+        event_active.update(txn).await
+            .map_err(|e| crate::errors::AppError::Database(e.into()))
+    })).await?;
+    let owner_id = updated_event.user_id;
+
+    // Broadcast websocket message for calendar event update to everyone who shares the calendar
+    tracing::info!("Calendar event link changed, broadcasting websocket message for user {} (excluding connection {:?})", owner_id, connection_id);
     let ws_message = WebSocketMessage {
         event_type: "UPDATE".to_string(),
         table: "calendar_events".to_string(),
-        user_id: auth_user.0.id,
+        user_id: owner_id,
         record_id: Some(updated_event.id),
-        data: Some(serde_json::to_value(&CalendarEventResponse::from(updated_event.clone())).unwrap_or_default()),
+        data: Some(serde_json::to_value(CalendarEventResponse::from(updated_event.clone())).unwrap_or_default()),
     };
-    app_state.ws_state.broadcast_to_user(&auth_user.0.id, ws_message, connection_id).await;
+    let audience = event_audience(&app_state.db.connection, owner_id, [updated_event.calendar_id]).await?;
+    app_state.ws_state.broadcast_many_to_users(&audience, vec![ws_message], connection_id).await;
 
-    Ok(Json(ApiResponse::with_message(updated_event.into(), "Calendar event updated successfully")))
+    let message = if task_id.is_some() { "Calendar event linked successfully" } else { "Calendar event unlinked successfully" };
+    Ok(Json(ApiResponse::with_message(updated_event.into(), message)))
+}
+
+pub async fn link_task(
+    State(app_state): State<AppState>,
+    auth_user: AuthUser,
+    OriginConnection(connection_id): OriginConnection,
+    Path(id): Path<Uuid>,
+    ValidatedJson(request): ValidatedJson<LinkTaskRequest>,
+) -> Result<Json<ApiResponse<CalendarEventResponse>>> {
+    set_linked_task(app_state, auth_user, connection_id, id, Some(request.task_id)).await
+}
+
+pub async fn unlink_task(
+    State(app_state): State<AppState>,
+    auth_user: AuthUser,
+    OriginConnection(connection_id): OriginConnection,
+    Path(id): Path<Uuid>,
+) -> Result<Json<ApiResponse<CalendarEventResponse>>> {
+    set_linked_task(app_state, auth_user, connection_id, id, None).await
 }
 
 pub async fn delete_event(
     State(app_state): State<AppState>,
     auth_user: AuthUser,
-    headers: HeaderMap,
+    OriginConnection(connection_id): OriginConnection,
     Path(id): Path<Uuid>,
+    preconditions: Preconditions,
+    Query(query): Query<DeleteQuery>,
 ) -> Result<Json<ApiResponse<()>>> {
-    let connection_id = extract_connection_id(&headers);
-    
-    let result = CalendarEvents::delete_by_id(id)
+    let permanent = query.permanent.unwrap_or(false);
+    let scope = event_scope(&app_state.db.connection, auth_user.0.id, ProjectRole::Editor).await?;
+    let mut trashed_overrides = Vec::new();
+
+    let (owner_id, calendar_id) = if permanent {
+        let txn = app_state.db.connection.begin().await
+            .map_err(|e| crate::errors::AppError::Database(e.into()))?;
+
+        let event = CalendarEvents::find_by_id(id)
+            .filter(scope)
+            .one(&txn)
+            .await
+            .map_err(|e| crate::errors::AppError::Database(e.into()))?
+            .ok_or_else(|| crate::errors::AppError::NotFound("Calendar event not found".to_string()))?;
+        preconditions.check(&event.updated_at)?;
+        ensure_editable(&event)?;
+
+        CalendarEvents::delete_by_id(id)
+            .filter(calendar_events::Column::UserId.eq(event.user_id))
+            .exec(&txn)
+            .await
+            .map_err(|e| crate::errors::AppError::Database(e.into()))?;
+
+        record_deletion(&txn, event.user_id, "calendar_events", id).await?;
+
+        txn.commit().await
+            .map_err(|e| crate::errors::AppError::Database(e.into()))?;
+        (event.user_id, event.calendar_id)
+    } else {
+        let event = CalendarEvents::find_by_id(id)
+            .filter(scope)
+            .filter(calendar_events::Column::DeletedAt.is_null())
+            .one(&app_state.db.connection)
+            .await
+            .map_err(|e| crate::errors::AppError::Database(e.into()))?
+            .ok_or_else(|| crate::errors::AppError::NotFound("Calendar event not found".to_string()))?;
+        preconditions.check(&event.updated_at)?;
+        ensure_editable(&event)?;
+        let (owner_id, calendar_id) = (event.user_id, event.calendar_id);
+
+        // Overrides of a recurring event's occurrences go to the trash with it
+        let txn = app_state.db.connection.begin().await
+            .map_err(|e| crate::errors::AppError::Database(e.into()))?;
+
+        let deleted_at: prelude::DateTimeWithTimeZone = chrono::Utc::now().into();
+        trashed_overrides = trash_overrides(&txn, event.id, None, deleted_at).await?;
+        record_revision(&txn, auth_user.0.id, RevisionAction::Delete, &event).await?;
+
+        let mut event_active: calendar_events::ActiveModel = event.into();
+        event_active.deleted_at = Set(Some(deleted_at));
+        event_active.update(&txn).await
+            .map_err(|e| crate::errors::AppError::Database(e.into()))?;
+
+        txn.commit().await
+            .map_err(|e| crate::errors::AppError::Database(e.into()))?;
+        (owner_id, calendar_id)
+    };
+
+    // Broadcast websocket message for calendar event deletion to everyone who shares the calendar
+    tracing::info!("Calendar event deleted, broadcasting websocket message for user {} (excluding connection {:?})", owner_id, connection_id);
+    let ws_messages = std::iter::once(id)
+        .chain(trashed_overrides)
+        .map(|record_id| WebSocketMessage {
+            event_type: "DELETE".to_string(),
+            table: "calendar_events".to_string(),
+            user_id: owner_id,
+            record_id: Some(record_id),
+            data: None,
+        })
+        .collect();
+    let audience = event_audience(&app_state.db.connection, owner_id, [calendar_id]).await?;
+    app_state.ws_state.broadcast_many_to_users(&audience, ws_messages, connection_id).await;
+
+    let message = if permanent { "Calendar event deleted successfully" } else { "Calendar event moved to trash" };
+    Ok(Json(ApiResponse::with_message((), message)))
+}
+
+pub async fn restore_event(
+    State(app_state): State<AppState>,
+    auth_user: AuthUser,
+    OriginConnection(connection_id): OriginConnection,
+    Path(id): Path<Uuid>,
+) -> Result<Json<ApiResponse<CalendarEventResponse>>> {
+    let event = CalendarEvents::find_by_id(id)
         .filter(calendar_events::Column::UserId.eq(auth_user.0.id))
-        .exec(&app_state.db.connection)
+        .filter(calendar_events::Column::DeletedAt.is_not_null())
+        .one(&app_state.db.connection)
         .await
+        .map_err(|e| crate::errors::AppError::Database(e.into()))?
+        .ok_or_else(|| crate::errors::AppError::NotFound("Calendar event not found in trash".to_string()))?;
+    // Feed events are trashed and restored together with their calendar
+    ensure_editable(&event)?;
+
+    // The event's calendar may have been trashed or purged in the meantime; detach it
+    let calendar_active = match event.calendar_id {
+        Some(calendar_id) => calendar_is_active(&app_state.db.connection, auth_user.0.id, calendar_id).await?,
+        None => true,
+    };
+
+    let txn = app_state.db.connection.begin().await
         .map_err(|e| crate::errors::AppError::Database(e.into()))?;
 
-    if result.rows_affected == 0 {
-        return Err(crate::errors::AppError::NotFound("Calendar event not found".to_string()));
+    let restored_overrides = match event.deleted_at {
+        Some(deleted_at) => restore_overrides(&txn, event.id, deleted_at).await?,
+        None => Vec::new(),
+    };
+
+    let mut event_active: calendar_events::ActiveModel = event.into();
+    event_active.deleted_at = Set(None);
+    if !calendar_active {
+        event_active.calendar_id = Set(None);
     }
 
-    // Broadcast websocket message for calendar event deletion
-    tracing::info!("Calendar event deleted, broadcasting websocket message for user {} (excluding connection {:?})", auth_user.0.id, connection_id);
-    let ws_message = WebSocketMessage {
-        event_type: "DELETE".to_string(),
-        table: "calendar_events".to_string(),
-        user_id: auth_user.0.id,
-        record_id: Some(id),
-        data: None,
-    };
-    app_state.ws_state.broadcast_to_user(&auth_user.0.id, ws_message, connection_id).await;
+    let restored_event = event_active.update(&txn).await
+        .map_err(|e| crate::errors::AppError::Database(e.into()))?;
+    reschedule_event_reminders(&txn, &restored_event).await?;
+
+    txn.commit().await
+        .map_err(|e| crate::errors::AppError::Database(e.into()))?;
+
+    // Broadcast websocket message for calendar event restore; clients dropped the record on delete
+    tracing::info!("Calendar event restored, broadcasting websocket message for user {} (excluding connection {:?})", auth_user.0.id, connection_id);
+    let ws_messages = std::iter::once(&restored_event)
+        .chain(&restored_overrides)
+        .map(|event| WebSocketMessage {
+            event_type: "INSERT".to_string(),
+            table: "calendar_events".to_string(),
+            user_id: auth_user.0.id,
+            record_id: Some(event.id),
+            data: Some(serde_json::to_value(CalendarEventResponse::from(event.clone())).unwrap_or_default()),
+        })
+        .collect();
+    let audience = event_audience(&app_state.db.connection, auth_user.0.id, [restored_event.calendar_id]).await?;
+    app_state.ws_state.broadcast_many_to_users(&audience, ws_messages, connection_id).await;
 
-    Ok(Json(ApiResponse::with_message((), "Calendar event deleted successfully")))
+    Ok(Json(ApiResponse::with_message(restored_event.into(), "Calendar event restored successfully")))
 }
@@ -0,0 +1,214 @@
+use axum::{
+    extract::{Path, Query, State},
+    response::Json,
+};
+use sea_orm::*;
+use uuid::Uuid;
+
+use crate::{
+    entities::{prelude::*, calendar_subscriptions, calendars},
+    errors::{AppError, Result},
+    handlers::calendars::purge_calendar,
+    jobs,
+    middleware::{auth::AuthUser, connection::OriginConnection, preconditions::Preconditions, validation::ValidatedJson},
+    models::{
+        calendar::CalendarResponse,
+        calendar_subscription::{
+            CalendarSubscriptionResponse, CreateCalendarSubscriptionRequest, UpdateCalendarSubscriptionRequest,
+        },
+        ApiResponse, PageQuery,
+    },
+    quota::QuotaTable,
+    state::AppState,
+    websocket::WebSocketMessage,
+};
+
+fn subscription_message(event_type: &str, subscription: &calendar_subscriptions::Model) -> WebSocketMessage {
+    WebSocketMessage {
+        event_type: event_type.to_string(),
+        table: "calendar_subscriptions".to_string(),
+        user_id: subscription.user_id,
+        record_id: Some(subscription.id),
+        data: Some(serde_json::to_value(CalendarSubscriptionResponse::from(subscription.clone())).unwrap_or_default()),
+    }
+}
+
+async fn find_subscription<C: ConnectionTrait>(db: &C, user_id: Uuid, id: Uuid) -> Result<calendar_subscriptions::Model> {
+    CalendarSubscriptions::find_by_id(id)
+        .filter(calendar_subscriptions::Column::UserId.eq(user_id))
+        .one(db)
+        .await
+        .map_err(|e| AppError::Database(e.into()))?
+        .ok_or_else(|| AppError::NotFound("Calendar subscription not found".to_string()))
+}
+
+pub async fn list_subscriptions(
+    State(app_state): State<AppState>,
+    auth_user: AuthUser,
+    Query(paging): Query<PageQuery>,
+) -> Result<Json<ApiResponse<Vec<CalendarSubscriptionResponse>>>> {
+    let find = CalendarSubscriptions::find()
+        .filter(calendar_subscriptions::Column::UserId.eq(auth_user.0.id))
+        .order_by_asc(calendar_subscriptions::Column::CreatedAt);
+    let (subscriptions, meta) = paging.fetch(&app_state.db.connection, find).await?;
+
+    Ok(Json(ApiResponse::list(subscriptions.into_iter().map(Into::into).collect(), meta)))
+}
+
+pub async fn get_subscription(
+    State(app_state): State<AppState>,
+    auth_user: AuthUser,
+    Path(id): Path<Uuid>,
+) -> Result<Json<ApiResponse<CalendarSubscriptionResponse>>> {
+    let subscription = find_subscription(&app_state.db.connection, auth_user.0.id, id).await?;
+    Ok(Json(ApiResponse::new(subscription.into())))
+}
+
+/// Subscribe to a feed: creates the read-only calendar it fills and fetches it right away
+pub async fn create_subscription(
+    State(app_state): State<AppState>,
+    auth_user: AuthUser,
+    OriginConnection(connection_id): OriginConnection,
+    ValidatedJson(request): ValidatedJson<CreateCalendarSubscriptionRequest>,
+) -> Result<Json<ApiResponse<CalendarSubscriptionResponse>>> {
+    app_state.quotas.enforce(&app_state.db.connection, auth_user.0.id, QuotaTable::Calendars, 1).await?;
+
+    let txn = app_state.db.connection.begin().await
+        .map_err(|e| AppError::Database(e.into()))?;
+
+    let mut calendar_active = calendars::ActiveModel::new();
+    calendar_active.user_id = Set(auth_user.0.id);
+    calendar_active.encrypted_data = Set(request.encrypted_data.clone());
+    calendar_active.iv = Set(request.iv.clone());
+    calendar_active.salt = Set(request.salt.clone());
+    calendar_active.is_read_only = Set(true);
+    let calendar = calendar_active.insert(&txn).await
+        .map_err(|e| AppError::Database(e.into()))?;
+
+    let subscription = request
+        .subscription_active_model(auth_user.0.id, calendar.id)?
+        .insert(&txn)
+        .await
+        .map_err(|e| AppError::Database(e.into()))?;
+
+    txn.commit().await
+        .map_err(|e| AppError::Database(e.into()))?;
+
+    // Broadcast websocket messages for the new calendar and its subscription
+    tracing::info!("Calendar subscription created, broadcasting websocket message for user {} (excluding connection {:?})", auth_user.0.id, connection_id);
+    let ws_messages = vec![
+        WebSocketMessage {
+            event_type: "INSERT".to_string(),
+            table: "calendars".to_string(),
+            user_id: auth_user.0.id,
+            record_id: Some(calendar.id),
+            data: Some(serde_json::to_value(CalendarResponse::from(calendar)).unwrap_or_default()),
+        },
+        subscription_message("INSERT", &subscription),
+    ];
+    app_state.ws_state.broadcast_many_to_user(&auth_user.0.id, ws_messages, connection_id).await;
+
+    // The first import arrives over the websocket once the feed is fetched
+    let (db, ws_state, id) = (app_state.db.clone(), app_state.ws_state.clone(), subscription.id);
+    tokio::spawn(async move {
+        if let Err(e) = jobs::calendar_subscriptions::refresh(&db, &ws_state, id).await {
+            tracing::error!("Refreshing calendar subscription {} failed: {}", id, e);
+        }
+    });
+
+    Ok(Json(ApiResponse::with_message(subscription.into(), "Calendar subscription created successfully")))
+}
+
+pub async fn update_subscription(
+    State(app_state): State<AppState>,
+    auth_user: AuthUser,
+    OriginConnection(connection_id): OriginConnection,
+    Path(id): Path<Uuid>,
+    preconditions: Preconditions,
+    ValidatedJson(request): ValidatedJson<UpdateCalendarSubscriptionRequest>,
+) -> Result<Json<ApiResponse<CalendarSubscriptionResponse>>> {
+    let user_id = auth_user.0.id;
+    let subscription = app_state.db.with_txn(|txn| Box::pin(async move {
+        let subscription = find_subscription(txn, user_id, id).await?;
+        preconditions.check(&subscription.updated_at)?;
+
+        let mut subscription_active: calendar_subscriptions::ActiveModel = subscription.into();
+        request.apply_to(&mut subscription_active)?;
+        subscription_active.update(txn).await
+            .map_err(|e| AppError::Database(e.into()))
+    })).await?;
+
+    // Broadcast websocket message for calendar subscription update
+    tracing::info!("Calendar subscription updated, broadcasting websocket message for user {} (excluding connection {:?})", auth_user.0.id, connection_id);
+    app_state.ws_state
+        .broadcast_to_user(&auth_user.0.id, subscription_message("UPDATE", &subscription), connection_id)
+        .await;
+
+    Ok(Json(ApiResponse::with_message(subscription.into(), "Calendar subscription updated successfully")))
+}
+
+/// Unsubscribe: the subscription's calendar and its imported events are deleted permanently
+pub async fn delete_subscription(
+    State(app_state): State<AppState>,
+    auth_user: AuthUser,
+    OriginConnection(connection_id): OriginConnection,
+    Path(id): Path<Uuid>,
+    preconditions: Preconditions,
+) -> Result<Json<ApiResponse<()>>> {
+    let txn = app_state.db.connection.begin().await
+        .map_err(|e| AppError::Database(e.into()))?;
+
+    let subscription = find_subscription(&txn, auth_user.0.id, id).await?;
+    preconditions.check(&subscription.updated_at)?;
+    // The subscription goes with its calendar through the FK cascade
+    let event_ids = purge_calendar(&txn, auth_user.0.id, subscription.calendar_id).await?;
+
+    txn.commit().await
+        .map_err(|e| AppError::Database(e.into()))?;
+
+    // Broadcast websocket messages for the subscription, its calendar and events
+    tracing::info!("Calendar subscription deleted, broadcasting websocket message for user {} (excluding connection {:?})", auth_user.0.id, connection_id);
+    let ws_messages = [
+        ("calendar_subscriptions", subscription.id),
+        ("calendars", subscription.calendar_id),
+    ]
+    .into_iter()
+    .chain(event_ids.into_iter().map(|event_id| ("calendar_events", event_id)))
+    .map(|(table, record_id)| WebSocketMessage {
+        event_type: "DELETE".to_string(),
+        table: table.to_string(),
+        user_id: auth_user.0.id,
+        record_id: Some(record_id),
+        data: None,
+    })
+    .collect();
+    app_state.ws_state.broadcast_many_to_user(&auth_user.0.id, ws_messages, connection_id).await;
+
+    Ok(Json(ApiResponse::with_message((), "Calendar subscription deleted successfully")))
+}
+
+/// Fetch the feed now instead of waiting for the next scheduled refresh
+pub async fn refresh_subscription(
+    State(app_state): State<AppState>,
+    auth_user: AuthUser,
+    Path(id): Path<Uuid>,
+) -> Result<Json<ApiResponse<CalendarSubscriptionResponse>>> {
+    let subscription = find_subscription(&app_state.db.connection, auth_user.0.id, id).await?;
+
+    let calendar_trashed = Calendars::find_by_id(subscription.calendar_id)
+        .filter(calendars::Column::DeletedAt.is_not_null())
+        .one(&app_state.db.connection)
+        .await
+        .map_err(|e| AppError::Database(e.into()))?
+        .is_some();
+    if calendar_trashed {
+        return Err(AppError::Conflict("The subscription's calendar is in the trash".to_string()));
+    }
+
+    let subscription = jobs::calendar_subscriptions::refresh(&app_state.db, &app_state.ws_state, subscription.id).await?;
+    let message = match &subscription.last_error {
+        Some(_) => "Calendar subscription could not be refreshed",
+        None => "Calendar subscription refreshed successfully",
+    };
+    Ok(Json(ApiResponse::with_message(subscription.into(), message)))
+}
@@ -1,43 +1,165 @@
 use axum::{
-    extract::{Path, State},
-    http::HeaderMap,
+    extract::{Path, Query, State},
     response::Json,
 };
-use sea_orm::*;
+use sea_orm::{sea_query::Expr, *};
+use serde::Deserialize;
 use uuid::Uuid;
 
 use crate::{
-    entities::{prelude::*, calendars},
+    db::lock_siblings,
+    entities::{prelude::*, calendar_events, calendars, project_members::ProjectRole, record_revisions::RevisionAction},
     errors::Result,
-    middleware::auth::AuthUser,
+    handlers::{
+        organizations::{ensure_shareable, organization_audience, organization_ids, organization_project_role},
+        revisions::{find_revision, list_revisions, record_revision},
+        sync::record_deletion,
+        trash::DeleteQuery,
+    },
+    middleware::{auth::AuthUser, connection::OriginConnection, preconditions::Preconditions, validation::ValidatedJson},
     models::{
         calendar::{CreateCalendarRequest, UpdateCalendarRequest, CalendarResponse},
-        ApiResponse,
+        calendar_event::CalendarEventResponse,
+        revision::{RevisionResponse, Revisioned},
+        ApiResponse, PageQuery, ApplyTo, IntoRecord, FieldsQuery, ReorderRequest, validate_schema_version,
     },
+    quota::QuotaTable,
     state::AppState,
     websocket::WebSocketMessage,
 };
 
-fn extract_connection_id(headers: &HeaderMap) -> Option<Uuid> {
-    headers
-        .get("x-connection-id")
-        .and_then(|v| v.to_str().ok())
-        .and_then(|s| Uuid::parse_str(s).ok())
+/// Calendars the user may access with at least `min_role`: their own and those shared with them
+/// through an organization
+pub(crate) async fn calendar_scope<C: ConnectionTrait>(db: &C, user_id: Uuid, min_role: ProjectRole) -> Result<Condition> {
+    let organization_ids = organization_ids(db, user_id, min_role).await?;
+    Ok(Condition::any()
+        .add(calendars::Column::UserId.eq(user_id))
+        .add(calendars::Column::OrganizationId.is_in(organization_ids)))
+}
+
+/// An active calendar the user may access, with their rights in it. Calendars of other users are
+/// only found when they're shared with an organization the user belongs to.
+pub(crate) async fn accessible_calendar<C: ConnectionTrait>(
+    db: &C,
+    user_id: Uuid,
+    calendar_id: Uuid,
+) -> Result<(calendars::Model, ProjectRole)> {
+    let calendar = Calendars::find_by_id(calendar_id)
+        .filter(calendars::Column::DeletedAt.is_null())
+        .one(db)
+        .await
+        .map_err(|e| crate::errors::AppError::Database(e.into()))?
+        .ok_or_else(|| crate::errors::AppError::NotFound("Calendar not found".to_string()))?;
+    if calendar.user_id == user_id {
+        return Ok((calendar, ProjectRole::Owner));
+    }
+
+    let role = organization_project_role(db, user_id, calendar.organization_id)
+        .await?
+        .ok_or_else(|| crate::errors::AppError::NotFound("Calendar not found".to_string()))?;
+    Ok((calendar, role))
+}
+
+/// Move a calendar and its events to the trash under one shared `deleted_at`
+/// so restore can bring them back together. Returns the ids of the trashed events.
+pub(crate) async fn trash_calendar<C: ConnectionTrait>(
+    db: &C,
+    user_id: Uuid,
+    calendar_id: Uuid,
+) -> Result<Vec<Uuid>> {
+    let now = chrono::Utc::now();
+
+    let event_ids: Vec<Uuid> = CalendarEvents::find()
+        .select_only()
+        .column(calendar_events::Column::Id)
+        .filter(calendar_events::Column::UserId.eq(user_id))
+        .filter(calendar_events::Column::CalendarId.eq(calendar_id))
+        .filter(calendar_events::Column::DeletedAt.is_null())
+        .into_tuple()
+        .all(db)
+        .await
+        .map_err(|e| crate::errors::AppError::Database(e.into()))?;
+
+    Calendars::update_many()
+        .col_expr(calendars::Column::DeletedAt, Expr::value(now))
+        .col_expr(calendars::Column::UpdatedAt, Expr::value(now))
+        .filter(calendars::Column::UserId.eq(user_id))
+        .filter(calendars::Column::Id.eq(calendar_id))
+        .exec(db)
+        .await
+        .map_err(|e| crate::errors::AppError::Database(e.into()))?;
+
+    CalendarEvents::update_many()
+        .col_expr(calendar_events::Column::DeletedAt, Expr::value(now))
+        .col_expr(calendar_events::Column::UpdatedAt, Expr::value(now))
+        .filter(calendar_events::Column::Id.is_in(event_ids.clone()))
+        .exec(db)
+        .await
+        .map_err(|e| crate::errors::AppError::Database(e.into()))?;
+
+    Ok(event_ids)
+}
+
+/// Permanently delete a calendar together with its events (and its subscription, if any)
+/// and record the tombstones for sync. Returns the ids of the deleted events.
+pub(crate) async fn purge_calendar<C: ConnectionTrait>(
+    db: &C,
+    user_id: Uuid,
+    calendar_id: Uuid,
+) -> Result<Vec<Uuid>> {
+    let event_ids: Vec<Uuid> = CalendarEvents::find()
+        .select_only()
+        .column(calendar_events::Column::Id)
+        .filter(calendar_events::Column::UserId.eq(user_id))
+        .filter(calendar_events::Column::CalendarId.eq(calendar_id))
+        .into_tuple()
+        .all(db)
+        .await
+        .map_err(|e| crate::errors::AppError::Database(e.into()))?;
+
+    // Events are removed by the FK cascade
+    Calendars::delete_by_id(calendar_id)
+        .filter(calendars::Column::UserId.eq(user_id))
+        .exec(db)
+        .await
+        .map_err(|e| crate::errors::AppError::Database(e.into()))?;
+
+    record_deletion(db, user_id, "calendars", calendar_id).await?;
+    for event_id in &event_ids {
+        record_deletion(db, user_id, "calendar_events", *event_id).await?;
+    }
+    Ok(event_ids)
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CalendarQuery {
+    /// `true` for pinned calendars only, `false` for the rest
+    pub pinned: Option<bool>,
 }
 
 pub async fn list_calendars(
     State(app_state): State<AppState>,
     auth_user: AuthUser,
-) -> Result<Json<ApiResponse<Vec<CalendarResponse>>>> {
-    let calendars = Calendars::find()
-        .filter(calendars::Column::UserId.eq(auth_user.0.id))
-        .order_by_asc(calendars::Column::CreatedAt)
-        .all(&app_state.db.connection)
-        .await
-        .map_err(|e| crate::errors::AppError::Database(e.into()))?;
+    Query(query): Query<CalendarQuery>,
+    Query(fields): Query<FieldsQuery>,
+    Query(paging): Query<PageQuery>,
+) -> Result<Json<ApiResponse<Vec<serde_json::Value>>>> {
+    let scope = calendar_scope(app_state.db.reader(), auth_user.0.id, ProjectRole::Viewer).await?;
+    let mut find = Calendars::find()
+        .filter(scope)
+        .filter(calendars::Column::DeletedAt.is_null());
+
+    if let Some(pinned) = query.pinned {
+        find = find.filter(calendars::Column::IsPinned.eq(pinned));
+    }
+
+    let find = find
+        .order_by_asc(calendars::Column::DisplayOrder)
+        .order_by_asc(calendars::Column::CreatedAt);
+    let (calendars, meta) = paging.fetch(app_state.db.reader(), find).await?;
 
     let response: Vec<CalendarResponse> = calendars.into_iter().map(|calendar| calendar.into()).collect();
-    Ok(Json(ApiResponse::new(response)))
+    Ok(Json(ApiResponse::list(fields.select(response)?, meta)))
 }
 
 pub async fn get_calendar(
@@ -45,12 +167,7 @@ pub async fn get_calendar(
     auth_user: AuthUser,
     Path(id): Path<Uuid>,
 ) -> Result<Json<ApiResponse<CalendarResponse>>> {
-    let calendar = Calendars::find_by_id(id)
-        .filter(calendars::Column::UserId.eq(auth_user.0.id))
-        .one(&app_state.db.connection)
-        .await
-        .map_err(|e| crate::errors::AppError::Database(e.into()))?
-        .ok_or_else(|| crate::errors::AppError::NotFound("Calendar not found".to_string()))?;
+    let (calendar, _) = accessible_calendar(app_state.db.reader(), auth_user.0.id, id).await?;
 
     Ok(Json(ApiResponse::new(calendar.into())))
 }
@@ -58,30 +175,29 @@ pub async fn get_calendar(
 pub async fn create_calendar(
     State(app_state): State<AppState>,
     auth_user: AuthUser,
-    headers: HeaderMap,
-    Json(request): Json<CreateCalendarRequest>,
+    OriginConnection(connection_id): OriginConnection,
+    ValidatedJson(request): ValidatedJson<CreateCalendarRequest>,
 ) -> Result<Json<ApiResponse<CalendarResponse>>> {
-    let connection_id = extract_connection_id(&headers);
-    
-    let mut calendar_active = calendars::ActiveModel::new();
-    calendar_active.user_id = Set(auth_user.0.id);
-    calendar_active.encrypted_data = Set(request.encrypted_data);
-    calendar_active.iv = Set(request.iv);
-    calendar_active.salt = Set(request.salt);
+    validate_schema_version("calendars", request.schema_version)?;
+    ensure_shareable(&app_state.db.connection, auth_user.0.id, request.organization_id).await?;
+    app_state.quotas.enforce(&app_state.db.connection, auth_user.0.id, QuotaTable::Calendars, 1).await?;
+    let client_id = request.id;
+    let calendar_active = request.into_active_model(auth_user.0.id);
 
     let calendar = calendar_active.insert(&app_state.db.connection).await
-        .map_err(|e| crate::errors::AppError::Database(e.into()))?;
+        .map_err(|e| crate::errors::AppError::from_insert(e, client_id))?;
 
-    // Broadcast websocket message for calendar creation
+    // Broadcast websocket message for calendar creation, to the organization it's shared with too
     tracing::info!("Calendar created, broadcasting websocket message for user {} (excluding connection {:?})", auth_user.0.id, connection_id);
     let ws_message = WebSocketMessage {
         event_type: "INSERT".to_string(),
         table: "calendars".to_string(),
         user_id: auth_user.0.id,
         record_id: Some(calendar.id),
-        data: Some(serde_json::to_value(&CalendarResponse::from(calendar.clone())).unwrap_or_default()),
+        data: Some(serde_json::to_value(CalendarResponse::from(calendar.clone())).unwrap_or_default()),
     };
-    app_state.ws_state.broadcast_to_user(&auth_user.0.id, ws_message, connection_id).await;
+    let audience = organization_audience(&app_state.db.connection, auth_user.0.id, [calendar.organization_id]).await?;
+    app_state.ws_state.broadcast_many_to_users(&audience, vec![ws_message], connection_id).await;
 
     Ok(Json(ApiResponse::with_message(calendar.into(), "Calendar created successfully")))
 }
@@ -89,79 +205,384 @@ pub async fn create_calendar(
 pub async fn update_calendar(
     State(app_state): State<AppState>,
     auth_user: AuthUser,
-    headers: HeaderMap,
+    OriginConnection(connection_id): OriginConnection,
     Path(id): Path<Uuid>,
-    Json(request): Json<UpdateCalendarRequest>,
+    preconditions: Preconditions,
+    ValidatedJson(request): ValidatedJson<UpdateCalendarRequest>,
 ) -> Result<Json<ApiResponse<CalendarResponse>>> {
-    let connection_id = extract_connection_id(&headers);
-    
-    let calendar = Calendars::find_by_id(id)
-        .filter(calendars::Column::UserId.eq(auth_user.0.id))
-        .one(&app_state.db.connection)
-        .await
-        .map_err(|e| crate::errors::AppError::Database(e.into()))?
-        .ok_or_else(|| crate::errors::AppError::NotFound("Calendar not found".to_string()))?;
+    validate_schema_version("calendars", request.schema_version)?;
+    let user_id = auth_user.0.id;
+    let (updated_calendar, previous_organization_id) = app_state.db.with_txn(|txn| Box::pin(async move {
+        let (calendar, role) = accessible_calendar(txn, user_id, id).await?;
+        preconditions.check(&calendar.updated_at)?;
+        let owner_id = calendar.user_id;
+        if owner_id != user_id {
+            // Others with owner rights look after the calendar itself; its flags and who it's shared with stay with its user
+            if role < ProjectRole::Owner {
+                return Err(crate::errors::AppError::Forbidden("Only owners can change a shared calendar".to_string()));
+            }
+            if request.is_default.is_some()
+                || request.is_pinned.is_some()
+                || request.display_order.is_some()
+                || request.organization_id.is_some()
+            {
+                return Err(crate::errors::AppError::Forbidden(
+                    "Only the calendar's own user can pin, reorder, share or make it the default".to_string(),
+                ));
+            }
+        }
+        if let Some(organization_id) = request.organization_id {
+            ensure_shareable(txn, owner_id, organization_id).await?;
+        }
+        let previous_organization_id = calendar.organization_id;
 
-    let mut calendar_active: calendars::ActiveModel = calendar.into();
-    
-    if let Some(encrypted_data) = request.encrypted_data {
-        calendar_active.encrypted_data = Set(encrypted_data);
-    }
-    if let Some(iv) = request.iv {
-        calendar_active.iv = Set(iv);
-    }
-    if let Some(salt) = request.salt {
-        calendar_active.salt = Set(salt);
-    }
-    if let Some(is_default) = request.is_default {
-        calendar_active.is_default = Set(is_default);
-    }
+        record_revision(txn, user_id, RevisionAction::Update, &calendar).await?;
+        let mut calendar_active: calendars::ActiveModel = calendar.into();
+        request.apply_to(&mut calendar_active);
 
-    let updated_calendar = calendar_active.update(&app_state.db.connection).await
-        .map_err(|e| crate::errors::AppError::Database(e.into()))?;
+        let updated_calendar = calendar_active.update(txn).await
+            .map_err(|e| crate::errors::AppError::Database(e.into()))?;
+        Ok((updated_calendar, previous_organization_id))
+    })).await?;
+    let owner_id = updated_calendar.user_id;
 
-    // Broadcast websocket message for calendar update
-    tracing::info!("Calendar updated, broadcasting websocket message for user {} (excluding connection {:?})", auth_user.0.id, connection_id);
+    // Broadcast websocket message for calendar update, to everyone it is or just stopped being shared with
+    tracing::info!("Calendar updated, broadcasting websocket message for user {} (excluding connection {:?})", owner_id, connection_id);
     let ws_message = WebSocketMessage {
         event_type: "UPDATE".to_string(),
         table: "calendars".to_string(),
-        user_id: auth_user.0.id,
+        user_id: owner_id,
         record_id: Some(updated_calendar.id),
-        data: Some(serde_json::to_value(&CalendarResponse::from(updated_calendar.clone())).unwrap_or_default()),
+        data: Some(serde_json::to_value(CalendarResponse::from(updated_calendar.clone())).unwrap_or_default()),
     };
-    app_state.ws_state.broadcast_to_user(&auth_user.0.id, ws_message, connection_id).await;
+    let audience = organization_audience(
+        &app_state.db.connection,
+        owner_id,
+        [updated_calendar.organization_id, previous_organization_id],
+    )
+    .await?;
+    app_state.ws_state.broadcast_many_to_users(&audience, vec![ws_message], connection_id).await;
 
     Ok(Json(ApiResponse::with_message(updated_calendar.into(), "Calendar updated successfully")))
 }
 
+/// Rewrite `display_order` for the user's calendars in one transaction. Calendars left out of the
+/// request keep their relative order after the listed ones, so no two share a position.
+pub async fn reorder_calendars(
+    State(app_state): State<AppState>,
+    auth_user: AuthUser,
+    OriginConnection(connection_id): OriginConnection,
+    ValidatedJson(request): ValidatedJson<ReorderRequest>,
+) -> Result<Json<ApiResponse<Vec<CalendarResponse>>>> {
+    let user_id = auth_user.0.id;
+    let (reordered, changed_ids) = app_state.db.with_txn(|txn| Box::pin(async move {
+        // Concurrent reorders take turns instead of interleaving; rows are locked in id order
+        lock_siblings(txn, "calendars", [(user_id, None)]).await?;
+        let mut calendars = Calendars::find()
+            .filter(calendars::Column::UserId.eq(user_id))
+            .filter(calendars::Column::DeletedAt.is_null())
+            .order_by_asc(calendars::Column::Id)
+            .lock_exclusive()
+            .all(txn)
+            .await
+            .map_err(|e| crate::errors::AppError::Database(e.into()))?;
+        calendars.sort_by_key(|c| (c.display_order, c.created_at));
+
+        let mut ordered = Vec::with_capacity(calendars.len());
+        for id in &request.ids {
+            let index = calendars.iter().position(|c| c.id == *id)
+                .ok_or_else(|| crate::errors::AppError::NotFound("Calendar not found".to_string()))?;
+            ordered.push(calendars.remove(index));
+        }
+        ordered.append(&mut calendars);
+
+        let mut reordered = Vec::with_capacity(ordered.len());
+        let mut changed_ids = Vec::new();
+        for (index, calendar) in ordered.into_iter().enumerate() {
+            let display_order = ReorderRequest::position(index);
+
+            // Untouched calendars keep their `updated_at` so other clients don't refetch them
+            if calendar.display_order == display_order {
+                reordered.push(calendar);
+                continue;
+            }
+
+            let mut calendar_active: calendars::ActiveModel = calendar.into();
+            calendar_active.display_order = Set(display_order);
+            let updated_calendar = calendar_active.update(txn).await
+                .map_err(|e| crate::errors::AppError::Database(e.into()))?;
+            changed_ids.push(updated_calendar.id);
+            reordered.push(updated_calendar);
+        }
+        Ok((reordered, changed_ids))
+    })).await?;
+
+    // Broadcast websocket messages for every calendar whose position changed, to the organizations they're shared with too
+    tracing::info!("Calendars reordered, broadcasting websocket messages for user {} (excluding connection {:?})", user_id, connection_id);
+    let changed: Vec<&calendars::Model> = reordered.iter().filter(|c| changed_ids.contains(&c.id)).collect();
+    let ws_messages = changed
+        .iter()
+        .map(|calendar| WebSocketMessage {
+            event_type: "UPDATE".to_string(),
+            table: "calendars".to_string(),
+            user_id,
+            record_id: Some(calendar.id),
+            data: Some(serde_json::to_value(CalendarResponse::from((*calendar).clone())).unwrap_or_default()),
+        })
+        .collect();
+    let audience = organization_audience(
+        &app_state.db.connection,
+        user_id,
+        changed.iter().map(|calendar| calendar.organization_id),
+    )
+    .await?;
+    app_state.ws_state.broadcast_many_to_users(&audience, ws_messages, connection_id).await;
+
+    let response: Vec<CalendarResponse> = reordered.into_iter().map(|c| c.into()).collect();
+    Ok(Json(ApiResponse::with_message(response, "Calendars reordered successfully")))
+}
+
+/// Prior versions of a calendar, newest first
+pub async fn list_calendar_revisions(
+    State(app_state): State<AppState>,
+    auth_user: AuthUser,
+    Path(id): Path<Uuid>,
+    Query(paging): Query<PageQuery>,
+) -> Result<Json<ApiResponse<Vec<RevisionResponse<CalendarResponse>>>>> {
+    let db = app_state.db.reader();
+    let (calendar, _) = accessible_calendar(db, auth_user.0.id, id).await?;
+
+    let (response, meta) = paging.page(list_revisions::<_, calendars::Model, _>(db, calendar.id).await?)?;
+    Ok(Json(ApiResponse::list(response, meta)))
+}
+
+/// Bring back a calendar's content as of one of its revisions; the version replaced becomes a revision itself
+pub async fn revert_calendar(
+    State(app_state): State<AppState>,
+    auth_user: AuthUser,
+    OriginConnection(connection_id): OriginConnection,
+    Path((id, revision_id)): Path<(Uuid, Uuid)>,
+) -> Result<Json<ApiResponse<CalendarResponse>>> {
+    let user_id = auth_user.0.id;
+    let reverted_calendar = app_state.db.with_txn(|txn| Box::pin(async move {
+        let (calendar, role) = accessible_calendar(txn, user_id, id).await?;
+        if calendar.user_id != user_id && role < ProjectRole::Owner {
+            return Err(crate::errors::AppError::Forbidden("Only owners can change a shared calendar".to_string()));
+        }
+        let prior: calendars::Model = find_revision(txn, id, revision_id).await?;
+
+        record_revision(txn, user_id, RevisionAction::Revert, &calendar).await?;
+        let mut calendar_active: calendars::ActiveModel = calendar.into();
+        calendars::Model::revert(&mut calendar_active, prior);
+        calendar_active.update(txn).await
+            .map_err(|e| crate::errors::AppError::Database(e.into()))
+    })).await?;
+    let owner_id = reverted_calendar.user_id;
+
+    // Broadcast websocket message for calendar update to everyone it is shared with
+    tracing::info!("Calendar reverted, broadcasting websocket message for user {} (excluding connection {:?})", owner_id, connection_id);
+    let ws_message = WebSocketMessage {
+        event_type: "UPDATE".to_string(),
+        table: "calendars".to_string(),
+        user_id: owner_id,
+        record_id: Some(reverted_calendar.id),
+        data: Some(serde_json::to_value(CalendarResponse::from(reverted_calendar.clone())).unwrap_or_default()),
+    };
+    let audience = organization_audience(&app_state.db.connection, owner_id, [reverted_calendar.organization_id]).await?;
+    app_state.ws_state.broadcast_many_to_users(&audience, vec![ws_message], connection_id).await;
+
+    Ok(Json(ApiResponse::with_message(reverted_calendar.into(), "Calendar reverted successfully")))
+}
+
 pub async fn delete_calendar(
     State(app_state): State<AppState>,
     auth_user: AuthUser,
-    headers: HeaderMap,
+    OriginConnection(connection_id): OriginConnection,
     Path(id): Path<Uuid>,
+    preconditions: Preconditions,
+    Query(query): Query<DeleteQuery>,
 ) -> Result<Json<ApiResponse<()>>> {
-    let connection_id = extract_connection_id(&headers);
-    
-    let result = Calendars::delete_by_id(id)
-        .filter(calendars::Column::UserId.eq(auth_user.0.id))
-        .exec(&app_state.db.connection)
-        .await
+    let permanent = query.permanent.unwrap_or(false);
+
+    let txn = app_state.db.connection.begin().await
         .map_err(|e| crate::errors::AppError::Database(e.into()))?;
 
-    if result.rows_affected == 0 {
-        return Err(crate::errors::AppError::NotFound("Calendar not found".to_string()));
+    let mut find = Calendars::find_by_id(id).filter(calendars::Column::UserId.eq(auth_user.0.id));
+    if !permanent {
+        find = find.filter(calendars::Column::DeletedAt.is_null());
     }
+    let calendar = find.one(&txn)
+        .await
+        .map_err(|e| crate::errors::AppError::Database(e.into()))?
+        .ok_or_else(|| crate::errors::AppError::NotFound("Calendar not found".to_string()))?;
+    preconditions.check(&calendar.updated_at)?;
+    let audience = organization_audience(&txn, auth_user.0.id, [calendar.organization_id]).await?;
+
+    // Events belong to their calendar and go with it
+    let event_ids = if permanent {
+        purge_calendar(&txn, auth_user.0.id, id).await?
+    } else {
+        record_revision(&txn, auth_user.0.id, RevisionAction::Delete, &calendar).await?;
+        trash_calendar(&txn, auth_user.0.id, id).await?
+    };
 
-    // Broadcast websocket message for calendar deletion
+    txn.commit().await
+        .map_err(|e| crate::errors::AppError::Database(e.into()))?;
+
+    // Broadcast websocket messages for the calendar and its events to everyone it was shared with
     tracing::info!("Calendar deleted, broadcasting websocket message for user {} (excluding connection {:?})", auth_user.0.id, connection_id);
-    let ws_message = WebSocketMessage {
+    let mut ws_messages = vec![WebSocketMessage {
         event_type: "DELETE".to_string(),
         table: "calendars".to_string(),
         user_id: auth_user.0.id,
         record_id: Some(id),
         data: None,
+    }];
+    ws_messages.extend(event_ids.into_iter().map(|event_id| WebSocketMessage {
+        event_type: "DELETE".to_string(),
+        table: "calendar_events".to_string(),
+        user_id: auth_user.0.id,
+        record_id: Some(event_id),
+        data: None,
+    }));
+    app_state.ws_state.broadcast_many_to_users(&audience, ws_messages, connection_id).await;
+
+    let message = if permanent { "Calendar deleted successfully" } else { "Calendar moved to trash" };
+    Ok(Json(ApiResponse::with_message((), message)))
+}
+
+pub async fn restore_calendar(
+    State(app_state): State<AppState>,
+    auth_user: AuthUser,
+    OriginConnection(connection_id): OriginConnection,
+    Path(id): Path<Uuid>,
+) -> Result<Json<ApiResponse<CalendarResponse>>> {
+    let txn = app_state.db.connection.begin().await
+        .map_err(|e| crate::errors::AppError::Database(e.into()))?;
+
+    let calendar = Calendars::find_by_id(id)
+        .filter(calendars::Column::UserId.eq(auth_user.0.id))
+        .filter(calendars::Column::DeletedAt.is_not_null())
+        .one(&txn)
+        .await
+        .map_err(|e| crate::errors::AppError::Database(e.into()))?
+        .ok_or_else(|| crate::errors::AppError::NotFound("Calendar not found in trash".to_string()))?;
+
+    // Only bring back the events that were trashed together with the calendar
+    let trashed_event_ids: Vec<Uuid> = CalendarEvents::find()
+        .select_only()
+        .column(calendar_events::Column::Id)
+        .filter(calendar_events::Column::UserId.eq(auth_user.0.id))
+        .filter(calendar_events::Column::CalendarId.eq(id))
+        .filter(calendar_events::Column::DeletedAt.eq(calendar.deleted_at))
+        .into_tuple()
+        .all(&txn)
+        .await
+        .map_err(|e| crate::errors::AppError::Database(e.into()))?;
+
+    let mut calendar_active: calendars::ActiveModel = calendar.into();
+    calendar_active.deleted_at = Set(None);
+
+    let restored_calendar = calendar_active.update(&txn).await
+        .map_err(|e| crate::errors::AppError::Database(e.into()))?;
+
+    CalendarEvents::update_many()
+        .col_expr(calendar_events::Column::DeletedAt, Expr::value(Option::<chrono::DateTime<chrono::Utc>>::None))
+        .col_expr(calendar_events::Column::UpdatedAt, Expr::value(chrono::Utc::now()))
+        .filter(calendar_events::Column::Id.is_in(trashed_event_ids.clone()))
+        .exec(&txn)
+        .await
+        .map_err(|e| crate::errors::AppError::Database(e.into()))?;
+
+    let restored_events = CalendarEvents::find()
+        .filter(calendar_events::Column::Id.is_in(trashed_event_ids))
+        .all(&txn)
+        .await
+        .map_err(|e| crate::errors::AppError::Database(e.into()))?;
+
+    txn.commit().await
+        .map_err(|e| crate::errors::AppError::Database(e.into()))?;
+
+    // Broadcast websocket messages for calendar restore to everyone it's shared with; clients dropped the records on delete
+    tracing::info!("Calendar restored, broadcasting websocket message for user {} (excluding connection {:?})", auth_user.0.id, connection_id);
+    let mut ws_messages = vec![WebSocketMessage {
+        event_type: "INSERT".to_string(),
+        table: "calendars".to_string(),
+        user_id: auth_user.0.id,
+        record_id: Some(restored_calendar.id),
+        data: Some(serde_json::to_value(CalendarResponse::from(restored_calendar.clone())).unwrap_or_default()),
+    }];
+    ws_messages.extend(restored_events.into_iter().map(|event| WebSocketMessage {
+        event_type: "INSERT".to_string(),
+        table: "calendar_events".to_string(),
+        user_id: auth_user.0.id,
+        record_id: Some(event.id),
+        data: Some(serde_json::to_value(CalendarEventResponse::from(event)).unwrap_or_default()),
+    }));
+    let audience = organization_audience(&app_state.db.connection, auth_user.0.id, [restored_calendar.organization_id]).await?;
+    app_state.ws_state.broadcast_many_to_users(&audience, ws_messages, connection_id).await;
+
+    Ok(Json(ApiResponse::with_message(restored_calendar.into(), "Calendar restored successfully")))
+}
+
+/// Pin or unpin a calendar; favorites are listed with `?pinned=true`
+async fn set_calendar_pinned(
+    app_state: AppState,
+    auth_user: AuthUser,
+    connection_id: Option<Uuid>,
+    id: Uuid,
+    preconditions: Preconditions,
+    is_pinned: bool,
+) -> Result<Json<ApiResponse<CalendarResponse>>> {
+    let user_id = auth_user.0.id;
+    let updated_calendar = app_state.db.with_txn(|txn| Box::pin(async move {
+        let calendar = Calendars::find_by_id(id)
+            .filter(calendars::Column::UserId.eq(user_id))
+            .filter(calendars::Column::DeletedAt.is_null())
+            .one(txn)
+            .await
+            .map_err(|e| crate::errors::AppError::Database(e.into()))?
+            .ok_or_else(|| crate::errors::AppError::NotFound("Calendar not found".to_string()))?;
+        preconditions.check(&calendar.updated_at)?;
+
+        let mut calendar_active: calendars::ActiveModel = calendar.into();
+        calendar_active.is_pinned = Set(is_pinned);
+        calendar_active.update(txn).await
+            .map_err(|e| crate::errors::AppError::Database(e.into()))
+    })).await?;
+
+    // Broadcast websocket message for the pin change
+    tracing::info!("Calendar pin changed, broadcasting websocket message for user {} (excluding connection {:?})", auth_user.0.id, connection_id);
+    let ws_message = WebSocketMessage {
+        event_type: "UPDATE".to_string(),
+        table: "calendars".to_string(),
+        user_id: auth_user.0.id,
+        record_id: Some(updated_calendar.id),
+        data: Some(serde_json::to_value(CalendarResponse::from(updated_calendar.clone())).unwrap_or_default()),
     };
-    app_state.ws_state.broadcast_to_user(&auth_user.0.id, ws_message, connection_id).await;
+    let audience = organization_audience(&app_state.db.connection, auth_user.0.id, [updated_calendar.organization_id]).await?;
+    app_state.ws_state.broadcast_many_to_users(&audience, vec![ws_message], connection_id).await;
 
-    Ok(Json(ApiResponse::with_message((), "Calendar deleted successfully")))
+    let message = if is_pinned { "Calendar pinned successfully" } else { "Calendar unpinned successfully" };
+    Ok(Json(ApiResponse::with_message(updated_calendar.into(), message)))
+}
+
+pub async fn pin_calendar(
+    State(app_state): State<AppState>,
+    auth_user: AuthUser,
+    OriginConnection(connection_id): OriginConnection,
+    Path(id): Path<Uuid>,
+    preconditions: Preconditions,
+) -> Result<Json<ApiResponse<CalendarResponse>>> {
+    set_calendar_pinned(app_state, auth_user, connection_id, id, preconditions, true).await
+}
+
+pub async fn unpin_calendar(
+    State(app_state): State<AppState>,
+    auth_user: AuthUser,
+    OriginConnection(connection_id): OriginConnection,
+    Path(id): Path<Uuid>,
+    preconditions: Preconditions,
+) -> Result<Json<ApiResponse<CalendarResponse>>> {
+    set_calendar_pinned(app_state, auth_user, connection_id, id, preconditions, false).await
 }
@@ -1,56 +1,526 @@
 use axum::{
     extract::{Path, Query, State},
-    http::HeaderMap,
     response::Json,
 };
-use sea_orm::*;
+use chrono::{DateTime, Utc};
+use sea_orm::{prelude::DateTimeWithTimeZone, sea_query::{Expr, NullOrdering}, *};
 use serde::Deserialize;
 use uuid::Uuid;
 
 use crate::{
-    entities::{prelude::*, can_do_list},
+    db::{begin_write, lock_siblings},
+    entities::{
+        prelude::*, calendar_events::{self, EventStatus}, can_do_list, project_members::ProjectRole, projects,
+        record_revisions::RevisionAction,
+    },
     errors::Result,
-    middleware::auth::AuthUser,
+    handlers::{
+        calendar_events::{event_audience, event_scope},
+        project_members::{broadcast_task_changes, shared_project_ids, task_project_owner},
+        projects::ensure_default_project,
+        reminders::reschedule_event_reminders,
+        revisions::{find_revision, list_revisions, record_revision},
+        sync::record_deletion,
+        trash::DeleteQuery,
+    },
+    middleware::{auth::AuthUser, connection::OriginConnection, preconditions::Preconditions, validation::ValidatedJson},
     models::{
-        can_do_list::{CreateCanDoItemRequest, UpdateCanDoItemRequest, CanDoItemResponse},
-        ApiResponse,
+        calendar_event::CalendarEventResponse,
+        can_do_list::{
+            parse_priority_filter, CreateCanDoItemRequest, UpdateCanDoItemRequest,
+            CanDoItemResponse, LinkedEventsResponse,
+        },
+        project::ProjectResponse,
+        revision::{RevisionResponse, Revisioned},
+        ApiResponse, PageQuery, ApplyTo, IntoRecord, FieldsQuery, ReorderRequest, validate_schema_version,
     },
+    quota::QuotaTable,
     state::AppState,
     websocket::WebSocketMessage,
 };
 
-fn extract_connection_id(headers: &HeaderMap) -> Option<Uuid> {
-    headers
-        .get("x-connection-id")
-        .and_then(|v| v.to_str().ok())
-        .and_then(|s| Uuid::parse_str(s).ok())
-}
-
 #[derive(Debug, Deserialize)]
 pub struct CanDoListQuery {
     pub project_id: Option<Uuid>,
+    /// Only items due before this instant
+    pub due_before: Option<DateTime<Utc>>,
+    /// Only items due at or after this instant
+    pub due_after: Option<DateTime<Utc>>,
+    /// `true` for items whose due date has passed, `false` for items that aren't overdue
+    pub overdue: Option<bool>,
+    pub scheduled_before: Option<DateTime<Utc>>,
+    pub scheduled_after: Option<DateTime<Utc>>,
+    /// List the subtasks of this task instead of top-level tasks
+    pub parent_task_id: Option<Uuid>,
+    /// Also return nested subtasks, at any depth
+    pub include_subtasks: Option<bool>,
+    /// Comma-separated priorities to include, e.g. `high,urgent`
+    pub priority: Option<String>,
+    /// `true` for pinned items only, `false` for the rest
+    pub pinned: Option<bool>,
+    #[serde(default)]
+    pub sort: CanDoListSort,
+    /// Reverses the sort's own direction; items without the value stay last either way
+    pub dir: Option<SortDirection>,
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CanDoListSort {
+    /// The order set by the user
+    #[default]
+    #[serde(alias = "manual")]
+    DisplayOrder,
+    /// Earliest due first; items without a due date last
+    DueAt,
+    /// Earliest scheduled first; unscheduled items last
+    ScheduledAt,
+    /// Most urgent first; items without a priority last
+    Priority,
+    /// Newest first
+    CreatedAt,
+}
+
+impl CanDoListSort {
+    fn column(self) -> can_do_list::Column {
+        match self {
+            CanDoListSort::DisplayOrder => can_do_list::Column::DisplayOrder,
+            CanDoListSort::DueAt => can_do_list::Column::DueAt,
+            CanDoListSort::ScheduledAt => can_do_list::Column::ScheduledAt,
+            CanDoListSort::Priority => can_do_list::Column::Priority,
+            CanDoListSort::CreatedAt => can_do_list::Column::CreatedAt,
+        }
+    }
+
+    fn default_direction(self) -> SortDirection {
+        match self {
+            CanDoListSort::Priority | CanDoListSort::CreatedAt => SortDirection::Desc,
+            _ => SortDirection::Asc,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SortDirection {
+    Asc,
+    Desc,
+}
+
+impl From<SortDirection> for Order {
+    fn from(direction: SortDirection) -> Self {
+        match direction {
+            SortDirection::Asc => Order::Asc,
+            SortDirection::Desc => Order::Desc,
+        }
+    }
+}
+
+/// What completing or reopening a task does to its subtasks
+#[derive(Debug, Default, Clone, Copy, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SubtaskCompletion {
+    #[default]
+    Keep,
+    /// Complete open subtasks along with the task; reopening it reopens the ones completed with it
+    Cascade,
+    /// Refuse to complete a task while any of its subtasks is open
+    Require,
+}
+
+/// What completing a task does to the events linked to it
+#[derive(Debug, Default, Clone, Copy, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LinkedEventCompletion {
+    #[default]
+    Keep,
+    /// End linked events that are still running at the completion time and cancel those that
+    /// haven't started; recurring and past events stay as they are
+    Finish,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct UpdateCanDoItemQuery {
+    #[serde(default)]
+    pub subtasks: SubtaskCompletion,
+    #[serde(default)]
+    pub events: LinkedEventCompletion,
+}
+
+/// Tasks the user may access with at least `min_role`: their own and those in projects shared with them
+pub(crate) async fn task_scope<C: ConnectionTrait>(db: &C, user_id: Uuid, min_role: ProjectRole) -> Result<Condition> {
+    let shared_ids = shared_project_ids(db, user_id, min_role).await?;
+    Ok(Condition::any()
+        .add(can_do_list::Column::UserId.eq(user_id))
+        .add(can_do_list::Column::ProjectId.is_in(shared_ids)))
+}
+
+/// A task the user may access; `trashed` picks active or trashed tasks, `None` either. Changing a
+/// task of a shared project needs at least `min_role` there.
+pub(crate) async fn find_task<C: ConnectionTrait>(
+    db: &C,
+    user_id: Uuid,
+    id: Uuid,
+    trashed: Option<bool>,
+    min_role: ProjectRole,
+) -> Result<can_do_list::Model> {
+    let mut find = CanDoList::find_by_id(id).filter(task_scope(db, user_id, ProjectRole::Viewer).await?);
+    match trashed {
+        Some(true) => find = find.filter(can_do_list::Column::DeletedAt.is_not_null()),
+        Some(false) => find = find.filter(can_do_list::Column::DeletedAt.is_null()),
+        None => {}
+    }
+    let item = find
+        .one(db)
+        .await
+        .map_err(|e| crate::errors::AppError::Database(e.into()))?
+        .ok_or_else(|| {
+            let message = if trashed == Some(true) { "Can-do item not found in trash" } else { "Can-do item not found" };
+            crate::errors::AppError::NotFound(message.to_string())
+        })?;
+
+    if item.user_id != user_id && min_role > ProjectRole::Viewer {
+        let allowed_ids = shared_project_ids(db, user_id, min_role).await?;
+        if !item.project_id.is_some_and(|project_id| allowed_ids.contains(&project_id)) {
+            return Err(crate::errors::AppError::Forbidden(
+                "Viewers cannot change the tasks of a shared project".to_string(),
+            ));
+        }
+    }
+    Ok(item)
+}
+
+/// Where a task sits in the hierarchy, without its payload
+#[derive(Debug, Clone, FromQueryResult)]
+pub(crate) struct TaskNode {
+    pub id: Uuid,
+    pub parent_task_id: Option<Uuid>,
+    pub completed_at: Option<DateTimeWithTimeZone>,
+    pub deleted_at: Option<DateTimeWithTimeZone>,
+}
+
+/// Every task of the user, trashed ones included, as hierarchy nodes
+pub(crate) async fn task_tree<C: ConnectionTrait>(db: &C, user_id: Uuid) -> Result<Vec<TaskNode>> {
+    CanDoList::find()
+        .select_only()
+        .column(can_do_list::Column::Id)
+        .column(can_do_list::Column::ParentTaskId)
+        .column(can_do_list::Column::CompletedAt)
+        .column(can_do_list::Column::DeletedAt)
+        .filter(can_do_list::Column::UserId.eq(user_id))
+        .into_model::<TaskNode>()
+        .all(db)
+        .await
+        .map_err(|e| crate::errors::AppError::Database(e.into()))
+}
+
+/// Ids of `root_id` and all of its subtasks within `tasks`
+pub(crate) fn collect_subtask_ids<'a>(tasks: impl IntoIterator<Item = &'a TaskNode> + Clone, root_id: Uuid) -> Vec<Uuid> {
+    let mut ids = vec![root_id];
+    let mut index = 0;
+    while index < ids.len() {
+        let current = ids[index];
+        for task in tasks.clone().into_iter().filter(|t| t.parent_task_id == Some(current)) {
+            // Guard against parent cycles so a corrupt hierarchy can't loop forever
+            if !ids.contains(&task.id) {
+                ids.push(task.id);
+            }
+        }
+        index += 1;
+    }
+    ids
+}
+
+/// Check a requested parent task: it must be an active task of the user and not the task itself or one of its subtasks
+pub(crate) async fn validate_parent_task<C: ConnectionTrait>(
+    db: &C,
+    user_id: Uuid,
+    task_id: Option<Uuid>,
+    parent_task_id: Uuid,
+) -> Result<()> {
+    let tasks = task_tree(db, user_id).await?;
+    let active = tasks.iter().filter(|t| t.deleted_at.is_none());
+
+    if !active.clone().any(|t| t.id == parent_task_id) {
+        return Err(crate::errors::AppError::NotFound("Parent task not found".to_string()));
+    }
+    if let Some(task_id) = task_id
+        && collect_subtask_ids(active, task_id).contains(&parent_task_id)
+    {
+        return Err(crate::errors::AppError::Coded(
+            crate::errors::ErrorCode::ValidationParentCycle,
+            "A task cannot be moved into itself or one of its subtasks".to_string(),
+        ));
+    }
+    Ok(())
+}
+
+/// Move a task and its active subtasks to the trash under one shared `deleted_at`
+/// so restore can bring them back together. Returns the ids of the trashed tasks.
+pub(crate) async fn trash_task<C: ConnectionTrait>(db: &C, user_id: Uuid, task_id: Uuid) -> Result<Vec<Uuid>> {
+    let now = chrono::Utc::now();
+    let tasks = task_tree(db, user_id).await?;
+    let trashed_ids = collect_subtask_ids(tasks.iter().filter(|t| t.deleted_at.is_none()), task_id);
+
+    CanDoList::update_many()
+        .col_expr(can_do_list::Column::DeletedAt, Expr::value(now))
+        .col_expr(can_do_list::Column::UpdatedAt, Expr::value(now))
+        .filter(can_do_list::Column::UserId.eq(user_id))
+        .filter(can_do_list::Column::Id.is_in(trashed_ids.clone()))
+        .exec(db)
+        .await
+        .map_err(|e| crate::errors::AppError::Database(e.into()))?;
+
+    Ok(trashed_ids)
+}
+
+/// Check the repeat rule of a task about to be saved against the due or scheduled time it repeats from
+pub(crate) fn validate_task_recurrence(item_active: &can_do_list::ActiveModel) -> Result<()> {
+    let rrule = item_active.rrule.try_as_ref().cloned().flatten();
+    let anchor = item_active.due_at.try_as_ref().cloned().flatten()
+        .or_else(|| item_active.scheduled_at.try_as_ref().cloned().flatten());
+    crate::recurrence::validate_task(rrule.as_deref(), anchor.map(|dt| dt.naive_utc().and_utc()))
+}
+
+/// Put a task about to be saved without a project into the user's default project. Returns the
+/// default project if it had to be created, so it can be broadcast along with the task.
+pub(crate) async fn route_to_inbox<C: ConnectionTrait>(
+    db: &C,
+    user_id: Uuid,
+    item_active: &mut can_do_list::ActiveModel,
+) -> Result<Option<projects::Model>> {
+    if item_active.project_id.try_as_ref().is_some_and(|project_id| project_id.is_some()) {
+        return Ok(None);
+    }
+    let (default_project, created) = ensure_default_project(db, user_id).await?;
+    item_active.project_id = Set(Some(default_project.id));
+    Ok(created.then_some(default_project))
+}
+
+/// Change message for a default project created while saving a task
+fn project_created_message(user_id: Uuid, project: projects::Model) -> WebSocketMessage {
+    WebSocketMessage {
+        event_type: "INSERT".to_string(),
+        table: "projects".to_string(),
+        user_id,
+        record_id: Some(project.id),
+        data: Some(serde_json::to_value(ProjectResponse::from(project)).unwrap_or_default()),
+    }
+}
+
+/// When a repeating task is completed, create its next instance with the due and scheduled
+/// times moved forward to the next occurrence. The rule moves over to the new instance so
+/// reopening and completing the old one again doesn't repeat it twice.
+pub(crate) async fn create_next_occurrence<C: ConnectionTrait>(
+    db: &C,
+    previous: &can_do_list::Model,
+    task: can_do_list::Model,
+) -> Result<(can_do_list::Model, Option<can_do_list::Model>)> {
+    let completing = previous.completed_at.is_none() && task.completed_at.is_some();
+    let Some(rrule) = task.rrule.clone().filter(|_| completing && task.deleted_at.is_none()) else {
+        return Ok((task, None));
+    };
+    let Some(anchor) = task.due_at.or(task.scheduled_at).map(|dt| dt.naive_utc().and_utc()) else {
+        return Ok((task, None));
+    };
+    let time = crate::handlers::user_settings::time_preferences(db, task.user_id).await?;
+    let Some((next_at, next_rrule)) = crate::recurrence::next_task(&rrule, anchor, &time)? else {
+        return Ok((task, None));
+    };
+    let shift = next_at - anchor;
+
+    let mut next_active = can_do_list::ActiveModel::new();
+    next_active.user_id = Set(task.user_id);
+    next_active.project_id = Set(task.project_id);
+    next_active.encrypted_data = Set(task.encrypted_data.clone());
+    next_active.iv = Set(task.iv.clone());
+    next_active.salt = Set(task.salt.clone());
+    next_active.schema_version = Set(task.schema_version);
+    next_active.display_order = Set(task.display_order);
+    next_active.search_bloom = Set(task.search_bloom.clone());
+    next_active.search_tokens = Set(task.search_tokens.clone());
+    next_active.due_at = Set(task.due_at.map(|dt| dt + shift));
+    next_active.scheduled_at = Set(task.scheduled_at.map(|dt| dt + shift));
+    next_active.parent_task_id = Set(task.parent_task_id);
+    next_active.priority = Set(task.priority);
+    next_active.estimated_minutes = Set(task.estimated_minutes);
+    next_active.rrule = Set(Some(next_rrule));
+    next_active.pending_plaintext = Set(task.pending_plaintext.clone());
+    let next = next_active.insert(db).await
+        .map_err(|e| crate::errors::AppError::Database(e.into()))?;
+
+    let mut task_active: can_do_list::ActiveModel = task.into();
+    task_active.rrule = Set(None);
+    let task = task_active.update(db).await
+        .map_err(|e| crate::errors::AppError::Database(e.into()))?;
+
+    Ok((task, Some(next)))
+}
+
+/// Apply `mode` to the subtasks of `task` when its completion changes to `completed_at`;
+/// returns the subtasks that changed
+async fn complete_subtasks<C: ConnectionTrait>(
+    db: &C,
+    task: &can_do_list::Model,
+    completed_at: Option<DateTime<Utc>>,
+    mode: SubtaskCompletion,
+) -> Result<Vec<can_do_list::Model>> {
+    let completing = completed_at.is_some() && task.completed_at.is_none();
+    let reopening = completed_at.is_none() && task.completed_at.is_some();
+    if matches!(mode, SubtaskCompletion::Keep) || !(completing || reopening) {
+        return Ok(Vec::new());
+    }
+
+    let tasks = task_tree(db, task.user_id).await?;
+    let active: Vec<&TaskNode> = tasks.iter().filter(|t| t.deleted_at.is_none()).collect();
+    let subtask_ids: Vec<Uuid> = collect_subtask_ids(active.iter().copied(), task.id)
+        .into_iter()
+        .skip(1)
+        .collect();
+
+    match mode {
+        SubtaskCompletion::Keep => Ok(Vec::new()),
+        SubtaskCompletion::Require => {
+            let open = active
+                .iter()
+                .any(|t| subtask_ids.contains(&t.id) && t.completed_at.is_none());
+            if completing && open {
+                return Err(crate::errors::AppError::Validation(
+                    "A task cannot be completed while it has open subtasks".to_string(),
+                ));
+            }
+            Ok(Vec::new())
+        }
+        SubtaskCompletion::Cascade => {
+            let mut update = CanDoList::update_many()
+                .col_expr(can_do_list::Column::CompletedAt, Expr::value(completed_at))
+                .col_expr(can_do_list::Column::UpdatedAt, Expr::value(chrono::Utc::now()))
+                .filter(can_do_list::Column::Id.is_in(subtask_ids));
+            // Only reopen subtasks that were completed together with the task
+            update = match task.completed_at {
+                Some(previous) if reopening => update.filter(can_do_list::Column::CompletedAt.eq(previous)),
+                _ => update.filter(can_do_list::Column::CompletedAt.is_null()),
+            };
+            update
+                .exec_with_returning(db)
+                .await
+                .map_err(|e| crate::errors::AppError::Database(e.into()))
+        }
+    }
+}
+
+/// Finish the events linked to the tasks `task_ids` of `user_id` as of `completed_at`; returns the
+/// events that changed
+async fn finish_linked_events<C: ConnectionTrait>(
+    db: &C,
+    actor_id: Uuid,
+    user_id: Uuid,
+    task_ids: Vec<Uuid>,
+    completed_at: DateTime<Utc>,
+) -> Result<Vec<calendar_events::Model>> {
+    let events = CalendarEvents::find()
+        .filter(calendar_events::Column::UserId.eq(user_id))
+        .filter(calendar_events::Column::LinkedTaskId.is_in(task_ids))
+        .filter(calendar_events::Column::DeletedAt.is_null())
+        .filter(calendar_events::Column::SubscriptionId.is_null())
+        .filter(calendar_events::Column::Rrule.is_null())
+        .filter(calendar_events::Column::Status.ne(EventStatus::Cancelled))
+        .filter(calendar_events::Column::EndTime.gt(completed_at))
+        .all(db)
+        .await
+        .map_err(|e| crate::errors::AppError::Database(e.into()))?;
+
+    let mut finished = Vec::with_capacity(events.len());
+    for event in events {
+        let started = event.start_time.is_none_or(|start_time| start_time <= completed_at);
+        record_revision(db, actor_id, RevisionAction::Update, &event).await?;
+        let mut event_active: calendar_events::ActiveModel = event.into();
+        if started {
+            event_active.end_time = Set(Some(completed_at.into()));
+        } else {
+            event_active.status = Set(EventStatus::Cancelled);
+        }
+        let event = event_active.update(db).await
+            .map_err(|e| crate::errors::AppError::Database(e.into()))?;
+        reschedule_event_reminders(db, &event).await?;
+        finished.push(event);
+    }
+    Ok(finished)
 }
 
 pub async fn list_items(
     State(app_state): State<AppState>,
     auth_user: AuthUser,
     Query(query): Query<CanDoListQuery>,
-) -> Result<Json<ApiResponse<Vec<CanDoItemResponse>>>> {
-    let mut find = CanDoList::find().filter(can_do_list::Column::UserId.eq(auth_user.0.id));
+    Query(fields): Query<FieldsQuery>,
+    Query(paging): Query<PageQuery>,
+) -> Result<Json<ApiResponse<Vec<serde_json::Value>>>> {
+    let db = app_state.db.reader();
+    let mut find = CanDoList::find()
+        .filter(task_scope(db, auth_user.0.id, ProjectRole::Viewer).await?)
+        .filter(can_do_list::Column::DeletedAt.is_null());
     
     if let Some(project_id) = query.project_id {
         find = find.filter(can_do_list::Column::ProjectId.eq(project_id));
     }
-    
-    let items = find
-        .order_by_asc(can_do_list::Column::DisplayOrder)
-        .order_by_desc(can_do_list::Column::CreatedAt)
-        .all(&app_state.db.connection)
-        .await
-        .map_err(|e| crate::errors::AppError::Database(e.into()))?;
+    match (query.parent_task_id, query.include_subtasks.unwrap_or(false)) {
+        (None, false) => find = find.filter(can_do_list::Column::ParentTaskId.is_null()),
+        (Some(parent_task_id), false) => find = find.filter(can_do_list::Column::ParentTaskId.eq(parent_task_id)),
+        (None, true) => {}
+        (Some(parent_task_id), true) => {
+            // Subtasks live with their parent's owner, which is someone else in a shared project
+            let parent = find_task(db, auth_user.0.id, parent_task_id, Some(false), ProjectRole::Viewer).await?;
+            let tasks = task_tree(db, parent.user_id).await?;
+            let subtask_ids: Vec<Uuid> = collect_subtask_ids(tasks.iter().filter(|t| t.deleted_at.is_none()), parent_task_id)
+                .into_iter()
+                .skip(1)
+                .collect();
+            find = find.filter(can_do_list::Column::Id.is_in(subtask_ids));
+        }
+    }
+    if let Some(due_before) = query.due_before {
+        find = find.filter(can_do_list::Column::DueAt.lt(due_before));
+    }
+    if let Some(due_after) = query.due_after {
+        find = find.filter(can_do_list::Column::DueAt.gte(due_after));
+    }
+    match query.overdue {
+        Some(true) => find = find.filter(can_do_list::Column::DueAt.lt(Utc::now())),
+        Some(false) => {
+            find = find.filter(
+                Condition::any()
+                    .add(can_do_list::Column::DueAt.is_null())
+                    .add(can_do_list::Column::DueAt.gte(Utc::now())),
+            )
+        }
+        None => {}
+    }
+    if let Some(scheduled_before) = query.scheduled_before {
+        find = find.filter(can_do_list::Column::ScheduledAt.lt(scheduled_before));
+    }
+    if let Some(scheduled_after) = query.scheduled_after {
+        find = find.filter(can_do_list::Column::ScheduledAt.gte(scheduled_after));
+    }
+    if let Some(priorities) = parse_priority_filter(query.priority.as_deref())? {
+        find = find.filter(can_do_list::Column::Priority.is_in(priorities));
+    }
+    if let Some(pinned) = query.pinned {
+        find = find.filter(can_do_list::Column::IsPinned.eq(pinned));
+    }
+
+    let direction = query.dir.unwrap_or(query.sort.default_direction());
+    find = find.order_by_with_nulls(query.sort.column(), direction.into(), NullOrdering::Last);
+    // Ties fall back to the manual order, then creation; the id keeps pages from overlapping
+    for tiebreaker in [CanDoListSort::DisplayOrder, CanDoListSort::CreatedAt] {
+        if tiebreaker != query.sort {
+            find = find.order_by(tiebreaker.column(), tiebreaker.default_direction().into());
+        }
+    }
+    let find = find.order_by_asc(can_do_list::Column::Id);
+    let (items, meta) = paging.fetch(db, find).await?;
 
     let response: Vec<CanDoItemResponse> = items.into_iter().map(|item| item.into()).collect();
-    Ok(Json(ApiResponse::new(response)))
+    Ok(Json(ApiResponse::list(fields.select(response)?, meta)))
 }
 
 pub async fn get_item(
@@ -58,12 +528,7 @@ pub async fn get_item(
     auth_user: AuthUser,
     Path(id): Path<Uuid>,
 ) -> Result<Json<ApiResponse<CanDoItemResponse>>> {
-    let item = CanDoList::find_by_id(id)
-        .filter(can_do_list::Column::UserId.eq(auth_user.0.id))
-        .one(&app_state.db.connection)
-        .await
-        .map_err(|e| crate::errors::AppError::Database(e.into()))?
-        .ok_or_else(|| crate::errors::AppError::NotFound("Can-do item not found".to_string()))?;
+    let item = find_task(app_state.db.reader(), auth_user.0.id, id, Some(false), ProjectRole::Viewer).await?;
 
     Ok(Json(ApiResponse::new(item.into())))
 }
@@ -71,33 +536,48 @@ pub async fn get_item(
 pub async fn create_item(
     State(app_state): State<AppState>,
     auth_user: AuthUser,
-    headers: HeaderMap,
-    Json(request): Json<CreateCanDoItemRequest>,
+    OriginConnection(connection_id): OriginConnection,
+    ValidatedJson(request): ValidatedJson<CreateCanDoItemRequest>,
 ) -> Result<Json<ApiResponse<CanDoItemResponse>>> {
-    let connection_id = extract_connection_id(&headers);
-    let display_order = request.display_order.unwrap_or(0);
+    validate_schema_version("can_do_list", request.schema_version)?;
+    let user_id = auth_user.0.id;
+    let quotas = app_state.quotas.clone();
+    // The quota check, the default project and the task commit together
+    let (owner_id, item, created_project) = app_state.db.with_txn(|txn| Box::pin(async move {
+        // Tasks added to a shared project belong to the project's owner and count against their quota
+        let owner_id = match request.project_id {
+            Some(project_id) => task_project_owner(txn, user_id, project_id).await?,
+            None => user_id,
+        };
+        quotas.enforce(txn, owner_id, QuotaTable::CanDoList, 1).await?;
+        if let Some(parent_task_id) = request.parent_task_id {
+            validate_parent_task(txn, owner_id, request.id, parent_task_id).await?;
+        }
+        let client_id = request.id;
+        let mut item_active = request.into_active_model(owner_id);
+        validate_task_recurrence(&item_active)?;
 
-    let mut item_active = can_do_list::ActiveModel::new();
-    item_active.user_id = Set(auth_user.0.id);
-    item_active.project_id = Set(request.project_id);
-    item_active.encrypted_data = Set(request.encrypted_data);
-    item_active.iv = Set(request.iv);
-    item_active.salt = Set(request.salt);
-    item_active.display_order = Set(display_order);
+        let created_project = route_to_inbox(txn, owner_id, &mut item_active).await?;
 
-    let item = item_active.insert(&app_state.db.connection).await
-        .map_err(|e| crate::errors::AppError::Database(e.into()))?;
+        let item = item_active.insert(txn).await
+            .map_err(|e| crate::errors::AppError::from_insert(e, client_id))?;
+        Ok((owner_id, item, created_project))
+    })).await?;
 
-    // Broadcast websocket message for can-do item creation
-    tracing::info!("Can-do item created, broadcasting websocket message for user {} (excluding connection {:?})", auth_user.0.id, connection_id);
-    let ws_message = WebSocketMessage {
+    // Broadcast websocket messages for can-do item creation and the default project if it had to be created
+    tracing::info!("Can-do item created, broadcasting websocket message for user {} (excluding connection {:?})", owner_id, connection_id);
+    let mut changes: Vec<(Vec<Uuid>, WebSocketMessage)> = created_project
+        .map(|project| (Vec::new(), project_created_message(owner_id, project)))
+        .into_iter()
+        .collect();
+    changes.push((item.project_id.into_iter().collect(), WebSocketMessage {
         event_type: "INSERT".to_string(),
         table: "can_do_list".to_string(),
-        user_id: auth_user.0.id,
+        user_id: owner_id,
         record_id: Some(item.id),
-        data: Some(serde_json::to_value(&CanDoItemResponse::from(item.clone())).unwrap_or_default()),
-    };
-    app_state.ws_state.broadcast_to_user(&auth_user.0.id, ws_message, connection_id).await;
+        data: Some(serde_json::to_value(CanDoItemResponse::from(item.clone())).unwrap_or_default()),
+    }));
+    broadcast_task_changes(&app_state, owner_id, changes, connection_id).await?;
 
     Ok(Json(ApiResponse::with_message(item.into(), "Can-do item created successfully")))
 }
@@ -105,82 +585,522 @@ pub async fn create_item(
 pub async fn update_item(
     State(app_state): State<AppState>,
     auth_user: AuthUser,
-    headers: HeaderMap,
+    OriginConnection(connection_id): OriginConnection,
     Path(id): Path<Uuid>,
-    Json(request): Json<UpdateCanDoItemRequest>,
+    preconditions: Preconditions,
+    Query(query): Query<UpdateCanDoItemQuery>,
+    ValidatedJson(request): ValidatedJson<UpdateCanDoItemRequest>,
 ) -> Result<Json<ApiResponse<CanDoItemResponse>>> {
-    let connection_id = extract_connection_id(&headers);
-    
-    let item = CanDoList::find_by_id(id)
-        .filter(can_do_list::Column::UserId.eq(auth_user.0.id))
-        .one(&app_state.db.connection)
+    validate_schema_version("can_do_list", request.schema_version)?;
+
+    let txn = app_state.db.connection.begin().await
+        .map_err(|e| crate::errors::AppError::Database(e.into()))?;
+
+    let item = find_task(&txn, auth_user.0.id, id, Some(false), ProjectRole::Editor).await?;
+    preconditions.check(&item.updated_at)?;
+    let owner_id = item.user_id;
+
+    // A task stays with its owner, so it can only move between that owner's projects
+    if let Some(Some(project_id)) = request.project_id
+        && item.project_id != Some(project_id)
+        && task_project_owner(&txn, auth_user.0.id, project_id).await? != owner_id
+    {
+        return Err(crate::errors::AppError::Validation(
+            "A task can only move between projects of the same owner".to_string(),
+        ));
+    }
+    if let Some(Some(parent_task_id)) = request.parent_task_id
+        && item.parent_task_id != Some(parent_task_id)
+    {
+        validate_parent_task(&txn, owner_id, Some(item.id), parent_task_id).await?;
+    }
+
+    let changed_subtasks = match request.completed_at {
+        Some(completed_at) => complete_subtasks(&txn, &item, completed_at, query.subtasks).await?,
+        None => Vec::new(),
+    };
+
+    record_revision(&txn, auth_user.0.id, RevisionAction::Update, &item).await?;
+    let previous = item.clone();
+    let mut item_active: can_do_list::ActiveModel = item.into();
+    request.apply_to(&mut item_active);
+    validate_task_recurrence(&item_active)?;
+    let created_project = route_to_inbox(&txn, owner_id, &mut item_active).await?;
+
+    let updated_item = item_active.update(&txn).await
+        .map_err(|e| crate::errors::AppError::Database(e.into()))?;
+    let finished_events = match (query.events, updated_item.completed_at) {
+        (LinkedEventCompletion::Finish, Some(completed_at)) if previous.completed_at.is_none() => {
+            let task_ids = std::iter::once(id).chain(changed_subtasks.iter().map(|task| task.id)).collect();
+            finish_linked_events(&txn, auth_user.0.id, owner_id, task_ids, completed_at.naive_utc().and_utc()).await?
+        }
+        _ => Vec::new(),
+    };
+    let (updated_item, next_item) = create_next_occurrence(&txn, &previous, updated_item).await?;
+
+    txn.commit().await
+        .map_err(|e| crate::errors::AppError::Database(e.into()))?;
+
+    // Broadcast websocket messages for the can-do item update, any subtasks it completed or reopened,
+    // the next instance of a repeating task and the default project if it had to be created
+    tracing::info!("Can-do item updated, broadcasting websocket message for user {} (excluding connection {:?})", owner_id, connection_id);
+    let mut changes: Vec<(Vec<Uuid>, WebSocketMessage)> = created_project
+        .map(|project| (Vec::new(), project_created_message(owner_id, project)))
+        .into_iter()
+        .collect();
+    // Members of the project the task moved out of learn that it left
+    let moved_from = previous.project_id.filter(|project_id| updated_item.project_id != Some(*project_id));
+    changes.extend(
+        std::iter::once(updated_item.clone())
+            .chain(changed_subtasks)
+            .map(|item| ("UPDATE", item))
+            .chain(next_item.map(|item| ("INSERT", item)))
+            .map(|(event_type, item)| {
+                let project_ids = item.project_id.into_iter().chain(moved_from.filter(|_| item.id == id)).collect();
+                (project_ids, WebSocketMessage {
+                    event_type: event_type.to_string(),
+                    table: "can_do_list".to_string(),
+                    user_id: owner_id,
+                    record_id: Some(item.id),
+                    data: Some(serde_json::to_value(CanDoItemResponse::from(item)).unwrap_or_default()),
+                })
+            }),
+    );
+    broadcast_task_changes(&app_state, owner_id, changes, connection_id).await?;
+    if !finished_events.is_empty() {
+        let audience = event_audience(
+            &app_state.db.connection,
+            owner_id,
+            finished_events.iter().map(|event| event.calendar_id),
+        )
+        .await?;
+        let event_messages = finished_events
+            .into_iter()
+            .map(|event| WebSocketMessage {
+                event_type: "UPDATE".to_string(),
+                table: "calendar_events".to_string(),
+                user_id: owner_id,
+                record_id: Some(event.id),
+                data: Some(serde_json::to_value(CalendarEventResponse::from(event)).unwrap_or_default()),
+            })
+            .collect();
+        app_state.ws_state.broadcast_many_to_users(&audience, event_messages, connection_id).await;
+    }
+
+    Ok(Json(ApiResponse::with_message(updated_item.into(), "Can-do item updated successfully")))
+}
+
+/// Ids of the events linked to a task that the user can see
+pub async fn list_linked_events(
+    State(app_state): State<AppState>,
+    auth_user: AuthUser,
+    Path(id): Path<Uuid>,
+) -> Result<Json<ApiResponse<LinkedEventsResponse>>> {
+    let db = app_state.db.reader();
+    let item = find_task(db, auth_user.0.id, id, Some(false), ProjectRole::Viewer).await?;
+    let linked_event_ids = CalendarEvents::find()
+        .select_only()
+        .column(calendar_events::Column::Id)
+        .filter(event_scope(db, auth_user.0.id, ProjectRole::Viewer).await?)
+        .filter(calendar_events::Column::LinkedTaskId.eq(item.id))
+        .filter(calendar_events::Column::DeletedAt.is_null())
+        .order_by_with_nulls(calendar_events::Column::StartTime, Order::Asc, NullOrdering::Last)
+        .order_by_asc(calendar_events::Column::Id)
+        .into_tuple()
+        .all(db)
         .await
-        .map_err(|e| crate::errors::AppError::Database(e.into()))?
-        .ok_or_else(|| crate::errors::AppError::NotFound("Can-do item not found".to_string()))?;
+        .map_err(|e| crate::errors::AppError::Database(e.into()))?;
 
+    Ok(Json(ApiResponse::new(LinkedEventsResponse { task_id: item.id, linked_event_ids })))
+}
+
+/// Prior versions of a can-do item, newest first
+pub async fn list_item_revisions(
+    State(app_state): State<AppState>,
+    auth_user: AuthUser,
+    Path(id): Path<Uuid>,
+    Query(paging): Query<PageQuery>,
+) -> Result<Json<ApiResponse<Vec<RevisionResponse<CanDoItemResponse>>>>> {
+    let db = app_state.db.reader();
+    let item = find_task(db, auth_user.0.id, id, Some(false), ProjectRole::Viewer).await?;
+
+    let (response, meta) = paging.page(list_revisions::<_, can_do_list::Model, _>(db, item.id).await?)?;
+    Ok(Json(ApiResponse::list(response, meta)))
+}
+
+/// Bring back a can-do item's content as of one of its revisions; the version replaced becomes a revision itself
+pub async fn revert_item(
+    State(app_state): State<AppState>,
+    auth_user: AuthUser,
+    OriginConnection(connection_id): OriginConnection,
+    Path((id, revision_id)): Path<(Uuid, Uuid)>,
+) -> Result<Json<ApiResponse<CanDoItemResponse>>> {
+    let txn = app_state.db.connection.begin().await
+        .map_err(|e| crate::errors::AppError::Database(e.into()))?;
+
+    let item = find_task(&txn, auth_user.0.id, id, Some(false), ProjectRole::Editor).await?;
+    let owner_id = item.user_id;
+    let prior: can_do_list::Model = find_revision(&txn, id, revision_id).await?;
+
+    record_revision(&txn, auth_user.0.id, RevisionAction::Revert, &item).await?;
     let mut item_active: can_do_list::ActiveModel = item.into();
-    
-    if let Some(project_id) = request.project_id {
-        item_active.project_id = Set(Some(project_id));
+    can_do_list::Model::revert(&mut item_active, prior);
+    let reverted_item = item_active.update(&txn).await
+        .map_err(|e| crate::errors::AppError::Database(e.into()))?;
+
+    txn.commit().await
+        .map_err(|e| crate::errors::AppError::Database(e.into()))?;
+
+    // Broadcast websocket message for the can-do item update
+    tracing::info!("Can-do item reverted, broadcasting websocket message for user {} (excluding connection {:?})", owner_id, connection_id);
+    let changes = vec![(reverted_item.project_id.into_iter().collect(), WebSocketMessage {
+        event_type: "UPDATE".to_string(),
+        table: "can_do_list".to_string(),
+        user_id: owner_id,
+        record_id: Some(reverted_item.id),
+        data: Some(serde_json::to_value(CanDoItemResponse::from(reverted_item.clone())).unwrap_or_default()),
+    })];
+    broadcast_task_changes(&app_state, owner_id, changes, connection_id).await?;
+
+    Ok(Json(ApiResponse::with_message(reverted_item.into(), "Can-do item reverted successfully")))
+}
+
+/// Rewrite `display_order` for the given items in one transaction. Siblings share an owner and a
+/// parent task or, at the top level, a project; the siblings of the listed items that were left out
+/// keep their relative order after them, so no two share a position.
+pub async fn reorder_items(
+    State(app_state): State<AppState>,
+    auth_user: AuthUser,
+    OriginConnection(connection_id): OriginConnection,
+    ValidatedJson(request): ValidatedJson<ReorderRequest>,
+) -> Result<Json<ApiResponse<Vec<CanDoItemResponse>>>> {
+
+    let txn = begin_write(&app_state.db.connection).await?;
+
+    let listed: Vec<(Uuid, Uuid, Option<Uuid>, Option<Uuid>)> = CanDoList::find()
+        .select_only()
+        .column(can_do_list::Column::Id)
+        .column(can_do_list::Column::UserId)
+        .column(can_do_list::Column::ProjectId)
+        .column(can_do_list::Column::ParentTaskId)
+        .filter(task_scope(&txn, auth_user.0.id, ProjectRole::Editor).await?)
+        .filter(can_do_list::Column::DeletedAt.is_null())
+        .filter(can_do_list::Column::Id.is_in(request.ids.clone()))
+        .into_tuple()
+        .all(&txn)
+        .await
+        .map_err(|e| crate::errors::AppError::Database(e.into()))?;
+
+    if listed.len() != request.ids.len() {
+        return Err(crate::errors::AppError::NotFound("Can-do item not found".to_string()));
     }
-    if let Some(encrypted_data) = request.encrypted_data {
-        item_active.encrypted_data = Set(encrypted_data);
+
+    // Sibling groups in the order their first item is listed
+    let mut groups: Vec<(Uuid, Option<Uuid>, Option<Uuid>)> = Vec::new();
+    for id in &request.ids {
+        if let Some((_, owner_id, project_id, parent_task_id)) = listed.iter().find(|(item_id, ..)| item_id == id)
+            && !groups.contains(&(*owner_id, *project_id, *parent_task_id))
+        {
+            groups.push((*owner_id, *project_id, *parent_task_id));
+        }
     }
-    if let Some(iv) = request.iv {
-        item_active.iv = Set(iv);
+
+    // Concurrent reorders among the same siblings take turns instead of interleaving. Rows are
+    // locked in id order so overlapping reorders can't deadlock.
+    lock_siblings(
+        &txn,
+        "can_do_list",
+        groups.iter().map(|(owner_id, project_id, parent_task_id)| (*owner_id, parent_task_id.or(*project_id))),
+    )
+    .await?;
+    let mut sibling_scope = Condition::any();
+    for (owner_id, project_id, parent_task_id) in &groups {
+        sibling_scope = sibling_scope.add(
+            Condition::all()
+                .add(can_do_list::Column::UserId.eq(*owner_id))
+                .add(match project_id {
+                    Some(project_id) => can_do_list::Column::ProjectId.eq(*project_id),
+                    None => can_do_list::Column::ProjectId.is_null(),
+                })
+                .add(match parent_task_id {
+                    Some(parent_task_id) => can_do_list::Column::ParentTaskId.eq(*parent_task_id),
+                    None => can_do_list::Column::ParentTaskId.is_null(),
+                }),
+        );
     }
-    if let Some(salt) = request.salt {
-        item_active.salt = Set(salt);
+    let mut siblings = CanDoList::find()
+        .filter(sibling_scope)
+        .filter(can_do_list::Column::DeletedAt.is_null())
+        .order_by_asc(can_do_list::Column::Id)
+        .lock_exclusive()
+        .all(&txn)
+        .await
+        .map_err(|e| crate::errors::AppError::Database(e.into()))?;
+    siblings.sort_by_key(|item| (item.display_order, item.created_at));
+
+    let mut reordered = Vec::with_capacity(siblings.len());
+    let mut changed_ids = Vec::new();
+    for (owner_id, project_id, parent_task_id) in groups {
+        let in_group = |item: &can_do_list::Model| {
+            item.user_id == owner_id && item.project_id == project_id && item.parent_task_id == parent_task_id
+        };
+        let mut ordered = Vec::new();
+        for id in &request.ids {
+            if let Some(index) = siblings.iter().position(|item| item.id == *id && in_group(item)) {
+                ordered.push(siblings.remove(index));
+            }
+        }
+        let (rest, others): (Vec<_>, Vec<_>) = siblings.into_iter().partition(|item| in_group(item));
+        siblings = others;
+        ordered.extend(rest);
+
+        for (index, item) in ordered.into_iter().enumerate() {
+            let display_order = ReorderRequest::position(index);
+
+            // Untouched items keep their `updated_at` so other clients don't refetch them
+            if item.display_order == display_order {
+                reordered.push(item);
+                continue;
+            }
+
+            let mut item_active: can_do_list::ActiveModel = item.into();
+            item_active.display_order = Set(display_order);
+            let updated_item = item_active.update(&txn).await
+                .map_err(|e| crate::errors::AppError::Database(e.into()))?;
+            changed_ids.push(updated_item.id);
+            reordered.push(updated_item);
+        }
     }
-    if let Some(display_order) = request.display_order {
-        item_active.display_order = Set(display_order);
+
+    // A listed item that left its siblings in the meantime isn't among them anymore
+    if request.ids.iter().any(|id| !reordered.iter().any(|item| item.id == *id)) {
+        return Err(crate::errors::AppError::NotFound("Can-do item not found".to_string()));
     }
 
-    let updated_item = item_active.update(&app_state.db.connection).await
+    txn.commit().await
         .map_err(|e| crate::errors::AppError::Database(e.into()))?;
 
-    // Broadcast websocket message for can-do item update
-    tracing::info!("Can-do item updated, broadcasting websocket message for user {} (excluding connection {:?})", auth_user.0.id, connection_id);
-    let ws_message = WebSocketMessage {
-        event_type: "UPDATE".to_string(),
-        table: "can_do_list".to_string(),
-        user_id: auth_user.0.id,
-        record_id: Some(updated_item.id),
-        data: Some(serde_json::to_value(&CanDoItemResponse::from(updated_item.clone())).unwrap_or_default()),
-    };
-    app_state.ws_state.broadcast_to_user(&auth_user.0.id, ws_message, connection_id).await;
+    // Broadcast websocket messages for every can-do item whose position changed
+    tracing::info!("Can-do items reordered, broadcasting websocket messages for user {} (excluding connection {:?})", auth_user.0.id, connection_id);
+    // The items may belong to different owners when some come from shared projects
+    let mut owner_ids: Vec<Uuid> = reordered.iter().map(|item| item.user_id).collect();
+    owner_ids.sort();
+    owner_ids.dedup();
+    for owner_id in owner_ids {
+        let changes = reordered
+            .iter()
+            .filter(|item| item.user_id == owner_id && changed_ids.contains(&item.id))
+            .map(|item| (item.project_id.into_iter().collect(), WebSocketMessage {
+                event_type: "UPDATE".to_string(),
+                table: "can_do_list".to_string(),
+                user_id: owner_id,
+                record_id: Some(item.id),
+                data: Some(serde_json::to_value(CanDoItemResponse::from(item.clone())).unwrap_or_default()),
+            }))
+            .collect();
+        broadcast_task_changes(&app_state, owner_id, changes, connection_id).await?;
+    }
 
-    Ok(Json(ApiResponse::with_message(updated_item.into(), "Can-do item updated successfully")))
+    let response: Vec<CanDoItemResponse> = reordered.into_iter().map(|item| item.into()).collect();
+    Ok(Json(ApiResponse::with_message(response, "Can-do items reordered successfully")))
 }
 
 pub async fn delete_item(
     State(app_state): State<AppState>,
     auth_user: AuthUser,
-    headers: HeaderMap,
+    OriginConnection(connection_id): OriginConnection,
     Path(id): Path<Uuid>,
+    preconditions: Preconditions,
+    Query(query): Query<DeleteQuery>,
 ) -> Result<Json<ApiResponse<()>>> {
-    let connection_id = extract_connection_id(&headers);
-    
-    let result = CanDoList::delete_by_id(id)
-        .filter(can_do_list::Column::UserId.eq(auth_user.0.id))
-        .exec(&app_state.db.connection)
+    let permanent = query.permanent.unwrap_or(false);
+
+    let txn = app_state.db.connection.begin().await
+        .map_err(|e| crate::errors::AppError::Database(e.into()))?;
+
+    // Only permanent deletes reach tasks that are already in the trash
+    let item = find_task(&txn, auth_user.0.id, id, (!permanent).then_some(false), ProjectRole::Editor).await?;
+    preconditions.check(&item.updated_at)?;
+    let owner_id = item.user_id;
+
+    let removed_ids = if permanent {
+        let tasks = task_tree(&txn, owner_id).await?;
+        // Subtasks, trashed ones included, go with it through `ON DELETE CASCADE`
+        let removed_ids = collect_subtask_ids(&tasks, id);
+
+        CanDoList::delete_by_id(id)
+            .filter(can_do_list::Column::UserId.eq(owner_id))
+            .exec(&txn)
+            .await
+            .map_err(|e| crate::errors::AppError::Database(e.into()))?;
+
+        for removed_id in &removed_ids {
+            record_deletion(&txn, owner_id, "can_do_list", *removed_id).await?;
+        }
+        removed_ids
+    } else {
+        record_revision(&txn, auth_user.0.id, RevisionAction::Delete, &item).await?;
+        trash_task(&txn, owner_id, id).await?
+    };
+
+    txn.commit().await
+        .map_err(|e| crate::errors::AppError::Database(e.into()))?;
+
+    // Broadcast websocket messages for the can-do item deletion and its subtasks
+    tracing::info!("Can-do item deleted, broadcasting websocket message for user {} (excluding connection {:?})", owner_id, connection_id);
+    let changes = removed_ids
+        .into_iter()
+        .map(|removed_id| (item.project_id.into_iter().collect(), WebSocketMessage {
+            event_type: "DELETE".to_string(),
+            table: "can_do_list".to_string(),
+            user_id: owner_id,
+            record_id: Some(removed_id),
+            data: None,
+        }))
+        .collect();
+    broadcast_task_changes(&app_state, owner_id, changes, connection_id).await?;
+
+    let message = if permanent { "Can-do item deleted successfully" } else { "Can-do item moved to trash" };
+    Ok(Json(ApiResponse::with_message((), message)))
+}
+
+pub async fn restore_item(
+    State(app_state): State<AppState>,
+    auth_user: AuthUser,
+    OriginConnection(connection_id): OriginConnection,
+    Path(id): Path<Uuid>,
+) -> Result<Json<ApiResponse<CanDoItemResponse>>> {
+    let txn = app_state.db.connection.begin().await
+        .map_err(|e| crate::errors::AppError::Database(e.into()))?;
+
+    let item = find_task(&txn, auth_user.0.id, id, Some(true), ProjectRole::Editor).await?;
+    let owner_id = item.user_id;
+
+    // The item's project may have been trashed or purged in the meantime; fall back to the inbox
+    let project_active = match item.project_id {
+        Some(project_id) => Projects::find_by_id(project_id)
+            .filter(projects::Column::UserId.eq(owner_id))
+            .filter(projects::Column::DeletedAt.is_null())
+            .one(&txn)
+            .await
+            .map_err(|e| crate::errors::AppError::Database(e.into()))?
+            .is_some(),
+        None => true,
+    };
+
+    // Only bring back the subtasks that were trashed together with this item
+    let tasks = task_tree(&txn, owner_id).await?;
+    let restored_ids = collect_subtask_ids(tasks.iter().filter(|t| t.deleted_at == item.deleted_at), id);
+    // Re-root the item if its parent task is gone or still in the trash
+    let parent_active = item.parent_task_id.is_none_or(|parent_task_id| {
+        tasks.iter().any(|t| t.id == parent_task_id && t.deleted_at.is_none())
+    });
+
+    CanDoList::update_many()
+        .col_expr(can_do_list::Column::DeletedAt, Expr::value(Option::<DateTime<Utc>>::None))
+        .col_expr(can_do_list::Column::UpdatedAt, Expr::value(Utc::now()))
+        .filter(can_do_list::Column::Id.is_in(restored_ids.iter().skip(1).copied().collect::<Vec<_>>()))
+        .exec(&txn)
         .await
         .map_err(|e| crate::errors::AppError::Database(e.into()))?;
 
-    if result.rows_affected == 0 {
-        return Err(crate::errors::AppError::NotFound("Can-do item not found".to_string()));
+    let mut item_active: can_do_list::ActiveModel = item.into();
+    item_active.deleted_at = Set(None);
+    if !project_active {
+        item_active.project_id = Set(None);
     }
+    if !parent_active {
+        item_active.parent_task_id = Set(None);
+    }
+    let created_project = route_to_inbox(&txn, owner_id, &mut item_active).await?;
+
+    let restored_item = item_active.update(&txn).await
+        .map_err(|e| crate::errors::AppError::Database(e.into()))?;
+
+    let restored_subtasks = CanDoList::find()
+        .filter(can_do_list::Column::Id.is_in(restored_ids.into_iter().skip(1).collect::<Vec<_>>()))
+        .all(&txn)
+        .await
+        .map_err(|e| crate::errors::AppError::Database(e.into()))?;
+
+    txn.commit().await
+        .map_err(|e| crate::errors::AppError::Database(e.into()))?;
+
+    // Broadcast websocket messages for the restored records; clients dropped them on delete
+    tracing::info!("Can-do item restored, broadcasting websocket message for user {} (excluding connection {:?})", owner_id, connection_id);
+    let mut changes: Vec<(Vec<Uuid>, WebSocketMessage)> = created_project
+        .map(|project| (Vec::new(), project_created_message(owner_id, project)))
+        .into_iter()
+        .collect();
+    changes.extend(
+        std::iter::once(restored_item.clone())
+            .chain(restored_subtasks)
+            .map(|item| (item.project_id.into_iter().collect(), WebSocketMessage {
+                event_type: "INSERT".to_string(),
+                table: "can_do_list".to_string(),
+                user_id: owner_id,
+                record_id: Some(item.id),
+                data: Some(serde_json::to_value(CanDoItemResponse::from(item)).unwrap_or_default()),
+            })),
+    );
+    broadcast_task_changes(&app_state, owner_id, changes, connection_id).await?;
+
+    Ok(Json(ApiResponse::with_message(restored_item.into(), "Can-do item restored successfully")))
+}
+
+/// Pin or unpin a can-do item; favorites are listed with `?pinned=true`
+async fn set_item_pinned(
+    app_state: AppState,
+    auth_user: AuthUser,
+    connection_id: Option<Uuid>,
+    id: Uuid,
+    preconditions: Preconditions,
+    is_pinned: bool,
+) -> Result<Json<ApiResponse<CanDoItemResponse>>> {
+    let user_id = auth_user.0.id;
+    let updated_item = app_state.db.with_txn(|txn| Box::pin(async move {
+        let item = find_task(txn, user_id, id, Some(false), ProjectRole::Editor).await?;
+        preconditions.check(&item.updated_at)?;
+
+        let mut item_active: can_do_list::ActiveModel = item.into();
+        item_active.is_pinned = Set(is_pinned);
+        item_active.update(txn).await
+            .map_err(|e| crate::errors::AppError::Database(e.into()))
+    })).await?;
+    let owner_id = updated_item.user_id;
 
-    // Broadcast websocket message for can-do item deletion
-    tracing::info!("Can-do item deleted, broadcasting websocket message for user {} (excluding connection {:?})", auth_user.0.id, connection_id);
+    // Broadcast websocket message for the pin change
+    tracing::info!("Can-do item pin changed, broadcasting websocket message for user {} (excluding connection {:?})", owner_id, connection_id);
     let ws_message = WebSocketMessage {
-        event_type: "DELETE".to_string(),
+        event_type: "UPDATE".to_string(),
         table: "can_do_list".to_string(),
-        user_id: auth_user.0.id,
-        record_id: Some(id),
-        data: None,
+        user_id: owner_id,
+        record_id: Some(updated_item.id),
+        data: Some(serde_json::to_value(CanDoItemResponse::from(updated_item.clone())).unwrap_or_default()),
     };
-    app_state.ws_state.broadcast_to_user(&auth_user.0.id, ws_message, connection_id).await;
+    let changes = vec![(updated_item.project_id.into_iter().collect(), ws_message)];
+    broadcast_task_changes(&app_state, owner_id, changes, connection_id).await?;
+
+    let message = if is_pinned { "Can-do item pinned successfully" } else { "Can-do item unpinned successfully" };
+    Ok(Json(ApiResponse::with_message(updated_item.into(), message)))
+}
+
+pub async fn pin_item(
+    State(app_state): State<AppState>,
+    auth_user: AuthUser,
+    OriginConnection(connection_id): OriginConnection,
+    Path(id): Path<Uuid>,
+    preconditions: Preconditions,
+) -> Result<Json<ApiResponse<CanDoItemResponse>>> {
+    set_item_pinned(app_state, auth_user, connection_id, id, preconditions, true).await
+}
 
-    Ok(Json(ApiResponse::with_message((), "Can-do item deleted successfully")))
+pub async fn unpin_item(
+    State(app_state): State<AppState>,
+    auth_user: AuthUser,
+    OriginConnection(connection_id): OriginConnection,
+    Path(id): Path<Uuid>,
+    preconditions: Preconditions,
+) -> Result<Json<ApiResponse<CanDoItemResponse>>> {
+    set_item_pinned(app_state, auth_user, connection_id, id, preconditions, false).await
 }
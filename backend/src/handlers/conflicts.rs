@@ -0,0 +1,169 @@
+use axum::{
+    extract::{Path, Query, State},
+    response::Json,
+};
+use chrono::Utc;
+use sea_orm::*;
+use uuid::Uuid;
+
+use crate::{
+    entities::{
+        conflicts::{self, ConflictAction, ConflictResolution},
+        prelude::*,
+    },
+    errors::{AppError, Result},
+    handlers::sync::apply_operation,
+    middleware::{auth::AuthUser, connection::OriginConnection, validation::ValidatedJson},
+    models::{
+        conflict::{ConflictQuery, ConflictResponse, ResolveConflictRequest, ResolveConflictResponse},
+        sync::{SyncAction, SyncOperation, SyncOperationStatus},
+        ApiResponse, PageQuery,
+    },
+    state::AppState,
+};
+
+/// Log a pushed operation that lost against the server version. A record has at most one open
+/// conflict; pushing the same edit again replaces it rather than adding another.
+pub async fn record_conflict<C: ConnectionTrait>(
+    db: &C,
+    user_id: Uuid,
+    op: &SyncOperation,
+    server_data: serde_json::Value,
+) -> Result<Uuid> {
+    // Creates never conflict, they either find their own record or are invalid
+    let action = match op.action {
+        SyncAction::Delete => ConflictAction::Delete,
+        SyncAction::Create | SyncAction::Update => ConflictAction::Update,
+    };
+    let open = Conflicts::find()
+        .filter(conflicts::Column::UserId.eq(user_id))
+        .filter(conflicts::Column::RecordId.eq(op.record_id))
+        .filter(conflicts::Column::TableName.eq(&op.table))
+        .filter(conflicts::Column::ResolvedAt.is_null())
+        .one(db)
+        .await
+        .map_err(|e| AppError::Database(e.into()))?;
+
+    let is_new = open.is_none();
+    let mut conflict_active = match open {
+        Some(conflict) => conflict.into(),
+        None => {
+            let mut conflict_active = conflicts::ActiveModel::new();
+            conflict_active.user_id = Set(user_id);
+            conflict_active.table_name = Set(op.table.clone());
+            conflict_active.record_id = Set(op.record_id);
+            conflict_active
+        }
+    };
+    conflict_active.action = Set(action);
+    conflict_active.base_updated_at = Set(op.base_updated_at.map(Into::into));
+    conflict_active.client_data = Set(op.data.clone());
+    conflict_active.server_data = Set(server_data);
+    let conflict = if is_new {
+        conflict_active.insert(db).await
+    } else {
+        conflict_active.update(db).await
+    }
+    .map_err(|e| AppError::Database(e.into()))?;
+
+    Ok(conflict.id)
+}
+
+async fn find_conflict<C: ConnectionTrait>(db: &C, user_id: Uuid, id: Uuid) -> Result<conflicts::Model> {
+    Conflicts::find_by_id(id)
+        .filter(conflicts::Column::UserId.eq(user_id))
+        .one(db)
+        .await
+        .map_err(|e| AppError::Database(e.into()))?
+        .ok_or_else(|| AppError::NotFound("Conflict not found".to_string()))
+}
+
+/// Open conflicts, or settled ones with `?resolved=true`, newest first
+pub async fn list_conflicts(
+    State(app_state): State<AppState>,
+    auth_user: AuthUser,
+    Query(query): Query<ConflictQuery>,
+    Query(paging): Query<PageQuery>,
+) -> Result<Json<ApiResponse<Vec<ConflictResponse>>>> {
+    let resolved_at = conflicts::Column::ResolvedAt;
+    let find = Conflicts::find()
+        .filter(conflicts::Column::UserId.eq(auth_user.0.id))
+        .filter(if query.resolved { resolved_at.is_not_null() } else { resolved_at.is_null() })
+        .order_by_desc(conflicts::Column::CreatedAt)
+        .order_by_desc(conflicts::Column::Id);
+    let (conflicts, meta) = paging.fetch(app_state.db.reader(), find).await?;
+
+    Ok(Json(ApiResponse::list(conflicts.into_iter().map(Into::into).collect(), meta)))
+}
+
+pub async fn get_conflict(
+    State(app_state): State<AppState>,
+    auth_user: AuthUser,
+    Path(id): Path<Uuid>,
+) -> Result<Json<ApiResponse<ConflictResponse>>> {
+    let conflict = find_conflict(app_state.db.reader(), auth_user.0.id, id).await?;
+    Ok(Json(ApiResponse::new(conflict.into())))
+}
+
+/// Settle a conflict: `keep_mine` replays the client's operation over the server version,
+/// `keep_theirs` drops it and `merged` saves the given update instead
+pub async fn resolve_conflict(
+    State(app_state): State<AppState>,
+    auth_user: AuthUser,
+    OriginConnection(connection_id): OriginConnection,
+    Path(id): Path<Uuid>,
+    ValidatedJson(request): ValidatedJson<ResolveConflictRequest>,
+) -> Result<Json<ApiResponse<ResolveConflictResponse>>> {
+    let user_id = auth_user.0.id;
+    let quotas = app_state.quotas.clone();
+    let (conflict, record, changes) = app_state.db.with_txn(|txn| Box::pin(async move {
+        let conflict = find_conflict(txn, user_id, id).await?;
+        if conflict.resolved_at.is_some() {
+            return Err(AppError::Conflict("The conflict was already resolved".to_string()));
+        }
+
+        let mut changes = Vec::new();
+        let replay = match request.resolution {
+            ConflictResolution::KeepTheirs => None,
+            ConflictResolution::KeepMine => Some(match conflict.action {
+                ConflictAction::Update => (SyncAction::Update, conflict.client_data.clone()),
+                ConflictAction::Delete => (SyncAction::Delete, serde_json::Value::Null),
+            }),
+            ConflictResolution::Merged => Some((SyncAction::Update, request.data.unwrap_or_default())),
+        };
+        let mut record = None;
+        if let Some((action, data)) = replay {
+            // Without a base version the operation overwrites whatever is stored now
+            let op = SyncOperation {
+                op_id: conflict.id.to_string(),
+                table: conflict.table_name.clone(),
+                action,
+                record_id: conflict.record_id,
+                base_updated_at: None,
+                data,
+            };
+            let result = apply_operation(txn, user_id, &quotas, &op, &mut changes).await?;
+            let error = result.error.unwrap_or_default();
+            match result.status {
+                SyncOperationStatus::Applied => record = result.record,
+                SyncOperationStatus::NotFound => return Err(AppError::NotFound(error)),
+                SyncOperationStatus::Conflict => return Err(AppError::Conflict(error)),
+                SyncOperationStatus::Invalid => return Err(AppError::invalid_field("data", error)),
+            }
+        }
+
+        let mut conflict_active: conflicts::ActiveModel = conflict.into();
+        conflict_active.resolution = Set(Some(request.resolution));
+        conflict_active.resolved_at = Set(Some(Utc::now().into()));
+        let conflict = conflict_active.update(txn).await
+            .map_err(|e| AppError::Database(e.into()))?;
+        Ok((conflict, record, changes))
+    })).await?;
+
+    // Broadcast websocket messages for the record the resolution saved
+    tracing::info!("Conflict resolved, broadcasting {} websocket messages for user {} (excluding connection {:?})", changes.len(), user_id, connection_id);
+    app_state.ws_state.broadcast_many_to_user(&user_id, changes, connection_id).await;
+
+    let response = ResolveConflictResponse { conflict: conflict.into(), record };
+    Ok(Json(ApiResponse::with_message(response, "Conflict resolved successfully")))
+}
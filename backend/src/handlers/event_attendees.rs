@@ -0,0 +1,340 @@
+use std::collections::HashMap;
+
+use axum::{
+    extract::{Path, Query, State},
+    response::Json,
+};
+use chrono::Utc;
+use sea_orm::{sea_query::Expr, *};
+use streamline_client::routes;
+use streamline_models::DEFAULT_SCHEMA_VERSION;
+use uuid::Uuid;
+
+use crate::{
+    entities::{prelude::*, calendar_events, event_attendees::{self, RsvpStatus}, users},
+    errors::{AppError, Result},
+    ics::itip::{self, Invitation, Party},
+    mailer::{format_local, templates, user_timezone, Mailer},
+    middleware::{
+        auth::AuthUser,
+        connection::OriginConnection,
+        preconditions::Preconditions,
+        rate_limit::RateLimitPolicy,
+        validation::ValidatedJson,
+    },
+    models::{
+        event_attendee::{AttendeeQuery, AttendeeResponse, AttendeeSummary, CreateAttendeeRequest, InviteRequest, RsvpRequest},
+        inbound_email::{generate_inbound_token, inbound_email_domain},
+        ApiResponse, PageQuery, validate_schema_version,
+    },
+    state::AppState,
+    websocket::WebSocketMessage,
+};
+
+/// Invitations name `rsvp-<token>@<INBOUND_EMAIL_DOMAIN>` as the organizer, so replies come
+/// back through the inbound mail hook
+pub(crate) const RSVP_ADDRESS_PREFIX: &str = "rsvp-";
+
+/// Invitations a user can send, since each one mails an address of their choosing
+const INVITATION_RATE_LIMIT: RateLimitPolicy = RateLimitPolicy::new(10, 20);
+
+/// Attendee counts by response for each of the given events; events without attendees are left out
+pub(crate) async fn attendee_summaries<C: ConnectionTrait>(
+    db: &C,
+    event_ids: Vec<Uuid>,
+) -> Result<HashMap<Uuid, AttendeeSummary>> {
+    if event_ids.is_empty() {
+        return Ok(HashMap::new());
+    }
+
+    let counts: Vec<(Uuid, RsvpStatus, i64)> = EventAttendees::find()
+        .select_only()
+        .column(event_attendees::Column::EventId)
+        .column(event_attendees::Column::RsvpStatus)
+        .column_as(Expr::col(event_attendees::Column::Id).count(), "count")
+        .filter(event_attendees::Column::EventId.is_in(event_ids))
+        .group_by(event_attendees::Column::EventId)
+        .group_by(event_attendees::Column::RsvpStatus)
+        .into_tuple()
+        .all(db)
+        .await
+        .map_err(|e| AppError::Database(e.into()))?;
+
+    let mut summaries: HashMap<Uuid, AttendeeSummary> = HashMap::new();
+    for (event_id, status, count) in counts {
+        summaries.entry(event_id).or_default().add(status, count);
+    }
+    Ok(summaries)
+}
+
+fn attendee_message(event_type: &str, attendee: &event_attendees::Model) -> WebSocketMessage {
+    WebSocketMessage {
+        event_type: event_type.to_string(),
+        table: "event_attendees".to_string(),
+        user_id: attendee.user_id,
+        record_id: Some(attendee.id),
+        data: Some(serde_json::to_value(AttendeeResponse::from(attendee.clone())).unwrap_or_default()),
+    }
+}
+
+/// Email `attendee` an iTIP REQUEST for `event`. The body only says who invited them to what and
+/// when; the rest is in the calendar part.
+async fn send_invitation<C: ConnectionTrait>(
+    db: &C,
+    mailer: &Mailer,
+    organizer: &users::Model,
+    event: &calendar_events::Model,
+    attendee: &event_attendees::Model,
+    invite: InviteRequest,
+) -> Result<()> {
+    let (Some(start), Some(email), Some(token)) = (event.start_time, &attendee.email, &attendee.invite_token) else {
+        return Ok(());
+    };
+    let start = start.naive_utc().and_utc();
+    let end = event.end_time.map_or(start, |end| end.naive_utc().and_utc());
+    // Without inbound mail, replies go to the organizer, who records them by hand
+    let reply_to = match inbound_email_domain() {
+        Some(domain) => format!("{}{}@{}", RSVP_ADDRESS_PREFIX, token, domain),
+        None => organizer.email.clone(),
+    };
+    let days = event.start_date.filter(|_| event.is_all_day).map(|first_day| (first_day, event.end_date.unwrap_or(first_day)));
+
+    let start_time = match days {
+        Some((first_day, _)) => first_day.format("%a %Y-%m-%d").to_string(),
+        None => format_local(start, user_timezone(db, organizer.id).await?),
+    };
+    let organizer_name = invite.organizer_name.clone().filter(|name| !name.trim().is_empty());
+    let message = templates::INVITATION.render(&[
+        ("organizer", organizer_name.as_deref().unwrap_or(&organizer.email)),
+        ("summary", invite.summary.trim()),
+        ("start_time", &start_time),
+    ]);
+
+    let invitation = Invitation {
+        event_id: event.id,
+        start,
+        end,
+        days,
+        rrule: event.rrule.clone(),
+        summary: invite.summary,
+        location: invite.location,
+        organizer: Party { name: organizer_name, email: reply_to },
+        attendee: Party { name: invite.name, email: email.clone() },
+    };
+    let calendar = invitation.to_request(Utc::now());
+    mailer.send_with_calendar(email, message, "REQUEST", calendar).await.map_err(|error| {
+        tracing::warn!("Sending an invitation failed: {}", error);
+        AppError::Internal("The invitation couldn't be sent".to_string())
+    })
+}
+
+/// Record the answer in an iTIP REPLY mailed to the reply addresses of invitations with the
+/// given tokens. A reply only counts for the event and address its invitation went to, and
+/// an invitation that can't be updated doesn't keep the others from being recorded.
+pub(crate) async fn receive_reply(app_state: &AppState, tokens: Vec<String>, calendar: Option<&str>) -> Result<()> {
+    let calendar = calendar.ok_or_else(|| AppError::Validation("The reply has no iCalendar part".to_string()))?;
+    let reply = itip::parse_reply(calendar)?;
+    let db = &app_state.db.connection;
+
+    let attendees = EventAttendees::find()
+        .filter(event_attendees::Column::InviteToken.is_in(tokens))
+        .all(db)
+        .await
+        .map_err(|e| AppError::Database(e.into()))?;
+    if attendees.is_empty() {
+        return Err(AppError::NotFound("Unknown invitation".to_string()));
+    }
+
+    let mut recorded = false;
+    for attendee in attendees {
+        if reply.uid != itip::event_uid(attendee.event_id) || attendee.email.as_deref() != Some(reply.attendee_email.as_str()) {
+            tracing::warn!("Ignoring an invitation reply for attendee {} that names another event or address", attendee.id);
+            continue;
+        }
+
+        let id = attendee.id;
+        let mut attendee_active: event_attendees::ActiveModel = attendee.into();
+        attendee_active.rsvp_status = Set(reply.rsvp_status);
+        attendee_active.responded_at = Set(match reply.rsvp_status {
+            RsvpStatus::NeedsAction => None,
+            _ => Some(Utc::now().into()),
+        });
+        let attendee = match attendee_active.update(db).await {
+            Ok(attendee) => attendee,
+            Err(e) => {
+                tracing::error!("Recording an invitation reply for attendee {} failed: {}", id, e);
+                continue;
+            }
+        };
+        recorded = true;
+
+        // Broadcast websocket message for the emailed response
+        tracing::info!("Invitation reply recorded, broadcasting websocket message for user {}", attendee.user_id);
+        app_state.ws_state
+            .broadcast_to_user(&attendee.user_id, attendee_message("UPDATE", &attendee), None)
+            .await;
+    }
+    if !recorded {
+        return Err(AppError::Validation("The reply doesn't match its invitation".to_string()));
+    }
+    Ok(())
+}
+
+pub async fn list_attendees(
+    State(app_state): State<AppState>,
+    auth_user: AuthUser,
+    Query(query): Query<AttendeeQuery>,
+    Query(paging): Query<PageQuery>,
+) -> Result<Json<ApiResponse<Vec<AttendeeResponse>>>> {
+    let mut find = EventAttendees::find().filter(event_attendees::Column::UserId.eq(auth_user.0.id));
+    if let Some(event_id) = query.event_id {
+        find = find.filter(event_attendees::Column::EventId.eq(event_id));
+    }
+
+    let find = find
+        .order_by_asc(event_attendees::Column::CreatedAt);
+    let (attendees, meta) = paging.fetch(&app_state.db.connection, find).await?;
+
+    Ok(Json(ApiResponse::list(attendees.into_iter().map(Into::into).collect(), meta)))
+}
+
+pub async fn create_attendee(
+    State(app_state): State<AppState>,
+    auth_user: AuthUser,
+    OriginConnection(connection_id): OriginConnection,
+    ValidatedJson(mut request): ValidatedJson<CreateAttendeeRequest>,
+) -> Result<Json<ApiResponse<AttendeeResponse>>> {
+    validate_schema_version("event_attendees", request.schema_version)?;
+    let invite = request.invite.take();
+    let mailer = match &invite {
+        Some(_) => Some(app_state.mailer.clone().ok_or_else(|| {
+            AppError::invalid_field("invite", "Email is not set up on this server, so invitations can't be sent")
+        })?),
+        None => None,
+    };
+    let event = CalendarEvents::find_by_id(request.event_id)
+        .filter(calendar_events::Column::UserId.eq(auth_user.0.id))
+        .filter(calendar_events::Column::DeletedAt.is_null())
+        .one(&app_state.db.connection)
+        .await
+        .map_err(|e| AppError::Database(e.into()))?
+        .ok_or_else(|| AppError::NotFound("Calendar event not found".to_string()))?;
+
+    let rsvp_status = request.rsvp_status.unwrap_or_default();
+    let mut attendee_active = event_attendees::ActiveModel::new();
+    attendee_active.user_id = Set(auth_user.0.id);
+    attendee_active.event_id = Set(event.id);
+    attendee_active.encrypted_data = Set(request.encrypted_data);
+    attendee_active.iv = Set(request.iv);
+    attendee_active.salt = Set(request.salt);
+    attendee_active.schema_version = Set(request.schema_version.unwrap_or(DEFAULT_SCHEMA_VERSION));
+    attendee_active.rsvp_status = Set(rsvp_status);
+    if rsvp_status != RsvpStatus::NeedsAction {
+        attendee_active.responded_at = Set(Some(Utc::now().into()));
+    }
+    if let Some(invite) = &invite {
+        if event.start_time.is_none() {
+            return Err(AppError::invalid_field("invite", "Only events with a start_time can be sent as invitations"));
+        }
+        attendee_active.email = Set(Some(invite.email.trim().to_ascii_lowercase()));
+        attendee_active.invite_token = Set(Some(generate_inbound_token()));
+        attendee_active.invited_at = Set(Some(Utc::now().into()));
+    }
+
+    if invite.is_some() {
+        app_state.rate_limiter.check_user(auth_user.0.id, routes::EVENT_ATTENDEES, INVITATION_RATE_LIMIT)?;
+    }
+
+    // The invitation goes out before the attendee is committed, so nobody is recorded as
+    // invited unless the mail server took the invitation
+    let organizer = auth_user.0.clone();
+    let attendee = app_state.db.with_txn(|txn| Box::pin(async move {
+        let attendee = attendee_active.insert(txn).await
+            .map_err(|e| AppError::Database(e.into()))?;
+        if let (Some(invite), Some(mailer)) = (invite, mailer) {
+            send_invitation(txn, &mailer, &organizer, &event, &attendee, invite).await?;
+        }
+        Ok(attendee)
+    })).await?;
+
+    // Broadcast websocket message for attendee creation
+    tracing::info!("Attendee created, broadcasting websocket message for user {} (excluding connection {:?})", auth_user.0.id, connection_id);
+    app_state.ws_state
+        .broadcast_to_user(&auth_user.0.id, attendee_message("INSERT", &attendee), connection_id)
+        .await;
+
+    Ok(Json(ApiResponse::with_message(attendee.into(), "Attendee added successfully")))
+}
+
+/// Record an attendee's response to the event
+pub async fn record_rsvp(
+    State(app_state): State<AppState>,
+    auth_user: AuthUser,
+    OriginConnection(connection_id): OriginConnection,
+    Path(id): Path<Uuid>,
+    preconditions: Preconditions,
+    ValidatedJson(request): ValidatedJson<RsvpRequest>,
+) -> Result<Json<ApiResponse<AttendeeResponse>>> {
+    let attendee = EventAttendees::find_by_id(id)
+        .filter(event_attendees::Column::UserId.eq(auth_user.0.id))
+        .one(&app_state.db.connection)
+        .await
+        .map_err(|e| AppError::Database(e.into()))?
+        .ok_or_else(|| AppError::NotFound("Attendee not found".to_string()))?;
+    preconditions.check(&attendee.updated_at)?;
+
+    let mut attendee_active: event_attendees::ActiveModel = attendee.into();
+    attendee_active.rsvp_status = Set(request.rsvp_status);
+    attendee_active.responded_at = Set(match request.rsvp_status {
+        RsvpStatus::NeedsAction => None,
+        _ => Some(Utc::now().into()),
+    });
+
+    let attendee = attendee_active.update(&app_state.db.connection).await
+        .map_err(|e| AppError::Database(e.into()))?;
+
+    // Broadcast websocket message for the recorded response
+    tracing::info!("Attendee response recorded, broadcasting websocket message for user {} (excluding connection {:?})", auth_user.0.id, connection_id);
+    app_state.ws_state
+        .broadcast_to_user(&auth_user.0.id, attendee_message("UPDATE", &attendee), connection_id)
+        .await;
+
+    Ok(Json(ApiResponse::with_message(attendee.into(), "Response recorded successfully")))
+}
+
+pub async fn delete_attendee(
+    State(app_state): State<AppState>,
+    auth_user: AuthUser,
+    OriginConnection(connection_id): OriginConnection,
+    Path(id): Path<Uuid>,
+    preconditions: Preconditions,
+) -> Result<Json<ApiResponse<()>>> {
+    let user_id = auth_user.0.id;
+    app_state.db.with_txn(|txn| Box::pin(async move {
+        let attendee = EventAttendees::find_by_id(id)
+            .filter(event_attendees::Column::UserId.eq(user_id))
+            .one(txn)
+            .await
+            .map_err(|e| AppError::Database(e.into()))?
+            .ok_or_else(|| AppError::NotFound("Attendee not found".to_string()))?;
+        preconditions.check(&attendee.updated_at)?;
+        EventAttendees::delete_by_id(attendee.id)
+            .exec(txn)
+            .await
+            .map_err(|e| AppError::Database(e.into()))?;
+        Ok(())
+    })).await?;
+
+    // Broadcast websocket message for attendee removal
+    tracing::info!("Attendee removed, broadcasting websocket message for user {} (excluding connection {:?})", auth_user.0.id, connection_id);
+    let ws_message = WebSocketMessage {
+        event_type: "DELETE".to_string(),
+        table: "event_attendees".to_string(),
+        user_id: auth_user.0.id,
+        record_id: Some(id),
+        data: None,
+    };
+    app_state.ws_state.broadcast_to_user(&auth_user.0.id, ws_message, connection_id).await;
+
+    Ok(Json(ApiResponse::with_message((), "Attendee removed successfully")))
+}
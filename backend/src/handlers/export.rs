@@ -0,0 +1,971 @@
+use async_zip::{
+    base::read::mem::ZipFileReader,
+    tokio::write::ZipFileWriter,
+    Compression, ZipEntryBuilder,
+};
+use axum::{
+    body::{Body, Bytes},
+    extract::{Path, State},
+    http::header,
+    response::{IntoResponse, Json, Response},
+};
+use chrono::Utc;
+use futures_util::AsyncReadExt;
+use sea_orm::{sea_query::OnConflict, *};
+use serde::{de::DeserializeOwned, Serialize};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use tokio::io::DuplexStream;
+use tokio_util::io::ReaderStream;
+use uuid::Uuid;
+
+use crate::{
+    entities::{
+        prelude::*, calendar_events, calendar_subscriptions, calendars, can_do_list, event_attendees,
+        focus_sessions::{self, FocusStatus}, notes, organization_members::{self, OrganizationRole}, organizations, projects, reminders, settings, time_entries, user_settings,
+    },
+    errors::{AppError, Result},
+    handlers::{
+        activity::record_audit, organizations::organization_membership, reminders::next_fire_at, settings::{save_setting, setting_message}, sync::record_deletion,
+        user_settings::time_preferences,
+    },
+    middleware::{auth::{AuthUser, Impersonator}, connection::OriginConnection},
+    models::{
+        calendar::CalendarResponse,
+        calendar_event::CalendarEventResponse,
+        calendar_subscription::CalendarSubscriptionResponse,
+        can_do_list::CanDoItemResponse,
+        event_attendee::AttendeeResponse,
+        export::{ExportManifest, ImportSummary, OrganizationExportManifest, EXPORT_FORMAT_VERSION, MANIFEST_ENTRY, MAX_IMPORT_ENTRY_BYTES},
+        note::NoteResponse,
+        organization::{OrganizationMemberResponse, OrganizationResponse},
+        setting::{validate_namespace, SaveSettingRequest, GENERAL_NAMESPACE},
+        user_settings::TimePreferences,
+        project::ProjectResponse,
+        reminder::ReminderResponse,
+        focus_session::FocusSessionResponse,
+        time_entry::TimeEntryResponse,
+        validate_schema_version, ApiResponse,
+    },
+    quota::QuotaTable,
+    state::AppState,
+    websocket::WebSocketMessage,
+};
+
+/// Buffer between the archive writer and the response body
+const EXPORT_BUFFER_BYTES: usize = 64 * 1024;
+/// Rows per INSERT statement, well below Postgres' limit on bind parameters
+const INSERT_CHUNK_SIZE: usize = 500;
+
+/// Download everything the user owns as a ZIP of JSON dumps. Encrypted fields are exported as
+/// stored, so the archive is only readable with the user's key. Events imported from calendar
+/// subscriptions are left out; importing the subscription fetches them again. The archive
+/// can't be redacted like JSON responses, so impersonation sessions can't download it.
+pub async fn export_account(
+    State(app_state): State<AppState>,
+    auth_user: AuthUser,
+    Impersonator(impersonator): Impersonator,
+) -> Result<Response> {
+    if impersonator.is_some() {
+        return Err(AppError::Forbidden("Impersonation sessions can't export the account".to_string()));
+    }
+    let user_id = auth_user.0.id;
+    // One snapshot for every table, so the dumps agree with each other
+    let txn = app_state.db.connection
+        .begin_with_config(Some(IsolationLevel::RepeatableRead), Some(AccessMode::ReadOnly))
+        .await
+        .map_err(|e| AppError::Database(e.into()))?;
+
+    record_audit(&app_state.db.connection, user_id, None, "account_exported", serde_json::json!({})).await?;
+
+    // The archive is written while it is sent; a failure past this point can only cut the
+    // download short, which leaves a ZIP without its central directory that nothing will open
+    let (writer, reader) = tokio::io::duplex(EXPORT_BUFFER_BYTES);
+    tokio::spawn(async move {
+        if let Err(e) = write_archive(txn, user_id, writer).await {
+            tracing::error!("Exporting the account of user {} failed: {}", user_id, e);
+        }
+    });
+
+    let filename = format!("streamline-export-{}.zip", Utc::now().format("%Y-%m-%d"));
+    Ok((
+        [
+            (header::CONTENT_TYPE, "application/zip".to_string()),
+            (header::CONTENT_DISPOSITION, format!("attachment; filename=\"{}\"", filename)),
+        ],
+        Body::from_stream(ReaderStream::new(reader)),
+    )
+        .into_response())
+}
+
+async fn write_archive(txn: DatabaseTransaction, user_id: Uuid, writer: DuplexStream) -> Result<()> {
+    let mut zip = ZipFileWriter::with_tokio(writer);
+    let mut counts = BTreeMap::new();
+
+    let rows = Projects::find()
+        .filter(projects::Column::UserId.eq(user_id))
+        .order_by_asc(projects::Column::Id)
+        .all(&txn)
+        .await
+        .map_err(|e| AppError::Database(e.into()))?;
+    write_table(&mut zip, &mut counts, "projects", &rows).await?;
+
+    let rows = CanDoList::find()
+        .filter(can_do_list::Column::UserId.eq(user_id))
+        .order_by_asc(can_do_list::Column::Id)
+        .all(&txn)
+        .await
+        .map_err(|e| AppError::Database(e.into()))?;
+    write_table(&mut zip, &mut counts, "can_do_list", &rows).await?;
+
+    let rows = Calendars::find()
+        .filter(calendars::Column::UserId.eq(user_id))
+        .order_by_asc(calendars::Column::Id)
+        .all(&txn)
+        .await
+        .map_err(|e| AppError::Database(e.into()))?;
+    write_table(&mut zip, &mut counts, "calendars", &rows).await?;
+
+    let rows = CalendarEvents::find()
+        .filter(calendar_events::Column::UserId.eq(user_id))
+        .filter(calendar_events::Column::SubscriptionId.is_null())
+        .order_by_asc(calendar_events::Column::Id)
+        .all(&txn)
+        .await
+        .map_err(|e| AppError::Database(e.into()))?;
+    write_table(&mut zip, &mut counts, "calendar_events", &rows).await?;
+
+    let rows = Notes::find()
+        .filter(notes::Column::UserId.eq(user_id))
+        .order_by_asc(notes::Column::Id)
+        .all(&txn)
+        .await
+        .map_err(|e| AppError::Database(e.into()))?;
+    write_table(&mut zip, &mut counts, "notes", &rows).await?;
+
+    let rows = Reminders::find()
+        .filter(reminders::Column::UserId.eq(user_id))
+        .order_by_asc(reminders::Column::Id)
+        .all(&txn)
+        .await
+        .map_err(|e| AppError::Database(e.into()))?;
+    write_table(&mut zip, &mut counts, "reminders", &rows).await?;
+
+    let rows = TimeEntries::find()
+        .filter(time_entries::Column::UserId.eq(user_id))
+        .order_by_asc(time_entries::Column::Id)
+        .all(&txn)
+        .await
+        .map_err(|e| AppError::Database(e.into()))?;
+    write_table(&mut zip, &mut counts, "time_entries", &rows).await?;
+
+    let rows = FocusSessions::find()
+        .filter(focus_sessions::Column::UserId.eq(user_id))
+        .order_by_asc(focus_sessions::Column::Id)
+        .all(&txn)
+        .await
+        .map_err(|e| AppError::Database(e.into()))?;
+    write_table(&mut zip, &mut counts, "focus_sessions", &rows).await?;
+
+    let rows = EventAttendees::find()
+        .filter(event_attendees::Column::UserId.eq(user_id))
+        .order_by_asc(event_attendees::Column::Id)
+        .all(&txn)
+        .await
+        .map_err(|e| AppError::Database(e.into()))?;
+    write_table(&mut zip, &mut counts, "event_attendees", &rows).await?;
+
+    let rows = CalendarSubscriptions::find()
+        .filter(calendar_subscriptions::Column::UserId.eq(user_id))
+        .order_by_asc(calendar_subscriptions::Column::Id)
+        .all(&txn)
+        .await
+        .map_err(|e| AppError::Database(e.into()))?;
+    write_table(&mut zip, &mut counts, "calendar_subscriptions", &rows).await?;
+
+    let rows: Vec<user_settings::Model> = UserSettings::find_by_id(user_id)
+        .one(&txn)
+        .await
+        .map_err(|e| AppError::Database(e.into()))?
+        .into_iter()
+        .collect();
+    write_table(&mut zip, &mut counts, "user_settings", &rows).await?;
+
+    let rows = Settings::find()
+        .filter(settings::Column::UserId.eq(user_id))
+        .order_by_asc(settings::Column::Namespace)
+        .all(&txn)
+        .await
+        .map_err(|e| AppError::Database(e.into()))?;
+    write_table(&mut zip, &mut counts, "settings", &rows).await?;
+
+    txn.commit().await
+        .map_err(|e| AppError::Database(e.into()))?;
+
+    let manifest = ExportManifest {
+        format_version: EXPORT_FORMAT_VERSION,
+        exported_at: Utc::now(),
+        counts,
+    };
+    write_entry(&mut zip, MANIFEST_ENTRY, &manifest).await?;
+
+    zip.close().await
+        .map_err(|e| AppError::Internal(format!("Writing the export archive failed: {}", e)))?;
+    Ok(())
+}
+
+/// Download an organization's shared projects and calendars with their tasks, events and
+/// attendees, and its memberships, as a ZIP laid out like the account export. Owners only.
+/// Members' own records stay private: their unshared projects and calendars, notes, reminders,
+/// time entries and settings are left out, members appear only as listed to other members,
+/// and tasks created from an email keep their plaintext out of the archive. Records in the
+/// trash are left out, and rows are written as the API returns them.
+pub async fn export_organization(
+    State(app_state): State<AppState>,
+    auth_user: AuthUser,
+    Impersonator(impersonator): Impersonator,
+    Path(id): Path<Uuid>,
+) -> Result<Response> {
+    if impersonator.is_some() {
+        return Err(AppError::Forbidden("Impersonation sessions can't export the organization".to_string()));
+    }
+    let user_id = auth_user.0.id;
+    let txn = app_state.db.connection
+        .begin_with_config(Some(IsolationLevel::RepeatableRead), Some(AccessMode::ReadOnly))
+        .await
+        .map_err(|e| AppError::Database(e.into()))?;
+    let (organization, member) = organization_membership(&txn, user_id, id).await?;
+    if member.role < OrganizationRole::Owner {
+        return Err(AppError::Forbidden("Only owners can export the organization".to_string()));
+    }
+
+    record_audit(
+        &app_state.db.connection,
+        user_id,
+        None,
+        "organization_exported",
+        serde_json::json!({ "organization_id": id }),
+    )
+    .await?;
+
+    let (writer, reader) = tokio::io::duplex(EXPORT_BUFFER_BYTES);
+    tokio::spawn(async move {
+        if let Err(e) = write_organization_archive(txn, organization, writer).await {
+            tracing::error!("Exporting organization {} failed: {}", id, e);
+        }
+    });
+
+    let filename = format!("streamline-organization-export-{}.zip", Utc::now().format("%Y-%m-%d"));
+    Ok((
+        [
+            (header::CONTENT_TYPE, "application/zip".to_string()),
+            (header::CONTENT_DISPOSITION, format!("attachment; filename=\"{}\"", filename)),
+        ],
+        Body::from_stream(ReaderStream::new(reader)),
+    )
+        .into_response())
+}
+
+async fn write_organization_archive(
+    txn: DatabaseTransaction,
+    organization: organizations::Model,
+    writer: DuplexStream,
+) -> Result<()> {
+    let mut zip = ZipFileWriter::with_tokio(writer);
+    let mut counts = BTreeMap::new();
+    let organization_id = organization.id;
+    write_entry(&mut zip, "organization.json", &OrganizationResponse::new(organization, OrganizationRole::Owner)).await?;
+
+    let rows: Vec<OrganizationMemberResponse> = OrganizationMembers::find()
+        .find_also_related(Users)
+        .filter(organization_members::Column::OrganizationId.eq(organization_id))
+        .order_by_asc(organization_members::Column::CreatedAt)
+        .all(&txn)
+        .await
+        .map_err(|e| AppError::Database(e.into()))?
+        .into_iter()
+        .map(|(member, user)| OrganizationMemberResponse::new(member, user.map(|u| u.email).unwrap_or_default()))
+        .collect();
+    write_table(&mut zip, &mut counts, "organization_members", &rows).await?;
+
+    let rows: Vec<ProjectResponse> = Projects::find()
+        .filter(projects::Column::OrganizationId.eq(organization_id))
+        .filter(projects::Column::DeletedAt.is_null())
+        .order_by_asc(projects::Column::Id)
+        .all(&txn)
+        .await
+        .map_err(|e| AppError::Database(e.into()))?
+        .into_iter()
+        .map(Into::into)
+        .collect();
+    let project_ids: Vec<Uuid> = rows.iter().map(|project| project.id).collect();
+    write_table(&mut zip, &mut counts, "projects", &rows).await?;
+
+    let rows: Vec<CanDoItemResponse> = CanDoList::find()
+        .filter(can_do_list::Column::ProjectId.is_in(project_ids))
+        .filter(can_do_list::Column::DeletedAt.is_null())
+        .order_by_asc(can_do_list::Column::Id)
+        .all(&txn)
+        .await
+        .map_err(|e| AppError::Database(e.into()))?
+        .into_iter()
+        .map(|item| CanDoItemResponse { pending_plaintext: None, ..item.into() })
+        .collect();
+    write_table(&mut zip, &mut counts, "can_do_list", &rows).await?;
+
+    let rows: Vec<CalendarResponse> = Calendars::find()
+        .filter(calendars::Column::OrganizationId.eq(organization_id))
+        .filter(calendars::Column::DeletedAt.is_null())
+        .order_by_asc(calendars::Column::Id)
+        .all(&txn)
+        .await
+        .map_err(|e| AppError::Database(e.into()))?
+        .into_iter()
+        .map(Into::into)
+        .collect();
+    let calendar_ids: Vec<Uuid> = rows.iter().map(|calendar| calendar.id).collect();
+    write_table(&mut zip, &mut counts, "calendars", &rows).await?;
+
+    let events = || {
+        CalendarEvents::find()
+            .filter(calendar_events::Column::CalendarId.is_in(calendar_ids.clone()))
+            .filter(calendar_events::Column::SubscriptionId.is_null())
+            .filter(calendar_events::Column::DeletedAt.is_null())
+    };
+    let rows: Vec<CalendarEventResponse> = events()
+        .order_by_asc(calendar_events::Column::Id)
+        .all(&txn)
+        .await
+        .map_err(|e| AppError::Database(e.into()))?
+        .into_iter()
+        .map(Into::into)
+        .collect();
+    write_table(&mut zip, &mut counts, "calendar_events", &rows).await?;
+
+    let rows: Vec<AttendeeResponse> = EventAttendees::find()
+        .filter(
+            event_attendees::Column::EventId.in_subquery(
+                events()
+                    .select_only()
+                    .column(calendar_events::Column::Id)
+                    .into_query(),
+            ),
+        )
+        .order_by_asc(event_attendees::Column::Id)
+        .all(&txn)
+        .await
+        .map_err(|e| AppError::Database(e.into()))?
+        .into_iter()
+        .map(Into::into)
+        .collect();
+    write_table(&mut zip, &mut counts, "event_attendees", &rows).await?;
+
+    txn.commit().await
+        .map_err(|e| AppError::Database(e.into()))?;
+
+    let manifest = OrganizationExportManifest {
+        format_version: EXPORT_FORMAT_VERSION,
+        exported_at: Utc::now(),
+        organization_id,
+        counts,
+    };
+    write_entry(&mut zip, MANIFEST_ENTRY, &manifest).await?;
+
+    zip.close().await
+        .map_err(|e| AppError::Internal(format!("Writing the export archive failed: {}", e)))?;
+    Ok(())
+}
+
+async fn write_table<T: Serialize>(
+    zip: &mut ZipFileWriter<DuplexStream>,
+    counts: &mut BTreeMap<String, usize>,
+    table: &str,
+    rows: &[T],
+) -> Result<()> {
+    counts.insert(table.to_string(), rows.len());
+    write_entry(zip, &format!("{}.json", table), rows).await
+}
+
+async fn write_entry<T: Serialize + ?Sized>(zip: &mut ZipFileWriter<DuplexStream>, name: &str, value: &T) -> Result<()> {
+    let json = serde_json::to_vec(value)?;
+    let entry = ZipEntryBuilder::new(name.to_string().into(), Compression::Deflate);
+    zip.write_entry_whole(entry, &json)
+        .await
+        .map_err(|e| AppError::Internal(format!("Writing the export archive failed: {}", e)))
+}
+
+/// The rows of an export archive, as exported
+#[derive(Default)]
+struct Archive {
+    projects: Vec<projects::Model>,
+    can_do_list: Vec<can_do_list::Model>,
+    calendars: Vec<calendars::Model>,
+    calendar_events: Vec<calendar_events::Model>,
+    notes: Vec<notes::Model>,
+    reminders: Vec<reminders::Model>,
+    time_entries: Vec<time_entries::Model>,
+    focus_sessions: Vec<focus_sessions::Model>,
+    event_attendees: Vec<event_attendees::Model>,
+    calendar_subscriptions: Vec<calendar_subscriptions::Model>,
+    user_settings: Vec<user_settings::Model>,
+    settings: Vec<settings::Model>,
+}
+
+impl Archive {
+    async fn read(data: Vec<u8>) -> Result<Self> {
+        let zip = ZipFileReader::new(data)
+            .await
+            .map_err(|e| AppError::Validation(format!("Not a valid export archive: {}", e)))?;
+
+        let manifest: ExportManifest = read_entry(&zip, MANIFEST_ENTRY)
+            .await?
+            .ok_or_else(|| AppError::Validation(format!("The archive has no {}", MANIFEST_ENTRY)))?;
+        if manifest.format_version != EXPORT_FORMAT_VERSION {
+            return Err(AppError::Validation(format!(
+                "Unsupported export format version {}; this server reads version {}",
+                manifest.format_version, EXPORT_FORMAT_VERSION
+            )));
+        }
+
+        // A table missing from the archive has no rows
+        Ok(Self {
+            projects: read_table(&zip, "projects").await?,
+            can_do_list: read_table(&zip, "can_do_list").await?,
+            calendars: read_table(&zip, "calendars").await?,
+            calendar_events: read_table(&zip, "calendar_events").await?,
+            notes: read_table(&zip, "notes").await?,
+            reminders: read_table(&zip, "reminders").await?,
+            time_entries: read_table(&zip, "time_entries").await?,
+            focus_sessions: read_table(&zip, "focus_sessions").await?,
+            event_attendees: read_table(&zip, "event_attendees").await?,
+            calendar_subscriptions: read_table(&zip, "calendar_subscriptions").await?,
+            user_settings: read_table(&zip, "user_settings").await?,
+            settings: read_table(&zip, "settings").await?,
+        })
+    }
+}
+
+async fn read_table<T: DeserializeOwned>(zip: &ZipFileReader, table: &str) -> Result<Vec<T>> {
+    Ok(read_entry(zip, &format!("{}.json", table)).await?.unwrap_or_default())
+}
+
+async fn read_entry<T: DeserializeOwned>(zip: &ZipFileReader, name: &str) -> Result<Option<T>> {
+    let entries = zip.file().entries();
+    let Some(index) = entries.iter().position(|entry| entry.filename().as_str().is_ok_and(|filename| filename == name)) else {
+        return Ok(None);
+    };
+    let invalid = |e: async_zip::error::ZipError| AppError::Validation(format!("Can't read {} from the archive: {}", name, e));
+
+    let mut reader = zip.reader_with_entry(index).await.map_err(invalid)?;
+    // The declared size can't be trusted, so stop inflating once the limit is passed
+    let mut data = Vec::new();
+    (&mut reader)
+        .take(MAX_IMPORT_ENTRY_BYTES + 1)
+        .read_to_end(&mut data)
+        .await
+        .map_err(|e| AppError::Validation(format!("Can't read {} from the archive: {}", name, e)))?;
+    if data.len() as u64 > MAX_IMPORT_ENTRY_BYTES {
+        return Err(AppError::Validation(format!("{} is larger than {} bytes", name, MAX_IMPORT_ENTRY_BYTES)));
+    }
+    if reader.compute_hash() != entries[index].crc32() {
+        return Err(AppError::Validation(format!("{} is corrupted", name)));
+    }
+
+    serde_json::from_slice(&data)
+        .map(Some)
+        .map_err(|e| AppError::Validation(format!("{} is not a valid export: {}", name, e)))
+}
+
+/// Order rows so each comes after the row it links to, so the links hold while the rows are
+/// inserted one statement after another. Links caught in a cycle are dropped.
+fn parents_first<T>(rows: Vec<T>, id: fn(&T) -> Uuid, parent: fn(&mut T) -> &mut Option<Uuid>) -> Vec<T> {
+    let ids: HashSet<Uuid> = rows.iter().map(id).collect();
+    let mut placed = HashSet::with_capacity(ids.len());
+    let mut ordered = Vec::with_capacity(rows.len());
+    let mut pending = rows;
+
+    loop {
+        let (mut ready, mut rest) = (Vec::new(), Vec::new());
+        for mut row in pending {
+            match *parent(&mut row) {
+                Some(parent_id) if ids.contains(&parent_id) && !placed.contains(&parent_id) => rest.push(row),
+                _ => ready.push(row),
+            }
+        }
+        pending = rest;
+        if ready.is_empty() {
+            break;
+        }
+        placed.extend(ready.iter().map(id));
+        ordered.append(&mut ready);
+    }
+
+    for mut row in pending {
+        *parent(&mut row) = None;
+        ordered.push(row);
+    }
+    ordered
+}
+
+pub(crate) async fn insert_rows<C, M, A>(db: &C, rows: &[M]) -> Result<()>
+where
+    C: ConnectionTrait,
+    M: ModelTrait + IntoActiveModel<A> + Clone,
+    A: ActiveModelTrait<Entity = M::Entity>,
+    <M::Entity as EntityTrait>::Model: IntoActiveModel<A>,
+{
+    for chunk in rows.chunks(INSERT_CHUNK_SIZE) {
+        <M::Entity as EntityTrait>::insert_many(chunk.iter().cloned().map(|row| row.into_active_model().reset_all()))
+            .exec_without_returning(db)
+            .await
+            .map_err(|e| AppError::Database(e.into()))?;
+    }
+    Ok(())
+}
+
+/// Fail unless the account holds nothing an import could clash with; only the default
+/// project the server creates on its own and the settings may exist
+async fn ensure_fresh_account<C: ConnectionTrait>(db: &C, user_id: Uuid) -> Result<()> {
+    let counts = [
+        Projects::find()
+            .filter(projects::Column::UserId.eq(user_id))
+            .filter(projects::Column::IsDefault.eq(false))
+            .count(db)
+            .await,
+        CanDoList::find().filter(can_do_list::Column::UserId.eq(user_id)).count(db).await,
+        Calendars::find().filter(calendars::Column::UserId.eq(user_id)).count(db).await,
+        CalendarEvents::find().filter(calendar_events::Column::UserId.eq(user_id)).count(db).await,
+        Notes::find().filter(notes::Column::UserId.eq(user_id)).count(db).await,
+        TimeEntries::find().filter(time_entries::Column::UserId.eq(user_id)).count(db).await,
+        FocusSessions::find().filter(focus_sessions::Column::UserId.eq(user_id)).count(db).await,
+    ];
+    for count in counts {
+        if count.map_err(|e| AppError::Database(e.into()))? > 0 {
+            return Err(AppError::Conflict(
+                "Exports can only be imported into a fresh account without projects, tasks, calendars, events or notes".to_string(),
+            ));
+        }
+    }
+    Ok(())
+}
+
+/// New ids for the rows of one table
+fn new_ids<T>(rows: &[T], id: fn(&T) -> Uuid) -> HashMap<Uuid, Uuid> {
+    rows.iter().map(|row| (id(row), Uuid::now_v7())).collect()
+}
+
+/// Follow a link through the id map; a link to a row that wasn't imported is dropped
+fn remap(ids: &HashMap<Uuid, Uuid>, link: Option<Uuid>) -> Option<Uuid> {
+    link.and_then(|old| ids.get(&old).copied())
+}
+
+fn insert_message<T: Serialize>(user_id: Uuid, table: &str, record_id: Uuid, data: T) -> WebSocketMessage {
+    WebSocketMessage {
+        event_type: "INSERT".to_string(),
+        table: table.to_string(),
+        user_id,
+        record_id: Some(record_id),
+        data: Some(serde_json::to_value(data).unwrap_or_default()),
+    }
+}
+
+/// Restore an export into the authenticated account. Every row gets a new id and links between
+/// rows are rewritten to match; rows whose parent record is missing from the archive are skipped.
+pub async fn import_account(
+    State(app_state): State<AppState>,
+    auth_user: AuthUser,
+    OriginConnection(connection_id): OriginConnection,
+    body: Bytes,
+) -> Result<Json<ApiResponse<ImportSummary>>> {
+    let user_id = auth_user.0.id;
+    let archive = Archive::read(body.into()).await?;
+    let now = Utc::now();
+    let mut summary = ImportSummary::default();
+
+    let txn = app_state.db.connection.begin().await
+        .map_err(|e| AppError::Database(e.into()))?;
+
+    // Lock the account so two imports can't both find it empty
+    Users::find_by_id(user_id)
+        .lock_exclusive()
+        .one(&txn)
+        .await
+        .map_err(|e| AppError::Database(e.into()))?
+        .ok_or_else(|| AppError::NotFound("User not found".to_string()))?;
+    ensure_fresh_account(&txn, user_id).await?;
+
+    let Archive {
+        projects: project_rows,
+        can_do_list: task_rows,
+        calendars: calendar_rows,
+        calendar_events: event_rows,
+        notes: note_rows,
+        reminders: reminder_rows,
+        time_entries: time_entry_rows,
+        focus_sessions: focus_session_rows,
+        event_attendees: attendee_rows,
+        calendar_subscriptions: subscription_rows,
+        user_settings: settings_rows,
+        settings: namespace_rows,
+    } = archive;
+
+    // Events filled by a subscription are fetched again rather than restored
+    let (event_rows, feed_event_rows): (Vec<_>, Vec<_>) =
+        event_rows.into_iter().partition(|event| event.subscription_id.is_none());
+    if !feed_event_rows.is_empty() {
+        summary.skipped.insert("calendar_events".to_string(), feed_event_rows.len());
+    }
+
+    // Creating records counts against the quotas like any other write
+    let quota_counts = [
+        (QuotaTable::Projects, project_rows.len()),
+        (QuotaTable::CanDoList, task_rows.len()),
+        (QuotaTable::Calendars, calendar_rows.len()),
+        (QuotaTable::CalendarEvents, event_rows.len()),
+        (QuotaTable::Notes, note_rows.len()),
+    ];
+    for (table, count) in quota_counts {
+        if count > 0 {
+            app_state.quotas.enforce(&txn, user_id, table, count as u64).await?;
+        }
+    }
+
+    let project_ids = new_ids(&project_rows, |project| project.id);
+    let task_ids = new_ids(&task_rows, |task| task.id);
+    let calendar_ids = new_ids(&calendar_rows, |calendar| calendar.id);
+    let event_ids = new_ids(&event_rows, |event| event.id);
+
+    let mut deleted_projects = Vec::new();
+
+    // Projects: the exported default project replaces the empty one the server made
+    let imports_default = project_rows.iter().any(|project| project.is_default);
+    let mut seen_default = false;
+    let project_rows: Vec<projects::Model> = parents_first(project_rows, |project| project.id, |project| &mut project.parent_id)
+        .into_iter()
+        .map(|project| {
+            // Only one project can be the default
+            let is_default = project.is_default && !seen_default;
+            seen_default |= is_default;
+            projects::Model {
+                id: project_ids[&project.id],
+                user_id,
+                parent_id: remap(&project_ids, project.parent_id),
+                is_default,
+                // Memberships aren't part of an export
+                organization_id: None,
+                updated_at: now.into(),
+                ..project
+            }
+        })
+        .collect();
+    if imports_default {
+        let existing_defaults = Projects::find()
+            .filter(projects::Column::UserId.eq(user_id))
+            .filter(projects::Column::IsDefault.eq(true))
+            .all(&txn)
+            .await
+            .map_err(|e| AppError::Database(e.into()))?;
+        for project in existing_defaults {
+            Projects::delete_by_id(project.id)
+                .exec(&txn)
+                .await
+                .map_err(|e| AppError::Database(e.into()))?;
+            record_deletion(&txn, user_id, "projects", project.id).await?;
+            deleted_projects.push(project.id);
+        }
+    }
+    insert_rows(&txn, &project_rows).await?;
+    summary.imported.insert("projects".to_string(), project_rows.len());
+
+    // Tasks without a project land in the inbox, as when they are created
+    let inbox_id = match project_rows.iter().find(|project| project.is_default) {
+        Some(project) => project.id,
+        None => crate::handlers::projects::ensure_default_project(&txn, user_id).await?.0.id,
+    };
+    let task_rows: Vec<can_do_list::Model> = parents_first(task_rows, |task| task.id, |task| &mut task.parent_task_id)
+        .into_iter()
+        .map(|task| can_do_list::Model {
+            id: task_ids[&task.id],
+            user_id,
+            project_id: Some(remap(&project_ids, task.project_id).unwrap_or(inbox_id)),
+            parent_task_id: remap(&task_ids, task.parent_task_id),
+            updated_at: now.into(),
+            ..task
+        })
+        .collect();
+    insert_rows(&txn, &task_rows).await?;
+    summary.imported.insert("can_do_list".to_string(), task_rows.len());
+
+    // Calendars: read-only ones stay read-only only if their subscription comes along
+    let subscribed: HashSet<Uuid> = subscription_rows.iter().map(|subscription| subscription.calendar_id).collect();
+    let calendar_rows: Vec<calendars::Model> = calendar_rows
+        .into_iter()
+        .map(|calendar| calendars::Model {
+            id: calendar_ids[&calendar.id],
+            user_id,
+            is_read_only: calendar.is_read_only && subscribed.contains(&calendar.id),
+            organization_id: None,
+            updated_at: now.into(),
+            ..calendar
+        })
+        .collect();
+    insert_rows(&txn, &calendar_rows).await?;
+    summary.imported.insert("calendars".to_string(), calendar_rows.len());
+
+    let event_rows: Vec<calendar_events::Model> = parents_first(event_rows, |event| event.id, |event| &mut event.recurring_event_id)
+        .into_iter()
+        .map(|event| calendar_events::Model {
+            id: event_ids[&event.id],
+            user_id,
+            calendar_id: remap(&calendar_ids, event.calendar_id),
+            recurring_event_id: remap(&event_ids, event.recurring_event_id),
+            linked_task_id: remap(&task_ids, event.linked_task_id),
+            updated_at: now.into(),
+            ..event
+        })
+        .collect();
+    insert_rows(&txn, &event_rows).await?;
+    summary.imported.insert("calendar_events".to_string(), event_rows.len());
+
+    let note_rows: Vec<notes::Model> = note_rows
+        .into_iter()
+        .map(|note| notes::Model {
+            id: Uuid::now_v7(),
+            user_id,
+            project_id: remap(&project_ids, note.project_id),
+            event_id: remap(&event_ids, note.event_id),
+            updated_at: now.into(),
+            ..note
+        })
+        .collect();
+    insert_rows(&txn, &note_rows).await?;
+    summary.imported.insert("notes".to_string(), note_rows.len());
+
+    // Recurring events are expanded in the timezone being imported, or else the user's current one
+    let time = match settings_rows.first() {
+        Some(settings) => TimePreferences::from(settings),
+        None => time_preferences(&txn, user_id).await?,
+    };
+
+    // Reminders are rescheduled from now, so ones that were due at export time don't all fire at once
+    let events_by_id: HashMap<Uuid, &calendar_events::Model> = event_rows.iter().map(|event| (event.id, event)).collect();
+    let mut skipped = 0;
+    let mut imported_reminders = Vec::with_capacity(reminder_rows.len());
+    for reminder in reminder_rows {
+        let Some(event) = event_ids.get(&reminder.event_id).map(|event_id| events_by_id[event_id]) else {
+            skipped += 1;
+            continue;
+        };
+        let mut reminder = reminders::Model {
+            id: Uuid::now_v7(),
+            user_id,
+            event_id: event.id,
+            updated_at: now.into(),
+            ..reminder
+        };
+        reminder.fire_at = match event.deleted_at {
+            Some(_) => None,
+            None => next_fire_at(&reminder, event, &time, now)?.map(Into::into),
+        };
+        imported_reminders.push(reminder);
+    }
+    insert_rows(&txn, &imported_reminders).await?;
+    summary.imported.insert("reminders".to_string(), imported_reminders.len());
+    if skipped > 0 {
+        summary.skipped.insert("reminders".to_string(), skipped);
+    }
+
+    let mut skipped = 0;
+    let mut imported_time_entries = Vec::with_capacity(time_entry_rows.len());
+    for entry in time_entry_rows {
+        let Some(task_id) = task_ids.get(&entry.task_id) else {
+            skipped += 1;
+            continue;
+        };
+        imported_time_entries.push(time_entries::Model {
+            id: Uuid::now_v7(),
+            user_id,
+            task_id: *task_id,
+            updated_at: now.into(),
+            ..entry
+        });
+    }
+    insert_rows(&txn, &imported_time_entries).await?;
+    summary.imported.insert("time_entries".to_string(), imported_time_entries.len());
+    if skipped > 0 {
+        summary.skipped.insert("time_entries".to_string(), skipped);
+    }
+
+    // Sessions still running at export time are long over, so they come back cancelled
+    let imported_focus_sessions: Vec<focus_sessions::Model> = focus_session_rows
+        .into_iter()
+        .map(|session| {
+            let running = session.ended_at.is_none();
+            focus_sessions::Model {
+                id: Uuid::now_v7(),
+                user_id,
+                task_id: remap(&task_ids, session.task_id),
+                status: if running { FocusStatus::Cancelled } else { session.status },
+                ended_at: Some(session.ended_at.unwrap_or(session.started_at)),
+                updated_at: now.into(),
+                ..session
+            }
+        })
+        .collect();
+    insert_rows(&txn, &imported_focus_sessions).await?;
+    summary.imported.insert("focus_sessions".to_string(), imported_focus_sessions.len());
+
+    let mut skipped = 0;
+    let mut imported_attendees = Vec::with_capacity(attendee_rows.len());
+    for attendee in attendee_rows {
+        let Some(event_id) = event_ids.get(&attendee.event_id) else {
+            skipped += 1;
+            continue;
+        };
+        imported_attendees.push(event_attendees::Model {
+            id: Uuid::now_v7(),
+            user_id,
+            event_id: *event_id,
+            updated_at: now.into(),
+            ..attendee
+        });
+    }
+    insert_rows(&txn, &imported_attendees).await?;
+    summary.imported.insert("event_attendees".to_string(), imported_attendees.len());
+    if skipped > 0 {
+        summary.skipped.insert("event_attendees".to_string(), skipped);
+    }
+
+    // Subscriptions start over: the refresh job fetches their feeds on its next run
+    let mut skipped = 0;
+    let mut imported_subscriptions = Vec::with_capacity(subscription_rows.len());
+    for subscription in subscription_rows {
+        let Some(calendar_id) = calendar_ids.get(&subscription.calendar_id) else {
+            skipped += 1;
+            continue;
+        };
+        imported_subscriptions.push(calendar_subscriptions::Model {
+            id: Uuid::now_v7(),
+            user_id,
+            calendar_id: *calendar_id,
+            etag: None,
+            last_modified: None,
+            last_fetched_at: None,
+            last_error: None,
+            next_fetch_at: now.into(),
+            updated_at: now.into(),
+            ..subscription
+        });
+    }
+    insert_rows(&txn, &imported_subscriptions).await?;
+    summary.imported.insert("calendar_subscriptions".to_string(), imported_subscriptions.len());
+    if skipped > 0 {
+        summary.skipped.insert("calendar_subscriptions".to_string(), skipped);
+    }
+
+    // Archives from before namespaces hold the whole settings payload in user_settings
+    let mut namespace_rows: Vec<(String, SaveSettingRequest)> = namespace_rows
+        .into_iter()
+        .map(|setting| {
+            let request = SaveSettingRequest {
+                encrypted_data: setting.encrypted_data,
+                iv: setting.iv,
+                salt: setting.salt,
+                schema_version: Some(setting.schema_version),
+            };
+            (setting.namespace, request)
+        })
+        .collect();
+    if let Some(settings) = settings_rows.into_iter().next() {
+        if namespace_rows.is_empty() && settings.encrypted_data != "{}" {
+            let request = SaveSettingRequest {
+                encrypted_data: settings.encrypted_data.clone(),
+                iv: settings.iv.clone(),
+                salt: settings.salt.clone(),
+                schema_version: Some(settings.schema_version),
+            };
+            namespace_rows.push((GENERAL_NAMESPACE.to_string(), request));
+        }
+        let settings_active = user_settings::ActiveModel {
+            user_id: Set(user_id),
+            encrypted_data: Set(settings.encrypted_data),
+            iv: Set(settings.iv),
+            salt: Set(settings.salt),
+            schema_version: Set(settings.schema_version),
+            // Values that no longer parse, e.g. a timezone since renamed, fall back to the defaults
+            timezone: Set(time.timezone.name().to_string()),
+            week_start: Set(time.week_start.number_from_monday() as i16),
+            created_at: Set(settings.created_at),
+            updated_at: Set(now.into()),
+        };
+        UserSettings::insert(settings_active)
+            .on_conflict(
+                OnConflict::column(user_settings::Column::UserId)
+                    .update_columns([
+                        user_settings::Column::EncryptedData,
+                        user_settings::Column::Iv,
+                        user_settings::Column::Salt,
+                        user_settings::Column::SchemaVersion,
+                        user_settings::Column::Timezone,
+                        user_settings::Column::WeekStart,
+                        user_settings::Column::UpdatedAt,
+                    ])
+                    .to_owned(),
+            )
+            .exec_without_returning(&txn)
+            .await
+            .map_err(|e| AppError::Database(e.into()))?;
+        summary.imported.insert("user_settings".to_string(), 1);
+    }
+
+    // Namespaces the user already saved are overwritten
+    let mut skipped = 0;
+    let mut imported_settings = Vec::with_capacity(namespace_rows.len());
+    for (namespace, request) in namespace_rows {
+        if validate_namespace(&namespace).is_err() || validate_schema_version("settings", request.schema_version).is_err() {
+            skipped += 1;
+            continue;
+        }
+        let (setting, _) = save_setting(&txn, user_id, &namespace, request).await?;
+        imported_settings.push(setting);
+    }
+    summary.imported.insert("settings".to_string(), imported_settings.len());
+    if skipped > 0 {
+        summary.skipped.insert("settings".to_string(), skipped);
+    }
+
+    record_audit(&txn, user_id, None, "account_imported", serde_json::to_value(&summary)?).await?;
+
+    txn.commit().await
+        .map_err(|e| AppError::Database(e.into()))?;
+
+    // Broadcast websocket messages for everything the import created
+    tracing::info!("Account imported, broadcasting websocket message for user {} (excluding connection {:?})", user_id, connection_id);
+    let mut ws_messages: Vec<WebSocketMessage> = deleted_projects
+        .into_iter()
+        .map(|project_id| WebSocketMessage {
+            event_type: "DELETE".to_string(),
+            table: "projects".to_string(),
+            user_id,
+            record_id: Some(project_id),
+            data: None,
+        })
+        .collect();
+    ws_messages.extend(project_rows.into_iter().map(|row| insert_message(user_id, "projects", row.id, ProjectResponse::from(row))));
+    ws_messages.extend(task_rows.into_iter().map(|row| insert_message(user_id, "can_do_list", row.id, CanDoItemResponse::from(row))));
+    ws_messages.extend(calendar_rows.into_iter().map(|row| insert_message(user_id, "calendars", row.id, CalendarResponse::from(row))));
+    ws_messages.extend(event_rows.into_iter().map(|row| insert_message(user_id, "calendar_events", row.id, CalendarEventResponse::from(row))));
+    ws_messages.extend(note_rows.into_iter().map(|row| insert_message(user_id, "notes", row.id, NoteResponse::from(row))));
+    ws_messages.extend(imported_reminders.into_iter().map(|row| insert_message(user_id, "reminders", row.id, ReminderResponse::from(row))));
+    ws_messages.extend(imported_time_entries.into_iter().map(|row| insert_message(user_id, "time_entries", row.id, TimeEntryResponse::from(row))));
+    ws_messages.extend(imported_focus_sessions.into_iter().map(|row| insert_message(user_id, "focus_sessions", row.id, FocusSessionResponse::from(row))));
+    ws_messages.extend(imported_attendees.into_iter().map(|row| insert_message(user_id, "event_attendees", row.id, AttendeeResponse::from(row))));
+    ws_messages.extend(
+        imported_subscriptions
+            .into_iter()
+            .map(|row| insert_message(user_id, "calendar_subscriptions", row.id, CalendarSubscriptionResponse::from(row))),
+    );
+    ws_messages.extend(imported_settings.iter().map(|row| setting_message("INSERT", user_id, row)));
+    app_state.ws_state.broadcast_many_to_user(&user_id, ws_messages, connection_id).await;
+
+    Ok(Json(ApiResponse::with_message(summary, "Account imported successfully")))
+}
@@ -0,0 +1,20 @@
+use std::collections::BTreeMap;
+
+use axum::{extract::State, response::Json};
+
+use crate::{
+    errors::Result,
+    features::Feature,
+    middleware::auth::AuthUser,
+    models::ApiResponse,
+    state::AppState,
+};
+
+/// Which features are on for the signed-in user, so clients can hide the ones that aren't
+pub async fn list_features(
+    State(app_state): State<AppState>,
+    auth_user: AuthUser,
+) -> Result<Json<ApiResponse<BTreeMap<Feature, bool>>>> {
+    let features = app_state.features.for_user(app_state.db.reader(), auth_user.0.id).await?;
+    Ok(Json(ApiResponse::new(features)))
+}
@@ -0,0 +1,208 @@
+use axum::{
+    extract::{Path, Query, State},
+    response::Json,
+};
+use chrono::Utc;
+use sea_orm::{sea_query::Expr, *};
+use uuid::Uuid;
+
+use crate::{
+    entities::{prelude::*, can_do_list, focus_sessions::{self, FocusStatus}},
+    errors::{AppError, Result},
+    handlers::calendar_events::validate_time_range,
+    middleware::{auth::AuthUser, connection::OriginConnection, validation::ValidatedJson},
+    models::{
+        focus_session::{FocusSessionQuery, FocusSessionResponse, StartFocusSessionRequest},
+        ApiResponse, PageQuery,
+    },
+    state::AppState,
+    websocket::WebSocketMessage,
+};
+
+fn session_message(event_type: &str, user_id: Uuid, session: &focus_sessions::Model) -> WebSocketMessage {
+    WebSocketMessage {
+        event_type: event_type.to_string(),
+        table: "focus_sessions".to_string(),
+        user_id,
+        record_id: Some(session.id),
+        data: Some(serde_json::to_value(FocusSessionResponse::from(session.clone())).unwrap_or_default()),
+    }
+}
+
+pub async fn list_sessions(
+    State(app_state): State<AppState>,
+    auth_user: AuthUser,
+    Query(query): Query<FocusSessionQuery>,
+    Query(paging): Query<PageQuery>,
+) -> Result<Json<ApiResponse<Vec<FocusSessionResponse>>>> {
+    validate_time_range(query.start, query.end)?;
+
+    let mut find = FocusSessions::find().filter(focus_sessions::Column::UserId.eq(auth_user.0.id));
+    if let Some(task_id) = query.task_id {
+        find = find.filter(focus_sessions::Column::TaskId.eq(task_id));
+    }
+    if let Some(start) = query.start {
+        find = find.filter(
+            Condition::any()
+                .add(focus_sessions::Column::EndedAt.is_null())
+                .add(focus_sessions::Column::EndedAt.gt(start)),
+        );
+    }
+    if let Some(end) = query.end {
+        find = find.filter(focus_sessions::Column::StartedAt.lt(end));
+    }
+
+    let find = find
+        .order_by_asc(focus_sessions::Column::StartedAt);
+    let (sessions, meta) = paging.fetch(app_state.db.reader(), find).await?;
+
+    Ok(Json(ApiResponse::list(sessions.into_iter().map(Into::into).collect(), meta)))
+}
+
+/// The running session, if any
+pub async fn current_session(
+    State(app_state): State<AppState>,
+    auth_user: AuthUser,
+) -> Result<Json<ApiResponse<Option<FocusSessionResponse>>>> {
+    let session = FocusSessions::find()
+        .filter(focus_sessions::Column::UserId.eq(auth_user.0.id))
+        .filter(focus_sessions::Column::EndedAt.is_null())
+        .one(app_state.db.reader())
+        .await
+        .map_err(|e| AppError::Database(e.into()))?;
+
+    Ok(Json(ApiResponse::new(session.map(Into::into))))
+}
+
+/// Start a focus session or break, completing whichever session was running before
+pub async fn start_session(
+    State(app_state): State<AppState>,
+    auth_user: AuthUser,
+    OriginConnection(connection_id): OriginConnection,
+    request: Option<ValidatedJson<StartFocusSessionRequest>>,
+) -> Result<Json<ApiResponse<FocusSessionResponse>>> {
+    let ValidatedJson(request) = request.unwrap_or_default();
+    let planned_minutes = request.planned_minutes()?;
+    let user_id = auth_user.0.id;
+
+    let (session, ws_messages) = app_state.db.with_txn(|txn| Box::pin(async move {
+        if let Some(task_id) = request.task_id {
+            CanDoList::find_by_id(task_id)
+                .filter(can_do_list::Column::UserId.eq(user_id))
+                .filter(can_do_list::Column::DeletedAt.is_null())
+                .one(txn)
+                .await
+                .map_err(|e| AppError::Database(e.into()))?
+                .ok_or_else(|| AppError::NotFound("Can-do item not found".to_string()))?;
+        }
+
+        let running = FocusSessions::find()
+            .filter(focus_sessions::Column::UserId.eq(user_id))
+            .filter(focus_sessions::Column::EndedAt.is_null())
+            .lock_exclusive()
+            .one(txn)
+            .await
+            .map_err(|e| AppError::Database(e.into()))?;
+
+        let now = Utc::now();
+        let mut ws_messages = Vec::new();
+        if let Some(running) = running {
+            let mut running_active: focus_sessions::ActiveModel = running.into();
+            running_active.status = Set(FocusStatus::Completed);
+            running_active.ended_at = Set(Some(now.into()));
+            let completed = running_active.update(txn).await
+                .map_err(|e| AppError::Database(e.into()))?;
+            ws_messages.push(session_message("UPDATE", user_id, &completed));
+        }
+
+        let mut session_active = focus_sessions::ActiveModel::new();
+        session_active.user_id = Set(user_id);
+        session_active.task_id = Set(request.task_id);
+        session_active.kind = Set(request.kind);
+        session_active.planned_minutes = Set(planned_minutes);
+        session_active.started_at = Set(now.into());
+
+        // A concurrent start for the same user trips the one-running-session index
+        let session = session_active.insert(txn).await.map_err(|e| match e.sql_err() {
+            Some(SqlErr::UniqueConstraintViolation(_)) => {
+                AppError::Conflict("Another focus session was started at the same time".to_string())
+            }
+            _ => AppError::Database(e.into()),
+        })?;
+        ws_messages.push(session_message("INSERT", user_id, &session));
+        Ok((session, ws_messages))
+    })).await?;
+
+    // Broadcast websocket messages for the new session and the one it replaced
+    tracing::info!("Focus session started, broadcasting websocket message for user {} (excluding connection {:?})", user_id, connection_id);
+    app_state.ws_state.broadcast_many_to_user(&user_id, ws_messages, connection_id).await;
+
+    Ok(Json(ApiResponse::with_message(session.into(), "Focus session started successfully")))
+}
+
+/// End a running session as `status`
+async fn end_session(
+    app_state: AppState,
+    auth_user: AuthUser,
+    connection_id: Option<Uuid>,
+    id: Uuid,
+    status: FocusStatus,
+) -> Result<Json<ApiResponse<FocusSessionResponse>>> {
+    let user_id = auth_user.0.id;
+    let db = &app_state.db.connection;
+    // Only a running session ends, so two devices stopping it at once can't both succeed
+    let now = Utc::now();
+    let ended = FocusSessions::update_many()
+        .col_expr(focus_sessions::Column::Status, Expr::value(status.to_value()))
+        .col_expr(focus_sessions::Column::EndedAt, Expr::value(now))
+        .col_expr(focus_sessions::Column::UpdatedAt, Expr::value(now))
+        .filter(focus_sessions::Column::Id.eq(id))
+        .filter(focus_sessions::Column::UserId.eq(user_id))
+        .filter(focus_sessions::Column::EndedAt.is_null())
+        .exec_with_returning(db)
+        .await
+        .map_err(|e| AppError::Database(e.into()))?;
+    let Some(session) = ended.into_iter().next() else {
+        let exists = FocusSessions::find_by_id(id)
+            .filter(focus_sessions::Column::UserId.eq(user_id))
+            .count(db)
+            .await
+            .map_err(|e| AppError::Database(e.into()))?
+            > 0;
+        return Err(if exists {
+            AppError::Conflict("The focus session has already ended".to_string())
+        } else {
+            AppError::NotFound("Focus session not found".to_string())
+        });
+    };
+
+    // Broadcast websocket message for the ended session
+    tracing::info!("Focus session ended, broadcasting websocket message for user {} (excluding connection {:?})", user_id, connection_id);
+    app_state.ws_state
+        .broadcast_to_user(&user_id, session_message("UPDATE", user_id, &session), connection_id)
+        .await;
+
+    let message = match status {
+        FocusStatus::Cancelled => "Focus session cancelled successfully",
+        _ => "Focus session stopped successfully",
+    };
+    Ok(Json(ApiResponse::with_message(session.into(), message)))
+}
+
+pub async fn stop_session(
+    State(app_state): State<AppState>,
+    auth_user: AuthUser,
+    OriginConnection(connection_id): OriginConnection,
+    Path(id): Path<Uuid>,
+) -> Result<Json<ApiResponse<FocusSessionResponse>>> {
+    end_session(app_state, auth_user, connection_id, id, FocusStatus::Completed).await
+}
+
+pub async fn cancel_session(
+    State(app_state): State<AppState>,
+    auth_user: AuthUser,
+    OriginConnection(connection_id): OriginConnection,
+    Path(id): Path<Uuid>,
+) -> Result<Json<ApiResponse<FocusSessionResponse>>> {
+    end_session(app_state, auth_user, connection_id, id, FocusStatus::Cancelled).await
+}
@@ -0,0 +1,322 @@
+use std::collections::{HashMap, HashSet};
+
+use axum::{
+    extract::{Path, Query, State},
+    response::Json,
+};
+use chrono::Utc;
+use sea_orm::{prelude::DateTimeWithTimeZone, sea_query::{NullOrdering, OnConflict}, *};
+use uuid::Uuid;
+
+use crate::{
+    entities::{prelude::*, can_do_list, goal_links, goals, project_members::ProjectRole},
+    errors::{AppError, Result},
+    handlers::{
+        can_do_list::{find_task, task_scope},
+        project_members::accessible_project,
+        sync::record_deletion,
+    },
+    middleware::{auth::AuthUser, connection::OriginConnection, preconditions::Preconditions, validation::ValidatedJson},
+    models::{
+        goal::{CreateGoalRequest, GoalProgress, GoalResponse, UpdateGoalRequest},
+        validate_schema_version, ApiResponse, PageQuery,
+    },
+    state::AppState,
+    websocket::WebSocketMessage,
+};
+
+/// What a link points a goal at
+#[derive(Debug, Clone, Copy)]
+enum LinkTarget {
+    Project(Uuid),
+    Task(Uuid),
+}
+
+/// Goals with their links and progress. Tasks count once per goal whether they are linked
+/// directly, through a project or both; trashed tasks and tasks the user can no longer see
+/// don't count.
+async fn goal_responses<C: ConnectionTrait>(db: &C, user_id: Uuid, goals: Vec<goals::Model>) -> Result<Vec<GoalResponse>> {
+    let links = GoalLinks::find()
+        .filter(goal_links::Column::GoalId.is_in(goals.iter().map(|goal| goal.id)))
+        .order_by_asc(goal_links::Column::CreatedAt)
+        .all(db)
+        .await
+        .map_err(|e| AppError::Database(e.into()))?;
+    let project_ids: HashSet<Uuid> = links.iter().filter_map(|link| link.project_id).collect();
+    let task_ids: HashSet<Uuid> = links.iter().filter_map(|link| link.task_id).collect();
+
+    let tasks: Vec<(Uuid, Option<Uuid>, Option<DateTimeWithTimeZone>)> = if links.is_empty() {
+        Vec::new()
+    } else {
+        CanDoList::find()
+            .select_only()
+            .columns([can_do_list::Column::Id, can_do_list::Column::ProjectId, can_do_list::Column::CompletedAt])
+            .filter(task_scope(db, user_id, ProjectRole::Viewer).await?)
+            .filter(can_do_list::Column::DeletedAt.is_null())
+            .filter(
+                Condition::any()
+                    .add(can_do_list::Column::Id.is_in(task_ids))
+                    .add(can_do_list::Column::ProjectId.is_in(project_ids)),
+            )
+            .into_tuple()
+            .all(db)
+            .await
+            .map_err(|e| AppError::Database(e.into()))?
+    };
+
+    let mut links_by_goal: HashMap<Uuid, Vec<goal_links::Model>> = HashMap::new();
+    for link in links {
+        links_by_goal.entry(link.goal_id).or_default().push(link);
+    }
+    Ok(goals
+        .into_iter()
+        .map(|goal| {
+            let links = links_by_goal.remove(&goal.id).unwrap_or_default();
+            let project_ids: Vec<Uuid> = links.iter().filter_map(|link| link.project_id).collect();
+            let task_ids: Vec<Uuid> = links.iter().filter_map(|link| link.task_id).collect();
+            let counted: Vec<_> = tasks
+                .iter()
+                .filter(|(id, project_id, _)| {
+                    task_ids.contains(id) || project_id.is_some_and(|project_id| project_ids.contains(&project_id))
+                })
+                .collect();
+            let completed = counted.iter().filter(|(_, _, completed_at)| completed_at.is_some()).count();
+            let progress = GoalProgress::new(counted.len() as u64, completed as u64);
+            GoalResponse::new(goal, project_ids, task_ids, progress)
+        })
+        .collect())
+}
+
+async fn goal_response<C: ConnectionTrait>(db: &C, user_id: Uuid, goal: goals::Model) -> Result<GoalResponse> {
+    let mut responses = goal_responses(db, user_id, vec![goal]).await?;
+    responses.pop().ok_or_else(|| AppError::Internal("Goal response went missing".to_string()))
+}
+
+fn goal_message(event_type: &str, user_id: Uuid, goal: &GoalResponse) -> WebSocketMessage {
+    WebSocketMessage {
+        event_type: event_type.to_string(),
+        table: "goals".to_string(),
+        user_id,
+        record_id: Some(goal.id),
+        data: Some(serde_json::to_value(goal).unwrap_or_default()),
+    }
+}
+
+async fn find_goal<C: ConnectionTrait>(db: &C, user_id: Uuid, id: Uuid) -> Result<goals::Model> {
+    Goals::find_by_id(id)
+        .filter(goals::Column::UserId.eq(user_id))
+        .one(db)
+        .await
+        .map_err(|e| AppError::Database(e.into()))?
+        .ok_or_else(|| AppError::NotFound("Goal not found".to_string()))
+}
+
+pub async fn list_goals(
+    State(app_state): State<AppState>,
+    auth_user: AuthUser,
+    Query(paging): Query<PageQuery>,
+) -> Result<Json<ApiResponse<Vec<GoalResponse>>>> {
+    let db = app_state.db.reader();
+    let find = Goals::find()
+        .filter(goals::Column::UserId.eq(auth_user.0.id))
+        .order_by_with_nulls(goals::Column::TargetDate, Order::Asc, NullOrdering::Last)
+        .order_by_asc(goals::Column::CreatedAt);
+    let (goals, meta) = paging.fetch(db, find).await?;
+
+    Ok(Json(ApiResponse::list(goal_responses(db, auth_user.0.id, goals).await?, meta)))
+}
+
+pub async fn get_goal(
+    State(app_state): State<AppState>,
+    auth_user: AuthUser,
+    Path(id): Path<Uuid>,
+) -> Result<Json<ApiResponse<GoalResponse>>> {
+    let db = app_state.db.reader();
+    let goal = find_goal(db, auth_user.0.id, id).await?;
+
+    Ok(Json(ApiResponse::new(goal_response(db, auth_user.0.id, goal).await?)))
+}
+
+pub async fn create_goal(
+    State(app_state): State<AppState>,
+    auth_user: AuthUser,
+    OriginConnection(connection_id): OriginConnection,
+    ValidatedJson(request): ValidatedJson<CreateGoalRequest>,
+) -> Result<Json<ApiResponse<GoalResponse>>> {
+    validate_schema_version("goals", request.schema_version)?;
+    let user_id = auth_user.0.id;
+    let client_id = request.id;
+    let goal = request
+        .into_active_model(user_id)
+        .insert(&app_state.db.connection)
+        .await
+        .map_err(|e| AppError::from_insert(e, client_id))?;
+    let goal = GoalResponse::new(goal, Vec::new(), Vec::new(), GoalProgress::default());
+
+    // Broadcast websocket message for goal creation
+    tracing::info!("Goal created, broadcasting websocket message for user {} (excluding connection {:?})", user_id, connection_id);
+    app_state.ws_state.broadcast_to_user(&user_id, goal_message("INSERT", user_id, &goal), connection_id).await;
+
+    Ok(Json(ApiResponse::with_message(goal, "Goal created successfully")))
+}
+
+pub async fn update_goal(
+    State(app_state): State<AppState>,
+    auth_user: AuthUser,
+    OriginConnection(connection_id): OriginConnection,
+    Path(id): Path<Uuid>,
+    preconditions: Preconditions,
+    ValidatedJson(request): ValidatedJson<UpdateGoalRequest>,
+) -> Result<Json<ApiResponse<GoalResponse>>> {
+    validate_schema_version("goals", request.schema_version)?;
+    let user_id = auth_user.0.id;
+    let goal = app_state.db.with_txn(|txn| Box::pin(async move {
+        let goal = find_goal(txn, user_id, id).await?;
+        preconditions.check(&goal.updated_at)?;
+        let mut goal_active: goals::ActiveModel = goal.into();
+        request.apply_to(&mut goal_active);
+        let goal = goal_active.update(txn).await
+            .map_err(|e| AppError::Database(e.into()))?;
+        goal_response(txn, user_id, goal).await
+    })).await?;
+
+    // Broadcast websocket message for goal update
+    tracing::info!("Goal updated, broadcasting websocket message for user {} (excluding connection {:?})", user_id, connection_id);
+    app_state.ws_state.broadcast_to_user(&user_id, goal_message("UPDATE", user_id, &goal), connection_id).await;
+
+    Ok(Json(ApiResponse::with_message(goal, "Goal updated successfully")))
+}
+
+/// Delete a goal and its links; the linked projects and tasks stay
+pub async fn delete_goal(
+    State(app_state): State<AppState>,
+    auth_user: AuthUser,
+    OriginConnection(connection_id): OriginConnection,
+    Path(id): Path<Uuid>,
+    preconditions: Preconditions,
+) -> Result<Json<ApiResponse<()>>> {
+    let user_id = auth_user.0.id;
+    app_state.db.with_txn(|txn| Box::pin(async move {
+        let goal = find_goal(txn, user_id, id).await?;
+        preconditions.check(&goal.updated_at)?;
+        Goals::delete_by_id(goal.id)
+            .exec(txn)
+            .await
+            .map_err(|e| AppError::Database(e.into()))?;
+        record_deletion(txn, user_id, "goals", goal.id).await
+    })).await?;
+
+    // Broadcast websocket message for goal deletion
+    tracing::info!("Goal deleted, broadcasting websocket message for user {} (excluding connection {:?})", user_id, connection_id);
+    let ws_message = WebSocketMessage {
+        event_type: "DELETE".to_string(),
+        table: "goals".to_string(),
+        user_id,
+        record_id: Some(id),
+        data: None,
+    };
+    app_state.ws_state.broadcast_to_user(&user_id, ws_message, connection_id).await;
+
+    Ok(Json(ApiResponse::with_message((), "Goal deleted successfully")))
+}
+
+/// Link a project or task to a goal or unlink it. Linking needs read access to the project or
+/// task; linking twice and unlinking something not linked change nothing.
+async fn set_link(
+    app_state: AppState,
+    auth_user: AuthUser,
+    connection_id: Option<Uuid>,
+    id: Uuid,
+    target: LinkTarget,
+    linked: bool,
+) -> Result<GoalResponse> {
+    let user_id = auth_user.0.id;
+    let goal = app_state.db.with_txn(|txn| Box::pin(async move {
+        let goal = find_goal(txn, user_id, id).await?;
+
+        let (column, target_id) = match target {
+            LinkTarget::Project(project_id) => (goal_links::Column::ProjectId, project_id),
+            LinkTarget::Task(task_id) => (goal_links::Column::TaskId, task_id),
+        };
+        if linked {
+            let mut link = goal_links::ActiveModel::new();
+            link.goal_id = Set(goal.id);
+            link.user_id = Set(user_id);
+            match target {
+                LinkTarget::Project(project_id) => {
+                    accessible_project(txn, user_id, project_id).await?;
+                    link.project_id = Set(Some(project_id));
+                }
+                LinkTarget::Task(task_id) => {
+                    find_task(txn, user_id, task_id, Some(false), ProjectRole::Viewer).await?;
+                    link.task_id = Set(Some(task_id));
+                }
+            }
+            GoalLinks::insert(link)
+                .on_conflict(OnConflict::columns([goal_links::Column::GoalId, column]).do_nothing().to_owned())
+                .exec_without_returning(txn)
+                .await
+                .map_err(|e| AppError::Database(e.into()))?;
+        } else {
+            GoalLinks::delete_many()
+                .filter(goal_links::Column::GoalId.eq(goal.id))
+                .filter(column.eq(target_id))
+                .exec(txn)
+                .await
+                .map_err(|e| AppError::Database(e.into()))?;
+        }
+
+        // Touch the goal so delta-syncing clients pick up its new links
+        let mut goal_active: goals::ActiveModel = goal.into();
+        goal_active.updated_at = Set(Utc::now().into());
+        let goal = goal_active.update(txn).await
+            .map_err(|e| AppError::Database(e.into()))?;
+        goal_response(txn, user_id, goal).await
+    })).await?;
+
+    // Broadcast websocket message for the changed links and progress
+    tracing::info!("Goal links changed, broadcasting websocket message for user {} (excluding connection {:?})", user_id, connection_id);
+    app_state.ws_state.broadcast_to_user(&user_id, goal_message("UPDATE", user_id, &goal), connection_id).await;
+
+    Ok(goal)
+}
+
+pub async fn link_project(
+    State(app_state): State<AppState>,
+    auth_user: AuthUser,
+    OriginConnection(connection_id): OriginConnection,
+    Path((id, project_id)): Path<(Uuid, Uuid)>,
+) -> Result<Json<ApiResponse<GoalResponse>>> {
+    let goal = set_link(app_state, auth_user, connection_id, id, LinkTarget::Project(project_id), true).await?;
+    Ok(Json(ApiResponse::with_message(goal, "Project linked successfully")))
+}
+
+pub async fn unlink_project(
+    State(app_state): State<AppState>,
+    auth_user: AuthUser,
+    OriginConnection(connection_id): OriginConnection,
+    Path((id, project_id)): Path<(Uuid, Uuid)>,
+) -> Result<Json<ApiResponse<GoalResponse>>> {
+    let goal = set_link(app_state, auth_user, connection_id, id, LinkTarget::Project(project_id), false).await?;
+    Ok(Json(ApiResponse::with_message(goal, "Project unlinked successfully")))
+}
+
+pub async fn link_task(
+    State(app_state): State<AppState>,
+    auth_user: AuthUser,
+    OriginConnection(connection_id): OriginConnection,
+    Path((id, task_id)): Path<(Uuid, Uuid)>,
+) -> Result<Json<ApiResponse<GoalResponse>>> {
+    let goal = set_link(app_state, auth_user, connection_id, id, LinkTarget::Task(task_id), true).await?;
+    Ok(Json(ApiResponse::with_message(goal, "Task linked successfully")))
+}
+
+pub async fn unlink_task(
+    State(app_state): State<AppState>,
+    auth_user: AuthUser,
+    OriginConnection(connection_id): OriginConnection,
+    Path((id, task_id)): Path<(Uuid, Uuid)>,
+) -> Result<Json<ApiResponse<GoalResponse>>> {
+    let goal = set_link(app_state, auth_user, connection_id, id, LinkTarget::Task(task_id), false).await?;
+    Ok(Json(ApiResponse::with_message(goal, "Task unlinked successfully")))
+}
@@ -0,0 +1,446 @@
+use axum::{
+    extract::{Path, Query, State},
+    response::Json,
+};
+use chrono::{Duration, NaiveDate};
+use sea_orm::{sea_query::OnConflict, *};
+use uuid::Uuid;
+
+use crate::{
+    entities::{prelude::*, habit_checkins, habits},
+    errors::{AppError, Result},
+    handlers::{sync::record_deletion, user_settings::time_preferences},
+    middleware::{auth::AuthUser, connection::OriginConnection, preconditions::Preconditions, validation::ValidatedJson},
+    models::{
+        habit::{
+            CheckinListQuery, CheckinRequest, CreateHabitRequest, HabitCheckinResponse, HabitResponse,
+            UpdateHabitRequest, MAX_CHECKIN_WINDOW_DAYS,
+        },
+        user_settings::TimePreferences,
+        validate_schema_version, ApiResponse, PageQuery,
+    },
+    recurrence,
+    state::AppState,
+    websocket::WebSocketMessage,
+};
+
+pub(crate) fn habit_message(event_type: &str, user_id: Uuid, habit: &habits::Model) -> WebSocketMessage {
+    WebSocketMessage {
+        event_type: event_type.to_string(),
+        table: "habits".to_string(),
+        user_id,
+        record_id: Some(habit.id),
+        data: Some(serde_json::to_value(HabitResponse::from(habit.clone())).unwrap_or_default()),
+    }
+}
+
+#[derive(Debug, Default, PartialEq, Eq)]
+struct Streaks {
+    current: i32,
+    longest: i32,
+    expires_on: Option<NaiveDate>,
+}
+
+/// Each due date opens a period lasting until the next one; a period is done when the habit was
+/// checked in on any day of it, so a weekly habit can be done on any day of its week. The
+/// current streak counts done periods back from the one `today` falls in, which doesn't break
+/// the streak while it is still open.
+fn count_streaks(due: &[NaiveDate], checkins: &[NaiveDate], today: NaiveDate) -> Streaks {
+    let open = due.partition_point(|date| *date <= today);
+    let done: Vec<bool> = (0..open)
+        .map(|index| {
+            let from = checkins.partition_point(|date| *date < due[index]);
+            let until = due.get(index + 1).map_or(checkins.len(), |next| checkins.partition_point(|date| date < next));
+            until > from
+        })
+        .collect();
+
+    let mut streaks = Streaks::default();
+    let mut run = 0;
+    for &period_done in &done {
+        run = if period_done { run + 1 } else { 0 };
+        streaks.longest = streaks.longest.max(run);
+    }
+
+    // The period today falls in doesn't count against the streak until it is over
+    let last = if done.last() == Some(&false) { done.len() - 1 } else { done.len() };
+    streaks.current = done[..last].iter().rev().take_while(|period_done| **period_done).count() as i32;
+    if streaks.current > 0 {
+        // Broken once the period after the last done one is over
+        streaks.expires_on = due.get(last + 1).copied();
+    }
+    streaks
+}
+
+/// Recount a habit's streaks from all of its check-ins and store them; `checkins` come sorted
+async fn update_streaks<C: ConnectionTrait>(db: &C, habit: habits::Model, time: &TimePreferences) -> Result<habits::Model> {
+    let checkins: Vec<NaiveDate> = HabitCheckins::find()
+        .select_only()
+        .column(habit_checkins::Column::CheckedOn)
+        .filter(habit_checkins::Column::HabitId.eq(habit.id))
+        .order_by_asc(habit_checkins::Column::CheckedOn)
+        .into_tuple()
+        .all(db)
+        .await
+        .map_err(|e| AppError::Database(e.into()))?;
+
+    let today = time.today();
+    let last_checkin_on = checkins.last().copied();
+    let through = last_checkin_on.map_or(today, |date| date.max(today));
+    let due = recurrence::habit_due_dates(&habit.rrule, habit.starts_on, through, time)?;
+    let streaks = count_streaks(&due, &checkins, today);
+
+    let mut habit_active: habits::ActiveModel = habit.into();
+    habit_active.current_streak = Set(streaks.current);
+    habit_active.longest_streak = Set(streaks.longest);
+    habit_active.streak_expires_on = Set(streaks.expires_on);
+    habit_active.last_checkin_on = Set(last_checkin_on);
+    habit_active.update(db).await
+        .map_err(|e| AppError::Database(e.into()))
+}
+
+pub(crate) async fn find_habit<C: ConnectionTrait>(db: &C, user_id: Uuid, id: Uuid) -> Result<habits::Model> {
+    Habits::find_by_id(id)
+        .filter(habits::Column::UserId.eq(user_id))
+        .one(db)
+        .await
+        .map_err(|e| AppError::Database(e.into()))?
+        .ok_or_else(|| AppError::NotFound("Habit not found".to_string()))
+}
+
+/// Insert a habit, rejecting schedules that can't be expanded into due dates
+pub(crate) async fn insert_habit<C: ConnectionTrait>(
+    db: &C,
+    user_id: Uuid,
+    request: CreateHabitRequest,
+    time: &TimePreferences,
+) -> Result<habits::Model> {
+    let client_id = request.id;
+    let habit_active = request.into_active_model(user_id, time.today());
+    recurrence::validate_habit(habit_active.rrule.as_ref(), *habit_active.starts_on.as_ref())?;
+    habit_active.insert(db).await
+        .map_err(|e| AppError::from_insert(e, client_id))
+}
+
+/// Apply an update to a habit, recounting its streaks when its schedule changes
+pub(crate) async fn change_habit<C: ConnectionTrait>(
+    db: &C,
+    habit: habits::Model,
+    request: UpdateHabitRequest,
+    time: &TimePreferences,
+) -> Result<habits::Model> {
+    let reschedules = request.reschedules();
+    let mut habit_active: habits::ActiveModel = habit.into();
+    request.apply_to(&mut habit_active);
+    if reschedules {
+        recurrence::validate_habit(habit_active.rrule.as_ref(), *habit_active.starts_on.as_ref())?;
+    }
+    let habit = habit_active.update(db).await
+        .map_err(|e| AppError::Database(e.into()))?;
+    if reschedules {
+        return update_streaks(db, habit, time).await;
+    }
+    Ok(habit)
+}
+
+pub async fn list_habits(
+    State(app_state): State<AppState>,
+    auth_user: AuthUser,
+    Query(paging): Query<PageQuery>,
+) -> Result<Json<ApiResponse<Vec<HabitResponse>>>> {
+    let db = app_state.db.reader();
+    let today = time_preferences(db, auth_user.0.id).await?.today();
+    let find = Habits::find()
+        .filter(habits::Column::UserId.eq(auth_user.0.id))
+        .order_by_asc(habits::Column::CreatedAt);
+    let (habits, meta) = paging.fetch(db, find).await?;
+
+    Ok(Json(ApiResponse::list(
+        habits.into_iter().map(|habit| HabitResponse::from(habit).on(today)).collect(),
+        meta,
+    )))
+}
+
+pub async fn get_habit(
+    State(app_state): State<AppState>,
+    auth_user: AuthUser,
+    Path(id): Path<Uuid>,
+) -> Result<Json<ApiResponse<HabitResponse>>> {
+    let db = app_state.db.reader();
+    let habit = find_habit(db, auth_user.0.id, id).await?;
+    let today = time_preferences(db, auth_user.0.id).await?.today();
+
+    Ok(Json(ApiResponse::new(HabitResponse::from(habit).on(today))))
+}
+
+pub async fn create_habit(
+    State(app_state): State<AppState>,
+    auth_user: AuthUser,
+    OriginConnection(connection_id): OriginConnection,
+    ValidatedJson(request): ValidatedJson<CreateHabitRequest>,
+) -> Result<Json<ApiResponse<HabitResponse>>> {
+    validate_schema_version("habits", request.schema_version)?;
+    let user_id = auth_user.0.id;
+    let time = time_preferences(&app_state.db.connection, user_id).await?;
+    let habit = insert_habit(&app_state.db.connection, user_id, request, &time).await?;
+
+    // Broadcast websocket message for habit creation
+    tracing::info!("Habit created, broadcasting websocket message for user {} (excluding connection {:?})", user_id, connection_id);
+    app_state.ws_state.broadcast_to_user(&user_id, habit_message("INSERT", user_id, &habit), connection_id).await;
+
+    Ok(Json(ApiResponse::with_message(habit.into(), "Habit created successfully")))
+}
+
+pub async fn update_habit(
+    State(app_state): State<AppState>,
+    auth_user: AuthUser,
+    OriginConnection(connection_id): OriginConnection,
+    Path(id): Path<Uuid>,
+    preconditions: Preconditions,
+    ValidatedJson(request): ValidatedJson<UpdateHabitRequest>,
+) -> Result<Json<ApiResponse<HabitResponse>>> {
+    validate_schema_version("habits", request.schema_version)?;
+    let user_id = auth_user.0.id;
+    let time = time_preferences(&app_state.db.connection, user_id).await?;
+    let habit = app_state.db.with_txn(|txn| Box::pin(async move {
+        let habit = find_habit(txn, user_id, id).await?;
+        preconditions.check(&habit.updated_at)?;
+        change_habit(txn, habit, request, &time).await
+    })).await?;
+
+    // Broadcast websocket message for habit update
+    tracing::info!("Habit updated, broadcasting websocket message for user {} (excluding connection {:?})", user_id, connection_id);
+    app_state.ws_state.broadcast_to_user(&user_id, habit_message("UPDATE", user_id, &habit), connection_id).await;
+
+    Ok(Json(ApiResponse::with_message(HabitResponse::from(habit).on(time.today()), "Habit updated successfully")))
+}
+
+/// Delete a habit and its check-ins for good
+pub async fn delete_habit(
+    State(app_state): State<AppState>,
+    auth_user: AuthUser,
+    OriginConnection(connection_id): OriginConnection,
+    Path(id): Path<Uuid>,
+    preconditions: Preconditions,
+) -> Result<Json<ApiResponse<()>>> {
+    let user_id = auth_user.0.id;
+    app_state.db.with_txn(|txn| Box::pin(async move {
+        let habit = find_habit(txn, user_id, id).await?;
+        preconditions.check(&habit.updated_at)?;
+        Habits::delete_by_id(habit.id)
+            .exec(txn)
+            .await
+            .map_err(|e| AppError::Database(e.into()))?;
+        record_deletion(txn, user_id, "habits", habit.id).await
+    })).await?;
+
+    // Broadcast websocket message for habit deletion
+    tracing::info!("Habit deleted, broadcasting websocket message for user {} (excluding connection {:?})", user_id, connection_id);
+    let ws_message = WebSocketMessage {
+        event_type: "DELETE".to_string(),
+        table: "habits".to_string(),
+        user_id,
+        record_id: Some(id),
+        data: None,
+    };
+    app_state.ws_state.broadcast_to_user(&user_id, ws_message, connection_id).await;
+
+    Ok(Json(ApiResponse::with_message((), "Habit deleted successfully")))
+}
+
+/// Check-ins of a habit within `[start, end]`, oldest first
+pub async fn list_checkins(
+    State(app_state): State<AppState>,
+    auth_user: AuthUser,
+    Path(id): Path<Uuid>,
+    Query(query): Query<CheckinListQuery>,
+    Query(paging): Query<PageQuery>,
+) -> Result<Json<ApiResponse<Vec<HabitCheckinResponse>>>> {
+    let db = app_state.db.reader();
+    let habit = find_habit(db, auth_user.0.id, id).await?;
+    let end = match query.end {
+        Some(end) => end,
+        None => time_preferences(db, auth_user.0.id).await?.today(),
+    };
+    let start = query.start.unwrap_or(end - Duration::days(MAX_CHECKIN_WINDOW_DAYS - 1));
+    if end < start {
+        return Err(AppError::invalid_field("end", "end must not be before start"));
+    }
+    if (end - start).num_days() >= MAX_CHECKIN_WINDOW_DAYS {
+        return Err(AppError::invalid_field(
+            "end",
+            format!("At most {} days of check-ins can be listed at once", MAX_CHECKIN_WINDOW_DAYS),
+        ));
+    }
+
+    let find = HabitCheckins::find()
+        .filter(habit_checkins::Column::HabitId.eq(habit.id))
+        .filter(habit_checkins::Column::CheckedOn.gte(start))
+        .filter(habit_checkins::Column::CheckedOn.lte(end))
+        .order_by_asc(habit_checkins::Column::CheckedOn);
+    let (checkins, meta) = paging.fetch(db, find).await?;
+
+    Ok(Json(ApiResponse::list(checkins.into_iter().map(Into::into).collect(), meta)))
+}
+
+/// Add or remove the check-in for one day and recount the habit's streaks
+async fn set_checkin(
+    app_state: AppState,
+    auth_user: AuthUser,
+    connection_id: Option<Uuid>,
+    id: Uuid,
+    date: Option<NaiveDate>,
+    checked: bool,
+) -> Result<HabitResponse> {
+    let user_id = auth_user.0.id;
+    let time = time_preferences(&app_state.db.connection, user_id).await?;
+    let today = time.today();
+    let date = date.unwrap_or(today);
+    if date > today {
+        return Err(AppError::invalid_field("date", "Habits can't be checked in ahead of time"));
+    }
+
+    let habit = app_state.db.with_txn(|txn| Box::pin(async move {
+        let habit = Habits::find_by_id(id)
+            .filter(habits::Column::UserId.eq(user_id))
+            .lock_exclusive()
+            .one(txn)
+            .await
+            .map_err(|e| AppError::Database(e.into()))?
+            .ok_or_else(|| AppError::NotFound("Habit not found".to_string()))?;
+
+        if checked {
+            if date < habit.starts_on {
+                return Err(AppError::invalid_field("date", "date must not be before the habit starts"));
+            }
+            let mut checkin = habit_checkins::ActiveModel::new();
+            checkin.habit_id = Set(habit.id);
+            checkin.user_id = Set(user_id);
+            checkin.checked_on = Set(date);
+            // Checking in twice on the same day is a no-op
+            HabitCheckins::insert(checkin)
+                .on_conflict(
+                    OnConflict::columns([habit_checkins::Column::HabitId, habit_checkins::Column::CheckedOn])
+                        .do_nothing()
+                        .to_owned(),
+                )
+                .exec_without_returning(txn)
+                .await
+                .map_err(|e| AppError::Database(e.into()))?;
+        } else {
+            HabitCheckins::delete_many()
+                .filter(habit_checkins::Column::HabitId.eq(habit.id))
+                .filter(habit_checkins::Column::CheckedOn.eq(date))
+                .exec(txn)
+                .await
+                .map_err(|e| AppError::Database(e.into()))?;
+        }
+
+        update_streaks(txn, habit, &time).await
+    })).await?;
+
+    // Broadcast websocket message for the recounted streaks
+    tracing::info!("Habit checked in, broadcasting websocket message for user {} (excluding connection {:?})", user_id, connection_id);
+    app_state.ws_state.broadcast_to_user(&user_id, habit_message("UPDATE", user_id, &habit), connection_id).await;
+
+    Ok(habit.into())
+}
+
+/// Mark a habit done for a day, today unless `date` is given
+pub async fn check_in(
+    State(app_state): State<AppState>,
+    auth_user: AuthUser,
+    OriginConnection(connection_id): OriginConnection,
+    Path(id): Path<Uuid>,
+    request: Option<ValidatedJson<CheckinRequest>>,
+) -> Result<Json<ApiResponse<HabitResponse>>> {
+    let ValidatedJson(request) = request.unwrap_or_default();
+    let habit = set_checkin(app_state, auth_user, connection_id, id, request.date, true).await?;
+    Ok(Json(ApiResponse::with_message(habit, "Habit checked in successfully")))
+}
+
+/// Take back the check-in for a day, today unless `date` is given
+pub async fn undo_check_in(
+    State(app_state): State<AppState>,
+    auth_user: AuthUser,
+    OriginConnection(connection_id): OriginConnection,
+    Path(id): Path<Uuid>,
+    Query(request): Query<CheckinRequest>,
+) -> Result<Json<ApiResponse<HabitResponse>>> {
+    let habit = set_checkin(app_state, auth_user, connection_id, id, request.date, false).await?;
+    Ok(Json(ApiResponse::with_message(habit, "Check-in removed successfully")))
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{NaiveDate, Weekday};
+    use chrono_tz::Tz;
+
+    use super::{count_streaks, Streaks};
+    use crate::{models::user_settings::TimePreferences, recurrence};
+
+    fn march(day: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(2025, 3, day).unwrap()
+    }
+
+    fn daily_through(through: NaiveDate) -> Vec<NaiveDate> {
+        recurrence::habit_due_dates("FREQ=DAILY", march(1), through, &TimePreferences::default()).unwrap()
+    }
+
+    #[test]
+    fn a_missed_day_restarts_the_current_streak() {
+        let today = march(10);
+        let checkins: Vec<_> = [1, 2, 3, 5, 6, 7, 8, 9].map(march).to_vec();
+        // Today is still open, so not having checked in yet doesn't break the streak
+        assert_eq!(
+            count_streaks(&daily_through(today), &checkins, today),
+            Streaks { current: 5, longest: 5, expires_on: Some(march(11)) }
+        );
+
+        let checkins: Vec<_> = [1, 2, 3, 4, 5, 6, 7].map(march).to_vec();
+        assert_eq!(
+            count_streaks(&daily_through(today), &checkins, today),
+            Streaks { current: 0, longest: 7, expires_on: None }
+        );
+    }
+
+    #[test]
+    fn checking_in_today_extends_the_streak() {
+        let today = march(3);
+        let checkins: Vec<_> = [1, 2, 3].map(march).to_vec();
+        assert_eq!(
+            count_streaks(&daily_through(today), &checkins, today),
+            Streaks { current: 3, longest: 3, expires_on: Some(march(5)) }
+        );
+    }
+
+    #[test]
+    fn a_weekly_habit_can_be_done_on_any_day_of_its_week() {
+        let today = march(20);
+        // Saturdays, starting March 1st
+        let due = recurrence::habit_due_dates("FREQ=WEEKLY", march(1), today, &TimePreferences::default()).unwrap();
+        let checkins: Vec<_> = [3, 14].map(march).to_vec();
+        assert_eq!(count_streaks(&due, &checkins, today), Streaks { current: 2, longest: 2, expires_on: Some(march(22)) });
+    }
+
+    #[test]
+    fn due_dates_are_local_days_whatever_the_timezone() {
+        // Auckland leaves daylight saving time on April 6th; Pago Pago's date is a day behind it
+        let start = NaiveDate::from_ymd_opt(2025, 4, 4).unwrap();
+        let through = NaiveDate::from_ymd_opt(2025, 4, 8).unwrap();
+        let due_on = |timezone: Tz| {
+            let time = TimePreferences { timezone, week_start: Weekday::Mon };
+            recurrence::habit_due_dates("FREQ=DAILY", start, through, &time).unwrap()
+        };
+        let expected: Vec<_> = start.iter_days().take(7).collect();
+        assert_eq!(due_on(chrono_tz::Pacific::Auckland), expected);
+        assert_eq!(due_on(chrono_tz::Pacific::Pago_Pago), expected);
+
+        // Checked in every local day up to today, as seen from Auckland
+        let checkins: Vec<_> = start.iter_days().take(5).collect();
+        assert_eq!(
+            count_streaks(&due_on(chrono_tz::Pacific::Auckland), &checkins, through),
+            Streaks { current: 5, longest: 5, expires_on: Some(NaiveDate::from_ymd_opt(2025, 4, 10).unwrap()) }
+        );
+    }
+}
@@ -1,6 +1,136 @@
-use axum::Json;
-use crate::{models::ApiResponse, errors::Result};
+use axum::{extract::State, http::StatusCode, Json};
+use sea_orm::{ConnectionTrait, Statement};
+use serde::Serialize;
+use std::time::{Duration, Instant};
+
+use crate::{db::PoolStats, errors::Result, migrator::runner::pending_migrations, models::ApiResponse, state::AppState};
+
+/// How long the readiness probe waits for the database before reporting it down
+const DATABASE_CHECK_TIMEOUT: Duration = Duration::from_secs(2);
 
 pub async fn health_check() -> Result<Json<ApiResponse<String>>> {
     Ok(Json(ApiResponse::new("Backend is running successfully!".to_string())))
 }
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum CheckStatus {
+    Up,
+    Down,
+}
+
+#[derive(Debug, Serialize)]
+pub struct LivenessResponse {
+    pub status: &'static str,
+}
+
+#[derive(Debug, Serialize)]
+pub struct DatabaseCheck {
+    pub status: CheckStatus,
+    pub latency_ms: u64,
+    pub pool: PoolStats,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct MigrationsCheck {
+    pub status: CheckStatus,
+    /// Versions this binary expects that the database doesn't have yet
+    pub pending: Vec<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct WebSocketCheck {
+    pub status: CheckStatus,
+    pub connections: usize,
+    pub shutting_down: bool,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ReadinessChecks {
+    pub database: DatabaseCheck,
+    pub migrations: MigrationsCheck,
+    pub websocket: WebSocketCheck,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ReadinessResponse {
+    /// `ready` when every check is up, `not_ready` otherwise
+    pub status: &'static str,
+    pub checks: ReadinessChecks,
+}
+
+/// Liveness probe: the process is up and serving requests. Deliberately checks no
+/// dependencies, so a database outage doesn't get every replica restarted.
+pub async fn liveness() -> Result<Json<ApiResponse<LivenessResponse>>> {
+    Ok(Json(ApiResponse::new(LivenessResponse { status: "alive" })))
+}
+
+/// Readiness probe: the database answers, its schema is fully migrated and the WebSocket
+/// state accepts connections. Responds with 503 while any of them is down, e.g. during a
+/// shutdown drain, so the replica is taken out of the load balancer.
+pub async fn readiness(
+    State(app_state): State<AppState>,
+) -> Result<(StatusCode, Json<ApiResponse<ReadinessResponse>>)> {
+    let db = &app_state.db.connection;
+
+    let started = Instant::now();
+    let round_trip = tokio::time::timeout(
+        DATABASE_CHECK_TIMEOUT,
+        db.execute(Statement::from_string(db.get_database_backend(), "SELECT 1")),
+    )
+    .await;
+    let latency_ms = started.elapsed().as_millis() as u64;
+    let pool = app_state.db.pool_stats();
+    let database = match round_trip {
+        Ok(Ok(_)) => DatabaseCheck { status: CheckStatus::Up, latency_ms, pool, error: None },
+        Ok(Err(e)) => DatabaseCheck { status: CheckStatus::Down, latency_ms, pool, error: Some(e.to_string()) },
+        Err(_) => DatabaseCheck {
+            status: CheckStatus::Down,
+            latency_ms,
+            pool,
+            error: Some(format!("No response within {}s", DATABASE_CHECK_TIMEOUT.as_secs())),
+        },
+    };
+
+    let migrations = if database.status == CheckStatus::Up {
+        match pending_migrations(db).await {
+            Ok(pending) if pending.is_empty() => MigrationsCheck { status: CheckStatus::Up, pending, error: None },
+            Ok(pending) => MigrationsCheck { status: CheckStatus::Down, pending, error: None },
+            Err(e) => MigrationsCheck { status: CheckStatus::Down, pending: Vec::new(), error: Some(e.to_string()) },
+        }
+    } else {
+        MigrationsCheck {
+            status: CheckStatus::Down,
+            pending: Vec::new(),
+            error: Some("Database unavailable".to_string()),
+        }
+    };
+
+    let shutting_down = app_state.ws_state.is_shutting_down();
+    let websocket = WebSocketCheck {
+        status: if shutting_down { CheckStatus::Down } else { CheckStatus::Up },
+        connections: app_state.ws_state.connection_count().await,
+        shutting_down,
+    };
+
+    let ready = [database.status, migrations.status, websocket.status]
+        .iter()
+        .all(|status| *status == CheckStatus::Up);
+    let (status_code, status) = if ready {
+        (StatusCode::OK, "ready")
+    } else {
+        (StatusCode::SERVICE_UNAVAILABLE, "not_ready")
+    };
+
+    Ok((
+        status_code,
+        Json(ApiResponse::new(ReadinessResponse {
+            status,
+            checks: ReadinessChecks { database, migrations, websocket },
+        })),
+    ))
+}
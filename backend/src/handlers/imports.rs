@@ -0,0 +1,217 @@
+use axum::{
+    body::Bytes,
+    extract::State,
+    http::StatusCode,
+    response::Json,
+};
+use sea_orm::*;
+use std::collections::HashMap;
+use streamline_models::DEFAULT_SCHEMA_VERSION;
+use uuid::Uuid;
+
+use crate::{
+    entities::{can_do_list, projects},
+    errors::{AppError, Result},
+    handlers::{export::insert_rows, projects::ensure_default_project},
+    importers,
+    middleware::{auth::AuthUser, connection::OriginConnection, validation::ValidatedJson},
+    models::{
+        can_do_list::CanDoItemResponse,
+        import::{CommitImportRequest, ImportPlan, ImportProgress, ImportStarted, ImportStatus},
+        project::ProjectResponse,
+        search::join_search_tokens,
+        ApiResponse, ReorderRequest,
+    },
+    quota::QuotaTable,
+    state::AppState,
+    websocket::{WebSocketMessage, WebSocketState},
+};
+
+/// Records created per statement; progress is reported after each batch
+const IMPORT_BATCH_SIZE: usize = 200;
+
+/// Phase one of a Todoist import: read the backup and return its projects and tasks as
+/// plaintext, for the client to encrypt and send back to the commit endpoint. Nothing is stored.
+pub async fn plan_todoist_import(
+    _auth_user: AuthUser,
+    body: Bytes,
+) -> Result<Json<ApiResponse<ImportPlan>>> {
+    let plan = importers::todoist::parse_backup(body.into()).await?;
+    Ok(Json(ApiResponse::new(plan)))
+}
+
+/// Phase two: create the encrypted records in the background. `IMPORT_PROGRESS` messages
+/// report each batch, and the new records are broadcast once the import is committed.
+pub async fn commit_todoist_import(
+    State(app_state): State<AppState>,
+    auth_user: AuthUser,
+    OriginConnection(connection_id): OriginConnection,
+    ValidatedJson(request): ValidatedJson<CommitImportRequest>,
+) -> Result<(StatusCode, Json<ApiResponse<ImportStarted>>)> {
+    let user_id = auth_user.0.id;
+    if !request.projects.is_empty() {
+        app_state.quotas.enforce(&app_state.db.connection, user_id, QuotaTable::Projects, request.projects.len() as u64).await?;
+    }
+    if !request.tasks.is_empty() {
+        app_state.quotas.enforce(&app_state.db.connection, user_id, QuotaTable::CanDoList, request.tasks.len() as u64).await?;
+    }
+
+    let started = ImportStarted { import_id: Uuid::now_v7(), total: request.total() };
+    let import_id = started.import_id;
+    tokio::spawn(async move {
+        let progress = ProgressReporter { ws_state: &app_state.ws_state, user_id, import_id, source: "todoist", total: request.total() };
+        match import_records(&app_state.db.connection, &progress, user_id, request).await {
+            Ok(ws_messages) => {
+                progress.report(ImportStatus::Completed, progress.total, None).await;
+                // Broadcast websocket messages for the imported projects and tasks
+                tracing::info!("Todoist import completed, broadcasting websocket message for user {} (excluding connection {:?})", user_id, connection_id);
+                app_state.ws_state.broadcast_many_to_user(&user_id, ws_messages, connection_id).await;
+            }
+            Err(e) => {
+                tracing::error!("Todoist import {} for user {} failed: {}", import_id, user_id, e);
+                progress.report(ImportStatus::Failed, 0, Some(e.to_string())).await;
+            }
+        }
+    });
+
+    Ok((StatusCode::ACCEPTED, Json(ApiResponse::with_message(started, "Import started"))))
+}
+
+struct ProgressReporter<'a> {
+    ws_state: &'a WebSocketState,
+    user_id: Uuid,
+    import_id: Uuid,
+    source: &'static str,
+    total: usize,
+}
+
+impl ProgressReporter<'_> {
+    /// Sent to every connection, including the one that started the import
+    async fn report(&self, status: ImportStatus, processed: usize, error: Option<String>) {
+        let progress = ImportProgress { source: self.source, status, processed, total: self.total, error };
+        let message = WebSocketMessage {
+            event_type: "IMPORT_PROGRESS".to_string(),
+            table: "imports".to_string(),
+            user_id: self.user_id,
+            record_id: Some(self.import_id),
+            data: Some(serde_json::to_value(progress).unwrap_or_default()),
+        };
+        self.ws_state.broadcast_to_user(&self.user_id, message, None).await;
+    }
+}
+
+/// Create the records in one transaction, so a failed import leaves nothing behind.
+/// Returns the change messages to broadcast once it is committed.
+async fn import_records(
+    db: &DatabaseConnection,
+    progress: &ProgressReporter<'_>,
+    user_id: Uuid,
+    request: CommitImportRequest,
+) -> Result<Vec<WebSocketMessage>> {
+    let txn = db.begin().await
+        .map_err(|e| AppError::Database(e.into()))?;
+    let now = chrono::Utc::now();
+    let mut processed = 0;
+
+    // Parents come before their children in the request, so each batch's links already exist
+    let mut project_ids = HashMap::with_capacity(request.projects.len());
+    let mut imported_projects = Vec::with_capacity(request.projects.len());
+    for (index, project) in request.projects.into_iter().enumerate() {
+        let id = Uuid::now_v7();
+        imported_projects.push(projects::Model {
+            id,
+            user_id,
+            encrypted_data: project.encrypted_data,
+            iv: project.iv,
+            salt: project.salt,
+            schema_version: project.schema_version.unwrap_or(DEFAULT_SCHEMA_VERSION),
+            is_default: false,
+            parent_id: project.parent_key.map(|key| project_ids[&key]),
+            display_order: ReorderRequest::position(index),
+            is_collapsed: false,
+            is_pinned: false,
+            organization_id: None,
+            search_tokens: project.search_tokens.map(join_search_tokens),
+            created_at: now.into(),
+            updated_at: now.into(),
+            deleted_at: None,
+        });
+        project_ids.insert(project.key, id);
+    }
+    for batch in imported_projects.chunks(IMPORT_BATCH_SIZE) {
+        insert_rows(&txn, batch).await?;
+        processed += batch.len();
+        progress.report(ImportStatus::Running, processed, None).await;
+    }
+
+    let mut inbox_id = None;
+    let mut created_default = None;
+    let mut task_ids = HashMap::with_capacity(request.tasks.len());
+    let mut imported_tasks = Vec::with_capacity(request.tasks.len());
+    for (index, task) in request.tasks.into_iter().enumerate() {
+        // Tasks without a project land in the inbox, as when they are created
+        let project_id = match task.project_key {
+            Some(key) => project_ids[&key],
+            None => match inbox_id {
+                Some(inbox_id) => inbox_id,
+                None => {
+                    let (default_project, created) = ensure_default_project(&txn, user_id).await?;
+                    inbox_id = Some(default_project.id);
+                    let project_id = default_project.id;
+                    created_default = created.then_some(default_project);
+                    project_id
+                }
+            },
+        };
+        let id = Uuid::now_v7();
+        imported_tasks.push(can_do_list::Model {
+            id,
+            user_id,
+            project_id: Some(project_id),
+            encrypted_data: task.encrypted_data,
+            iv: task.iv,
+            salt: task.salt,
+            schema_version: task.schema_version.unwrap_or(DEFAULT_SCHEMA_VERSION),
+            display_order: ReorderRequest::position(index),
+            search_bloom: task.search_bloom,
+            search_tokens: task.search_tokens.map(join_search_tokens),
+            due_at: task.due_at.map(Into::into),
+            scheduled_at: None,
+            parent_task_id: task.parent_key.map(|key| task_ids[&key]),
+            completed_at: None,
+            rrule: task.rrule,
+            priority: task.priority,
+            estimated_minutes: task.estimated_minutes,
+            is_pinned: false,
+            pending_plaintext: None,
+            created_at: now.into(),
+            updated_at: now.into(),
+            deleted_at: None,
+        });
+        task_ids.insert(task.key, id);
+    }
+    for batch in imported_tasks.chunks(IMPORT_BATCH_SIZE) {
+        insert_rows(&txn, batch).await?;
+        processed += batch.len();
+        progress.report(ImportStatus::Running, processed, None).await;
+    }
+
+    txn.commit().await
+        .map_err(|e| AppError::Database(e.into()))?;
+
+    let projects = created_default.into_iter().chain(imported_projects).map(|project| WebSocketMessage {
+        event_type: "INSERT".to_string(),
+        table: "projects".to_string(),
+        user_id,
+        record_id: Some(project.id),
+        data: Some(serde_json::to_value(ProjectResponse::from(project)).unwrap_or_default()),
+    });
+    let tasks = imported_tasks.into_iter().map(|task| WebSocketMessage {
+        event_type: "INSERT".to_string(),
+        table: "can_do_list".to_string(),
+        user_id,
+        record_id: Some(task.id),
+        data: Some(serde_json::to_value(CanDoItemResponse::from(task)).unwrap_or_default()),
+    });
+    Ok(projects.chain(tasks).collect())
+}
@@ -0,0 +1,257 @@
+use axum::{extract::State, response::Json};
+use axum_extra::{
+    headers::{authorization::Bearer, Authorization},
+    TypedHeader,
+};
+use sea_orm::{sea_query::OnConflict, *};
+use streamline_client::routes;
+use uuid::Uuid;
+
+use crate::{
+    entities::{prelude::*, can_do_list, inbound_email_addresses},
+    errors::{AppError, Result},
+    handlers::{
+        activity::record_audit,
+        event_attendees::{receive_reply, RSVP_ADDRESS_PREFIX},
+        projects::ensure_default_project,
+    },
+    middleware::{
+        auth::{AuthUser, Impersonator},
+        preconditions::Preconditions,
+        rate_limit::RateLimitPolicy,
+        validation::ValidatedJson,
+    },
+    models::{
+        can_do_list::CanDoItemResponse,
+        inbound_email::{
+            generate_inbound_token, inbound_email_domain, inbound_email_secret, inbound_secret_matches,
+            InboundEmailAddressResponse, InboundEmailRequest,
+        },
+        project::ProjectResponse,
+        ApiResponse,
+    },
+    quota::QuotaTable,
+    state::AppState,
+    websocket::WebSocketMessage,
+};
+
+/// Emails one inbound address turns into tasks, so a leaked address can't flood the inbox
+const INBOUND_ADDRESS_RATE_LIMIT: RateLimitPolicy = RateLimitPolicy::new(30, 10);
+
+fn configured_domain() -> Result<String> {
+    inbound_email_domain().ok_or_else(|| AppError::NotFound("Inbound email is not configured".to_string()))
+}
+
+/// Return the user's email-to-task address. The address is a secret that lets anyone add
+/// tasks, so it isn't shown to impersonation sessions.
+pub async fn get_inbound_address(
+    State(app_state): State<AppState>,
+    auth_user: AuthUser,
+    Impersonator(impersonator): Impersonator,
+) -> Result<Json<ApiResponse<InboundEmailAddressResponse>>> {
+    let domain = configured_domain()?;
+    if impersonator.is_some() {
+        return Err(AppError::Forbidden("The inbound email address is not shown to impersonation sessions".to_string()));
+    }
+
+    let address = InboundEmailAddresses::find_by_id(auth_user.0.id)
+        .one(app_state.db.reader())
+        .await
+        .map_err(|e| AppError::Database(e.into()))?
+        .ok_or_else(|| AppError::NotFound("No inbound email address yet".to_string()))?;
+
+    Ok(Json(ApiResponse::new(InboundEmailAddressResponse::new(address, &domain))))
+}
+
+/// Return the user's email-to-task address, creating it on first use
+pub async fn create_inbound_address(
+    State(app_state): State<AppState>,
+    auth_user: AuthUser,
+) -> Result<Json<ApiResponse<InboundEmailAddressResponse>>> {
+    let domain = configured_domain()?;
+    let db = &app_state.db.connection;
+
+    let mut address_active = inbound_email_addresses::ActiveModel::new();
+    address_active.user_id = Set(auth_user.0.id);
+    address_active.token = Set(generate_inbound_token());
+    InboundEmailAddresses::insert(address_active)
+        .on_conflict(OnConflict::column(inbound_email_addresses::Column::UserId).do_nothing().to_owned())
+        .do_nothing()
+        .exec(db)
+        .await
+        .map_err(|e| AppError::Database(e.into()))?;
+
+    let address = InboundEmailAddresses::find_by_id(auth_user.0.id)
+        .one(db)
+        .await
+        .map_err(|e| AppError::Database(e.into()))?
+        .ok_or_else(|| AppError::Internal("Inbound email address vanished after creation".to_string()))?;
+
+    Ok(Json(ApiResponse::new(InboundEmailAddressResponse::new(address, &domain))))
+}
+
+/// Replace the address, e.g. after it leaked; mail to the old one is rejected from now on
+pub async fn rotate_inbound_address(
+    State(app_state): State<AppState>,
+    auth_user: AuthUser,
+) -> Result<Json<ApiResponse<InboundEmailAddressResponse>>> {
+    let domain = configured_domain()?;
+    let db = &app_state.db.connection;
+
+    let mut address_active = inbound_email_addresses::ActiveModel::new();
+    address_active.user_id = Set(auth_user.0.id);
+    address_active.token = Set(generate_inbound_token());
+    let address = InboundEmailAddresses::insert(address_active)
+        .on_conflict(
+            OnConflict::column(inbound_email_addresses::Column::UserId)
+                .update_columns([inbound_email_addresses::Column::Token, inbound_email_addresses::Column::UpdatedAt])
+                .to_owned(),
+        )
+        .exec_with_returning(db)
+        .await
+        .map_err(|e| AppError::Database(e.into()))?;
+
+    record_audit(db, auth_user.0.id, None, "inbound_email_address_rotated", serde_json::json!({})).await?;
+
+    Ok(Json(ApiResponse::with_message(
+        InboundEmailAddressResponse::new(address, &domain),
+        "Inbound email address rotated successfully",
+    )))
+}
+
+/// Turn off email-to-task; creating the address again makes a new one
+pub async fn delete_inbound_address(
+    State(app_state): State<AppState>,
+    auth_user: AuthUser,
+    preconditions: Preconditions,
+) -> Result<Json<ApiResponse<()>>> {
+    let user_id = auth_user.0.id;
+    app_state.db.with_txn(|txn| Box::pin(async move {
+        let address = InboundEmailAddresses::find_by_id(user_id)
+            .one(txn)
+            .await
+            .map_err(|e| AppError::Database(e.into()))?;
+        if let Some(address) = address {
+            preconditions.check(&address.updated_at)?;
+        }
+        InboundEmailAddresses::delete_by_id(user_id)
+            .exec(txn)
+            .await
+            .map_err(|e| AppError::Database(e.into()))?;
+        Ok(())
+    })).await?;
+
+    Ok(Json(ApiResponse::with_message((), "Inbound email address deleted successfully")))
+}
+
+/// Hook for the mail server: each email sent to a user's secret address becomes a task in
+/// their inbox. The server can't encrypt it, so the task keeps the email in `pending_plaintext`
+/// with empty encrypted fields until one of the user's clients encrypts it. Replies to
+/// invitations come in on `rsvp-` addresses and update the attendee's response instead.
+/// The mail server authenticates with `INBOUND_EMAIL_SECRET` as a bearer token.
+pub async fn receive_email(
+    State(app_state): State<AppState>,
+    authorization: Option<TypedHeader<Authorization<Bearer>>>,
+    ValidatedJson(request): ValidatedJson<InboundEmailRequest>,
+) -> Result<Json<ApiResponse<()>>> {
+    let domain = configured_domain()?;
+    let authorized = inbound_email_secret()
+        .zip(authorization)
+        .is_some_and(|(secret, TypedHeader(authorization))| inbound_secret_matches(authorization.token(), &secret));
+    if !authorized {
+        return Err(AppError::Auth("The inbound email hook needs INBOUND_EMAIL_SECRET as a bearer token".to_string()));
+    }
+    let db = &app_state.db.connection;
+
+    let (reply_tokens, tokens): (Vec<String>, Vec<String>) = request
+        .recipient_tokens(&domain)
+        .into_iter()
+        .partition(|token| token.starts_with(RSVP_ADDRESS_PREFIX));
+    if reply_tokens.is_empty() && tokens.is_empty() {
+        return Err(AppError::Validation(format!("No recipient on {}", domain)));
+    }
+    if !reply_tokens.is_empty() {
+        let reply_tokens = reply_tokens.iter().map(|token| token[RSVP_ADDRESS_PREFIX.len()..].to_string()).collect();
+        let replied = receive_reply(&app_state, reply_tokens, request.calendar.as_deref()).await;
+        if tokens.is_empty() {
+            replied?;
+            return Ok(Json(ApiResponse::with_message((), "Reply received")));
+        }
+        // The email still becomes a task for the other recipients
+        if let Err(e) = replied {
+            tracing::warn!("Recording an invitation reply failed: {}", e);
+        }
+    }
+    let user_ids: Vec<Uuid> = InboundEmailAddresses::find()
+        .filter(inbound_email_addresses::Column::Token.is_in(tokens))
+        .all(db)
+        .await
+        .map_err(|e| AppError::Database(e.into()))?
+        .into_iter()
+        .map(|address| address.user_id)
+        .collect();
+    if user_ids.is_empty() {
+        return Err(AppError::NotFound("Unknown inbound email address".to_string()));
+    }
+    // Addresses over their limit miss this email; when all are, the mail server can retry it later
+    let mut limited = None;
+    let user_ids: Vec<Uuid> = user_ids
+        .into_iter()
+        .filter(|user_id| match app_state.rate_limiter.check_user(*user_id, routes::INBOUND_EMAIL_RECEIVE, INBOUND_ADDRESS_RATE_LIMIT) {
+            Ok(()) => true,
+            Err(e) => {
+                tracing::warn!("Dropping email to the inbound address of user {}: {}", user_id, e);
+                limited = Some(e);
+                false
+            }
+        })
+        .collect();
+    if let (true, Some(e)) = (user_ids.is_empty(), limited) {
+        return Err(e);
+    }
+
+    let pending = serde_json::to_value(request.into_pending_task())?;
+    for user_id in user_ids {
+        app_state.quotas.enforce(db, user_id, QuotaTable::CanDoList, 1).await?;
+
+        let txn = db.begin().await
+            .map_err(|e| AppError::Database(e.into()))?;
+        let (inbox, created_inbox) = ensure_default_project(&txn, user_id).await?;
+
+        let mut item_active = can_do_list::ActiveModel::new();
+        item_active.user_id = Set(user_id);
+        item_active.project_id = Set(Some(inbox.id));
+        item_active.encrypted_data = Set(String::new());
+        item_active.iv = Set(String::new());
+        item_active.salt = Set(String::new());
+        item_active.pending_plaintext = Set(Some(pending.clone()));
+        let item = item_active.insert(&txn).await
+            .map_err(|e| AppError::Database(e.into()))?;
+
+        txn.commit().await
+            .map_err(|e| AppError::Database(e.into()))?;
+
+        // Broadcast websocket messages for the new task, and the inbox if it had to be created
+        tracing::info!("Task created from email, broadcasting websocket message for user {}", user_id);
+        let mut ws_messages = Vec::with_capacity(2);
+        if created_inbox {
+            ws_messages.push(WebSocketMessage {
+                event_type: "INSERT".to_string(),
+                table: "projects".to_string(),
+                user_id,
+                record_id: Some(inbox.id),
+                data: Some(serde_json::to_value(ProjectResponse::from(inbox)).unwrap_or_default()),
+            });
+        }
+        ws_messages.push(WebSocketMessage {
+            event_type: "INSERT".to_string(),
+            table: "can_do_list".to_string(),
+            user_id,
+            record_id: Some(item.id),
+            data: Some(serde_json::to_value(CanDoItemResponse::from(item)).unwrap_or_default()),
+        });
+        app_state.ws_state.broadcast_many_to_user(&user_id, ws_messages, None).await;
+    }
+
+    Ok(Json(ApiResponse::with_message((), "Email received")))
+}
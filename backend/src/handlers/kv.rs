@@ -0,0 +1,164 @@
+use axum::{
+    extract::{Path, Query, State},
+    response::Json,
+};
+use sea_orm::*;
+use uuid::Uuid;
+
+use crate::{
+    entities::{kv_entries, prelude::*},
+    errors::{AppError, ErrorCode, Result},
+    handlers::sync::record_deletion,
+    middleware::{auth::AuthUser, connection::OriginConnection, preconditions::Preconditions, validation::ValidatedJson},
+    models::{
+        kv::{validate_kv_key, KvEntryResponse, KvQuery, SaveKvEntryRequest, MAX_KV_ENTRIES},
+        validate_schema_version, ApiResponse, PageQuery,
+    },
+    state::AppState,
+    websocket::WebSocketMessage,
+};
+use streamline_models::DEFAULT_SCHEMA_VERSION;
+
+fn kv_message(event_type: &str, user_id: Uuid, entry: &kv_entries::Model) -> WebSocketMessage {
+    WebSocketMessage {
+        event_type: event_type.to_string(),
+        table: "kv_entries".to_string(),
+        user_id,
+        record_id: Some(entry.id),
+        data: Some(serde_json::to_value(KvEntryResponse::from(entry.clone())).unwrap_or_default()),
+    }
+}
+
+async fn find_entry<C: ConnectionTrait>(db: &C, user_id: Uuid, key: &str) -> Result<Option<kv_entries::Model>> {
+    KvEntries::find()
+        .filter(kv_entries::Column::UserId.eq(user_id))
+        .filter(kv_entries::Column::Key.eq(key))
+        .one(db)
+        .await
+        .map_err(|e| AppError::Database(e.into()))
+}
+
+/// The user's entries by key, optionally only those under a prefix
+pub async fn list_entries(
+    State(app_state): State<AppState>,
+    auth_user: AuthUser,
+    Query(query): Query<KvQuery>,
+    Query(paging): Query<PageQuery>,
+) -> Result<Json<ApiResponse<Vec<KvEntryResponse>>>> {
+    let mut find = KvEntries::find()
+        .filter(kv_entries::Column::UserId.eq(auth_user.0.id))
+        .order_by_asc(kv_entries::Column::Key);
+    if let Some(prefix) = query.prefix.filter(|prefix| !prefix.is_empty()) {
+        validate_kv_key(&prefix).map_err(|_| AppError::invalid_field("prefix", "prefix may only hold characters allowed in keys"))?;
+        find = find.filter(kv_entries::Column::Key.starts_with(&prefix));
+    }
+    let (entries, meta) = paging.fetch(app_state.db.reader(), find).await?;
+
+    Ok(Json(ApiResponse::list(entries.into_iter().map(Into::into).collect(), meta)))
+}
+
+pub async fn get_entry(
+    State(app_state): State<AppState>,
+    auth_user: AuthUser,
+    Path(key): Path<String>,
+) -> Result<Json<ApiResponse<KvEntryResponse>>> {
+    validate_kv_key(&key)?;
+    let entry = find_entry(app_state.db.reader(), auth_user.0.id, &key)
+        .await?
+        .ok_or_else(|| AppError::NotFound("Key not found".to_string()))?;
+
+    Ok(Json(ApiResponse::new(entry.into())))
+}
+
+/// Store a value under the key, replacing the one there
+pub async fn put_entry(
+    State(app_state): State<AppState>,
+    auth_user: AuthUser,
+    OriginConnection(connection_id): OriginConnection,
+    Path(key): Path<String>,
+    preconditions: Preconditions,
+    ValidatedJson(request): ValidatedJson<SaveKvEntryRequest>,
+) -> Result<Json<ApiResponse<KvEntryResponse>>> {
+    validate_kv_key(&key)?;
+    validate_schema_version("kv_entries", request.schema_version)?;
+    let user_id = auth_user.0.id;
+    let schema_version = request.schema_version.unwrap_or(DEFAULT_SCHEMA_VERSION);
+    let (entry, created) = app_state.db.with_txn(|txn| Box::pin(async move {
+        if let Some(existing) = find_entry(txn, user_id, &key).await? {
+            preconditions.check(&existing.updated_at)?;
+            let mut entry_active: kv_entries::ActiveModel = existing.into();
+            entry_active.encrypted_data = Set(request.encrypted_data);
+            entry_active.iv = Set(request.iv);
+            entry_active.salt = Set(request.salt);
+            entry_active.schema_version = Set(schema_version);
+            let entry = entry_active.update(txn).await
+                .map_err(|e| AppError::Database(e.into()))?;
+            return Ok((entry, false));
+        }
+
+        let entries = KvEntries::find()
+            .filter(kv_entries::Column::UserId.eq(user_id))
+            .count(txn)
+            .await
+            .map_err(|e| AppError::Database(e.into()))?;
+        if entries >= MAX_KV_ENTRIES {
+            return Err(AppError::Coded(
+                ErrorCode::QuotaExceeded,
+                format!("At most {} keys are allowed", MAX_KV_ENTRIES),
+            ));
+        }
+        let mut entry_active = kv_entries::ActiveModel::new();
+        entry_active.user_id = Set(user_id);
+        entry_active.key = Set(key);
+        entry_active.encrypted_data = Set(request.encrypted_data);
+        entry_active.iv = Set(request.iv);
+        entry_active.salt = Set(request.salt);
+        entry_active.schema_version = Set(schema_version);
+        let entry = entry_active.insert(txn).await
+            .map_err(|e| AppError::Database(e.into()))?;
+        Ok((entry, true))
+    })).await?;
+
+    // Broadcast websocket message for the stored value
+    let event_type = if created { "INSERT" } else { "UPDATE" };
+    tracing::info!("Key saved, broadcasting websocket message for user {} (excluding connection {:?})", user_id, connection_id);
+    app_state.ws_state.broadcast_to_user(&user_id, kv_message(event_type, user_id, &entry), connection_id).await;
+
+    Ok(Json(ApiResponse::with_message(entry.into(), "Key saved successfully")))
+}
+
+pub async fn delete_entry(
+    State(app_state): State<AppState>,
+    auth_user: AuthUser,
+    OriginConnection(connection_id): OriginConnection,
+    Path(key): Path<String>,
+    preconditions: Preconditions,
+) -> Result<Json<ApiResponse<()>>> {
+    validate_kv_key(&key)?;
+    let user_id = auth_user.0.id;
+    let id = app_state.db.with_txn(|txn| Box::pin(async move {
+        let entry = find_entry(txn, user_id, &key)
+            .await?
+            .ok_or_else(|| AppError::NotFound("Key not found".to_string()))?;
+        preconditions.check(&entry.updated_at)?;
+        KvEntries::delete_by_id(entry.id)
+            .exec(txn)
+            .await
+            .map_err(|e| AppError::Database(e.into()))?;
+        record_deletion(txn, user_id, "kv_entries", entry.id).await?;
+        Ok(entry.id)
+    })).await?;
+
+    // Broadcast websocket message for the deleted key
+    tracing::info!("Key deleted, broadcasting websocket message for user {} (excluding connection {:?})", user_id, connection_id);
+    let ws_message = WebSocketMessage {
+        event_type: "DELETE".to_string(),
+        table: "kv_entries".to_string(),
+        user_id,
+        record_id: Some(id),
+        data: None,
+    };
+    app_state.ws_state.broadcast_to_user(&user_id, ws_message, connection_id).await;
+
+    Ok(Json(ApiResponse::with_message((), "Key deleted successfully")))
+}
@@ -0,0 +1,85 @@
+use std::collections::BTreeMap;
+
+use axum::{extract::State, response::Json};
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+
+use crate::{
+    errors::Result,
+    features::{Feature, Rollout},
+    middleware::{
+        rate_limit::RateLimitPolicy,
+        versioning::{CURRENT_API_VERSION, DEFAULT_API_VERSION, MIN_API_VERSION},
+    },
+    models::{ApiResponse, MAX_BODY_BYTES, MAX_PAGE_LIMIT},
+    state::AppState,
+    websocket::protocol::{CURRENT_PROTOCOL_VERSION, MIN_PROTOCOL_VERSION},
+};
+
+#[derive(Debug, Serialize)]
+pub struct VersionRange {
+    pub min: u32,
+    pub current: u32,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ApiVersionsMeta {
+    pub min: u32,
+    pub current: u32,
+    /// Version of requests that name none
+    pub default: u32,
+    /// When each deprecated version stops being served
+    pub sunsets: BTreeMap<u32, DateTime<Utc>>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct WebSocketMeta {
+    pub protocol_versions: VersionRange,
+    pub heartbeat_interval_ms: u64,
+    pub heartbeat_timeout_ms: u64,
+}
+
+#[derive(Debug, Serialize)]
+pub struct LimitsMeta {
+    /// Largest request body most routes accept; uploads such as imports allow more
+    pub max_body_bytes: usize,
+    pub max_page_limit: u64,
+    /// Per user, or per address before signing in; `null` when requests aren't limited
+    pub rate_limit: Option<RateLimitPolicy>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct MetaResponse {
+    pub server_version: &'static str,
+    pub api_versions: ApiVersionsMeta,
+    pub websocket: WebSocketMeta,
+    /// Instance-wide rollouts; `GET /api/features` tells whether each is on for the signed-in user
+    pub features: BTreeMap<Feature, Rollout>,
+    pub limits: LimitsMeta,
+}
+
+/// What this server speaks and allows, so clients can adapt or warn about an incompatible
+/// version before signing in
+pub async fn server_meta(State(app_state): State<AppState>) -> Result<Json<ApiResponse<MetaResponse>>> {
+    let heartbeat = app_state.ws_state.heartbeat;
+    Ok(Json(ApiResponse::new(MetaResponse {
+        server_version: env!("CARGO_PKG_VERSION"),
+        api_versions: ApiVersionsMeta {
+            min: MIN_API_VERSION,
+            current: CURRENT_API_VERSION,
+            default: DEFAULT_API_VERSION,
+            sunsets: app_state.api_versions.sunsets().clone(),
+        },
+        websocket: WebSocketMeta {
+            protocol_versions: VersionRange { min: MIN_PROTOCOL_VERSION, current: CURRENT_PROTOCOL_VERSION },
+            heartbeat_interval_ms: heartbeat.interval.as_millis() as u64,
+            heartbeat_timeout_ms: heartbeat.timeout.as_millis() as u64,
+        },
+        features: app_state.features.rollouts(),
+        limits: LimitsMeta {
+            max_body_bytes: MAX_BODY_BYTES,
+            max_page_limit: MAX_PAGE_LIMIT,
+            rate_limit: app_state.rate_limiter.default_policy(),
+        },
+    })))
+}
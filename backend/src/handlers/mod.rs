@@ -1,7 +1,42 @@
 pub mod auth;
+pub mod activity;
+pub mod admin;
 pub mod projects;
 pub mod can_do_list;
 pub mod calendars;
 pub mod calendar_events;
 pub mod health;
 pub mod user_settings;
+pub mod sync;
+pub mod search;
+pub mod trash;
+pub mod usage;
+pub mod reports;
+pub mod recurrence;
+pub mod reminders;
+pub mod time_entries;
+pub mod event_attendees;
+pub mod notes;
+pub mod project_members;
+pub mod organizations;
+pub mod polls;
+pub mod booking_pages;
+pub mod calendar_subscriptions;
+pub mod export;
+pub mod imports;
+pub mod webhooks;
+pub mod inbound_email;
+pub mod revisions;
+pub mod schedule;
+pub mod scheduling_constraints;
+pub mod focus_sessions;
+pub mod habits;
+pub mod goals;
+pub mod templates;
+pub mod quick_add;
+pub mod features;
+pub mod settings;
+pub mod announcements;
+pub mod meta;
+pub mod kv;
+pub mod conflicts;
@@ -0,0 +1,356 @@
+use axum::{
+    extract::{Path, Query, State},
+    response::Json,
+};
+use sea_orm::*;
+use uuid::Uuid;
+
+use crate::{
+    entities::{prelude::*, calendar_events, notes, projects, record_revisions::RevisionAction},
+    errors::Result,
+    handlers::{
+        revisions::{find_revision, list_revisions, record_revision},
+        sync::record_deletion,
+        trash::DeleteQuery,
+    },
+    middleware::{auth::AuthUser, connection::OriginConnection, preconditions::Preconditions, validation::ValidatedJson},
+    models::{
+        note::{CreateNoteRequest, NoteQuery, NoteResponse, UpdateNoteRequest},
+        revision::{RevisionResponse, Revisioned},
+        ApiResponse, PageQuery, ApplyTo, IntoRecord, FieldsQuery, validate_schema_version,
+    },
+    quota::QuotaTable,
+    state::AppState,
+    websocket::WebSocketMessage,
+};
+
+async fn project_is_active<C: ConnectionTrait>(db: &C, user_id: Uuid, project_id: Uuid) -> Result<bool> {
+    let project = Projects::find_by_id(project_id)
+        .filter(projects::Column::UserId.eq(user_id))
+        .filter(projects::Column::DeletedAt.is_null())
+        .one(db)
+        .await
+        .map_err(|e| crate::errors::AppError::Database(e.into()))?;
+    Ok(project.is_some())
+}
+
+async fn event_is_active<C: ConnectionTrait>(db: &C, user_id: Uuid, event_id: Uuid) -> Result<bool> {
+    let event = CalendarEvents::find_by_id(event_id)
+        .filter(calendar_events::Column::UserId.eq(user_id))
+        .filter(calendar_events::Column::DeletedAt.is_null())
+        .one(db)
+        .await
+        .map_err(|e| crate::errors::AppError::Database(e.into()))?;
+    Ok(event.is_some())
+}
+
+/// Check the project and event a note is being linked to; both must be active records of the user
+pub(crate) async fn validate_note_links<C: ConnectionTrait>(
+    db: &C,
+    user_id: Uuid,
+    project_id: Option<Uuid>,
+    event_id: Option<Uuid>,
+) -> Result<()> {
+    if let Some(project_id) = project_id
+        && !project_is_active(db, user_id, project_id).await?
+    {
+        return Err(crate::errors::AppError::NotFound("Project not found".to_string()));
+    }
+    if let Some(event_id) = event_id
+        && !event_is_active(db, user_id, event_id).await?
+    {
+        return Err(crate::errors::AppError::NotFound("Calendar event not found".to_string()));
+    }
+    Ok(())
+}
+
+pub async fn list_notes(
+    State(app_state): State<AppState>,
+    auth_user: AuthUser,
+    Query(query): Query<NoteQuery>,
+    Query(fields): Query<FieldsQuery>,
+    Query(paging): Query<PageQuery>,
+) -> Result<Json<ApiResponse<Vec<serde_json::Value>>>> {
+    let mut find = Notes::find()
+        .filter(notes::Column::UserId.eq(auth_user.0.id))
+        .filter(notes::Column::DeletedAt.is_null());
+
+    if let Some(project_id) = query.project_id {
+        find = find.filter(notes::Column::ProjectId.eq(project_id));
+    }
+    if let Some(event_id) = query.event_id {
+        find = find.filter(notes::Column::EventId.eq(event_id));
+    }
+
+    let find = find
+        .order_by_desc(notes::Column::UpdatedAt);
+    let (notes, meta) = paging.fetch(app_state.db.reader(), find).await?;
+
+    let response: Vec<NoteResponse> = notes.into_iter().map(|note| note.into()).collect();
+    Ok(Json(ApiResponse::list(fields.select(response)?, meta)))
+}
+
+pub async fn get_note(
+    State(app_state): State<AppState>,
+    auth_user: AuthUser,
+    Path(id): Path<Uuid>,
+) -> Result<Json<ApiResponse<NoteResponse>>> {
+    let note = Notes::find_by_id(id)
+        .filter(notes::Column::UserId.eq(auth_user.0.id))
+        .filter(notes::Column::DeletedAt.is_null())
+        .one(app_state.db.reader())
+        .await
+        .map_err(|e| crate::errors::AppError::Database(e.into()))?
+        .ok_or_else(|| crate::errors::AppError::NotFound("Note not found".to_string()))?;
+
+    Ok(Json(ApiResponse::new(note.into())))
+}
+
+pub async fn create_note(
+    State(app_state): State<AppState>,
+    auth_user: AuthUser,
+    OriginConnection(connection_id): OriginConnection,
+    ValidatedJson(request): ValidatedJson<CreateNoteRequest>,
+) -> Result<Json<ApiResponse<NoteResponse>>> {
+    validate_schema_version("notes", request.schema_version)?;
+    validate_note_links(&app_state.db.connection, auth_user.0.id, request.project_id, request.event_id).await?;
+    app_state.quotas.enforce(&app_state.db.connection, auth_user.0.id, QuotaTable::Notes, 1).await?;
+    let client_id = request.id;
+    let note_active = request.into_active_model(auth_user.0.id);
+
+    let note = note_active.insert(&app_state.db.connection).await
+        .map_err(|e| crate::errors::AppError::from_insert(e, client_id))?;
+
+    // Broadcast websocket message for note creation
+    tracing::info!("Note created, broadcasting websocket message for user {} (excluding connection {:?})", auth_user.0.id, connection_id);
+    let ws_message = WebSocketMessage {
+        event_type: "INSERT".to_string(),
+        table: "notes".to_string(),
+        user_id: auth_user.0.id,
+        record_id: Some(note.id),
+        data: Some(serde_json::to_value(NoteResponse::from(note.clone())).unwrap_or_default()),
+    };
+    app_state.ws_state.broadcast_to_user(&auth_user.0.id, ws_message, connection_id).await;
+
+    Ok(Json(ApiResponse::with_message(note.into(), "Note created successfully")))
+}
+
+pub async fn update_note(
+    State(app_state): State<AppState>,
+    auth_user: AuthUser,
+    OriginConnection(connection_id): OriginConnection,
+    Path(id): Path<Uuid>,
+    preconditions: Preconditions,
+    ValidatedJson(request): ValidatedJson<UpdateNoteRequest>,
+) -> Result<Json<ApiResponse<NoteResponse>>> {
+    validate_schema_version("notes", request.schema_version)?;
+    let user_id = auth_user.0.id;
+    let updated_note = app_state.db.with_txn(|txn| Box::pin(async move {
+        let note = Notes::find_by_id(id)
+            .filter(notes::Column::UserId.eq(user_id))
+            .filter(notes::Column::DeletedAt.is_null())
+            .one(txn)
+            .await
+            .map_err(|e| crate::errors::AppError::Database(e.into()))?
+            .ok_or_else(|| crate::errors::AppError::NotFound("Note not found".to_string()))?;
+        preconditions.check(&note.updated_at)?;
+
+        validate_note_links(
+            txn,
+            user_id,
+            request.project_id.flatten().filter(|id| note.project_id != Some(*id)),
+            request.event_id.flatten().filter(|id| note.event_id != Some(*id)),
+        )
+        .await?;
+
+        record_revision(txn, user_id, RevisionAction::Update, &note).await?;
+        let mut note_active: notes::ActiveModel = note.into();
+        request.apply_to(&mut note_active);
+
+        note_active.update(txn).await
+            .map_err(|e| crate::errors::AppError::Database(e.into()))
+    })).await?;
+
+    // Broadcast websocket message for note update
+    tracing::info!("Note updated, broadcasting websocket message for user {} (excluding connection {:?})", auth_user.0.id, connection_id);
+    let ws_message = WebSocketMessage {
+        event_type: "UPDATE".to_string(),
+        table: "notes".to_string(),
+        user_id: auth_user.0.id,
+        record_id: Some(updated_note.id),
+        data: Some(serde_json::to_value(NoteResponse::from(updated_note.clone())).unwrap_or_default()),
+    };
+    app_state.ws_state.broadcast_to_user(&auth_user.0.id, ws_message, connection_id).await;
+
+    Ok(Json(ApiResponse::with_message(updated_note.into(), "Note updated successfully")))
+}
+
+/// Prior versions of a note, newest first
+pub async fn list_note_revisions(
+    State(app_state): State<AppState>,
+    auth_user: AuthUser,
+    Path(id): Path<Uuid>,
+    Query(paging): Query<PageQuery>,
+) -> Result<Json<ApiResponse<Vec<RevisionResponse<NoteResponse>>>>> {
+    let db = app_state.db.reader();
+    let note = Notes::find_by_id(id)
+        .filter(notes::Column::UserId.eq(auth_user.0.id))
+        .filter(notes::Column::DeletedAt.is_null())
+        .one(db)
+        .await
+        .map_err(|e| crate::errors::AppError::Database(e.into()))?
+        .ok_or_else(|| crate::errors::AppError::NotFound("Note not found".to_string()))?;
+
+    let (response, meta) = paging.page(list_revisions::<_, notes::Model, _>(db, note.id).await?)?;
+    Ok(Json(ApiResponse::list(response, meta)))
+}
+
+/// Bring back a note's content as of one of its revisions; the version replaced becomes a revision itself
+pub async fn revert_note(
+    State(app_state): State<AppState>,
+    auth_user: AuthUser,
+    OriginConnection(connection_id): OriginConnection,
+    Path((id, revision_id)): Path<(Uuid, Uuid)>,
+) -> Result<Json<ApiResponse<NoteResponse>>> {
+    let user_id = auth_user.0.id;
+    let reverted_note = app_state.db.with_txn(|txn| Box::pin(async move {
+        let note = Notes::find_by_id(id)
+            .filter(notes::Column::UserId.eq(user_id))
+            .filter(notes::Column::DeletedAt.is_null())
+            .one(txn)
+            .await
+            .map_err(|e| crate::errors::AppError::Database(e.into()))?
+            .ok_or_else(|| crate::errors::AppError::NotFound("Note not found".to_string()))?;
+        let prior: notes::Model = find_revision(txn, id, revision_id).await?;
+
+        record_revision(txn, user_id, RevisionAction::Revert, &note).await?;
+        let mut note_active: notes::ActiveModel = note.into();
+        notes::Model::revert(&mut note_active, prior);
+        note_active.update(txn).await
+            .map_err(|e| crate::errors::AppError::Database(e.into()))
+    })).await?;
+
+    // Broadcast websocket message for note update
+    tracing::info!("Note reverted, broadcasting websocket message for user {} (excluding connection {:?})", user_id, connection_id);
+    let ws_message = WebSocketMessage {
+        event_type: "UPDATE".to_string(),
+        table: "notes".to_string(),
+        user_id,
+        record_id: Some(reverted_note.id),
+        data: Some(serde_json::to_value(NoteResponse::from(reverted_note.clone())).unwrap_or_default()),
+    };
+    app_state.ws_state.broadcast_to_user(&user_id, ws_message, connection_id).await;
+
+    Ok(Json(ApiResponse::with_message(reverted_note.into(), "Note reverted successfully")))
+}
+
+pub async fn delete_note(
+    State(app_state): State<AppState>,
+    auth_user: AuthUser,
+    OriginConnection(connection_id): OriginConnection,
+    Path(id): Path<Uuid>,
+    preconditions: Preconditions,
+    Query(query): Query<DeleteQuery>,
+) -> Result<Json<ApiResponse<()>>> {
+    let permanent = query.permanent.unwrap_or(false);
+
+    let mut find = Notes::find_by_id(id).filter(notes::Column::UserId.eq(auth_user.0.id));
+    if !permanent {
+        find = find.filter(notes::Column::DeletedAt.is_null());
+    }
+    let note = find
+        .one(&app_state.db.connection)
+        .await
+        .map_err(|e| crate::errors::AppError::Database(e.into()))?
+        .ok_or_else(|| crate::errors::AppError::NotFound("Note not found".to_string()))?;
+    preconditions.check(&note.updated_at)?;
+
+    if permanent {
+        let txn = app_state.db.connection.begin().await
+            .map_err(|e| crate::errors::AppError::Database(e.into()))?;
+        Notes::delete_by_id(note.id)
+            .exec(&txn)
+            .await
+            .map_err(|e| crate::errors::AppError::Database(e.into()))?;
+        record_deletion(&txn, auth_user.0.id, "notes", note.id).await?;
+        txn.commit().await
+            .map_err(|e| crate::errors::AppError::Database(e.into()))?;
+    } else {
+        let txn = app_state.db.connection.begin().await
+            .map_err(|e| crate::errors::AppError::Database(e.into()))?;
+        record_revision(&txn, auth_user.0.id, RevisionAction::Delete, &note).await?;
+        let mut note_active: notes::ActiveModel = note.into();
+        note_active.deleted_at = Set(Some(chrono::Utc::now().into()));
+        note_active.update(&txn).await
+            .map_err(|e| crate::errors::AppError::Database(e.into()))?;
+        txn.commit().await
+            .map_err(|e| crate::errors::AppError::Database(e.into()))?;
+    }
+
+    // Broadcast websocket message for note deletion
+    tracing::info!("Note deleted, broadcasting websocket message for user {} (excluding connection {:?})", auth_user.0.id, connection_id);
+    let ws_message = WebSocketMessage {
+        event_type: "DELETE".to_string(),
+        table: "notes".to_string(),
+        user_id: auth_user.0.id,
+        record_id: Some(id),
+        data: None,
+    };
+    app_state.ws_state.broadcast_to_user(&auth_user.0.id, ws_message, connection_id).await;
+
+    let message = if permanent { "Note deleted successfully" } else { "Note moved to trash" };
+    Ok(Json(ApiResponse::with_message((), message)))
+}
+
+pub async fn restore_note(
+    State(app_state): State<AppState>,
+    auth_user: AuthUser,
+    OriginConnection(connection_id): OriginConnection,
+    Path(id): Path<Uuid>,
+) -> Result<Json<ApiResponse<NoteResponse>>> {
+    let user_id = auth_user.0.id;
+    let restored_note = app_state.db.with_txn(|txn| Box::pin(async move {
+        let note = Notes::find_by_id(id)
+            .filter(notes::Column::UserId.eq(user_id))
+            .filter(notes::Column::DeletedAt.is_not_null())
+            .one(txn)
+            .await
+            .map_err(|e| crate::errors::AppError::Database(e.into()))?
+            .ok_or_else(|| crate::errors::AppError::NotFound("Note not found in trash".to_string()))?;
+
+        // The linked project or event may have been trashed in the meantime; unlink it
+        let project_active = match note.project_id {
+            Some(project_id) => project_is_active(txn, user_id, project_id).await?,
+            None => true,
+        };
+        let event_active = match note.event_id {
+            Some(event_id) => event_is_active(txn, user_id, event_id).await?,
+            None => true,
+        };
+
+        let mut note_active: notes::ActiveModel = note.into();
+        note_active.deleted_at = Set(None);
+        if !project_active {
+            note_active.project_id = Set(None);
+        }
+        if !event_active {
+            note_active.event_id = Set(None);
+        }
+
+        note_active.update(txn).await
+            .map_err(|e| crate::errors::AppError::Database(e.into()))
+    })).await?;
+
+    // Broadcast websocket message for note restore; clients dropped the record on delete
+    tracing::info!("Note restored, broadcasting websocket message for user {} (excluding connection {:?})", auth_user.0.id, connection_id);
+    let ws_message = WebSocketMessage {
+        event_type: "INSERT".to_string(),
+        table: "notes".to_string(),
+        user_id: auth_user.0.id,
+        record_id: Some(restored_note.id),
+        data: Some(serde_json::to_value(NoteResponse::from(restored_note.clone())).unwrap_or_default()),
+    };
+    app_state.ws_state.broadcast_to_user(&auth_user.0.id, ws_message, connection_id).await;
+
+    Ok(Json(ApiResponse::with_message(restored_note.into(), "Note restored successfully")))
+}
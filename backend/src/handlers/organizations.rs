@@ -0,0 +1,514 @@
+use axum::{
+    extract::{Path, Query, State},
+    response::Json,
+};
+use sea_orm::{sea_query::Expr, *};
+use uuid::Uuid;
+
+use crate::{
+    entities::{
+        calendars,
+        organization_members::{self, OrganizationRole},
+        organizations, prelude::*,
+        project_members::ProjectRole,
+        projects, users,
+    },
+    errors::{AppError, Result},
+    middleware::{auth::AuthUser, connection::OriginConnection, preconditions::Preconditions, validation::ValidatedJson},
+    models::{
+        organization::{
+            normalize_organization_name, AddOrganizationMemberRequest, CreateOrganizationRequest,
+            OrganizationMemberResponse, OrganizationResponse, UpdateOrganizationMemberRequest, UpdateOrganizationRequest,
+        },
+        ApiResponse, PageQuery,
+    },
+    state::AppState,
+    websocket::WebSocketMessage,
+};
+
+/// The organization and the user's membership in it; not found unless the user is a member
+pub(crate) async fn organization_membership<C: ConnectionTrait>(
+    db: &C,
+    user_id: Uuid,
+    organization_id: Uuid,
+) -> Result<(organizations::Model, organization_members::Model)> {
+    let (member, organization) = OrganizationMembers::find()
+        .find_also_related(Organizations)
+        .filter(organization_members::Column::OrganizationId.eq(organization_id))
+        .filter(organization_members::Column::UserId.eq(user_id))
+        .one(db)
+        .await
+        .map_err(|e| AppError::Database(e.into()))?
+        .ok_or_else(|| AppError::NotFound("Organization not found".to_string()))?;
+    let organization = organization.ok_or_else(|| AppError::NotFound("Organization not found".to_string()))?;
+    Ok((organization, member))
+}
+
+/// Ids of the organizations whose projects and calendars the user may access with at least `min_role`
+pub(crate) async fn organization_ids<C: ConnectionTrait>(db: &C, user_id: Uuid, min_role: ProjectRole) -> Result<Vec<Uuid>> {
+    OrganizationMembers::find()
+        .select_only()
+        .column(organization_members::Column::OrganizationId)
+        .filter(organization_members::Column::UserId.eq(user_id))
+        .filter(organization_members::Column::Role.is_in(OrganizationRole::with_project_role(min_role)))
+        .into_tuple()
+        .all(db)
+        .await
+        .map_err(|e| AppError::Database(e.into()))
+}
+
+/// The user's rights in a project or calendar through the organization it is shared with, if any
+pub(crate) async fn organization_project_role<C: ConnectionTrait>(
+    db: &C,
+    user_id: Uuid,
+    organization_id: Option<Uuid>,
+) -> Result<Option<ProjectRole>> {
+    let Some(organization_id) = organization_id else {
+        return Ok(None);
+    };
+    let role: Option<OrganizationRole> = OrganizationMembers::find()
+        .select_only()
+        .column(organization_members::Column::Role)
+        .filter(organization_members::Column::OrganizationId.eq(organization_id))
+        .filter(organization_members::Column::UserId.eq(user_id))
+        .into_tuple()
+        .one(db)
+        .await
+        .map_err(|e| AppError::Database(e.into()))?;
+    Ok(role.map(OrganizationRole::project_role))
+}
+
+/// A project or calendar can only be shared with an organization its user belongs to
+pub(crate) async fn ensure_shareable<C: ConnectionTrait>(db: &C, user_id: Uuid, organization_id: Option<Uuid>) -> Result<()> {
+    if let Some(organization_id) = organization_id {
+        organization_membership(db, user_id, organization_id).await?;
+    }
+    Ok(())
+}
+
+/// `(user_id, organization_id)` of every member of the given organizations
+pub(crate) async fn organization_memberships<C: ConnectionTrait>(
+    db: &C,
+    organization_ids: impl IntoIterator<Item = Option<Uuid>>,
+) -> Result<Vec<(Uuid, Uuid)>> {
+    let mut organization_ids: Vec<Uuid> = organization_ids.into_iter().flatten().collect();
+    organization_ids.sort();
+    organization_ids.dedup();
+    if organization_ids.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    OrganizationMembers::find()
+        .select_only()
+        .column(organization_members::Column::UserId)
+        .column(organization_members::Column::OrganizationId)
+        .filter(organization_members::Column::OrganizationId.is_in(organization_ids))
+        .into_tuple()
+        .all(db)
+        .await
+        .map_err(|e| AppError::Database(e.into()))
+}
+
+/// Users who see changes to a project or calendar of `owner_id` shared with `organization_id`
+pub(crate) async fn organization_audience<C: ConnectionTrait>(
+    db: &C,
+    owner_id: Uuid,
+    organization_ids: impl IntoIterator<Item = Option<Uuid>>,
+) -> Result<Vec<Uuid>> {
+    let mut audience = vec![owner_id];
+    for (user_id, _) in organization_memberships(db, organization_ids).await? {
+        if !audience.contains(&user_id) {
+            audience.push(user_id);
+        }
+    }
+    Ok(audience)
+}
+
+/// Admins manage members; only owners grant, change or revoke the owner role
+fn ensure_can_manage(
+    actor: OrganizationRole,
+    current: Option<OrganizationRole>,
+    requested: Option<OrganizationRole>,
+) -> Result<()> {
+    if actor < OrganizationRole::Admin {
+        return Err(AppError::Forbidden("Only admins can manage organization members".to_string()));
+    }
+    let touches_owner = current == Some(OrganizationRole::Owner) || requested == Some(OrganizationRole::Owner);
+    if touches_owner && actor < OrganizationRole::Owner {
+        return Err(AppError::Forbidden("Only owners can manage the organization's owners".to_string()));
+    }
+    Ok(())
+}
+
+/// Fails if `user_id` is the organization's last owner
+async fn ensure_other_owner<C: ConnectionTrait>(db: &C, organization_id: Uuid, user_id: Uuid) -> Result<()> {
+    let owners = OrganizationMembers::find()
+        .filter(organization_members::Column::OrganizationId.eq(organization_id))
+        .filter(organization_members::Column::Role.eq(OrganizationRole::Owner))
+        .filter(organization_members::Column::UserId.ne(user_id))
+        .count(db)
+        .await
+        .map_err(|e| AppError::Database(e.into()))?;
+    if owners == 0 {
+        return Err(AppError::Conflict("An organization needs at least one owner".to_string()));
+    }
+    Ok(())
+}
+
+async fn member_ids<C: ConnectionTrait>(db: &C, organization_id: Uuid) -> Result<Vec<Uuid>> {
+    Ok(organization_memberships(db, [Some(organization_id)])
+        .await?
+        .into_iter()
+        .map(|(user_id, _)| user_id)
+        .collect())
+}
+
+fn organization_message(event_type: &str, user_id: Uuid, table: &str, record_id: Uuid, data: Option<serde_json::Value>) -> WebSocketMessage {
+    WebSocketMessage {
+        event_type: event_type.to_string(),
+        table: table.to_string(),
+        user_id,
+        record_id: Some(record_id),
+        data,
+    }
+}
+
+/// Organizations the authenticated user belongs to
+pub async fn list_organizations(
+    State(app_state): State<AppState>,
+    auth_user: AuthUser,
+    Query(paging): Query<PageQuery>,
+) -> Result<Json<ApiResponse<Vec<OrganizationResponse>>>> {
+    let memberships = OrganizationMembers::find()
+        .find_also_related(Organizations)
+        .filter(organization_members::Column::UserId.eq(auth_user.0.id))
+        .order_by_asc(organization_members::Column::CreatedAt)
+        .all(app_state.db.reader())
+        .await
+        .map_err(|e| AppError::Database(e.into()))?;
+
+    let response = memberships
+        .into_iter()
+        .filter_map(|(member, organization)| organization.map(|organization| OrganizationResponse::new(organization, member.role)))
+        .collect();
+    let (response, meta) = paging.page(response)?;
+    Ok(Json(ApiResponse::list(response, meta)))
+}
+
+pub async fn get_organization(
+    State(app_state): State<AppState>,
+    auth_user: AuthUser,
+    Path(id): Path<Uuid>,
+) -> Result<Json<ApiResponse<OrganizationResponse>>> {
+    let (organization, member) = organization_membership(app_state.db.reader(), auth_user.0.id, id).await?;
+    Ok(Json(ApiResponse::new(OrganizationResponse::new(organization, member.role))))
+}
+
+/// Create an organization with the authenticated user as its owner
+pub async fn create_organization(
+    State(app_state): State<AppState>,
+    auth_user: AuthUser,
+    OriginConnection(connection_id): OriginConnection,
+    ValidatedJson(request): ValidatedJson<CreateOrganizationRequest>,
+) -> Result<Json<ApiResponse<OrganizationResponse>>> {
+    let name = normalize_organization_name(&request.name)?;
+
+    let txn = app_state.db.connection.begin().await
+        .map_err(|e| AppError::Database(e.into()))?;
+
+    let mut organization_active = organizations::ActiveModel::new();
+    organization_active.name = Set(name);
+    let organization = organization_active.insert(&txn).await
+        .map_err(|e| AppError::Database(e.into()))?;
+
+    let mut member_active = organization_members::ActiveModel::new();
+    member_active.organization_id = Set(organization.id);
+    member_active.user_id = Set(auth_user.0.id);
+    member_active.role = Set(OrganizationRole::Owner);
+    member_active.insert(&txn).await
+        .map_err(|e| AppError::Database(e.into()))?;
+
+    txn.commit().await
+        .map_err(|e| AppError::Database(e.into()))?;
+    let response = OrganizationResponse::new(organization, OrganizationRole::Owner);
+
+    tracing::info!("Organization created, broadcasting websocket message for user {} (excluding connection {:?})", auth_user.0.id, connection_id);
+    let data = serde_json::to_value(&response).ok();
+    let ws_message = organization_message("INSERT", auth_user.0.id, "organizations", response.id, data);
+    app_state.ws_state.broadcast_to_user(&auth_user.0.id, ws_message, connection_id).await;
+
+    Ok(Json(ApiResponse::with_message(response, "Organization created successfully")))
+}
+
+pub async fn update_organization(
+    State(app_state): State<AppState>,
+    auth_user: AuthUser,
+    OriginConnection(connection_id): OriginConnection,
+    Path(id): Path<Uuid>,
+    preconditions: Preconditions,
+    ValidatedJson(request): ValidatedJson<UpdateOrganizationRequest>,
+) -> Result<Json<ApiResponse<OrganizationResponse>>> {
+    let user_id = auth_user.0.id;
+    let (organization, member) = app_state.db.with_txn(|txn| Box::pin(async move {
+        let (organization, member) = organization_membership(txn, user_id, id).await?;
+        preconditions.check(&organization.updated_at)?;
+        if member.role < OrganizationRole::Admin {
+            return Err(AppError::Forbidden("Only admins can change the organization".to_string()));
+        }
+
+        let mut organization_active: organizations::ActiveModel = organization.into();
+        if let Some(name) = request.name {
+            organization_active.name = Set(normalize_organization_name(&name)?);
+        }
+        let organization = organization_active.update(txn).await
+            .map_err(|e| AppError::Database(e.into()))?;
+        Ok((organization, member))
+    })).await?;
+
+    // Every member gets it, with their own role
+    tracing::info!("Organization updated, broadcasting websocket message for organization {} (excluding connection {:?})", id, connection_id);
+    for (user_id, role) in OrganizationMembers::find()
+        .select_only()
+        .column(organization_members::Column::UserId)
+        .column(organization_members::Column::Role)
+        .filter(organization_members::Column::OrganizationId.eq(id))
+        .into_tuple::<(Uuid, OrganizationRole)>()
+        .all(&app_state.db.connection)
+        .await
+        .map_err(|e| AppError::Database(e.into()))?
+    {
+        let data = serde_json::to_value(OrganizationResponse::new(organization.clone(), role)).ok();
+        let ws_message = organization_message("UPDATE", auth_user.0.id, "organizations", id, data);
+        app_state.ws_state.broadcast_to_user(&user_id, ws_message, connection_id).await;
+    }
+
+    Ok(Json(ApiResponse::with_message(
+        OrganizationResponse::new(organization, member.role),
+        "Organization updated successfully",
+    )))
+}
+
+/// Delete an organization; its projects and calendars become private to their users again
+pub async fn delete_organization(
+    State(app_state): State<AppState>,
+    auth_user: AuthUser,
+    OriginConnection(connection_id): OriginConnection,
+    Path(id): Path<Uuid>,
+    preconditions: Preconditions,
+) -> Result<Json<ApiResponse<()>>> {
+    let user_id = auth_user.0.id;
+    let audience = app_state.db.with_txn(|txn| Box::pin(async move {
+        let (organization, member) = organization_membership(txn, user_id, id).await?;
+        preconditions.check(&organization.updated_at)?;
+        if member.role < OrganizationRole::Owner {
+            return Err(AppError::Forbidden("Only owners can delete the organization".to_string()));
+        }
+        let audience = member_ids(txn, id).await?;
+
+        // Memberships go with the organization; projects and calendars are detached by the FK
+        Organizations::delete_by_id(id)
+            .exec(txn)
+            .await
+            .map_err(|e| AppError::Database(e.into()))?;
+        Ok(audience)
+    })).await?;
+
+    tracing::info!("Organization deleted, broadcasting websocket message for organization {} (excluding connection {:?})", id, connection_id);
+    let ws_message = organization_message("DELETE", auth_user.0.id, "organizations", id, None);
+    app_state.ws_state.broadcast_many_to_users(&audience, vec![ws_message], connection_id).await;
+
+    Ok(Json(ApiResponse::with_message((), "Organization deleted successfully")))
+}
+
+pub async fn list_members(
+    State(app_state): State<AppState>,
+    auth_user: AuthUser,
+    Path(id): Path<Uuid>,
+    Query(paging): Query<PageQuery>,
+) -> Result<Json<ApiResponse<Vec<OrganizationMemberResponse>>>> {
+    let db = app_state.db.reader();
+    organization_membership(db, auth_user.0.id, id).await?;
+
+    let members = OrganizationMembers::find()
+        .find_also_related(Users)
+        .filter(organization_members::Column::OrganizationId.eq(id))
+        .order_by_asc(organization_members::Column::CreatedAt)
+        .all(db)
+        .await
+        .map_err(|e| AppError::Database(e.into()))?;
+
+    let response = members
+        .into_iter()
+        .map(|(member, user)| OrganizationMemberResponse::new(member, user.map(|u| u.email).unwrap_or_default()))
+        .collect();
+    let (response, meta) = paging.page(response)?;
+    Ok(Json(ApiResponse::list(response, meta)))
+}
+
+/// Broadcast a membership change to every member of the organization
+async fn broadcast_member_change(
+    app_state: &AppState,
+    actor_id: Uuid,
+    event_type: &str,
+    member: &OrganizationMemberResponse,
+    extra_user: Option<Uuid>,
+    connection_id: Option<Uuid>,
+) -> Result<()> {
+    let mut audience = member_ids(&app_state.db.connection, member.organization_id).await?;
+    if let Some(user_id) = extra_user
+        && !audience.contains(&user_id)
+    {
+        audience.push(user_id);
+    }
+
+    let data = serde_json::to_value(member).ok();
+    let ws_message = organization_message(event_type, actor_id, "organization_members", member.id, data);
+    app_state.ws_state.broadcast_many_to_users(&audience, vec![ws_message], connection_id).await;
+    Ok(())
+}
+
+pub async fn add_member(
+    State(app_state): State<AppState>,
+    auth_user: AuthUser,
+    OriginConnection(connection_id): OriginConnection,
+    Path(id): Path<Uuid>,
+    ValidatedJson(request): ValidatedJson<AddOrganizationMemberRequest>,
+) -> Result<Json<ApiResponse<OrganizationMemberResponse>>> {
+    let role = request.role.unwrap_or_default();
+    let (_, actor) = organization_membership(&app_state.db.connection, auth_user.0.id, id).await?;
+    ensure_can_manage(actor.role, None, Some(role))?;
+
+    let user = Users::find()
+        .filter(users::Column::Email.eq(request.email.trim()))
+        .one(&app_state.db.connection)
+        .await
+        .map_err(|e| AppError::Database(e.into()))?
+        .ok_or_else(|| AppError::NotFound("User not found".to_string()))?;
+
+    let existing = OrganizationMembers::find()
+        .filter(organization_members::Column::OrganizationId.eq(id))
+        .filter(organization_members::Column::UserId.eq(user.id))
+        .one(&app_state.db.connection)
+        .await
+        .map_err(|e| AppError::Database(e.into()))?;
+    if existing.is_some() {
+        return Err(AppError::Conflict("User is already a member of this organization".to_string()));
+    }
+
+    let mut member_active = organization_members::ActiveModel::new();
+    member_active.organization_id = Set(id);
+    member_active.user_id = Set(user.id);
+    member_active.role = Set(role);
+    let member = member_active.insert(&app_state.db.connection).await
+        .map_err(|e| AppError::Database(e.into()))?;
+    let response = OrganizationMemberResponse::new(member, user.email);
+
+    tracing::info!("Organization member added, broadcasting websocket message for organization {} (excluding connection {:?})", id, connection_id);
+    broadcast_member_change(&app_state, auth_user.0.id, "INSERT", &response, None, connection_id).await?;
+
+    Ok(Json(ApiResponse::with_message(response, "Organization member added successfully")))
+}
+
+pub async fn update_member(
+    State(app_state): State<AppState>,
+    auth_user: AuthUser,
+    OriginConnection(connection_id): OriginConnection,
+    Path((id, user_id)): Path<(Uuid, Uuid)>,
+    preconditions: Preconditions,
+    ValidatedJson(request): ValidatedJson<UpdateOrganizationMemberRequest>,
+) -> Result<Json<ApiResponse<OrganizationMemberResponse>>> {
+    let txn = app_state.db.connection.begin().await
+        .map_err(|e| AppError::Database(e.into()))?;
+
+    let (_, actor) = organization_membership(&txn, auth_user.0.id, id).await?;
+    let (member, user) = OrganizationMembers::find()
+        .find_also_related(Users)
+        .filter(organization_members::Column::OrganizationId.eq(id))
+        .filter(organization_members::Column::UserId.eq(user_id))
+        .one(&txn)
+        .await
+        .map_err(|e| AppError::Database(e.into()))?
+        .ok_or_else(|| AppError::NotFound("Organization member not found".to_string()))?;
+    preconditions.check(&member.updated_at)?;
+    ensure_can_manage(actor.role, Some(member.role), Some(request.role))?;
+    if member.role == OrganizationRole::Owner && request.role != OrganizationRole::Owner {
+        ensure_other_owner(&txn, id, user_id).await?;
+    }
+
+    let mut member_active: organization_members::ActiveModel = member.into();
+    member_active.role = Set(request.role);
+    let member = member_active.update(&txn).await
+        .map_err(|e| AppError::Database(e.into()))?;
+
+    txn.commit().await
+        .map_err(|e| AppError::Database(e.into()))?;
+    let response = OrganizationMemberResponse::new(member, user.map(|u| u.email).unwrap_or_default());
+
+    tracing::info!("Organization member updated, broadcasting websocket message for organization {} (excluding connection {:?})", id, connection_id);
+    broadcast_member_change(&app_state, auth_user.0.id, "UPDATE", &response, None, connection_id).await?;
+
+    Ok(Json(ApiResponse::with_message(response, "Organization member updated successfully")))
+}
+
+/// Remove a member; admins can remove others, members can leave on their own. The member's
+/// projects and calendars in the organization become private to them again.
+pub async fn remove_member(
+    State(app_state): State<AppState>,
+    auth_user: AuthUser,
+    OriginConnection(connection_id): OriginConnection,
+    Path((id, user_id)): Path<(Uuid, Uuid)>,
+    preconditions: Preconditions,
+) -> Result<Json<ApiResponse<()>>> {
+    let txn = app_state.db.connection.begin().await
+        .map_err(|e| AppError::Database(e.into()))?;
+
+    let (_, actor) = organization_membership(&txn, auth_user.0.id, id).await?;
+    let (member, user) = OrganizationMembers::find()
+        .find_also_related(Users)
+        .filter(organization_members::Column::OrganizationId.eq(id))
+        .filter(organization_members::Column::UserId.eq(user_id))
+        .one(&txn)
+        .await
+        .map_err(|e| AppError::Database(e.into()))?
+        .ok_or_else(|| AppError::NotFound("Organization member not found".to_string()))?;
+    preconditions.check(&member.updated_at)?;
+    if user_id != auth_user.0.id {
+        ensure_can_manage(actor.role, Some(member.role), None)?;
+    }
+    if member.role == OrganizationRole::Owner {
+        ensure_other_owner(&txn, id, user_id).await?;
+    }
+
+    OrganizationMembers::delete_by_id(member.id)
+        .exec(&txn)
+        .await
+        .map_err(|e| AppError::Database(e.into()))?;
+
+    let now = chrono::Utc::now();
+    Projects::update_many()
+        .col_expr(projects::Column::OrganizationId, Expr::value(Option::<Uuid>::None))
+        .col_expr(projects::Column::UpdatedAt, Expr::value(now))
+        .filter(projects::Column::UserId.eq(user_id))
+        .filter(projects::Column::OrganizationId.eq(id))
+        .exec(&txn)
+        .await
+        .map_err(|e| AppError::Database(e.into()))?;
+    Calendars::update_many()
+        .col_expr(calendars::Column::OrganizationId, Expr::value(Option::<Uuid>::None))
+        .col_expr(calendars::Column::UpdatedAt, Expr::value(now))
+        .filter(calendars::Column::UserId.eq(user_id))
+        .filter(calendars::Column::OrganizationId.eq(id))
+        .exec(&txn)
+        .await
+        .map_err(|e| AppError::Database(e.into()))?;
+
+    txn.commit().await
+        .map_err(|e| AppError::Database(e.into()))?;
+    let response = OrganizationMemberResponse::new(member, user.map(|u| u.email).unwrap_or_default());
+
+    // The removed member no longer belongs to the audience but still has to learn about it
+    tracing::info!("Organization member removed, broadcasting websocket message for organization {} (excluding connection {:?})", id, connection_id);
+    broadcast_member_change(&app_state, auth_user.0.id, "DELETE", &response, Some(user_id), connection_id).await?;
+
+    Ok(Json(ApiResponse::with_message((), "Organization member removed successfully")))
+}
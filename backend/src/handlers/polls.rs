@@ -0,0 +1,415 @@
+use std::collections::HashMap;
+
+use axum::{
+    extract::{Path, Query, State},
+    response::Json,
+};
+use sea_orm::*;
+use serde::Deserialize;
+use streamline_models::DEFAULT_SCHEMA_VERSION;
+use uuid::Uuid;
+
+use crate::{
+    entities::{
+        prelude::*,
+        calendar_events,
+        poll_slots,
+        poll_votes,
+        scheduling_polls::{self, PollStatus},
+    },
+    errors::{AppError, Result},
+    handlers::calendar_events::calendar_is_active,
+    middleware::{auth::AuthUser, connection::OriginConnection, preconditions::Preconditions, validation::ValidatedJson},
+    models::{
+        calendar_event::CalendarEventResponse,
+        poll::{
+            CreatePollRequest, FinalizePollRequest, PollResponse, PollVoteResponse, PublicPollResponse,
+            SubmitVoteRequest, MAX_POLL_VOTES,
+        },
+        ApiResponse, PageQuery, generate_public_token, validate_schema_version,
+    },
+    quota::QuotaTable,
+    state::AppState,
+    websocket::WebSocketMessage,
+};
+
+#[derive(Debug, Deserialize)]
+pub struct PollQuery {
+    pub status: Option<PollStatus>,
+}
+
+fn poll_message(event_type: &str, poll: &PollResponse) -> WebSocketMessage {
+    WebSocketMessage {
+        event_type: event_type.to_string(),
+        table: "scheduling_polls".to_string(),
+        user_id: poll.user_id,
+        record_id: Some(poll.id),
+        data: Some(serde_json::to_value(poll).unwrap_or_default()),
+    }
+}
+
+/// The given polls with their slots and ballots, loaded in one query each
+async fn poll_details<C: ConnectionTrait>(
+    db: &C,
+    polls: Vec<scheduling_polls::Model>,
+) -> Result<Vec<PollResponse>> {
+    let poll_ids: Vec<Uuid> = polls.iter().map(|poll| poll.id).collect();
+
+    let mut slots: HashMap<Uuid, Vec<poll_slots::Model>> = HashMap::new();
+    for slot in PollSlots::find()
+        .filter(poll_slots::Column::PollId.is_in(poll_ids.clone()))
+        .order_by_asc(poll_slots::Column::StartTime)
+        .all(db)
+        .await
+        .map_err(|e| AppError::Database(e.into()))?
+    {
+        slots.entry(slot.poll_id).or_default().push(slot);
+    }
+
+    let mut votes: HashMap<Uuid, Vec<poll_votes::Model>> = HashMap::new();
+    for vote in PollVotes::find()
+        .filter(poll_votes::Column::PollId.is_in(poll_ids))
+        .order_by_asc(poll_votes::Column::CreatedAt)
+        .all(db)
+        .await
+        .map_err(|e| AppError::Database(e.into()))?
+    {
+        votes.entry(vote.poll_id).or_default().push(vote);
+    }
+
+    Ok(polls
+        .into_iter()
+        .map(|poll| {
+            let poll_slots = slots.remove(&poll.id).unwrap_or_default();
+            let poll_votes = votes.remove(&poll.id).unwrap_or_default();
+            PollResponse::new(poll, poll_slots, poll_votes)
+        })
+        .collect())
+}
+
+async fn poll_detail<C: ConnectionTrait>(db: &C, poll: scheduling_polls::Model) -> Result<PollResponse> {
+    poll_details(db, vec![poll])
+        .await?
+        .pop()
+        .ok_or_else(|| AppError::NotFound("Poll not found".to_string()))
+}
+
+async fn find_poll<C: ConnectionTrait>(db: &C, user_id: Uuid, id: Uuid) -> Result<scheduling_polls::Model> {
+    SchedulingPolls::find_by_id(id)
+        .filter(scheduling_polls::Column::UserId.eq(user_id))
+        .one(db)
+        .await
+        .map_err(|e| AppError::Database(e.into()))?
+        .ok_or_else(|| AppError::NotFound("Poll not found".to_string()))
+}
+
+pub async fn list_polls(
+    State(app_state): State<AppState>,
+    auth_user: AuthUser,
+    Query(query): Query<PollQuery>,
+    Query(paging): Query<PageQuery>,
+) -> Result<Json<ApiResponse<Vec<PollResponse>>>> {
+    let mut find = SchedulingPolls::find().filter(scheduling_polls::Column::UserId.eq(auth_user.0.id));
+    if let Some(status) = query.status {
+        find = find.filter(scheduling_polls::Column::Status.eq(status));
+    }
+
+    let find = find
+        .order_by_desc(scheduling_polls::Column::CreatedAt);
+    let (polls, meta) = paging.fetch(&app_state.db.connection, find).await?;
+
+    Ok(Json(ApiResponse::list(poll_details(&app_state.db.connection, polls).await?, meta)))
+}
+
+pub async fn get_poll(
+    State(app_state): State<AppState>,
+    auth_user: AuthUser,
+    Path(id): Path<Uuid>,
+) -> Result<Json<ApiResponse<PollResponse>>> {
+    let poll = find_poll(&app_state.db.connection, auth_user.0.id, id).await?;
+    Ok(Json(ApiResponse::new(poll_detail(&app_state.db.connection, poll).await?)))
+}
+
+pub async fn create_poll(
+    State(app_state): State<AppState>,
+    auth_user: AuthUser,
+    OriginConnection(connection_id): OriginConnection,
+    ValidatedJson(request): ValidatedJson<CreatePollRequest>,
+) -> Result<Json<ApiResponse<PollResponse>>> {
+    validate_schema_version("scheduling_polls", request.schema_version)?;
+
+    let txn = app_state.db.connection.begin().await
+        .map_err(|e| AppError::Database(e.into()))?;
+
+    let mut poll_active = scheduling_polls::ActiveModel::new();
+    poll_active.user_id = Set(auth_user.0.id);
+    poll_active.encrypted_data = Set(request.encrypted_data);
+    poll_active.iv = Set(request.iv);
+    poll_active.salt = Set(request.salt);
+    poll_active.schema_version = Set(request.schema_version.unwrap_or(DEFAULT_SCHEMA_VERSION));
+    poll_active.public_token = Set(generate_public_token());
+    let poll = poll_active.insert(&txn).await
+        .map_err(|e| AppError::Database(e.into()))?;
+
+    let slots: Vec<poll_slots::ActiveModel> = request
+        .slots
+        .into_iter()
+        .map(|slot| {
+            let mut slot_active = poll_slots::ActiveModel::new();
+            slot_active.poll_id = Set(poll.id);
+            slot_active.start_time = Set(slot.start_time.into());
+            slot_active.end_time = Set(slot.end_time.into());
+            slot_active
+        })
+        .collect();
+    PollSlots::insert_many(slots).exec_without_returning(&txn).await
+        .map_err(|e| AppError::Database(e.into()))?;
+
+    let poll = poll_detail(&txn, poll).await?;
+    txn.commit().await
+        .map_err(|e| AppError::Database(e.into()))?;
+
+    // Broadcast websocket message for poll creation
+    tracing::info!("Poll created, broadcasting websocket message for user {} (excluding connection {:?})", auth_user.0.id, connection_id);
+    app_state.ws_state
+        .broadcast_to_user(&auth_user.0.id, poll_message("INSERT", &poll), connection_id)
+        .await;
+
+    Ok(Json(ApiResponse::with_message(poll, "Poll created successfully")))
+}
+
+/// Pick the winning slot and turn it into a calendar event; the poll stops accepting votes
+pub async fn finalize_poll(
+    State(app_state): State<AppState>,
+    auth_user: AuthUser,
+    OriginConnection(connection_id): OriginConnection,
+    Path(id): Path<Uuid>,
+    ValidatedJson(request): ValidatedJson<FinalizePollRequest>,
+) -> Result<Json<ApiResponse<PollResponse>>> {
+    validate_schema_version("calendar_events", request.schema_version)?;
+    let txn = app_state.db.connection.begin().await
+        .map_err(|e| AppError::Database(e.into()))?;
+
+    // Lock the poll so votes can't land after it's finalized
+    let poll = SchedulingPolls::find_by_id(id)
+        .filter(scheduling_polls::Column::UserId.eq(auth_user.0.id))
+        .lock_exclusive()
+        .one(&txn)
+        .await
+        .map_err(|e| AppError::Database(e.into()))?
+        .ok_or_else(|| AppError::NotFound("Poll not found".to_string()))?;
+    if poll.status == PollStatus::Finalized {
+        return Err(AppError::Conflict("Poll is already finalized".to_string()));
+    }
+
+    let slot = PollSlots::find_by_id(request.slot_id)
+        .filter(poll_slots::Column::PollId.eq(poll.id))
+        .one(&txn)
+        .await
+        .map_err(|e| AppError::Database(e.into()))?
+        .ok_or_else(|| AppError::NotFound("Slot not found".to_string()))?;
+
+    if let Some(calendar_id) = request.calendar_id
+        && !calendar_is_active(&txn, auth_user.0.id, calendar_id).await?
+    {
+        return Err(AppError::NotFound("Calendar not found".to_string()));
+    }
+    app_state.quotas.enforce(&txn, auth_user.0.id, QuotaTable::CalendarEvents, 1).await?;
+
+    let mut event_active = calendar_events::ActiveModel::new();
+    event_active.user_id = Set(auth_user.0.id);
+    event_active.calendar_id = Set(request.calendar_id);
+    event_active.encrypted_data = Set(request.encrypted_data);
+    event_active.iv = Set(request.iv);
+    event_active.salt = Set(request.salt);
+    event_active.schema_version = Set(request.schema_version.unwrap_or(DEFAULT_SCHEMA_VERSION));
+    event_active.start_time = Set(Some(slot.start_time));
+    event_active.end_time = Set(Some(slot.end_time));
+    let event = event_active.insert(&txn).await
+        .map_err(|e| AppError::Database(e.into()))?;
+
+    let mut poll_active: scheduling_polls::ActiveModel = poll.into();
+    poll_active.status = Set(PollStatus::Finalized);
+    poll_active.finalized_slot_id = Set(Some(slot.id));
+    poll_active.event_id = Set(Some(event.id));
+    let poll = poll_active.update(&txn).await
+        .map_err(|e| AppError::Database(e.into()))?;
+
+    let poll = poll_detail(&txn, poll).await?;
+    txn.commit().await
+        .map_err(|e| AppError::Database(e.into()))?;
+
+    // Broadcast websocket messages for the new event and the finalized poll
+    tracing::info!("Poll finalized, broadcasting websocket message for user {} (excluding connection {:?})", auth_user.0.id, connection_id);
+    let event_message = WebSocketMessage {
+        event_type: "INSERT".to_string(),
+        table: "calendar_events".to_string(),
+        user_id: auth_user.0.id,
+        record_id: Some(event.id),
+        data: Some(serde_json::to_value(CalendarEventResponse::from(event)).unwrap_or_default()),
+    };
+    app_state.ws_state
+        .broadcast_many_to_user(&auth_user.0.id, vec![event_message, poll_message("UPDATE", &poll)], connection_id)
+        .await;
+
+    Ok(Json(ApiResponse::with_message(poll, "Poll finalized successfully")))
+}
+
+/// Delete a poll with its slots and ballots; an event created from it stays
+pub async fn delete_poll(
+    State(app_state): State<AppState>,
+    auth_user: AuthUser,
+    OriginConnection(connection_id): OriginConnection,
+    Path(id): Path<Uuid>,
+    preconditions: Preconditions,
+) -> Result<Json<ApiResponse<()>>> {
+    let user_id = auth_user.0.id;
+    app_state.db.with_txn(|txn| Box::pin(async move {
+        let poll = find_poll(txn, user_id, id).await?;
+        preconditions.check(&poll.updated_at)?;
+        SchedulingPolls::delete_by_id(poll.id)
+            .exec(txn)
+            .await
+            .map_err(|e| AppError::Database(e.into()))?;
+        Ok(())
+    })).await?;
+
+    // Broadcast websocket message for poll deletion
+    tracing::info!("Poll deleted, broadcasting websocket message for user {} (excluding connection {:?})", auth_user.0.id, connection_id);
+    let ws_message = WebSocketMessage {
+        event_type: "DELETE".to_string(),
+        table: "scheduling_polls".to_string(),
+        user_id: auth_user.0.id,
+        record_id: Some(id),
+        data: None,
+    };
+    app_state.ws_state.broadcast_to_user(&auth_user.0.id, ws_message, connection_id).await;
+
+    Ok(Json(ApiResponse::with_message((), "Poll deleted successfully")))
+}
+
+/// Remove an invitee's ballot, e.g. a duplicate or spam
+pub async fn delete_vote(
+    State(app_state): State<AppState>,
+    auth_user: AuthUser,
+    OriginConnection(connection_id): OriginConnection,
+    Path((id, vote_id)): Path<(Uuid, Uuid)>,
+    preconditions: Preconditions,
+) -> Result<Json<ApiResponse<()>>> {
+    let poll = find_poll(&app_state.db.connection, auth_user.0.id, id).await?;
+
+    let vote = PollVotes::find_by_id(vote_id)
+        .filter(poll_votes::Column::PollId.eq(poll.id))
+        .one(&app_state.db.connection)
+        .await
+        .map_err(|e| AppError::Database(e.into()))?
+        .ok_or_else(|| AppError::NotFound("Vote not found".to_string()))?;
+    preconditions.check(&vote.updated_at)?;
+
+    PollVotes::delete_by_id(vote.id)
+        .exec(&app_state.db.connection)
+        .await
+        .map_err(|e| AppError::Database(e.into()))?;
+
+    // Broadcast websocket message for vote removal
+    tracing::info!("Poll vote removed, broadcasting websocket message for user {} (excluding connection {:?})", auth_user.0.id, connection_id);
+    let ws_message = WebSocketMessage {
+        event_type: "DELETE".to_string(),
+        table: "poll_votes".to_string(),
+        user_id: auth_user.0.id,
+        record_id: Some(vote_id),
+        data: None,
+    };
+    app_state.ws_state.broadcast_to_user(&auth_user.0.id, ws_message, connection_id).await;
+
+    Ok(Json(ApiResponse::with_message((), "Vote removed successfully")))
+}
+
+async fn find_public_poll<C: ConnectionTrait>(db: &C, token: &str) -> Result<scheduling_polls::Model> {
+    SchedulingPolls::find()
+        .filter(scheduling_polls::Column::PublicToken.eq(token))
+        .one(db)
+        .await
+        .map_err(|e| AppError::Database(e.into()))?
+        .ok_or_else(|| AppError::NotFound("Poll not found".to_string()))
+}
+
+/// The poll behind an invite link; no account needed
+pub async fn get_public_poll(
+    State(app_state): State<AppState>,
+    Path(token): Path<String>,
+) -> Result<Json<ApiResponse<PublicPollResponse>>> {
+    let poll = find_public_poll(&app_state.db.connection, &token).await?;
+    Ok(Json(ApiResponse::new(poll_detail(&app_state.db.connection, poll).await?.into())))
+}
+
+/// Cast a ballot through an invite link; the organizer is notified live
+pub async fn submit_vote(
+    State(app_state): State<AppState>,
+    Path(token): Path<String>,
+    ValidatedJson(request): ValidatedJson<SubmitVoteRequest>,
+) -> Result<Json<ApiResponse<PollVoteResponse>>> {
+    validate_schema_version("poll_votes", request.schema_version)?;
+    let txn = app_state.db.connection.begin().await
+        .map_err(|e| AppError::Database(e.into()))?;
+
+    // Shared lock: votes may land concurrently, but not while the poll is being finalized
+    let poll = SchedulingPolls::find()
+        .filter(scheduling_polls::Column::PublicToken.eq(token.as_str()))
+        .lock_shared()
+        .one(&txn)
+        .await
+        .map_err(|e| AppError::Database(e.into()))?
+        .ok_or_else(|| AppError::NotFound("Poll not found".to_string()))?;
+    if poll.status == PollStatus::Finalized {
+        return Err(AppError::Conflict("Poll is no longer accepting votes".to_string()));
+    }
+
+    let slot_ids: Vec<Uuid> = PollSlots::find()
+        .select_only()
+        .column(poll_slots::Column::Id)
+        .filter(poll_slots::Column::PollId.eq(poll.id))
+        .into_tuple()
+        .all(&txn)
+        .await
+        .map_err(|e| AppError::Database(e.into()))?;
+    if let Some(unknown) = request.answers.keys().find(|slot_id| !slot_ids.contains(slot_id)) {
+        return Err(AppError::Validation(format!("Slot {} is not part of this poll", unknown)));
+    }
+
+    let vote_count = PollVotes::find()
+        .filter(poll_votes::Column::PollId.eq(poll.id))
+        .count(&txn)
+        .await
+        .map_err(|e| AppError::Database(e.into()))?;
+    if vote_count >= MAX_POLL_VOTES {
+        return Err(AppError::Validation(format!("A poll can have at most {} votes", MAX_POLL_VOTES)));
+    }
+
+    let mut vote_active = poll_votes::ActiveModel::new();
+    vote_active.poll_id = Set(poll.id);
+    vote_active.encrypted_data = Set(request.encrypted_data);
+    vote_active.iv = Set(request.iv);
+    vote_active.salt = Set(request.salt);
+    vote_active.schema_version = Set(request.schema_version.unwrap_or(DEFAULT_SCHEMA_VERSION));
+    vote_active.answers = Set(serde_json::json!(request.answers));
+    let vote = vote_active.insert(&txn).await
+        .map_err(|e| AppError::Database(e.into()))?;
+
+    txn.commit().await
+        .map_err(|e| AppError::Database(e.into()))?;
+
+    // Broadcast websocket message for the new vote to the organizer
+    tracing::info!("Poll vote submitted, broadcasting websocket message for user {}", poll.user_id);
+    let vote = PollVoteResponse::from(vote);
+    let ws_message = WebSocketMessage {
+        event_type: "INSERT".to_string(),
+        table: "poll_votes".to_string(),
+        user_id: poll.user_id,
+        record_id: Some(vote.id),
+        data: Some(serde_json::to_value(&vote).unwrap_or_default()),
+    };
+    app_state.ws_state.broadcast_to_user(&poll.user_id, ws_message, None).await;
+
+    Ok(Json(ApiResponse::with_message(vote, "Vote submitted successfully")))
+}
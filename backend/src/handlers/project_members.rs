@@ -0,0 +1,443 @@
+use axum::{
+    extract::{Path, Query, State},
+    response::Json,
+};
+use sea_orm::*;
+use uuid::Uuid;
+
+use crate::{
+    entities::{
+        organization_members::{self, OrganizationRole},
+        prelude::*,
+        project_members::{self, ProjectRole},
+        projects, users,
+    },
+    errors::{AppError, Result},
+    handlers::organizations::{organization_ids, organization_memberships, organization_project_role},
+    middleware::{auth::AuthUser, connection::OriginConnection, preconditions::Preconditions, validation::ValidatedJson},
+    models::{
+        project::ProjectResponse,
+        project_member::{AddMemberRequest, ProjectMemberResponse, SharedProjectResponse, UpdateMemberRequest},
+        ApiResponse, PageQuery,
+    },
+    state::AppState,
+    websocket::WebSocketMessage,
+};
+
+/// An active project the user can see, with their role in it: `owner` for the project's own user,
+/// otherwise the higher of their membership role and the role their organization gives them
+pub(crate) async fn accessible_project<C: ConnectionTrait>(
+    db: &C,
+    user_id: Uuid,
+    project_id: Uuid,
+) -> Result<(projects::Model, ProjectRole)> {
+    let project = Projects::find_by_id(project_id)
+        .filter(projects::Column::DeletedAt.is_null())
+        .one(db)
+        .await
+        .map_err(|e| AppError::Database(e.into()))?
+        .ok_or_else(|| AppError::NotFound("Project not found".to_string()))?;
+    if project.user_id == user_id {
+        return Ok((project, ProjectRole::Owner));
+    }
+
+    let member_role: Option<ProjectRole> = ProjectMembers::find()
+        .select_only()
+        .column(project_members::Column::Role)
+        .filter(project_members::Column::ProjectId.eq(project_id))
+        .filter(project_members::Column::UserId.eq(user_id))
+        .into_tuple()
+        .one(db)
+        .await
+        .map_err(|e| AppError::Database(e.into()))?;
+    let organization_role = organization_project_role(db, user_id, project.organization_id).await?;
+    let role = member_role
+        .max(organization_role)
+        .ok_or_else(|| AppError::NotFound("Project not found".to_string()))?;
+    Ok((project, role))
+}
+
+/// Whose tasks a new or moved task in `project_id` belongs to. Tasks of a shared project stay with
+/// the project's own user, so the user needs at least `editor` rights there.
+pub(crate) async fn task_project_owner<C: ConnectionTrait>(db: &C, user_id: Uuid, project_id: Uuid) -> Result<Uuid> {
+    let (project, role) = accessible_project(db, user_id, project_id).await?;
+    if role < ProjectRole::Editor {
+        return Err(AppError::Forbidden("Viewers cannot change the tasks of a shared project".to_string()));
+    }
+    Ok(project.user_id)
+}
+
+/// Ids of the active projects shared with the user with at least `min_role`, directly or through
+/// an organization
+pub(crate) async fn shared_project_ids<C: ConnectionTrait>(
+    db: &C,
+    user_id: Uuid,
+    min_role: ProjectRole,
+) -> Result<Vec<Uuid>> {
+    let roles: Vec<ProjectRole> = [ProjectRole::Viewer, ProjectRole::Editor, ProjectRole::Owner]
+        .into_iter()
+        .filter(|role| *role >= min_role)
+        .collect();
+
+    let mut ids: Vec<Uuid> = ProjectMembers::find()
+        .select_only()
+        .column(project_members::Column::ProjectId)
+        .inner_join(Projects)
+        .filter(project_members::Column::UserId.eq(user_id))
+        .filter(project_members::Column::Role.is_in(roles))
+        .filter(projects::Column::DeletedAt.is_null())
+        .into_tuple()
+        .all(db)
+        .await
+        .map_err(|e| AppError::Database(e.into()))?;
+
+    let organization_ids = organization_ids(db, user_id, min_role).await?;
+    if !organization_ids.is_empty() {
+        let organization_project_ids: Vec<Uuid> = Projects::find()
+            .select_only()
+            .column(projects::Column::Id)
+            .filter(projects::Column::OrganizationId.is_in(organization_ids))
+            .filter(projects::Column::UserId.ne(user_id))
+            .filter(projects::Column::DeletedAt.is_null())
+            .into_tuple()
+            .all(db)
+            .await
+            .map_err(|e| AppError::Database(e.into()))?;
+        ids.extend(organization_project_ids);
+        ids.sort();
+        ids.dedup();
+    }
+    Ok(ids)
+}
+
+/// `(user_id, project_id)` of everyone a project is shared with, directly or through its organization
+async fn project_memberships<C: ConnectionTrait>(db: &C, project_ids: Vec<Uuid>) -> Result<Vec<(Uuid, Uuid)>> {
+    if project_ids.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    let mut memberships: Vec<(Uuid, Uuid)> = ProjectMembers::find()
+        .select_only()
+        .column(project_members::Column::UserId)
+        .column(project_members::Column::ProjectId)
+        .filter(project_members::Column::ProjectId.is_in(project_ids.clone()))
+        .into_tuple()
+        .all(db)
+        .await
+        .map_err(|e| AppError::Database(e.into()))?;
+
+    let organization_projects: Vec<(Uuid, Uuid)> = Projects::find()
+        .select_only()
+        .column(projects::Column::Id)
+        .column(projects::Column::OrganizationId)
+        .filter(projects::Column::Id.is_in(project_ids))
+        .filter(projects::Column::OrganizationId.is_not_null())
+        .into_tuple()
+        .all(db)
+        .await
+        .map_err(|e| AppError::Database(e.into()))?;
+    let organization_members =
+        organization_memberships(db, organization_projects.iter().map(|(_, organization_id)| Some(*organization_id))).await?;
+    for (project_id, organization_id) in &organization_projects {
+        memberships.extend(
+            organization_members
+                .iter()
+                .filter(|(_, member_organization_id)| member_organization_id == organization_id)
+                .map(|(user_id, _)| (*user_id, *project_id)),
+        );
+    }
+    Ok(memberships)
+}
+
+/// Users who see changes to tasks of the given owners in the given projects: the owners, every
+/// member and every member of the projects' organizations
+pub(crate) async fn task_audience<C: ConnectionTrait>(
+    db: &C,
+    owner_ids: impl IntoIterator<Item = Uuid>,
+    project_ids: impl IntoIterator<Item = Option<Uuid>>,
+) -> Result<Vec<Uuid>> {
+    let project_ids: Vec<Uuid> = project_ids.into_iter().flatten().collect();
+    let mut audience: Vec<Uuid> = Vec::new();
+    for owner_id in owner_ids {
+        if !audience.contains(&owner_id) {
+            audience.push(owner_id);
+        }
+    }
+    if project_ids.is_empty() {
+        return Ok(audience);
+    }
+
+    for (member_id, _) in project_memberships(db, project_ids).await? {
+        if !audience.contains(&member_id) {
+            audience.push(member_id);
+        }
+    }
+    Ok(audience)
+}
+
+/// Broadcast changes to tasks of `owner_id`. The owner gets all of them; members of shared projects
+/// and of their organizations only those for tasks in, or just moved out of, their projects, listed with each change.
+pub(crate) async fn broadcast_task_changes(
+    app_state: &AppState,
+    owner_id: Uuid,
+    changes: Vec<(Vec<Uuid>, WebSocketMessage)>,
+    connection_id: Option<Uuid>,
+) -> Result<()> {
+    let mut project_ids: Vec<Uuid> = changes.iter().flat_map(|(ids, _)| ids.iter().copied()).collect();
+    project_ids.sort();
+    project_ids.dedup();
+
+    let memberships = project_memberships(&app_state.db.connection, project_ids).await?;
+
+    let mut member_ids: Vec<Uuid> = memberships.iter().map(|(user_id, _)| *user_id).filter(|id| *id != owner_id).collect();
+    member_ids.sort();
+    member_ids.dedup();
+    for member_id in member_ids {
+        let visible: Vec<WebSocketMessage> = changes
+            .iter()
+            .filter(|(ids, _)| memberships.iter().any(|(user_id, project_id)| *user_id == member_id && ids.contains(project_id)))
+            .map(|(_, message)| message.clone())
+            .collect();
+        app_state.ws_state.broadcast_many_to_user(&member_id, visible, connection_id).await;
+    }
+
+    let messages = changes.into_iter().map(|(_, message)| message).collect();
+    app_state.ws_state.broadcast_many_to_user(&owner_id, messages, connection_id).await;
+    Ok(())
+}
+
+/// Projects shared with the authenticated user by other accounts, directly or through an organization
+pub async fn list_shared_projects(
+    State(app_state): State<AppState>,
+    auth_user: AuthUser,
+    Query(paging): Query<PageQuery>,
+) -> Result<Json<ApiResponse<Vec<SharedProjectResponse>>>> {
+    let db = &app_state.db.connection;
+    let shared = ProjectMembers::find()
+        .find_also_related(Projects)
+        .filter(project_members::Column::UserId.eq(auth_user.0.id))
+        .filter(projects::Column::DeletedAt.is_null())
+        .order_by_asc(project_members::Column::CreatedAt)
+        .all(db)
+        .await
+        .map_err(|e| AppError::Database(e.into()))?;
+
+    let mut response: Vec<SharedProjectResponse> = shared
+        .into_iter()
+        .filter_map(|(member, project)| {
+            project.map(|project| SharedProjectResponse {
+                project: ProjectResponse::from(project),
+                role: member.role,
+            })
+        })
+        .collect();
+
+    let memberships: Vec<(Uuid, OrganizationRole)> = OrganizationMembers::find()
+        .select_only()
+        .column(organization_members::Column::OrganizationId)
+        .column(organization_members::Column::Role)
+        .filter(organization_members::Column::UserId.eq(auth_user.0.id))
+        .into_tuple()
+        .all(db)
+        .await
+        .map_err(|e| AppError::Database(e.into()))?;
+    if !memberships.is_empty() {
+        let organization_projects = Projects::find()
+            .filter(projects::Column::OrganizationId.is_in(memberships.iter().map(|(id, _)| *id)))
+            .filter(projects::Column::UserId.ne(auth_user.0.id))
+            .filter(projects::Column::DeletedAt.is_null())
+            .order_by_asc(projects::Column::CreatedAt)
+            .all(db)
+            .await
+            .map_err(|e| AppError::Database(e.into()))?;
+        for project in organization_projects {
+            let Some(role) = memberships
+                .iter()
+                .find(|(id, _)| Some(*id) == project.organization_id)
+                .map(|(_, role)| role.project_role())
+            else {
+                continue;
+            };
+            match response.iter_mut().find(|shared| shared.project.id == project.id) {
+                Some(shared) => shared.role = shared.role.max(role),
+                None => response.push(SharedProjectResponse { project: ProjectResponse::from(project), role }),
+            }
+        }
+    }
+    let (response, meta) = paging.page(response)?;
+    Ok(Json(ApiResponse::list(response, meta)))
+}
+
+pub async fn list_members(
+    State(app_state): State<AppState>,
+    auth_user: AuthUser,
+    Path(id): Path<Uuid>,
+    Query(paging): Query<PageQuery>,
+) -> Result<Json<ApiResponse<Vec<ProjectMemberResponse>>>> {
+    accessible_project(&app_state.db.connection, auth_user.0.id, id).await?;
+
+    let members = ProjectMembers::find()
+        .find_also_related(Users)
+        .filter(project_members::Column::ProjectId.eq(id))
+        .order_by_asc(project_members::Column::CreatedAt)
+        .all(&app_state.db.connection)
+        .await
+        .map_err(|e| AppError::Database(e.into()))?;
+
+    let response = members
+        .into_iter()
+        .map(|(member, user)| ProjectMemberResponse::new(member, user.map(|u| u.email).unwrap_or_default()))
+        .collect();
+    let (response, meta) = paging.page(response)?;
+    Ok(Json(ApiResponse::list(response, meta)))
+}
+
+/// Broadcast a membership change to the project's owner and all of its members
+async fn broadcast_member_change(
+    app_state: &AppState,
+    project: &projects::Model,
+    event_type: &str,
+    member: &ProjectMemberResponse,
+    extra_user: Option<Uuid>,
+    connection_id: Option<Uuid>,
+) -> Result<()> {
+    let mut audience = task_audience(&app_state.db.connection, [project.user_id], [Some(project.id)]).await?;
+    if let Some(user_id) = extra_user
+        && !audience.contains(&user_id)
+    {
+        audience.push(user_id);
+    }
+
+    let ws_message = WebSocketMessage {
+        event_type: event_type.to_string(),
+        table: "project_members".to_string(),
+        user_id: project.user_id,
+        record_id: Some(member.id),
+        data: Some(serde_json::to_value(member).unwrap_or_default()),
+    };
+    app_state.ws_state.broadcast_many_to_users(&audience, vec![ws_message], connection_id).await;
+    Ok(())
+}
+
+pub async fn add_member(
+    State(app_state): State<AppState>,
+    auth_user: AuthUser,
+    OriginConnection(connection_id): OriginConnection,
+    Path(id): Path<Uuid>,
+    ValidatedJson(request): ValidatedJson<AddMemberRequest>,
+) -> Result<Json<ApiResponse<ProjectMemberResponse>>> {
+    let (project, role) = accessible_project(&app_state.db.connection, auth_user.0.id, id).await?;
+    if role < ProjectRole::Owner {
+        return Err(AppError::Forbidden("Only owners can manage project members".to_string()));
+    }
+
+    let user = Users::find()
+        .filter(users::Column::Email.eq(request.email.trim()))
+        .one(&app_state.db.connection)
+        .await
+        .map_err(|e| AppError::Database(e.into()))?
+        .ok_or_else(|| AppError::NotFound("User not found".to_string()))?;
+    if user.id == project.user_id {
+        return Err(AppError::Validation("The project's owner cannot be added as a member".to_string()));
+    }
+
+    let existing = ProjectMembers::find()
+        .filter(project_members::Column::ProjectId.eq(id))
+        .filter(project_members::Column::UserId.eq(user.id))
+        .one(&app_state.db.connection)
+        .await
+        .map_err(|e| AppError::Database(e.into()))?;
+    if existing.is_some() {
+        return Err(AppError::Conflict("User is already a member of this project".to_string()));
+    }
+
+    let mut member_active = project_members::ActiveModel::new();
+    member_active.project_id = Set(id);
+    member_active.user_id = Set(user.id);
+    member_active.role = Set(request.role.unwrap_or_default());
+    let member = member_active.insert(&app_state.db.connection).await
+        .map_err(|e| AppError::Database(e.into()))?;
+    let response = ProjectMemberResponse::new(member, user.email);
+
+    tracing::info!("Project member added, broadcasting websocket message for project {} (excluding connection {:?})", id, connection_id);
+    broadcast_member_change(&app_state, &project, "INSERT", &response, None, connection_id).await?;
+
+    Ok(Json(ApiResponse::with_message(response, "Project member added successfully")))
+}
+
+pub async fn update_member(
+    State(app_state): State<AppState>,
+    auth_user: AuthUser,
+    OriginConnection(connection_id): OriginConnection,
+    Path((id, user_id)): Path<(Uuid, Uuid)>,
+    preconditions: Preconditions,
+    ValidatedJson(request): ValidatedJson<UpdateMemberRequest>,
+) -> Result<Json<ApiResponse<ProjectMemberResponse>>> {
+    let current_user_id = auth_user.0.id;
+    let (project, response) = app_state.db.with_txn(|txn| Box::pin(async move {
+        let (project, role) = accessible_project(txn, current_user_id, id).await?;
+        if role < ProjectRole::Owner {
+            return Err(AppError::Forbidden("Only owners can manage project members".to_string()));
+        }
+
+        let (member, user) = ProjectMembers::find()
+            .find_also_related(Users)
+            .filter(project_members::Column::ProjectId.eq(id))
+            .filter(project_members::Column::UserId.eq(user_id))
+            .one(txn)
+            .await
+            .map_err(|e| AppError::Database(e.into()))?
+            .ok_or_else(|| AppError::NotFound("Project member not found".to_string()))?;
+        preconditions.check(&member.updated_at)?;
+
+        let mut member_active: project_members::ActiveModel = member.into();
+        member_active.role = Set(request.role);
+        let member = member_active.update(txn).await
+            .map_err(|e| AppError::Database(e.into()))?;
+        Ok((project, ProjectMemberResponse::new(member, user.map(|u| u.email).unwrap_or_default())))
+    })).await?;
+
+    tracing::info!("Project member updated, broadcasting websocket message for project {} (excluding connection {:?})", id, connection_id);
+    broadcast_member_change(&app_state, &project, "UPDATE", &response, None, connection_id).await?;
+
+    Ok(Json(ApiResponse::with_message(response, "Project member updated successfully")))
+}
+
+/// Remove a member; owners can remove anyone, members can leave on their own
+pub async fn remove_member(
+    State(app_state): State<AppState>,
+    auth_user: AuthUser,
+    OriginConnection(connection_id): OriginConnection,
+    Path((id, user_id)): Path<(Uuid, Uuid)>,
+    preconditions: Preconditions,
+) -> Result<Json<ApiResponse<()>>> {
+    let current_user_id = auth_user.0.id;
+    let (project, response) = app_state.db.with_txn(|txn| Box::pin(async move {
+        let (project, role) = accessible_project(txn, current_user_id, id).await?;
+        if role < ProjectRole::Owner && user_id != current_user_id {
+            return Err(AppError::Forbidden("Only owners can manage project members".to_string()));
+        }
+
+        let (member, user) = ProjectMembers::find()
+            .find_also_related(Users)
+            .filter(project_members::Column::ProjectId.eq(id))
+            .filter(project_members::Column::UserId.eq(user_id))
+            .one(txn)
+            .await
+            .map_err(|e| AppError::Database(e.into()))?
+            .ok_or_else(|| AppError::NotFound("Project member not found".to_string()))?;
+        preconditions.check(&member.updated_at)?;
+
+        ProjectMembers::delete_by_id(member.id)
+            .exec(txn)
+            .await
+            .map_err(|e| AppError::Database(e.into()))?;
+        Ok((project, ProjectMemberResponse::new(member, user.map(|u| u.email).unwrap_or_default())))
+    })).await?;
+
+    // The removed member no longer belongs to the audience but still has to learn about it
+    tracing::info!("Project member removed, broadcasting websocket message for project {} (excluding connection {:?})", id, connection_id);
+    broadcast_member_change(&app_state, &project, "DELETE", &response, Some(user_id), connection_id).await?;
+
+    Ok(Json(ApiResponse::with_message((), "Project member removed successfully")))
+}
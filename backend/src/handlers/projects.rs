@@ -1,43 +1,261 @@
 use axum::{
     extract::{Path, Query, State},
-    http::HeaderMap,
     response::Json,
 };
-use sea_orm::*;
+use sea_orm::{sea_query::Expr, *};
 use serde::Deserialize;
+use std::collections::HashMap;
 use uuid::Uuid;
 
 use crate::{
-    entities::{prelude::*, projects},
+    db::{begin_write, lock_siblings},
+    entities::{prelude::*, can_do_list, project_members::ProjectRole, projects, record_revisions::RevisionAction},
     errors::Result,
-    middleware::auth::AuthUser,
+    handlers::{
+        organizations::{ensure_shareable, organization_audience, organization_membership},
+        project_members::{accessible_project, task_audience},
+        revisions::{find_revision, list_revisions, record_revision},
+        sync::record_deletion,
+    },
+    middleware::{auth::AuthUser, connection::OriginConnection, preconditions::Preconditions, validation::ValidatedJson},
     models::{
-        project::{CreateProjectRequest, UpdateProjectRequest, ProjectResponse},
-        ApiResponse,
+        can_do_list::CanDoItemResponse,
+        project::{CreateProjectRequest, DuplicateProjectResponse, MoveProjectRequest, UpdateProjectRequest, ProjectResponse},
+        revision::{RevisionResponse, Revisioned},
+        ApiResponse, PageQuery, ApplyTo, IntoRecord, FieldsQuery, ReorderRequest, validate_schema_version,
     },
+    quota::QuotaTable,
     state::AppState,
     websocket::WebSocketMessage,
 };
 
-fn extract_connection_id(headers: &HeaderMap) -> Option<Uuid> {
-    headers
-        .get("x-connection-id")
-        .and_then(|v| v.to_str().ok())
-        .and_then(|s| Uuid::parse_str(s).ok())
+/// Ids of `root_id` and all of its descendants within `projects`
+pub(crate) fn collect_subtree_ids(projects: &[projects::Model], root_id: Uuid) -> Vec<Uuid> {
+    let mut ids = vec![root_id];
+    let mut index = 0;
+    while index < ids.len() {
+        let current = ids[index];
+        for project in projects.iter().filter(|p| p.parent_id == Some(current)) {
+            // Guard against parent cycles so a corrupt hierarchy can't loop forever
+            if !ids.contains(&project.id) {
+                ids.push(project.id);
+            }
+        }
+        index += 1;
+    }
+    ids
+}
+
+/// Whether making `parent_id` the parent of `project_id` would make the project its own ancestor
+fn would_create_cycle(projects: &[projects::Model], project_id: Uuid, parent_id: Uuid) -> bool {
+    collect_subtree_ids(projects, project_id).contains(&parent_id)
+}
+
+/// Check a requested parent change: the parent must be an active project of the user and not inside the moved subtree
+pub(crate) async fn validate_parent<C: ConnectionTrait>(
+    db: &C,
+    user_id: Uuid,
+    project_id: Uuid,
+    parent_id: Uuid,
+) -> Result<()> {
+    let user_projects = Projects::find()
+        .filter(projects::Column::UserId.eq(user_id))
+        .filter(projects::Column::DeletedAt.is_null())
+        .all(db)
+        .await
+        .map_err(|e| crate::errors::AppError::Database(e.into()))?;
+
+    if !user_projects.iter().any(|p| p.id == parent_id) {
+        return Err(crate::errors::AppError::NotFound("Parent project not found".to_string()));
+    }
+    if would_create_cycle(&user_projects, project_id, parent_id) {
+        return Err(crate::errors::AppError::Coded(
+            crate::errors::ErrorCode::ValidationParentCycle,
+            "A project cannot be moved into itself or one of its descendants".to_string(),
+        ));
+    }
+    Ok(())
+}
+
+/// Move the given projects and their tasks to the trash under one shared `deleted_at`
+/// so restore can bring them back together. Returns the ids of the trashed tasks.
+pub(crate) async fn trash_projects<C: ConnectionTrait>(
+    db: &C,
+    user_id: Uuid,
+    project_ids: &[Uuid],
+) -> Result<Vec<Uuid>> {
+    let now = chrono::Utc::now();
+
+    Projects::update_many()
+        .col_expr(projects::Column::DeletedAt, Expr::value(now))
+        .col_expr(projects::Column::UpdatedAt, Expr::value(now))
+        .filter(projects::Column::UserId.eq(user_id))
+        .filter(projects::Column::Id.is_in(project_ids.to_vec()))
+        .exec(db)
+        .await
+        .map_err(|e| crate::errors::AppError::Database(e.into()))?;
+
+    let trashed_item_ids = CanDoList::find()
+        .select_only()
+        .column(can_do_list::Column::Id)
+        .filter(can_do_list::Column::UserId.eq(user_id))
+        .filter(can_do_list::Column::DeletedAt.is_null())
+        .filter(can_do_list::Column::ProjectId.is_in(project_ids.to_vec()))
+        .into_tuple::<Uuid>()
+        .all(db)
+        .await
+        .map_err(|e| crate::errors::AppError::Database(e.into()))?;
+
+    CanDoList::update_many()
+        .col_expr(can_do_list::Column::DeletedAt, Expr::value(now))
+        .col_expr(can_do_list::Column::UpdatedAt, Expr::value(now))
+        .filter(can_do_list::Column::Id.is_in(trashed_item_ids.clone()))
+        .exec(db)
+        .await
+        .map_err(|e| crate::errors::AppError::Database(e.into()))?;
+
+    Ok(trashed_item_ids)
+}
+
+/// The user's default project, the inbox for tasks without a project. It is created, or brought back
+/// from the trash, when missing; the flag tells whether clients have yet to see it.
+pub(crate) async fn ensure_default_project<C: ConnectionTrait>(db: &C, user_id: Uuid) -> Result<(projects::Model, bool)> {
+    let existing = Projects::find()
+        .filter(projects::Column::UserId.eq(user_id))
+        .filter(projects::Column::IsDefault.eq(true))
+        .one(db)
+        .await
+        .map_err(|e| crate::errors::AppError::Database(e.into()))?;
+
+    if let Some(project) = existing {
+        if project.deleted_at.is_none() {
+            return Ok((project, false));
+        }
+        let mut project_active: projects::ActiveModel = project.into();
+        project_active.deleted_at = Set(None);
+        project_active.parent_id = Set(None);
+        let project = project_active.update(db).await
+            .map_err(|e| crate::errors::AppError::Database(e.into()))?;
+        return Ok((project, true));
+    }
+
+    // The server can't encrypt a name; clients label the empty default project themselves
+    let mut project_active = projects::ActiveModel::new();
+    project_active.user_id = Set(user_id);
+    project_active.encrypted_data = Set(String::new());
+    project_active.iv = Set(String::new());
+    project_active.salt = Set(String::new());
+    project_active.is_default = Set(true);
+
+    // A concurrent request may have created it first; the partial unique index keeps only one.
+    // The literal must match the index predicate, which SQLite won't do for a bound value.
+    Projects::insert(project_active)
+        .on_conflict(
+            sea_query::OnConflict::column(projects::Column::UserId)
+                .target_and_where(Expr::col(projects::Column::IsDefault).eq(Expr::cust("TRUE")))
+                .do_nothing()
+                .to_owned(),
+        )
+        .exec_without_returning(db)
+        .await
+        .map_err(|e| crate::errors::AppError::Database(e.into()))?;
+
+    let project = Projects::find()
+        .filter(projects::Column::UserId.eq(user_id))
+        .filter(projects::Column::IsDefault.eq(true))
+        .one(db)
+        .await
+        .map_err(|e| crate::errors::AppError::Database(e.into()))?
+        .ok_or_else(|| crate::errors::AppError::Internal("Default project was not created".to_string()))?;
+    Ok((project, true))
+}
+
+/// Reject removing a subtree that contains the default project
+pub(crate) fn ensure_default_kept(projects: &[projects::Model], removed_ids: &[Uuid]) -> Result<()> {
+    if projects.iter().any(|p| p.is_default && removed_ids.contains(&p.id)) {
+        return Err(crate::errors::AppError::Validation(
+            "The default project cannot be deleted; make another project the default first".to_string(),
+        ));
+    }
+    Ok(())
+}
+
+/// Apply a requested `is_default` change before `project` is updated. Making a project the default
+/// takes the flag from the previous default, which is returned so it can be broadcast; the flag
+/// can't simply be cleared because every user keeps exactly one default project.
+pub(crate) async fn change_default_project<C: ConnectionTrait>(
+    db: &C,
+    user_id: Uuid,
+    project: &projects::Model,
+    is_default: Option<bool>,
+) -> Result<Option<projects::Model>> {
+    match is_default {
+        Some(true) if !project.is_default => {
+            let previous = Projects::find()
+                .filter(projects::Column::UserId.eq(user_id))
+                .filter(projects::Column::IsDefault.eq(true))
+                .one(db)
+                .await
+                .map_err(|e| crate::errors::AppError::Database(e.into()))?;
+            let Some(previous) = previous else {
+                return Ok(None);
+            };
+
+            let mut previous_active: projects::ActiveModel = previous.into();
+            previous_active.is_default = Set(false);
+            let previous = previous_active.update(db).await
+                .map_err(|e| crate::errors::AppError::Database(e.into()))?;
+            Ok(Some(previous))
+        }
+        Some(false) if project.is_default => Err(crate::errors::AppError::Validation(
+            "The default project cannot be unset; make another project the default instead".to_string(),
+        )),
+        _ => Ok(None),
+    }
+}
+
+/// What happens to the tasks of a deleted project subtree
+#[derive(Debug, Clone, Copy, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TaskDeleteStrategy {
+    Delete,
+    MoveToInbox,
+    MoveToParent,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct DeleteProjectQuery {
+    /// Skip the trash and remove the project immediately
+    pub permanent: Option<bool>,
+    pub tasks: Option<TaskDeleteStrategy>,
 }
 
 #[derive(Debug, Deserialize)]
 pub struct ProjectQuery {
     pub parent_id: Option<Uuid>,
     pub all: Option<bool>,
+    /// `true` for pinned projects only, `false` for the rest
+    pub pinned: Option<bool>,
+    /// List the projects shared with this organization, whoever they belong to, instead of the user's own
+    pub organization_id: Option<Uuid>,
 }
 
 pub async fn list_projects(
     State(app_state): State<AppState>,
     auth_user: AuthUser,
     Query(query): Query<ProjectQuery>,
-) -> Result<Json<ApiResponse<Vec<ProjectResponse>>>> {
-    let mut find = Projects::find().filter(projects::Column::UserId.eq(auth_user.0.id));
+    Query(fields): Query<FieldsQuery>,
+    Query(paging): Query<PageQuery>,
+) -> Result<Json<ApiResponse<Vec<serde_json::Value>>>> {
+    let db = app_state.db.reader();
+    let mut find = match query.organization_id {
+        Some(organization_id) => {
+            organization_membership(db, auth_user.0.id, organization_id).await?;
+            Projects::find().filter(projects::Column::OrganizationId.eq(organization_id))
+        }
+        None => Projects::find().filter(projects::Column::UserId.eq(auth_user.0.id)),
+    }
+    .filter(projects::Column::DeletedAt.is_null());
     
     // If 'all' parameter is true, return all projects regardless of parent_id
     if !query.all.unwrap_or(false) {
@@ -50,16 +268,17 @@ pub async fn list_projects(
             }
         }
     }
+    if let Some(pinned) = query.pinned {
+        find = find.filter(projects::Column::IsPinned.eq(pinned));
+    }
     
-    let projects = find
+    let find = find
         .order_by_asc(projects::Column::DisplayOrder)
-        .order_by_asc(projects::Column::CreatedAt)
-        .all(&app_state.db.connection)
-        .await
-        .map_err(|e| crate::errors::AppError::Database(e.into()))?;
+        .order_by_asc(projects::Column::CreatedAt);
+    let (projects, meta) = paging.fetch(db, find).await?;
 
     let response: Vec<ProjectResponse> = projects.into_iter().map(|p| p.into()).collect();
-    Ok(Json(ApiResponse::new(response)))
+    Ok(Json(ApiResponse::list(fields.select(response)?, meta)))
 }
 
 pub async fn get_project(
@@ -67,12 +286,7 @@ pub async fn get_project(
     auth_user: AuthUser,
     Path(id): Path<Uuid>,
 ) -> Result<Json<ApiResponse<ProjectResponse>>> {
-    let project = Projects::find_by_id(id)
-        .filter(projects::Column::UserId.eq(auth_user.0.id))
-        .one(&app_state.db.connection)
-        .await
-        .map_err(|e| crate::errors::AppError::Database(e.into()))?
-        .ok_or_else(|| crate::errors::AppError::NotFound("Project not found".to_string()))?;
+    let (project, _) = accessible_project(app_state.db.reader(), auth_user.0.id, id).await?;
 
     Ok(Json(ApiResponse::new(project.into())))
 }
@@ -80,24 +294,17 @@ pub async fn get_project(
 pub async fn create_project(
     State(app_state): State<AppState>,
     auth_user: AuthUser,
-    headers: HeaderMap,
-    Json(request): Json<CreateProjectRequest>,
+    OriginConnection(connection_id): OriginConnection,
+    ValidatedJson(request): ValidatedJson<CreateProjectRequest>,
 ) -> Result<Json<ApiResponse<ProjectResponse>>> {
-    let connection_id = extract_connection_id(&headers);
-    let display_order = request.display_order.unwrap_or(0);
-    let is_collapsed = request.is_collapsed.unwrap_or(false);
-
-    let mut project_active = projects::ActiveModel::new();
-    project_active.user_id = Set(auth_user.0.id);
-    project_active.encrypted_data = Set(request.encrypted_data);
-    project_active.iv = Set(request.iv);
-    project_active.salt = Set(request.salt);
-    project_active.parent_id = Set(request.parent_id);
-    project_active.display_order = Set(display_order);
-    project_active.is_collapsed = Set(is_collapsed);
+    validate_schema_version("projects", request.schema_version)?;
+    ensure_shareable(&app_state.db.connection, auth_user.0.id, request.organization_id).await?;
+    app_state.quotas.enforce(&app_state.db.connection, auth_user.0.id, QuotaTable::Projects, 1).await?;
+    let client_id = request.id;
+    let project_active = request.into_active_model(auth_user.0.id);
 
     let project = project_active.insert(&app_state.db.connection).await
-        .map_err(|e| crate::errors::AppError::Database(e.into()))?;
+        .map_err(|e| crate::errors::AppError::from_insert(e, client_id))?;
 
     // Broadcast websocket message for project creation
     tracing::info!("Project created, broadcasting websocket message for user {} (excluding connection {:?})", auth_user.0.id, connection_id);
@@ -106,98 +313,862 @@ pub async fn create_project(
         table: "projects".to_string(),
         user_id: auth_user.0.id,
         record_id: Some(project.id),
-        data: Some(serde_json::to_value(&ProjectResponse::from(project.clone())).unwrap_or_default()),
+        data: Some(serde_json::to_value(ProjectResponse::from(project.clone())).unwrap_or_default()),
     };
-    app_state.ws_state.broadcast_to_user(&auth_user.0.id, ws_message, connection_id).await;
+    let audience = organization_audience(&app_state.db.connection, auth_user.0.id, [project.organization_id]).await?;
+    app_state.ws_state.broadcast_many_to_users(&audience, vec![ws_message], connection_id).await;
 
     Ok(Json(ApiResponse::with_message(project.into(), "Project created successfully")))
 }
 
+/// Return the user's default project, creating it first if the user has none
+pub async fn default_project(
+    State(app_state): State<AppState>,
+    auth_user: AuthUser,
+    OriginConnection(connection_id): OriginConnection,
+) -> Result<Json<ApiResponse<ProjectResponse>>> {
+    let (project, created) = ensure_default_project(&app_state.db.connection, auth_user.0.id).await?;
+
+    if created {
+        // Broadcast websocket message for the new default project
+        tracing::info!("Default project created, broadcasting websocket message for user {} (excluding connection {:?})", auth_user.0.id, connection_id);
+        let ws_message = WebSocketMessage {
+            event_type: "INSERT".to_string(),
+            table: "projects".to_string(),
+            user_id: auth_user.0.id,
+            record_id: Some(project.id),
+            data: Some(serde_json::to_value(ProjectResponse::from(project.clone())).unwrap_or_default()),
+        };
+        app_state.ws_state.broadcast_to_user(&auth_user.0.id, ws_message, connection_id).await;
+    }
+
+    Ok(Json(ApiResponse::new(project.into())))
+}
+
 pub async fn update_project(
     State(app_state): State<AppState>,
     auth_user: AuthUser,
-    headers: HeaderMap,
+    OriginConnection(connection_id): OriginConnection,
     Path(id): Path<Uuid>,
-    Json(request): Json<UpdateProjectRequest>,
+    preconditions: Preconditions,
+    ValidatedJson(request): ValidatedJson<UpdateProjectRequest>,
 ) -> Result<Json<ApiResponse<ProjectResponse>>> {
-    let connection_id = extract_connection_id(&headers);
-    
-    let project = Projects::find_by_id(id)
-        .filter(projects::Column::UserId.eq(auth_user.0.id))
-        .one(&app_state.db.connection)
-        .await
-        .map_err(|e| crate::errors::AppError::Database(e.into()))?
-        .ok_or_else(|| crate::errors::AppError::NotFound("Project not found".to_string()))?;
+    validate_schema_version("projects", request.schema_version)?;
 
-    let mut project_active: projects::ActiveModel = project.into();
-    
-    if let Some(encrypted_data) = request.encrypted_data {
-        project_active.encrypted_data = Set(encrypted_data);
+    let txn = app_state.db.connection.begin().await
+        .map_err(|e| crate::errors::AppError::Database(e.into()))?;
+
+    let (project, role) = accessible_project(&txn, auth_user.0.id, id).await?;
+    preconditions.check(&project.updated_at)?;
+    let owner_id = project.user_id;
+    if owner_id != auth_user.0.id {
+        // Others with owner rights look after the project itself; where it sits and who it's shared with stay with its user
+        if role < ProjectRole::Owner {
+            return Err(crate::errors::AppError::Forbidden("Only owners can change a shared project".to_string()));
+        }
+        if request.parent_id.is_some()
+            || request.display_order.is_some()
+            || request.is_default.is_some()
+            || request.organization_id.is_some()
+        {
+            return Err(crate::errors::AppError::Forbidden(
+                "Only the project's own user can move, reorder or share it".to_string(),
+            ));
+        }
     }
-    if let Some(iv) = request.iv {
-        project_active.iv = Set(iv);
+
+    if let Some(Some(parent_id)) = request.parent_id
+        && project.parent_id != Some(parent_id)
+    {
+        validate_parent(&txn, owner_id, project.id, parent_id).await?;
     }
-    if let Some(salt) = request.salt {
-        project_active.salt = Set(salt);
+    if let Some(organization_id) = request.organization_id {
+        ensure_shareable(&txn, owner_id, organization_id).await?;
     }
-    if let Some(is_default) = request.is_default {
-        project_active.is_default = Set(is_default);
+    if request.organization_id.unwrap_or(project.organization_id).is_some()
+        && request.is_default.unwrap_or(project.is_default)
+    {
+        return Err(crate::errors::AppError::Validation(
+            "The default project cannot be shared with an organization".to_string(),
+        ));
     }
-    if let Some(parent_id) = request.parent_id {
-        project_active.parent_id = Set(Some(parent_id));
+    let previous_default = change_default_project(&txn, owner_id, &project, request.is_default).await?;
+    let previous_organization_id = project.organization_id;
+
+    record_revision(&txn, auth_user.0.id, RevisionAction::Update, &project).await?;
+    let mut project_active: projects::ActiveModel = project.into();
+    request.apply_to(&mut project_active);
+
+    let updated_project = project_active.update(&txn).await
+        .map_err(|e| crate::errors::AppError::Database(e.into()))?;
+
+    txn.commit().await
+        .map_err(|e| crate::errors::AppError::Database(e.into()))?;
+
+    // Broadcast websocket messages for the project update, to everyone it is or just stopped being
+    // shared with, and for the project that stopped being the default, to its user
+    tracing::info!("Project updated, broadcasting websocket message for user {} (excluding connection {:?})", owner_id, connection_id);
+    let message = |p: projects::Model| WebSocketMessage {
+        event_type: "UPDATE".to_string(),
+        table: "projects".to_string(),
+        user_id: owner_id,
+        record_id: Some(p.id),
+        data: Some(serde_json::to_value(ProjectResponse::from(p)).unwrap_or_default()),
+    };
+    let mut audience = task_audience(&app_state.db.connection, [owner_id], [Some(id)]).await?;
+    for user_id in organization_audience(&app_state.db.connection, owner_id, [previous_organization_id]).await? {
+        if !audience.contains(&user_id) {
+            audience.push(user_id);
+        }
     }
-    if let Some(display_order) = request.display_order {
-        project_active.display_order = Set(display_order);
+    app_state.ws_state.broadcast_many_to_users(&audience, vec![message(updated_project.clone())], connection_id).await;
+    if let Some(previous_default) = previous_default {
+        app_state.ws_state.broadcast_to_user(&owner_id, message(previous_default), connection_id).await;
     }
-    if let Some(is_collapsed) = request.is_collapsed {
-        project_active.is_collapsed = Set(is_collapsed);
+
+    Ok(Json(ApiResponse::with_message(updated_project.into(), "Project updated successfully")))
+}
+
+/// Prior versions of a project, newest first
+pub async fn list_project_revisions(
+    State(app_state): State<AppState>,
+    auth_user: AuthUser,
+    Path(id): Path<Uuid>,
+    Query(paging): Query<PageQuery>,
+) -> Result<Json<ApiResponse<Vec<RevisionResponse<ProjectResponse>>>>> {
+    let db = app_state.db.reader();
+    let (project, _) = accessible_project(db, auth_user.0.id, id).await?;
+
+    let (response, meta) = paging.page(list_revisions::<_, projects::Model, _>(db, project.id).await?)?;
+    Ok(Json(ApiResponse::list(response, meta)))
+}
+
+/// Bring back a project's content as of one of its revisions; the version replaced becomes a revision itself
+pub async fn revert_project(
+    State(app_state): State<AppState>,
+    auth_user: AuthUser,
+    OriginConnection(connection_id): OriginConnection,
+    Path((id, revision_id)): Path<(Uuid, Uuid)>,
+) -> Result<Json<ApiResponse<ProjectResponse>>> {
+    let txn = app_state.db.connection.begin().await
+        .map_err(|e| crate::errors::AppError::Database(e.into()))?;
+
+    let (project, role) = accessible_project(&txn, auth_user.0.id, id).await?;
+    let owner_id = project.user_id;
+    if owner_id != auth_user.0.id && role < ProjectRole::Owner {
+        return Err(crate::errors::AppError::Forbidden("Only owners can change a shared project".to_string()));
     }
+    let prior: projects::Model = find_revision(&txn, id, revision_id).await?;
+
+    record_revision(&txn, auth_user.0.id, RevisionAction::Revert, &project).await?;
+    let mut project_active: projects::ActiveModel = project.into();
+    projects::Model::revert(&mut project_active, prior);
+    let reverted_project = project_active.update(&txn).await
+        .map_err(|e| crate::errors::AppError::Database(e.into()))?;
 
-    let updated_project = project_active.update(&app_state.db.connection).await
+    txn.commit().await
         .map_err(|e| crate::errors::AppError::Database(e.into()))?;
 
-    // Broadcast websocket message for project update
-    tracing::info!("Project updated, broadcasting websocket message for user {} (excluding connection {:?})", auth_user.0.id, connection_id);
+    // Broadcast websocket message for the project update to everyone it is shared with
+    tracing::info!("Project reverted, broadcasting websocket message for user {} (excluding connection {:?})", owner_id, connection_id);
     let ws_message = WebSocketMessage {
         event_type: "UPDATE".to_string(),
         table: "projects".to_string(),
-        user_id: auth_user.0.id,
-        record_id: Some(updated_project.id),
-        data: Some(serde_json::to_value(&ProjectResponse::from(updated_project.clone())).unwrap_or_default()),
+        user_id: owner_id,
+        record_id: Some(id),
+        data: Some(serde_json::to_value(ProjectResponse::from(reverted_project.clone())).unwrap_or_default()),
     };
-    app_state.ws_state.broadcast_to_user(&auth_user.0.id, ws_message, connection_id).await;
+    let audience = task_audience(&app_state.db.connection, [owner_id], [Some(id)]).await?;
+    app_state.ws_state.broadcast_many_to_users(&audience, vec![ws_message], connection_id).await;
 
-    Ok(Json(ApiResponse::with_message(updated_project.into(), "Project updated successfully")))
+    Ok(Json(ApiResponse::with_message(reverted_project.into(), "Project reverted successfully")))
+}
+
+/// Rewrite `display_order` for sibling projects in one transaction. Siblings left out of the
+/// request keep their relative order after the listed ones, so no two share a position.
+pub async fn reorder_projects(
+    State(app_state): State<AppState>,
+    auth_user: AuthUser,
+    OriginConnection(connection_id): OriginConnection,
+    ValidatedJson(request): ValidatedJson<ReorderRequest>,
+) -> Result<Json<ApiResponse<Vec<ProjectResponse>>>> {
+
+    let txn = begin_write(&app_state.db.connection).await?;
+
+    let parent_ids: Vec<Option<Uuid>> = Projects::find()
+        .select_only()
+        .column(projects::Column::ParentId)
+        .filter(projects::Column::UserId.eq(auth_user.0.id))
+        .filter(projects::Column::DeletedAt.is_null())
+        .filter(projects::Column::Id.is_in(request.ids.clone()))
+        .into_tuple()
+        .all(&txn)
+        .await
+        .map_err(|e| crate::errors::AppError::Database(e.into()))?;
+
+    if parent_ids.len() != request.ids.len() {
+        return Err(crate::errors::AppError::NotFound("Project not found".to_string()));
+    }
+
+    // Ordering is only meaningful among siblings
+    let parent_id = parent_ids[0];
+    if parent_ids.iter().any(|id| *id != parent_id) {
+        return Err(crate::errors::AppError::Validation("Projects must share the same parent".to_string()));
+    }
+
+    // Concurrent reorders and moves among the same siblings take turns instead of interleaving.
+    // Rows are locked in id order, like in every other reorder or move, so they can't deadlock.
+    lock_siblings(&txn, "projects", [(auth_user.0.id, parent_id)]).await?;
+    let mut siblings = Projects::find()
+        .filter(projects::Column::UserId.eq(auth_user.0.id))
+        .filter(projects::Column::DeletedAt.is_null())
+        .filter(match parent_id {
+            Some(parent_id) => projects::Column::ParentId.eq(parent_id),
+            None => projects::Column::ParentId.is_null(),
+        })
+        .order_by_asc(projects::Column::Id)
+        .lock_exclusive()
+        .all(&txn)
+        .await
+        .map_err(|e| crate::errors::AppError::Database(e.into()))?;
+    siblings.sort_by_key(|p| (p.display_order, p.created_at));
+
+    let mut ordered = Vec::with_capacity(siblings.len());
+    for id in &request.ids {
+        let index = siblings.iter().position(|p| p.id == *id)
+            .ok_or_else(|| crate::errors::AppError::NotFound("Project not found".to_string()))?;
+        ordered.push(siblings.remove(index));
+    }
+    ordered.append(&mut siblings);
+
+    let mut reordered = Vec::with_capacity(ordered.len());
+    let mut changed_ids = Vec::new();
+    for (index, project) in ordered.into_iter().enumerate() {
+        let display_order = ReorderRequest::position(index);
+
+        // Untouched projects keep their `updated_at` so other clients don't refetch them
+        if project.display_order == display_order {
+            reordered.push(project);
+            continue;
+        }
+
+        let mut project_active: projects::ActiveModel = project.into();
+        project_active.display_order = Set(display_order);
+        let updated_project = project_active.update(&txn).await
+            .map_err(|e| crate::errors::AppError::Database(e.into()))?;
+        changed_ids.push(updated_project.id);
+        reordered.push(updated_project);
+    }
+
+    txn.commit().await
+        .map_err(|e| crate::errors::AppError::Database(e.into()))?;
+
+    // Broadcast websocket messages for every project whose position changed
+    tracing::info!("Projects reordered, broadcasting websocket messages for user {} (excluding connection {:?})", auth_user.0.id, connection_id);
+    let ws_messages = reordered
+        .iter()
+        .filter(|p| changed_ids.contains(&p.id))
+        .map(|project| WebSocketMessage {
+            event_type: "UPDATE".to_string(),
+            table: "projects".to_string(),
+            user_id: auth_user.0.id,
+            record_id: Some(project.id),
+            data: Some(serde_json::to_value(ProjectResponse::from(project.clone())).unwrap_or_default()),
+        })
+        .collect();
+    app_state.ws_state.broadcast_many_to_user(&auth_user.0.id, ws_messages, connection_id).await;
+
+    let response: Vec<ProjectResponse> = reordered.into_iter().map(|p| p.into()).collect();
+    Ok(Json(ApiResponse::with_message(response, "Projects reordered successfully")))
 }
 
 pub async fn delete_project(
     State(app_state): State<AppState>,
     auth_user: AuthUser,
-    headers: HeaderMap,
+    OriginConnection(connection_id): OriginConnection,
     Path(id): Path<Uuid>,
+    preconditions: Preconditions,
+    Query(query): Query<DeleteProjectQuery>,
 ) -> Result<Json<ApiResponse<()>>> {
-    let connection_id = extract_connection_id(&headers);
-    
-    let result = Projects::delete_by_id(id)
-        .filter(projects::Column::UserId.eq(auth_user.0.id))
-        .exec(&app_state.db.connection)
+    let permanent = query.permanent.unwrap_or(false);
+    // Trashed projects take their tasks along so restore brings them back; purged projects move them to the inbox
+    let strategy = query.tasks.unwrap_or(if permanent {
+        TaskDeleteStrategy::MoveToInbox
+    } else {
+        TaskDeleteStrategy::Delete
+    });
+
+    let txn = app_state.db.connection.begin().await
+        .map_err(|e| crate::errors::AppError::Database(e.into()))?;
+
+    let mut find = Projects::find().filter(projects::Column::UserId.eq(auth_user.0.id));
+    if !permanent {
+        find = find.filter(projects::Column::DeletedAt.is_null());
+    }
+    let user_projects = find
+        .all(&txn)
         .await
         .map_err(|e| crate::errors::AppError::Database(e.into()))?;
 
-    if result.rows_affected == 0 {
-        return Err(crate::errors::AppError::NotFound("Project not found".to_string()));
+    let project = user_projects
+        .iter()
+        .find(|p| p.id == id)
+        .cloned()
+        .ok_or_else(|| crate::errors::AppError::NotFound("Project not found".to_string()))?;
+    preconditions.check(&project.updated_at)?;
+
+    let removed_ids = collect_subtree_ids(&user_projects, id);
+    ensure_default_kept(&user_projects, &removed_ids)?;
+    // Everyone the project was shared with, gathered while the project still exists
+    let audience = task_audience(&txn, [auth_user.0.id], [Some(id)]).await?;
+    let mut removed_item_ids = Vec::new();
+    let mut moved_items = Vec::new();
+
+    match strategy {
+        TaskDeleteStrategy::Delete => {
+            if permanent {
+                removed_item_ids = CanDoList::find()
+                    .select_only()
+                    .column(can_do_list::Column::Id)
+                    .filter(can_do_list::Column::UserId.eq(auth_user.0.id))
+                    .filter(can_do_list::Column::ProjectId.is_in(removed_ids.clone()))
+                    .into_tuple::<Uuid>()
+                    .all(&txn)
+                    .await
+                    .map_err(|e| crate::errors::AppError::Database(e.into()))?;
+
+                CanDoList::delete_many()
+                    .filter(can_do_list::Column::Id.is_in(removed_item_ids.clone()))
+                    .exec(&txn)
+                    .await
+                    .map_err(|e| crate::errors::AppError::Database(e.into()))?;
+
+                for item_id in &removed_item_ids {
+                    record_deletion(&txn, auth_user.0.id, "can_do_list", *item_id).await?;
+                }
+            }
+        }
+        TaskDeleteStrategy::MoveToInbox | TaskDeleteStrategy::MoveToParent => {
+            // The parent only qualifies if it survives this delete; otherwise the tasks go to the inbox
+            let parent_id = match strategy {
+                TaskDeleteStrategy::MoveToParent => project.parent_id.filter(|parent_id| {
+                    user_projects.iter().any(|p| p.id == *parent_id && p.deleted_at.is_none())
+                }),
+                _ => None,
+            };
+            let target_project_id = match parent_id {
+                Some(parent_id) => parent_id,
+                None => ensure_default_project(&txn, auth_user.0.id).await?.0.id,
+            };
+
+            let moved_item_ids: Vec<Uuid> = CanDoList::find()
+                .select_only()
+                .column(can_do_list::Column::Id)
+                .filter(can_do_list::Column::UserId.eq(auth_user.0.id))
+                .filter(can_do_list::Column::DeletedAt.is_null())
+                .filter(can_do_list::Column::ProjectId.is_in(removed_ids.clone()))
+                .into_tuple()
+                .all(&txn)
+                .await
+                .map_err(|e| crate::errors::AppError::Database(e.into()))?;
+
+            CanDoList::update_many()
+                .col_expr(can_do_list::Column::ProjectId, Expr::value(target_project_id))
+                .col_expr(can_do_list::Column::UpdatedAt, Expr::value(chrono::Utc::now()))
+                .filter(can_do_list::Column::Id.is_in(moved_item_ids.clone()))
+                .exec(&txn)
+                .await
+                .map_err(|e| crate::errors::AppError::Database(e.into()))?;
+
+            moved_items = CanDoList::find()
+                .filter(can_do_list::Column::Id.is_in(moved_item_ids))
+                .all(&txn)
+                .await
+                .map_err(|e| crate::errors::AppError::Database(e.into()))?;
+        }
+    }
+
+    if permanent {
+        // Remaining (trashed) tasks would be detached by `ON DELETE SET NULL`; send them to the inbox
+        // explicitly instead so they have a project once restored and `updated_at` moves for delta sync
+        let (default_project, _) = ensure_default_project(&txn, auth_user.0.id).await?;
+        CanDoList::update_many()
+            .col_expr(can_do_list::Column::ProjectId, Expr::value(default_project.id))
+            .col_expr(can_do_list::Column::UpdatedAt, Expr::value(chrono::Utc::now()))
+            .filter(can_do_list::Column::UserId.eq(auth_user.0.id))
+            .filter(can_do_list::Column::ProjectId.is_in(removed_ids.clone()))
+            .exec(&txn)
+            .await
+            .map_err(|e| crate::errors::AppError::Database(e.into()))?;
+
+        Projects::delete_by_id(id)
+            .filter(projects::Column::UserId.eq(auth_user.0.id))
+            .exec(&txn)
+            .await
+            .map_err(|e| crate::errors::AppError::Database(e.into()))?;
+
+        for removed_id in &removed_ids {
+            record_deletion(&txn, auth_user.0.id, "projects", *removed_id).await?;
+        }
+    } else {
+        record_revision(&txn, auth_user.0.id, RevisionAction::Delete, &project).await?;
+        // Moved tasks are no longer in the subtree, so only `delete` leaves tasks to trash here
+        removed_item_ids = trash_projects(&txn, auth_user.0.id, &removed_ids).await?;
     }
 
-    // Broadcast websocket message for project deletion
+    txn.commit().await
+        .map_err(|e| crate::errors::AppError::Database(e.into()))?;
+
+    // Broadcast websocket messages for the project deletion and everything that happened to its tasks
     tracing::info!("Project deleted, broadcasting websocket message for user {} (excluding connection {:?})", auth_user.0.id, connection_id);
-    let ws_message = WebSocketMessage {
+    let project_message = WebSocketMessage {
         event_type: "DELETE".to_string(),
         table: "projects".to_string(),
         user_id: auth_user.0.id,
         record_id: Some(id),
         data: None,
     };
+    let others: Vec<Uuid> = audience.into_iter().filter(|user_id| *user_id != auth_user.0.id).collect();
+    app_state.ws_state.broadcast_many_to_users(&others, vec![project_message.clone()], connection_id).await;
+    let mut ws_messages = vec![project_message];
+    for item_id in removed_item_ids {
+        ws_messages.push(WebSocketMessage {
+            event_type: "DELETE".to_string(),
+            table: "can_do_list".to_string(),
+            user_id: auth_user.0.id,
+            record_id: Some(item_id),
+            data: None,
+        });
+    }
+    for item in moved_items {
+        ws_messages.push(WebSocketMessage {
+            event_type: "UPDATE".to_string(),
+            table: "can_do_list".to_string(),
+            user_id: auth_user.0.id,
+            record_id: Some(item.id),
+            data: Some(serde_json::to_value(CanDoItemResponse::from(item)).unwrap_or_default()),
+        });
+    }
+    app_state.ws_state.broadcast_many_to_user(&auth_user.0.id, ws_messages, connection_id).await;
+
+    let message = if permanent { "Project deleted successfully" } else { "Project moved to trash" };
+    Ok(Json(ApiResponse::with_message((), message)))
+}
+
+/// Change a project's parent and place it among its new siblings in one transaction
+pub async fn move_project(
+    State(app_state): State<AppState>,
+    auth_user: AuthUser,
+    OriginConnection(connection_id): OriginConnection,
+    Path(id): Path<Uuid>,
+    ValidatedJson(request): ValidatedJson<MoveProjectRequest>,
+) -> Result<Json<ApiResponse<ProjectResponse>>> {
+    let txn = begin_write(&app_state.db.connection).await?;
+
+    // Take turns with reorders among the project's current and new siblings
+    let current_parent_id: Option<Option<Uuid>> = Projects::find_by_id(id)
+        .select_only()
+        .column(projects::Column::ParentId)
+        .filter(projects::Column::UserId.eq(auth_user.0.id))
+        .into_tuple()
+        .one(&txn)
+        .await
+        .map_err(|e| crate::errors::AppError::Database(e.into()))?;
+    lock_siblings(
+        &txn,
+        "projects",
+        current_parent_id.into_iter().chain([request.parent_id]).map(|parent_id| (auth_user.0.id, parent_id)),
+    )
+    .await?;
+
+    // Lock the user's hierarchy so concurrent moves can't combine into a cycle
+    let user_projects = Projects::find()
+        .filter(projects::Column::UserId.eq(auth_user.0.id))
+        .filter(projects::Column::DeletedAt.is_null())
+        .order_by_asc(projects::Column::Id)
+        .lock_exclusive()
+        .all(&txn)
+        .await
+        .map_err(|e| crate::errors::AppError::Database(e.into()))?;
+
+    let project = user_projects
+        .iter()
+        .find(|p| p.id == id)
+        .cloned()
+        .ok_or_else(|| crate::errors::AppError::NotFound("Project not found".to_string()))?;
+
+    if let Some(parent_id) = request.parent_id {
+        if !user_projects.iter().any(|p| p.id == parent_id) {
+            return Err(crate::errors::AppError::NotFound("Parent project not found".to_string()));
+        }
+        if would_create_cycle(&user_projects, id, parent_id) {
+            return Err(crate::errors::AppError::Coded(
+                crate::errors::ErrorCode::ValidationParentCycle,
+                "A project cannot be moved into itself or one of its descendants".to_string(),
+            ));
+        }
+    }
+
+    let mut siblings: Vec<&projects::Model> = user_projects
+        .iter()
+        .filter(|p| p.parent_id == request.parent_id && p.id != id)
+        .collect();
+    siblings.sort_by_key(|p| (p.display_order, p.created_at));
+    let position = request.position.unwrap_or(siblings.len()).min(siblings.len());
+    siblings.insert(position, &project);
+
+    let mut moved = None;
+    let mut changed = Vec::new();
+    for (index, sibling) in siblings.into_iter().enumerate() {
+        let display_order = ReorderRequest::position(index);
+        let is_moved = sibling.id == id;
+        if !is_moved && sibling.display_order == display_order {
+            continue;
+        }
+
+        let mut project_active: projects::ActiveModel = sibling.clone().into();
+        project_active.display_order = Set(display_order);
+        if is_moved {
+            project_active.parent_id = Set(request.parent_id);
+        }
+        let updated_project = project_active.update(&txn).await
+            .map_err(|e| crate::errors::AppError::Database(e.into()))?;
+        if is_moved {
+            moved = Some(updated_project.clone());
+        }
+        changed.push(updated_project);
+    }
+
+    txn.commit().await
+        .map_err(|e| crate::errors::AppError::Database(e.into()))?;
+
+    // Broadcast websocket messages for the moved project and every re-numbered sibling
+    tracing::info!("Project moved, broadcasting websocket messages for user {} (excluding connection {:?})", auth_user.0.id, connection_id);
+    let ws_messages = changed
+        .into_iter()
+        .map(|p| WebSocketMessage {
+            event_type: "UPDATE".to_string(),
+            table: "projects".to_string(),
+            user_id: auth_user.0.id,
+            record_id: Some(p.id),
+            data: Some(serde_json::to_value(ProjectResponse::from(p)).unwrap_or_default()),
+        })
+        .collect();
+    app_state.ws_state.broadcast_many_to_user(&auth_user.0.id, ws_messages, connection_id).await;
+
+    let moved = moved.ok_or_else(|| crate::errors::AppError::Internal("Moved project was not updated".to_string()))?;
+    Ok(Json(ApiResponse::with_message(moved.into(), "Project moved successfully")))
+}
+
+/// Deep-copy a project, its sub-projects and their tasks under new ids
+pub async fn duplicate_project(
+    State(app_state): State<AppState>,
+    auth_user: AuthUser,
+    OriginConnection(connection_id): OriginConnection,
+    Path(id): Path<Uuid>,
+) -> Result<Json<ApiResponse<DuplicateProjectResponse>>> {
+    let txn = app_state.db.connection.begin().await
+        .map_err(|e| crate::errors::AppError::Database(e.into()))?;
+
+    let user_projects = Projects::find()
+        .filter(projects::Column::UserId.eq(auth_user.0.id))
+        .filter(projects::Column::DeletedAt.is_null())
+        .all(&txn)
+        .await
+        .map_err(|e| crate::errors::AppError::Database(e.into()))?;
+
+    if !user_projects.iter().any(|p| p.id == id) {
+        return Err(crate::errors::AppError::NotFound("Project not found".to_string()));
+    }
+
+    // Parents come before their children, so every copied parent_id already exists
+    let source_ids = collect_subtree_ids(&user_projects, id);
+    app_state.quotas.enforce(&txn, auth_user.0.id, QuotaTable::Projects, source_ids.len() as u64).await?;
+    let mut id_map: HashMap<Uuid, Uuid> = HashMap::new();
+    let mut copied_projects = Vec::with_capacity(source_ids.len());
+
+    for source_id in &source_ids {
+        let Some(source) = user_projects.iter().find(|p| p.id == *source_id) else {
+            continue;
+        };
+
+        let mut project_active = projects::ActiveModel::new();
+        project_active.user_id = Set(auth_user.0.id);
+        project_active.encrypted_data = Set(source.encrypted_data.clone());
+        project_active.iv = Set(source.iv.clone());
+        project_active.salt = Set(source.salt.clone());
+        project_active.schema_version = Set(source.schema_version);
+        // The copy sits next to the original; only descendants are re-parented
+        project_active.parent_id = Set(match source.parent_id {
+            Some(parent_id) if source.id != id => id_map.get(&parent_id).copied(),
+            parent_id => parent_id,
+        });
+        project_active.display_order = Set(source.display_order);
+        project_active.is_collapsed = Set(source.is_collapsed);
+        project_active.is_pinned = Set(source.is_pinned);
+        project_active.search_tokens = Set(source.search_tokens.clone());
+
+        let copy = project_active.insert(&txn).await
+            .map_err(|e| crate::errors::AppError::Database(e.into()))?;
+        id_map.insert(source.id, copy.id);
+        copied_projects.push(copy);
+    }
+
+    let source_items = CanDoList::find()
+        .filter(can_do_list::Column::UserId.eq(auth_user.0.id))
+        .filter(can_do_list::Column::DeletedAt.is_null())
+        .filter(can_do_list::Column::ProjectId.is_in(source_ids))
+        .order_by_asc(can_do_list::Column::DisplayOrder)
+        .order_by_asc(can_do_list::Column::CreatedAt)
+        .all(&txn)
+        .await
+        .map_err(|e| crate::errors::AppError::Database(e.into()))?;
+    app_state.quotas.enforce(&txn, auth_user.0.id, QuotaTable::CanDoList, source_items.len() as u64).await?;
+
+    let mut copied_items = Vec::with_capacity(source_items.len());
+    let mut item_id_map = HashMap::new();
+    let mut source_parents = Vec::with_capacity(source_items.len());
+    for source in source_items {
+        source_parents.push(source.parent_task_id);
+        let mut item_active = can_do_list::ActiveModel::new();
+        item_active.user_id = Set(auth_user.0.id);
+        item_active.project_id = Set(source.project_id.and_then(|project_id| id_map.get(&project_id).copied()));
+        item_active.encrypted_data = Set(source.encrypted_data);
+        item_active.iv = Set(source.iv);
+        item_active.salt = Set(source.salt);
+        item_active.schema_version = Set(source.schema_version);
+        item_active.display_order = Set(source.display_order);
+        item_active.search_bloom = Set(source.search_bloom);
+        item_active.search_tokens = Set(source.search_tokens);
+        item_active.due_at = Set(source.due_at);
+        item_active.scheduled_at = Set(source.scheduled_at);
+        item_active.completed_at = Set(source.completed_at);
+        item_active.rrule = Set(source.rrule);
+        item_active.priority = Set(source.priority);
+        item_active.estimated_minutes = Set(source.estimated_minutes);
+        item_active.is_pinned = Set(source.is_pinned);
+        item_active.pending_plaintext = Set(source.pending_plaintext);
+
+        let copy = item_active.insert(&txn).await
+            .map_err(|e| crate::errors::AppError::Database(e.into()))?;
+        item_id_map.insert(source.id, copy.id);
+        copied_items.push(copy);
+    }
+
+    // Subtasks point at the copies of their parents; ones whose parent stayed behind become top-level
+    for (copy, parent_task_id) in copied_items.iter_mut().zip(source_parents) {
+        let Some(copied_parent_id) = parent_task_id.and_then(|parent_id| item_id_map.get(&parent_id).copied()) else {
+            continue;
+        };
+        let mut item_active: can_do_list::ActiveModel = copy.clone().into();
+        item_active.parent_task_id = Set(Some(copied_parent_id));
+        *copy = item_active.update(&txn).await
+            .map_err(|e| crate::errors::AppError::Database(e.into()))?;
+    }
+
+    txn.commit().await
+        .map_err(|e| crate::errors::AppError::Database(e.into()))?;
+
+    // Broadcast websocket messages for the copied records
+    tracing::info!("Project duplicated, broadcasting websocket message for user {} (excluding connection {:?})", auth_user.0.id, connection_id);
+    let mut ws_messages = Vec::with_capacity(copied_projects.len() + copied_items.len());
+    for copy in &copied_projects {
+        ws_messages.push(WebSocketMessage {
+            event_type: "INSERT".to_string(),
+            table: "projects".to_string(),
+            user_id: auth_user.0.id,
+            record_id: Some(copy.id),
+            data: Some(serde_json::to_value(ProjectResponse::from(copy.clone())).unwrap_or_default()),
+        });
+    }
+    for copy in &copied_items {
+        ws_messages.push(WebSocketMessage {
+            event_type: "INSERT".to_string(),
+            table: "can_do_list".to_string(),
+            user_id: auth_user.0.id,
+            record_id: Some(copy.id),
+            data: Some(serde_json::to_value(CanDoItemResponse::from(copy.clone())).unwrap_or_default()),
+        });
+    }
+    app_state.ws_state.broadcast_many_to_user(&auth_user.0.id, ws_messages, connection_id).await;
+
+    let response = DuplicateProjectResponse {
+        projects: copied_projects.into_iter().map(|p| p.into()).collect(),
+        can_do_list: copied_items.into_iter().map(|item| item.into()).collect(),
+    };
+    Ok(Json(ApiResponse::with_message(response, "Project duplicated successfully")))
+}
+
+pub async fn restore_project(
+    State(app_state): State<AppState>,
+    auth_user: AuthUser,
+    OriginConnection(connection_id): OriginConnection,
+    Path(id): Path<Uuid>,
+) -> Result<Json<ApiResponse<ProjectResponse>>> {
+    let txn = app_state.db.connection.begin().await
+        .map_err(|e| crate::errors::AppError::Database(e.into()))?;
+
+    let user_projects = Projects::find()
+        .filter(projects::Column::UserId.eq(auth_user.0.id))
+        .all(&txn)
+        .await
+        .map_err(|e| crate::errors::AppError::Database(e.into()))?;
+
+    let project = user_projects
+        .iter()
+        .find(|p| p.id == id && p.deleted_at.is_some())
+        .cloned()
+        .ok_or_else(|| crate::errors::AppError::NotFound("Project not found in trash".to_string()))?;
+
+    // Only bring back the part of the subtree that was trashed together with this project
+    let deleted_at = project.deleted_at;
+    let trashed_together: Vec<projects::Model> = user_projects
+        .iter()
+        .filter(|p| p.deleted_at == deleted_at)
+        .cloned()
+        .collect();
+    let restored_ids = collect_subtree_ids(&trashed_together, id);
+
+    Projects::update_many()
+        .col_expr(projects::Column::DeletedAt, Expr::value(Option::<chrono::DateTime<chrono::Utc>>::None))
+        .col_expr(projects::Column::UpdatedAt, Expr::value(chrono::Utc::now()))
+        .filter(projects::Column::Id.is_in(restored_ids.clone()))
+        .exec(&txn)
+        .await
+        .map_err(|e| crate::errors::AppError::Database(e.into()))?;
+
+    // Re-root the project if its parent is gone or still in the trash
+    let parent_active = project.parent_id.is_none_or(|parent_id| {
+        user_projects.iter().any(|p| p.id == parent_id && p.deleted_at.is_none())
+    });
+    if !parent_active {
+        Projects::update_many()
+            .col_expr(projects::Column::ParentId, Expr::value(Option::<Uuid>::None))
+            .filter(projects::Column::Id.eq(id))
+            .exec(&txn)
+            .await
+            .map_err(|e| crate::errors::AppError::Database(e.into()))?;
+    }
+
+    let mut item_restore = CanDoList::update_many()
+        .col_expr(can_do_list::Column::DeletedAt, Expr::value(Option::<chrono::DateTime<chrono::Utc>>::None))
+        .col_expr(can_do_list::Column::UpdatedAt, Expr::value(chrono::Utc::now()))
+        .filter(can_do_list::Column::UserId.eq(auth_user.0.id))
+        .filter(can_do_list::Column::ProjectId.is_in(restored_ids.clone()));
+    if let Some(deleted_at) = deleted_at {
+        item_restore = item_restore.filter(can_do_list::Column::DeletedAt.eq(deleted_at));
+    }
+    item_restore
+        .exec(&txn)
+        .await
+        .map_err(|e| crate::errors::AppError::Database(e.into()))?;
+
+    let restored_projects = Projects::find()
+        .filter(projects::Column::Id.is_in(restored_ids.clone()))
+        .order_by_asc(projects::Column::DisplayOrder)
+        .all(&txn)
+        .await
+        .map_err(|e| crate::errors::AppError::Database(e.into()))?;
+
+    let restored_items = CanDoList::find()
+        .filter(can_do_list::Column::UserId.eq(auth_user.0.id))
+        .filter(can_do_list::Column::DeletedAt.is_null())
+        .filter(can_do_list::Column::ProjectId.is_in(restored_ids))
+        .all(&txn)
+        .await
+        .map_err(|e| crate::errors::AppError::Database(e.into()))?;
+
+    txn.commit().await
+        .map_err(|e| crate::errors::AppError::Database(e.into()))?;
+
+    // Broadcast websocket messages for the restored records; clients dropped them on delete
+    tracing::info!("Project restored, broadcasting websocket message for user {} (excluding connection {:?})", auth_user.0.id, connection_id);
+    for restored in &restored_projects {
+        let ws_message = WebSocketMessage {
+            event_type: "INSERT".to_string(),
+            table: "projects".to_string(),
+            user_id: auth_user.0.id,
+            record_id: Some(restored.id),
+            data: Some(serde_json::to_value(ProjectResponse::from(restored.clone())).unwrap_or_default()),
+        };
+        app_state.ws_state.broadcast_to_user(&auth_user.0.id, ws_message, connection_id).await;
+    }
+    for item in restored_items {
+        let ws_message = WebSocketMessage {
+            event_type: "INSERT".to_string(),
+            table: "can_do_list".to_string(),
+            user_id: auth_user.0.id,
+            record_id: Some(item.id),
+            data: Some(serde_json::to_value(CanDoItemResponse::from(item)).unwrap_or_default()),
+        };
+        app_state.ws_state.broadcast_to_user(&auth_user.0.id, ws_message, connection_id).await;
+    }
+
+    let restored_project = restored_projects
+        .into_iter()
+        .find(|p| p.id == id)
+        .ok_or_else(|| crate::errors::AppError::NotFound("Project not found".to_string()))?;
+
+    Ok(Json(ApiResponse::with_message(restored_project.into(), "Project restored successfully")))
+}
+
+/// Pin or unpin a project; favorites are listed with `?pinned=true`
+async fn set_project_pinned(
+    app_state: AppState,
+    auth_user: AuthUser,
+    connection_id: Option<Uuid>,
+    id: Uuid,
+    preconditions: Preconditions,
+    is_pinned: bool,
+) -> Result<Json<ApiResponse<ProjectResponse>>> {
+    let user_id = auth_user.0.id;
+    let updated_project = app_state.db.with_txn(|txn| Box::pin(async move {
+        let project = Projects::find_by_id(id)
+            .filter(projects::Column::UserId.eq(user_id))
+            .filter(projects::Column::DeletedAt.is_null())
+            .one(txn)
+            .await
+            .map_err(|e| crate::errors::AppError::Database(e.into()))?
+            .ok_or_else(|| crate::errors::AppError::NotFound("Project not found".to_string()))?;
+        preconditions.check(&project.updated_at)?;
+
+        let mut project_active: projects::ActiveModel = project.into();
+        project_active.is_pinned = Set(is_pinned);
+        project_active.update(txn).await
+            .map_err(|e| crate::errors::AppError::Database(e.into()))
+    })).await?;
+
+    // Broadcast websocket message for the pin change
+    tracing::info!("Project pin changed, broadcasting websocket message for user {} (excluding connection {:?})", auth_user.0.id, connection_id);
+    let ws_message = WebSocketMessage {
+        event_type: "UPDATE".to_string(),
+        table: "projects".to_string(),
+        user_id: auth_user.0.id,
+        record_id: Some(updated_project.id),
+        data: Some(serde_json::to_value(ProjectResponse::from(updated_project.clone())).unwrap_or_default()),
+    };
     app_state.ws_state.broadcast_to_user(&auth_user.0.id, ws_message, connection_id).await;
 
-    Ok(Json(ApiResponse::with_message((), "Project deleted successfully")))
-}
\ No newline at end of file
+    let message = if is_pinned { "Project pinned successfully" } else { "Project unpinned successfully" };
+    Ok(Json(ApiResponse::with_message(updated_project.into(), message)))
+}
+
+pub async fn pin_project(
+    State(app_state): State<AppState>,
+    auth_user: AuthUser,
+    OriginConnection(connection_id): OriginConnection,
+    Path(id): Path<Uuid>,
+    preconditions: Preconditions,
+) -> Result<Json<ApiResponse<ProjectResponse>>> {
+    set_project_pinned(app_state, auth_user, connection_id, id, preconditions, true).await
+}
+
+pub async fn unpin_project(
+    State(app_state): State<AppState>,
+    auth_user: AuthUser,
+    OriginConnection(connection_id): OriginConnection,
+    Path(id): Path<Uuid>,
+    preconditions: Preconditions,
+) -> Result<Json<ApiResponse<ProjectResponse>>> {
+    set_project_pinned(app_state, auth_user, connection_id, id, preconditions, false).await
+}
@@ -0,0 +1,30 @@
+use axum::{extract::State, response::Json};
+
+use crate::{
+    errors::Result,
+    features::Feature,
+    handlers::user_settings::time_preferences,
+    middleware::{auth::AuthUser, validation::ValidatedJson},
+    models::{
+        quick_add::{QuickAddRequest, QuickAddResponse},
+        ApiResponse,
+    },
+    quick_add::{parser_for, ParseContext},
+    state::AppState,
+};
+
+/// Take a quick-add line apart into a title, dates, a project hint and tags. Nothing is stored;
+/// the client encrypts the parts and creates the task or event.
+pub async fn quick_add(
+    State(app_state): State<AppState>,
+    auth_user: AuthUser,
+    ValidatedJson(request): ValidatedJson<QuickAddRequest>,
+) -> Result<Json<ApiResponse<QuickAddResponse>>> {
+    let db = app_state.db.reader();
+    app_state.features.require(db, auth_user.0.id, Feature::QuickAdd).await?;
+    let preferences = time_preferences(db, auth_user.0.id).await?;
+    let context = ParseContext { now: chrono::Utc::now(), timezone: preferences.timezone };
+    let parsed = parser_for(request.locale.as_deref()).parse(&request.text, &context);
+
+    Ok(Json(ApiResponse::new(parsed)))
+}
@@ -0,0 +1,535 @@
+use axum::{
+    extract::{Path, Query, State},
+    response::Json,
+};
+use chrono::{DateTime, Duration, Utc};
+use sea_orm::{prelude::DateTimeWithTimeZone, sea_query::Expr, *};
+use uuid::Uuid;
+
+use crate::{
+    entities::{prelude::*, calendar_events, project_members::ProjectRole},
+    errors::{AppError, Result},
+    handlers::{
+        calendar_events::{
+            ensure_editable, ensure_same_owner, event_audience, event_scope, resolve_all_day_update, validate_time_range,
+        },
+        reminders::{copy_offset_reminders, reschedule_event_reminders},
+        user_settings::{time_preferences, time_preferences_of},
+    },
+    middleware::{auth::AuthUser, connection::OriginConnection, preconditions::Preconditions, validation::ValidatedJson},
+    models::{
+        calendar_event::{all_day_dates, parse_status_filter, CalendarEventResponse, UpdateCalendarEventRequest},
+        recurrence::{DeleteOccurrenceQuery, OccurrenceQuery, OccurrenceResponse, OccurrenceScope, UpdateOccurrenceRequest},
+        user_settings::TimePreferences,
+        ApiResponse, PageQuery, ApplyTo,
+    },
+    quota::QuotaTable,
+    recurrence::{self, Series, MAX_OCCURRENCE_WINDOW_DAYS},
+    state::AppState,
+    websocket::WebSocketMessage,
+};
+
+pub(crate) fn event_message(event_type: &str, event: &calendar_events::Model) -> WebSocketMessage {
+    WebSocketMessage {
+        event_type: event_type.to_string(),
+        table: "calendar_events".to_string(),
+        user_id: event.user_id,
+        record_id: Some(event.id),
+        data: Some(serde_json::to_value(CalendarEventResponse::from(event.clone())).unwrap_or_default()),
+    }
+}
+
+fn delete_message(user_id: Uuid, id: Uuid) -> WebSocketMessage {
+    WebSocketMessage {
+        event_type: "DELETE".to_string(),
+        table: "calendar_events".to_string(),
+        user_id,
+        record_id: Some(id),
+        data: None,
+    }
+}
+
+/// Expand every event overlapping the window into concrete occurrences.
+/// Recurring series are expanded server-side; overrides and one-off events appear as stored.
+pub async fn list_occurrences(
+    State(app_state): State<AppState>,
+    auth_user: AuthUser,
+    Query(query): Query<OccurrenceQuery>,
+    Query(paging): Query<PageQuery>,
+) -> Result<Json<ApiResponse<Vec<OccurrenceResponse>>>> {
+    validate_time_range(Some(query.start), Some(query.end))?;
+    if query.end - query.start > Duration::days(MAX_OCCURRENCE_WINDOW_DAYS) {
+        return Err(AppError::Validation(format!(
+            "The occurrence window must not exceed {} days",
+            MAX_OCCURRENCE_WINDOW_DAYS
+        )));
+    }
+
+    let scope = event_scope(app_state.db.reader(), auth_user.0.id, ProjectRole::Viewer).await?;
+    let mut find = CalendarEvents::find().filter(scope);
+    if let Some(calendar_id) = query.calendar_id {
+        find = find.filter(calendar_events::Column::CalendarId.eq(calendar_id));
+    }
+    if let Some(statuses) = parse_status_filter(query.status.as_deref())? {
+        find = find.filter(calendar_events::Column::Status.is_in(statuses));
+    }
+
+    let occurrences = occurrences_in(app_state.db.reader(), find, query.start, query.end).await?;
+    let (response, meta) = paging.page(occurrences)?;
+    Ok(Json(ApiResponse::list(response, meta)))
+}
+
+/// Occurrences of the selected events overlapping `[start, end)`, sorted by start time.
+/// Trashed events are left out; callers narrow `find` down to a user and any other filters.
+pub(crate) async fn occurrences_in<C: ConnectionTrait>(
+    db: &C,
+    find: Select<CalendarEvents>,
+    start: DateTime<Utc>,
+    end: DateTime<Utc>,
+) -> Result<Vec<OccurrenceResponse>> {
+    let events = find
+        .filter(calendar_events::Column::DeletedAt.is_null())
+        .filter(calendar_events::Column::StartTime.lt(end))
+        .filter(
+            Condition::any()
+                .add(calendar_events::Column::Rrule.is_not_null())
+                .add(calendar_events::Column::EndTime.gt(start))
+                .add(
+                    Condition::all()
+                        .add(calendar_events::Column::EndTime.is_null())
+                        .add(calendar_events::Column::StartTime.gte(start)),
+                ),
+        )
+        .all(db)
+        .await
+        .map_err(|e| AppError::Database(e.into()))?;
+    let owners: Vec<Uuid> = events.iter().filter(|event| event.rrule.is_some()).map(|event| event.user_id).collect();
+    let times = time_preferences_of(db, owners).await?;
+
+    let mut occurrences = Vec::new();
+    for event in events {
+        let time = times.get(&event.user_id).copied().unwrap_or_default();
+        let Some(series) = Series::from_event(&event, &time) else {
+            if let Some(start_time) = event.start_time {
+                occurrences.push(OccurrenceResponse {
+                    event_id: event.id,
+                    start_time: start_time.naive_utc().and_utc(),
+                    end_time: event.end_time.map(|dt| dt.naive_utc().and_utc()),
+                    recurring_event_id: event.recurring_event_id,
+                    original_start_time: event.original_start_time.map(|dt| dt.naive_utc().and_utc()),
+                    status: event.status,
+                });
+            }
+            continue;
+        };
+
+        for start_time in series.occurrences(start, end)? {
+            occurrences.push(OccurrenceResponse {
+                event_id: event.id,
+                start_time,
+                end_time: event.end_time.map(|_| start_time + series.duration),
+                recurring_event_id: Some(event.id),
+                original_start_time: Some(start_time),
+                status: event.status,
+            });
+        }
+    }
+
+    occurrences.sort_by_key(|occurrence| (occurrence.start_time, occurrence.event_id));
+    Ok(occurrences)
+}
+
+/// A recurring event the user may edit, in their own calendars or in ones shared with them
+async fn find_series<C: ConnectionTrait>(db: &C, user_id: Uuid, id: Uuid) -> Result<(calendar_events::Model, Series)> {
+    let event = CalendarEvents::find_by_id(id)
+        .filter(event_scope(db, user_id, ProjectRole::Editor).await?)
+        .filter(calendar_events::Column::DeletedAt.is_null())
+        .one(db)
+        .await
+        .map_err(|e| AppError::Database(e.into()))?
+        .ok_or_else(|| AppError::NotFound("Calendar event not found".to_string()))?;
+    ensure_editable(&event)?;
+    let time = time_preferences(db, event.user_id).await?;
+    let series = Series::from_event(&event, &time)
+        .ok_or_else(|| AppError::Validation("Calendar event is not recurring".to_string()))?;
+    Ok((event, series))
+}
+
+/// Overrides of a series, optionally only those replacing occurrences from `from` on
+async fn find_overrides<C: ConnectionTrait>(
+    db: &C,
+    series_id: Uuid,
+    from: Option<DateTime<Utc>>,
+) -> Result<Vec<calendar_events::Model>> {
+    let mut find = CalendarEvents::find()
+        .filter(calendar_events::Column::RecurringEventId.eq(series_id))
+        .filter(calendar_events::Column::DeletedAt.is_null());
+    if let Some(from) = from {
+        find = find.filter(calendar_events::Column::OriginalStartTime.gte(from));
+    }
+    find.all(db).await.map_err(|e| AppError::Database(e.into()))
+}
+
+/// Move a series' overrides to the trash along with it, stamped with the same time
+/// so restoring the series can bring them back. Returns the ids of trashed overrides.
+pub(crate) async fn trash_overrides<C: ConnectionTrait>(
+    db: &C,
+    series_id: Uuid,
+    from: Option<DateTime<Utc>>,
+    deleted_at: DateTimeWithTimeZone,
+) -> Result<Vec<Uuid>> {
+    let ids: Vec<Uuid> = find_overrides(db, series_id, from).await?.into_iter().map(|e| e.id).collect();
+    if ids.is_empty() {
+        return Ok(ids);
+    }
+
+    CalendarEvents::update_many()
+        .col_expr(calendar_events::Column::DeletedAt, Expr::value(deleted_at))
+        .col_expr(calendar_events::Column::UpdatedAt, Expr::value(deleted_at))
+        .filter(calendar_events::Column::Id.is_in(ids.clone()))
+        .exec(db)
+        .await
+        .map_err(|e| AppError::Database(e.into()))?;
+    Ok(ids)
+}
+
+/// Bring back overrides trashed together with their series
+pub(crate) async fn restore_overrides<C: ConnectionTrait>(
+    db: &C,
+    series_id: Uuid,
+    deleted_at: DateTimeWithTimeZone,
+) -> Result<Vec<calendar_events::Model>> {
+    let overrides = CalendarEvents::find()
+        .filter(calendar_events::Column::RecurringEventId.eq(series_id))
+        .filter(calendar_events::Column::DeletedAt.eq(deleted_at))
+        .all(db)
+        .await
+        .map_err(|e| AppError::Database(e.into()))?;
+
+    let mut restored = Vec::with_capacity(overrides.len());
+    for event in overrides {
+        let mut event_active: calendar_events::ActiveModel = event.into();
+        event_active.deleted_at = Set(None);
+        restored.push(event_active.update(db).await.map_err(|e| AppError::Database(e.into()))?);
+    }
+    Ok(restored)
+}
+
+/// Rewrite times given for the occurrence at `occurrence` into times for a record whose
+/// first occurrence is `anchor`, returning how far the occurrence moved
+fn retime(
+    changes: &mut UpdateCalendarEventRequest,
+    series: &Series,
+    has_end: bool,
+    occurrence: DateTime<Utc>,
+    anchor: DateTime<Utc>,
+) -> Result<Duration> {
+    let new_start = match changes.start_time {
+        Some(Some(start_time)) => start_time,
+        Some(None) => return Err(AppError::Validation("A recurring event requires a start_time".to_string())),
+        None => occurrence,
+    };
+    let shift = new_start - occurrence;
+    let new_end = match changes.end_time {
+        Some(end_time) => end_time,
+        None => has_end.then(|| new_start + series.duration),
+    };
+    validate_time_range(Some(new_start), new_end)?;
+
+    changes.start_time = Some(Some(anchor + shift));
+    changes.end_time = Some(new_end.map(|end_time| anchor + shift + (end_time - new_start)));
+    Ok(shift)
+}
+
+/// Set the dates of a record that is all-day after the edit from its times, which `retime` has
+/// already moved to the record's first occurrence
+fn redate(changes: &mut UpdateCalendarEventRequest, event: &calendar_events::Model, preferences: &TimePreferences) {
+    if !changes.is_all_day.unwrap_or(event.is_all_day) {
+        return;
+    }
+    if let Some(Some(start_time)) = changes.start_time {
+        let (start_date, end_date) = all_day_dates(preferences, start_time, changes.end_time.flatten());
+        changes.start_date = Some(Some(start_date));
+        changes.end_date = Some(Some(end_date));
+    }
+}
+
+fn exceptions_value(exceptions: &[DateTime<Utc>]) -> serde_json::Value {
+    serde_json::json!(exceptions)
+}
+
+/// Edit one occurrence of a series, it and every later one, or the whole series
+pub async fn update_occurrence(
+    State(app_state): State<AppState>,
+    auth_user: AuthUser,
+    OriginConnection(connection_id): OriginConnection,
+    Path(id): Path<Uuid>,
+    preconditions: Preconditions,
+    ValidatedJson(request): ValidatedJson<UpdateOccurrenceRequest>,
+) -> Result<Json<ApiResponse<CalendarEventResponse>>> {
+    let txn = app_state.db.connection.begin().await
+        .map_err(|e| AppError::Database(e.into()))?;
+
+    // Overrides and split-off series belong to the series' user, like the series itself
+    let (event, series) = find_series(&txn, auth_user.0.id, id).await?;
+    preconditions.check(&event.updated_at)?;
+    let user_id = event.user_id;
+    let previous_calendar_id = event.calendar_id;
+    let occurrence = request.occurrence_start;
+    if !series.contains(occurrence)? {
+        return Err(AppError::NotFound("Occurrence not found".to_string()));
+    }
+
+    let mut changes = request.changes;
+    if let Some(Some(calendar_id)) = changes.calendar_id {
+        ensure_same_owner(&txn, auth_user.0.id, calendar_id, user_id).await?;
+    }
+    // Dates, like times, are the occurrence's; check them against it and turn them into its times
+    let preferences = time_preferences(&txn, user_id).await?;
+    if changes.is_all_day.is_some() || changes.start_date.is_some() || changes.end_date.is_some() {
+        let mut current = event.clone();
+        if event.is_all_day {
+            let (start_date, end_date) = all_day_dates(&preferences, occurrence, Some(occurrence + series.duration));
+            current.start_date = Some(start_date);
+            current.end_date = Some(end_date);
+            // A new first day without a last one keeps the length, as a new start_time does
+            if let (Some(Some(new_start_date)), None) = (changes.start_date, changes.end_date) {
+                changes.end_date = Some(new_start_date.checked_add_signed(end_date - start_date));
+            }
+        }
+        resolve_all_day_update(&txn, &current, &mut changes).await?;
+    }
+
+    let has_end = event.end_time.is_some();
+    let scope = match request.scope {
+        OccurrenceScope::ThisAndFuture if occurrence == series.start => OccurrenceScope::All,
+        scope => scope,
+    };
+    let mut ws_messages = Vec::new();
+
+    let result = match scope {
+        OccurrenceScope::This => {
+            if changes.rrule.is_some() || changes.recurrence_exceptions.is_some() {
+                return Err(AppError::Validation("A single occurrence cannot change the recurrence".to_string()));
+            }
+            retime(&mut changes, &series, has_end, occurrence, occurrence)?;
+            redate(&mut changes, &event, &preferences);
+            app_state.quotas.enforce(&txn, user_id, QuotaTable::CalendarEvents, 1).await?;
+
+            let mut override_active = calendar_events::ActiveModel::new();
+            override_active.user_id = Set(user_id);
+            override_active.calendar_id = Set(event.calendar_id);
+            override_active.encrypted_data = Set(event.encrypted_data.clone());
+            override_active.iv = Set(event.iv.clone());
+            override_active.salt = Set(event.salt.clone());
+            override_active.schema_version = Set(event.schema_version);
+            override_active.search_bloom = Set(event.search_bloom.clone());
+            override_active.search_tokens = Set(event.search_tokens.clone());
+            override_active.status = Set(event.status);
+            override_active.is_all_day = Set(event.is_all_day);
+            override_active.start_date = Set(event.start_date);
+            override_active.end_date = Set(event.end_date);
+            override_active.recurring_event_id = Set(Some(event.id));
+            override_active.original_start_time = Set(Some(occurrence.into()));
+            changes.apply_to(&mut override_active);
+            let override_event = override_active.insert(&txn).await
+                .map_err(|e| AppError::Database(e.into()))?;
+
+            let mut exceptions = series.exceptions.clone();
+            exceptions.push(occurrence);
+            exceptions.sort();
+            let mut series_active: calendar_events::ActiveModel = event.into();
+            series_active.recurrence_exceptions = Set(exceptions_value(&exceptions));
+            let updated_series = series_active.update(&txn).await
+                .map_err(|e| AppError::Database(e.into()))?;
+            reschedule_event_reminders(&txn, &updated_series).await?;
+
+            ws_messages.push(event_message("UPDATE", &updated_series));
+            ws_messages.push(event_message("INSERT", &override_event));
+            override_event
+        }
+        OccurrenceScope::All => {
+            let shift = retime(&mut changes, &series, has_end, occurrence, series.start)?;
+            redate(&mut changes, &event, &preferences);
+            let rrule = match &changes.rrule {
+                Some(rrule) => rrule.clone(),
+                None => Some(series.rrule.clone()),
+            };
+            recurrence::validate(rrule.as_deref(), changes.start_time.flatten())?;
+
+            // Excluded and overridden slots move with the series
+            if changes.recurrence_exceptions.is_none() && !shift.is_zero() {
+                changes.recurrence_exceptions = Some(series.exceptions.iter().map(|dt| *dt + shift).collect());
+            }
+            if !shift.is_zero() {
+                for override_event in find_overrides(&txn, event.id, None).await? {
+                    let original = override_event.original_start_time.map(|dt| dt.naive_utc().and_utc() + shift);
+                    let mut override_active: calendar_events::ActiveModel = override_event.into();
+                    override_active.original_start_time = Set(original.map(Into::into));
+                    let moved = override_active.update(&txn).await
+                        .map_err(|e| AppError::Database(e.into()))?;
+                    ws_messages.push(event_message("UPDATE", &moved));
+                }
+            }
+
+            let mut series_active: calendar_events::ActiveModel = event.into();
+            changes.apply_to(&mut series_active);
+            let updated_series = series_active.update(&txn).await
+                .map_err(|e| AppError::Database(e.into()))?;
+            reschedule_event_reminders(&txn, &updated_series).await?;
+            ws_messages.insert(0, event_message("UPDATE", &updated_series));
+            updated_series
+        }
+        OccurrenceScope::ThisAndFuture => {
+            let shift = retime(&mut changes, &series, has_end, occurrence, occurrence)?;
+            redate(&mut changes, &event, &preferences);
+            let rrule = match &changes.rrule {
+                Some(rrule) => rrule.clone(),
+                None => Some(recurrence::continued(&series.rrule, series.count_before(occurrence)?)),
+            };
+            recurrence::validate(rrule.as_deref(), changes.start_time.flatten())?;
+            changes.rrule = Some(rrule);
+            if changes.recurrence_exceptions.is_none() {
+                changes.recurrence_exceptions = Some(
+                    series.exceptions.iter().filter(|dt| **dt >= occurrence).map(|dt| *dt + shift).collect(),
+                );
+            }
+            app_state.quotas.enforce(&txn, user_id, QuotaTable::CalendarEvents, 1).await?;
+
+            let mut future_active = calendar_events::ActiveModel::new();
+            future_active.user_id = Set(user_id);
+            future_active.calendar_id = Set(event.calendar_id);
+            future_active.encrypted_data = Set(event.encrypted_data.clone());
+            future_active.iv = Set(event.iv.clone());
+            future_active.salt = Set(event.salt.clone());
+            future_active.schema_version = Set(event.schema_version);
+            future_active.search_bloom = Set(event.search_bloom.clone());
+            future_active.search_tokens = Set(event.search_tokens.clone());
+            future_active.status = Set(event.status);
+            future_active.is_all_day = Set(event.is_all_day);
+            future_active.start_date = Set(event.start_date);
+            future_active.end_date = Set(event.end_date);
+            changes.apply_to(&mut future_active);
+            let future_series = future_active.insert(&txn).await
+                .map_err(|e| AppError::Database(e.into()))?;
+            copy_offset_reminders(&txn, event.id, &future_series).await?;
+
+            // Overrides of later occurrences now belong to the new series
+            for override_event in find_overrides(&txn, event.id, Some(occurrence)).await? {
+                let original = override_event.original_start_time.map(|dt| dt.naive_utc().and_utc() + shift);
+                let mut override_active: calendar_events::ActiveModel = override_event.into();
+                override_active.recurring_event_id = Set(Some(future_series.id));
+                override_active.original_start_time = Set(original.map(Into::into));
+                let moved = override_active.update(&txn).await
+                    .map_err(|e| AppError::Database(e.into()))?;
+                ws_messages.push(event_message("UPDATE", &moved));
+            }
+
+            let earlier: Vec<DateTime<Utc>> = series.exceptions.iter().copied().filter(|dt| *dt < occurrence).collect();
+            let mut series_active: calendar_events::ActiveModel = event.into();
+            series_active.rrule = Set(Some(recurrence::ended_before(&series.rrule, occurrence)));
+            series_active.recurrence_exceptions = Set(exceptions_value(&earlier));
+            let updated_series = series_active.update(&txn).await
+                .map_err(|e| AppError::Database(e.into()))?;
+            reschedule_event_reminders(&txn, &updated_series).await?;
+
+            ws_messages.insert(0, event_message("INSERT", &future_series));
+            ws_messages.insert(0, event_message("UPDATE", &updated_series));
+            future_series
+        }
+    };
+
+    txn.commit().await
+        .map_err(|e| AppError::Database(e.into()))?;
+
+    // Broadcast websocket messages for every record the edit touched
+    tracing::info!("Calendar event occurrence updated, broadcasting websocket messages for user {} (excluding connection {:?})", user_id, connection_id);
+    let audience = event_audience(
+        &app_state.db.connection,
+        user_id,
+        [result.calendar_id, previous_calendar_id],
+    )
+    .await?;
+    app_state.ws_state.broadcast_many_to_users(&audience, ws_messages, connection_id).await;
+
+    Ok(Json(ApiResponse::with_message(result.into(), "Calendar event updated successfully")))
+}
+
+/// Remove one occurrence of a series, it and every later one, or the whole series
+pub async fn delete_occurrence(
+    State(app_state): State<AppState>,
+    auth_user: AuthUser,
+    OriginConnection(connection_id): OriginConnection,
+    Path(id): Path<Uuid>,
+    preconditions: Preconditions,
+    Query(query): Query<DeleteOccurrenceQuery>,
+) -> Result<Json<ApiResponse<()>>> {
+    let txn = app_state.db.connection.begin().await
+        .map_err(|e| AppError::Database(e.into()))?;
+
+    let (event, series) = find_series(&txn, auth_user.0.id, id).await?;
+    preconditions.check(&event.updated_at)?;
+    let user_id = event.user_id;
+    let calendar_id = event.calendar_id;
+    let occurrence = query.occurrence_start;
+    if !series.contains(occurrence)? {
+        return Err(AppError::NotFound("Occurrence not found".to_string()));
+    }
+
+    let scope = match query.scope {
+        OccurrenceScope::ThisAndFuture if occurrence == series.start => OccurrenceScope::All,
+        scope => scope,
+    };
+    let mut ws_messages = Vec::new();
+
+    match scope {
+        OccurrenceScope::This => {
+            let mut exceptions = series.exceptions.clone();
+            exceptions.push(occurrence);
+            exceptions.sort();
+            let mut series_active: calendar_events::ActiveModel = event.into();
+            series_active.recurrence_exceptions = Set(exceptions_value(&exceptions));
+            let updated_series = series_active.update(&txn).await
+                .map_err(|e| AppError::Database(e.into()))?;
+            reschedule_event_reminders(&txn, &updated_series).await?;
+            ws_messages.push(event_message("UPDATE", &updated_series));
+        }
+        OccurrenceScope::ThisAndFuture => {
+            let deleted_at: DateTimeWithTimeZone = Utc::now().into();
+            let trashed = trash_overrides(&txn, event.id, Some(occurrence), deleted_at).await?;
+
+            let earlier: Vec<DateTime<Utc>> = series.exceptions.iter().copied().filter(|dt| *dt < occurrence).collect();
+            let mut series_active: calendar_events::ActiveModel = event.into();
+            series_active.rrule = Set(Some(recurrence::ended_before(&series.rrule, occurrence)));
+            series_active.recurrence_exceptions = Set(exceptions_value(&earlier));
+            let updated_series = series_active.update(&txn).await
+                .map_err(|e| AppError::Database(e.into()))?;
+            reschedule_event_reminders(&txn, &updated_series).await?;
+
+            ws_messages.push(event_message("UPDATE", &updated_series));
+            ws_messages.extend(trashed.into_iter().map(|id| delete_message(user_id, id)));
+        }
+        OccurrenceScope::All => {
+            let deleted_at: DateTimeWithTimeZone = Utc::now().into();
+            let trashed = trash_overrides(&txn, event.id, None, deleted_at).await?;
+
+            let mut series_active: calendar_events::ActiveModel = event.into();
+            series_active.deleted_at = Set(Some(deleted_at));
+            series_active.update(&txn).await
+                .map_err(|e| AppError::Database(e.into()))?;
+
+            ws_messages.push(delete_message(user_id, id));
+            ws_messages.extend(trashed.into_iter().map(|id| delete_message(user_id, id)));
+        }
+    }
+
+    txn.commit().await
+        .map_err(|e| AppError::Database(e.into()))?;
+
+    // Broadcast websocket messages for every record the deletion touched
+    tracing::info!("Calendar event occurrence deleted, broadcasting websocket messages for user {} (excluding connection {:?})", user_id, connection_id);
+    let audience = event_audience(&app_state.db.connection, user_id, [calendar_id]).await?;
+    app_state.ws_state.broadcast_many_to_users(&audience, ws_messages, connection_id).await;
+
+    Ok(Json(ApiResponse::with_message((), "Calendar event occurrence deleted successfully")))
+}
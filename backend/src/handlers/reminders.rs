@@ -0,0 +1,201 @@
+use axum::{
+    extract::{Path, Query, State},
+    response::Json,
+};
+use chrono::{DateTime, Duration, Utc};
+use sea_orm::*;
+use uuid::Uuid;
+
+use crate::{
+    entities::{prelude::*, calendar_events::{self, EventStatus}, reminders},
+    errors::{AppError, Result},
+    handlers::user_settings::time_preferences,
+    middleware::{auth::AuthUser, connection::OriginConnection, preconditions::Preconditions, validation::ValidatedJson},
+    models::{
+        reminder::{CreateReminderRequest, ReminderQuery, ReminderResponse},
+        user_settings::TimePreferences,
+        ApiResponse, PageQuery,
+    },
+    recurrence::Series,
+    state::AppState,
+    websocket::WebSocketMessage,
+};
+
+/// When a reminder fires next, given when it last fired. Offset reminders follow the
+/// event's occurrences that haven't started yet, expanded in `time` of the event's owner;
+/// absolute ones fire exactly once. Cancelled events don't fire until they are confirmed again.
+pub(crate) fn next_fire_at(
+    reminder: &reminders::Model,
+    event: &calendar_events::Model,
+    time: &TimePreferences,
+    now: DateTime<Utc>,
+) -> Result<Option<DateTime<Utc>>> {
+    if event.status == EventStatus::Cancelled {
+        return Ok(None);
+    }
+    let last_fired_at = reminder.last_fired_at.map(|dt| dt.naive_utc().and_utc());
+    if let Some(remind_at) = reminder.remind_at {
+        return Ok(last_fired_at.is_none().then(|| remind_at.naive_utc().and_utc()));
+    }
+
+    let offset = Duration::minutes(reminder.offset_minutes.unwrap_or_default().into());
+    // Skip the occurrence this reminder already fired for
+    let after = last_fired_at.map_or(now, |fired| now.max(fired + offset));
+    let next = match Series::from_event(event, time) {
+        Some(series) => series.next_after(after)?,
+        None => event.start_time.map(|dt| dt.naive_utc().and_utc()).filter(|start| *start > after),
+    };
+    Ok(next.map(|occurrence| occurrence - offset))
+}
+
+/// Recompute when an event's reminders fire after its times or recurrence changed
+pub(crate) async fn reschedule_event_reminders<C: ConnectionTrait>(db: &C, event: &calendar_events::Model) -> Result<()> {
+    let now = Utc::now();
+    let time = time_preferences(db, event.user_id).await?;
+    let event_reminders = Reminders::find()
+        .filter(reminders::Column::EventId.eq(event.id))
+        .all(db)
+        .await
+        .map_err(|e| AppError::Database(e.into()))?;
+
+    for reminder in event_reminders {
+        let fire_at = next_fire_at(&reminder, event, &time, now)?.map(Into::into);
+        if fire_at == reminder.fire_at {
+            continue;
+        }
+        let mut reminder_active: reminders::ActiveModel = reminder.into();
+        reminder_active.fire_at = Set(fire_at);
+        reminder_active.update(db).await
+            .map_err(|e| AppError::Database(e.into()))?;
+    }
+    Ok(())
+}
+
+/// Give the continuation of a split series the same relative reminders as the original
+pub(crate) async fn copy_offset_reminders<C: ConnectionTrait>(
+    db: &C,
+    from_event_id: Uuid,
+    to_event: &calendar_events::Model,
+) -> Result<()> {
+    let now = Utc::now();
+    let time = time_preferences(db, to_event.user_id).await?;
+    let offset_reminders = Reminders::find()
+        .filter(reminders::Column::EventId.eq(from_event_id))
+        .filter(reminders::Column::OffsetMinutes.is_not_null())
+        .all(db)
+        .await
+        .map_err(|e| AppError::Database(e.into()))?;
+
+    for source in offset_reminders {
+        let mut reminder = reminders::Model { event_id: to_event.id, last_fired_at: None, ..source.clone() };
+        reminder.fire_at = next_fire_at(&reminder, to_event, &time, now)?.map(Into::into);
+
+        let mut reminder_active = reminders::ActiveModel::new();
+        reminder_active.user_id = Set(reminder.user_id);
+        reminder_active.event_id = Set(reminder.event_id);
+        reminder_active.offset_minutes = Set(reminder.offset_minutes);
+        reminder_active.fire_at = Set(reminder.fire_at);
+        reminder_active.insert(db).await
+            .map_err(|e| AppError::Database(e.into()))?;
+    }
+    Ok(())
+}
+
+pub async fn list_reminders(
+    State(app_state): State<AppState>,
+    auth_user: AuthUser,
+    Query(query): Query<ReminderQuery>,
+    Query(paging): Query<PageQuery>,
+) -> Result<Json<ApiResponse<Vec<ReminderResponse>>>> {
+    let mut find = Reminders::find().filter(reminders::Column::UserId.eq(auth_user.0.id));
+    if let Some(event_id) = query.event_id {
+        find = find.filter(reminders::Column::EventId.eq(event_id));
+    }
+
+    let find = find
+        .order_by_asc(reminders::Column::CreatedAt);
+    let (reminders, meta) = paging.fetch(&app_state.db.connection, find).await?;
+
+    Ok(Json(ApiResponse::list(reminders.into_iter().map(Into::into).collect(), meta)))
+}
+
+pub async fn create_reminder(
+    State(app_state): State<AppState>,
+    auth_user: AuthUser,
+    OriginConnection(connection_id): OriginConnection,
+    ValidatedJson(request): ValidatedJson<CreateReminderRequest>,
+) -> Result<Json<ApiResponse<ReminderResponse>>> {
+    let event = CalendarEvents::find_by_id(request.event_id)
+        .filter(calendar_events::Column::UserId.eq(auth_user.0.id))
+        .filter(calendar_events::Column::DeletedAt.is_null())
+        .one(&app_state.db.connection)
+        .await
+        .map_err(|e| AppError::Database(e.into()))?
+        .ok_or_else(|| AppError::NotFound("Calendar event not found".to_string()))?;
+
+    let mut reminder_active = reminders::ActiveModel::new();
+    reminder_active.user_id = Set(auth_user.0.id);
+    reminder_active.event_id = Set(event.id);
+    reminder_active.offset_minutes = Set(request.offset_minutes);
+    reminder_active.remind_at = Set(request.remind_at.map(Into::into));
+
+    let mut reminder = reminder_active.insert(&app_state.db.connection).await
+        .map_err(|e| AppError::Database(e.into()))?;
+    let time = time_preferences(&app_state.db.connection, event.user_id).await?;
+    if let Some(fire_at) = next_fire_at(&reminder, &event, &time, Utc::now())? {
+        let mut reminder_active: reminders::ActiveModel = reminder.into();
+        reminder_active.fire_at = Set(Some(fire_at.into()));
+        reminder = reminder_active.update(&app_state.db.connection).await
+            .map_err(|e| AppError::Database(e.into()))?;
+    }
+
+    // Broadcast websocket message for reminder creation
+    tracing::info!("Reminder created, broadcasting websocket message for user {} (excluding connection {:?})", auth_user.0.id, connection_id);
+    let ws_message = WebSocketMessage {
+        event_type: "INSERT".to_string(),
+        table: "reminders".to_string(),
+        user_id: auth_user.0.id,
+        record_id: Some(reminder.id),
+        data: Some(serde_json::to_value(ReminderResponse::from(reminder.clone())).unwrap_or_default()),
+    };
+    app_state.ws_state.broadcast_to_user(&auth_user.0.id, ws_message, connection_id).await;
+
+    Ok(Json(ApiResponse::with_message(reminder.into(), "Reminder created successfully")))
+}
+
+pub async fn delete_reminder(
+    State(app_state): State<AppState>,
+    auth_user: AuthUser,
+    OriginConnection(connection_id): OriginConnection,
+    Path(id): Path<Uuid>,
+    preconditions: Preconditions,
+) -> Result<Json<ApiResponse<()>>> {
+    let user_id = auth_user.0.id;
+    app_state.db.with_txn(|txn| Box::pin(async move {
+        let reminder = Reminders::find_by_id(id)
+            .filter(reminders::Column::UserId.eq(user_id))
+            .one(txn)
+            .await
+            .map_err(|e| AppError::Database(e.into()))?
+            .ok_or_else(|| AppError::NotFound("Reminder not found".to_string()))?;
+        preconditions.check(&reminder.updated_at)?;
+        Reminders::delete_by_id(reminder.id)
+            .exec(txn)
+            .await
+            .map_err(|e| AppError::Database(e.into()))?;
+        Ok(())
+    })).await?;
+
+    // Broadcast websocket message for reminder deletion
+    tracing::info!("Reminder deleted, broadcasting websocket message for user {} (excluding connection {:?})", auth_user.0.id, connection_id);
+    let ws_message = WebSocketMessage {
+        event_type: "DELETE".to_string(),
+        table: "reminders".to_string(),
+        user_id: auth_user.0.id,
+        record_id: Some(id),
+        data: None,
+    };
+    app_state.ws_state.broadcast_to_user(&auth_user.0.id, ws_message, connection_id).await;
+
+    Ok(Json(ApiResponse::with_message((), "Reminder deleted successfully")))
+}
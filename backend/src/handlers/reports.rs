@@ -0,0 +1,197 @@
+use std::collections::HashMap;
+
+use axum::{
+    extract::{Query, State},
+    response::Json,
+};
+use chrono::{DateTime, Datelike, Duration, NaiveDate, Utc, Weekday};
+use sea_orm::{prelude::DateTimeWithTimeZone, *};
+use uuid::Uuid;
+
+use crate::{
+    entities::{
+        prelude::*, calendar_events::{self, EventStatus}, can_do_list,
+        focus_sessions::{self, FocusKind, FocusStatus}, time_entries,
+    },
+    errors::{AppError, Result},
+    handlers::{recurrence::occurrences_in, time_entries::project_time_totals},
+    middleware::auth::AuthUser,
+    models::{
+        report::{
+            CalendarEventCount, DailyCount, DailyFocus, DailySeconds, FocusTime, ReportQuery, ReportSummary, TimeTracked,
+            WeekdayActivity,
+        },
+        ApiResponse,
+    },
+    state::AppState,
+};
+
+const WEEKDAYS: [Weekday; 7] = [Weekday::Mon, Weekday::Tue, Weekday::Wed, Weekday::Thu, Weekday::Fri, Weekday::Sat, Weekday::Sun];
+
+fn midnight(date: NaiveDate) -> DateTime<Utc> {
+    date.and_hms_opt(0, 0, 0).unwrap_or_default().and_utc()
+}
+
+/// Seconds of `[from, until)` on each day it touches
+fn seconds_per_day(mut from: DateTime<Utc>, until: DateTime<Utc>) -> Vec<(NaiveDate, i64)> {
+    let mut days = Vec::new();
+    while from < until {
+        let next_day = midnight(from.date_naive() + Duration::days(1)).min(until);
+        days.push((from.date_naive(), (next_day - from).num_seconds()));
+        from = next_day;
+    }
+    days
+}
+
+/// Completions, events, tracked time and focus time of the user's own records over the last `range` days,
+/// shaped as series for charts
+pub async fn get_summary(
+    State(app_state): State<AppState>,
+    auth_user: AuthUser,
+    Query(query): Query<ReportQuery>,
+) -> Result<Json<ApiResponse<ReportSummary>>> {
+    let days = query.days()?;
+    let user_id = auth_user.0.id;
+    let db = app_state.db.reader();
+
+    let end = Utc::now();
+    let first_day = end.date_naive() - Duration::days(days - 1);
+    let start = midnight(first_day);
+    let day_index = |time: DateTime<Utc>| (time.date_naive() - first_day).num_days() as usize;
+
+    let mut weekdays: Vec<WeekdayActivity> = WEEKDAYS
+        .iter()
+        .map(|&weekday| WeekdayActivity { weekday, events: 0, event_minutes: 0, tasks_completed: 0 })
+        .collect();
+
+    // Tasks completed per day
+    let completions: Vec<Option<DateTimeWithTimeZone>> = CanDoList::find()
+        .select_only()
+        .column(can_do_list::Column::CompletedAt)
+        .filter(can_do_list::Column::UserId.eq(user_id))
+        .filter(can_do_list::Column::DeletedAt.is_null())
+        .filter(can_do_list::Column::CompletedAt.gte(start))
+        .filter(can_do_list::Column::CompletedAt.lt(end))
+        .into_tuple()
+        .all(db)
+        .await
+        .map_err(|e| AppError::Database(e.into()))?;
+    let mut tasks_completed: Vec<DailyCount> = (0..days)
+        .map(|offset| DailyCount { date: first_day + Duration::days(offset), count: 0 })
+        .collect();
+    for completed_at in completions.into_iter().flatten() {
+        let completed_at = completed_at.naive_utc().and_utc();
+        tasks_completed[day_index(completed_at)].count += 1;
+        weekdays[completed_at.weekday().num_days_from_monday() as usize].tasks_completed += 1;
+    }
+
+    // Event occurrences starting in the window, recurring series expanded
+    let occurrences = occurrences_in(
+        db,
+        CalendarEvents::find()
+            .filter(calendar_events::Column::UserId.eq(user_id))
+            .filter(calendar_events::Column::Status.ne(EventStatus::Cancelled)),
+        start,
+        end,
+    )
+    .await?;
+    let occurrences: Vec<_> = occurrences.into_iter().filter(|occurrence| occurrence.start_time >= start).collect();
+    let event_ids: Vec<Uuid> = occurrences.iter().map(|occurrence| occurrence.event_id).collect();
+    let calendar_ids: HashMap<Uuid, Option<Uuid>> = CalendarEvents::find()
+        .select_only()
+        .column(calendar_events::Column::Id)
+        .column(calendar_events::Column::CalendarId)
+        .filter(calendar_events::Column::Id.is_in(event_ids))
+        .into_tuple::<(Uuid, Option<Uuid>)>()
+        .all(db)
+        .await
+        .map_err(|e| AppError::Database(e.into()))?
+        .into_iter()
+        .collect();
+
+    let mut per_calendar: HashMap<Option<Uuid>, CalendarEventCount> = HashMap::new();
+    for occurrence in &occurrences {
+        let minutes = occurrence.end_time.map(|end_time| (end_time - occurrence.start_time).num_minutes()).unwrap_or(0);
+        let calendar_id = calendar_ids.get(&occurrence.event_id).copied().flatten();
+        let count = per_calendar
+            .entry(calendar_id)
+            .or_insert(CalendarEventCount { calendar_id, events: 0, minutes: 0 });
+        count.events += 1;
+        count.minutes += minutes;
+
+        let weekday = &mut weekdays[occurrence.start_time.weekday().num_days_from_monday() as usize];
+        weekday.events += 1;
+        weekday.event_minutes += minutes;
+    }
+    let mut events_per_calendar: Vec<CalendarEventCount> = per_calendar.into_values().collect();
+    events_per_calendar.sort_by(|a, b| b.events.cmp(&a.events).then(b.minutes.cmp(&a.minutes)).then(a.calendar_id.cmp(&b.calendar_id)));
+
+    // Tracked time, each entry split across the days it spans
+    let entries = TimeEntries::find()
+        .filter(time_entries::Column::UserId.eq(user_id))
+        .filter(time_entries::Column::StartedAt.lt(end))
+        .filter(
+            Condition::any()
+                .add(time_entries::Column::StoppedAt.is_null())
+                .add(time_entries::Column::StoppedAt.gt(start)),
+        )
+        .all(db)
+        .await
+        .map_err(|e| AppError::Database(e.into()))?;
+    let mut per_day: Vec<DailySeconds> = (0..days)
+        .map(|offset| DailySeconds { date: first_day + Duration::days(offset), seconds: 0 })
+        .collect();
+    for entry in entries {
+        let from = entry.started_at.naive_utc().and_utc().max(start);
+        let until = entry.stopped_at.map_or(end, |dt| dt.naive_utc().and_utc()).min(end);
+        for (date, seconds) in seconds_per_day(from, until) {
+            per_day[day_index(midnight(date))].seconds += seconds;
+        }
+    }
+
+    // Focus time, split like tracked time; breaks and cancelled sessions don't count
+    let sessions = FocusSessions::find()
+        .filter(focus_sessions::Column::UserId.eq(user_id))
+        .filter(focus_sessions::Column::Kind.eq(FocusKind::Focus))
+        .filter(focus_sessions::Column::Status.ne(FocusStatus::Cancelled))
+        .filter(focus_sessions::Column::StartedAt.lt(end))
+        .filter(
+            Condition::any()
+                .add(focus_sessions::Column::EndedAt.is_null())
+                .add(focus_sessions::Column::EndedAt.gt(start)),
+        )
+        .all(db)
+        .await
+        .map_err(|e| AppError::Database(e.into()))?;
+    let mut focus_per_day: Vec<DailyFocus> = (0..days)
+        .map(|offset| DailyFocus { date: first_day + Duration::days(offset), seconds: 0, sessions: 0 })
+        .collect();
+    for session in sessions {
+        let started_at = session.started_at.naive_utc().and_utc();
+        let until = session.ended_at.map_or(end, |dt| dt.naive_utc().and_utc()).min(end);
+        for (date, seconds) in seconds_per_day(started_at.max(start), until) {
+            focus_per_day[day_index(midnight(date))].seconds += seconds;
+        }
+        if session.status == FocusStatus::Completed && started_at >= start {
+            focus_per_day[day_index(started_at)].sessions += 1;
+        }
+    }
+
+    Ok(Json(ApiResponse::new(ReportSummary {
+        start,
+        end,
+        tasks_completed,
+        events_per_calendar,
+        busiest_weekdays: weekdays,
+        time_tracked: TimeTracked {
+            total_seconds: per_day.iter().map(|day| day.seconds).sum(),
+            per_day,
+            per_project: project_time_totals(db, user_id, start, end).await?,
+        },
+        focus: FocusTime {
+            total_seconds: focus_per_day.iter().map(|day| day.seconds).sum(),
+            sessions_completed: focus_per_day.iter().map(|day| day.sessions).sum(),
+            per_day: focus_per_day,
+        },
+    })))
+}
@@ -0,0 +1,106 @@
+use std::{env, sync::LazyLock};
+
+use sea_orm::*;
+use uuid::Uuid;
+
+use crate::{
+    entities::{prelude::*, record_revisions::{self, RevisionAction}},
+    errors::{AppError, Result},
+    models::revision::{RevisionResponse, Revisioned, DEFAULT_HISTORY_LIMIT},
+};
+
+/// Revisions kept per record; 0 turns history off
+static HISTORY_LIMIT: LazyLock<u64> = LazyLock::new(|| {
+    env::var("RECORD_HISTORY_LIMIT")
+        .ok()
+        .and_then(|v| v.parse().ok())
+        .unwrap_or(DEFAULT_HISTORY_LIMIT)
+});
+
+/// Save the record as it is before `action` changes it, dropping its oldest revisions beyond the limit
+pub async fn record_revision<C: ConnectionTrait, M: Revisioned>(
+    db: &C,
+    actor_id: Uuid,
+    action: RevisionAction,
+    record: &M,
+) -> Result<()> {
+    if *HISTORY_LIMIT == 0 {
+        return Ok(());
+    }
+
+    let mut revision = record_revisions::ActiveModel::new();
+    revision.user_id = Set(record.user_id());
+    revision.actor_id = Set(Some(actor_id));
+    revision.table_name = Set(M::TABLE.to_string());
+    revision.record_id = Set(record.id());
+    revision.action = Set(action);
+    revision.snapshot = Set(record.snapshot()?);
+    revision.insert(db).await
+        .map_err(|e| AppError::Database(e.into()))?;
+
+    // History is capped, so this is at most one past the limit; SQLite has no OFFSET without LIMIT
+    let revision_ids: Vec<Uuid> = RecordRevisions::find()
+        .select_only()
+        .column(record_revisions::Column::Id)
+        .filter(record_revisions::Column::TableName.eq(M::TABLE))
+        .filter(record_revisions::Column::RecordId.eq(record.id()))
+        .order_by_desc(record_revisions::Column::CreatedAt)
+        .order_by_desc(record_revisions::Column::Id)
+        .into_tuple()
+        .all(db)
+        .await
+        .map_err(|e| AppError::Database(e.into()))?;
+    let expired: Vec<Uuid> = revision_ids.into_iter().skip(*HISTORY_LIMIT as usize).collect();
+    if !expired.is_empty() {
+        RecordRevisions::delete_many()
+            .filter(record_revisions::Column::Id.is_in(expired))
+            .exec(db)
+            .await
+            .map_err(|e| AppError::Database(e.into()))?;
+    }
+
+    Ok(())
+}
+
+/// Forget the history of a record that was deleted for good
+pub async fn drop_revisions<C: ConnectionTrait>(db: &C, table: &str, record_id: Uuid) -> Result<()> {
+    RecordRevisions::delete_many()
+        .filter(record_revisions::Column::TableName.eq(table))
+        .filter(record_revisions::Column::RecordId.eq(record_id))
+        .exec(db)
+        .await
+        .map_err(|e| AppError::Database(e.into()))?;
+    Ok(())
+}
+
+/// Prior versions of a record, newest first; the caller checks access to the record
+pub async fn list_revisions<C, M, T>(db: &C, record_id: Uuid) -> Result<Vec<RevisionResponse<T>>>
+where
+    C: ConnectionTrait,
+    M: Revisioned + Into<T>,
+{
+    RecordRevisions::find()
+        .filter(record_revisions::Column::TableName.eq(M::TABLE))
+        .filter(record_revisions::Column::RecordId.eq(record_id))
+        .order_by_desc(record_revisions::Column::CreatedAt)
+        .order_by_desc(record_revisions::Column::Id)
+        .all(db)
+        .await
+        .map_err(|e| AppError::Database(e.into()))?
+        .into_iter()
+        .map(RevisionResponse::new::<M>)
+        .collect()
+}
+
+/// The version of a record saved in one of its revisions
+pub async fn find_revision<C: ConnectionTrait, M: Revisioned>(db: &C, record_id: Uuid, revision_id: Uuid) -> Result<M> {
+    let revision = RecordRevisions::find_by_id(revision_id)
+        .filter(record_revisions::Column::TableName.eq(M::TABLE))
+        .filter(record_revisions::Column::RecordId.eq(record_id))
+        .one(db)
+        .await
+        .map_err(|e| AppError::Database(e.into()))?
+        .ok_or_else(|| AppError::NotFound("Revision not found".to_string()))?;
+    serde_json::from_value(revision.snapshot)
+        .map_err(|e| AppError::Internal(format!("Unreadable revision {}: {}", revision.id, e)))
+}
@@ -0,0 +1,258 @@
+use std::collections::HashMap;
+
+use axum::{extract::State, response::Json};
+use chrono::{DateTime, Duration, Utc};
+use sea_orm::*;
+use streamline_models::DEFAULT_SCHEMA_VERSION;
+use uuid::Uuid;
+
+use crate::{
+    entities::{prelude::*, calendar_events::{self, EventStatus}, can_do_list, record_revisions::RevisionAction},
+    errors::{AppError, Result},
+    features::Feature,
+    handlers::{
+        calendar_events::{calendar_is_active, event_audience},
+        project_members::broadcast_task_changes,
+        recurrence::occurrences_in,
+        revisions::record_revision,
+        scheduling_constraints::scheduling_constraints,
+        user_settings::time_preferences,
+    },
+    middleware::{auth::AuthUser, connection::OriginConnection, validation::ValidatedJson},
+    models::{
+        calendar_event::CalendarEventResponse,
+        can_do_list::CanDoItemResponse,
+        schedule::{
+            ApplyScheduleRequest, ApplyScheduleResponse, PlanResponse, PlanScheduleRequest, PlannedBlock,
+            ScheduledEvent, UnscheduledTask, MAX_PLAN_TASKS,
+        },
+        search::join_search_tokens,
+        ApiResponse,
+    },
+    quota::QuotaTable,
+    scheduler::{self, Interval, PlanOptions, SchedulableTask},
+    state::AppState,
+    websocket::WebSocketMessage,
+};
+
+/// Time taken by the user's own confirmed and tentative events within `[start, end)`. Events
+/// without an end time don't block anything.
+async fn busy_intervals<C: ConnectionTrait>(
+    db: &C,
+    user_id: Uuid,
+    start: DateTime<Utc>,
+    end: DateTime<Utc>,
+) -> Result<Vec<Interval>> {
+    let occurrences = occurrences_in(
+        db,
+        CalendarEvents::find()
+            .filter(calendar_events::Column::UserId.eq(user_id))
+            .filter(calendar_events::Column::Status.ne(EventStatus::Cancelled)),
+        start,
+        end,
+    )
+    .await?;
+    Ok(occurrences
+        .into_iter()
+        .filter_map(|occurrence| Some(Interval { start: occurrence.start_time, end: occurrence.end_time? }))
+        .filter(|interval| interval.start < interval.end)
+        .collect())
+}
+
+/// Propose time blocks for open tasks in the free time of the user's calendar, within their
+/// working hours and outside their protected windows. Nothing is saved; see `apply_schedule`.
+pub async fn plan_schedule(
+    State(app_state): State<AppState>,
+    auth_user: AuthUser,
+    ValidatedJson(request): ValidatedJson<PlanScheduleRequest>,
+) -> Result<Json<ApiResponse<PlanResponse>>> {
+    let user_id = auth_user.0.id;
+    app_state.features.require(app_state.db.reader(), user_id, Feature::AutoScheduling).await?;
+    let (start, end) = request.window(Utc::now())?;
+    let default_duration = request.default_duration()?;
+    let min_block = request.min_block()?;
+    let requested_hours = request.working_hours.as_ref().map(|hours| hours.parse()).transpose()?;
+    let db = app_state.db.reader();
+
+    let mut find = CanDoList::find()
+        .filter(can_do_list::Column::UserId.eq(user_id))
+        .filter(can_do_list::Column::DeletedAt.is_null())
+        .filter(can_do_list::Column::CompletedAt.is_null());
+    find = match &request.task_ids {
+        Some(task_ids) => find.filter(can_do_list::Column::Id.is_in(task_ids.clone())),
+        None => find.filter(can_do_list::Column::ScheduledAt.is_null()),
+    };
+    let tasks: Vec<SchedulableTask> = find
+        .order_by_asc(can_do_list::Column::CreatedAt)
+        .limit(MAX_PLAN_TASKS)
+        .all(db)
+        .await
+        .map_err(|e| AppError::Database(e.into()))?
+        .into_iter()
+        .map(|task| SchedulableTask {
+            id: task.id,
+            duration: task.estimated_minutes.map_or(default_duration, |minutes| Duration::minutes(minutes as i64)),
+            due_at: task.due_at.map(|dt| dt.naive_utc().and_utc()),
+            priority: task.priority,
+            created_at: task.created_at.naive_utc().and_utc(),
+        })
+        .collect();
+
+    let time = time_preferences(db, user_id).await?;
+    let constraints = scheduling_constraints(db, user_id).await?;
+    let working_hours = requested_hours.unwrap_or(constraints.working_hours);
+    let available = scheduler::weekly_intervals(&working_hours, time.timezone, start, end);
+    let mut busy = busy_intervals(db, user_id, start, end).await?;
+    busy.extend(scheduler::weekly_intervals(&constraints.protected_windows, time.timezone, start, end));
+    let options = PlanOptions { min_block, max_blocks_per_day: constraints.max_blocks_per_day, timezone: time.timezone };
+    let plan = scheduler::plan(&tasks, scheduler::free_intervals(available, &busy), &options);
+
+    let due_dates: HashMap<Uuid, DateTime<Utc>> =
+        tasks.iter().filter_map(|task| Some((task.id, task.due_at?))).collect();
+    Ok(Json(ApiResponse::new(PlanResponse {
+        start,
+        end,
+        timezone: time.timezone.name().to_string(),
+        blocks: plan
+            .blocks
+            .into_iter()
+            .map(|block| PlannedBlock {
+                task_id: block.task_id,
+                start_time: block.start,
+                end_time: block.end,
+                after_due: due_dates.get(&block.task_id).is_some_and(|due_at| block.end > *due_at),
+            })
+            .collect(),
+        unscheduled: plan
+            .unscheduled
+            .into_iter()
+            .map(|(task_id, duration)| UnscheduledTask { task_id, minutes: duration.num_minutes() })
+            .collect(),
+    })))
+}
+
+/// Create a calendar event for each accepted block and set each task's `scheduled_at` to its
+/// first block. Blocks that now overlap other events or each other are rejected with a conflict.
+pub async fn apply_schedule(
+    State(app_state): State<AppState>,
+    auth_user: AuthUser,
+    OriginConnection(connection_id): OriginConnection,
+    ValidatedJson(request): ValidatedJson<ApplyScheduleRequest>,
+) -> Result<Json<ApiResponse<ApplyScheduleResponse>>> {
+    let user_id = auth_user.0.id;
+    app_state.features.require(app_state.db.reader(), user_id, Feature::AutoScheduling).await?;
+
+    let calendar_id = request.calendar_id;
+    let quotas = app_state.quotas.clone();
+    let (events, tasks) = app_state.db.with_txn(|txn| Box::pin(async move {
+        if let Some(calendar_id) = calendar_id
+            && !calendar_is_active(txn, user_id, calendar_id).await?
+        {
+            return Err(AppError::NotFound("Calendar not found".to_string()));
+        }
+
+        let mut task_ids: Vec<Uuid> = request.blocks.iter().map(|block| block.task_id).collect();
+        task_ids.sort();
+        task_ids.dedup();
+        let tasks = CanDoList::find()
+            .filter(can_do_list::Column::Id.is_in(task_ids.clone()))
+            .filter(can_do_list::Column::UserId.eq(user_id))
+            .filter(can_do_list::Column::DeletedAt.is_null())
+            .filter(can_do_list::Column::CompletedAt.is_null())
+            .all(txn)
+            .await
+            .map_err(|e| AppError::Database(e.into()))?;
+        if tasks.len() != task_ids.len() {
+            return Err(AppError::NotFound("Can-do item not found".to_string()));
+        }
+
+        let mut blocks: Vec<Interval> = request
+            .blocks
+            .iter()
+            .map(|block| Interval { start: block.start_time, end: block.end_time })
+            .collect();
+        blocks.sort_by_key(|block| block.start);
+        if blocks.windows(2).any(|pair| pair[1].start < pair[0].end) {
+            return Err(AppError::Conflict("Blocks must not overlap each other".to_string()));
+        }
+        let (first, last) = (blocks[0].start, blocks.iter().map(|block| block.end).max().unwrap_or(blocks[0].end));
+        let busy = busy_intervals(txn, user_id, first, last).await?;
+        if blocks.iter().any(|block| busy.iter().any(|taken| taken.start < block.end && taken.end > block.start)) {
+            return Err(AppError::Conflict("A block overlaps an event in the calendar".to_string()));
+        }
+        quotas.enforce(txn, user_id, QuotaTable::CalendarEvents, request.blocks.len() as u64).await?;
+
+        let mut events = Vec::new();
+        for block in request.blocks {
+            let mut event_active = calendar_events::ActiveModel::new();
+            event_active.user_id = Set(user_id);
+            event_active.calendar_id = Set(calendar_id);
+            event_active.encrypted_data = Set(block.encrypted_data);
+            event_active.iv = Set(block.iv);
+            event_active.salt = Set(block.salt);
+            event_active.schema_version = Set(block.schema_version.unwrap_or(DEFAULT_SCHEMA_VERSION));
+            event_active.search_tokens = Set(block.search_tokens.map(join_search_tokens));
+            event_active.start_time = Set(Some(block.start_time.into()));
+            event_active.end_time = Set(Some(block.end_time.into()));
+            event_active.linked_task_id = Set(Some(block.task_id));
+            let event = event_active.insert(txn).await
+                .map_err(|e| AppError::Database(e.into()))?;
+            events.push((block.task_id, event));
+        }
+
+        let mut updated_tasks = Vec::new();
+        for task in tasks {
+            let Some(first_start) = events
+                .iter()
+                .filter(|(task_id, _)| *task_id == task.id)
+                .filter_map(|(_, event)| event.start_time)
+                .min()
+            else {
+                continue;
+            };
+            record_revision(txn, user_id, RevisionAction::Update, &task).await?;
+            let mut task_active: can_do_list::ActiveModel = task.into();
+            task_active.scheduled_at = Set(Some(first_start));
+            updated_tasks.push(task_active.update(txn).await
+                .map_err(|e| AppError::Database(e.into()))?);
+        }
+        Ok((events, updated_tasks))
+    })).await?;
+
+    // Broadcast websocket messages for the new events and the rescheduled tasks
+    tracing::info!("Schedule applied, broadcasting websocket messages for user {} (excluding connection {:?})", user_id, connection_id);
+    let event_messages = events
+        .iter()
+        .map(|(_, event)| WebSocketMessage {
+            event_type: "INSERT".to_string(),
+            table: "calendar_events".to_string(),
+            user_id,
+            record_id: Some(event.id),
+            data: Some(serde_json::to_value(CalendarEventResponse::from(event.clone())).unwrap_or_default()),
+        })
+        .collect();
+    let audience = event_audience(&app_state.db.connection, user_id, [calendar_id]).await?;
+    app_state.ws_state.broadcast_many_to_users(&audience, event_messages, connection_id).await;
+    let task_changes = tasks
+        .iter()
+        .map(|task| {
+            (task.project_id.into_iter().collect(), WebSocketMessage {
+                event_type: "UPDATE".to_string(),
+                table: "can_do_list".to_string(),
+                user_id,
+                record_id: Some(task.id),
+                data: Some(serde_json::to_value(CanDoItemResponse::from(task.clone())).unwrap_or_default()),
+            })
+        })
+        .collect();
+    broadcast_task_changes(&app_state, user_id, task_changes, connection_id).await?;
+
+    let response = ApplyScheduleResponse {
+        events: events
+            .into_iter()
+            .map(|(task_id, event)| ScheduledEvent { task_id, event: event.into() })
+            .collect(),
+        tasks: tasks.into_iter().map(Into::into).collect(),
+    };
+    Ok(Json(ApiResponse::with_message(response, "Schedule applied successfully")))
+}
@@ -0,0 +1,107 @@
+use axum::{extract::State, response::Json};
+use sea_orm::{sea_query::OnConflict, ActiveModelTrait, ConnectionTrait, EntityTrait};
+use uuid::Uuid;
+
+use crate::{
+    entities::{prelude::*, scheduling_constraints},
+    errors::{AppError, Result},
+    middleware::{auth::AuthUser, preconditions::Preconditions, validation::ValidatedJson},
+    models::{
+        scheduling_constraints::{
+            default_constraints, SchedulingConstraintsResponse, UpdateSchedulingConstraintsRequest,
+        },
+        ApiResponse,
+    },
+    scheduler::Constraints,
+    state::AppState,
+};
+
+async fn find_constraints<C: ConnectionTrait>(db: &C, user_id: Uuid) -> Result<scheduling_constraints::Model> {
+    Ok(SchedulingConstraints::find_by_id(user_id)
+        .one(db)
+        .await
+        .map_err(|e| AppError::Database(e.into()))?
+        .unwrap_or_else(|| default_constraints(user_id)))
+}
+
+/// The user's working hours, protected windows and block limit, the defaults if they never set any
+pub(crate) async fn scheduling_constraints<C: ConnectionTrait>(db: &C, user_id: Uuid) -> Result<Constraints> {
+    Ok(Constraints::from(&find_constraints(db, user_id).await?))
+}
+
+/// Get the scheduling constraints, the defaults if the user never changed them
+pub async fn get_scheduling_constraints(
+    State(app_state): State<AppState>,
+    auth_user: AuthUser,
+) -> Result<Json<ApiResponse<SchedulingConstraintsResponse>>> {
+    let constraints = find_constraints(app_state.db.reader(), auth_user.0.id).await?;
+    Ok(Json(ApiResponse::new(constraints.into())))
+}
+
+/// Update the scheduling constraints; fields left out keep their values
+pub async fn update_scheduling_constraints(
+    State(app_state): State<AppState>,
+    auth_user: AuthUser,
+    preconditions: Preconditions,
+    ValidatedJson(request): ValidatedJson<UpdateSchedulingConstraintsRequest>,
+) -> Result<Json<ApiResponse<SchedulingConstraintsResponse>>> {
+    let user_id = auth_user.0.id;
+    let constraints = app_state.db.with_txn(|txn| Box::pin(async move {
+        let stored = SchedulingConstraints::find_by_id(user_id)
+            .one(txn)
+            .await
+            .map_err(|e| AppError::Database(e.into()))?;
+        // Defaults are new on every read, so only stored constraints can be out of date
+        if let Some(stored) = &stored {
+            preconditions.check(&stored.updated_at)?;
+        }
+        let mut constraints = stored.unwrap_or_else(|| default_constraints(user_id));
+        request.apply_to(&mut constraints)?;
+        constraints.updated_at = chrono::Utc::now().into();
+
+        let constraints_active: scheduling_constraints::ActiveModel = constraints.into();
+        SchedulingConstraints::insert(constraints_active.reset_all())
+            .on_conflict(
+                OnConflict::column(scheduling_constraints::Column::UserId)
+                    .update_columns([
+                        scheduling_constraints::Column::WorkingHours,
+                        scheduling_constraints::Column::ProtectedWindows,
+                        scheduling_constraints::Column::MaxBlocksPerDay,
+                        scheduling_constraints::Column::UpdatedAt,
+                    ])
+                    .to_owned(),
+            )
+            .exec_with_returning(txn)
+            .await
+            .map_err(|e| AppError::Database(e.into()))
+    })).await?;
+
+    Ok(Json(ApiResponse::with_message(constraints.into(), "Scheduling constraints updated successfully")))
+}
+
+/// Go back to the default constraints
+pub async fn delete_scheduling_constraints(
+    State(app_state): State<AppState>,
+    auth_user: AuthUser,
+    preconditions: Preconditions,
+) -> Result<Json<ApiResponse<SchedulingConstraintsResponse>>> {
+    let user_id = auth_user.0.id;
+    app_state.db.with_txn(|txn| Box::pin(async move {
+        if let Some(stored) = SchedulingConstraints::find_by_id(user_id)
+            .one(txn)
+            .await
+            .map_err(|e| AppError::Database(e.into()))?
+        {
+            preconditions.check(&stored.updated_at)?;
+        }
+        SchedulingConstraints::delete_by_id(user_id)
+            .exec(txn)
+            .await
+            .map_err(|e| AppError::Database(e.into()))?;
+        Ok(())
+    })).await?;
+    Ok(Json(ApiResponse::with_message(
+        default_constraints(auth_user.0.id).into(),
+        "Scheduling constraints reset successfully",
+    )))
+}
@@ -0,0 +1,154 @@
+use axum::{
+    extract::{Query, State},
+    response::Json,
+};
+use base64::{engine::general_purpose::STANDARD, Engine as _};
+use sea_orm::*;
+use uuid::Uuid;
+
+use crate::{
+    entities::{prelude::*, calendar_events, can_do_list, projects},
+    errors::Result,
+    middleware::{auth::AuthUser, validation::ValidatedJson},
+    models::{
+        search::{has_all_tokens, PrefilterRequest, PrefilterResponse, SearchQuery, SearchResponse, SearchTable},
+        ApiResponse,
+    },
+    state::AppState,
+};
+
+/// Whether every bit is set; positions past the end of the filter are never set
+fn bloom_contains(filter: &[u8], bits: &[u32]) -> bool {
+    bits.iter().all(|&bit| {
+        filter
+            .get((bit / 8) as usize)
+            .is_some_and(|byte| byte & (1 << (bit % 8)) != 0)
+    })
+}
+
+/// Records without a usable filter can't be ruled out, so they stay candidates
+fn is_candidate(search_bloom: Option<&str>, bits: &[u32]) -> bool {
+    match search_bloom.map(|bloom| STANDARD.decode(bloom)) {
+        Some(Ok(filter)) => bloom_contains(&filter, bits),
+        _ => true,
+    }
+}
+
+fn candidates(rows: Vec<(Uuid, Option<String>)>, bits: &[u32]) -> Vec<Uuid> {
+    rows.into_iter()
+        .filter(|(_, search_bloom)| is_candidate(search_bloom.as_deref(), bits))
+        .map(|(id, _)| id)
+        .collect()
+}
+
+/// Return ids of active records whose bloom filter may contain all query tokens,
+/// so clients only download and decrypt likely matches
+pub async fn prefilter(
+    State(app_state): State<AppState>,
+    auth_user: AuthUser,
+    ValidatedJson(request): ValidatedJson<PrefilterRequest>,
+) -> Result<Json<ApiResponse<PrefilterResponse>>> {
+    let user_id = auth_user.0.id;
+    let db = app_state.db.reader();
+
+    let mut response = PrefilterResponse::default();
+
+    if request.includes(SearchTable::CanDoList) {
+        let rows: Vec<(Uuid, Option<String>)> = CanDoList::find()
+            .select_only()
+            .column(can_do_list::Column::Id)
+            .column(can_do_list::Column::SearchBloom)
+            .filter(can_do_list::Column::UserId.eq(user_id))
+            .filter(can_do_list::Column::DeletedAt.is_null())
+            .into_tuple()
+            .all(db)
+            .await
+            .map_err(|e| crate::errors::AppError::Database(e.into()))?;
+        response.can_do_list = candidates(rows, &request.bits);
+    }
+
+    if request.includes(SearchTable::CalendarEvents) {
+        let rows: Vec<(Uuid, Option<String>)> = CalendarEvents::find()
+            .select_only()
+            .column(calendar_events::Column::Id)
+            .column(calendar_events::Column::SearchBloom)
+            .filter(calendar_events::Column::UserId.eq(user_id))
+            .filter(calendar_events::Column::DeletedAt.is_null())
+            .into_tuple()
+            .all(db)
+            .await
+            .map_err(|e| crate::errors::AppError::Database(e.into()))?;
+        response.calendar_events = candidates(rows, &request.bits);
+    }
+
+    Ok(Json(ApiResponse::new(response)))
+}
+
+fn matches(rows: Vec<(Uuid, String)>, tokens: &[&str]) -> Vec<Uuid> {
+    rows.into_iter()
+        .filter(|(_, search_tokens)| has_all_tokens(search_tokens, tokens))
+        .map(|(id, _)| id)
+        .collect()
+}
+
+/// Return ids of active records whose blind index holds every query token. Unlike the
+/// prefilter there are no false positives, but records without tokens are never found.
+pub async fn search(
+    State(app_state): State<AppState>,
+    auth_user: AuthUser,
+    Query(query): Query<SearchQuery>,
+) -> Result<Json<ApiResponse<SearchResponse>>> {
+    let user_id = auth_user.0.id;
+    let db = app_state.db.reader();
+    let tokens = query.tokens()?;
+    let tables = query.tables()?;
+
+    let mut response = SearchResponse::default();
+
+    if tables.contains(&SearchTable::CanDoList) {
+        let rows: Vec<(Uuid, String)> = CanDoList::find()
+            .select_only()
+            .column(can_do_list::Column::Id)
+            .column(can_do_list::Column::SearchTokens)
+            .filter(can_do_list::Column::UserId.eq(user_id))
+            .filter(can_do_list::Column::DeletedAt.is_null())
+            .filter(can_do_list::Column::SearchTokens.is_not_null())
+            .into_tuple()
+            .all(db)
+            .await
+            .map_err(|e| crate::errors::AppError::Database(e.into()))?;
+        response.can_do_list = matches(rows, &tokens);
+    }
+
+    if tables.contains(&SearchTable::CalendarEvents) {
+        let rows: Vec<(Uuid, String)> = CalendarEvents::find()
+            .select_only()
+            .column(calendar_events::Column::Id)
+            .column(calendar_events::Column::SearchTokens)
+            .filter(calendar_events::Column::UserId.eq(user_id))
+            .filter(calendar_events::Column::DeletedAt.is_null())
+            .filter(calendar_events::Column::SearchTokens.is_not_null())
+            .into_tuple()
+            .all(db)
+            .await
+            .map_err(|e| crate::errors::AppError::Database(e.into()))?;
+        response.calendar_events = matches(rows, &tokens);
+    }
+
+    if tables.contains(&SearchTable::Projects) {
+        let rows: Vec<(Uuid, String)> = Projects::find()
+            .select_only()
+            .column(projects::Column::Id)
+            .column(projects::Column::SearchTokens)
+            .filter(projects::Column::UserId.eq(user_id))
+            .filter(projects::Column::DeletedAt.is_null())
+            .filter(projects::Column::SearchTokens.is_not_null())
+            .into_tuple()
+            .all(db)
+            .await
+            .map_err(|e| crate::errors::AppError::Database(e.into()))?;
+        response.projects = matches(rows, &tokens);
+    }
+
+    Ok(Json(ApiResponse::new(response)))
+}
@@ -0,0 +1,175 @@
+use axum::{
+    extract::{Path, Query, State},
+    response::Json,
+};
+use sea_orm::*;
+use uuid::Uuid;
+
+use crate::{
+    entities::{prelude::*, settings},
+    errors::{AppError, ErrorCode, Result},
+    handlers::sync::record_deletion,
+    middleware::{auth::AuthUser, connection::OriginConnection, preconditions::Preconditions, validation::ValidatedJson},
+    models::{
+        setting::{validate_namespace, SaveSettingRequest, SettingResponse, MAX_NAMESPACES},
+        validate_schema_version, ApiResponse, PageQuery,
+    },
+    state::AppState,
+    websocket::WebSocketMessage,
+};
+use streamline_models::DEFAULT_SCHEMA_VERSION;
+
+pub(crate) fn setting_message(event_type: &str, user_id: Uuid, setting: &settings::Model) -> WebSocketMessage {
+    WebSocketMessage {
+        event_type: event_type.to_string(),
+        table: "settings".to_string(),
+        user_id,
+        record_id: Some(setting.id),
+        data: Some(serde_json::to_value(SettingResponse::from(setting.clone())).unwrap_or_default()),
+    }
+}
+
+pub(crate) async fn find_setting<C: ConnectionTrait>(db: &C, user_id: Uuid, namespace: &str) -> Result<Option<settings::Model>> {
+    Settings::find()
+        .filter(settings::Column::UserId.eq(user_id))
+        .filter(settings::Column::Namespace.eq(namespace))
+        .one(db)
+        .await
+        .map_err(|e| AppError::Database(e.into()))
+}
+
+/// Replace the payload of a namespace, creating it if it's new. Returns the row and whether it
+/// was created.
+pub(crate) async fn save_setting<C: ConnectionTrait>(
+    db: &C,
+    user_id: Uuid,
+    namespace: &str,
+    request: SaveSettingRequest,
+) -> Result<(settings::Model, bool)> {
+    let schema_version = request.schema_version.unwrap_or(DEFAULT_SCHEMA_VERSION);
+    match find_setting(db, user_id, namespace).await? {
+        Some(existing) => {
+            let mut setting_active: settings::ActiveModel = existing.into();
+            setting_active.encrypted_data = Set(request.encrypted_data);
+            setting_active.iv = Set(request.iv);
+            setting_active.salt = Set(request.salt);
+            setting_active.schema_version = Set(schema_version);
+            let setting = setting_active.update(db).await
+                .map_err(|e| AppError::Database(e.into()))?;
+            Ok((setting, false))
+        }
+        None => {
+            let namespaces = Settings::find()
+                .filter(settings::Column::UserId.eq(user_id))
+                .count(db)
+                .await
+                .map_err(|e| AppError::Database(e.into()))?;
+            if namespaces >= MAX_NAMESPACES {
+                return Err(AppError::Coded(
+                    ErrorCode::QuotaExceeded,
+                    format!("At most {} settings namespaces are allowed", MAX_NAMESPACES),
+                ));
+            }
+            let mut setting_active = settings::ActiveModel::new();
+            setting_active.user_id = Set(user_id);
+            setting_active.namespace = Set(namespace.to_string());
+            setting_active.encrypted_data = Set(request.encrypted_data);
+            setting_active.iv = Set(request.iv);
+            setting_active.salt = Set(request.salt);
+            setting_active.schema_version = Set(schema_version);
+            let setting = setting_active.insert(db).await
+                .map_err(|e| AppError::Database(e.into()))?;
+            Ok((setting, true))
+        }
+    }
+}
+
+/// Every settings namespace of the user, by name
+pub async fn list_settings(
+    State(app_state): State<AppState>,
+    auth_user: AuthUser,
+    Query(paging): Query<PageQuery>,
+) -> Result<Json<ApiResponse<Vec<SettingResponse>>>> {
+    let find = Settings::find()
+        .filter(settings::Column::UserId.eq(auth_user.0.id))
+        .order_by_asc(settings::Column::Namespace);
+    let (settings, meta) = paging.fetch(app_state.db.reader(), find).await?;
+
+    Ok(Json(ApiResponse::list(settings.into_iter().map(Into::into).collect(), meta)))
+}
+
+pub async fn get_setting(
+    State(app_state): State<AppState>,
+    auth_user: AuthUser,
+    Path(namespace): Path<String>,
+) -> Result<Json<ApiResponse<SettingResponse>>> {
+    validate_namespace(&namespace)?;
+    let setting = find_setting(app_state.db.reader(), auth_user.0.id, &namespace)
+        .await?
+        .ok_or_else(|| AppError::NotFound("Settings namespace not found".to_string()))?;
+
+    Ok(Json(ApiResponse::new(setting.into())))
+}
+
+/// Save one namespace; the others are left alone
+pub async fn update_setting(
+    State(app_state): State<AppState>,
+    auth_user: AuthUser,
+    OriginConnection(connection_id): OriginConnection,
+    Path(namespace): Path<String>,
+    preconditions: Preconditions,
+    ValidatedJson(request): ValidatedJson<SaveSettingRequest>,
+) -> Result<Json<ApiResponse<SettingResponse>>> {
+    validate_namespace(&namespace)?;
+    validate_schema_version("settings", request.schema_version)?;
+    let user_id = auth_user.0.id;
+    let (setting, created) = app_state.db.with_txn(|txn| Box::pin(async move {
+        if let Some(existing) = find_setting(txn, user_id, &namespace).await? {
+            preconditions.check(&existing.updated_at)?;
+        }
+        save_setting(txn, user_id, &namespace, request).await
+    })).await?;
+
+    // Broadcast websocket message for the settings change
+    let event_type = if created { "INSERT" } else { "UPDATE" };
+    tracing::info!("Settings saved, broadcasting websocket message for user {} (excluding connection {:?})", user_id, connection_id);
+    app_state.ws_state.broadcast_to_user(&user_id, setting_message(event_type, user_id, &setting), connection_id).await;
+
+    Ok(Json(ApiResponse::with_message(setting.into(), "Settings saved successfully")))
+}
+
+pub async fn delete_setting(
+    State(app_state): State<AppState>,
+    auth_user: AuthUser,
+    OriginConnection(connection_id): OriginConnection,
+    Path(namespace): Path<String>,
+    preconditions: Preconditions,
+) -> Result<Json<ApiResponse<()>>> {
+    validate_namespace(&namespace)?;
+    let user_id = auth_user.0.id;
+    let id = app_state.db.with_txn(|txn| Box::pin(async move {
+        let setting = find_setting(txn, user_id, &namespace)
+            .await?
+            .ok_or_else(|| AppError::NotFound("Settings namespace not found".to_string()))?;
+        preconditions.check(&setting.updated_at)?;
+        Settings::delete_by_id(setting.id)
+            .exec(txn)
+            .await
+            .map_err(|e| AppError::Database(e.into()))?;
+        record_deletion(txn, user_id, "settings", setting.id).await?;
+        Ok(setting.id)
+    })).await?;
+
+    // Broadcast websocket message for the settings deletion
+    tracing::info!("Settings deleted, broadcasting websocket message for user {} (excluding connection {:?})", user_id, connection_id);
+    let ws_message = WebSocketMessage {
+        event_type: "DELETE".to_string(),
+        table: "settings".to_string(),
+        user_id,
+        record_id: Some(id),
+        data: None,
+    };
+    app_state.ws_state.broadcast_to_user(&user_id, ws_message, connection_id).await;
+
+    Ok(Json(ApiResponse::with_message((), "Settings deleted successfully")))
+}
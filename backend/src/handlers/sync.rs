@@ -0,0 +1,1062 @@
+use axum::{
+    extract::{Query, State},
+    response::Json,
+};
+use chrono::{DateTime, Duration, Utc};
+use sea_orm::*;
+use serde::de::DeserializeOwned;
+use uuid::Uuid;
+
+use crate::{
+    db::consistent_as_of,
+    entities::{
+        prelude::*, calendar_events, calendars, can_do_list, deleted_records, habits, kv_entries, notes, projects,
+        project_members::ProjectRole, record_revisions::RevisionAction, settings, user_settings,
+    },
+    errors::Result,
+    handlers::{
+        calendar_events::{
+            calendar_is_active, ensure_editable, event_scope, resolve_all_day, resolve_all_day_update, validate_time_range,
+        },
+        calendars::{calendar_scope, trash_calendar},
+        conflicts::record_conflict,
+        habits::{change_habit, insert_habit},
+        notes::validate_note_links,
+        can_do_list::{create_next_occurrence, route_to_inbox, task_scope, trash_task, validate_parent_task, validate_task_recurrence},
+        project_members::shared_project_ids,
+        projects::{change_default_project, collect_subtree_ids, ensure_default_kept, trash_projects, validate_parent},
+        reminders::reschedule_event_reminders,
+        revisions::{drop_revisions, record_revision},
+        settings::find_setting,
+        user_settings::{time_preferences, UserSettingsResponse},
+    },
+    middleware::{auth::AuthUser, connection::OriginConnection, validation::ValidatedJson},
+    models::{
+        calendar::{CreateCalendarRequest, UpdateCalendarRequest, CalendarResponse},
+        calendar_event::{CreateCalendarEventRequest, UpdateCalendarEventRequest, CalendarEventResponse},
+        can_do_list::{CreateCanDoItemRequest, UpdateCanDoItemRequest, CanDoItemResponse},
+        habit::{CreateHabitRequest, HabitResponse, UpdateHabitRequest},
+        note::{CreateNoteRequest, UpdateNoteRequest, NoteResponse},
+        project::{CreateProjectRequest, UpdateProjectRequest, ProjectResponse},
+        setting::GENERAL_NAMESPACE,
+        sync::{
+            SyncAction, SyncOperation, SyncOperationResult, SyncOperationStatus, SyncPushRequest,
+            SyncPushResponse, SyncQuery, SyncResponse,
+        },
+        validate_client_id, validate_schema_version, ApiResponse, ApplyTo, IntoRecord, Validate,
+    },
+    quota::{QuotaTable, Quotas},
+    recurrence,
+    state::AppState,
+    websocket::WebSocketMessage,
+};
+
+/// Upper bound on operations accepted by a single push
+const MAX_PUSH_OPERATIONS: usize = 500;
+/// How far before `since` changes are read again. `updated_at` is stamped when a record is written
+/// but only becomes visible when its transaction commits, so a change committed after a sync
+/// can carry an earlier time than that sync's watermark. This covers transactions running up to
+/// this long, e.g. large imports; clients already have the records sent again and skip them.
+const SYNC_OVERLAP: Duration = Duration::minutes(5);
+
+
+/// Record a tombstone so delta-syncing clients learn about a hard delete; the record's
+/// history goes with it
+pub async fn record_deletion<C: ConnectionTrait>(
+    db: &C,
+    user_id: Uuid,
+    table: &str,
+    record_id: Uuid,
+) -> Result<()> {
+    let mut tombstone = deleted_records::ActiveModel::new();
+    tombstone.user_id = Set(user_id);
+    tombstone.table_name = Set(table.to_string());
+    tombstone.record_id = Set(record_id);
+
+    tombstone.insert(db).await
+        .map_err(|e| crate::errors::AppError::Database(e.into()))?;
+
+    drop_revisions(db, table, record_id).await
+}
+
+/// Return every record the user can see that changed since the `since` watermark, plus tombstones
+/// for deletes: their own, and projects, tasks, calendars and events shared with them. Records
+/// changed shortly before `since` are returned again, see [`SYNC_OVERLAP`]. Without `since` a
+/// full snapshot is returned and tombstones are omitted.
+pub async fn sync_changes(
+    State(app_state): State<AppState>,
+    auth_user: AuthUser,
+    Query(query): Query<SyncQuery>,
+) -> Result<Json<ApiResponse<SyncResponse>>> {
+    let user_id = auth_user.0.id;
+    let db = app_state.db.reader();
+
+    // Capture the watermark before querying. A lagging replica hasn't seen the
+    // latest writes yet, so its watermark trails behind.
+    let server_time = if app_state.db.has_replicas() {
+        consistent_as_of(db).await?.min(chrono::Utc::now())
+    } else {
+        chrono::Utc::now()
+    };
+
+    let shared_ids = shared_project_ids(db, user_id, ProjectRole::Viewer).await?;
+    let mut projects_find = Projects::find().filter(
+        Condition::any()
+            .add(projects::Column::UserId.eq(user_id))
+            .add(projects::Column::Id.is_in(shared_ids)),
+    );
+    let mut items_find = CanDoList::find().filter(task_scope(db, user_id, ProjectRole::Viewer).await?);
+    let mut calendars_find = Calendars::find().filter(calendar_scope(db, user_id, ProjectRole::Viewer).await?);
+    let mut events_find = CalendarEvents::find().filter(event_scope(db, user_id, ProjectRole::Viewer).await?);
+    let mut notes_find = Notes::find().filter(notes::Column::UserId.eq(user_id));
+    let mut habits_find = Habits::find().filter(habits::Column::UserId.eq(user_id));
+    let mut settings_find = UserSettings::find().filter(user_settings::Column::UserId.eq(user_id));
+    let mut namespaces_find = Settings::find().filter(settings::Column::UserId.eq(user_id));
+    let mut kv_find = KvEntries::find().filter(kv_entries::Column::UserId.eq(user_id));
+
+    let since = query.since.map(|since| since - SYNC_OVERLAP);
+    if let Some(since) = since {
+        projects_find = projects_find.filter(projects::Column::UpdatedAt.gt(since));
+        items_find = items_find.filter(can_do_list::Column::UpdatedAt.gt(since));
+        calendars_find = calendars_find.filter(calendars::Column::UpdatedAt.gt(since));
+        events_find = events_find.filter(calendar_events::Column::UpdatedAt.gt(since));
+        notes_find = notes_find.filter(notes::Column::UpdatedAt.gt(since));
+        habits_find = habits_find.filter(habits::Column::UpdatedAt.gt(since));
+        settings_find = settings_find.filter(user_settings::Column::UpdatedAt.gt(since));
+        namespaces_find = namespaces_find.filter(settings::Column::UpdatedAt.gt(since));
+        kv_find = kv_find.filter(kv_entries::Column::UpdatedAt.gt(since));
+    }
+
+    let projects = projects_find
+        .order_by_asc(projects::Column::UpdatedAt)
+        .all(db)
+        .await
+        .map_err(|e| crate::errors::AppError::Database(e.into()))?;
+
+    let items = items_find
+        .order_by_asc(can_do_list::Column::UpdatedAt)
+        .all(db)
+        .await
+        .map_err(|e| crate::errors::AppError::Database(e.into()))?;
+
+    let calendars = calendars_find
+        .order_by_asc(calendars::Column::UpdatedAt)
+        .all(db)
+        .await
+        .map_err(|e| crate::errors::AppError::Database(e.into()))?;
+
+    let events = events_find
+        .order_by_asc(calendar_events::Column::UpdatedAt)
+        .all(db)
+        .await
+        .map_err(|e| crate::errors::AppError::Database(e.into()))?;
+
+    let notes = notes_find
+        .order_by_asc(notes::Column::UpdatedAt)
+        .all(db)
+        .await
+        .map_err(|e| crate::errors::AppError::Database(e.into()))?;
+
+    let habits = habits_find
+        .order_by_asc(habits::Column::UpdatedAt)
+        .all(db)
+        .await
+        .map_err(|e| crate::errors::AppError::Database(e.into()))?;
+
+    let settings = settings_find
+        .one(db)
+        .await
+        .map_err(|e| crate::errors::AppError::Database(e.into()))?;
+
+    let namespaces = namespaces_find
+        .order_by_asc(settings::Column::UpdatedAt)
+        .all(db)
+        .await
+        .map_err(|e| crate::errors::AppError::Database(e.into()))?;
+
+    let kv = kv_find
+        .order_by_asc(kv_entries::Column::UpdatedAt)
+        .all(db)
+        .await
+        .map_err(|e| crate::errors::AppError::Database(e.into()))?;
+    // The legacy settings carry the general namespace, whether or not it changed
+    let user_settings = match settings {
+        Some(settings) => Some(UserSettingsResponse::new(
+            Some(settings),
+            find_setting(db, user_id, GENERAL_NAMESPACE).await?,
+        )),
+        None => None,
+    };
+    // Streaks are reported as they stand in the user's today
+    let today = time_preferences(db, user_id).await?.today();
+
+    let deleted = match since {
+        Some(since) => DeletedRecords::find()
+            .filter(deleted_records::Column::UserId.eq(user_id))
+            .filter(deleted_records::Column::DeletedAt.gt(since))
+            .order_by_asc(deleted_records::Column::DeletedAt)
+            .all(db)
+            .await
+            .map_err(|e| crate::errors::AppError::Database(e.into()))?,
+        None => Vec::new(),
+    };
+
+    let pending_encryption = CanDoList::find()
+        .select_only()
+        .column(can_do_list::Column::Id)
+        .filter(can_do_list::Column::UserId.eq(user_id))
+        .filter(can_do_list::Column::PendingPlaintext.is_not_null())
+        .into_tuple::<Uuid>()
+        .all(db)
+        .await
+        .map_err(|e| crate::errors::AppError::Database(e.into()))?;
+
+    let response = SyncResponse {
+        projects: projects.into_iter().map(|p| p.into()).collect(),
+        can_do_list: items.into_iter().map(|item| item.into()).collect(),
+        calendars: calendars.into_iter().map(|calendar| calendar.into()).collect(),
+        calendar_events: events.into_iter().map(|event| event.into()).collect(),
+        notes: notes.into_iter().map(|note| note.into()).collect(),
+        habits: habits.into_iter().map(|habit| HabitResponse::from(habit).on(today)).collect(),
+        user_settings,
+        settings: namespaces.into_iter().map(|setting| setting.into()).collect(),
+        kv: kv.into_iter().map(|entry| entry.into()).collect(),
+        deleted: deleted.into_iter().map(|record| record.into()).collect(),
+        pending_encryption,
+        server_time,
+    };
+
+    Ok(Json(ApiResponse::new(response)))
+}
+
+/// Result of applying one pushed operation
+enum PushOutcome {
+    Applied(Option<serde_json::Value>),
+    Conflict(serde_json::Value),
+    NotFound,
+    Invalid(String),
+}
+
+impl PushOutcome {
+    fn into_result(self, op: &SyncOperation) -> SyncOperationResult {
+        let (status, record, error) = match self {
+            PushOutcome::Applied(record) => (SyncOperationStatus::Applied, record, None),
+            PushOutcome::Conflict(record) => (
+                SyncOperationStatus::Conflict,
+                Some(record),
+                Some("Record was changed on the server".to_string()),
+            ),
+            PushOutcome::NotFound => (SyncOperationStatus::NotFound, None, Some("Record not found".to_string())),
+            PushOutcome::Invalid(error) => (SyncOperationStatus::Invalid, None, Some(error)),
+        };
+
+        SyncOperationResult {
+            op_id: op.op_id.clone(),
+            record_id: op.record_id,
+            status,
+            record,
+            error,
+            conflict_id: None,
+        }
+    }
+}
+
+/// Decode an operation's payload and run the checks the REST endpoints apply to the same body
+fn parse_data<T: DeserializeOwned + Validate>(op: &SyncOperation) -> std::result::Result<T, PushOutcome> {
+    let data: T = serde_json::from_value(op.data.clone())
+        .map_err(|e| PushOutcome::Invalid(format!("Invalid data: {}", e)))?;
+    data.check().map_err(|error| PushOutcome::Invalid(error.to_string()))?;
+    Ok(data)
+}
+
+/// Whether the client edited the version currently stored; without a base version the last write wins
+fn base_matches(base_updated_at: Option<DateTime<Utc>>, updated_at: sea_orm::prelude::DateTimeWithTimeZone) -> bool {
+    base_updated_at.is_none_or(|base| base == updated_at.naive_utc().and_utc())
+}
+
+fn change_message(event_type: &str, table: &str, user_id: Uuid, record_id: Uuid, data: Option<serde_json::Value>) -> WebSocketMessage {
+    WebSocketMessage {
+        event_type: event_type.to_string(),
+        table: table.to_string(),
+        user_id,
+        record_id: Some(record_id),
+        data,
+    }
+}
+
+async fn project_is_active<C: ConnectionTrait>(db: &C, user_id: Uuid, project_id: Uuid) -> Result<bool> {
+    let project = Projects::find_by_id(project_id)
+        .filter(projects::Column::UserId.eq(user_id))
+        .filter(projects::Column::DeletedAt.is_null())
+        .one(db)
+        .await
+        .map_err(|e| crate::errors::AppError::Database(e.into()))?;
+    Ok(project.is_some())
+}
+
+/// Report an exceeded quota as a failed operation rather than aborting the whole push
+async fn quota_outcome<C: ConnectionTrait>(
+    db: &C,
+    quotas: &Quotas,
+    user_id: Uuid,
+    table: QuotaTable,
+) -> Result<Option<PushOutcome>> {
+    match quotas.enforce(db, user_id, table, 1).await {
+        Ok(()) => Ok(None),
+        Err(crate::errors::AppError::QuotaExceeded(message)) => Ok(Some(PushOutcome::Invalid(message))),
+        Err(e) => Err(e),
+    }
+}
+
+async fn push_project<C: ConnectionTrait>(
+    db: &C,
+    user_id: Uuid,
+    quotas: &Quotas,
+    op: &SyncOperation,
+    changes: &mut Vec<WebSocketMessage>,
+) -> Result<PushOutcome> {
+    let existing = Projects::find_by_id(op.record_id)
+        .one(db)
+        .await
+        .map_err(|e| crate::errors::AppError::Database(e.into()))?;
+
+    match op.action {
+        SyncAction::Create => {
+            if let Some(project) = existing {
+                if project.user_id != user_id {
+                    return Ok(PushOutcome::Invalid("Record id is already in use".to_string()));
+                }
+                // A retried create whose response never reached the client
+                return Ok(PushOutcome::Applied(Some(serde_json::to_value(ProjectResponse::from(project))?)));
+            }
+
+            if let Some(outcome) = quota_outcome(db, quotas, user_id, QuotaTable::Projects).await? {
+                return Ok(outcome);
+            }
+
+            let request: CreateProjectRequest = match parse_data(op) {
+                Ok(request) => request,
+                Err(outcome) => return Ok(outcome),
+            };
+            if let Err(error) = validate_schema_version("projects", request.schema_version) {
+                return Ok(PushOutcome::Invalid(error.to_string()));
+            }
+            if let Some(parent_id) = request.parent_id
+                && !project_is_active(db, user_id, parent_id).await?
+            {
+                return Ok(PushOutcome::Invalid("Parent project not found".to_string()));
+            }
+
+            let mut project_active = request.into_active_model(user_id);
+            project_active.id = Set(op.record_id);
+            let project = project_active.insert(db).await
+                .map_err(|e| crate::errors::AppError::Database(e.into()))?;
+
+            let data = serde_json::to_value(ProjectResponse::from(project))?;
+            changes.push(change_message("INSERT", "projects", user_id, op.record_id, Some(data.clone())));
+            Ok(PushOutcome::Applied(Some(data)))
+        }
+        SyncAction::Update => {
+            let Some(project) = existing.filter(|p| p.user_id == user_id) else {
+                return Ok(PushOutcome::NotFound);
+            };
+            if project.deleted_at.is_some() || !base_matches(op.base_updated_at, project.updated_at) {
+                return Ok(PushOutcome::Conflict(serde_json::to_value(ProjectResponse::from(project))?));
+            }
+
+            let request: UpdateProjectRequest = match parse_data(op) {
+                Ok(request) => request,
+                Err(outcome) => return Ok(outcome),
+            };
+            if let Err(error) = validate_schema_version("projects", request.schema_version) {
+                return Ok(PushOutcome::Invalid(error.to_string()));
+            }
+            if let Some(Some(parent_id)) = request.parent_id
+                && project.parent_id != Some(parent_id)
+            {
+                match validate_parent(db, user_id, project.id, parent_id).await {
+                    Ok(()) => {}
+                    Err(
+                        crate::errors::AppError::NotFound(message)
+                        | crate::errors::AppError::Validation(message)
+                        | crate::errors::AppError::Coded(_, message),
+                    ) => {
+                        return Ok(PushOutcome::Invalid(message));
+                    }
+                    Err(e) => return Err(e),
+                }
+            }
+
+            let previous_default = match change_default_project(db, user_id, &project, request.is_default).await {
+                Ok(previous_default) => previous_default,
+                Err(crate::errors::AppError::Validation(message)) => return Ok(PushOutcome::Invalid(message)),
+                Err(e) => return Err(e),
+            };
+
+            record_revision(db, user_id, RevisionAction::Update, &project).await?;
+            let mut project_active: projects::ActiveModel = project.into();
+            request.apply_to(&mut project_active);
+            let updated_project = project_active.update(db).await
+                .map_err(|e| crate::errors::AppError::Database(e.into()))?;
+
+            let data = serde_json::to_value(ProjectResponse::from(updated_project))?;
+            changes.push(change_message("UPDATE", "projects", user_id, op.record_id, Some(data.clone())));
+            if let Some(previous_default) = previous_default {
+                let previous_data = serde_json::to_value(ProjectResponse::from(previous_default.clone()))?;
+                changes.push(change_message("UPDATE", "projects", user_id, previous_default.id, Some(previous_data)));
+            }
+            Ok(PushOutcome::Applied(Some(data)))
+        }
+        SyncAction::Delete => {
+            // Deleting something that is already gone is what the client wanted anyway
+            let Some(project) = existing.filter(|p| p.user_id == user_id && p.deleted_at.is_none()) else {
+                return Ok(PushOutcome::Applied(None));
+            };
+            if !base_matches(op.base_updated_at, project.updated_at) {
+                return Ok(PushOutcome::Conflict(serde_json::to_value(ProjectResponse::from(project))?));
+            }
+
+            let user_projects = Projects::find()
+                .filter(projects::Column::UserId.eq(user_id))
+                .filter(projects::Column::DeletedAt.is_null())
+                .all(db)
+                .await
+                .map_err(|e| crate::errors::AppError::Database(e.into()))?;
+            let removed_ids = collect_subtree_ids(&user_projects, project.id);
+            if let Err(crate::errors::AppError::Validation(message)) = ensure_default_kept(&user_projects, &removed_ids) {
+                return Ok(PushOutcome::Invalid(message));
+            }
+            record_revision(db, user_id, RevisionAction::Delete, &project).await?;
+            let trashed_item_ids = trash_projects(db, user_id, &removed_ids).await?;
+
+            changes.push(change_message("DELETE", "projects", user_id, op.record_id, None));
+            for item_id in trashed_item_ids {
+                changes.push(change_message("DELETE", "can_do_list", user_id, item_id, None));
+            }
+            Ok(PushOutcome::Applied(None))
+        }
+    }
+}
+
+async fn push_item<C: ConnectionTrait>(
+    db: &C,
+    user_id: Uuid,
+    quotas: &Quotas,
+    op: &SyncOperation,
+    changes: &mut Vec<WebSocketMessage>,
+) -> Result<PushOutcome> {
+    let existing = CanDoList::find_by_id(op.record_id)
+        .one(db)
+        .await
+        .map_err(|e| crate::errors::AppError::Database(e.into()))?;
+
+    match op.action {
+        SyncAction::Create => {
+            if let Some(item) = existing {
+                if item.user_id != user_id {
+                    return Ok(PushOutcome::Invalid("Record id is already in use".to_string()));
+                }
+                return Ok(PushOutcome::Applied(Some(serde_json::to_value(CanDoItemResponse::from(item))?)));
+            }
+
+            if let Some(outcome) = quota_outcome(db, quotas, user_id, QuotaTable::CanDoList).await? {
+                return Ok(outcome);
+            }
+
+            let request: CreateCanDoItemRequest = match parse_data(op) {
+                Ok(request) => request,
+                Err(outcome) => return Ok(outcome),
+            };
+            if let Err(error) = validate_schema_version("can_do_list", request.schema_version) {
+                return Ok(PushOutcome::Invalid(error.to_string()));
+            }
+            if let Some(project_id) = request.project_id
+                && !project_is_active(db, user_id, project_id).await?
+            {
+                return Ok(PushOutcome::Invalid("Project not found".to_string()));
+            }
+            if let Some(parent_task_id) = request.parent_task_id {
+                match validate_parent_task(db, user_id, Some(op.record_id), parent_task_id).await {
+                    Ok(()) => {}
+                    Err(
+                        crate::errors::AppError::NotFound(message)
+                        | crate::errors::AppError::Validation(message)
+                        | crate::errors::AppError::Coded(_, message),
+                    ) => {
+                        return Ok(PushOutcome::Invalid(message));
+                    }
+                    Err(e) => return Err(e),
+                }
+            }
+
+            let mut item_active = request.into_active_model(user_id);
+            item_active.id = Set(op.record_id);
+            if let Err(crate::errors::AppError::Validation(message)) = validate_task_recurrence(&item_active) {
+                return Ok(PushOutcome::Invalid(message));
+            }
+            if let Some(project) = route_to_inbox(db, user_id, &mut item_active).await? {
+                let project_data = serde_json::to_value(ProjectResponse::from(project.clone()))?;
+                changes.push(change_message("INSERT", "projects", user_id, project.id, Some(project_data)));
+            }
+            let item = item_active.insert(db).await
+                .map_err(|e| crate::errors::AppError::Database(e.into()))?;
+
+            let data = serde_json::to_value(CanDoItemResponse::from(item))?;
+            changes.push(change_message("INSERT", "can_do_list", user_id, op.record_id, Some(data.clone())));
+            Ok(PushOutcome::Applied(Some(data)))
+        }
+        SyncAction::Update => {
+            let Some(item) = existing.filter(|item| item.user_id == user_id) else {
+                return Ok(PushOutcome::NotFound);
+            };
+            if item.deleted_at.is_some() || !base_matches(op.base_updated_at, item.updated_at) {
+                return Ok(PushOutcome::Conflict(serde_json::to_value(CanDoItemResponse::from(item))?));
+            }
+
+            let request: UpdateCanDoItemRequest = match parse_data(op) {
+                Ok(request) => request,
+                Err(outcome) => return Ok(outcome),
+            };
+            if let Err(error) = validate_schema_version("can_do_list", request.schema_version) {
+                return Ok(PushOutcome::Invalid(error.to_string()));
+            }
+            if let Some(Some(project_id)) = request.project_id
+                && !project_is_active(db, user_id, project_id).await?
+            {
+                return Ok(PushOutcome::Invalid("Project not found".to_string()));
+            }
+            if let Some(Some(parent_task_id)) = request.parent_task_id
+                && item.parent_task_id != Some(parent_task_id)
+            {
+                match validate_parent_task(db, user_id, Some(item.id), parent_task_id).await {
+                    Ok(()) => {}
+                    Err(
+                        crate::errors::AppError::NotFound(message)
+                        | crate::errors::AppError::Validation(message)
+                        | crate::errors::AppError::Coded(_, message),
+                    ) => {
+                        return Ok(PushOutcome::Invalid(message));
+                    }
+                    Err(e) => return Err(e),
+                }
+            }
+
+            record_revision(db, user_id, RevisionAction::Update, &item).await?;
+            let previous = item.clone();
+            let mut item_active: can_do_list::ActiveModel = item.into();
+            request.apply_to(&mut item_active);
+            if let Err(crate::errors::AppError::Validation(message)) = validate_task_recurrence(&item_active) {
+                return Ok(PushOutcome::Invalid(message));
+            }
+            if let Some(project) = route_to_inbox(db, user_id, &mut item_active).await? {
+                let project_data = serde_json::to_value(ProjectResponse::from(project.clone()))?;
+                changes.push(change_message("INSERT", "projects", user_id, project.id, Some(project_data)));
+            }
+            let updated_item = item_active.update(db).await
+                .map_err(|e| crate::errors::AppError::Database(e.into()))?;
+            let (updated_item, next_item) = create_next_occurrence(db, &previous, updated_item).await?;
+
+            let data = serde_json::to_value(CanDoItemResponse::from(updated_item))?;
+            changes.push(change_message("UPDATE", "can_do_list", user_id, op.record_id, Some(data.clone())));
+            if let Some(next_item) = next_item {
+                let next_data = serde_json::to_value(CanDoItemResponse::from(next_item.clone()))?;
+                changes.push(change_message("INSERT", "can_do_list", user_id, next_item.id, Some(next_data)));
+            }
+            Ok(PushOutcome::Applied(Some(data)))
+        }
+        SyncAction::Delete => {
+            let Some(item) = existing.filter(|item| item.user_id == user_id && item.deleted_at.is_none()) else {
+                return Ok(PushOutcome::Applied(None));
+            };
+            if !base_matches(op.base_updated_at, item.updated_at) {
+                return Ok(PushOutcome::Conflict(serde_json::to_value(CanDoItemResponse::from(item))?));
+            }
+
+            record_revision(db, user_id, RevisionAction::Delete, &item).await?;
+            for trashed_id in trash_task(db, user_id, item.id).await? {
+                changes.push(change_message("DELETE", "can_do_list", user_id, trashed_id, None));
+            }
+            Ok(PushOutcome::Applied(None))
+        }
+    }
+}
+
+async fn push_calendar<C: ConnectionTrait>(
+    db: &C,
+    user_id: Uuid,
+    quotas: &Quotas,
+    op: &SyncOperation,
+    changes: &mut Vec<WebSocketMessage>,
+) -> Result<PushOutcome> {
+    let existing = Calendars::find_by_id(op.record_id)
+        .one(db)
+        .await
+        .map_err(|e| crate::errors::AppError::Database(e.into()))?;
+
+    match op.action {
+        SyncAction::Create => {
+            if let Some(calendar) = existing {
+                if calendar.user_id != user_id {
+                    return Ok(PushOutcome::Invalid("Record id is already in use".to_string()));
+                }
+                return Ok(PushOutcome::Applied(Some(serde_json::to_value(CalendarResponse::from(calendar))?)));
+            }
+
+            if let Some(outcome) = quota_outcome(db, quotas, user_id, QuotaTable::Calendars).await? {
+                return Ok(outcome);
+            }
+
+            let request: CreateCalendarRequest = match parse_data(op) {
+                Ok(request) => request,
+                Err(outcome) => return Ok(outcome),
+            };
+            if let Err(error) = validate_schema_version("calendars", request.schema_version) {
+                return Ok(PushOutcome::Invalid(error.to_string()));
+            }
+
+            let mut calendar_active = request.into_active_model(user_id);
+            calendar_active.id = Set(op.record_id);
+            let calendar = calendar_active.insert(db).await
+                .map_err(|e| crate::errors::AppError::Database(e.into()))?;
+
+            let data = serde_json::to_value(CalendarResponse::from(calendar))?;
+            changes.push(change_message("INSERT", "calendars", user_id, op.record_id, Some(data.clone())));
+            Ok(PushOutcome::Applied(Some(data)))
+        }
+        SyncAction::Update => {
+            let Some(calendar) = existing.filter(|c| c.user_id == user_id) else {
+                return Ok(PushOutcome::NotFound);
+            };
+            if calendar.deleted_at.is_some() || !base_matches(op.base_updated_at, calendar.updated_at) {
+                return Ok(PushOutcome::Conflict(serde_json::to_value(CalendarResponse::from(calendar))?));
+            }
+
+            let request: UpdateCalendarRequest = match parse_data(op) {
+                Ok(request) => request,
+                Err(outcome) => return Ok(outcome),
+            };
+            if let Err(error) = validate_schema_version("calendars", request.schema_version) {
+                return Ok(PushOutcome::Invalid(error.to_string()));
+            }
+
+            record_revision(db, user_id, RevisionAction::Update, &calendar).await?;
+            let mut calendar_active: calendars::ActiveModel = calendar.into();
+            request.apply_to(&mut calendar_active);
+            let updated_calendar = calendar_active.update(db).await
+                .map_err(|e| crate::errors::AppError::Database(e.into()))?;
+
+            let data = serde_json::to_value(CalendarResponse::from(updated_calendar))?;
+            changes.push(change_message("UPDATE", "calendars", user_id, op.record_id, Some(data.clone())));
+            Ok(PushOutcome::Applied(Some(data)))
+        }
+        SyncAction::Delete => {
+            let Some(calendar) = existing.filter(|c| c.user_id == user_id && c.deleted_at.is_none()) else {
+                return Ok(PushOutcome::Applied(None));
+            };
+            if !base_matches(op.base_updated_at, calendar.updated_at) {
+                return Ok(PushOutcome::Conflict(serde_json::to_value(CalendarResponse::from(calendar))?));
+            }
+
+            record_revision(db, user_id, RevisionAction::Delete, &calendar).await?;
+            let event_ids = trash_calendar(db, user_id, calendar.id).await?;
+
+            changes.push(change_message("DELETE", "calendars", user_id, op.record_id, None));
+            for event_id in event_ids {
+                changes.push(change_message("DELETE", "calendar_events", user_id, event_id, None));
+            }
+            Ok(PushOutcome::Applied(None))
+        }
+    }
+}
+
+async fn push_event<C: ConnectionTrait>(
+    db: &C,
+    user_id: Uuid,
+    quotas: &Quotas,
+    op: &SyncOperation,
+    changes: &mut Vec<WebSocketMessage>,
+) -> Result<PushOutcome> {
+    let existing = CalendarEvents::find_by_id(op.record_id)
+        .one(db)
+        .await
+        .map_err(|e| crate::errors::AppError::Database(e.into()))?;
+
+    match op.action {
+        SyncAction::Create => {
+            if let Some(event) = existing {
+                if event.user_id != user_id {
+                    return Ok(PushOutcome::Invalid("Record id is already in use".to_string()));
+                }
+                return Ok(PushOutcome::Applied(Some(serde_json::to_value(CalendarEventResponse::from(event))?)));
+            }
+
+            if let Some(outcome) = quota_outcome(db, quotas, user_id, QuotaTable::CalendarEvents).await? {
+                return Ok(outcome);
+            }
+
+            let mut request: CreateCalendarEventRequest = match parse_data(op) {
+                Ok(request) => request,
+                Err(outcome) => return Ok(outcome),
+            };
+            if let Err(error) = validate_schema_version("calendar_events", request.schema_version) {
+                return Ok(PushOutcome::Invalid(error.to_string()));
+            }
+            resolve_all_day(db, user_id, &mut request).await?;
+            if let Err(error) = validate_time_range(request.start_time, request.end_time)
+                .and_then(|_| recurrence::validate(request.rrule.as_deref(), request.start_time))
+            {
+                return Ok(PushOutcome::Invalid(error.to_string()));
+            }
+            if let Some(calendar_id) = request.calendar_id
+                && !calendar_is_active(db, user_id, calendar_id).await?
+            {
+                return Ok(PushOutcome::Invalid("Calendar not found".to_string()));
+            }
+
+            let mut event_active = request.into_active_model(user_id);
+            event_active.id = Set(op.record_id);
+            let event = event_active.insert(db).await
+                .map_err(|e| crate::errors::AppError::Database(e.into()))?;
+
+            let data = serde_json::to_value(CalendarEventResponse::from(event))?;
+            changes.push(change_message("INSERT", "calendar_events", user_id, op.record_id, Some(data.clone())));
+            Ok(PushOutcome::Applied(Some(data)))
+        }
+        SyncAction::Update => {
+            let Some(event) = existing.filter(|e| e.user_id == user_id) else {
+                return Ok(PushOutcome::NotFound);
+            };
+            if event.deleted_at.is_some() || !base_matches(op.base_updated_at, event.updated_at) {
+                return Ok(PushOutcome::Conflict(serde_json::to_value(CalendarEventResponse::from(event))?));
+            }
+            if let Err(error) = ensure_editable(&event) {
+                return Ok(PushOutcome::Invalid(error.to_string()));
+            }
+
+            let mut request: UpdateCalendarEventRequest = match parse_data(op) {
+                Ok(request) => request,
+                Err(outcome) => return Ok(outcome),
+            };
+            if let Err(error) = validate_schema_version("calendar_events", request.schema_version) {
+                return Ok(PushOutcome::Invalid(error.to_string()));
+            }
+            match resolve_all_day_update(db, &event, &mut request).await {
+                Ok(()) => {}
+                Err(error @ crate::errors::AppError::InvalidFields(_)) => {
+                    return Ok(PushOutcome::Invalid(error.to_string()));
+                }
+                Err(e) => return Err(e),
+            }
+            let start_time = request.start_time.unwrap_or(event.start_time.map(|dt| dt.naive_utc().and_utc()));
+            if let Err(error) = validate_time_range(
+                start_time,
+                request.end_time.unwrap_or(event.end_time.map(|dt| dt.naive_utc().and_utc())),
+            )
+            .and_then(|_| recurrence::validate(request.rrule.clone().unwrap_or(event.rrule.clone()).as_deref(), start_time))
+            {
+                return Ok(PushOutcome::Invalid(error.to_string()));
+            }
+            if let Some(Some(calendar_id)) = request.calendar_id
+                && !calendar_is_active(db, user_id, calendar_id).await?
+            {
+                return Ok(PushOutcome::Invalid("Calendar not found".to_string()));
+            }
+
+            record_revision(db, user_id, RevisionAction::Update, &event).await?;
+            let mut event_active: calendar_events::ActiveModel = event.into();
+            request.apply_to(&mut event_active);
+            let updated_event = event_active.update(db).await
+                .map_err(|e| crate::errors::AppError::Database(e.into()))?;
+            reschedule_event_reminders(db, &updated_event).await?;
+
+            let data = serde_json::to_value(CalendarEventResponse::from(updated_event))?;
+            changes.push(change_message("UPDATE", "calendar_events", user_id, op.record_id, Some(data.clone())));
+            Ok(PushOutcome::Applied(Some(data)))
+        }
+        SyncAction::Delete => {
+            let Some(event) = existing.filter(|e| e.user_id == user_id && e.deleted_at.is_none()) else {
+                return Ok(PushOutcome::Applied(None));
+            };
+            if !base_matches(op.base_updated_at, event.updated_at) {
+                return Ok(PushOutcome::Conflict(serde_json::to_value(CalendarEventResponse::from(event))?));
+            }
+            if let Err(error) = ensure_editable(&event) {
+                return Ok(PushOutcome::Invalid(error.to_string()));
+            }
+
+            record_revision(db, user_id, RevisionAction::Delete, &event).await?;
+            let mut event_active: calendar_events::ActiveModel = event.into();
+            event_active.deleted_at = Set(Some(Utc::now().into()));
+            event_active.update(db).await
+                .map_err(|e| crate::errors::AppError::Database(e.into()))?;
+
+            changes.push(change_message("DELETE", "calendar_events", user_id, op.record_id, None));
+            Ok(PushOutcome::Applied(None))
+        }
+    }
+}
+
+async fn push_note<C: ConnectionTrait>(
+    db: &C,
+    user_id: Uuid,
+    quotas: &Quotas,
+    op: &SyncOperation,
+    changes: &mut Vec<WebSocketMessage>,
+) -> Result<PushOutcome> {
+    let existing = Notes::find_by_id(op.record_id)
+        .one(db)
+        .await
+        .map_err(|e| crate::errors::AppError::Database(e.into()))?;
+
+    match op.action {
+        SyncAction::Create => {
+            if let Some(note) = existing {
+                if note.user_id != user_id {
+                    return Ok(PushOutcome::Invalid("Record id is already in use".to_string()));
+                }
+                return Ok(PushOutcome::Applied(Some(serde_json::to_value(NoteResponse::from(note))?)));
+            }
+
+            if let Some(outcome) = quota_outcome(db, quotas, user_id, QuotaTable::Notes).await? {
+                return Ok(outcome);
+            }
+
+            let request: CreateNoteRequest = match parse_data(op) {
+                Ok(request) => request,
+                Err(outcome) => return Ok(outcome),
+            };
+            if let Err(error) = validate_schema_version("notes", request.schema_version) {
+                return Ok(PushOutcome::Invalid(error.to_string()));
+            }
+            match validate_note_links(db, user_id, request.project_id, request.event_id).await {
+                Ok(()) => {}
+                Err(crate::errors::AppError::NotFound(message)) => return Ok(PushOutcome::Invalid(message)),
+                Err(e) => return Err(e),
+            }
+
+            let mut note_active = request.into_active_model(user_id);
+            note_active.id = Set(op.record_id);
+            let note = note_active.insert(db).await
+                .map_err(|e| crate::errors::AppError::Database(e.into()))?;
+
+            let data = serde_json::to_value(NoteResponse::from(note))?;
+            changes.push(change_message("INSERT", "notes", user_id, op.record_id, Some(data.clone())));
+            Ok(PushOutcome::Applied(Some(data)))
+        }
+        SyncAction::Update => {
+            let Some(note) = existing.filter(|n| n.user_id == user_id) else {
+                return Ok(PushOutcome::NotFound);
+            };
+            if note.deleted_at.is_some() || !base_matches(op.base_updated_at, note.updated_at) {
+                return Ok(PushOutcome::Conflict(serde_json::to_value(NoteResponse::from(note))?));
+            }
+
+            let request: UpdateNoteRequest = match parse_data(op) {
+                Ok(request) => request,
+                Err(outcome) => return Ok(outcome),
+            };
+            if let Err(error) = validate_schema_version("notes", request.schema_version) {
+                return Ok(PushOutcome::Invalid(error.to_string()));
+            }
+            match validate_note_links(
+                db,
+                user_id,
+                request.project_id.flatten().filter(|id| note.project_id != Some(*id)),
+                request.event_id.flatten().filter(|id| note.event_id != Some(*id)),
+            )
+            .await
+            {
+                Ok(()) => {}
+                Err(crate::errors::AppError::NotFound(message)) => return Ok(PushOutcome::Invalid(message)),
+                Err(e) => return Err(e),
+            }
+
+            record_revision(db, user_id, RevisionAction::Update, &note).await?;
+            let mut note_active: notes::ActiveModel = note.into();
+            request.apply_to(&mut note_active);
+            let updated_note = note_active.update(db).await
+                .map_err(|e| crate::errors::AppError::Database(e.into()))?;
+
+            let data = serde_json::to_value(NoteResponse::from(updated_note))?;
+            changes.push(change_message("UPDATE", "notes", user_id, op.record_id, Some(data.clone())));
+            Ok(PushOutcome::Applied(Some(data)))
+        }
+        SyncAction::Delete => {
+            let Some(note) = existing.filter(|n| n.user_id == user_id && n.deleted_at.is_none()) else {
+                return Ok(PushOutcome::Applied(None));
+            };
+            if !base_matches(op.base_updated_at, note.updated_at) {
+                return Ok(PushOutcome::Conflict(serde_json::to_value(NoteResponse::from(note))?));
+            }
+
+            record_revision(db, user_id, RevisionAction::Delete, &note).await?;
+            let mut note_active: notes::ActiveModel = note.into();
+            note_active.deleted_at = Set(Some(Utc::now().into()));
+            note_active.update(db).await
+                .map_err(|e| crate::errors::AppError::Database(e.into()))?;
+
+            changes.push(change_message("DELETE", "notes", user_id, op.record_id, None));
+            Ok(PushOutcome::Applied(None))
+        }
+    }
+}
+
+async fn push_habit<C: ConnectionTrait>(
+    db: &C,
+    user_id: Uuid,
+    op: &SyncOperation,
+    changes: &mut Vec<WebSocketMessage>,
+) -> Result<PushOutcome> {
+    let existing = Habits::find_by_id(op.record_id)
+        .one(db)
+        .await
+        .map_err(|e| crate::errors::AppError::Database(e.into()))?;
+
+    match op.action {
+        SyncAction::Create => {
+            if let Some(habit) = existing {
+                if habit.user_id != user_id {
+                    return Ok(PushOutcome::Invalid("Record id is already in use".to_string()));
+                }
+                return Ok(PushOutcome::Applied(Some(serde_json::to_value(HabitResponse::from(habit))?)));
+            }
+
+            let mut request: CreateHabitRequest = match parse_data(op) {
+                Ok(request) => request,
+                Err(outcome) => return Ok(outcome),
+            };
+            if let Err(error) = validate_schema_version("habits", request.schema_version) {
+                return Ok(PushOutcome::Invalid(error.to_string()));
+            }
+
+            request.id = Some(op.record_id);
+            let time = time_preferences(db, user_id).await?;
+            let habit = match insert_habit(db, user_id, request, &time).await {
+                Ok(habit) => habit,
+                Err(error @ (crate::errors::AppError::Validation(_) | crate::errors::AppError::InvalidFields(_))) => {
+                    return Ok(PushOutcome::Invalid(error.to_string()));
+                }
+                Err(e) => return Err(e),
+            };
+
+            let data = serde_json::to_value(HabitResponse::from(habit))?;
+            changes.push(change_message("INSERT", "habits", user_id, op.record_id, Some(data.clone())));
+            Ok(PushOutcome::Applied(Some(data)))
+        }
+        SyncAction::Update => {
+            let Some(habit) = existing.filter(|h| h.user_id == user_id) else {
+                return Ok(PushOutcome::NotFound);
+            };
+            if !base_matches(op.base_updated_at, habit.updated_at) {
+                return Ok(PushOutcome::Conflict(serde_json::to_value(HabitResponse::from(habit))?));
+            }
+
+            let request: UpdateHabitRequest = match parse_data(op) {
+                Ok(request) => request,
+                Err(outcome) => return Ok(outcome),
+            };
+            if let Err(error) = validate_schema_version("habits", request.schema_version) {
+                return Ok(PushOutcome::Invalid(error.to_string()));
+            }
+
+            let time = time_preferences(db, user_id).await?;
+            let updated_habit = match change_habit(db, habit, request, &time).await {
+                Ok(habit) => habit,
+                Err(error @ (crate::errors::AppError::Validation(_) | crate::errors::AppError::InvalidFields(_))) => {
+                    return Ok(PushOutcome::Invalid(error.to_string()));
+                }
+                Err(e) => return Err(e),
+            };
+
+            let data = serde_json::to_value(HabitResponse::from(updated_habit))?;
+            changes.push(change_message("UPDATE", "habits", user_id, op.record_id, Some(data.clone())));
+            Ok(PushOutcome::Applied(Some(data)))
+        }
+        SyncAction::Delete => {
+            let Some(habit) = existing.filter(|h| h.user_id == user_id) else {
+                return Ok(PushOutcome::Applied(None));
+            };
+            if !base_matches(op.base_updated_at, habit.updated_at) {
+                return Ok(PushOutcome::Conflict(serde_json::to_value(HabitResponse::from(habit))?));
+            }
+
+            Habits::delete_by_id(habit.id)
+                .exec(db)
+                .await
+                .map_err(|e| crate::errors::AppError::Database(e.into()))?;
+            record_deletion(db, user_id, "habits", habit.id).await?;
+
+            changes.push(change_message("DELETE", "habits", user_id, op.record_id, None));
+            Ok(PushOutcome::Applied(None))
+        }
+    }
+}
+
+/// Apply one operation to the table it names, queueing the changes to broadcast
+pub(crate) async fn apply_operation<C: ConnectionTrait>(
+    db: &C,
+    user_id: Uuid,
+    quotas: &Quotas,
+    op: &SyncOperation,
+    changes: &mut Vec<WebSocketMessage>,
+) -> Result<SyncOperationResult> {
+    let outcome = match op.table.as_str() {
+        "projects" => push_project(db, user_id, quotas, op, changes).await?,
+        "can_do_list" => push_item(db, user_id, quotas, op, changes).await?,
+        "calendars" => push_calendar(db, user_id, quotas, op, changes).await?,
+        "calendar_events" => push_event(db, user_id, quotas, op, changes).await?,
+        "notes" => push_note(db, user_id, quotas, op, changes).await?,
+        "habits" => push_habit(db, user_id, op, changes).await?,
+        table => PushOutcome::Invalid(format!("Unknown table: {}", table)),
+    };
+    Ok(outcome.into_result(op))
+}
+
+/// Apply operations a client queued while offline, in order, within one transaction.
+/// Each operation gets its own result; conflicts and invalid operations don't stop the rest.
+/// Conflicts are also kept in the conflict log, so any of the user's devices can settle them later.
+pub async fn push_changes(
+    State(app_state): State<AppState>,
+    auth_user: AuthUser,
+    OriginConnection(connection_id): OriginConnection,
+    ValidatedJson(request): ValidatedJson<SyncPushRequest>,
+) -> Result<Json<ApiResponse<SyncPushResponse>>> {
+    let user_id = auth_user.0.id;
+
+    if request.operations.len() > MAX_PUSH_OPERATIONS {
+        return Err(crate::errors::AppError::Validation(format!(
+            "At most {} operations can be pushed at once",
+            MAX_PUSH_OPERATIONS
+        )));
+    }
+
+    let txn = app_state.db.connection.begin().await
+        .map_err(|e| crate::errors::AppError::Database(e.into()))?;
+
+    let mut results = Vec::with_capacity(request.operations.len());
+    let mut changes = Vec::new();
+
+    for op in &request.operations {
+        if matches!(op.action, SyncAction::Create)
+            && let Err(error) = validate_client_id(Some(op.record_id))
+        {
+            results.push(PushOutcome::Invalid(error.to_string()).into_result(op));
+            continue;
+        }
+
+        let mut result = apply_operation(&txn, user_id, &app_state.quotas, op, &mut changes).await?;
+        if let (SyncOperationStatus::Conflict, Some(server_record)) = (&result.status, &result.record) {
+            result.conflict_id = Some(record_conflict(&txn, user_id, op, server_record.clone()).await?);
+        }
+        results.push(result);
+    }
+
+    txn.commit().await
+        .map_err(|e| crate::errors::AppError::Database(e.into()))?;
+
+    // Broadcast websocket messages for every applied change
+    tracing::info!("Offline operations applied, broadcasting {} websocket messages for user {} (excluding connection {:?})", changes.len(), user_id, connection_id);
+    app_state.ws_state.broadcast_many_to_user(&user_id, changes, connection_id).await;
+
+    Ok(Json(ApiResponse::new(SyncPushResponse { results })))
+}
@@ -0,0 +1,464 @@
+use axum::{
+    extract::{Path, Query, State},
+    response::Json,
+};
+use chrono::{Duration, NaiveDate};
+use chrono_tz::Tz;
+use sea_orm::*;
+use uuid::Uuid;
+
+use crate::{
+    entities::{prelude::*, calendar_events, can_do_list, projects, templates},
+    errors::{AppError, Result},
+    features::Feature,
+    handlers::{
+        calendar_events::{calendar_is_active, ensure_editable, event_audience},
+        projects::collect_subtree_ids,
+        sync::record_deletion,
+        user_settings::time_preferences,
+    },
+    middleware::{auth::AuthUser, connection::OriginConnection, preconditions::Preconditions, validation::ValidatedJson},
+    models::{
+        calendar_event::all_day_bounds,
+        can_do_list::CanDoItemResponse,
+        project::ProjectResponse,
+        template::{
+            CreateTemplateRequest, EventTemplate, InstantiateTemplateRequest, InstantiateTemplateResponse,
+            ProjectTemplate, TemplateContent, TemplateListQuery, TemplateResponse, UpdateTemplateRequest,
+        },
+        user_settings::TimePreferences,
+        validate_schema_version, ApiResponse, PageQuery,
+    },
+    quota::{QuotaTable, Quotas},
+    state::AppState,
+    websocket::WebSocketMessage,
+};
+
+fn template_message(event_type: &str, user_id: Uuid, template: &templates::Model) -> WebSocketMessage {
+    WebSocketMessage {
+        event_type: event_type.to_string(),
+        table: "templates".to_string(),
+        user_id,
+        record_id: Some(template.id),
+        data: Some(serde_json::to_value(TemplateResponse::from(template.clone())).unwrap_or_default()),
+    }
+}
+
+async fn find_template<C: ConnectionTrait>(db: &C, user_id: Uuid, id: Uuid) -> Result<templates::Model> {
+    Templates::find_by_id(id)
+        .filter(templates::Column::UserId.eq(user_id))
+        .one(db)
+        .await
+        .map_err(|e| AppError::Database(e.into()))?
+        .ok_or_else(|| AppError::NotFound("Template not found".to_string()))
+}
+
+/// Save one of the user's active projects with its active sub-projects and their tasks
+async fn capture_project<C: ConnectionTrait>(
+    db: &C,
+    user_id: Uuid,
+    project_id: Uuid,
+    anchor_date: Option<NaiveDate>,
+) -> Result<TemplateContent> {
+    let user_projects = Projects::find()
+        .filter(projects::Column::UserId.eq(user_id))
+        .filter(projects::Column::DeletedAt.is_null())
+        .all(db)
+        .await
+        .map_err(|e| AppError::Database(e.into()))?;
+    if !user_projects.iter().any(|p| p.id == project_id) {
+        return Err(AppError::NotFound("Project not found".to_string()));
+    }
+
+    // The root comes first and every project after its parent
+    let source_ids = collect_subtree_ids(&user_projects, project_id);
+    let source_projects: Vec<projects::Model> = source_ids
+        .iter()
+        .filter_map(|id| user_projects.iter().find(|p| p.id == *id).cloned())
+        .collect();
+    let source_tasks = CanDoList::find()
+        .filter(can_do_list::Column::UserId.eq(user_id))
+        .filter(can_do_list::Column::DeletedAt.is_null())
+        .filter(can_do_list::Column::ProjectId.is_in(source_ids))
+        .order_by_asc(can_do_list::Column::DisplayOrder)
+        .order_by_asc(can_do_list::Column::CreatedAt)
+        .all(db)
+        .await
+        .map_err(|e| AppError::Database(e.into()))?;
+
+    let preferences = time_preferences(db, user_id).await?;
+    let anchor_date = anchor_date.unwrap_or_else(|| preferences.today());
+    Ok(TemplateContent::Project(ProjectTemplate::capture(&source_projects, &source_tasks, anchor_date, preferences.timezone)))
+}
+
+/// Save one of the user's own timed events
+async fn capture_event<C: ConnectionTrait>(
+    db: &C,
+    user_id: Uuid,
+    event_id: Uuid,
+    anchor_date: Option<NaiveDate>,
+) -> Result<TemplateContent> {
+    let event = CalendarEvents::find_by_id(event_id)
+        .filter(calendar_events::Column::UserId.eq(user_id))
+        .filter(calendar_events::Column::DeletedAt.is_null())
+        .one(db)
+        .await
+        .map_err(|e| AppError::Database(e.into()))?
+        .ok_or_else(|| AppError::NotFound("Calendar event not found".to_string()))?;
+    ensure_editable(&event)?;
+    let Some(start_time) = event.start_time else {
+        return Err(AppError::invalid_field("event_id", "Only events with a start time can be saved as a template"));
+    };
+
+    let timezone = time_preferences(db, user_id).await?.timezone;
+    let first_day = event.start_date.filter(|_| event.is_all_day);
+    let anchor_date = anchor_date.or(first_day).unwrap_or_else(|| start_time.with_timezone(&timezone).date_naive());
+    Ok(TemplateContent::Event(EventTemplate::capture(&event, start_time, anchor_date, timezone)))
+}
+
+pub async fn list_templates(
+    State(app_state): State<AppState>,
+    auth_user: AuthUser,
+    Query(query): Query<TemplateListQuery>,
+    Query(paging): Query<PageQuery>,
+) -> Result<Json<ApiResponse<Vec<TemplateResponse>>>> {
+    let mut find = Templates::find().filter(templates::Column::UserId.eq(auth_user.0.id));
+    if let Some(kind) = query.kind {
+        find = find.filter(templates::Column::Kind.eq(kind));
+    }
+    let find = find
+        .order_by_asc(templates::Column::CreatedAt);
+    let (templates, meta) = paging.fetch(app_state.db.reader(), find).await?;
+
+    Ok(Json(ApiResponse::list(templates.into_iter().map(TemplateResponse::from).collect(), meta)))
+}
+
+pub async fn get_template(
+    State(app_state): State<AppState>,
+    auth_user: AuthUser,
+    Path(id): Path<Uuid>,
+) -> Result<Json<ApiResponse<TemplateResponse>>> {
+    let template = find_template(app_state.db.reader(), auth_user.0.id, id).await?;
+    Ok(Json(ApiResponse::new(template.into())))
+}
+
+/// Save a project subtree or an event as a new template
+pub async fn create_template(
+    State(app_state): State<AppState>,
+    auth_user: AuthUser,
+    OriginConnection(connection_id): OriginConnection,
+    ValidatedJson(request): ValidatedJson<CreateTemplateRequest>,
+) -> Result<Json<ApiResponse<TemplateResponse>>> {
+    let user_id = auth_user.0.id;
+    app_state.features.require(app_state.db.reader(), user_id, Feature::Templates).await?;
+    validate_schema_version("templates", request.schema_version)?;
+    let client_id = request.id;
+    let template = app_state.db.with_txn(|txn| Box::pin(async move {
+        let content = match (request.project_id, request.event_id) {
+            (Some(project_id), None) => capture_project(txn, user_id, project_id, request.anchor_date).await?,
+            (None, Some(event_id)) => capture_event(txn, user_id, event_id, request.anchor_date).await?,
+            _ => return Err(AppError::Validation("Exactly one of project_id and event_id is required".to_string())),
+        };
+        request
+            .into_active_model(user_id, &content)
+            .insert(txn)
+            .await
+            .map_err(|e| AppError::from_insert(e, client_id))
+    })).await?;
+
+    // Broadcast websocket message for template creation
+    tracing::info!("Template created, broadcasting websocket message for user {} (excluding connection {:?})", user_id, connection_id);
+    app_state.ws_state.broadcast_to_user(&user_id, template_message("INSERT", user_id, &template), connection_id).await;
+
+    Ok(Json(ApiResponse::with_message(template.into(), "Template created successfully")))
+}
+
+pub async fn update_template(
+    State(app_state): State<AppState>,
+    auth_user: AuthUser,
+    OriginConnection(connection_id): OriginConnection,
+    Path(id): Path<Uuid>,
+    preconditions: Preconditions,
+    ValidatedJson(request): ValidatedJson<UpdateTemplateRequest>,
+) -> Result<Json<ApiResponse<TemplateResponse>>> {
+    validate_schema_version("templates", request.schema_version)?;
+    let user_id = auth_user.0.id;
+    let template = app_state.db.with_txn(|txn| Box::pin(async move {
+        let template = find_template(txn, user_id, id).await?;
+        preconditions.check(&template.updated_at)?;
+        let mut template_active: templates::ActiveModel = template.into();
+        request.apply_to(&mut template_active);
+        template_active.update(txn).await
+            .map_err(|e| AppError::Database(e.into()))
+    })).await?;
+
+    // Broadcast websocket message for template update
+    tracing::info!("Template updated, broadcasting websocket message for user {} (excluding connection {:?})", user_id, connection_id);
+    app_state.ws_state.broadcast_to_user(&user_id, template_message("UPDATE", user_id, &template), connection_id).await;
+
+    Ok(Json(ApiResponse::with_message(template.into(), "Template updated successfully")))
+}
+
+/// Delete a template; records created from it stay
+pub async fn delete_template(
+    State(app_state): State<AppState>,
+    auth_user: AuthUser,
+    OriginConnection(connection_id): OriginConnection,
+    Path(id): Path<Uuid>,
+    preconditions: Preconditions,
+) -> Result<Json<ApiResponse<()>>> {
+    let user_id = auth_user.0.id;
+    app_state.db.with_txn(|txn| Box::pin(async move {
+        let template = find_template(txn, user_id, id).await?;
+        preconditions.check(&template.updated_at)?;
+        Templates::delete_by_id(template.id)
+            .exec(txn)
+            .await
+            .map_err(|e| AppError::Database(e.into()))?;
+        record_deletion(txn, user_id, "templates", template.id).await
+    })).await?;
+
+    // Broadcast websocket message for template deletion
+    tracing::info!("Template deleted, broadcasting websocket message for user {} (excluding connection {:?})", user_id, connection_id);
+    let ws_message = WebSocketMessage {
+        event_type: "DELETE".to_string(),
+        table: "templates".to_string(),
+        user_id,
+        record_id: Some(id),
+        data: None,
+    };
+    app_state.ws_state.broadcast_to_user(&user_id, ws_message, connection_id).await;
+
+    Ok(Json(ApiResponse::with_message((), "Template deleted successfully")))
+}
+
+/// Create the projects and tasks of a project template, shifting their dates to `start_date`
+async fn instantiate_project<C: ConnectionTrait>(
+    txn: &C,
+    quotas: &Quotas,
+    user_id: Uuid,
+    content: ProjectTemplate,
+    request: &InstantiateTemplateRequest,
+    start_date: NaiveDate,
+    timezone: Tz,
+) -> Result<InstantiateTemplateResponse> {
+    if let Some(parent_id) = request.parent_id {
+        Projects::find_by_id(parent_id)
+            .filter(projects::Column::UserId.eq(user_id))
+            .filter(projects::Column::DeletedAt.is_null())
+            .one(txn)
+            .await
+            .map_err(|e| AppError::Database(e.into()))?
+            .ok_or_else(|| AppError::NotFound("Parent project not found".to_string()))?;
+    }
+    quotas.enforce(txn, user_id, QuotaTable::Projects, content.projects.len() as u64).await?;
+    quotas.enforce(txn, user_id, QuotaTable::CanDoList, content.tasks.len() as u64).await?;
+
+    // Every project comes after its parent, so the parent's copy already exists
+    let mut project_ids: Vec<Uuid> = Vec::with_capacity(content.projects.len());
+    let mut created_projects = Vec::with_capacity(content.projects.len());
+    for project in content.projects {
+        let mut project_active = projects::ActiveModel::new();
+        project_active.user_id = Set(user_id);
+        project_active.encrypted_data = Set(project.payload.encrypted_data);
+        project_active.iv = Set(project.payload.iv);
+        project_active.salt = Set(project.payload.salt);
+        project_active.schema_version = Set(project.payload.schema_version);
+        project_active.parent_id = Set(match project.parent {
+            Some(parent) => project_ids.get(parent).copied(),
+            None => request.parent_id,
+        });
+        project_active.display_order = Set(project.display_order);
+        project_active.is_collapsed = Set(project.is_collapsed);
+        project_active.search_tokens = Set(project.search_tokens);
+
+        let created = project_active.insert(txn).await
+            .map_err(|e| AppError::Database(e.into()))?;
+        project_ids.push(created.id);
+        created_projects.push(created);
+    }
+
+    let mut created_tasks = Vec::with_capacity(content.tasks.len());
+    let mut task_parents = Vec::with_capacity(content.tasks.len());
+    for task in content.tasks {
+        task_parents.push(task.parent);
+        let mut item_active = can_do_list::ActiveModel::new();
+        item_active.user_id = Set(user_id);
+        item_active.project_id = Set(project_ids.get(task.project).copied());
+        item_active.encrypted_data = Set(task.payload.encrypted_data);
+        item_active.iv = Set(task.payload.iv);
+        item_active.salt = Set(task.payload.salt);
+        item_active.schema_version = Set(task.payload.schema_version);
+        item_active.display_order = Set(task.display_order);
+        item_active.search_bloom = Set(task.search_bloom);
+        item_active.search_tokens = Set(task.search_tokens);
+        item_active.due_at = Set(task.due.and_then(|due| due.resolve(start_date, timezone)).map(Into::into));
+        item_active.scheduled_at = Set(task.scheduled.and_then(|scheduled| scheduled.resolve(start_date, timezone)).map(Into::into));
+        item_active.rrule = Set(task.rrule);
+        item_active.priority = Set(task.priority);
+        item_active.estimated_minutes = Set(task.estimated_minutes);
+        item_active.is_pinned = Set(task.is_pinned);
+
+        let created = item_active.insert(txn).await
+            .map_err(|e| AppError::Database(e.into()))?;
+        created_tasks.push(created);
+    }
+
+    // Subtasks point at the new copies of their parents once all of them exist
+    let task_ids: Vec<Uuid> = created_tasks.iter().map(|task| task.id).collect();
+    for (task, parent) in created_tasks.iter_mut().zip(task_parents) {
+        let Some(parent_id) = parent.and_then(|parent| task_ids.get(parent).copied()) else {
+            continue;
+        };
+        let mut item_active: can_do_list::ActiveModel = task.clone().into();
+        item_active.parent_task_id = Set(Some(parent_id));
+        *task = item_active.update(txn).await
+            .map_err(|e| AppError::Database(e.into()))?;
+    }
+
+    Ok(InstantiateTemplateResponse {
+        projects: created_projects.into_iter().map(ProjectResponse::from).collect(),
+        can_do_list: created_tasks.into_iter().map(CanDoItemResponse::from).collect(),
+        ..Default::default()
+    })
+}
+
+/// Create the event of an event template, starting relative to `start_date`
+async fn instantiate_event<C: ConnectionTrait>(
+    txn: &C,
+    quotas: &Quotas,
+    user_id: Uuid,
+    content: EventTemplate,
+    request: &InstantiateTemplateRequest,
+    start_date: NaiveDate,
+    preferences: &TimePreferences,
+) -> Result<InstantiateTemplateResponse> {
+    let calendar_id = match request.calendar_id {
+        Some(calendar_id) => {
+            if !calendar_is_active(txn, user_id, calendar_id).await? {
+                return Err(AppError::NotFound("Calendar not found".to_string()));
+            }
+            Some(calendar_id)
+        }
+        // A saved calendar that has since been deleted or turned read-only is left out
+        None => match content.calendar_id {
+            Some(calendar_id) if calendar_is_active(txn, user_id, calendar_id).await? => Some(calendar_id),
+            _ => None,
+        },
+    };
+    quotas.enforce(txn, user_id, QuotaTable::CalendarEvents, 1).await?;
+
+    let out_of_range = || AppError::invalid_field("start_date", "start_date is out of range");
+    let start_time = content.start.resolve(start_date, preferences.timezone).ok_or_else(out_of_range)?;
+    // All-day events keep their days rather than their times
+    let days = content
+        .all_day_span
+        .map(|span| {
+            let first_day = Duration::try_days(content.start.days).and_then(|days| start_date.checked_add_signed(days))?;
+            let last_day = Duration::try_days(span - 1).and_then(|days| first_day.checked_add_signed(days))?;
+            Some((first_day, last_day))
+        })
+        .map(|days| days.ok_or_else(out_of_range))
+        .transpose()?;
+    let mut event_active = calendar_events::ActiveModel::new();
+    event_active.user_id = Set(user_id);
+    event_active.calendar_id = Set(calendar_id);
+    event_active.encrypted_data = Set(content.payload.encrypted_data);
+    event_active.iv = Set(content.payload.iv);
+    event_active.salt = Set(content.payload.salt);
+    event_active.schema_version = Set(content.payload.schema_version);
+    match days {
+        Some((first_day, last_day)) => {
+            let (start_time, end_time) = all_day_bounds(preferences, first_day, last_day);
+            event_active.start_time = Set(Some(start_time.into()));
+            event_active.end_time = Set(Some(end_time.into()));
+            event_active.is_all_day = Set(true);
+            event_active.start_date = Set(Some(first_day));
+            event_active.end_date = Set(Some(last_day));
+        }
+        None => {
+            event_active.start_time = Set(Some(start_time.into()));
+            event_active.end_time = Set(content
+                .duration_minutes
+                .map(|minutes| (start_time + Duration::minutes(minutes)).into()));
+        }
+    }
+    event_active.search_bloom = Set(content.search_bloom);
+    event_active.search_tokens = Set(content.search_tokens);
+    event_active.rrule = Set(content.rrule);
+    event_active.status = Set(content.status);
+
+    let created = event_active.insert(txn).await
+        .map_err(|e| AppError::Database(e.into()))?;
+    Ok(InstantiateTemplateResponse {
+        calendar_events: vec![created.into()],
+        ..Default::default()
+    })
+}
+
+/// Create new records from a template, with its saved dates shifted to `start_date`
+pub async fn instantiate_template(
+    State(app_state): State<AppState>,
+    auth_user: AuthUser,
+    OriginConnection(connection_id): OriginConnection,
+    Path(id): Path<Uuid>,
+    ValidatedJson(request): ValidatedJson<InstantiateTemplateRequest>,
+) -> Result<Json<ApiResponse<InstantiateTemplateResponse>>> {
+    let user_id = auth_user.0.id;
+    app_state.features.require(app_state.db.reader(), user_id, Feature::Templates).await?;
+    let quotas = app_state.quotas.clone();
+    let response = app_state.db.with_txn(|txn| Box::pin(async move {
+        let template = find_template(txn, user_id, id).await?;
+        let content = TemplateContent::parse(&template)
+            .map_err(|e| AppError::Internal(format!("Template {} has unreadable content: {}", template.id, e)))?;
+        let preferences = time_preferences(txn, user_id).await?;
+        let start_date = request.start_date.unwrap_or_else(|| preferences.today());
+        match content {
+            TemplateContent::Project(content) => {
+                instantiate_project(txn, &quotas, user_id, content, &request, start_date, preferences.timezone).await
+            }
+            TemplateContent::Event(content) => {
+                instantiate_event(txn, &quotas, user_id, content, &request, start_date, &preferences).await
+            }
+        }
+    })).await?;
+
+    // Broadcast websocket messages for the created records
+    tracing::info!("Template instantiated, broadcasting websocket message for user {} (excluding connection {:?})", user_id, connection_id);
+    let mut ws_messages = Vec::with_capacity(response.projects.len() + response.can_do_list.len());
+    for project in &response.projects {
+        ws_messages.push(WebSocketMessage {
+            event_type: "INSERT".to_string(),
+            table: "projects".to_string(),
+            user_id,
+            record_id: Some(project.id),
+            data: Some(serde_json::to_value(project).unwrap_or_default()),
+        });
+    }
+    for item in &response.can_do_list {
+        ws_messages.push(WebSocketMessage {
+            event_type: "INSERT".to_string(),
+            table: "can_do_list".to_string(),
+            user_id,
+            record_id: Some(item.id),
+            data: Some(serde_json::to_value(item).unwrap_or_default()),
+        });
+    }
+    if !ws_messages.is_empty() {
+        app_state.ws_state.broadcast_many_to_user(&user_id, ws_messages, connection_id).await;
+    }
+    // Events go to everyone who shares their calendar
+    for event in &response.calendar_events {
+        let ws_message = WebSocketMessage {
+            event_type: "INSERT".to_string(),
+            table: "calendar_events".to_string(),
+            user_id,
+            record_id: Some(event.id),
+            data: Some(serde_json::to_value(event).unwrap_or_default()),
+        };
+        let audience = event_audience(&app_state.db.connection, user_id, [event.calendar_id]).await?;
+        app_state.ws_state.broadcast_many_to_users(&audience, vec![ws_message], connection_id).await;
+    }
+
+    Ok(Json(ApiResponse::with_message(response, "Template instantiated successfully")))
+}
@@ -0,0 +1,246 @@
+use axum::{
+    extract::{Path, Query, State},
+    response::Json,
+};
+use chrono::{DateTime, Utc};
+use sea_orm::*;
+use streamline_models::DEFAULT_SCHEMA_VERSION;
+use uuid::Uuid;
+
+use crate::{
+    entities::{prelude::*, can_do_list, time_entries},
+    errors::{AppError, Result},
+    handlers::calendar_events::validate_time_range,
+    middleware::{auth::AuthUser, connection::OriginConnection, preconditions::Preconditions, validation::ValidatedJson},
+    models::{
+        time_entry::{ProjectTimeTotal, StopTimerRequest, TimeEntryQuery, TimeEntryResponse, TimeTotalsQuery},
+        ApiResponse, PageQuery, validate_schema_version,
+    },
+    state::AppState,
+    websocket::WebSocketMessage,
+};
+
+fn entry_message(event_type: &str, user_id: Uuid, entry: &time_entries::Model) -> WebSocketMessage {
+    WebSocketMessage {
+        event_type: event_type.to_string(),
+        table: "time_entries".to_string(),
+        user_id,
+        record_id: Some(entry.id),
+        data: Some(serde_json::to_value(TimeEntryResponse::from(entry.clone())).unwrap_or_default()),
+    }
+}
+
+pub async fn list_time_entries(
+    State(app_state): State<AppState>,
+    auth_user: AuthUser,
+    Query(query): Query<TimeEntryQuery>,
+    Query(paging): Query<PageQuery>,
+) -> Result<Json<ApiResponse<Vec<TimeEntryResponse>>>> {
+    validate_time_range(query.start, query.end)?;
+
+    let mut find = TimeEntries::find().filter(time_entries::Column::UserId.eq(auth_user.0.id));
+    if let Some(task_id) = query.task_id {
+        find = find.filter(time_entries::Column::TaskId.eq(task_id));
+    }
+    if let Some(start) = query.start {
+        find = find.filter(
+            Condition::any()
+                .add(time_entries::Column::StoppedAt.is_null())
+                .add(time_entries::Column::StoppedAt.gt(start)),
+        );
+    }
+    if let Some(end) = query.end {
+        find = find.filter(time_entries::Column::StartedAt.lt(end));
+    }
+
+    let find = find
+        .order_by_asc(time_entries::Column::StartedAt);
+    let (entries, meta) = paging.fetch(&app_state.db.connection, find).await?;
+
+    Ok(Json(ApiResponse::list(entries.into_iter().map(Into::into).collect(), meta)))
+}
+
+/// Tracked time per project, counting only the part of each entry inside the window;
+/// running timers count up to now
+pub async fn time_totals(
+    State(app_state): State<AppState>,
+    auth_user: AuthUser,
+    Query(query): Query<TimeTotalsQuery>,
+    Query(paging): Query<PageQuery>,
+) -> Result<Json<ApiResponse<Vec<ProjectTimeTotal>>>> {
+    validate_time_range(Some(query.start), Some(query.end))?;
+
+    let totals = project_time_totals(&app_state.db.connection, auth_user.0.id, query.start, query.end).await?;
+    let (response, meta) = paging.page(totals)?;
+    Ok(Json(ApiResponse::list(response, meta)))
+}
+
+/// Seconds tracked per project between `start` and `end`, most tracked first
+pub(crate) async fn project_time_totals<C: ConnectionTrait>(
+    db: &C,
+    user_id: Uuid,
+    start: DateTime<Utc>,
+    end: DateTime<Utc>,
+) -> Result<Vec<ProjectTimeTotal>> {
+    let seconds = match db.get_database_backend() {
+        DbBackend::Sqlite => {
+            "CAST(COALESCE(SUM((MIN(julianday(COALESCE(e.stopped_at, 'now')), julianday($3)) \
+             - MAX(julianday(e.started_at), julianday($2))) * 86400), 0) AS INTEGER)"
+        }
+        _ => "COALESCE(SUM(EXTRACT(EPOCH FROM LEAST(COALESCE(e.stopped_at, NOW()), $3) - GREATEST(e.started_at, $2))), 0)::bigint",
+    };
+    ProjectTimeTotal::find_by_statement(Statement::from_sql_and_values(
+        db.get_database_backend(),
+        format!(
+            "SELECT t.project_id, {} AS total_seconds, \
+             COUNT(*) AS entry_count \
+             FROM time_entries e JOIN can_do_list t ON t.id = e.task_id \
+             WHERE e.user_id = $1 AND e.started_at < $3 AND (e.stopped_at IS NULL OR e.stopped_at > $2) \
+             GROUP BY t.project_id \
+             ORDER BY total_seconds DESC",
+            seconds
+        ),
+        [user_id.into(), start.into(), end.into()],
+    ))
+    .all(db)
+    .await
+    .map_err(|e| AppError::Database(e.into()))
+}
+
+/// Start a timer on a task, stopping whichever timer was running before
+pub async fn start_timer(
+    State(app_state): State<AppState>,
+    auth_user: AuthUser,
+    OriginConnection(connection_id): OriginConnection,
+    Path(task_id): Path<Uuid>,
+) -> Result<Json<ApiResponse<TimeEntryResponse>>> {
+    let txn = app_state.db.connection.begin().await
+        .map_err(|e| AppError::Database(e.into()))?;
+
+    CanDoList::find_by_id(task_id)
+        .filter(can_do_list::Column::UserId.eq(auth_user.0.id))
+        .filter(can_do_list::Column::DeletedAt.is_null())
+        .one(&txn)
+        .await
+        .map_err(|e| AppError::Database(e.into()))?
+        .ok_or_else(|| AppError::NotFound("Can-do item not found".to_string()))?;
+
+    let running = TimeEntries::find()
+        .filter(time_entries::Column::UserId.eq(auth_user.0.id))
+        .filter(time_entries::Column::StoppedAt.is_null())
+        .lock_exclusive()
+        .one(&txn)
+        .await
+        .map_err(|e| AppError::Database(e.into()))?;
+
+    let now = Utc::now();
+    let mut ws_messages = Vec::new();
+    if let Some(running) = running {
+        if running.task_id == task_id {
+            return Err(AppError::Conflict("A timer is already running for this task".to_string()));
+        }
+        let mut running_active: time_entries::ActiveModel = running.into();
+        running_active.stopped_at = Set(Some(now.into()));
+        let stopped = running_active.update(&txn).await
+            .map_err(|e| AppError::Database(e.into()))?;
+        ws_messages.push(entry_message("UPDATE", auth_user.0.id, &stopped));
+    }
+
+    let mut entry_active = time_entries::ActiveModel::new();
+    entry_active.user_id = Set(auth_user.0.id);
+    entry_active.task_id = Set(task_id);
+    entry_active.started_at = Set(now.into());
+
+    // A concurrent start for the same user trips the one-running-timer index
+    let entry = entry_active.insert(&txn).await.map_err(|e| match e.sql_err() {
+        Some(SqlErr::UniqueConstraintViolation(_)) => AppError::Conflict("Another timer was started at the same time".to_string()),
+        _ => AppError::Database(e.into()),
+    })?;
+
+    txn.commit().await
+        .map_err(|e| AppError::Database(e.into()))?;
+
+    // Broadcast websocket messages for the new timer and the one it replaced
+    tracing::info!("Timer started, broadcasting websocket message for user {} (excluding connection {:?})", auth_user.0.id, connection_id);
+    ws_messages.push(entry_message("INSERT", auth_user.0.id, &entry));
+    app_state.ws_state.broadcast_many_to_user(&auth_user.0.id, ws_messages, connection_id).await;
+
+    Ok(Json(ApiResponse::with_message(entry.into(), "Timer started successfully")))
+}
+
+pub async fn stop_timer(
+    State(app_state): State<AppState>,
+    auth_user: AuthUser,
+    OriginConnection(connection_id): OriginConnection,
+    Path(task_id): Path<Uuid>,
+    request: Option<ValidatedJson<StopTimerRequest>>,
+) -> Result<Json<ApiResponse<TimeEntryResponse>>> {
+    if let Some(ValidatedJson(note)) = &request {
+        validate_schema_version("time_entries", note.schema_version)?;
+    }
+    let running = TimeEntries::find()
+        .filter(time_entries::Column::UserId.eq(auth_user.0.id))
+        .filter(time_entries::Column::TaskId.eq(task_id))
+        .filter(time_entries::Column::StoppedAt.is_null())
+        .one(&app_state.db.connection)
+        .await
+        .map_err(|e| AppError::Database(e.into()))?
+        .ok_or_else(|| AppError::NotFound("No timer is running for this task".to_string()))?;
+
+    let mut entry_active: time_entries::ActiveModel = running.into();
+    entry_active.stopped_at = Set(Some(Utc::now().into()));
+    if let Some(ValidatedJson(note)) = request {
+        entry_active.encrypted_data = Set(Some(note.encrypted_data));
+        entry_active.iv = Set(Some(note.iv));
+        entry_active.salt = Set(Some(note.salt));
+        entry_active.schema_version = Set(note.schema_version.unwrap_or(DEFAULT_SCHEMA_VERSION));
+    }
+
+    let entry = entry_active.update(&app_state.db.connection).await
+        .map_err(|e| AppError::Database(e.into()))?;
+
+    // Broadcast websocket message for the stopped timer
+    tracing::info!("Timer stopped, broadcasting websocket message for user {} (excluding connection {:?})", auth_user.0.id, connection_id);
+    app_state.ws_state
+        .broadcast_to_user(&auth_user.0.id, entry_message("UPDATE", auth_user.0.id, &entry), connection_id)
+        .await;
+
+    Ok(Json(ApiResponse::with_message(entry.into(), "Timer stopped successfully")))
+}
+
+pub async fn delete_time_entry(
+    State(app_state): State<AppState>,
+    auth_user: AuthUser,
+    OriginConnection(connection_id): OriginConnection,
+    Path(id): Path<Uuid>,
+    preconditions: Preconditions,
+) -> Result<Json<ApiResponse<()>>> {
+    let user_id = auth_user.0.id;
+    app_state.db.with_txn(|txn| Box::pin(async move {
+        let entry = TimeEntries::find_by_id(id)
+            .filter(time_entries::Column::UserId.eq(user_id))
+            .one(txn)
+            .await
+            .map_err(|e| AppError::Database(e.into()))?
+            .ok_or_else(|| AppError::NotFound("Time entry not found".to_string()))?;
+        preconditions.check(&entry.updated_at)?;
+        TimeEntries::delete_by_id(entry.id)
+            .exec(txn)
+            .await
+            .map_err(|e| AppError::Database(e.into()))?;
+        Ok(())
+    })).await?;
+
+    // Broadcast websocket message for time entry deletion
+    tracing::info!("Time entry deleted, broadcasting websocket message for user {} (excluding connection {:?})", auth_user.0.id, connection_id);
+    let ws_message = WebSocketMessage {
+        event_type: "DELETE".to_string(),
+        table: "time_entries".to_string(),
+        user_id: auth_user.0.id,
+        record_id: Some(id),
+        data: None,
+    };
+    app_state.ws_state.broadcast_to_user(&auth_user.0.id, ws_message, connection_id).await;
+
+    Ok(Json(ApiResponse::with_message((), "Time entry deleted successfully")))
+}
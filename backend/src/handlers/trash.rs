@@ -0,0 +1,79 @@
+use axum::{
+    extract::State,
+    response::Json,
+};
+use sea_orm::*;
+use serde::Deserialize;
+
+use crate::{
+    entities::{prelude::*, calendar_events, calendars, can_do_list, notes, projects},
+    errors::Result,
+    middleware::auth::AuthUser,
+    models::{trash::TrashResponse, ApiResponse},
+    state::AppState,
+};
+
+/// Query parameters accepted by every DELETE handler
+#[derive(Debug, Deserialize)]
+pub struct DeleteQuery {
+    /// Skip the trash and remove the record immediately
+    pub permanent: Option<bool>,
+}
+
+pub async fn list_trash(
+    State(app_state): State<AppState>,
+    auth_user: AuthUser,
+) -> Result<Json<ApiResponse<TrashResponse>>> {
+    let user_id = auth_user.0.id;
+    let db = app_state.db.reader();
+
+    let projects = Projects::find()
+        .filter(projects::Column::UserId.eq(user_id))
+        .filter(projects::Column::DeletedAt.is_not_null())
+        .order_by_desc(projects::Column::DeletedAt)
+        .all(db)
+        .await
+        .map_err(|e| crate::errors::AppError::Database(e.into()))?;
+
+    let items = CanDoList::find()
+        .filter(can_do_list::Column::UserId.eq(user_id))
+        .filter(can_do_list::Column::DeletedAt.is_not_null())
+        .order_by_desc(can_do_list::Column::DeletedAt)
+        .all(db)
+        .await
+        .map_err(|e| crate::errors::AppError::Database(e.into()))?;
+
+    let calendars = Calendars::find()
+        .filter(calendars::Column::UserId.eq(user_id))
+        .filter(calendars::Column::DeletedAt.is_not_null())
+        .order_by_desc(calendars::Column::DeletedAt)
+        .all(db)
+        .await
+        .map_err(|e| crate::errors::AppError::Database(e.into()))?;
+
+    let events = CalendarEvents::find()
+        .filter(calendar_events::Column::UserId.eq(user_id))
+        .filter(calendar_events::Column::DeletedAt.is_not_null())
+        .order_by_desc(calendar_events::Column::DeletedAt)
+        .all(db)
+        .await
+        .map_err(|e| crate::errors::AppError::Database(e.into()))?;
+
+    let notes = Notes::find()
+        .filter(notes::Column::UserId.eq(user_id))
+        .filter(notes::Column::DeletedAt.is_not_null())
+        .order_by_desc(notes::Column::DeletedAt)
+        .all(db)
+        .await
+        .map_err(|e| crate::errors::AppError::Database(e.into()))?;
+
+    let response = TrashResponse {
+        projects: projects.into_iter().map(|p| p.into()).collect(),
+        can_do_list: items.into_iter().map(|item| item.into()).collect(),
+        calendars: calendars.into_iter().map(|calendar| calendar.into()).collect(),
+        calendar_events: events.into_iter().map(|event| event.into()).collect(),
+        notes: notes.into_iter().map(|note| note.into()).collect(),
+    };
+
+    Ok(Json(ApiResponse::new(response)))
+}
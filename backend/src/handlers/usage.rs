@@ -0,0 +1,48 @@
+use axum::{
+    extract::State,
+    response::Json,
+};
+use uuid::Uuid;
+
+use crate::{
+    errors::Result,
+    middleware::auth::AuthUser,
+    models::{usage::{TableUsage, UsageResponse}, ApiResponse},
+    quota::{QuotaTable, TableStats},
+    state::AppState,
+};
+
+async fn table_usage(app_state: &AppState, user_id: Uuid, table: QuotaTable) -> Result<TableUsage> {
+    let stats = TableStats::load(&app_state.db.connection, user_id, table).await?;
+
+    Ok(TableUsage {
+        records: stats.records as u64,
+        trashed: stats.trashed as u64,
+        bytes: stats.bytes as u64,
+        quota: app_state.quotas.limit(table),
+    })
+}
+
+/// Record counts, storage size and configured quotas for the authenticated user
+pub async fn get_usage(
+    State(app_state): State<AppState>,
+    auth_user: AuthUser,
+) -> Result<Json<ApiResponse<UsageResponse>>> {
+    let user_id = auth_user.0.id;
+
+    let projects = table_usage(&app_state, user_id, QuotaTable::Projects).await?;
+    let can_do_list = table_usage(&app_state, user_id, QuotaTable::CanDoList).await?;
+    let calendars = table_usage(&app_state, user_id, QuotaTable::Calendars).await?;
+    let calendar_events = table_usage(&app_state, user_id, QuotaTable::CalendarEvents).await?;
+    let notes = table_usage(&app_state, user_id, QuotaTable::Notes).await?;
+    let total_bytes = projects.bytes + can_do_list.bytes + calendars.bytes + calendar_events.bytes + notes.bytes;
+
+    Ok(Json(ApiResponse::new(UsageResponse {
+        projects,
+        can_do_list,
+        calendars,
+        calendar_events,
+        notes,
+        total_bytes,
+    })))
+}
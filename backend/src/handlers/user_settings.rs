@@ -1,23 +1,56 @@
+use std::collections::HashMap;
+
 use axum::{
     extract::State,
     response::Json,
 };
-use sea_orm::{ActiveModelTrait, ActiveValue, EntityTrait, QueryFilter, ColumnTrait};
+use chrono::{DateTime, Utc};
+use sea_orm::{sea_query::OnConflict, ActiveModelTrait, ActiveValue, ConnectionTrait, EntityTrait, QueryFilter, ColumnTrait};
 use serde::{Deserialize, Serialize};
+use uuid::Uuid;
 
 use crate::{
-    entities::{prelude::*, user_settings},
-    errors::Result,
-    middleware::auth::AuthUser,
-    models::ApiResponse,
+    entities::{prelude::*, notification_preferences, settings, user_settings},
+    errors::{AppError, FieldError, Result},
+    handlers::settings::{find_setting, save_setting, setting_message},
+    middleware::{auth::AuthUser, connection::OriginConnection, preconditions::Preconditions, validation::ValidatedJson},
+    models::{
+        notification_preferences::{
+            default_preferences, next_digest_at, NotificationPreferencesResponse, UpdateNotificationPreferencesRequest,
+        },
+        setting::{SaveSettingRequest, GENERAL_NAMESPACE},
+        user_settings::{validate_timezone, validate_week_start, TimePreferences, DEFAULT_TIMEZONE, DEFAULT_WEEK_START},
+        validate_schema_version,
+        validation::{collect, Validate},
+        ApiResponse,
+    },
     state::AppState,
 };
+use streamline_models::DEFAULT_SCHEMA_VERSION;
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct UserSettingsRequest {
     pub encrypted_data: String,
     pub iv: String,
     pub salt: String,
+    /// Format version of `encrypted_data`; 1 when omitted
+    pub schema_version: Option<i16>,
+    /// IANA name, e.g. `Europe/Berlin`; unchanged when omitted
+    pub timezone: Option<String>,
+    /// ISO weekday weeks start on, 1 (Monday) to 7 (Sunday); unchanged when omitted
+    pub week_start: Option<i16>,
+}
+
+impl Validate for UserSettingsRequest {
+    fn validate(&self, errors: &mut Vec<FieldError>) {
+        collect(errors, "schema_version", validate_schema_version("user_settings", self.schema_version));
+        if let Some(timezone) = &self.timezone {
+            validate_timezone("timezone", timezone, errors);
+        }
+        if let Some(week_start) = self.week_start {
+            validate_week_start(week_start, errors);
+        }
+    }
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -25,86 +58,266 @@ pub struct UserSettingsResponse {
     pub encrypted_data: String,
     pub iv: String,
     pub salt: String,
+    pub schema_version: i16,
+    pub timezone: String,
+    pub week_start: i16,
+    /// When either the time preferences or the general namespace last changed; unset while
+    /// neither was saved
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub updated_at: Option<DateTime<Utc>>,
+}
+
+impl UserSettingsResponse {
+    /// The time preferences together with the general settings namespace, which holds what used
+    /// to be the single settings blob
+    pub fn new(settings: Option<user_settings::Model>, general: Option<settings::Model>) -> Self {
+        let updated_at = last_updated(settings.as_ref(), general.as_ref());
+        let (encrypted_data, iv, salt, schema_version) = match general {
+            Some(general) => (general.encrypted_data, general.iv, general.salt, general.schema_version),
+            None => (String::from("{}"), String::new(), String::new(), DEFAULT_SCHEMA_VERSION),
+        };
+        Self {
+            encrypted_data,
+            iv,
+            salt,
+            schema_version,
+            timezone: settings.as_ref().map_or_else(|| DEFAULT_TIMEZONE.to_string(), |settings| settings.timezone.clone()),
+            week_start: settings.map_or(DEFAULT_WEEK_START, |settings| settings.week_start),
+            updated_at,
+        }
+    }
+}
+
+/// The later `updated_at` of the two records user settings are made of
+fn last_updated(settings: Option<&user_settings::Model>, general: Option<&settings::Model>) -> Option<DateTime<Utc>> {
+    let settings = settings.map(|settings| settings.updated_at.naive_utc().and_utc());
+    let general = general.map(|general| general.updated_at.naive_utc().and_utc());
+    settings.max(general)
+}
+
+/// The user's timezone and week start, the defaults if they never saved settings
+pub(crate) async fn time_preferences<C: ConnectionTrait>(db: &C, user_id: Uuid) -> Result<TimePreferences> {
+    let settings = UserSettings::find_by_id(user_id)
+        .one(db)
+        .await
+        .map_err(|e| AppError::Database(e.into()))?;
+    Ok(settings.as_ref().map(TimePreferences::from).unwrap_or_default())
 }
 
-/// Get user settings
+/// Time preferences of several users at once; users without settings are left out
+pub(crate) async fn time_preferences_of<C: ConnectionTrait>(
+    db: &C,
+    user_ids: impl IntoIterator<Item = Uuid>,
+) -> Result<HashMap<Uuid, TimePreferences>> {
+    let settings = UserSettings::find()
+        .filter(user_settings::Column::UserId.is_in(user_ids))
+        .all(db)
+        .await
+        .map_err(|e| AppError::Database(e.into()))?;
+    Ok(settings.iter().map(|settings| (settings.user_id, TimePreferences::from(settings))).collect())
+}
+
+/// Set the user's timezone, creating their time preferences if they have none yet
+async fn save_timezone<C: ConnectionTrait>(db: &C, user_id: Uuid, timezone: &str) -> Result<()> {
+    let now = chrono::Utc::now().into();
+    let settings_active = user_settings::ActiveModel {
+        user_id: ActiveValue::Set(user_id),
+        encrypted_data: ActiveValue::Set(String::from("{}")),
+        iv: ActiveValue::Set(String::new()),
+        salt: ActiveValue::Set(String::new()),
+        schema_version: ActiveValue::Set(DEFAULT_SCHEMA_VERSION),
+        timezone: ActiveValue::Set(timezone.to_string()),
+        week_start: ActiveValue::Set(DEFAULT_WEEK_START),
+        created_at: ActiveValue::Set(now),
+        updated_at: ActiveValue::Set(now),
+    };
+    UserSettings::insert(settings_active)
+        .on_conflict(
+            OnConflict::column(user_settings::Column::UserId)
+                .update_columns([user_settings::Column::Timezone, user_settings::Column::UpdatedAt])
+                .to_owned(),
+        )
+        .exec_without_returning(db)
+        .await
+        .map_err(|e| AppError::Database(e.into()))?;
+    Ok(())
+}
+
+/// Move the next digest to the new timezone. The preferences' own copy of the timezone is
+/// kept in step for replicas still reading it.
+async fn reschedule_digest<C: ConnectionTrait>(db: &C, user_id: Uuid, preferences: &TimePreferences) -> Result<()> {
+    let Some(existing) = NotificationPreferences::find_by_id(user_id)
+        .one(db)
+        .await
+        .map_err(|e| AppError::Database(e.into()))?
+    else {
+        return Ok(());
+    };
+    let next_digest_at = next_digest_at(&existing, preferences.timezone, chrono::Utc::now()).map(Into::into);
+    let mut preferences_active: notification_preferences::ActiveModel = existing.into();
+    preferences_active.timezone = ActiveValue::Set(preferences.timezone.name().to_string());
+    preferences_active.next_digest_at = ActiveValue::Set(next_digest_at);
+    preferences_active.update(db).await
+        .map_err(|e| AppError::Database(e.into()))?;
+    Ok(())
+}
+
+/// Get user settings: the time preferences and the general settings namespace
 pub async fn get_user_settings(
     State(app_state): State<AppState>,
     auth_user: AuthUser,
 ) -> Result<Json<ApiResponse<UserSettingsResponse>>> {
-    // Try to find existing settings
-    let settings = UserSettings::find()
-        .filter(user_settings::Column::UserId.eq(auth_user.0.id))
-        .one(&app_state.db.connection)
-        .await?;
+    let db = app_state.db.reader();
+    let settings = UserSettings::find_by_id(auth_user.0.id)
+        .one(db)
+        .await
+        .map_err(|e| AppError::Database(e.into()))?;
+    let general = find_setting(db, auth_user.0.id, GENERAL_NAMESPACE).await?;
 
-    let response = match settings {
-        Some(settings) => UserSettingsResponse {
-            encrypted_data: settings.encrypted_data,
-            iv: settings.iv,
-            salt: settings.salt,
-        },
-        None => {
-            // Return empty encrypted data if settings don't exist
-            UserSettingsResponse {
-                encrypted_data: String::from("{}"),
-                iv: String::new(),
-                salt: String::new(),
+    Ok(Json(ApiResponse::new(UserSettingsResponse::new(settings, general))))
+}
+
+/// Update user settings; the payload is saved as the general settings namespace
+pub async fn update_user_settings(
+    State(app_state): State<AppState>,
+    auth_user: AuthUser,
+    OriginConnection(connection_id): OriginConnection,
+    preconditions: Preconditions,
+    ValidatedJson(payload): ValidatedJson<UserSettingsRequest>,
+) -> Result<Json<ApiResponse<UserSettingsResponse>>> {
+    let timezone = payload.timezone.as_deref().map(|timezone| timezone.trim().to_string());
+    let week_start = payload.week_start;
+
+    let user_id = auth_user.0.id;
+    let (settings, general, created) = app_state.db.with_txn(|txn| Box::pin(async move {
+        let existing_settings = UserSettings::find_by_id(user_id)
+            .one(txn)
+            .await?;
+        let existing_general = find_setting(txn, user_id, GENERAL_NAMESPACE).await?;
+        if let Some(updated_at) = last_updated(existing_settings.as_ref(), existing_general.as_ref()) {
+            preconditions.check(&updated_at)?;
+        }
+
+        let now = chrono::Utc::now().into();
+        let previous_timezone = existing_settings.as_ref().map(|existing| existing.timezone.clone());
+
+        let settings = match existing_settings {
+            Some(existing) => {
+                let mut active_model: user_settings::ActiveModel = existing.into();
+                if let Some(timezone) = timezone.clone() {
+                    active_model.timezone = ActiveValue::Set(timezone);
+                }
+                if let Some(week_start) = week_start {
+                    active_model.week_start = ActiveValue::Set(week_start);
+                }
+                // Touched either way so delta syncs pick the settings up again
+                active_model.updated_at = ActiveValue::Set(now);
+                active_model.update(txn).await?
             }
+            None => {
+                // The payload columns predate namespaces and stay empty
+                let active_model = user_settings::ActiveModel {
+                    user_id: ActiveValue::Set(user_id),
+                    encrypted_data: ActiveValue::Set(String::from("{}")),
+                    iv: ActiveValue::Set(String::new()),
+                    salt: ActiveValue::Set(String::new()),
+                    schema_version: ActiveValue::Set(DEFAULT_SCHEMA_VERSION),
+                    timezone: ActiveValue::Set(timezone.clone().unwrap_or_else(|| DEFAULT_TIMEZONE.to_string())),
+                    week_start: ActiveValue::Set(week_start.unwrap_or(DEFAULT_WEEK_START)),
+                    created_at: ActiveValue::Set(now),
+                    updated_at: ActiveValue::Set(now),
+                };
+                active_model.insert(txn).await?
+            }
+        };
+
+        if previous_timezone.as_deref().unwrap_or(DEFAULT_TIMEZONE) != settings.timezone {
+            reschedule_digest(txn, user_id, &TimePreferences::from(&settings)).await?;
         }
-    };
 
-    Ok(Json(ApiResponse {
-        data: response,
-        message: None,
-    }))
+        let general = SaveSettingRequest {
+            encrypted_data: payload.encrypted_data,
+            iv: payload.iv,
+            salt: payload.salt,
+            schema_version: payload.schema_version,
+        };
+        let (general, created) = save_setting(txn, user_id, GENERAL_NAMESPACE, general).await?;
+        Ok((settings, general, created))
+    })).await?;
+
+    // Broadcast websocket message for the general namespace
+    let event_type = if created { "INSERT" } else { "UPDATE" };
+    tracing::info!("Settings saved, broadcasting websocket message for user {} (excluding connection {:?})", user_id, connection_id);
+    app_state.ws_state.broadcast_to_user(&user_id, setting_message(event_type, user_id, &general), connection_id).await;
+
+    Ok(Json(ApiResponse::new(UserSettingsResponse::new(Some(settings), Some(general)))))
 }
 
-/// Update user settings
-pub async fn update_user_settings(
+/// Get the notification preferences, the defaults if the user never changed them
+pub async fn get_notification_preferences(
     State(app_state): State<AppState>,
     auth_user: AuthUser,
-    Json(payload): Json<UserSettingsRequest>,
-) -> Result<Json<ApiResponse<UserSettingsResponse>>> {
-    // Check if settings already exist
-    let existing_settings = UserSettings::find()
-        .filter(user_settings::Column::UserId.eq(auth_user.0.id))
+) -> Result<Json<ApiResponse<NotificationPreferencesResponse>>> {
+    let preferences = NotificationPreferences::find_by_id(auth_user.0.id)
         .one(&app_state.db.connection)
-        .await?;
+        .await
+        .map_err(|e| AppError::Database(e.into()))?
+        .unwrap_or_else(|| default_preferences(auth_user.0.id));
+    let time = time_preferences(&app_state.db.connection, auth_user.0.id).await?;
 
-    let now = chrono::Utc::now().into();
+    Ok(Json(ApiResponse::new(NotificationPreferencesResponse {
+        timezone: time.timezone.name().to_string(),
+        ..preferences.into()
+    })))
+}
 
-    let settings = match existing_settings {
-        Some(existing) => {
-            // Update existing settings
-            let mut active_model: user_settings::ActiveModel = existing.into();
-            active_model.encrypted_data = ActiveValue::Set(payload.encrypted_data.clone());
-            active_model.iv = ActiveValue::Set(payload.iv.clone());
-            active_model.salt = ActiveValue::Set(payload.salt.clone());
-            active_model.updated_at = ActiveValue::Set(now);
-            active_model.update(&app_state.db.connection).await?
+/// Update the notification preferences; the next digest is rescheduled to match
+pub async fn update_notification_preferences(
+    State(app_state): State<AppState>,
+    auth_user: AuthUser,
+    preconditions: Preconditions,
+    ValidatedJson(request): ValidatedJson<UpdateNotificationPreferencesRequest>,
+) -> Result<Json<ApiResponse<NotificationPreferencesResponse>>> {
+    let user_id = auth_user.0.id;
+    let preferences = app_state.db.with_txn(|txn| Box::pin(async move {
+        let stored = NotificationPreferences::find_by_id(user_id)
+            .one(txn)
+            .await
+            .map_err(|e| AppError::Database(e.into()))?;
+        // Defaults are new on every read, so only stored preferences can be out of date
+        if let Some(stored) = &stored {
+            preconditions.check(&stored.updated_at)?;
         }
-        None => {
-            // Create new settings
-            let active_model = user_settings::ActiveModel {
-                user_id: ActiveValue::Set(auth_user.0.id),
-                encrypted_data: ActiveValue::Set(payload.encrypted_data.clone()),
-                iv: ActiveValue::Set(payload.iv.clone()),
-                salt: ActiveValue::Set(payload.salt.clone()),
-                created_at: ActiveValue::Set(now),
-                updated_at: ActiveValue::Set(now),
-            };
-            active_model.insert(&app_state.db.connection).await?
+        let mut preferences = stored.unwrap_or_else(|| default_preferences(user_id));
+        // The timezone is the one in the user settings; setting it here is still supported
+        let timezone_given = request.timezone.is_some();
+        request.apply_to(&mut preferences)?;
+        if timezone_given {
+            save_timezone(txn, user_id, &preferences.timezone).await?;
         }
-    };
+        let time = time_preferences(txn, user_id).await?;
+        preferences.timezone = time.timezone.name().to_string();
+        preferences.next_digest_at = next_digest_at(&preferences, time.timezone, chrono::Utc::now()).map(Into::into);
+        preferences.updated_at = chrono::Utc::now().into();
 
-    Ok(Json(ApiResponse {
-        data: UserSettingsResponse {
-            encrypted_data: settings.encrypted_data,
-            iv: settings.iv,
-            salt: settings.salt,
-        },
-        message: None,
-    }))
-}
+        let preferences_active: notification_preferences::ActiveModel = preferences.into();
+        NotificationPreferences::insert(preferences_active.reset_all())
+            .on_conflict(
+                OnConflict::column(notification_preferences::Column::UserId)
+                    .update_columns([
+                        notification_preferences::Column::Timezone,
+                        notification_preferences::Column::DigestFrequency,
+                        notification_preferences::Column::DigestHour,
+                        notification_preferences::Column::DigestWeekday,
+                        notification_preferences::Column::NextDigestAt,
+                        notification_preferences::Column::UpdatedAt,
+                    ])
+                    .to_owned(),
+            )
+            .exec_with_returning(txn)
+            .await
+            .map_err(|e| AppError::Database(e.into()))
+    })).await?;
 
+    Ok(Json(ApiResponse::with_message(preferences.into(), "Notification preferences updated successfully")))
+}
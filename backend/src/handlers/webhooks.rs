@@ -0,0 +1,256 @@
+use axum::{
+    extract::{Path, Query, State},
+    response::Json,
+};
+use chrono::{DateTime, Duration, Utc};
+use sea_orm::*;
+use uuid::Uuid;
+
+use crate::{
+    entities::{prelude::*, webhook_deliveries, webhooks},
+    errors::{AppError, Result},
+    middleware::{auth::AuthUser, connection::OriginConnection, preconditions::Preconditions, validation::ValidatedJson},
+    models::{
+        webhook::{
+            rotate_secret, CreateWebhookRequest, RotateWebhookSecretRequest, UpdateWebhookRequest,
+            VerifyWebhookSignatureRequest, WebhookDeliveryResponse, WebhookResponse, WebhookSignatureResponse,
+            DEFAULT_GRACE_PERIOD_MINUTES,
+        },
+        ApiResponse, PageQuery,
+    },
+    state::AppState,
+    webhooks::{signing_secrets, verify},
+    websocket::WebSocketMessage,
+};
+
+/// Deliveries returned by the delivery log, newest first
+const DELIVERY_LOG_LIMIT: u64 = 50;
+
+/// Change message for the user's other clients; the secret is never broadcast
+fn webhook_message(event_type: &str, webhook: &webhooks::Model) -> WebSocketMessage {
+    WebSocketMessage {
+        event_type: event_type.to_string(),
+        table: "webhooks".to_string(),
+        user_id: webhook.user_id,
+        record_id: Some(webhook.id),
+        data: Some(serde_json::to_value(WebhookResponse::from(webhook.clone())).unwrap_or_default()),
+    }
+}
+
+async fn find_webhook<C: ConnectionTrait>(db: &C, user_id: Uuid, id: Uuid) -> Result<webhooks::Model> {
+    Webhooks::find_by_id(id)
+        .filter(webhooks::Column::UserId.eq(user_id))
+        .one(db)
+        .await
+        .map_err(|e| AppError::Database(e.into()))?
+        .ok_or_else(|| AppError::NotFound("Webhook not found".to_string()))
+}
+
+pub async fn list_webhooks(
+    State(app_state): State<AppState>,
+    auth_user: AuthUser,
+    Query(paging): Query<PageQuery>,
+) -> Result<Json<ApiResponse<Vec<WebhookResponse>>>> {
+    let find = Webhooks::find()
+        .filter(webhooks::Column::UserId.eq(auth_user.0.id))
+        .order_by_asc(webhooks::Column::CreatedAt);
+    let (webhooks, meta) = paging.fetch(&app_state.db.connection, find).await?;
+
+    Ok(Json(ApiResponse::list(webhooks.into_iter().map(Into::into).collect(), meta)))
+}
+
+pub async fn get_webhook(
+    State(app_state): State<AppState>,
+    auth_user: AuthUser,
+    Path(id): Path<Uuid>,
+) -> Result<Json<ApiResponse<WebhookResponse>>> {
+    let webhook = find_webhook(&app_state.db.connection, auth_user.0.id, id).await?;
+    Ok(Json(ApiResponse::new(webhook.into())))
+}
+
+/// Register a webhook; the response carries the signing secret, which isn't shown again
+pub async fn create_webhook(
+    State(app_state): State<AppState>,
+    auth_user: AuthUser,
+    OriginConnection(connection_id): OriginConnection,
+    ValidatedJson(request): ValidatedJson<CreateWebhookRequest>,
+) -> Result<Json<ApiResponse<WebhookResponse>>> {
+    let webhook = request
+        .webhook_active_model(auth_user.0.id)?
+        .insert(&app_state.db.connection)
+        .await
+        .map_err(|e| AppError::Database(e.into()))?;
+
+    // Broadcast websocket message for webhook creation
+    tracing::info!("Webhook created, broadcasting websocket message for user {} (excluding connection {:?})", auth_user.0.id, connection_id);
+    app_state.ws_state
+        .broadcast_to_user(&auth_user.0.id, webhook_message("INSERT", &webhook), connection_id)
+        .await;
+
+    Ok(Json(ApiResponse::with_message(WebhookResponse::with_secret(webhook), "Webhook created successfully")))
+}
+
+pub async fn update_webhook(
+    State(app_state): State<AppState>,
+    auth_user: AuthUser,
+    OriginConnection(connection_id): OriginConnection,
+    Path(id): Path<Uuid>,
+    preconditions: Preconditions,
+    ValidatedJson(request): ValidatedJson<UpdateWebhookRequest>,
+) -> Result<Json<ApiResponse<WebhookResponse>>> {
+    let user_id = auth_user.0.id;
+    let rotated = request.rotate_secret;
+    let webhook = app_state.db.with_txn(|txn| Box::pin(async move {
+        let webhook = find_webhook(txn, user_id, id).await?;
+        preconditions.check(&webhook.updated_at)?;
+
+        let mut webhook_active: webhooks::ActiveModel = webhook.into();
+        request.apply_to(&mut webhook_active)?;
+        webhook_active.update(txn).await
+            .map_err(|e| AppError::Database(e.into()))
+    })).await?;
+
+    // Broadcast websocket message for webhook update
+    tracing::info!("Webhook updated, broadcasting websocket message for user {} (excluding connection {:?})", auth_user.0.id, connection_id);
+    app_state.ws_state
+        .broadcast_to_user(&auth_user.0.id, webhook_message("UPDATE", &webhook), connection_id)
+        .await;
+
+    let response = if rotated { WebhookResponse::with_secret(webhook) } else { webhook.into() };
+    Ok(Json(ApiResponse::with_message(response, "Webhook updated successfully")))
+}
+
+/// Delete a webhook; its pending deliveries go with it
+pub async fn delete_webhook(
+    State(app_state): State<AppState>,
+    auth_user: AuthUser,
+    OriginConnection(connection_id): OriginConnection,
+    Path(id): Path<Uuid>,
+    preconditions: Preconditions,
+) -> Result<Json<ApiResponse<()>>> {
+    let webhook = find_webhook(&app_state.db.connection, auth_user.0.id, id).await?;
+    preconditions.check(&webhook.updated_at)?;
+    Webhooks::delete_by_id(webhook.id)
+        .exec(&app_state.db.connection)
+        .await
+        .map_err(|e| AppError::Database(e.into()))?;
+
+    // Broadcast websocket message for webhook deletion
+    tracing::info!("Webhook deleted, broadcasting websocket message for user {} (excluding connection {:?})", auth_user.0.id, connection_id);
+    let ws_message = WebSocketMessage {
+        event_type: "DELETE".to_string(),
+        table: "webhooks".to_string(),
+        user_id: auth_user.0.id,
+        record_id: Some(webhook.id),
+        data: None,
+    };
+    app_state.ws_state.broadcast_to_user(&auth_user.0.id, ws_message, connection_id).await;
+
+    Ok(Json(ApiResponse::with_message((), "Webhook deleted successfully")))
+}
+
+/// Replace the signing secret. For a grace period deliveries carry a signature made with the
+/// old secret too, so receivers can switch over without rejecting any.
+pub async fn rotate_webhook_secret(
+    State(app_state): State<AppState>,
+    auth_user: AuthUser,
+    OriginConnection(connection_id): OriginConnection,
+    Path(id): Path<Uuid>,
+    preconditions: Preconditions,
+    ValidatedJson(request): ValidatedJson<RotateWebhookSecretRequest>,
+) -> Result<Json<ApiResponse<WebhookResponse>>> {
+    let user_id = auth_user.0.id;
+    let grace_period = Duration::minutes(request.grace_period_minutes.unwrap_or(DEFAULT_GRACE_PERIOD_MINUTES));
+    let webhook = app_state.db.with_txn(|txn| Box::pin(async move {
+        let webhook = find_webhook(txn, user_id, id).await?;
+        preconditions.check(&webhook.updated_at)?;
+
+        let previous = (!grace_period.is_zero()).then(|| (webhook.secret.clone(), Utc::now() + grace_period));
+        let mut webhook_active: webhooks::ActiveModel = webhook.into();
+        rotate_secret(&mut webhook_active, previous);
+        webhook_active.update(txn).await
+            .map_err(|e| AppError::Database(e.into()))
+    })).await?;
+
+    // Broadcast websocket message for webhook update
+    tracing::info!("Webhook secret rotated, broadcasting websocket message for user {} (excluding connection {:?})", user_id, connection_id);
+    app_state.ws_state
+        .broadcast_to_user(&user_id, webhook_message("UPDATE", &webhook), connection_id)
+        .await;
+
+    Ok(Json(ApiResponse::with_message(WebhookResponse::with_secret(webhook), "Webhook secret rotated successfully")))
+}
+
+/// Check a signature header against the webhook's secrets, for integrators debugging their
+/// verification code
+pub async fn verify_webhook_signature(
+    State(app_state): State<AppState>,
+    auth_user: AuthUser,
+    Path(id): Path<Uuid>,
+    ValidatedJson(request): ValidatedJson<VerifyWebhookSignatureRequest>,
+) -> Result<Json<ApiResponse<WebhookSignatureResponse>>> {
+    let webhook = find_webhook(&app_state.db.connection, auth_user.0.id, id).await?;
+    let secrets = signing_secrets(&webhook, Utc::now());
+    let checked = verify(&secrets, &request.signature, request.payload.as_bytes());
+
+    let matched = checked.and_then(|(_, matched)| matched);
+    Ok(Json(ApiResponse::new(WebhookSignatureResponse {
+        valid: matched.is_some(),
+        signed_at: checked.and_then(|(timestamp, _)| DateTime::from_timestamp(timestamp, 0)),
+        secret: matched.map(|index| if index == 0 { "current" } else { "previous" }),
+    })))
+}
+
+/// Send a finished delivery again, e.g. after fixing the receiver. It keeps its id and payload
+/// and starts over with a fresh set of attempts.
+pub async fn redeliver(
+    State(app_state): State<AppState>,
+    auth_user: AuthUser,
+    Path((id, delivery_id)): Path<(Uuid, Uuid)>,
+) -> Result<Json<ApiResponse<WebhookDeliveryResponse>>> {
+    let user_id = auth_user.0.id;
+    let delivery = app_state.db.with_txn(|txn| Box::pin(async move {
+        let webhook = find_webhook(txn, user_id, id).await?;
+        let delivery = WebhookDeliveries::find_by_id(delivery_id)
+            .filter(webhook_deliveries::Column::WebhookId.eq(webhook.id))
+            .lock_exclusive()
+            .one(txn)
+            .await
+            .map_err(|e| AppError::Database(e.into()))?
+            .ok_or_else(|| AppError::NotFound("Delivery not found".to_string()))?;
+        if !webhook.is_active {
+            return Err(AppError::Conflict("The webhook is paused".to_string()));
+        }
+        if delivery.next_attempt_at.is_some() {
+            return Err(AppError::Conflict("The delivery is still being attempted".to_string()));
+        }
+
+        let mut delivery_active: webhook_deliveries::ActiveModel = delivery.into();
+        delivery_active.attempts = Set(0);
+        delivery_active.next_attempt_at = Set(Some(Utc::now().into()));
+        delivery_active.update(txn).await
+            .map_err(|e| AppError::Database(e.into()))
+    })).await?;
+
+    Ok(Json(ApiResponse::with_message(delivery.into(), "Delivery queued")))
+}
+
+/// Recent deliveries of a webhook with their outcome, for debugging a receiver
+pub async fn list_deliveries(
+    State(app_state): State<AppState>,
+    auth_user: AuthUser,
+    Path(id): Path<Uuid>,
+    Query(paging): Query<PageQuery>,
+) -> Result<Json<ApiResponse<Vec<WebhookDeliveryResponse>>>> {
+    let webhook = find_webhook(&app_state.db.connection, auth_user.0.id, id).await?;
+    let deliveries = WebhookDeliveries::find()
+        .filter(webhook_deliveries::Column::WebhookId.eq(webhook.id))
+        .order_by_desc(webhook_deliveries::Column::CreatedAt)
+        .limit(DELIVERY_LOG_LIMIT)
+        .all(&app_state.db.connection)
+        .await
+        .map_err(|e| AppError::Database(e.into()))?;
+
+    let (response, meta) = paging.page(deliveries.into_iter().map(Into::into).collect())?;
+    Ok(Json(ApiResponse::list(response, meta)))
+}
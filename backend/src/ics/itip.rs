@@ -0,0 +1,244 @@
+//! iTIP (RFC 5546) invitations for people outside the app: a REQUEST that goes out by email
+//! and the REPLY their calendar app sends back.
+
+use chrono::{DateTime, NaiveDate, Utc};
+use ical::IcalParser;
+use uuid::Uuid;
+
+use super::{find, param};
+use crate::{
+    entities::event_attendees::RsvpStatus,
+    errors::{AppError, Result},
+};
+
+/// Longest content line before it is folded (RFC 5545 section 3.1)
+const MAX_LINE_OCTETS: usize = 75;
+
+/// UID of an event in invitations, the same for every attendee so replies name the event
+pub fn event_uid(event_id: Uuid) -> String {
+    format!("{}@streamline-scheduler", event_id)
+}
+
+/// Someone on an invitation
+pub struct Party {
+    pub name: Option<String>,
+    pub email: String,
+}
+
+/// What an invitation says. The event itself is encrypted, so everything but its times is what
+/// the organizer's client chose to disclose for the invitation.
+pub struct Invitation {
+    pub event_id: Uuid,
+    pub start: DateTime<Utc>,
+    pub end: DateTime<Utc>,
+    /// First and last day, for all-day events
+    pub days: Option<(NaiveDate, NaiveDate)>,
+    pub rrule: Option<String>,
+    pub summary: String,
+    pub location: Option<String>,
+    pub organizer: Party,
+    pub attendee: Party,
+}
+
+impl Invitation {
+    /// A METHOD:REQUEST calendar asking the attendee to respond
+    pub fn to_request(&self, now: DateTime<Utc>) -> String {
+        let mut lines = vec![
+            "BEGIN:VCALENDAR".to_string(),
+            "VERSION:2.0".to_string(),
+            "PRODID:-//Streamline Scheduler//EN".to_string(),
+            "METHOD:REQUEST".to_string(),
+            "BEGIN:VEVENT".to_string(),
+            format!("UID:{}", event_uid(self.event_id)),
+            format!("DTSTAMP:{}", utc(now)),
+            "SEQUENCE:0".to_string(),
+        ];
+        match self.days {
+            // DTEND of a DATE event is exclusive
+            Some((first_day, last_day)) => {
+                lines.push(format!("DTSTART;VALUE=DATE:{}", first_day.format("%Y%m%d")));
+                lines.push(format!("DTEND;VALUE=DATE:{}", last_day.succ_opt().unwrap_or(last_day).format("%Y%m%d")));
+            }
+            None => {
+                lines.push(format!("DTSTART:{}", utc(self.start)));
+                lines.push(format!("DTEND:{}", utc(self.end)));
+            }
+        }
+        if let Some(rrule) = &self.rrule {
+            lines.push(format!("RRULE:{}", rrule.replace(['\r', '\n'], "")));
+        }
+        lines.push(format!("SUMMARY:{}", escape(&self.summary)));
+        if let Some(location) = &self.location {
+            lines.push(format!("LOCATION:{}", escape(location)));
+        }
+        lines.push(format!("ORGANIZER{}:mailto:{}", common_name(&self.organizer), address(&self.organizer.email)));
+        lines.push(format!(
+            "ATTENDEE{};ROLE=REQ-PARTICIPANT;PARTSTAT=NEEDS-ACTION;RSVP=TRUE:mailto:{}",
+            common_name(&self.attendee),
+            address(&self.attendee.email),
+        ));
+        lines.push("END:VEVENT".to_string());
+        lines.push("END:VCALENDAR".to_string());
+
+        lines.iter().map(|line| fold(line)).map(|line| line + "\r\n").collect()
+    }
+}
+
+/// An attendee's answer to an invitation
+#[derive(Debug)]
+pub struct Reply {
+    pub uid: String,
+    /// Lowercase, without `mailto:`
+    pub attendee_email: String,
+    pub rsvp_status: RsvpStatus,
+}
+
+/// Read the answer from a METHOD:REPLY calendar
+pub fn parse_reply(body: &str) -> Result<Reply> {
+    let invalid = |message: &str| AppError::Validation(format!("Invalid invitation reply: {}", message));
+    let calendar = IcalParser::new(body.trim_start_matches('\u{feff}').as_bytes())
+        .next()
+        .ok_or_else(|| invalid("no calendar"))?
+        .map_err(|e| invalid(&e.to_string()))?;
+
+    let method = find(&calendar.properties, "METHOD").and_then(|property| property.value.as_deref());
+    if !method.is_some_and(|method| method.eq_ignore_ascii_case("REPLY")) {
+        return Err(invalid("METHOD is not REPLY"));
+    }
+    let event = calendar.events.first().ok_or_else(|| invalid("no event"))?;
+    let uid = find(&event.properties, "UID")
+        .and_then(|property| property.value.clone())
+        .ok_or_else(|| invalid("no UID"))?;
+    let attendee = find(&event.properties, "ATTENDEE").ok_or_else(|| invalid("no ATTENDEE"))?;
+    let value = attendee.value.as_deref().unwrap_or_default().trim();
+    let attendee_email = match value.get(..7) {
+        Some(scheme) if scheme.eq_ignore_ascii_case("mailto:") => &value[7..],
+        _ => value,
+    }
+    .to_ascii_lowercase();
+    if attendee_email.is_empty() {
+        return Err(invalid("ATTENDEE has no address"));
+    }
+    let rsvp_status = match param(attendee, "PARTSTAT").map(str::to_ascii_uppercase).as_deref() {
+        Some("ACCEPTED") => RsvpStatus::Accepted,
+        Some("DECLINED") => RsvpStatus::Declined,
+        Some("TENTATIVE") => RsvpStatus::Tentative,
+        Some("NEEDS-ACTION") => RsvpStatus::NeedsAction,
+        Some(other) => return Err(invalid(&format!("unsupported PARTSTAT {}", other))),
+        None => return Err(invalid("ATTENDEE has no PARTSTAT")),
+    };
+    Ok(Reply { uid, attendee_email, rsvp_status })
+}
+
+fn utc(at: DateTime<Utc>) -> String {
+    at.format("%Y%m%dT%H%M%SZ").to_string()
+}
+
+/// TEXT escaping (RFC 5545 section 3.3.11), the reverse of `unescape`
+fn escape(value: &str) -> String {
+    let mut result = String::with_capacity(value.len());
+    for c in value.chars() {
+        match c {
+            '\\' | ';' | ',' => {
+                result.push('\\');
+                result.push(c);
+            }
+            '\n' => result.push_str("\\n"),
+            '\r' => {}
+            c => result.push(c),
+        }
+    }
+    result
+}
+
+/// `;CN="..."` for a party with a name; parameter values can't hold quotes or line breaks
+fn common_name(party: &Party) -> String {
+    match party.name.as_deref().map(|name| name.replace(['"', '\r', '\n'], "")) {
+        Some(name) if !name.trim().is_empty() => format!(";CN=\"{}\"", name.trim()),
+        _ => String::new(),
+    }
+}
+
+fn address(email: &str) -> String {
+    email.chars().filter(|c| !c.is_whitespace() && !c.is_control()).collect()
+}
+
+/// Split a content line into lines of at most 75 octets, continued with a leading space
+fn fold(line: &str) -> String {
+    let mut folded = String::with_capacity(line.len() + line.len() / MAX_LINE_OCTETS * 3);
+    let mut octets = 0;
+    for c in line.chars() {
+        if octets + c.len_utf8() > MAX_LINE_OCTETS {
+            folded.push_str("\r\n ");
+            octets = 1;
+        }
+        folded.push(c);
+        octets += c.len_utf8();
+    }
+    folded
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{DateTime, Utc};
+    use ical::IcalParser;
+    use uuid::Uuid;
+
+    use super::{escape, fold, Invitation, Party, MAX_LINE_OCTETS};
+    use crate::ics::{find, unescape};
+
+    fn invitation(summary: &str, organizer_name: &str) -> Invitation {
+        let start: DateTime<Utc> = "2025-01-06T09:00:00Z".parse().unwrap();
+        Invitation {
+            event_id: Uuid::now_v7(),
+            start,
+            end: start + chrono::Duration::hours(1),
+            days: None,
+            rrule: None,
+            summary: summary.to_string(),
+            location: Some("Room 1; 2nd floor, east".to_string()),
+            organizer: Party { name: Some(organizer_name.to_string()), email: "organizer@example.com".to_string() },
+            attendee: Party { name: None, email: " guest@example.com\r\n".to_string() },
+        }
+    }
+
+    #[test]
+    fn text_is_escaped_and_unescapes_to_itself() {
+        assert_eq!(escape(r"a\b;c,d"), r"a\\b\;c\,d");
+        assert_eq!(escape("line one\r\nline two"), r"line one\nline two");
+        let text = "Plan; budget, Q1\\Q2\nnext steps";
+        assert_eq!(unescape(&escape(text)), text);
+    }
+
+    #[test]
+    fn long_lines_are_folded_without_splitting_characters() {
+        let short = "SUMMARY:Lunch";
+        assert_eq!(fold(short), short);
+
+        let line = format!("SUMMARY:{}", "Grüße aus München ".repeat(10));
+        let folded = fold(&line);
+        let physical: Vec<&str> = folded.split("\r\n").collect();
+        assert!(physical.len() > 2);
+        assert!(physical.iter().all(|part| part.len() <= MAX_LINE_OCTETS), "{:?}", physical);
+        assert!(physical[1..].iter().all(|part| part.starts_with(' ')));
+        // Unfolding removes the line break and the space after it
+        assert_eq!(folded.replace("\r\n ", ""), line);
+    }
+
+    #[test]
+    fn requests_keep_user_text_inside_its_properties() {
+        let summary = format!("Kickoff\r\nATTENDEE:mailto:intruder@example.com {}", "x".repeat(100));
+        let request = invitation(&summary, "Ada \"The\" Organizer\r\nX-INJECTED:1").to_request(Utc::now());
+        assert!(request.split("\r\n").all(|line| line.len() <= MAX_LINE_OCTETS));
+
+        let calendar = IcalParser::new(request.as_bytes()).next().unwrap().unwrap();
+        let event = &calendar.events[0];
+        let values = |name: &str| event.properties.iter().filter(|property| property.name == name).count();
+        assert_eq!(values("ATTENDEE"), 1);
+        assert_eq!(values("X-INJECTED"), 0);
+        let text = |name: &str| unescape(find(&event.properties, name).unwrap().value.as_deref().unwrap());
+        assert_eq!(text("SUMMARY"), summary.replace('\r', ""));
+        assert_eq!(text("LOCATION"), "Room 1; 2nd floor, east");
+        assert_eq!(find(&event.properties, "ATTENDEE").unwrap().value.as_deref(), Some("mailto:guest@example.com"));
+    }
+}
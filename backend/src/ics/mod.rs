@@ -0,0 +1,280 @@
+use chrono::{DateTime, Duration, NaiveDate, NaiveDateTime, Offset, TimeZone, Utc};
+use chrono_tz::Tz;
+use ical::{parser::ical::component::IcalEvent, property::Property, IcalParser};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    entities::calendar_events::EventStatus,
+    errors::{AppError, Result},
+    recurrence,
+};
+
+pub mod itip;
+
+/// Most events imported from a single feed
+pub const MAX_FEED_EVENTS: usize = 5_000;
+
+/// A VEVENT from an iCalendar feed, with its times resolved to UTC
+#[derive(Debug, Clone)]
+pub struct FeedEvent {
+    pub uid: String,
+    /// Start of the occurrence this event overrides (RECURRENCE-ID)
+    pub recurrence_id: Option<DateTime<Utc>>,
+    pub start: DateTime<Utc>,
+    pub end: DateTime<Utc>,
+    /// Zone DTSTART was given in, if any
+    pub time_zone: Option<Tz>,
+    pub rrule: Option<String>,
+    pub exdates: Vec<DateTime<Utc>>,
+    pub status: EventStatus,
+    pub details: FeedEventDetails,
+}
+
+impl FeedEvent {
+    /// Series are expanded in UTC from their start, so every occurrence keeps the UTC offset
+    /// the start had. Move a time from the feed, such as an EXDATE or a RECURRENCE-ID given
+    /// after a DST change, onto that grid so it names the occurrence it meant.
+    pub fn on_series_grid(&self, at: DateTime<Utc>) -> DateTime<Utc> {
+        let Some(tz) = self.time_zone else {
+            return at;
+        };
+        let offset = |dt: DateTime<Utc>| i64::from(tz.offset_from_utc_datetime(&dt.naive_utc()).fix().local_minus_utc());
+        at + Duration::seconds(offset(at) - offset(self.start))
+    }
+}
+
+/// What the feed says about an event; stored unencrypted in `external_data`
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct FeedEventDetails {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub summary: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub location: Option<String>,
+    /// DTSTART was a DATE; times are UTC midnights
+    pub all_day: bool,
+}
+
+/// Parse the events of an iCalendar document. Events without a UID or a usable
+/// DTSTART, or with a rule the recurrence engine can't expand, are skipped.
+pub fn parse_feed(body: &str) -> Result<Vec<FeedEvent>> {
+    let body = body.trim_start_matches('\u{feff}');
+    let mut events = Vec::new();
+    for calendar in IcalParser::new(body.as_bytes()) {
+        let calendar = calendar.map_err(|e| AppError::Validation(format!("Invalid iCalendar feed: {}", e)))?;
+        // Floating times are read in the calendar's time zone when it names one
+        let default_tz = find(&calendar.properties, "X-WR-TIMEZONE")
+            .and_then(|property| property.value.as_deref())
+            .and_then(parse_tzid);
+
+        for event in &calendar.events {
+            if let Some(event) = parse_event(event, default_tz) {
+                events.push(event);
+            }
+        }
+        if events.len() > MAX_FEED_EVENTS {
+            return Err(AppError::Validation(format!("Feed has more than {} events", MAX_FEED_EVENTS)));
+        }
+    }
+    if events.is_empty() && !body.trim_start().starts_with("BEGIN:VCALENDAR") {
+        return Err(AppError::Validation("Feed is not an iCalendar document".to_string()));
+    }
+    Ok(events)
+}
+
+fn parse_event(event: &IcalEvent, default_tz: Option<Tz>) -> Option<FeedEvent> {
+    let properties = &event.properties;
+    let uid = find(properties, "UID")?.value.clone()?;
+    let ParsedTime { at: start, all_day, time_zone } = parse_date_time(find(properties, "DTSTART")?, default_tz)?;
+
+    let end = match find(properties, "DTEND").and_then(|property| parse_date_time(property, default_tz)) {
+        Some(end) => end.at,
+        None => match find(properties, "DURATION").and_then(|property| property.value.as_deref()).and_then(parse_duration) {
+            Some(duration) => start + duration,
+            // RFC 5545: a DATE start without an end lasts one day, a DATE-TIME start ends when it starts
+            None if all_day => start + Duration::days(1),
+            None => start,
+        },
+    };
+    if end < start {
+        return None;
+    }
+
+    let rrule = match find(properties, "RRULE").and_then(|property| property.value.as_deref()) {
+        Some(rule) => {
+            let rule = normalize_rrule(rule);
+            recurrence::validate(Some(&rule), Some(start)).ok()?;
+            Some(rule)
+        }
+        None => None,
+    };
+
+    let exdates: Vec<DateTime<Utc>> = properties
+        .iter()
+        .filter(|property| property.name.eq_ignore_ascii_case("EXDATE"))
+        .flat_map(|property| {
+            property.value.as_deref().unwrap_or_default().split(',').filter_map(|value| {
+                let single = Property { value: Some(value.trim().to_string()), ..property.clone() };
+                parse_date_time(&single, default_tz).map(|time| time.at)
+            }).collect::<Vec<_>>()
+        })
+        .collect();
+
+    let status = match find(properties, "STATUS").and_then(|property| property.value.as_deref()) {
+        Some(status) if status.eq_ignore_ascii_case("CANCELLED") => EventStatus::Cancelled,
+        Some(status) if status.eq_ignore_ascii_case("TENTATIVE") => EventStatus::Tentative,
+        _ => EventStatus::Confirmed,
+    };
+
+    let text = |name: &str| {
+        find(properties, name)
+            .and_then(|property| property.value.as_deref())
+            .map(unescape)
+            .filter(|value| !value.is_empty())
+    };
+
+    let mut event = FeedEvent {
+        uid,
+        recurrence_id: find(properties, "RECURRENCE-ID")
+            .and_then(|property| parse_date_time(property, default_tz))
+            .map(|time| time.at),
+        start,
+        end,
+        time_zone,
+        rrule,
+        exdates: Vec::new(),
+        status,
+        details: FeedEventDetails {
+            summary: text("SUMMARY"),
+            description: text("DESCRIPTION"),
+            location: text("LOCATION"),
+            all_day,
+        },
+    };
+    event.exdates = exdates.into_iter().map(|at| event.on_series_grid(at)).collect();
+    Some(event)
+}
+
+fn find<'a>(properties: &'a [Property], name: &str) -> Option<&'a Property> {
+    properties.iter().find(|property| property.name.eq_ignore_ascii_case(name))
+}
+
+fn param<'a>(property: &'a Property, name: &str) -> Option<&'a str> {
+    property
+        .params
+        .as_ref()?
+        .iter()
+        .find(|(key, _)| key.eq_ignore_ascii_case(name))
+        .and_then(|(_, values)| values.first())
+        .map(String::as_str)
+}
+
+/// A TZID as an IANA zone; also accepts vendor prefixes such as
+/// `/mozilla.org/20050126_1/Europe/Berlin`
+fn parse_tzid(tzid: &str) -> Option<Tz> {
+    let tzid = tzid.trim().trim_matches('"');
+    if let Ok(tz) = tzid.parse() {
+        return Some(tz);
+    }
+    let segments: Vec<&str> = tzid.split('/').filter(|segment| !segment.is_empty()).collect();
+    (2..=3)
+        .filter_map(|n| segments.len().checked_sub(n).map(|from| segments[from..].join("/")))
+        .find_map(|name| name.parse().ok())
+}
+
+struct ParsedTime {
+    at: DateTime<Utc>,
+    /// The value was a DATE
+    all_day: bool,
+    /// Zone a local DATE-TIME was read in
+    time_zone: Option<Tz>,
+}
+
+/// A DATE or DATE-TIME value, resolved to UTC
+fn parse_date_time(property: &Property, default_tz: Option<Tz>) -> Option<ParsedTime> {
+    let value = property.value.as_deref()?.trim();
+    let is_date = param(property, "VALUE").is_some_and(|kind| kind.eq_ignore_ascii_case("DATE")) || value.len() == 8;
+    if is_date {
+        let date = NaiveDate::parse_from_str(value, "%Y%m%d").ok()?;
+        return Some(ParsedTime { at: date.and_hms_opt(0, 0, 0)?.and_utc(), all_day: true, time_zone: None });
+    }
+
+    if let Some(utc) = value.strip_suffix('Z') {
+        let at = NaiveDateTime::parse_from_str(utc, "%Y%m%dT%H%M%S").ok()?.and_utc();
+        return Some(ParsedTime { at, all_day: false, time_zone: None });
+    }
+    let local = NaiveDateTime::parse_from_str(value, "%Y%m%dT%H%M%S").ok()?;
+    let tz = param(property, "TZID").and_then(parse_tzid).or(default_tz).unwrap_or(Tz::UTC);
+    // A time skipped by a DST change is read an hour later, as RFC 5545 asks
+    let resolved = tz
+        .from_local_datetime(&local)
+        .earliest()
+        .or_else(|| tz.from_local_datetime(&(local + Duration::hours(1))).earliest())?;
+    Some(ParsedTime { at: resolved.with_timezone(&Utc), all_day: false, time_zone: Some(tz) })
+}
+
+/// A DURATION such as `PT1H30M`, `P1D` or `P2W`
+fn parse_duration(value: &str) -> Option<Duration> {
+    let value = value.trim().trim_start_matches('+').strip_prefix('P')?;
+    let mut total = Duration::zero();
+    let mut number = String::new();
+    let mut in_time = false;
+    for c in value.chars() {
+        match c {
+            '0'..='9' => number.push(c),
+            'T' => in_time = true,
+            'W' | 'D' | 'H' | 'M' | 'S' => {
+                let n: i64 = number.parse().ok()?;
+                number.clear();
+                total += match (c, in_time) {
+                    ('W', false) => Duration::weeks(n),
+                    ('D', false) => Duration::days(n),
+                    ('H', true) => Duration::hours(n),
+                    ('M', true) => Duration::minutes(n),
+                    ('S', true) => Duration::seconds(n),
+                    _ => return None,
+                };
+            }
+            _ => return None,
+        }
+    }
+    number.is_empty().then_some(total)
+}
+
+/// Series are expanded from a UTC start, so an UNTIL has to be a UTC DATE-TIME too
+fn normalize_rrule(rule: &str) -> String {
+    rule.trim()
+        .trim_start_matches("RRULE:")
+        .split(';')
+        .filter(|part| !part.is_empty())
+        .map(|part| match part.split_once('=') {
+            Some((key, until)) if key.eq_ignore_ascii_case("UNTIL") && until.len() == 8 => {
+                format!("UNTIL={}T235959Z", until)
+            }
+            Some((key, until)) if key.eq_ignore_ascii_case("UNTIL") && !until.ends_with('Z') => {
+                format!("UNTIL={}Z", until)
+            }
+            _ => part.to_string(),
+        })
+        .collect::<Vec<_>>()
+        .join(";")
+}
+
+/// Undo TEXT escaping (RFC 5545 section 3.3.11)
+fn unescape(value: &str) -> String {
+    let mut result = String::with_capacity(value.len());
+    let mut chars = value.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            result.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('n' | 'N') => result.push('\n'),
+            Some(escaped) => result.push(escaped),
+            None => result.push('\\'),
+        }
+    }
+    result
+}
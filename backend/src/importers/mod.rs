@@ -0,0 +1,5 @@
+//! Readers for other apps' exports. Each turns an export into an `ImportPlan`: the plaintext
+//! the client encrypts before the records are created, since the server never holds the key.
+
+mod csv;
+pub mod todoist;
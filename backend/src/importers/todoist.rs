@@ -0,0 +1,185 @@
+use async_zip::base::read::mem::ZipFileReader;
+use chrono::{DateTime, NaiveDate, NaiveDateTime, TimeZone, Utc};
+use chrono_tz::Tz;
+use futures_util::AsyncReadExt;
+
+use super::csv;
+use crate::{
+    entities::can_do_list::TaskPriority,
+    errors::{AppError, Result},
+    models::import::{ImportPlan, PlannedProject, PlannedTask, MAX_IMPORT_PROJECTS, MAX_IMPORT_TASKS},
+};
+
+/// Largest CSV read from a backup
+const MAX_CSV_BYTES: u64 = 16 * 1024 * 1024;
+
+/// Read a Todoist backup: the ZIP Todoist's backup settings offer, holding one CSV per project,
+/// or a single project's CSV export. Sections become subprojects of their project, and indented
+/// tasks subtasks of the task above them.
+pub async fn parse_backup(data: Vec<u8>) -> Result<ImportPlan> {
+    let mut plan = ImportPlan::default();
+
+    if data.starts_with(b"PK") {
+        let zip = ZipFileReader::new(data)
+            .await
+            .map_err(|e| AppError::Validation(format!("Not a valid Todoist backup: {}", e)))?;
+        for (index, entry) in zip.file().entries().iter().enumerate() {
+            let Ok(filename) = entry.filename().as_str() else {
+                continue;
+            };
+            if !filename.to_ascii_lowercase().ends_with(".csv") {
+                continue;
+            }
+            let mut reader = zip
+                .reader_with_entry(index)
+                .await
+                .map_err(|e| AppError::Validation(format!("Can't read {} from the backup: {}", filename, e)))?;
+            let mut csv = Vec::new();
+            (&mut reader)
+                .take(MAX_CSV_BYTES + 1)
+                .read_to_end(&mut csv)
+                .await
+                .map_err(|e| AppError::Validation(format!("Can't read {} from the backup: {}", filename, e)))?;
+            if csv.len() as u64 > MAX_CSV_BYTES {
+                return Err(AppError::Validation(format!("{} is larger than {} bytes", filename, MAX_CSV_BYTES)));
+            }
+            add_project(&mut plan, &project_name(filename), &String::from_utf8_lossy(&csv))?;
+        }
+    } else {
+        let csv = String::from_utf8(data)
+            .map_err(|_| AppError::Validation("A Todoist export must be a ZIP backup or a UTF-8 CSV file".to_string()))?;
+        add_project(&mut plan, "Todoist", &csv)?;
+    }
+
+    if plan.projects.is_empty() {
+        return Err(AppError::Validation("The backup holds no Todoist projects".to_string()));
+    }
+    if plan.projects.len() > MAX_IMPORT_PROJECTS || plan.tasks.len() > MAX_IMPORT_TASKS {
+        return Err(AppError::Validation(format!(
+            "At most {} projects and {} tasks can be imported at once",
+            MAX_IMPORT_PROJECTS, MAX_IMPORT_TASKS
+        )));
+    }
+    Ok(plan)
+}
+
+/// Backups name each file `<project> [<id>].csv`
+fn project_name(filename: &str) -> String {
+    let name = filename.rsplit('/').next().unwrap_or(filename);
+    let name = &name[..name.len() - ".csv".len()];
+    let name = match name.rfind(" [") {
+        Some(at) if name.ends_with(']') => &name[..at],
+        _ => name,
+    };
+    name.trim().to_string()
+}
+
+/// Add one project's CSV to the plan. Rows are typed `task`, `section` or `note`;
+/// a note is a comment on the task before it.
+fn add_project(plan: &mut ImportPlan, name: &str, csv: &str) -> Result<()> {
+    let mut rows = csv::parse(csv).into_iter();
+    let Some(header) = rows.next() else {
+        return Ok(());
+    };
+    let column = |name: &str| header.iter().position(|column| column.trim().eq_ignore_ascii_case(name));
+    let (Some(kind_column), Some(content_column)) = (column("TYPE"), column("CONTENT")) else {
+        return Err(AppError::Validation(format!("The CSV of project '{}' is not a Todoist export", name)));
+    };
+    let description_column = column("DESCRIPTION");
+    let priority_column = column("PRIORITY");
+    let indent_column = column("INDENT");
+    let date_column = column("DATE");
+    let timezone_column = column("TIMEZONE");
+    let duration_column = column("DURATION");
+    let duration_unit_column = column("DURATION_UNIT");
+
+    let project_key = format!("p{}", plan.projects.len() + 1);
+    plan.projects.push(PlannedProject { key: project_key.clone(), parent_key: None, name: name.to_string() });
+    let mut current_project = project_key.clone();
+    // Keys of the last task seen at each indent level, for finding a subtask's parent
+    let mut ancestors: Vec<String> = Vec::new();
+    let mut last_task = None;
+
+    for row in rows {
+        let field = |column: Option<usize>| {
+            column
+                .and_then(|column| row.get(column))
+                .map(|value| value.trim())
+                .filter(|value| !value.is_empty())
+        };
+        let Some(content) = field(Some(content_column)) else {
+            continue;
+        };
+
+        match field(Some(kind_column)).map(str::to_ascii_lowercase).as_deref() {
+            Some("section") => {
+                let key = format!("p{}", plan.projects.len() + 1);
+                plan.projects.push(PlannedProject {
+                    key: key.clone(),
+                    parent_key: Some(project_key.clone()),
+                    name: content.to_string(),
+                });
+                current_project = key;
+                ancestors.clear();
+                last_task = None;
+            }
+            Some("task") => {
+                let indent = field(indent_column).and_then(|indent| indent.parse::<usize>().ok()).unwrap_or(1).max(1);
+                ancestors.truncate(indent - 1);
+                let key = format!("t{}", plan.tasks.len() + 1);
+                let date_string = field(date_column);
+                let time_zone = field(timezone_column).and_then(|tz| tz.parse::<Tz>().ok()).unwrap_or(Tz::UTC);
+
+                plan.tasks.push(PlannedTask {
+                    key: key.clone(),
+                    project_key: current_project.clone(),
+                    parent_key: ancestors.last().cloned(),
+                    content: content.to_string(),
+                    description: field(description_column).map(str::to_string),
+                    comments: Vec::new(),
+                    priority: field(priority_column).and_then(parse_priority),
+                    due_at: date_string.and_then(|date| parse_date(date, time_zone)),
+                    date_string: date_string.map(str::to_string),
+                    estimated_minutes: field(duration_column)
+                        .and_then(|duration| duration.parse::<i32>().ok())
+                        .and_then(|duration| match field(duration_unit_column) {
+                            Some(unit) if unit.eq_ignore_ascii_case("day") => duration.checked_mul(24 * 60),
+                            _ => Some(duration),
+                        })
+                        .filter(|minutes| *minutes > 0),
+                });
+                ancestors.push(key);
+                last_task = Some(plan.tasks.len() - 1);
+            }
+            Some("note") => {
+                if let Some(index) = last_task {
+                    plan.tasks[index].comments.push(content.to_string());
+                }
+            }
+            _ => {}
+        }
+    }
+    Ok(())
+}
+
+/// Todoist numbers priorities as its app shows them: 1 is the most urgent, 4 means none
+fn parse_priority(priority: &str) -> Option<TaskPriority> {
+    match priority {
+        "1" => Some(TaskPriority::Urgent),
+        "2" => Some(TaskPriority::High),
+        "3" => Some(TaskPriority::Medium),
+        _ => None,
+    }
+}
+
+/// Dates written as ISO dates or date-times; natural-language dates such as "every day" are left to the client
+fn parse_date(date: &str, time_zone: Tz) -> Option<DateTime<Utc>> {
+    if let Ok(at) = DateTime::parse_from_rfc3339(date) {
+        return Some(at.with_timezone(&Utc));
+    }
+    let local = ["%Y-%m-%d %H:%M", "%Y-%m-%dT%H:%M:%S", "%Y-%m-%dT%H:%M"]
+        .iter()
+        .find_map(|format| NaiveDateTime::parse_from_str(date, format).ok())
+        .or_else(|| NaiveDate::parse_from_str(date, "%Y-%m-%d").ok().and_then(|day| day.and_hms_opt(0, 0, 0)))?;
+    time_zone.from_local_datetime(&local).earliest().map(|at| at.with_timezone(&Utc))
+}
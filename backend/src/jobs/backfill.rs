@@ -0,0 +1,153 @@
+use sea_orm::*;
+use std::env;
+use std::time::Duration;
+use uuid::Uuid;
+
+use crate::{
+    db::Database,
+    errors::{AppError, Result},
+    migrator::expand_contract::{Backfill, BACKFILLS},
+};
+
+const DEFAULT_BATCH_SIZE: i64 = 500;
+const DEFAULT_BATCH_DELAY_MS: u64 = 100;
+
+/// Spawn the background task that works through pending expand/contract backfills
+pub fn spawn(db: Database) {
+    if BACKFILLS.is_empty() {
+        return;
+    }
+
+    let batch_size = env::var("BACKFILL_BATCH_SIZE")
+        .ok()
+        .and_then(|v| v.parse().ok())
+        .filter(|&size: &i64| size > 0)
+        .unwrap_or(DEFAULT_BATCH_SIZE);
+    let batch_delay = Duration::from_millis(
+        env::var("BACKFILL_BATCH_DELAY_MS")
+            .ok()
+            .and_then(|v| v.parse().ok())
+            .unwrap_or(DEFAULT_BATCH_DELAY_MS),
+    );
+
+    tokio::spawn(async move {
+        for backfill in BACKFILLS {
+            if let Err(e) = run(&db, backfill, batch_size, batch_delay).await {
+                tracing::error!("Backfill '{}' failed: {}", backfill.name, e);
+            }
+        }
+    });
+}
+
+async fn run(db: &Database, backfill: &Backfill, batch_size: i64, batch_delay: Duration) -> Result<()> {
+    start(db, backfill).await?;
+
+    // Replicas share progress through the locked row, so each batch picks up where any left off
+    while let Some(processed) = run_batch(db, backfill, batch_size).await? {
+        tracing::debug!("Backfill '{}' processed {} rows", backfill.name, processed);
+        tokio::time::sleep(batch_delay).await;
+    }
+
+    if let Some(progress) = crate::migrator::expand_contract::progress(&db.connection, backfill.name)
+        .await
+        .map_err(|e| AppError::Database(e.into()))?
+    {
+        tracing::info!(
+            "Backfill '{}' completed ({}/{} rows)",
+            backfill.name, progress.processed_rows, progress.total_rows
+        );
+    }
+    Ok(())
+}
+
+/// Record the backfill and how many rows it has to go through, unless another replica already did
+async fn start(db: &Database, backfill: &Backfill) -> Result<()> {
+    db.connection
+        .execute(Statement::from_sql_and_values(
+            DbBackend::Postgres,
+            format!(
+                "INSERT INTO schema_backfills (name, table_name, total_rows) \
+                 SELECT $1, $2, COUNT(*) FROM {} WHERE {} \
+                 ON CONFLICT (name) DO NOTHING",
+                backfill.table, backfill.pending
+            ),
+            [backfill.name.into(), backfill.table.into()],
+        ))
+        .await
+        .map_err(|e| AppError::Database(e.into()))?;
+    Ok(())
+}
+
+#[derive(Debug, FromQueryResult)]
+struct BatchCursor {
+    last_id: Option<Uuid>,
+    completed: bool,
+}
+
+#[derive(Debug, FromQueryResult)]
+struct BackfilledRow {
+    id: Uuid,
+}
+
+/// Rewrite the next batch of pending rows in id order; `None` once nothing is left
+async fn run_batch(db: &Database, backfill: &Backfill, batch_size: i64) -> Result<Option<usize>> {
+    let txn = db.connection.begin().await
+        .map_err(|e| AppError::Database(e.into()))?;
+
+    let cursor = BatchCursor::find_by_statement(Statement::from_sql_and_values(
+        DbBackend::Postgres,
+        "SELECT last_id, completed_at IS NOT NULL AS completed FROM schema_backfills WHERE name = $1 FOR UPDATE",
+        [backfill.name.into()],
+    ))
+    .one(&txn)
+    .await
+    .map_err(|e| AppError::Database(e.into()))?
+    .ok_or_else(|| AppError::Internal(format!("Backfill '{}' is not recorded", backfill.name)))?;
+    if cursor.completed {
+        return Ok(None);
+    }
+
+    let rows = BackfilledRow::find_by_statement(Statement::from_sql_and_values(
+        DbBackend::Postgres,
+        format!(
+            "WITH batch AS ( \
+                 SELECT id FROM {table} WHERE id > $1 AND ({pending}) ORDER BY id LIMIT $2 FOR UPDATE \
+             ) \
+             UPDATE {table} SET {set} FROM batch WHERE {table}.id = batch.id RETURNING {table}.id",
+            table = backfill.table,
+            pending = backfill.pending,
+            set = backfill.set,
+        ),
+        [cursor.last_id.unwrap_or(Uuid::nil()).into(), batch_size.into()],
+    ))
+    .all(&txn)
+    .await
+    .map_err(|e| AppError::Database(e.into()))?;
+
+    let processed = match rows.iter().map(|row| row.id).max() {
+        Some(last_id) => {
+            txn.execute(Statement::from_sql_and_values(
+                DbBackend::Postgres,
+                "UPDATE schema_backfills SET processed_rows = processed_rows + $2, last_id = $3, updated_at = NOW() WHERE name = $1",
+                [backfill.name.into(), (rows.len() as i64).into(), last_id.into()],
+            ))
+            .await
+            .map_err(|e| AppError::Database(e.into()))?;
+            Some(rows.len())
+        }
+        None => {
+            txn.execute(Statement::from_sql_and_values(
+                DbBackend::Postgres,
+                "UPDATE schema_backfills SET completed_at = NOW(), updated_at = NOW() WHERE name = $1",
+                [backfill.name.into()],
+            ))
+            .await
+            .map_err(|e| AppError::Database(e.into()))?;
+            None
+        }
+    };
+
+    txn.commit().await
+        .map_err(|e| AppError::Database(e.into()))?;
+    Ok(processed)
+}
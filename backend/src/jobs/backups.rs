@@ -0,0 +1,194 @@
+use chrono::{DateTime, Utc};
+use std::env;
+use std::path::{Path, PathBuf};
+use std::sync::LazyLock;
+use std::time::Duration;
+use uuid::Uuid;
+
+use crate::{
+    backup::Snapshot,
+    db::Database,
+    errors::{AppError, Result},
+};
+
+/// Arbitrary but fixed key for the advisory lock that lets one replica take each scheduled backup
+const BACKUP_LOCK_KEY: i64 = 0x5354_524d_4241_434b; // "STRMBACK"
+const DEFAULT_RETENTION: usize = 7;
+const FILE_PREFIX: &str = "streamline-backup-";
+const FILE_SUFFIX: &str = ".zip";
+
+static HTTP_CLIENT: LazyLock<reqwest::Client> = LazyLock::new(|| {
+    reqwest::Client::builder()
+        .connect_timeout(Duration::from_secs(30))
+        .user_agent(concat!("streamline-scheduler-backups/", env!("CARGO_PKG_VERSION")))
+        .build()
+        .expect("Failed to build HTTP client")
+});
+
+/// Where scheduled backups are stored (`BACKUP_STORAGE_URL`)
+#[derive(Debug, Clone)]
+pub enum BackupStorage {
+    /// A local directory, given as a path or `file://` URL; old backups are pruned
+    Directory { path: PathBuf, retention: usize },
+    /// An HTTP(S) endpoint each backup is `PUT` to as `<url>/<file name>`, e.g. a pre-authorized
+    /// object storage bucket; pruning is left to the storage's own lifecycle rules
+    Http { url: String, token: Option<String> },
+}
+
+impl BackupStorage {
+    pub fn from_env() -> Result<Self> {
+        let url = env::var("BACKUP_STORAGE_URL")
+            .map_err(|_| AppError::Internal("BACKUP_STORAGE_URL must be set for scheduled backups".to_string()))?;
+        if url.starts_with("http://") || url.starts_with("https://") {
+            return Ok(BackupStorage::Http {
+                url: url.trim_end_matches('/').to_string(),
+                token: env::var("BACKUP_STORAGE_TOKEN").ok().filter(|token| !token.is_empty()),
+            });
+        }
+        if url.contains("://") && !url.starts_with("file://") {
+            return Err(AppError::Internal(format!(
+                "Unsupported BACKUP_STORAGE_URL '{}', expected a directory, file:// or http(s):// URL",
+                url
+            )));
+        }
+
+        Ok(BackupStorage::Directory {
+            path: PathBuf::from(url.trim_start_matches("file://")),
+            retention: env::var("BACKUP_RETENTION")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(DEFAULT_RETENTION)
+                .max(1),
+        })
+    }
+
+    /// File the archive is written to before it is stored
+    fn staging_path(&self, name: &str) -> PathBuf {
+        match self {
+            // Same directory, so storing it is an atomic rename
+            BackupStorage::Directory { path, .. } => path.join(format!(".{}.partial", name)),
+            BackupStorage::Http { .. } => env::temp_dir().join(format!("{}-{}", Uuid::new_v4(), name)),
+        }
+    }
+
+    async fn store(&self, name: &str, staged: &Path) -> Result<()> {
+        match self {
+            BackupStorage::Directory { path, retention } => {
+                tokio::fs::rename(staged, path.join(name))
+                    .await
+                    .map_err(|e| AppError::Internal(format!("Storing backup {} failed: {}", name, e)))?;
+                prune(path, *retention).await
+            }
+            BackupStorage::Http { url, token } => {
+                let data = tokio::fs::read(staged)
+                    .await
+                    .map_err(|e| AppError::Internal(format!("Reading backup {} failed: {}", name, e)))?;
+                let mut request = HTTP_CLIENT
+                    .put(format!("{}/{}", url, name))
+                    .header(reqwest::header::CONTENT_TYPE, "application/zip")
+                    .body(data);
+                if let Some(token) = token {
+                    request = request.bearer_auth(token);
+                }
+                let response = request
+                    .send()
+                    .await
+                    .map_err(|e| AppError::Internal(format!("Uploading backup {} failed: {}", name, e)))?;
+                if !response.status().is_success() {
+                    return Err(AppError::Internal(format!(
+                        "Uploading backup {} failed with status {}",
+                        name,
+                        response.status()
+                    )));
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+/// Spawn the background task that backs up the database every `BACKUP_INTERVAL_HOURS`;
+/// does nothing when the interval is unset or zero
+pub fn spawn(db: Database) {
+    let hours: u64 = env::var("BACKUP_INTERVAL_HOURS")
+        .ok()
+        .and_then(|v| v.parse().ok())
+        .unwrap_or(0);
+    if hours == 0 {
+        return;
+    }
+    let storage = match BackupStorage::from_env() {
+        Ok(storage) => storage,
+        Err(e) => {
+            tracing::error!("Scheduled backups are disabled: {}", e);
+            return;
+        }
+    };
+    let period = (hours * 60 * 60) as i64;
+
+    tokio::spawn(async move {
+        loop {
+            // Slots are aligned to the epoch, so every replica wakes for the same one
+            let now = Utc::now().timestamp();
+            let slot = (now / period + 1) * period;
+            tokio::time::sleep(Duration::from_secs((slot - now) as u64)).await;
+
+            let slot = DateTime::from_timestamp(slot, 0).unwrap_or_else(Utc::now);
+            match run_backup(&db, &storage, slot).await {
+                Ok(Some(name)) => tracing::info!("Stored scheduled backup {}", name),
+                Ok(None) => {}
+                Err(e) => tracing::error!("Scheduled backup failed: {}", e),
+            }
+        }
+    });
+}
+
+/// Back up the database for `slot`; `None` if another replica is already doing so
+pub async fn run_backup(db: &Database, storage: &BackupStorage, slot: DateTime<Utc>) -> Result<Option<String>> {
+    let snapshot = Snapshot::begin(&db.connection).await?;
+    if !snapshot.try_lock(BACKUP_LOCK_KEY).await? {
+        return Ok(None);
+    }
+
+    let name = format!("{}{}{}", FILE_PREFIX, slot.format("%Y%m%dT%H%M%SZ"), FILE_SUFFIX);
+    let staged = storage.staging_path(&name);
+    let written = async {
+        if let Some(dir) = staged.parent() {
+            tokio::fs::create_dir_all(dir)
+                .await
+                .map_err(|e| AppError::Internal(format!("Creating the backup directory failed: {}", e)))?;
+        }
+        let file = tokio::fs::File::create(&staged)
+            .await
+            .map_err(|e| AppError::Internal(format!("Creating backup {} failed: {}", name, e)))?;
+        snapshot.write(file).await?;
+        storage.store(&name, &staged).await
+    }
+    .await;
+    // Gone already once it was renamed into place
+    let _ = tokio::fs::remove_file(&staged).await;
+    written.map(|_| Some(name))
+}
+
+/// Delete all but the newest `retention` backups in `dir`; names sort by the time they were taken
+async fn prune(dir: &Path, retention: usize) -> Result<()> {
+    let failed = |e: std::io::Error| AppError::Internal(format!("Pruning old backups failed: {}", e));
+    let mut names = Vec::new();
+    let mut entries = tokio::fs::read_dir(dir).await.map_err(failed)?;
+    while let Some(entry) = entries.next_entry().await.map_err(failed)? {
+        if let Some(name) = entry.file_name().to_str()
+            && name.starts_with(FILE_PREFIX)
+            && name.ends_with(FILE_SUFFIX)
+        {
+            names.push(name.to_string());
+        }
+    }
+
+    names.sort();
+    let expired = names.len().saturating_sub(retention);
+    for name in &names[..expired] {
+        tokio::fs::remove_file(dir.join(name)).await.map_err(failed)?;
+        tracing::info!("Removed old backup {}", name);
+    }
+    Ok(())
+}
@@ -0,0 +1,353 @@
+use chrono::{DateTime, Duration, Utc};
+use reqwest::{
+    header::{ACCEPT, ETAG, IF_MODIFIED_SINCE, IF_NONE_MATCH, LAST_MODIFIED},
+    StatusCode,
+};
+use sea_orm::{sea_query::{LockBehavior, LockType}, *};
+use std::{
+    collections::{BTreeMap, HashMap},
+    sync::LazyLock,
+};
+use uuid::Uuid;
+
+use crate::{
+    db::Database,
+    entities::{prelude::*, calendar_events, calendar_subscriptions, calendars},
+    errors::{AppError, Result},
+    handlers::{recurrence::event_message, reminders::reschedule_event_reminders, sync::record_deletion},
+    ics::{self, FeedEvent},
+    models::calendar_subscription::CalendarSubscriptionResponse,
+    outbound,
+    websocket::{WebSocketMessage, WebSocketState},
+};
+
+const POLL_INTERVAL: std::time::Duration = std::time::Duration::from_secs(60);
+/// Most feeds fetched per poll; the rest wait for the next tick
+const BATCH_SIZE: u64 = 10;
+const FETCH_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(30);
+/// Largest feed body that is downloaded
+const MAX_FEED_BYTES: usize = 10 * 1024 * 1024;
+
+static HTTP_CLIENT: LazyLock<reqwest::Client> = LazyLock::new(|| {
+    outbound::client_builder()
+        .timeout(FETCH_TIMEOUT)
+        .user_agent(concat!("streamline-scheduler/", env!("CARGO_PKG_VERSION")))
+        .build()
+        .expect("Failed to build HTTP client")
+});
+
+/// Spawn the background task that refreshes calendar subscriptions when they are due
+pub fn spawn(db: Database, ws_state: WebSocketState) {
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(POLL_INTERVAL);
+        loop {
+            interval.tick().await;
+            match refresh_due(&db, &ws_state).await {
+                Ok(0) => {}
+                Ok(count) => tracing::info!("Refreshed {} calendar subscriptions", count),
+                Err(e) => tracing::error!("Refreshing calendar subscriptions failed: {}", e),
+            }
+        }
+    });
+}
+
+pub async fn refresh_due(db: &Database, ws_state: &WebSocketState) -> Result<usize> {
+    let now = Utc::now();
+    let txn = db.connection.begin().await
+        .map_err(|e| AppError::Database(e.into()))?;
+
+    // Rows locked by another replica are skipped; subscriptions of trashed calendars are paused
+    let due = CalendarSubscriptions::find()
+        .inner_join(Calendars)
+        .filter(calendars::Column::DeletedAt.is_null())
+        .filter(calendar_subscriptions::Column::NextFetchAt.lte(now))
+        .order_by_asc(calendar_subscriptions::Column::NextFetchAt)
+        .limit(BATCH_SIZE)
+        .lock_with_behavior(LockType::Update, LockBehavior::SkipLocked)
+        .all(&txn)
+        .await
+        .map_err(|e| AppError::Database(e.into()))?;
+
+    // Push the next fetch out before the slow part, so the feeds aren't picked up again meanwhile
+    for subscription in &due {
+        let mut subscription_active: calendar_subscriptions::ActiveModel = subscription.clone().into();
+        subscription_active.next_fetch_at = Set(next_fetch_at(subscription, now).into());
+        subscription_active.update(&txn).await
+            .map_err(|e| AppError::Database(e.into()))?;
+    }
+
+    txn.commit().await
+        .map_err(|e| AppError::Database(e.into()))?;
+
+    for subscription in &due {
+        if let Err(e) = refresh(db, ws_state, subscription.id).await {
+            tracing::error!("Refreshing calendar subscription {} failed: {}", subscription.id, e);
+        }
+    }
+    Ok(due.len())
+}
+
+fn next_fetch_at(subscription: &calendar_subscriptions::Model, now: DateTime<Utc>) -> DateTime<Utc> {
+    now + Duration::minutes(subscription.refresh_interval_minutes.into())
+}
+
+enum Fetched {
+    NotModified,
+    Feed {
+        body: String,
+        etag: Option<String>,
+        last_modified: Option<String>,
+    },
+}
+
+async fn fetch(subscription: &calendar_subscriptions::Model) -> std::result::Result<Fetched, String> {
+    let url = reqwest::Url::parse(&subscription.url).map_err(|e| format!("Invalid feed URL: {}", e))?;
+    outbound::check_url(&url)?;
+    let mut request = HTTP_CLIENT
+        .get(url)
+        .header(ACCEPT, "text/calendar, */*;q=0.5");
+    if let Some(etag) = &subscription.etag {
+        request = request.header(IF_NONE_MATCH, etag);
+    }
+    if let Some(last_modified) = &subscription.last_modified {
+        request = request.header(IF_MODIFIED_SINCE, last_modified);
+    }
+
+    let mut response = request.send().await
+        .map_err(|e| format!("Fetching the feed failed: {}", e))?;
+    if response.status() == StatusCode::NOT_MODIFIED {
+        return Ok(Fetched::NotModified);
+    }
+    if !response.status().is_success() {
+        return Err(format!("Feed responded with {}", response.status()));
+    }
+
+    let header = |name| {
+        response.headers().get(name)
+            .and_then(|value: &reqwest::header::HeaderValue| value.to_str().ok())
+            .map(str::to_string)
+    };
+    let etag = header(ETAG);
+    let last_modified = header(LAST_MODIFIED);
+
+    let too_large = || format!("Feed is larger than {} MB", MAX_FEED_BYTES / 1024 / 1024);
+    if response.content_length().is_some_and(|length| length > MAX_FEED_BYTES as u64) {
+        return Err(too_large());
+    }
+    let mut body = Vec::new();
+    while let Some(chunk) = response.chunk().await.map_err(|e| format!("Fetching the feed failed: {}", e))? {
+        body.extend_from_slice(&chunk);
+        if body.len() > MAX_FEED_BYTES {
+            return Err(too_large());
+        }
+    }
+
+    Ok(Fetched::Feed {
+        body: String::from_utf8_lossy(&body).into_owned(),
+        etag,
+        last_modified,
+    })
+}
+
+/// Fetch a subscription's feed and bring its calendar in line with it. Feed problems
+/// are recorded in `last_error` rather than returned; the subscription is returned as updated.
+pub async fn refresh(db: &Database, ws_state: &WebSocketState, id: Uuid) -> Result<calendar_subscriptions::Model> {
+    let subscription = CalendarSubscriptions::find_by_id(id)
+        .one(&db.connection)
+        .await
+        .map_err(|e| AppError::Database(e.into()))?
+        .ok_or_else(|| AppError::NotFound("Calendar subscription not found".to_string()))?;
+
+    // Fetch outside the transaction; a slow feed shouldn't hold row locks
+    let fetched = fetch(&subscription).await;
+    let fetched_url = subscription.url;
+
+    let txn = db.connection.begin().await
+        .map_err(|e| AppError::Database(e.into()))?;
+
+    // Serializes with a concurrent refresh and drops out if the subscription was deleted meanwhile
+    let subscription = CalendarSubscriptions::find_by_id(id)
+        .lock_exclusive()
+        .one(&txn)
+        .await
+        .map_err(|e| AppError::Database(e.into()))?
+        .ok_or_else(|| AppError::NotFound("Calendar subscription not found".to_string()))?;
+    // The URL was changed meanwhile; the new feed is already due
+    if subscription.url != fetched_url {
+        return Ok(subscription);
+    }
+
+    let now = Utc::now();
+    let mut subscription_active: calendar_subscriptions::ActiveModel = subscription.clone().into();
+    subscription_active.last_fetched_at = Set(Some(now.into()));
+    subscription_active.next_fetch_at = Set(next_fetch_at(&subscription, now).into());
+    subscription_active.last_error = Set(None);
+
+    let mut ws_messages = Vec::new();
+    match fetched {
+        Ok(Fetched::NotModified) => {}
+        Ok(Fetched::Feed { body, etag, last_modified }) => match ics::parse_feed(&body) {
+            Ok(events) => {
+                ws_messages = sync_events(&txn, &subscription, events).await?;
+                subscription_active.etag = Set(etag);
+                subscription_active.last_modified = Set(last_modified);
+            }
+            Err(AppError::Validation(message)) => subscription_active.last_error = Set(Some(message)),
+            Err(e) => return Err(e),
+        },
+        Err(message) => subscription_active.last_error = Set(Some(message)),
+    }
+
+    let updated_subscription = subscription_active.update(&txn).await
+        .map_err(|e| AppError::Database(e.into()))?;
+
+    txn.commit().await
+        .map_err(|e| AppError::Database(e.into()))?;
+
+    // Broadcast websocket messages for the imported changes; no client caused them
+    tracing::info!("Calendar subscription refreshed, broadcasting websocket message for user {}", updated_subscription.user_id);
+    ws_messages.push(WebSocketMessage {
+        event_type: "UPDATE".to_string(),
+        table: "calendar_subscriptions".to_string(),
+        user_id: updated_subscription.user_id,
+        record_id: Some(updated_subscription.id),
+        data: Some(serde_json::to_value(CalendarSubscriptionResponse::from(updated_subscription.clone())).unwrap_or_default()),
+    });
+    ws_state.broadcast_many_to_user(&updated_subscription.user_id, ws_messages, None).await;
+
+    Ok(updated_subscription)
+}
+
+/// Insert, update and delete the subscription's events so they match the feed.
+/// Events are matched by UID and, for overridden occurrences, RECURRENCE-ID.
+async fn sync_events<C: ConnectionTrait>(
+    db: &C,
+    subscription: &calendar_subscriptions::Model,
+    feed: Vec<FeedEvent>,
+) -> Result<Vec<WebSocketMessage>> {
+    type Key = (String, Option<DateTime<Utc>>);
+
+    let mut existing: HashMap<Key, calendar_events::Model> = CalendarEvents::find()
+        .filter(calendar_events::Column::SubscriptionId.eq(subscription.id))
+        .all(db)
+        .await
+        .map_err(|e| AppError::Database(e.into()))?
+        .into_iter()
+        .filter_map(|event| {
+            let key = (event.external_uid.clone()?, event.original_start_time.map(|dt| dt.naive_utc().and_utc()));
+            Some((key, event))
+        })
+        .collect();
+
+    // Overrides name their occurrence by its time in the feed; line it up with the series' occurrences
+    let series: HashMap<String, FeedEvent> = feed
+        .iter()
+        .filter(|event| event.recurrence_id.is_none() && event.rrule.is_some())
+        .map(|event| (event.uid.clone(), event.clone()))
+        .collect();
+    let mut feed = feed;
+    for event in &mut feed {
+        if let (Some(recurrence_id), Some(series)) = (event.recurrence_id, series.get(&event.uid)) {
+            event.recurrence_id = Some(series.on_series_grid(recurrence_id));
+        }
+    }
+
+    // Series come before their overrides, which need the series' id; a repeated key keeps the last one
+    let mut feed: BTreeMap<(bool, Key), FeedEvent> = feed
+        .into_iter()
+        .map(|event| ((event.recurrence_id.is_some(), (event.uid.clone(), event.recurrence_id)), event))
+        .collect();
+
+    // Overridden occurrences are excluded from their series, as when editing a single occurrence
+    let mut overridden: HashMap<String, Vec<DateTime<Utc>>> = HashMap::new();
+    for (_, (uid, recurrence_id)) in feed.keys() {
+        if let Some(recurrence_id) = recurrence_id {
+            overridden.entry(uid.clone()).or_default().push(*recurrence_id);
+        }
+    }
+
+    let mut series_ids: HashMap<String, Uuid> = HashMap::new();
+    let mut ws_messages = Vec::new();
+    while let Some(((_, key), event)) = feed.pop_first() {
+        let (mut event_active, is_new) = match existing.remove(&key) {
+            Some(model) => (calendar_events::ActiveModel::from(model), false),
+            None => {
+                let mut event_active = calendar_events::ActiveModel::new();
+                event_active.user_id = Set(subscription.user_id);
+                event_active.calendar_id = Set(Some(subscription.calendar_id));
+                event_active.subscription_id = Set(Some(subscription.id));
+                event_active.external_uid = Set(Some(event.uid.clone()));
+                event_active.encrypted_data = Set(String::new());
+                event_active.iv = Set(String::new());
+                event_active.salt = Set(String::new());
+                (event_active, true)
+            }
+        };
+
+        let mut exceptions = event.exdates.clone();
+        if event.rrule.is_some() {
+            exceptions.extend(overridden.get(&event.uid).into_iter().flatten());
+        }
+        exceptions.sort();
+        exceptions.dedup();
+
+        event_active.start_time.set_if_not_equals(Some(event.start.into()));
+        event_active.end_time.set_if_not_equals(Some(event.end.into()));
+        // Feed times stay UTC midnights so EXDATEs keep matching; the dates are what the feed says
+        let all_day = event.details.all_day;
+        let last_day = (event.end - Duration::days(1)).date_naive().max(event.start.date_naive());
+        event_active.is_all_day.set_if_not_equals(all_day);
+        event_active.start_date.set_if_not_equals(all_day.then(|| event.start.date_naive()));
+        event_active.end_date.set_if_not_equals(all_day.then_some(last_day));
+        event_active.rrule.set_if_not_equals(event.rrule.clone());
+        event_active.recurrence_exceptions.set_if_not_equals(serde_json::json!(exceptions));
+        event_active.status.set_if_not_equals(event.status);
+        event_active.external_data.set_if_not_equals(Some(serde_json::to_value(&event.details)?));
+        event_active.original_start_time.set_if_not_equals(event.recurrence_id.map(Into::into));
+        event_active.recurring_event_id.set_if_not_equals(match event.recurrence_id {
+            Some(_) => series_ids.get(&event.uid).copied(),
+            None => None,
+        });
+
+        let saved = if is_new {
+            let inserted = event_active.insert(db).await
+                .map_err(|e| AppError::Database(e.into()))?;
+            ws_messages.push(event_message("INSERT", &inserted));
+            inserted
+        } else if event_active.is_changed() {
+            let updated = event_active.update(db).await
+                .map_err(|e| AppError::Database(e.into()))?;
+            reschedule_event_reminders(db, &updated).await?;
+            ws_messages.push(event_message("UPDATE", &updated));
+            updated
+        } else {
+            event_active.try_into_model()
+                .map_err(|e| AppError::Database(e.into()))?
+        };
+        if saved.rrule.is_some() && event.recurrence_id.is_none() {
+            series_ids.insert(event.uid, saved.id);
+        }
+    }
+
+    // Whatever is left was removed from the feed
+    let removed: Vec<Uuid> = existing.into_values().map(|event| event.id).collect();
+    if !removed.is_empty() {
+        CalendarEvents::delete_many()
+            .filter(calendar_events::Column::Id.is_in(removed.clone()))
+            .exec(db)
+            .await
+            .map_err(|e| AppError::Database(e.into()))?;
+    }
+    for id in removed {
+        record_deletion(db, subscription.user_id, "calendar_events", id).await?;
+        ws_messages.push(WebSocketMessage {
+            event_type: "DELETE".to_string(),
+            table: "calendar_events".to_string(),
+            user_id: subscription.user_id,
+            record_id: Some(id),
+            data: None,
+        });
+    }
+
+    Ok(ws_messages)
+}
@@ -0,0 +1,188 @@
+use chrono::{DateTime, Duration, Utc};
+use chrono_tz::Tz;
+use sea_orm::{sea_query::{Expr, LockBehavior, LockType}, *};
+use std::collections::HashMap;
+
+use crate::{
+    db::Database,
+    entities::{
+        calendar_events::{self, EventStatus},
+        can_do_list,
+        notification_preferences::{self, DigestFrequency},
+        prelude::*,
+    },
+    errors::{AppError, Result},
+    handlers::{recurrence::occurrences_in, user_settings::time_preferences_of},
+    mailer::{format_local, templates, Mailer},
+    models::notification_preferences::next_digest_at,
+};
+
+const POLL_INTERVAL: std::time::Duration = std::time::Duration::from_secs(60);
+/// Most digests sent per poll; the rest wait for the next tick
+const BATCH_SIZE: u64 = 50;
+/// Most events and tasks listed per digest section
+const MAX_SECTION_ITEMS: usize = 50;
+
+/// Spawn the background task that emails daily and weekly digests when they are due
+pub fn spawn(db: Database, mailer: Mailer) {
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(POLL_INTERVAL);
+        loop {
+            interval.tick().await;
+            match send_due(&db, &mailer).await {
+                Ok(0) => {}
+                Ok(count) => tracing::info!("Sent {} digests", count),
+                Err(e) => tracing::error!("Sending digests failed: {}", e),
+            }
+        }
+    });
+}
+
+pub async fn send_due(db: &Database, mailer: &Mailer) -> Result<usize> {
+    let now = Utc::now();
+    let txn = db.connection.begin().await
+        .map_err(|e| AppError::Database(e.into()))?;
+
+    // Rows locked by another replica are skipped, so each digest goes out once
+    let due = NotificationPreferences::find()
+        .filter(notification_preferences::Column::NextDigestAt.lte(now))
+        .order_by_asc(notification_preferences::Column::NextDigestAt)
+        .limit(BATCH_SIZE)
+        .lock_with_behavior(LockType::Update, LockBehavior::SkipLocked)
+        .all(&txn)
+        .await
+        .map_err(|e| AppError::Database(e.into()))?;
+
+    let times = time_preferences_of(&txn, due.iter().map(|preferences| preferences.user_id)).await?;
+    let timezone_of = |user_id| times.get(&user_id).map_or(Tz::UTC, |time| time.timezone);
+
+    // Schedule the next digest before sending; a failed send is skipped rather than repeated
+    for preferences in &due {
+        let mut preferences_active: notification_preferences::ActiveModel = preferences.clone().into();
+        preferences_active.next_digest_at = Set(next_digest_at(preferences, timezone_of(preferences.user_id), now).map(Into::into));
+        preferences_active.update(&txn).await
+            .map_err(|e| AppError::Database(e.into()))?;
+    }
+
+    txn.commit().await
+        .map_err(|e| AppError::Database(e.into()))?;
+
+    let mut sent = 0;
+    for preferences in &due {
+        match send_digest(db, mailer, preferences, timezone_of(preferences.user_id), now).await {
+            Ok(true) => sent += 1,
+            Ok(false) => {}
+            Err(e) => tracing::error!("Sending the digest of user {} failed: {}", preferences.user_id, e),
+        }
+    }
+    Ok(sent)
+}
+
+/// Email the events and due tasks of the coming day or week. Returns `false` when there is
+/// nothing to tell, in which case no email is sent.
+async fn send_digest(
+    db: &Database,
+    mailer: &Mailer,
+    preferences: &notification_preferences::Model,
+    timezone: Tz,
+    now: DateTime<Utc>,
+) -> Result<bool> {
+    let (period, range, end) = match preferences.digest_frequency {
+        DigestFrequency::Daily => ("daily", "in the next 24 hours", now + Duration::days(1)),
+        DigestFrequency::Weekly => ("weekly", "in the next 7 days", now + Duration::days(7)),
+        DigestFrequency::Off => return Ok(false),
+    };
+
+    let occurrences = occurrences_in(
+        &db.connection,
+        CalendarEvents::find()
+            .filter(calendar_events::Column::UserId.eq(preferences.user_id))
+            .filter(calendar_events::Column::Status.ne(EventStatus::Cancelled)),
+        now,
+        end,
+    )
+    .await?;
+
+    // Events of subscribed feeds are the only ones with a readable title
+    let summaries: HashMap<_, _> = CalendarEvents::find()
+        .filter(calendar_events::Column::Id.is_in(occurrences.iter().map(|occurrence| occurrence.event_id)))
+        .filter(calendar_events::Column::ExternalData.is_not_null())
+        .all(&db.connection)
+        .await
+        .map_err(|e| AppError::Database(e.into()))?
+        .into_iter()
+        .filter_map(|event| {
+            let summary = event.external_data?.get("summary")?.as_str()?.to_string();
+            Some((event.id, summary))
+        })
+        .collect();
+
+    // Overdue tasks are included, so nothing slips through between two digests
+    let tasks = CanDoList::find()
+        .filter(can_do_list::Column::UserId.eq(preferences.user_id))
+        .filter(can_do_list::Column::DeletedAt.is_null())
+        .filter(can_do_list::Column::CompletedAt.is_null())
+        .filter(can_do_list::Column::DueAt.lt(end))
+        .order_by_asc(can_do_list::Column::DueAt)
+        .all(&db.connection)
+        .await
+        .map_err(|e| AppError::Database(e.into()))?;
+
+    if occurrences.is_empty() && tasks.is_empty() {
+        return Ok(false);
+    }
+
+    let events = section(occurrences.iter().map(|occurrence| {
+        let mut line = format!("- {}", format_local(occurrence.start_time, timezone));
+        if let Some(end_time) = occurrence.end_time {
+            line.push_str(&format!(" to {}", format_local(end_time, timezone)));
+        }
+        if let Some(summary) = summaries.get(&occurrence.event_id) {
+            line.push_str(&format!(": {}", summary));
+        }
+        if occurrence.status == EventStatus::Tentative {
+            line.push_str(" (tentative)");
+        }
+        line
+    }));
+    let tasks = section(tasks.iter().filter_map(|task| {
+        let due_at = task.due_at?.naive_utc().and_utc();
+        let mut line = format!("- {}", format_local(due_at, timezone));
+        if due_at < now {
+            line.push_str(" (overdue)");
+        }
+        if let Some(priority) = task.priority {
+            line.push_str(&format!(", {:?} priority", priority).to_lowercase());
+        }
+        Some(line)
+    }));
+
+    let user = Users::find_by_id(preferences.user_id)
+        .one(&db.connection)
+        .await
+        .map_err(|e| AppError::Database(e.into()))?
+        .ok_or_else(|| AppError::NotFound("User not found".to_string()))?;
+    let email = templates::DIGEST.render(&[("period", period), ("range", range), ("events", &events), ("tasks", &tasks)]);
+    mailer.send(&user.email, email).await?;
+
+    NotificationPreferences::update_many()
+        .col_expr(notification_preferences::Column::LastDigestSentAt, Expr::value(now))
+        .filter(notification_preferences::Column::UserId.eq(preferences.user_id))
+        .exec(&db.connection)
+        .await
+        .map_err(|e| AppError::Database(e.into()))?;
+    Ok(true)
+}
+
+/// One line per item, capped at `MAX_SECTION_ITEMS`
+fn section(lines: impl Iterator<Item = String>) -> String {
+    let lines: Vec<String> = lines.collect();
+    if lines.is_empty() {
+        return "- Nothing".to_string();
+    }
+    let mut text = lines.iter().take(MAX_SECTION_ITEMS).cloned().collect::<Vec<_>>().join("\n");
+    if lines.len() > MAX_SECTION_ITEMS {
+        text.push_str(&format!("\n- ...and {} more", lines.len() - MAX_SECTION_ITEMS));
+    }
+    text
+}
@@ -0,0 +1,8 @@
+pub mod backfill;
+pub mod backups;
+pub mod calendar_subscriptions;
+pub mod digests;
+pub mod reminders;
+pub mod session_activity;
+pub mod trash_purge;
+pub mod webhooks;
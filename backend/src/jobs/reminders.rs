@@ -0,0 +1,96 @@
+use chrono::{Duration, Utc};
+use sea_orm::{sea_query::{LockBehavior, LockType}, *};
+
+use crate::{
+    db::Database,
+    entities::{prelude::*, reminders},
+    errors::{AppError, Result},
+    handlers::{reminders::next_fire_at, user_settings::time_preferences},
+    notifications::{Notification, Notifier, ReminderPayload},
+};
+
+const POLL_INTERVAL: std::time::Duration = std::time::Duration::from_secs(15);
+/// Most reminders fired per poll; the rest wait for the next tick
+const BATCH_SIZE: u64 = 100;
+
+/// Spawn the background task that fires due reminders
+pub fn spawn(db: Database, notifier: Notifier) {
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(POLL_INTERVAL);
+        loop {
+            interval.tick().await;
+            match fire_due(&db, &notifier).await {
+                Ok(0) => {}
+                Ok(count) => tracing::info!("Fired {} reminders", count),
+                Err(e) => tracing::error!("Firing reminders failed: {}", e),
+            }
+        }
+    });
+}
+
+pub async fn fire_due(db: &Database, notifier: &Notifier) -> Result<usize> {
+    let now = Utc::now();
+    let txn = db.connection.begin().await
+        .map_err(|e| AppError::Database(e.into()))?;
+
+    // Rows locked by another replica are skipped, so each reminder fires once
+    let due = Reminders::find()
+        .filter(reminders::Column::FireAt.lte(now))
+        .order_by_asc(reminders::Column::FireAt)
+        .limit(BATCH_SIZE)
+        .lock_with_behavior(LockType::Update, LockBehavior::SkipLocked)
+        .all(&txn)
+        .await
+        .map_err(|e| AppError::Database(e.into()))?;
+
+    let mut notifications = Vec::with_capacity(due.len());
+    for reminder in due {
+        let event = CalendarEvents::find_by_id(reminder.event_id)
+            .one(&txn)
+            .await
+            .map_err(|e| AppError::Database(e.into()))?
+            .filter(|event| event.deleted_at.is_none());
+        let fire_at = reminder.fire_at.map(|dt| dt.naive_utc().and_utc()).unwrap_or(now);
+        let offset = reminder.offset_minutes.map(|minutes| Duration::minutes(minutes.into()));
+
+        let mut reminder_active: reminders::ActiveModel = reminder.clone().into();
+        // Reminders of trashed events stay silent; restoring the event reschedules them
+        let Some(event) = event else {
+            reminder_active.fire_at = Set(None);
+            reminder_active.update(&txn).await
+                .map_err(|e| AppError::Database(e.into()))?;
+            continue;
+        };
+
+        reminder_active.last_fired_at = Set(Some(now.into()));
+        let fired = reminders::Model { last_fired_at: Some(now.into()), ..reminder };
+        let time = time_preferences(&txn, event.user_id).await?;
+        reminder_active.fire_at = Set(next_fire_at(&fired, &event, &time, now)?.map(Into::into));
+        reminder_active.update(&txn).await
+            .map_err(|e| AppError::Database(e.into()))?;
+
+        let payload = ReminderPayload {
+            event_id: event.id,
+            occurrence_start: match offset {
+                Some(offset) => Some(fire_at + offset),
+                None => event.start_time.map(|dt| dt.naive_utc().and_utc()),
+            },
+            fire_at,
+        };
+        notifications.push(Notification {
+            user_id: fired.user_id,
+            table: "reminders",
+            record_id: fired.id,
+            payload: serde_json::to_value(payload)?,
+        });
+    }
+
+    txn.commit().await
+        .map_err(|e| AppError::Database(e.into()))?;
+
+    // Deliver only once the reminders are marked as fired, so a rollback can't repeat them
+    for notification in &notifications {
+        notifier.notify(notification).await;
+    }
+    Ok(notifications.len())
+}
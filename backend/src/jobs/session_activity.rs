@@ -0,0 +1,74 @@
+use chrono::{DateTime, Utc};
+use sea_orm::{sea_query::Expr, *};
+use std::collections::HashMap;
+use uuid::Uuid;
+
+use crate::{
+    auth::sessions::SessionActivity,
+    db::Database,
+    entities::{prelude::*, sessions},
+    errors::{AppError, Result},
+};
+
+/// Spawn the background task that writes buffered session activity and drops expired sessions
+pub fn spawn(db: Database, activity: SessionActivity) {
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(activity.flush_interval);
+        loop {
+            interval.tick().await;
+            if let Err(e) = flush(&db, &activity).await {
+                tracing::error!("Flushing session activity failed: {}", e);
+            }
+            if let Err(e) = purge_expired(&db).await {
+                tracing::error!("Purging expired sessions failed: {}", e);
+            }
+        }
+    });
+}
+
+pub async fn flush(db: &Database, activity: &SessionActivity) -> Result<usize> {
+    let pending = activity.take_pending().await;
+    if pending.is_empty() {
+        return Ok(0);
+    }
+
+    match write_activity(db, &pending).await {
+        Ok(()) => Ok(pending.len()),
+        Err(e) => {
+            activity.restore(pending).await;
+            Err(e)
+        }
+    }
+}
+
+async fn write_activity(db: &Database, pending: &HashMap<Uuid, DateTime<Utc>>) -> Result<()> {
+    let txn = db.connection.begin().await
+        .map_err(|e| AppError::Database(e.into()))?;
+
+    // Another replica may have recorded later activity for the same session
+    let latest = match txn.get_database_backend() {
+        DbBackend::Sqlite => "MAX(last_active_at, $1)",
+        _ => "GREATEST(last_active_at, $1)",
+    };
+    for (session_id, at) in pending {
+        Sessions::update_many()
+            .col_expr(sessions::Column::LastActiveAt, Expr::cust_with_values(latest, [*at]))
+            .filter(sessions::Column::Id.eq(*session_id))
+            .exec(&txn)
+            .await
+            .map_err(|e| AppError::Database(e.into()))?;
+    }
+
+    txn.commit().await
+        .map_err(|e| AppError::Database(e.into()))?;
+    Ok(())
+}
+
+async fn purge_expired(db: &Database) -> Result<u64> {
+    let result = Sessions::delete_many()
+        .filter(sessions::Column::ExpiresAt.lt(Utc::now()))
+        .exec(&db.connection)
+        .await
+        .map_err(|e| AppError::Database(e.into()))?;
+    Ok(result.rows_affected)
+}
@@ -0,0 +1,132 @@
+use chrono::{Duration, Utc};
+use sea_orm::*;
+use std::env;
+use uuid::Uuid;
+
+use crate::{
+    db::Database,
+    entities::{prelude::*, calendar_events, calendars, can_do_list, notes, projects},
+    errors::Result,
+    handlers::sync::record_deletion,
+};
+
+const DEFAULT_RETENTION_DAYS: i64 = 30;
+const PURGE_INTERVAL: std::time::Duration = std::time::Duration::from_secs(60 * 60);
+
+/// Spawn the background task that permanently removes trash older than the retention period
+pub fn spawn(db: Database) {
+    let retention_days = env::var("TRASH_RETENTION_DAYS")
+        .ok()
+        .and_then(|v| v.parse().ok())
+        .unwrap_or(DEFAULT_RETENTION_DAYS);
+
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(PURGE_INTERVAL);
+        loop {
+            interval.tick().await;
+            match purge_expired(&db, retention_days).await {
+                Ok(0) => {}
+                Ok(count) => tracing::info!("Purged {} records from trash", count),
+                Err(e) => tracing::error!("Trash purge failed: {}", e),
+            }
+        }
+    });
+}
+
+pub async fn purge_expired(db: &Database, retention_days: i64) -> Result<usize> {
+    let cutoff = Utc::now() - Duration::days(retention_days);
+    let txn = db.connection.begin().await
+        .map_err(|e| crate::errors::AppError::Database(e.into()))?;
+
+    let mut purged: Vec<(&str, Uuid, Uuid)> = Vec::new();
+
+    // Tasks first so none are left pointing at a project that is about to disappear
+    let items: Vec<(Uuid, Uuid)> = CanDoList::find()
+        .select_only()
+        .column(can_do_list::Column::Id)
+        .column(can_do_list::Column::UserId)
+        .filter(can_do_list::Column::DeletedAt.lt(cutoff))
+        .into_tuple()
+        .all(&txn)
+        .await
+        .map_err(|e| crate::errors::AppError::Database(e.into()))?;
+    CanDoList::delete_many()
+        .filter(can_do_list::Column::Id.is_in(items.iter().map(|(id, _)| *id)))
+        .exec(&txn)
+        .await
+        .map_err(|e| crate::errors::AppError::Database(e.into()))?;
+    purged.extend(items.into_iter().map(|(id, user_id)| ("can_do_list", id, user_id)));
+
+    let notes: Vec<(Uuid, Uuid)> = Notes::find()
+        .select_only()
+        .column(notes::Column::Id)
+        .column(notes::Column::UserId)
+        .filter(notes::Column::DeletedAt.lt(cutoff))
+        .into_tuple()
+        .all(&txn)
+        .await
+        .map_err(|e| crate::errors::AppError::Database(e.into()))?;
+    Notes::delete_many()
+        .filter(notes::Column::Id.is_in(notes.iter().map(|(id, _)| *id)))
+        .exec(&txn)
+        .await
+        .map_err(|e| crate::errors::AppError::Database(e.into()))?;
+    purged.extend(notes.into_iter().map(|(id, user_id)| ("notes", id, user_id)));
+
+    let events: Vec<(Uuid, Uuid)> = CalendarEvents::find()
+        .select_only()
+        .column(calendar_events::Column::Id)
+        .column(calendar_events::Column::UserId)
+        .filter(calendar_events::Column::DeletedAt.lt(cutoff))
+        .into_tuple()
+        .all(&txn)
+        .await
+        .map_err(|e| crate::errors::AppError::Database(e.into()))?;
+    CalendarEvents::delete_many()
+        .filter(calendar_events::Column::Id.is_in(events.iter().map(|(id, _)| *id)))
+        .exec(&txn)
+        .await
+        .map_err(|e| crate::errors::AppError::Database(e.into()))?;
+    purged.extend(events.into_iter().map(|(id, user_id)| ("calendar_events", id, user_id)));
+
+    let calendars: Vec<(Uuid, Uuid)> = Calendars::find()
+        .select_only()
+        .column(calendars::Column::Id)
+        .column(calendars::Column::UserId)
+        .filter(calendars::Column::DeletedAt.lt(cutoff))
+        .into_tuple()
+        .all(&txn)
+        .await
+        .map_err(|e| crate::errors::AppError::Database(e.into()))?;
+    Calendars::delete_many()
+        .filter(calendars::Column::Id.is_in(calendars.iter().map(|(id, _)| *id)))
+        .exec(&txn)
+        .await
+        .map_err(|e| crate::errors::AppError::Database(e.into()))?;
+    purged.extend(calendars.into_iter().map(|(id, user_id)| ("calendars", id, user_id)));
+
+    let projects: Vec<(Uuid, Uuid)> = Projects::find()
+        .select_only()
+        .column(projects::Column::Id)
+        .column(projects::Column::UserId)
+        .filter(projects::Column::DeletedAt.lt(cutoff))
+        .into_tuple()
+        .all(&txn)
+        .await
+        .map_err(|e| crate::errors::AppError::Database(e.into()))?;
+    Projects::delete_many()
+        .filter(projects::Column::Id.is_in(projects.iter().map(|(id, _)| *id)))
+        .exec(&txn)
+        .await
+        .map_err(|e| crate::errors::AppError::Database(e.into()))?;
+    purged.extend(projects.into_iter().map(|(id, user_id)| ("projects", id, user_id)));
+
+    for (table, id, user_id) in &purged {
+        record_deletion(&txn, *user_id, table, *id).await?;
+    }
+
+    txn.commit().await
+        .map_err(|e| crate::errors::AppError::Database(e.into()))?;
+
+    Ok(purged.len())
+}
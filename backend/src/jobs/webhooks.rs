@@ -0,0 +1,184 @@
+use chrono::{DateTime, Duration, Utc};
+use futures_util::future::join_all;
+use reqwest::header::CONTENT_TYPE;
+use sea_orm::{sea_query::{Expr, LockBehavior, LockType}, *};
+use std::{collections::HashMap, sync::LazyLock};
+
+use crate::{
+    db::Database,
+    entities::{prelude::*, webhook_deliveries, webhooks},
+    errors::{AppError, Result},
+    outbound,
+    webhooks::{sign, signing_secrets, DELIVERY_HEADER, EVENT_HEADER, SIGNATURE_HEADER},
+};
+
+const POLL_INTERVAL: std::time::Duration = std::time::Duration::from_secs(5);
+/// Most deliveries attempted per poll; the rest wait for the next tick
+const BATCH_SIZE: u64 = 50;
+const DELIVERY_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(10);
+/// How long claimed deliveries are hidden from other replicas while they are attempted
+const CLAIM_LEASE: Duration = Duration::seconds(60);
+/// Attempts before a delivery is given up on; retries back off from 30 seconds to about half an hour
+pub const MAX_ATTEMPTS: i32 = 8;
+const FIRST_RETRY_SECONDS: i64 = 30;
+/// Longest error text kept from a failed attempt
+const MAX_ERROR_LENGTH: usize = 500;
+
+static HTTP_CLIENT: LazyLock<reqwest::Client> = LazyLock::new(|| {
+    outbound::client_builder()
+        .timeout(DELIVERY_TIMEOUT)
+        .redirect(reqwest::redirect::Policy::none())
+        .user_agent(concat!("streamline-scheduler-webhooks/", env!("CARGO_PKG_VERSION")))
+        .build()
+        .expect("Failed to build HTTP client")
+});
+
+/// Spawn the background task that posts queued webhook deliveries and retries failed ones
+pub fn spawn(db: Database) {
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(POLL_INTERVAL);
+        loop {
+            interval.tick().await;
+            match deliver_due(&db).await {
+                Ok(0) => {}
+                Ok(count) => tracing::info!("Attempted {} webhook deliveries", count),
+                Err(e) => tracing::error!("Delivering webhooks failed: {}", e),
+            }
+        }
+    });
+}
+
+pub async fn deliver_due(db: &Database) -> Result<usize> {
+    let now = Utc::now();
+    let txn = db.connection.begin().await
+        .map_err(|e| AppError::Database(e.into()))?;
+
+    // Rows locked by another replica are skipped
+    let due = WebhookDeliveries::find()
+        .filter(webhook_deliveries::Column::NextAttemptAt.lte(now))
+        .order_by_asc(webhook_deliveries::Column::NextAttemptAt)
+        .limit(BATCH_SIZE)
+        .lock_with_behavior(LockType::Update, LockBehavior::SkipLocked)
+        .all(&txn)
+        .await
+        .map_err(|e| AppError::Database(e.into()))?;
+    if due.is_empty() {
+        return Ok(0);
+    }
+
+    // Push the next attempt out before the slow part, so a crash mid-delivery still retries
+    WebhookDeliveries::update_many()
+        .col_expr(webhook_deliveries::Column::NextAttemptAt, Expr::value(now + CLAIM_LEASE))
+        .filter(webhook_deliveries::Column::Id.is_in(due.iter().map(|delivery| delivery.id)))
+        .exec(&txn)
+        .await
+        .map_err(|e| AppError::Database(e.into()))?;
+
+    txn.commit().await
+        .map_err(|e| AppError::Database(e.into()))?;
+
+    let hooks: HashMap<_, _> = Webhooks::find()
+        .filter(webhooks::Column::Id.is_in(due.iter().map(|delivery| delivery.webhook_id)))
+        .all(&db.connection)
+        .await
+        .map_err(|e| AppError::Database(e.into()))?
+        .into_iter()
+        .map(|hook| (hook.id, hook))
+        .collect();
+
+    let count = due.len();
+    join_all(due.into_iter().map(|delivery| {
+        let hook = hooks.get(&delivery.webhook_id);
+        async move {
+            let delivery_id = delivery.id;
+            if let Err(e) = attempt(db, delivery, hook).await {
+                tracing::error!("Recording webhook delivery {} failed: {}", delivery_id, e);
+            }
+        }
+    }))
+    .await;
+    Ok(count)
+}
+
+/// Post one delivery and record the outcome on it and its webhook
+async fn attempt(db: &Database, delivery: webhook_deliveries::Model, hook: Option<&webhooks::Model>) -> Result<()> {
+    let now = Utc::now();
+    let attempts = delivery.attempts + 1;
+    let mut delivery_active: webhook_deliveries::ActiveModel = delivery.clone().into();
+
+    // Deliveries of a paused webhook are dropped rather than held back
+    let Some(hook) = hook.filter(|hook| hook.is_active) else {
+        delivery_active.next_attempt_at = Set(None);
+        delivery_active.last_error = Set(Some("Webhook was disabled before delivery".to_string()));
+        delivery_active.update(&db.connection).await
+            .map_err(|e| AppError::Database(e.into()))?;
+        return Ok(());
+    };
+
+    let (status, outcome) = post(hook, &delivery, now).await;
+    delivery_active.attempts = Set(attempts);
+    delivery_active.last_status = Set(status);
+    let mut hook_active: webhooks::ActiveModel = hook.clone().into();
+    match outcome {
+        Ok(()) => {
+            delivery_active.next_attempt_at = Set(None);
+            delivery_active.delivered_at = Set(Some(now.into()));
+            delivery_active.last_error = Set(None);
+            hook_active.last_delivered_at = Set(Some(now.into()));
+            hook_active.last_error = Set(None);
+        }
+        Err(error) => {
+            let error: String = error.chars().take(MAX_ERROR_LENGTH).collect();
+            tracing::warn!("Webhook delivery {} to {} failed (attempt {}): {}", delivery.id, hook.url, attempts, error);
+            delivery_active.next_attempt_at = Set(next_attempt_at(attempts, now).map(Into::into));
+            delivery_active.last_error = Set(Some(error.clone()));
+            hook_active.last_error = Set(Some(error));
+        }
+    }
+
+    delivery_active.update(&db.connection).await
+        .map_err(|e| AppError::Database(e.into()))?;
+    // Only the outcome columns are written, so a concurrent edit of the webhook isn't undone
+    hook_active.update(&db.connection).await
+        .map_err(|e| AppError::Database(e.into()))?;
+    Ok(())
+}
+
+/// The receiver's status, if it answered, and whether the delivery counts as done
+async fn post(hook: &webhooks::Model, delivery: &webhook_deliveries::Model, now: DateTime<Utc>) -> (Option<i32>, std::result::Result<(), String>) {
+    let body = match serde_json::to_vec(&delivery.payload) {
+        Ok(body) => body,
+        Err(e) => return (None, Err(format!("Serializing the payload failed: {}", e))),
+    };
+    // The URL was checked when it was saved, but addresses are checked on every delivery
+    let url = match reqwest::Url::parse(&hook.url) {
+        Ok(url) => url,
+        Err(e) => return (None, Err(format!("Invalid webhook URL: {}", e))),
+    };
+    if let Err(e) = outbound::check_url(&url) {
+        return (None, Err(e));
+    }
+    let response = HTTP_CLIENT
+        .post(url)
+        .header(CONTENT_TYPE, "application/json")
+        .header(SIGNATURE_HEADER, sign(&signing_secrets(hook, now), now.timestamp(), &body))
+        .header(EVENT_HEADER, &delivery.event)
+        .header(DELIVERY_HEADER, delivery.id.to_string())
+        .body(body)
+        .send()
+        .await;
+
+    match response {
+        Ok(response) if response.status().is_success() => (Some(response.status().as_u16().into()), Ok(())),
+        Ok(response) => (Some(response.status().as_u16().into()), Err(format!("Receiver responded with {}", response.status()))),
+        Err(e) => (None, Err(format!("Posting the webhook failed: {}", e))),
+    }
+}
+
+/// Exponential backoff after a failed attempt, or `None` once the attempts are used up
+fn next_attempt_at(attempts: i32, now: DateTime<Utc>) -> Option<DateTime<Utc>> {
+    if attempts >= MAX_ATTEMPTS {
+        return None;
+    }
+    Some(now + Duration::seconds(FIRST_RETRY_SECONDS << (attempts - 1)))
+}
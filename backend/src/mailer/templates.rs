@@ -0,0 +1,59 @@
+//! Plain-text email templates. Placeholders are written `{{name}}`; the server never holds
+//! decrypted record details, so templates only fill in times, counts and other plaintext, or
+//! what a client disclosed for the email, as for invitations.
+
+/// A rendered email
+pub struct Email {
+    pub subject: String,
+    pub body: String,
+}
+
+pub struct Template {
+    subject: &'static str,
+    body: &'static str,
+}
+
+pub const BOOKING: Template = Template {
+    subject: "New booking",
+    body: include_str!("templates/booking.txt"),
+};
+
+pub const INVITATION: Template = Template {
+    subject: "Invitation: {{summary}}",
+    body: include_str!("templates/invitation.txt"),
+};
+
+pub const DIGEST: Template = Template {
+    subject: "Your {{period}} agenda",
+    body: include_str!("templates/digest.txt"),
+};
+
+impl Template {
+    /// Fill in the placeholders; unknown ones are left as they are
+    pub fn render(&self, values: &[(&str, &str)]) -> Email {
+        Email {
+            subject: fill(self.subject, values),
+            body: fill(self.body, values),
+        }
+    }
+}
+
+fn fill(template: &str, values: &[(&str, &str)]) -> String {
+    let mut output = String::with_capacity(template.len());
+    let mut rest = template;
+    while let Some(start) = rest.find("{{") {
+        output.push_str(&rest[..start]);
+        let Some(end) = rest[start..].find("}}") else {
+            rest = &rest[start..];
+            break;
+        };
+        let name = rest[start + 2..start + end].trim();
+        match values.iter().find(|(key, _)| *key == name) {
+            Some((_, value)) => output.push_str(value),
+            None => output.push_str(&rest[start..start + end + 2]),
+        }
+        rest = &rest[start + end + 2..];
+    }
+    output.push_str(rest);
+    output
+}
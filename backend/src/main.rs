@@ -1,38 +1,68 @@
 mod auth;
+mod backup;
+mod cli;
 mod db;
 mod entities;
 mod errors;
+mod features;
 mod handlers;
+mod ics;
+mod importers;
+mod jobs;
+mod mailer;
 mod middleware;
 mod migrator;
 mod models;
+mod notifications;
+mod outbound;
+mod quick_add;
+mod quota;
+mod recurrence;
+mod scheduler;
+mod seed;
 mod state;
+mod webhooks;
 mod websocket;
 
 use axum::{
-    routing::{get, post},
-    Router,
+    extract::DefaultBodyLimit,
+    routing::{delete, get, patch, post, put},
+    Router, ServiceExt,
 };
+use clap::Parser;
 use dotenvy::dotenv;
-use sea_orm_migration::MigratorTrait;
-use std::env;
-use tower::ServiceBuilder;
-use tower_http::{cors::CorsLayer, trace::TraceLayer};
+use std::{env, net::SocketAddr};
+use tower::{Layer, ServiceBuilder};
+use tower_http::{compression::CompressionLayer, cors::CorsLayer, trace::TraceLayer};
 use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt};
-use std::io::{self, Write};
+use std::io::Write;
+use streamline_client::routes;
 
 use crate::{
     auth::AuthService,
+    cli::{Cli, Command},
     db::Database,
-    middleware::auth::auth_middleware,
-    migrator::Migrator,
+    features::FeatureFlags,
+    middleware::{
+        auth::auth_middleware,
+        cache::{cache_headers_middleware, compression_predicate},
+        methods::{allow_options_middleware, entity_headers_middleware, method_not_allowed},
+        rate_limit::{rate_limit_middleware, RateLimitPolicy, RateLimiter},
+        versioning::{api_version_middleware, ApiVersions},
+    },
+    mailer::Mailer,
+    notifications::{email::EmailChannel, websocket::WebSocketChannel, Notifier},
+    quota::Quotas,
     state::AppState,
-    websocket::WebSocketState,
+    webhooks::WebhookDispatcher,
+    websocket::{shutdown::{ShutdownNotice, SHUTDOWN_DRAIN}, WebSocketState},
 };
 
 
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let cli = Cli::parse();
+
     // Load environment variables
     dotenv().ok();
 
@@ -45,6 +75,18 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
         .with(tracing_subscriber::fmt::layer())
         .init();
 
+    if let Some(Command::Migrate { command }) = cli.command {
+        let db = Database::new().await?;
+        crate::cli::migrate(&db.connection, command).await?;
+        return Ok(());
+    }
+    if let Some(Command::SeedDemo { email, password, tasks, months, seed }) = cli.command {
+        let db = Database::new().await?;
+        let seed = seed.unwrap_or_else(rand::random);
+        crate::cli::seed_demo(&db, crate::seed::DemoOptions { email, password, tasks, months, seed }).await?;
+        return Ok(());
+    }
+
     tracing::info!("Starting Streamline Backend...");
     std::io::stdout().flush().unwrap(); // force flush
 
@@ -54,61 +96,348 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     tracing::info!("Database connected successfully");
     
     // Run migrations
-    Migrator::up(&db.connection, None).await?;
+    crate::migrator::runner::run(&db.connection).await?;
     tracing::info!("Database migrations completed");
 
+    // Start background jobs
+    crate::jobs::trash_purge::spawn(db.clone());
+    crate::jobs::backfill::spawn(db.clone());
+
     // Initialize services
     let auth_service = AuthService::new(db.clone());
-    let ws_state = WebSocketState::new();
+    let ws_state = WebSocketState::new().with_webhooks(WebhookDispatcher::spawn(db.clone()));
+
+    let mut notifier = Notifier::new().with_channel(WebSocketChannel::new(ws_state.clone()));
+    let mailer = Mailer::from_env()?;
+    match &mailer {
+        Some(mailer) => {
+            notifier = notifier.with_channel(EmailChannel::new(db.clone(), mailer.clone()));
+            crate::jobs::digests::spawn(db.clone(), mailer.clone());
+        }
+        None => tracing::info!("SMTP_URL not set, email notifications, digests and invitations are disabled"),
+    }
+    crate::jobs::reminders::spawn(db.clone(), notifier.clone());
+    crate::jobs::session_activity::spawn(db.clone(), auth_service.session_activity().clone());
+    crate::jobs::calendar_subscriptions::spawn(db.clone(), ws_state.clone());
+    crate::jobs::webhooks::spawn(db.clone());
+    crate::jobs::backups::spawn(db.clone());
+
+    // Expensive or abusable routes get their own, tighter buckets
+    let rate_limiter = RateLimiter::from_env()
+        .with_override(routes::AUTH_LOGIN, RateLimitPolicy::new(10, 5))
+        .with_override(routes::AUTH_REGISTER, RateLimitPolicy::new(5, 3))
+        .with_override(routes::EXPORT, RateLimitPolicy::new(6, 2))
+        .with_override(routes::ORGANIZATION_EXPORT, RateLimitPolicy::new(6, 2))
+        .with_override(routes::IMPORT, RateLimitPolicy::new(2, 1))
+        .with_override(routes::IMPORT_TODOIST, RateLimitPolicy::new(6, 2))
+        .with_override(routes::IMPORT_TODOIST_COMMIT, RateLimitPolicy::new(6, 2))
+        .with_override(routes::ADMIN_BACKUP, RateLimitPolicy::new(6, 2))
+        .with_override(routes::ADMIN_RESTORE, RateLimitPolicy::new(2, 1))
+        .without_limit(routes::HEALTH)
+        .without_limit(routes::HEALTH_LIVE)
+        .without_limit(routes::HEALTH_READY)
+        // All inbound mail arrives from the mail server's address, so the hook limits each
+        // recipient address instead
+        .without_limit(routes::INBOUND_EMAIL_RECEIVE);
 
     let app_state = AppState {
         db: db.clone(),
         auth_service: auth_service.clone(),
         ws_state: ws_state.clone(),
+        quotas: Quotas::from_env(),
+        features: FeatureFlags::from_env(),
+        notifier,
+        mailer,
+        rate_limiter: rate_limiter.clone(),
+        api_versions: ApiVersions::from_env(),
     };
 
     // Public routes (no authentication required)
     let public_app = Router::new()
-        .route("/api/auth/register", post(crate::handlers::auth::register))
-        .route("/api/auth/login", post(crate::handlers::auth::login))
-        .route("/health", get(crate::handlers::health::health_check))
-        .route("/ws", get(crate::websocket::websocket_handler))
+        .route(routes::AUTH_REGISTER, post(crate::handlers::auth::register))
+        .route(routes::AUTH_LOGIN, post(crate::handlers::auth::login))
+        .route(routes::HEALTH, get(crate::handlers::health::health_check))
+        .route(routes::HEALTH_LIVE, get(crate::handlers::health::liveness))
+        .route(routes::HEALTH_READY, get(crate::handlers::health::readiness))
+        .route(routes::META, get(crate::handlers::meta::server_meta))
+        .route(routes::WEBSOCKET, get(crate::websocket::websocket_handler))
+        .route(routes::PUBLIC_POLL, get(crate::handlers::polls::get_public_poll))
+        .route(routes::PUBLIC_POLL_VOTES, post(crate::handlers::polls::submit_vote))
+        .route(routes::PUBLIC_BOOKING_PAGE, get(crate::handlers::booking_pages::get_public_booking_page))
+        .route(routes::PUBLIC_BOOKING_PAGE_SLOTS, get(crate::handlers::booking_pages::list_public_slots))
+        .route(routes::PUBLIC_BOOKING_PAGE_BOOKINGS, post(crate::handlers::booking_pages::create_booking))
+        .route(routes::INBOUND_EMAIL_RECEIVE,
+               post(crate::handlers::inbound_email::receive_email)
+               .layer(DefaultBodyLimit::max(crate::models::inbound_email::MAX_INBOUND_EMAIL_BYTES)))
+        .method_not_allowed_fallback(method_not_allowed)
+        .layer(axum::middleware::from_fn_with_state(
+            rate_limiter.clone(),
+            rate_limit_middleware,
+        ))
         .with_state(app_state.clone());
 
     // Protected routes (authentication required)
     let protected_app = Router::new()
-        .route("/api/auth/me", get(crate::handlers::auth::me))
-        .route("/api/projects", 
+        .route(routes::AUTH_ME, get(crate::handlers::auth::me))
+        .route(routes::AUTH_SESSIONS, get(crate::handlers::auth::list_sessions))
+        .route(routes::AUTH_SESSION, delete(crate::handlers::auth::revoke_session))
+        .route(routes::ACTIVITY, get(crate::handlers::activity::list_activity))
+        .route(routes::ADMIN_IMPERSONATE, post(crate::handlers::admin::impersonate))
+        .route(routes::ADMIN_IMPERSONATION, delete(crate::handlers::admin::end_impersonation))
+        .route(routes::ADMIN_BACKUP, post(crate::handlers::admin::backup))
+        .route(routes::ADMIN_RESTORE,
+               post(crate::handlers::admin::restore)
+               .layer(DefaultBodyLimit::max(crate::models::backup::MAX_RESTORE_BYTES)))
+        .route(routes::ADMIN_USER_FEATURE,
+               put(crate::handlers::admin::set_feature_override)
+               .delete(crate::handlers::admin::clear_feature_override))
+        .route(routes::ADMIN_BROADCAST, post(crate::handlers::admin::broadcast))
+        .route(routes::ANNOUNCEMENTS, get(crate::handlers::announcements::list_announcements))
+        .route(routes::PROJECTS, 
                get(crate::handlers::projects::list_projects)
                .post(crate::handlers::projects::create_project))
-        .route("/api/projects/{id}", 
+        .route(routes::PROJECTS_REORDER, post(crate::handlers::projects::reorder_projects))
+        .route(routes::PROJECTS_DEFAULT, post(crate::handlers::projects::default_project))
+        .route(routes::PROJECTS_SHARED, get(crate::handlers::project_members::list_shared_projects))
+        .route(routes::PROJECT, 
                get(crate::handlers::projects::get_project)
                .put(crate::handlers::projects::update_project)
+               .patch(crate::handlers::projects::update_project)
                .delete(crate::handlers::projects::delete_project))
-        .route("/api/can-do-list", 
+        .route(routes::PROJECT_RESTORE, post(crate::handlers::projects::restore_project))
+        .route(routes::PROJECT_DUPLICATE, post(crate::handlers::projects::duplicate_project))
+        .route(routes::PROJECT_MOVE, post(crate::handlers::projects::move_project))
+        .route(routes::PROJECT_PIN,
+               put(crate::handlers::projects::pin_project)
+               .delete(crate::handlers::projects::unpin_project))
+        .route(routes::PROJECT_REVISIONS, get(crate::handlers::projects::list_project_revisions))
+        .route(routes::PROJECT_REVERT, post(crate::handlers::projects::revert_project))
+        .route(routes::PROJECT_MEMBERS,
+               get(crate::handlers::project_members::list_members)
+               .post(crate::handlers::project_members::add_member))
+        .route(routes::PROJECT_MEMBER,
+               put(crate::handlers::project_members::update_member)
+               .delete(crate::handlers::project_members::remove_member))
+        .route(routes::ORGANIZATIONS,
+               get(crate::handlers::organizations::list_organizations)
+               .post(crate::handlers::organizations::create_organization))
+        .route(routes::ORGANIZATION,
+               get(crate::handlers::organizations::get_organization)
+               .put(crate::handlers::organizations::update_organization)
+               .patch(crate::handlers::organizations::update_organization)
+               .delete(crate::handlers::organizations::delete_organization))
+        .route(routes::ORGANIZATION_MEMBERS,
+               get(crate::handlers::organizations::list_members)
+               .post(crate::handlers::organizations::add_member))
+        .route(routes::ORGANIZATION_MEMBER,
+               put(crate::handlers::organizations::update_member)
+               .delete(crate::handlers::organizations::remove_member))
+        .route(routes::ORGANIZATION_EXPORT, get(crate::handlers::export::export_organization))
+        .route(routes::CAN_DO_LIST, 
                get(crate::handlers::can_do_list::list_items)
                .post(crate::handlers::can_do_list::create_item))
-        .route("/api/can-do-list/{id}", 
+        .route(routes::CAN_DO_LIST_REORDER, post(crate::handlers::can_do_list::reorder_items))
+        .route(routes::CAN_DO_ITEM, 
                get(crate::handlers::can_do_list::get_item)
                .put(crate::handlers::can_do_list::update_item)
+               .patch(crate::handlers::can_do_list::update_item)
                .delete(crate::handlers::can_do_list::delete_item))
-        .route("/api/calendars", 
+        .route(routes::CAN_DO_ITEM_RESTORE, post(crate::handlers::can_do_list::restore_item))
+        .route(routes::CAN_DO_ITEM_PIN,
+               put(crate::handlers::can_do_list::pin_item)
+               .delete(crate::handlers::can_do_list::unpin_item))
+        .route(routes::CAN_DO_ITEM_TIMER_START, post(crate::handlers::time_entries::start_timer))
+        .route(routes::CAN_DO_ITEM_TIMER_STOP, post(crate::handlers::time_entries::stop_timer))
+        .route(routes::CAN_DO_ITEM_REVISIONS, get(crate::handlers::can_do_list::list_item_revisions))
+        .route(routes::CAN_DO_ITEM_REVERT, post(crate::handlers::can_do_list::revert_item))
+        .route(routes::CAN_DO_ITEM_LINKED_EVENTS, get(crate::handlers::can_do_list::list_linked_events))
+        .route(routes::CALENDARS, 
                get(crate::handlers::calendars::list_calendars)
                .post(crate::handlers::calendars::create_calendar))
-        .route("/api/calendars/{id}", 
+        .route(routes::CALENDARS_REORDER, post(crate::handlers::calendars::reorder_calendars))
+        .route(routes::CALENDAR, 
                get(crate::handlers::calendars::get_calendar)
                .put(crate::handlers::calendars::update_calendar)
+               .patch(crate::handlers::calendars::update_calendar)
                .delete(crate::handlers::calendars::delete_calendar))
-        .route("/api/calendar-events", 
+        .route(routes::CALENDAR_RESTORE, post(crate::handlers::calendars::restore_calendar))
+        .route(routes::CALENDAR_PIN,
+               put(crate::handlers::calendars::pin_calendar)
+               .delete(crate::handlers::calendars::unpin_calendar))
+        .route(routes::CALENDAR_REVISIONS, get(crate::handlers::calendars::list_calendar_revisions))
+        .route(routes::CALENDAR_REVERT, post(crate::handlers::calendars::revert_calendar))
+        .route(routes::CALENDAR_EVENTS, 
                get(crate::handlers::calendar_events::list_events)
                .post(crate::handlers::calendar_events::create_event))
-        .route("/api/calendar-events/{id}", 
+        .route(routes::CALENDAR_EVENTS_BATCH, post(crate::handlers::calendar_events::batch_create_events))
+        .route(routes::CALENDAR_EVENT, 
                get(crate::handlers::calendar_events::get_event)
                .put(crate::handlers::calendar_events::update_event)
+               .patch(crate::handlers::calendar_events::update_event)
                .delete(crate::handlers::calendar_events::delete_event))
-        .route("/api/user-settings",
+        .route(routes::CALENDAR_EVENT_RESTORE, post(crate::handlers::calendar_events::restore_event))
+        .route(routes::CALENDAR_EVENT_DUPLICATE, post(crate::handlers::calendar_events::duplicate_event))
+        .route(routes::CALENDAR_EVENT_REVISIONS, get(crate::handlers::calendar_events::list_event_revisions))
+        .route(routes::CALENDAR_EVENT_REVERT, post(crate::handlers::calendar_events::revert_event))
+        .route(routes::CALENDAR_EVENT_LINK,
+               put(crate::handlers::calendar_events::link_task)
+               .delete(crate::handlers::calendar_events::unlink_task))
+        .route(routes::CALENDAR_EVENT_TIME, patch(crate::handlers::calendar_events::update_event_time))
+        .route(routes::CALENDAR_EVENT_OCCURRENCES, get(crate::handlers::recurrence::list_occurrences))
+        .route(routes::REMINDERS,
+               get(crate::handlers::reminders::list_reminders)
+               .post(crate::handlers::reminders::create_reminder))
+        .route(routes::REMINDER, delete(crate::handlers::reminders::delete_reminder))
+        .route(routes::EVENT_ATTENDEES,
+               get(crate::handlers::event_attendees::list_attendees)
+               .post(crate::handlers::event_attendees::create_attendee))
+        .route(routes::EVENT_ATTENDEE, delete(crate::handlers::event_attendees::delete_attendee))
+        .route(routes::EVENT_ATTENDEE_RSVP, put(crate::handlers::event_attendees::record_rsvp))
+        .route(routes::TIME_ENTRIES, get(crate::handlers::time_entries::list_time_entries))
+        .route(routes::TIME_ENTRY_TOTALS, get(crate::handlers::time_entries::time_totals))
+        .route(routes::TIME_ENTRY, delete(crate::handlers::time_entries::delete_time_entry))
+        .route(routes::FOCUS_SESSIONS,
+               get(crate::handlers::focus_sessions::list_sessions)
+               .post(crate::handlers::focus_sessions::start_session))
+        .route(routes::FOCUS_SESSION_CURRENT, get(crate::handlers::focus_sessions::current_session))
+        .route(routes::FOCUS_SESSION_STOP, post(crate::handlers::focus_sessions::stop_session))
+        .route(routes::FOCUS_SESSION_CANCEL, post(crate::handlers::focus_sessions::cancel_session))
+        .route(routes::HABITS,
+               get(crate::handlers::habits::list_habits)
+               .post(crate::handlers::habits::create_habit))
+        .route(routes::HABIT,
+               get(crate::handlers::habits::get_habit)
+               .put(crate::handlers::habits::update_habit)
+               .delete(crate::handlers::habits::delete_habit))
+        .route(routes::HABIT_CHECKIN,
+               post(crate::handlers::habits::check_in)
+               .delete(crate::handlers::habits::undo_check_in))
+        .route(routes::HABIT_CHECKINS, get(crate::handlers::habits::list_checkins))
+        .route(routes::GOALS,
+               get(crate::handlers::goals::list_goals)
+               .post(crate::handlers::goals::create_goal))
+        .route(routes::GOAL,
+               get(crate::handlers::goals::get_goal)
+               .put(crate::handlers::goals::update_goal)
+               .delete(crate::handlers::goals::delete_goal))
+        .route(routes::GOAL_PROJECT,
+               put(crate::handlers::goals::link_project)
+               .delete(crate::handlers::goals::unlink_project))
+        .route(routes::GOAL_TASK,
+               put(crate::handlers::goals::link_task)
+               .delete(crate::handlers::goals::unlink_task))
+        .route(routes::TEMPLATES,
+               get(crate::handlers::templates::list_templates)
+               .post(crate::handlers::templates::create_template))
+        .route(routes::TEMPLATE,
+               get(crate::handlers::templates::get_template)
+               .put(crate::handlers::templates::update_template)
+               .delete(crate::handlers::templates::delete_template))
+        .route(routes::TEMPLATE_INSTANTIATE, post(crate::handlers::templates::instantiate_template))
+        .route(routes::NOTES, 
+               get(crate::handlers::notes::list_notes)
+               .post(crate::handlers::notes::create_note))
+        .route(routes::NOTE, 
+               get(crate::handlers::notes::get_note)
+               .put(crate::handlers::notes::update_note)
+               .patch(crate::handlers::notes::update_note)
+               .delete(crate::handlers::notes::delete_note))
+        .route(routes::NOTE_RESTORE, post(crate::handlers::notes::restore_note))
+        .route(routes::NOTE_REVISIONS, get(crate::handlers::notes::list_note_revisions))
+        .route(routes::NOTE_REVERT, post(crate::handlers::notes::revert_note))
+        .route(routes::CALENDAR_EVENT_SERIES_OCCURRENCES,
+               put(crate::handlers::recurrence::update_occurrence)
+               .delete(crate::handlers::recurrence::delete_occurrence))
+        .route(routes::POLLS,
+               get(crate::handlers::polls::list_polls)
+               .post(crate::handlers::polls::create_poll))
+        .route(routes::POLL,
+               get(crate::handlers::polls::get_poll)
+               .delete(crate::handlers::polls::delete_poll))
+        .route(routes::POLL_FINALIZE, post(crate::handlers::polls::finalize_poll))
+        .route(routes::POLL_VOTE, delete(crate::handlers::polls::delete_vote))
+        .route(routes::BOOKING_PAGES,
+               get(crate::handlers::booking_pages::list_booking_pages)
+               .post(crate::handlers::booking_pages::create_booking_page))
+        .route(routes::BOOKING_PAGE,
+               get(crate::handlers::booking_pages::get_booking_page)
+               .put(crate::handlers::booking_pages::update_booking_page)
+               .delete(crate::handlers::booking_pages::delete_booking_page))
+        .route(routes::BOOKING_PAGE_BOOKINGS, get(crate::handlers::booking_pages::list_bookings))
+        .route(routes::CALENDAR_SUBSCRIPTIONS,
+               get(crate::handlers::calendar_subscriptions::list_subscriptions)
+               .post(crate::handlers::calendar_subscriptions::create_subscription))
+        .route(routes::CALENDAR_SUBSCRIPTION,
+               get(crate::handlers::calendar_subscriptions::get_subscription)
+               .put(crate::handlers::calendar_subscriptions::update_subscription)
+               .delete(crate::handlers::calendar_subscriptions::delete_subscription))
+        .route(routes::CALENDAR_SUBSCRIPTION_REFRESH, post(crate::handlers::calendar_subscriptions::refresh_subscription))
+        .route(routes::WEBHOOKS,
+               get(crate::handlers::webhooks::list_webhooks)
+               .post(crate::handlers::webhooks::create_webhook))
+        .route(routes::WEBHOOK,
+               get(crate::handlers::webhooks::get_webhook)
+               .put(crate::handlers::webhooks::update_webhook)
+               .delete(crate::handlers::webhooks::delete_webhook))
+        .route(routes::WEBHOOK_DELIVERIES, get(crate::handlers::webhooks::list_deliveries))
+        .route(routes::WEBHOOK_REDELIVER, post(crate::handlers::webhooks::redeliver))
+        .route(routes::WEBHOOK_ROTATE_SECRET, post(crate::handlers::webhooks::rotate_webhook_secret))
+        .route(routes::WEBHOOK_VERIFY_SIGNATURE, post(crate::handlers::webhooks::verify_webhook_signature))
+        .route(routes::INBOUND_EMAIL,
+               get(crate::handlers::inbound_email::get_inbound_address)
+               .post(crate::handlers::inbound_email::create_inbound_address)
+               .delete(crate::handlers::inbound_email::delete_inbound_address))
+        .route(routes::INBOUND_EMAIL_ROTATE, post(crate::handlers::inbound_email::rotate_inbound_address))
+        .route(routes::USER_SETTINGS,
                get(crate::handlers::user_settings::get_user_settings)
                .put(crate::handlers::user_settings::update_user_settings))
+        .route(routes::USER_SETTINGS_NOTIFICATIONS,
+               get(crate::handlers::user_settings::get_notification_preferences)
+               .put(crate::handlers::user_settings::update_notification_preferences))
+        .route(routes::SETTINGS, get(crate::handlers::settings::list_settings))
+        .route(routes::SETTING,
+               get(crate::handlers::settings::get_setting)
+               .put(crate::handlers::settings::update_setting)
+               .delete(crate::handlers::settings::delete_setting))
+        .route(routes::KV, get(crate::handlers::kv::list_entries))
+        .route(routes::KV_ENTRY,
+               get(crate::handlers::kv::get_entry)
+               .put(crate::handlers::kv::put_entry)
+               .delete(crate::handlers::kv::delete_entry))
+        .route(routes::SYNC, get(crate::handlers::sync::sync_changes))
+        .route(routes::SYNC_PUSH, post(crate::handlers::sync::push_changes))
+        .route(routes::CONFLICTS, get(crate::handlers::conflicts::list_conflicts))
+        .route(routes::CONFLICT, get(crate::handlers::conflicts::get_conflict))
+        .route(routes::CONFLICT_RESOLVE, post(crate::handlers::conflicts::resolve_conflict))
+        .route(routes::SEARCH, get(crate::handlers::search::search))
+        .route(routes::SEARCH_PREFILTER, post(crate::handlers::search::prefilter))
+        .route(routes::QUICK_ADD, post(crate::handlers::quick_add::quick_add))
+        .route(routes::FEATURES, get(crate::handlers::features::list_features))
+        .route(routes::TRASH, get(crate::handlers::trash::list_trash))
+        .route(routes::USAGE, get(crate::handlers::usage::get_usage))
+        .route(routes::REPORTS_SUMMARY, get(crate::handlers::reports::get_summary))
+        .route(routes::SCHEDULE_PLAN, post(crate::handlers::schedule::plan_schedule))
+        .route(routes::SCHEDULE_APPLY, post(crate::handlers::schedule::apply_schedule))
+        .route(routes::SCHEDULE_CONSTRAINTS,
+               get(crate::handlers::scheduling_constraints::get_scheduling_constraints)
+               .put(crate::handlers::scheduling_constraints::update_scheduling_constraints)
+               .delete(crate::handlers::scheduling_constraints::delete_scheduling_constraints))
+        .route(routes::EXPORT, get(crate::handlers::export::export_account))
+        .route(routes::IMPORT,
+               post(crate::handlers::export::import_account)
+               .layer(DefaultBodyLimit::max(crate::models::export::MAX_IMPORT_BYTES)))
+        .route(routes::IMPORT_TODOIST,
+               post(crate::handlers::imports::plan_todoist_import)
+               .layer(DefaultBodyLimit::max(crate::models::import::MAX_IMPORT_UPLOAD_BYTES)))
+        .route(routes::IMPORT_TODOIST_COMMIT,
+               post(crate::handlers::imports::commit_todoist_import)
+               .layer(DefaultBodyLimit::max(crate::models::import::MAX_IMPORT_UPLOAD_BYTES)))
+        .method_not_allowed_fallback(method_not_allowed)
+        // Added before authentication so it runs after it and can key buckets by user
+        .layer(axum::middleware::from_fn_with_state(
+            rate_limiter,
+            rate_limit_middleware,
+        ))
         .layer(axum::middleware::from_fn_with_state(
             app_state.clone(),
             auth_middleware,
@@ -119,18 +448,55 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     let app = Router::new()
         .merge(public_app)
         .merge(protected_app)
+        // Routes that take larger uploads set their own limit, which takes precedence
+        .layer(DefaultBodyLimit::max(crate::models::MAX_BODY_BYTES))
         .layer(
             ServiceBuilder::new()
                 .layer(TraceLayer::new_for_http())
-                .layer(CorsLayer::permissive()),
+                .layer(CorsLayer::permissive())
+                .layer(CompressionLayer::new().compress_when(compression_predicate()))
+                .layer(axum::middleware::from_fn(cache_headers_middleware))
+                // Innermost, so it hashes the uncompressed body
+                .layer(axum::middleware::from_fn(entity_headers_middleware)),
         );
+    let app = axum::middleware::from_fn_with_state(app_state.api_versions.clone(), api_version_middleware).layer(app);
+    let app = axum::middleware::from_fn(allow_options_middleware).layer(app);
 
     // Start server
     let port = env::var("PORT").unwrap_or_else(|_| "3001".to_string());
     let addr = format!("0.0.0.0:{}", port);
     tracing::info!("Listening on {}", addr);
     let listener = tokio::net::TcpListener::bind(&addr).await?;
-    axum::serve(listener, app.into_make_service()).await?;
+    axum::serve(listener, ServiceExt::<axum::extract::Request>::into_make_service_with_connect_info::<SocketAddr>(app))
+        .with_graceful_shutdown(shutdown_signal(ws_state))
+        .await?;
 
     Ok(())
-}
\ No newline at end of file
+}
+
+/// Resolve on Ctrl+C or SIGTERM, after warning WebSocket clients to reconnect later
+async fn shutdown_signal(ws_state: WebSocketState) {
+    let ctrl_c = async {
+        tokio::signal::ctrl_c().await.expect("failed to install Ctrl+C handler");
+    };
+
+    #[cfg(unix)]
+    let terminate = async {
+        tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate())
+            .expect("failed to install SIGTERM handler")
+            .recv()
+            .await;
+    };
+
+    #[cfg(not(unix))]
+    let terminate = std::future::pending::<()>();
+
+    tokio::select! {
+        _ = ctrl_c => {},
+        _ = terminate => {},
+    }
+
+    tracing::info!("Shutdown signal received");
+    ws_state.notify_shutdown(ShutdownNotice::new("shutdown"));
+    tokio::time::sleep(SHUTDOWN_DRAIN).await;
+}
@@ -1,21 +1,38 @@
 use axum::{
+    body::Body,
     extract::{Request, State},
+    http::{header, Method},
     middleware::Next,
-    response::Response,
+    response::{IntoResponse, Response},
 };
 use axum_extra::{
     headers::{authorization::Bearer, Authorization},
     TypedHeader,
 };
-
+use uuid::Uuid;
 use crate::{
     errors::AppError,
     entities::users,
 };
 
+/// Values shown to impersonation sessions besides ids and timestamps: the response envelope,
+/// error details, paging, and record states that hold no user content
+const METADATA_FIELDS: [&str; 12] = [
+    "message", "error", "code", "details", "field", "total_count", "limit", "next_cursor",
+    "schema_version", "status", "role", "current",
+];
+
 #[derive(Clone)]
 pub struct AuthUser(pub users::Model);
 
+/// Session the request's token belongs to, if it was issued for one
+#[derive(Clone, Copy, Debug)]
+pub struct CurrentSession(pub Option<Uuid>);
+
+/// Super admin impersonating the user, if the request's token was issued for impersonation
+#[derive(Clone, Copy, Debug)]
+pub struct Impersonator(pub Option<Uuid>);
+
 pub async fn auth_middleware(
     State(app_state): State<crate::state::AppState>,
     TypedHeader(authorization): TypedHeader<Authorization<Bearer>>,
@@ -24,12 +41,73 @@ pub async fn auth_middleware(
 ) -> Result<Response, AppError> {
     let token = authorization.token();
     
-    let user = app_state.auth_service.get_user_from_token(token).await?;
-    
-    // Insert the user into request extensions
+    let authenticated = app_state.auth_service.authenticate(token).await?;
+    let user = authenticated.user;
+    req.extensions_mut().insert(CurrentSession(authenticated.session_id));
+    req.extensions_mut().insert(Impersonator(authenticated.impersonator));
+
+    let Some(actor_id) = authenticated.impersonator else {
+        // Insert the user into request extensions
+        req.extensions_mut().insert(AuthUser(user));
+        return Ok(next.run(req).await);
+    };
+
+    if !matches!(*req.method(), Method::GET | Method::HEAD) {
+        return Err(AppError::Forbidden("Impersonation sessions are read-only".to_string()));
+    }
+
+    tracing::warn!("Super admin {} impersonating user {}: {} {}", actor_id, user.id, req.method(), req.uri());
     req.extensions_mut().insert(AuthUser(user));
-    
-    Ok(next.run(req).await)
+
+    Ok(redact_content(next.run(req).await).await)
+}
+
+/// Strip everything but metadata from a JSON response, so support staff never see user content
+/// in fields added after this list was written
+async fn redact_content(response: Response) -> Response {
+    let (mut parts, body) = response.into_parts();
+    let Ok(bytes) = axum::body::to_bytes(body, usize::MAX).await else {
+        return AppError::Internal("Failed to read response body".to_string()).into_response();
+    };
+
+    let Ok(mut value) = serde_json::from_slice::<serde_json::Value>(&bytes) else {
+        return Response::from_parts(parts, Body::from(bytes));
+    };
+    redact_value(&mut value);
+
+    parts.headers.remove(header::CONTENT_LENGTH);
+    Response::from_parts(parts, Body::from(value.to_string()))
+}
+
+/// Keep metadata values, and objects and arrays for their structure with their own contents
+/// redacted in turn; any other value is dropped. Lists of ids are kept whole.
+fn redact_value(value: &mut serde_json::Value) {
+    match value {
+        serde_json::Value::Object(object) => {
+            object.retain(|key, value| is_metadata_field(key) || is_container(value));
+            object
+                .iter_mut()
+                .filter(|(key, value)| !key.ends_with("_ids") || value.is_object())
+                .for_each(|(_, value)| redact_value(value));
+        }
+        serde_json::Value::Array(items) => {
+            items.retain(is_container);
+            items.iter_mut().for_each(redact_value);
+        }
+        _ => {}
+    }
+}
+
+fn is_metadata_field(key: &str) -> bool {
+    key == "id"
+        || key.ends_with("_id")
+        || key.ends_with("_ids")
+        || key.ends_with("_at")
+        || METADATA_FIELDS.contains(&key)
+}
+
+fn is_container(value: &serde_json::Value) -> bool {
+    value.is_object() || value.is_array()
 }
 
 // Helper to extract user from request extensions
@@ -47,3 +125,81 @@ impl axum::extract::FromRequestParts<crate::state::AppState> for AuthUser {
             .ok_or_else(|| AppError::Auth("User not found in request".to_string()))
     }
 }
+
+impl axum::extract::FromRequestParts<crate::state::AppState> for CurrentSession {
+    type Rejection = AppError;
+
+    async fn from_request_parts(
+        parts: &mut axum::http::request::Parts,
+        _state: &crate::state::AppState,
+    ) -> Result<Self, Self::Rejection> {
+        parts
+            .extensions
+            .get::<CurrentSession>()
+            .copied()
+            .ok_or_else(|| AppError::Auth("Session not found in request".to_string()))
+    }
+}
+
+impl axum::extract::FromRequestParts<crate::state::AppState> for Impersonator {
+    type Rejection = AppError;
+
+    async fn from_request_parts(
+        parts: &mut axum::http::request::Parts,
+        _state: &crate::state::AppState,
+    ) -> Result<Self, Self::Rejection> {
+        parts
+            .extensions
+            .get::<Impersonator>()
+            .copied()
+            .ok_or_else(|| AppError::Auth("Impersonator not found in request".to_string()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::redact_value;
+
+    #[test]
+    fn only_ids_timestamps_and_metadata_survive() {
+        let mut body = json!({
+            "data": [{
+                "id": "0192f6a0-7c1e-7cc2-9a4b-3f2d8e6b1a90",
+                "project_id": null,
+                "task_ids": ["0192f6a0-1b2c-7d3e-8f40-5a6b7c8d9e0f"],
+                "encrypted_data": "ciphertext",
+                "title": "added later, in plaintext",
+                "email": "user@example.com",
+                "tags": ["private"],
+                "duration_minutes": 30,
+                "completed": true,
+                "status": "confirmed",
+                "updated_at": "2025-09-15T10:00:00Z",
+                "details": { "reason": "plaintext", "session_id": "0192f6a0-8d2f-7e13-b5c6-7d8e9f0a1b2c" },
+            }],
+            "message": null,
+            "meta": { "total_count": 1, "limit": null, "next_cursor": null, "generated_at": "2025-09-15T10:00:00Z" },
+        });
+
+        redact_value(&mut body);
+
+        assert_eq!(
+            body,
+            json!({
+                "data": [{
+                    "id": "0192f6a0-7c1e-7cc2-9a4b-3f2d8e6b1a90",
+                    "project_id": null,
+                    "task_ids": ["0192f6a0-1b2c-7d3e-8f40-5a6b7c8d9e0f"],
+                    "tags": [],
+                    "status": "confirmed",
+                    "updated_at": "2025-09-15T10:00:00Z",
+                    "details": { "session_id": "0192f6a0-8d2f-7e13-b5c6-7d8e9f0a1b2c" },
+                }],
+                "message": null,
+                "meta": { "total_count": 1, "limit": null, "next_cursor": null, "generated_at": "2025-09-15T10:00:00Z" },
+            })
+        );
+    }
+}
@@ -0,0 +1,45 @@
+use axum::{
+    extract::Request,
+    http::{
+        header::{AUTHORIZATION, CACHE_CONTROL, VARY},
+        HeaderValue, Method, StatusCode,
+    },
+    middleware::Next,
+    response::Response,
+};
+use tower_http::compression::{
+    predicate::{NotForContentType, Predicate, SizeAbove},
+    DefaultPredicate,
+};
+
+/// Responses smaller than this aren't worth compressing
+const MIN_COMPRESSED_BYTES: u16 = 1024;
+
+/// Which responses the compression layer may encode: not WebSocket upgrades, event
+/// streams (excluded by the default predicate) or archives that are compressed already
+pub fn compression_predicate() -> impl Predicate {
+    DefaultPredicate::new()
+        .and(SizeAbove::new(MIN_COMPRESSED_BYTES))
+        .and(NotForContentType::const_new("application/zip"))
+        .and(|status: StatusCode, _, _: &_, _: &_| status != StatusCode::SWITCHING_PROTOCOLS)
+}
+
+/// Default `Cache-Control` and `Vary` headers for handlers that don't set their own.
+/// Responses for a signed-in user are private and never stored, since they hold account
+/// data even when encrypted; anonymous reads such as public polls may be cached but must
+/// be revalidated. Every response varies by `Authorization`, so shared caches never serve
+/// one user's response to another.
+pub async fn cache_headers_middleware(req: Request, next: Next) -> Response {
+    let authenticated = req.headers().contains_key(AUTHORIZATION);
+    let cacheable = matches!(*req.method(), Method::GET | Method::HEAD);
+
+    let mut response = next.run(req).await;
+    let shared = cacheable && !authenticated && response.status().is_success();
+    let headers = response.headers_mut();
+    if !headers.contains_key(CACHE_CONTROL) {
+        let value = if shared { "no-cache" } else { "private, no-store" };
+        headers.insert(CACHE_CONTROL, HeaderValue::from_static(value));
+    }
+    headers.append(VARY, HeaderValue::from_static("authorization"));
+    response
+}
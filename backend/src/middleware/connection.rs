@@ -0,0 +1,33 @@
+use axum::http::request::Parts;
+use uuid::Uuid;
+
+use crate::errors::AppError;
+
+/// Header a client sets to the `connection_id` it received in `auth_success`
+pub const CONNECTION_ID_HEADER: &str = "x-connection-id";
+
+/// WebSocket connection that originated a request, if the client sent one.
+/// Every broadcast caused by the request skips this connection so the client
+/// doesn't receive echoes of its own writes.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct OriginConnection(pub Option<Uuid>);
+
+impl OriginConnection {
+    pub fn from_parts(parts: &Parts) -> Self {
+        Self(
+            parts
+                .headers
+                .get(CONNECTION_ID_HEADER)
+                .and_then(|v| v.to_str().ok())
+                .and_then(|s| Uuid::parse_str(s).ok()),
+        )
+    }
+}
+
+impl<S: Send + Sync> axum::extract::FromRequestParts<S> for OriginConnection {
+    type Rejection = AppError;
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        Ok(Self::from_parts(parts))
+    }
+}
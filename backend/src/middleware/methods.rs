@@ -0,0 +1,137 @@
+use axum::{
+    body::{to_bytes, Body},
+    extract::Request,
+    http::{
+        header::{ALLOW, CONTENT_LENGTH, CONTENT_TYPE, ETAG, IF_NONE_MATCH, LAST_MODIFIED},
+        HeaderName, HeaderValue, Method, StatusCode,
+    },
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use chrono::DateTime;
+use serde_json::Value;
+use sha2::{Digest, Sha256};
+
+use crate::{
+    errors::{AppError, ErrorCode},
+    middleware::preconditions::{http_date, record_etag},
+};
+
+/// Number of records on all pages of a list response, for clients that only send `HEAD`
+pub const TOTAL_COUNT_HEADER: HeaderName = HeaderName::from_static("x-total-count");
+
+/// Called when a route exists but has no handler for the request's method. The router adds
+/// the route's methods as `Allow`; plain `OPTIONS` requests never get here, since the CORS
+/// layer answers them.
+pub async fn method_not_allowed(method: Method) -> Response {
+    AppError::Coded(ErrorCode::MethodNotAllowed, format!("{} is not supported on this route", method)).into_response()
+}
+
+/// `ETag` and `X-Total-Count` on successful JSON responses, answering `If-None-Match` on reads
+/// with `304`. Tags hash the body without `meta.generated_at`, which changes on every request, so
+/// a response is only `304` while it's the same: a record's streaks, progress or attendees can
+/// change without its `updated_at` changing, and a redacted body is a different representation
+/// of the same version. A single record also gets a `Last-Modified`, and its tag leads with
+/// [`record_etag`], the version [`Preconditions`](crate::middleware::preconditions::Preconditions)
+/// compares `If-Match` against. The router runs `GET` handlers for `HEAD` and drops the body
+/// itself, so both get the same headers.
+pub async fn entity_headers_middleware(req: Request, next: Next) -> Response {
+    let readable = matches!(*req.method(), Method::GET | Method::HEAD);
+    let if_none_match = req.headers().get(IF_NONE_MATCH).cloned();
+
+    let response = next.run(req).await;
+    let json = response
+        .headers()
+        .get(CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .is_some_and(|value| value.starts_with("application/json"));
+    if !json || !response.status().is_success() || response.status() == StatusCode::NO_CONTENT {
+        return response;
+    }
+
+    let (mut parts, body) = response.into_parts();
+    let bytes = match to_bytes(body, usize::MAX).await {
+        Ok(bytes) => bytes,
+        Err(error) => return AppError::Internal(format!("Failed to read response body: {}", error)).into_response(),
+    };
+
+    let mut envelope = serde_json::from_slice::<Value>(&bytes).ok();
+    let updated_at = envelope
+        .as_ref()
+        .and_then(|value| value.get("data"))
+        .and_then(|data| data.get("updated_at"))
+        .and_then(Value::as_str)
+        .and_then(|value| DateTime::parse_from_rfc3339(value).ok());
+    let meta = envelope.as_mut().and_then(|value| value.get_mut("meta")).and_then(Value::as_object_mut);
+    let content = match meta {
+        Some(meta) => {
+            if let Some(total_count) = meta.get("total_count").and_then(Value::as_u64) {
+                parts.headers.insert(TOTAL_COUNT_HEADER, HeaderValue::from(total_count));
+            }
+            meta.remove("generated_at");
+            body_etag(envelope.unwrap_or_default().to_string().as_bytes())
+        }
+        None => body_etag(&bytes),
+    };
+    let etag = match updated_at {
+        Some(updated_at) => {
+            if let Ok(value) = HeaderValue::from_str(&http_date(&updated_at)) {
+                parts.headers.insert(LAST_MODIFIED, value);
+            }
+            versioned_etag(&record_etag(&updated_at), &content)
+        }
+        None => content,
+    };
+    if let Ok(value) = HeaderValue::from_str(&etag) {
+        parts.headers.insert(ETAG, value);
+    }
+
+    if readable
+        && parts.status == StatusCode::OK
+        && if_none_match.is_some_and(|value| etag_matches(&value, &etag))
+    {
+        parts.status = StatusCode::NOT_MODIFIED;
+        parts.headers.remove(CONTENT_TYPE);
+        parts.headers.remove(CONTENT_LENGTH);
+        return Response::from_parts(parts, Body::empty());
+    }
+    Response::from_parts(parts, Body::from(bytes))
+}
+
+fn body_etag(bytes: &[u8]) -> String {
+    format!("\"{}\"", hex::encode(&Sha256::digest(bytes)[..16]))
+}
+
+/// `"<version>-<content>"`, which [`Preconditions`](crate::middleware::preconditions::Preconditions)
+/// matches by its version alone
+fn versioned_etag(version: &str, content: &str) -> String {
+    format!("{}-{}", version.trim_end_matches('"'), content.trim_start_matches('"'))
+}
+
+/// Lists `OPTIONS` in every `Allow` header, since all routes answer it. Wraps the whole
+/// router rather than each route, because the router adds `Allow` outside route layers.
+pub async fn allow_options_middleware(req: Request, next: Next) -> Response {
+    let mut response = next.run(req).await;
+    let headers = response.headers_mut();
+    let Some(allow) = headers.get(ALLOW).and_then(|value| value.to_str().ok()) else {
+        return response;
+    };
+    if allow.is_empty() || allow.split(',').any(|method| method.trim() == "OPTIONS") {
+        return response;
+    }
+    if let Ok(value) = HeaderValue::from_str(&format!("{},OPTIONS", allow)) {
+        headers.insert(ALLOW, value);
+    }
+    response
+}
+
+/// Whether an `If-None-Match` list names `etag`; weak validators match too, as RFC 9110 asks
+fn etag_matches(if_none_match: &HeaderValue, etag: &str) -> bool {
+    let Ok(value) = if_none_match.to_str() else {
+        return false;
+    };
+    value
+        .split(',')
+        .map(str::trim)
+        .any(|candidate| candidate == "*" || candidate.trim_start_matches("W/") == etag)
+}
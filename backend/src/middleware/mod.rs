@@ -1,2 +1,15 @@
 pub mod auth;
 
+pub mod cache;
+
+pub mod connection;
+
+pub mod methods;
+
+pub mod preconditions;
+
+pub mod rate_limit;
+
+pub mod validation;
+
+pub mod versioning;
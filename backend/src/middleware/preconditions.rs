@@ -0,0 +1,85 @@
+use axum::http::{
+    header::{IF_MATCH, IF_UNMODIFIED_SINCE},
+    request::Parts,
+};
+use chrono::{DateTime, TimeZone, Utc};
+use sha2::{Digest, Sha256};
+
+use crate::errors::{AppError, ErrorCode};
+
+/// Version of a record, derived from its `updated_at` so a handler can check `If-Match` without
+/// rendering the response. Responses tag a record by its version followed by a hash of the body,
+/// and `If-Match` compares only the version. Tags only need to be unique per URL, so the id isn't
+/// part of it.
+pub fn record_etag<Tz: TimeZone>(updated_at: &DateTime<Tz>) -> String {
+    let digest = Sha256::digest(updated_at.timestamp_micros().to_be_bytes());
+    format!("\"{}\"", hex::encode(&digest[..16]))
+}
+
+/// `updated_at` as an HTTP date for `Last-Modified`
+pub fn http_date<Tz: TimeZone>(updated_at: &DateTime<Tz>) -> String {
+    updated_at.with_timezone(&Utc).format("%a, %d %b %Y %H:%M:%S GMT").to_string()
+}
+
+/// `If-Match` and `If-Unmodified-Since` sent with a write. Handlers call [`Preconditions::check`]
+/// once they've loaded the record they're about to change, so a client that read an older
+/// version gets a `412` instead of overwriting someone else's edit.
+#[derive(Debug, Clone, Default)]
+pub struct Preconditions {
+    if_match: Option<String>,
+    if_unmodified_since: Option<DateTime<Utc>>,
+}
+
+impl Preconditions {
+    pub fn from_parts(parts: &Parts) -> Result<Self, AppError> {
+        let if_match = parts
+            .headers
+            .get(IF_MATCH)
+            .map(|value| value.to_str().map(str::to_string))
+            .transpose()
+            .map_err(|_| AppError::Validation("If-Match must be a list of entity tags".to_string()))?;
+        // RFC 9110 has servers ignore an unparseable date rather than reject the request
+        let if_unmodified_since = parts
+            .headers
+            .get(IF_UNMODIFIED_SINCE)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| DateTime::parse_from_rfc2822(value).ok())
+            .map(|date| date.with_timezone(&Utc));
+        Ok(Self { if_match, if_unmodified_since })
+    }
+
+    /// Fails with `412` unless the record still matches what the client read. `If-Match`
+    /// takes precedence; `If-Unmodified-Since` only has second precision.
+    pub fn check<Tz: TimeZone>(&self, updated_at: &DateTime<Tz>) -> Result<(), AppError> {
+        let unchanged = match (&self.if_match, self.if_unmodified_since) {
+            (Some(if_match), _) => {
+                let etag = record_etag(updated_at);
+                let version = etag.trim_end_matches('"');
+                // Weak tags never match, since If-Match uses strong comparison
+                if_match.split(',').map(str::trim).any(|candidate| {
+                    candidate == "*"
+                        || candidate == etag
+                        || candidate.strip_prefix(version).is_some_and(|content| content.starts_with('-'))
+                })
+            }
+            (None, Some(since)) => updated_at.timestamp() <= since.timestamp(),
+            (None, None) => true,
+        };
+        if unchanged {
+            Ok(())
+        } else {
+            Err(AppError::Coded(
+                ErrorCode::PreconditionFailed,
+                "The record has changed since it was read".to_string(),
+            ))
+        }
+    }
+}
+
+impl<S: Send + Sync> axum::extract::FromRequestParts<S> for Preconditions {
+    type Rejection = AppError;
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        Self::from_parts(parts)
+    }
+}
@@ -0,0 +1,244 @@
+use axum::{
+    extract::{ConnectInfo, MatchedPath, Request, State},
+    http::{HeaderMap, HeaderName, HeaderValue},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use serde::Serialize;
+use std::{
+    collections::HashMap,
+    env,
+    net::{IpAddr, SocketAddr},
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+use uuid::Uuid;
+
+use crate::{errors::AppError, middleware::auth::AuthUser};
+
+const DEFAULT_REQUESTS_PER_MINUTE: u32 = 600;
+const DEFAULT_BURST: u32 = 100;
+/// How often buckets that have refilled completely are dropped
+const SWEEP_INTERVAL: Duration = Duration::from_secs(60);
+
+const RATE_LIMIT_LIMIT: HeaderName = HeaderName::from_static("ratelimit-limit");
+const RATE_LIMIT_REMAINING: HeaderName = HeaderName::from_static("ratelimit-remaining");
+const RATE_LIMIT_RESET: HeaderName = HeaderName::from_static("ratelimit-reset");
+
+/// Token bucket: up to `burst` requests at once, refilled at `requests_per_minute`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct RateLimitPolicy {
+    pub requests_per_minute: u32,
+    pub burst: u32,
+}
+
+impl RateLimitPolicy {
+    pub const fn new(requests_per_minute: u32, burst: u32) -> Self {
+        Self { requests_per_minute, burst }
+    }
+
+    fn refill_per_sec(&self) -> f64 {
+        self.requests_per_minute as f64 / 60.0
+    }
+}
+
+/// Who a bucket belongs to: the signed-in user, or the client address for anonymous requests
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+enum ClientKey {
+    User(Uuid),
+    Ip(IpAddr),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+struct BucketKey {
+    client: ClientKey,
+    /// Route pattern of an override, which counts separately from the default bucket
+    route: Option<&'static str>,
+}
+
+#[derive(Debug)]
+struct Bucket {
+    tokens: f64,
+    updated_at: Instant,
+}
+
+/// Outcome of taking a token, as reported in the rate limit headers
+struct Decision {
+    allowed: bool,
+    limit: u32,
+    remaining: u32,
+    /// Seconds until the bucket is full again
+    reset_secs: u64,
+    /// Seconds until the next request is allowed, when this one wasn't
+    retry_after_secs: u64,
+}
+
+struct Buckets {
+    buckets: HashMap<BucketKey, Bucket>,
+    swept_at: Instant,
+}
+
+/// In-memory per-client rate limiter. Each replica keeps its own buckets, so the effective
+/// limit behind a load balancer is the configured one times the replica count.
+#[derive(Clone)]
+pub struct RateLimiter {
+    default: Option<RateLimitPolicy>,
+    /// Keyed by route pattern; `None` exempts the route
+    overrides: Arc<HashMap<&'static str, Option<RateLimitPolicy>>>,
+    trust_proxy: bool,
+    buckets: Arc<Mutex<Buckets>>,
+}
+
+impl RateLimiter {
+    /// Read `RATE_LIMIT_PER_MINUTE`, `RATE_LIMIT_BURST` and `RATE_LIMIT_TRUST_PROXY`;
+    /// a limit of 0 disables the default limit
+    pub fn from_env() -> Self {
+        let number = |key: &str, default: u32| {
+            env::var(key)
+                .ok()
+                .and_then(|v| v.trim().parse::<u32>().ok())
+                .unwrap_or(default)
+        };
+        let requests_per_minute = number("RATE_LIMIT_PER_MINUTE", DEFAULT_REQUESTS_PER_MINUTE);
+        let burst = number("RATE_LIMIT_BURST", DEFAULT_BURST);
+        let trust_proxy = env::var("RATE_LIMIT_TRUST_PROXY")
+            .map(|v| matches!(v.trim(), "1" | "true"))
+            .unwrap_or(false);
+
+        Self {
+            default: (requests_per_minute > 0 && burst > 0).then(|| RateLimitPolicy::new(requests_per_minute, burst)),
+            overrides: Arc::new(HashMap::new()),
+            trust_proxy,
+            buckets: Arc::new(Mutex::new(Buckets { buckets: HashMap::new(), swept_at: Instant::now() })),
+        }
+    }
+
+    /// Give a route its own bucket with a different policy, e.g. a tighter one for expensive endpoints
+    pub fn with_override(mut self, route: &'static str, policy: RateLimitPolicy) -> Self {
+        Arc::make_mut(&mut self.overrides).insert(route, Some(policy));
+        self
+    }
+
+    /// The policy of routes without an override, `None` when they aren't limited
+    pub fn default_policy(&self) -> Option<RateLimitPolicy> {
+        self.default
+    }
+
+    /// Never limit a route, e.g. health probes
+    pub fn without_limit(mut self, route: &'static str) -> Self {
+        Arc::make_mut(&mut self.overrides).insert(route, None);
+        self
+    }
+
+    /// Take a token from a user's bucket for `route` from within a handler, for hooks that act
+    /// for a user other than the caller, e.g. mail to a user's inbound address
+    pub fn check_user(&self, user_id: Uuid, route: &'static str, policy: RateLimitPolicy) -> Result<(), AppError> {
+        let decision = self.take(BucketKey { client: ClientKey::User(user_id), route: Some(route) }, policy);
+        if decision.allowed {
+            Ok(())
+        } else {
+            Err(AppError::RateLimited(format!("Too many requests, retry in {} seconds", decision.retry_after_secs)))
+        }
+    }
+
+    fn policy_for(&self, route: Option<&str>) -> Option<(RateLimitPolicy, Option<&'static str>)> {
+        match route.and_then(|route| self.overrides.get_key_value(route)) {
+            Some((route, policy)) => policy.map(|policy| (policy, Some(*route))),
+            None => self.default.map(|policy| (policy, None)),
+        }
+    }
+
+    /// Client address, from `X-Forwarded-For` when a trusted proxy sets it
+    fn client_ip(&self, headers: &HeaderMap, peer: Option<IpAddr>) -> Option<IpAddr> {
+        if self.trust_proxy {
+            // The proxy appends the address it saw, so the last entry is the one it vouches for
+            let forwarded = headers
+                .get("x-forwarded-for")
+                .and_then(|v| v.to_str().ok())
+                .and_then(|v| v.rsplit(',').next())
+                .and_then(|ip| ip.trim().parse().ok());
+            if forwarded.is_some() {
+                return forwarded;
+            }
+        }
+        peer
+    }
+
+    fn take(&self, key: BucketKey, policy: RateLimitPolicy) -> Decision {
+        let now = Instant::now();
+        let capacity = policy.burst as f64;
+        let refill_per_sec = policy.refill_per_sec();
+
+        let mut state = self.buckets.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+        if now.duration_since(state.swept_at) >= SWEEP_INTERVAL {
+            // A bucket idle long enough to be full again behaves exactly like a new one
+            state.buckets.retain(|_, bucket| {
+                bucket.tokens + now.duration_since(bucket.updated_at).as_secs_f64() * refill_per_sec < capacity
+            });
+            state.swept_at = now;
+        }
+
+        let bucket = state.buckets.entry(key).or_insert(Bucket { tokens: capacity, updated_at: now });
+        let elapsed = now.duration_since(bucket.updated_at).as_secs_f64();
+        bucket.tokens = (bucket.tokens + elapsed * refill_per_sec).min(capacity);
+        bucket.updated_at = now;
+
+        let allowed = bucket.tokens >= 1.0;
+        if allowed {
+            bucket.tokens -= 1.0;
+        }
+        let secs_until = |tokens: f64| ((tokens - bucket.tokens).max(0.0) / refill_per_sec).ceil() as u64;
+
+        Decision {
+            allowed,
+            limit: policy.burst,
+            remaining: bucket.tokens.floor() as u32,
+            reset_secs: secs_until(capacity),
+            retry_after_secs: if allowed { 0 } else { secs_until(1.0).max(1) },
+        }
+    }
+}
+
+/// Rate limit requests per user, or per client address before authentication. Runs after
+/// `auth_middleware` on protected routes so signed-in users are keyed by their id.
+pub async fn rate_limit_middleware(
+    State(limiter): State<RateLimiter>,
+    req: Request,
+    next: Next,
+) -> Response {
+    let route = req.extensions().get::<MatchedPath>().map(|path| path.as_str());
+    let Some((policy, override_route)) = limiter.policy_for(route) else {
+        return next.run(req).await;
+    };
+
+    let client = match req.extensions().get::<AuthUser>() {
+        Some(AuthUser(user)) => Some(ClientKey::User(user.id)),
+        None => {
+            let peer = req.extensions().get::<ConnectInfo<SocketAddr>>().map(|info| info.0.ip());
+            limiter.client_ip(req.headers(), peer).map(ClientKey::Ip)
+        }
+    };
+    let Some(client) = client else {
+        return next.run(req).await;
+    };
+
+    let decision = limiter.take(BucketKey { client, route: override_route }, policy);
+    let mut response = if decision.allowed {
+        next.run(req).await
+    } else {
+        tracing::warn!("Rate limit exceeded for {:?} on {}", client, route.unwrap_or("unknown route"));
+        let mut response = AppError::RateLimited(format!(
+            "Too many requests, retry in {} seconds",
+            decision.retry_after_secs
+        ))
+        .into_response();
+        response.headers_mut().insert(axum::http::header::RETRY_AFTER, HeaderValue::from(decision.retry_after_secs));
+        response
+    };
+
+    let headers = response.headers_mut();
+    headers.insert(RATE_LIMIT_LIMIT, HeaderValue::from(decision.limit));
+    headers.insert(RATE_LIMIT_REMAINING, HeaderValue::from(decision.remaining));
+    headers.insert(RATE_LIMIT_RESET, HeaderValue::from(decision.reset_secs));
+    response
+}
@@ -0,0 +1,86 @@
+use axum::{
+    body::Bytes,
+    extract::{FromRequest, OptionalFromRequest, Request},
+    http::header,
+};
+use serde::de::DeserializeOwned;
+use serde_json::error::Category;
+
+use crate::{
+    errors::{AppError, ErrorCode, FieldError},
+    models::Validate,
+};
+
+/// A JSON body that is deserialized and then validated. Fields of the wrong type, missing fields
+/// and failed checks come back as `422` with one entry per field; malformed JSON as `400`.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ValidatedJson<T>(pub T);
+
+impl<S, T> FromRequest<S> for ValidatedJson<T>
+where
+    S: Send + Sync,
+    T: DeserializeOwned + Validate,
+{
+    type Rejection = AppError;
+
+    async fn from_request(req: Request, state: &S) -> Result<Self, Self::Rejection> {
+        if !is_json(&req) {
+            return Err(AppError::Coded(
+                ErrorCode::ValidationInvalidFormat,
+                "Expected a request body with Content-Type: application/json".to_string(),
+            ));
+        }
+        let body = Bytes::from_request(req, state)
+            .await
+            .map_err(|rejection| AppError::Coded(ErrorCode::ValidationInvalidFormat, rejection.body_text()))?;
+        let value: T = deserialize(&body)?;
+        value.check()?;
+        Ok(Self(value))
+    }
+}
+
+/// Optional bodies are absent when the request has no `Content-Type`
+impl<S, T> OptionalFromRequest<S> for ValidatedJson<T>
+where
+    S: Send + Sync,
+    T: DeserializeOwned + Validate,
+{
+    type Rejection = AppError;
+
+    async fn from_request(req: Request, state: &S) -> Result<Option<Self>, Self::Rejection> {
+        if !req.headers().contains_key(header::CONTENT_TYPE) {
+            return Ok(None);
+        }
+        <Self as FromRequest<S>>::from_request(req, state).await.map(Some)
+    }
+}
+
+fn is_json(req: &Request) -> bool {
+    let Some(content_type) = req.headers().get(header::CONTENT_TYPE).and_then(|value| value.to_str().ok()) else {
+        return false;
+    };
+    let essence = content_type.split(';').next().unwrap_or_default().trim().to_ascii_lowercase();
+    essence == "application/json" || (essence.starts_with("application/") && essence.ends_with("+json"))
+}
+
+/// Deserialize keeping track of where it failed, so type errors name the field
+fn deserialize<T: DeserializeOwned>(body: &[u8]) -> Result<T, AppError> {
+    let deserializer = &mut serde_json::Deserializer::from_slice(body);
+    serde_path_to_error::deserialize(deserializer).map_err(|error| {
+        let path = error.path().to_string();
+        let inner = error.into_inner();
+        if inner.classify() != Category::Data {
+            return AppError::Coded(ErrorCode::ValidationInvalidFormat, format!("Invalid JSON: {}", inner));
+        }
+        // serde appends the position, which means nothing to the client
+        let message = inner.to_string();
+        let message = message.split(" at line ").next().unwrap_or_default().to_string();
+        // A missing field is reported on the object that lacks it
+        let field = match message.strip_prefix("missing field `").and_then(|rest| rest.strip_suffix('`')) {
+            Some(missing) if path == "." => missing.to_string(),
+            Some(missing) => format!("{}.{}", path, missing),
+            None => path,
+        };
+        AppError::InvalidFields(vec![FieldError { field, message }])
+    })
+}
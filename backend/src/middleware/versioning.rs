@@ -0,0 +1,150 @@
+//! REST API versions. Every route is served under `/api/v<N>/...`, and also unversioned under
+//! `/api/...`, where the version comes from the `API-Version` header or defaults to
+//! [`DEFAULT_API_VERSION`] so clients written before versioning keep working. Breaking changes
+//! ship as a new version; an old one is announced with `Deprecation` and `Sunset` headers for a
+//! while before it's dropped from the supported range.
+
+use std::{collections::BTreeMap, env};
+
+use axum::{
+    extract::{Request, State},
+    http::{request::Parts, HeaderName, HeaderValue, Uri},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use chrono::{DateTime, Utc};
+
+use crate::{
+    errors::{AppError, ErrorCode},
+    middleware::preconditions::http_date,
+};
+
+/// Oldest version the server still serves
+pub const MIN_API_VERSION: u32 = 1;
+/// Newest version the server serves
+pub const CURRENT_API_VERSION: u32 = 1;
+/// Version of unversioned requests without an `API-Version` header. It stays at 1 when newer
+/// versions ship, since those clients predate versioning.
+pub const DEFAULT_API_VERSION: u32 = 1;
+
+pub const API_VERSION_HEADER: HeaderName = HeaderName::from_static("api-version");
+const DEPRECATION_HEADER: HeaderName = HeaderName::from_static("deprecation");
+const SUNSET_HEADER: HeaderName = HeaderName::from_static("sunset");
+
+const API_PREFIX: &str = "/api";
+
+/// The version a request was served in; handlers that differ between versions extract it
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct ApiVersion(pub u32);
+
+/// Sunset dates of deprecated versions, from `API_V<N>_SUNSET` (RFC 3339), so operators can
+/// announce a version's end without a release
+#[derive(Debug, Clone, Default)]
+pub struct ApiVersions {
+    sunsets: BTreeMap<u32, DateTime<Utc>>,
+}
+
+impl ApiVersions {
+    pub fn from_env() -> Self {
+        let mut sunsets = BTreeMap::new();
+        for version in MIN_API_VERSION..=CURRENT_API_VERSION {
+            let key = format!("API_V{}_SUNSET", version);
+            let Ok(value) = env::var(&key) else {
+                continue;
+            };
+            match DateTime::parse_from_rfc3339(value.trim()) {
+                Ok(sunset) => {
+                    sunsets.insert(version, sunset.with_timezone(&Utc));
+                }
+                Err(_) => tracing::warn!("Ignoring {}={:?}: expected an RFC 3339 date", key, value),
+            }
+        }
+        Self { sunsets }
+    }
+
+    /// When a deprecated version stops being served
+    pub fn sunset(&self, version: u32) -> Option<DateTime<Utc>> {
+        self.sunsets.get(&version).copied()
+    }
+
+    pub fn sunsets(&self) -> &BTreeMap<u32, DateTime<Utc>> {
+        &self.sunsets
+    }
+}
+
+/// `/api/v2/projects` as version 2 and `/api/projects`; `None` for unversioned paths
+fn split_version(path: &str) -> Option<(&str, String)> {
+    let rest = path.strip_prefix(API_PREFIX)?.strip_prefix("/v")?;
+    let digits = rest.find('/').map_or(rest, |end| &rest[..end]);
+    if digits.is_empty() || !digits.bytes().all(|byte| byte.is_ascii_digit()) {
+        return None;
+    }
+    Some((digits, format!("{}{}", API_PREFIX, &rest[digits.len()..])))
+}
+
+fn unsupported(version: &str) -> AppError {
+    AppError::Coded(
+        ErrorCode::UnsupportedApiVersion,
+        format!(
+            "API version {} is not supported; use a version from {} to {}",
+            version, MIN_API_VERSION, CURRENT_API_VERSION
+        ),
+    )
+}
+
+/// Resolve the version of `/api` requests, strip it from the path so the router only knows
+/// unversioned routes, and report it in the response. Wraps the whole router, since the path
+/// has to change before routing.
+pub async fn api_version_middleware(State(versions): State<ApiVersions>, mut req: Request, next: Next) -> Response {
+    let path = req.uri().path();
+    if path != API_PREFIX && !path.starts_with("/api/") {
+        return next.run(req).await;
+    }
+
+    let (requested, path) = match split_version(path) {
+        Some((version, path)) => (Some(version.to_string()), Some(path)),
+        None => {
+            let header = req.headers().get(API_VERSION_HEADER).map(|value| value.to_str().unwrap_or_default().trim().to_string());
+            (header, None)
+        }
+    };
+    let version = match requested {
+        Some(requested) => match requested.parse::<u32>() {
+            Ok(version) if (MIN_API_VERSION..=CURRENT_API_VERSION).contains(&version) => version,
+            _ => return unsupported(&requested).into_response(),
+        },
+        None => DEFAULT_API_VERSION,
+    };
+
+    if let Some(path) = path {
+        let path_and_query = match req.uri().query() {
+            Some(query) => format!("{}?{}", path, query),
+            None => path,
+        };
+        let mut parts = req.uri().clone().into_parts();
+        parts.path_and_query = path_and_query.parse().ok();
+        if let Ok(uri) = Uri::from_parts(parts) {
+            *req.uri_mut() = uri;
+        }
+    }
+    req.extensions_mut().insert(ApiVersion(version));
+
+    let mut response = next.run(req).await;
+    let headers = response.headers_mut();
+    headers.insert(API_VERSION_HEADER, HeaderValue::from(version));
+    if let Some(sunset) = versions.sunset(version) {
+        headers.insert(DEPRECATION_HEADER, HeaderValue::from_static("true"));
+        if let Ok(value) = HeaderValue::from_str(&http_date(&sunset)) {
+            headers.insert(SUNSET_HEADER, value);
+        }
+    }
+    response
+}
+
+impl<S: Send + Sync> axum::extract::FromRequestParts<S> for ApiVersion {
+    type Rejection = std::convert::Infallible;
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        Ok(parts.extensions.get::<ApiVersion>().copied().unwrap_or(ApiVersion(DEFAULT_API_VERSION)))
+    }
+}
@@ -0,0 +1,147 @@
+//! Helpers for changing a column without downtime, in three releases:
+//!
+//! 1. **Expand**: a migration adds the new column as nullable and installs a dual-write
+//!    trigger with [`add_dual_write`], so rows written by replicas still running the
+//!    previous release keep the new column in sync. The release adds a [`Backfill`] to
+//!    [`BACKFILLS`]; the backfill job then fills in existing rows in the background.
+//! 2. **Migrate**: code switches to reading the new column once the backfill completed.
+//! 3. **Contract**: a migration calls [`require_backfilled`] first, which refuses to run
+//!    until the backfill finished, then drops the trigger with [`drop_dual_write`] and
+//!    the old column.
+//!
+//! Contract migrations must ship in a later release than their expand step; the
+//! backfill job only starts after migrations ran, so a contract in the same release
+//! would never pass its gate.
+
+use sea_orm::{ConnectionTrait, DbBackend, DbErr, FromQueryResult, Statement};
+use sea_orm_migration::SchemaManager;
+
+/// A batched rewrite of existing rows, tracked by name in `schema_backfills`
+#[allow(dead_code)]
+#[derive(Debug, Clone, Copy)]
+pub struct Backfill {
+    /// Unique, never reused name, e.g. `calendar_events-encrypted_blob`
+    pub name: &'static str,
+    pub table: &'static str,
+    /// SQL assignments applied to each pending row, e.g. `encrypted_blob = decode(encrypted_data, 'base64')`
+    pub set: &'static str,
+    /// SQL predicate matching rows that still need the backfill, e.g. `encrypted_blob IS NULL`
+    pub pending: &'static str,
+}
+
+/// Backfills of expand steps that haven't been contracted yet; remove an entry
+/// together with the contract migration that depends on it
+pub const BACKFILLS: &[Backfill] = &[];
+
+fn trigger_name(table: &str, column: &str) -> String {
+    format!("{}_{}_dual_write", table.replace('.', "_"), column)
+}
+
+/// Keep `column` derived from `sources` on every insert and on updates touching them.
+/// `expression` is evaluated against the written row as `NEW`, e.g. `decode(NEW.encrypted_data, 'base64')`.
+#[allow(dead_code)]
+pub async fn add_dual_write(
+    manager: &SchemaManager<'_>,
+    table: &str,
+    column: &str,
+    sources: &[&str],
+    expression: &str,
+) -> Result<(), DbErr> {
+    let name = trigger_name(table, column);
+    let db = manager.get_connection();
+
+    db.execute_unprepared(&format!(
+        r#"
+        CREATE OR REPLACE FUNCTION {name}() RETURNS trigger AS $$
+        BEGIN
+            NEW.{column} := {expression};
+            RETURN NEW;
+        END
+        $$ LANGUAGE plpgsql;
+        "#
+    ))
+    .await?;
+
+    db.execute_unprepared(&format!(
+        "DROP TRIGGER IF EXISTS {name} ON {table}; \
+         CREATE TRIGGER {name} BEFORE INSERT OR UPDATE OF {sources} ON {table} \
+         FOR EACH ROW EXECUTE FUNCTION {name}();",
+        sources = sources.join(", "),
+    ))
+    .await?;
+
+    Ok(())
+}
+
+/// Remove a trigger installed by [`add_dual_write`]
+#[allow(dead_code)]
+pub async fn drop_dual_write(manager: &SchemaManager<'_>, table: &str, column: &str) -> Result<(), DbErr> {
+    let name = trigger_name(table, column);
+    manager
+        .get_connection()
+        .execute_unprepared(&format!(
+            "DROP TRIGGER IF EXISTS {name} ON {table}; DROP FUNCTION IF EXISTS {name}();"
+        ))
+        .await?;
+    Ok(())
+}
+
+#[derive(Debug, FromQueryResult)]
+pub struct BackfillProgress {
+    pub total_rows: i64,
+    pub processed_rows: i64,
+    pub completed: bool,
+}
+
+/// Progress recorded for a backfill, if it was ever started
+pub async fn progress<C: ConnectionTrait>(db: &C, name: &str) -> Result<Option<BackfillProgress>, DbErr> {
+    BackfillProgress::find_by_statement(Statement::from_sql_and_values(
+        DbBackend::Postgres,
+        "SELECT total_rows, processed_rows, completed_at IS NOT NULL AS completed FROM schema_backfills WHERE name = $1",
+        [name.into()],
+    ))
+    .one(db)
+    .await
+}
+
+/// Gate for contract migrations: fail unless the backfill completed and no rows are
+/// left pending, e.g. ones written before the dual-write trigger existed
+#[allow(dead_code)]
+pub async fn require_backfilled(manager: &SchemaManager<'_>, backfill: &Backfill) -> Result<(), DbErr> {
+    let db = manager.get_connection();
+    match progress(db, backfill.name).await? {
+        Some(progress) if progress.completed => {}
+        Some(progress) => {
+            return Err(DbErr::Migration(format!(
+                "Backfill '{}' has not completed ({}/{} rows); let the previous release finish it first",
+                backfill.name, progress.processed_rows, progress.total_rows
+            )));
+        }
+        None => {
+            return Err(DbErr::Migration(format!(
+                "Backfill '{}' was never started; deploy the release that expands '{}' first",
+                backfill.name, backfill.table
+            )));
+        }
+    }
+
+    let remaining = db
+        .query_one(Statement::from_string(
+            DbBackend::Postgres,
+            format!(
+                "SELECT EXISTS (SELECT 1 FROM {} WHERE {}) AS remaining",
+                backfill.table, backfill.pending
+            ),
+        ))
+        .await?
+        .and_then(|row| row.try_get::<bool>("", "remaining").ok())
+        .unwrap_or(false);
+    if remaining {
+        return Err(DbErr::Migration(format!(
+            "Backfill '{}' completed but rows in '{}' are still pending",
+            backfill.name, backfill.table
+        )));
+    }
+
+    Ok(())
+}
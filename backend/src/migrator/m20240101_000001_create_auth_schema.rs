@@ -1,4 +1,5 @@
 use sea_orm_migration::prelude::*;
+use super::portable::is_sqlite;
 
 #[derive(DeriveMigrationName)]
 pub struct Migration;
@@ -6,6 +7,11 @@ pub struct Migration;
 #[async_trait::async_trait]
 impl MigrationTrait for Migration {
     async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // SQLite attaches a separate database file as `auth` instead
+        if is_sqlite(manager) {
+            return Ok(());
+        }
+
         // Create the auth schema for authentication-related tables
         manager
             .get_connection()
@@ -16,6 +22,10 @@ impl MigrationTrait for Migration {
     }
 
     async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        if is_sqlite(manager) {
+            return Ok(());
+        }
+
         // Drop the auth schema
         manager
             .get_connection()
@@ -1,4 +1,5 @@
 use sea_orm_migration::prelude::*;
+use super::portable::PortableColumnDef;
 
 #[derive(DeriveMigrationName)]
 pub struct Migration;
@@ -30,7 +31,7 @@ impl MigrationTrait for Migration {
                             .uuid()
                             .not_null()
                             .primary_key()
-                            .extra("DEFAULT gen_random_uuid()".to_string()),
+                            .default_uuid(manager, "gen_random_uuid()"),
                     )
                     .col(
                         ColumnDef::new(Users::Email)
@@ -44,25 +45,25 @@ impl MigrationTrait for Migration {
                         ColumnDef::new(Users::CreatedAt)
                             .timestamp_with_time_zone()
                             .not_null()
-                            .extra("DEFAULT NOW()".to_string()),
+                            .default_now(manager),
                     )
                     .col(
                         ColumnDef::new(Users::UpdatedAt)
                             .timestamp_with_time_zone()
                             .not_null()
-                            .extra("DEFAULT NOW()".to_string()),
+                            .default_now(manager),
                     )
                     .col(
                         ColumnDef::new(Users::RawAppMetaData)
                             .json()
                             .not_null()
-                            .extra("DEFAULT '{}'::jsonb".to_string()),
+                            .default_json(manager, "{}"),
                     )
                     .col(
                         ColumnDef::new(Users::RawUserMetaData)
                             .json()
                             .not_null()
-                            .extra("DEFAULT '{}'::jsonb".to_string()),
+                            .default_json(manager, "{}"),
                     )
                     .col(
                         ColumnDef::new(Users::IsSuperAdmin)
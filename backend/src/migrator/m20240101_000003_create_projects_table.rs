@@ -1,4 +1,5 @@
 use sea_orm_migration::prelude::*;
+use super::portable::{PortableColumnDef, PortableTableCreate};
 
 #[derive(DeriveMigrationName)]
 pub struct Migration;
@@ -39,7 +40,7 @@ impl MigrationTrait for Migration {
                             .uuid()
                             .not_null()
                             .primary_key()
-                            .extra("DEFAULT gen_random_uuid()".to_string()),
+                            .default_uuid(manager, "gen_random_uuid()"),
                     )
                     .col(ColumnDef::new(Projects::UserId).uuid().not_null())
                     .col(ColumnDef::new(Projects::EncryptedData).string().not_null())
@@ -68,15 +69,16 @@ impl MigrationTrait for Migration {
                         ColumnDef::new(Projects::CreatedAt)
                             .timestamp_with_time_zone()
                             .not_null()
-                            .extra("DEFAULT NOW()".to_string()),
+                            .default_now(manager),
                     )
                     .col(
                         ColumnDef::new(Projects::UpdatedAt)
                             .timestamp_with_time_zone()
                             .not_null()
-                            .extra("DEFAULT NOW()".to_string()),
+                            .default_now(manager),
                     )
-                    .foreign_key(
+                    .auth_foreign_key(
+                        manager,
                         ForeignKey::create()
                             .name("fk-projects-user_id")
                             .from(Projects::Table, Projects::UserId)
@@ -1,4 +1,5 @@
 use sea_orm_migration::prelude::*;
+use super::portable::{PortableColumnDef, PortableTableCreate};
 
 #[derive(DeriveMigrationName)]
 pub struct Migration;
@@ -42,7 +43,7 @@ impl MigrationTrait for Migration {
                             .uuid()
                             .not_null()
                             .primary_key()
-                            .extra("DEFAULT gen_random_uuid()".to_string()),
+                            .default_uuid(manager, "gen_random_uuid()"),
                     )
                     .col(ColumnDef::new(CanDoList::UserId).uuid().not_null())
                     .col(ColumnDef::new(CanDoList::ProjectId).uuid())
@@ -59,15 +60,16 @@ impl MigrationTrait for Migration {
                         ColumnDef::new(CanDoList::CreatedAt)
                             .timestamp_with_time_zone()
                             .not_null()
-                            .extra("DEFAULT NOW()".to_string()),
+                            .default_now(manager),
                     )
                     .col(
                         ColumnDef::new(CanDoList::UpdatedAt)
                             .timestamp_with_time_zone()
                             .not_null()
-                            .extra("DEFAULT NOW()".to_string()),
+                            .default_now(manager),
                     )
-                    .foreign_key(
+                    .auth_foreign_key(
+                        manager,
                         ForeignKey::create()
                             .name("fk-can_do_list-user_id")
                             .from(CanDoList::Table, CanDoList::UserId)
@@ -1,4 +1,5 @@
 use sea_orm_migration::prelude::*;
+use super::portable::{PortableColumnDef, PortableTableCreate};
 
 #[derive(DeriveMigrationName)]
 pub struct Migration;
@@ -35,7 +36,7 @@ impl MigrationTrait for Migration {
                             .uuid()
                             .not_null()
                             .primary_key()
-                            .extra("DEFAULT gen_random_uuid()".to_string()),
+                            .default_uuid(manager, "gen_random_uuid()"),
                     )
                     .col(ColumnDef::new(Calendars::UserId).uuid().not_null())
                     .col(ColumnDef::new(Calendars::EncryptedData).string().not_null())
@@ -51,15 +52,16 @@ impl MigrationTrait for Migration {
                         ColumnDef::new(Calendars::CreatedAt)
                             .timestamp_with_time_zone()
                             .not_null()
-                            .extra("DEFAULT NOW()".to_string()),
+                            .default_now(manager),
                     )
                     .col(
                         ColumnDef::new(Calendars::UpdatedAt)
                             .timestamp_with_time_zone()
                             .not_null()
-                            .extra("DEFAULT NOW()".to_string()),
+                            .default_now(manager),
                     )
-                    .foreign_key(
+                    .auth_foreign_key(
+                        manager,
                         ForeignKey::create()
                             .name("fk-calendars-user_id")
                             .from(Calendars::Table, Calendars::UserId)
@@ -1,4 +1,5 @@
 use sea_orm_migration::prelude::*;
+use super::portable::{PortableColumnDef, PortableTableCreate};
 
 #[derive(DeriveMigrationName)]
 pub struct Migration;
@@ -34,7 +35,7 @@ impl MigrationTrait for Migration {
                             .uuid()
                             .not_null()
                             .primary_key()
-                            .extra("DEFAULT gen_random_uuid()".to_string()),
+                            .default_uuid(manager, "gen_random_uuid()"),
                     )
                     .col(ColumnDef::new(CalendarEvents::UserId).uuid().not_null())
                     .col(ColumnDef::new(CalendarEvents::EncryptedData).string().not_null())
@@ -44,15 +45,16 @@ impl MigrationTrait for Migration {
                         ColumnDef::new(CalendarEvents::CreatedAt)
                             .timestamp_with_time_zone()
                             .not_null()
-                            .extra("DEFAULT NOW()".to_string()),
+                            .default_now(manager),
                     )
                     .col(
                         ColumnDef::new(CalendarEvents::UpdatedAt)
                             .timestamp_with_time_zone()
                             .not_null()
-                            .extra("DEFAULT NOW()".to_string()),
+                            .default_now(manager),
                     )
-                    .foreign_key(
+                    .auth_foreign_key(
+                        manager,
                         ForeignKey::create()
                             .name("fk-calendar_events-user_id")
                             .from(CalendarEvents::Table, CalendarEvents::UserId)
@@ -1,4 +1,5 @@
 use sea_orm_migration::prelude::*;
+use super::portable::PortableTableCreate;
 
 #[derive(DeriveMigrationName)]
 pub struct Migration;
@@ -32,7 +33,8 @@ impl MigrationTrait for Migration {
                             .not_null()
                             .default(Expr::current_timestamp()),
                     )
-                    .foreign_key(
+                    .auth_foreign_key(
+                        manager,
                         ForeignKey::create()
                             .name("fk_user_settings_user")
                             .from(UserSettings::Table, UserSettings::UserId)
@@ -0,0 +1,81 @@
+use sea_orm_migration::prelude::*;
+use super::portable::{PortableColumnDef, PortableTableCreate};
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[derive(DeriveIden)]
+enum DeletedRecords {
+    Table,
+    Id,
+    UserId,
+    TableName,
+    RecordId,
+    DeletedAt,
+}
+
+#[derive(DeriveIden)]
+enum Users {
+    Table,
+    Id,
+}
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // Tombstones for hard-deleted records, consumed by the delta sync endpoint
+        manager
+            .create_table(
+                Table::create()
+                    .table(DeletedRecords::Table)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(DeletedRecords::Id)
+                            .uuid()
+                            .not_null()
+                            .primary_key()
+                            .default_uuid(manager, "gen_random_uuid()"),
+                    )
+                    .col(ColumnDef::new(DeletedRecords::UserId).uuid().not_null())
+                    .col(ColumnDef::new(DeletedRecords::TableName).string().not_null())
+                    .col(ColumnDef::new(DeletedRecords::RecordId).uuid().not_null())
+                    .col(
+                        ColumnDef::new(DeletedRecords::DeletedAt)
+                            .timestamp_with_time_zone()
+                            .not_null()
+                            .default_now(manager),
+                    )
+                    .auth_foreign_key(
+                        manager,
+                        ForeignKey::create()
+                            .name("fk-deleted_records-user_id")
+                            .from(DeletedRecords::Table, DeletedRecords::UserId)
+                            .to((Alias::new("auth"), Users::Table), Users::Id)
+                            .on_delete(ForeignKeyAction::Cascade)
+                            .on_update(ForeignKeyAction::Cascade),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        // Create indexes
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx-deleted_records-user_deleted_at")
+                    .table(DeletedRecords::Table)
+                    .col(DeletedRecords::UserId)
+                    .col(DeletedRecords::DeletedAt)
+                    .to_owned(),
+            )
+            .await?;
+
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(DeletedRecords::Table).if_exists().to_owned())
+            .await
+    }
+}
@@ -0,0 +1,81 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[derive(DeriveIden)]
+enum Projects {
+    Table,
+    DeletedAt,
+}
+
+#[derive(DeriveIden)]
+enum CanDoList {
+    Table,
+    DeletedAt,
+}
+
+#[derive(DeriveIden)]
+enum Calendars {
+    Table,
+    DeletedAt,
+}
+
+#[derive(DeriveIden)]
+enum CalendarEvents {
+    Table,
+    DeletedAt,
+}
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        let tables: [(DynIden, DynIden, &str); 4] = [
+            (Projects::Table.into_iden(), Projects::DeletedAt.into_iden(), "idx-projects-deleted_at"),
+            (CanDoList::Table.into_iden(), CanDoList::DeletedAt.into_iden(), "idx-can_do_list-deleted_at"),
+            (Calendars::Table.into_iden(), Calendars::DeletedAt.into_iden(), "idx-calendars-deleted_at"),
+            (CalendarEvents::Table.into_iden(), CalendarEvents::DeletedAt.into_iden(), "idx-calendar_events-deleted_at"),
+        ];
+
+        for (table, column, index_name) in tables {
+            manager
+                .alter_table(
+                    Table::alter()
+                        .table(table.clone())
+                        .add_column_if_not_exists(ColumnDef::new(column.clone()).timestamp_with_time_zone())
+                        .to_owned(),
+                )
+                .await?;
+
+            // Used by the trash listing and the purge job
+            manager
+                .create_index(
+                    Index::create()
+                        .name(index_name)
+                        .table(table)
+                        .col(column)
+                        .to_owned(),
+                )
+                .await?;
+        }
+
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        let tables: [(DynIden, DynIden); 4] = [
+            (Projects::Table.into_iden(), Projects::DeletedAt.into_iden()),
+            (CanDoList::Table.into_iden(), CanDoList::DeletedAt.into_iden()),
+            (Calendars::Table.into_iden(), Calendars::DeletedAt.into_iden()),
+            (CalendarEvents::Table.into_iden(), CalendarEvents::DeletedAt.into_iden()),
+        ];
+
+        for (table, column) in tables {
+            manager
+                .alter_table(Table::alter().table(table).drop_column(column).to_owned())
+                .await?;
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,61 @@
+use sea_orm_migration::prelude::*;
+use super::portable::{add_columns, drop_columns};
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[derive(DeriveIden)]
+enum CalendarEvents {
+    Table,
+    UserId,
+    StartTime,
+    EndTime,
+}
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // Plaintext time bounds so the server can answer range queries without decrypting events
+        add_columns(
+            manager,
+            CalendarEvents::Table,
+            vec![
+                ColumnDef::new(CalendarEvents::StartTime).timestamp_with_time_zone().to_owned(),
+                ColumnDef::new(CalendarEvents::EndTime).timestamp_with_time_zone().to_owned(),
+            ],
+        )
+        .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx-calendar_events-user_time_range")
+                    .table(CalendarEvents::Table)
+                    .col(CalendarEvents::UserId)
+                    .col(CalendarEvents::StartTime)
+                    .col(CalendarEvents::EndTime)
+                    .to_owned(),
+            )
+            .await?;
+
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_index(
+                Index::drop()
+                    .name("idx-calendar_events-user_time_range")
+                    .table(CalendarEvents::Table)
+                    .to_owned(),
+            )
+            .await?;
+
+        drop_columns(
+            manager,
+            CalendarEvents::Table,
+            vec![CalendarEvents::StartTime.into_iden(), CalendarEvents::EndTime.into_iden()],
+        )
+        .await
+    }
+}
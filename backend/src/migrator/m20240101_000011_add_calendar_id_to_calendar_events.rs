@@ -0,0 +1,65 @@
+use sea_orm_migration::prelude::*;
+use super::portable::{add_foreign_key, drop_foreign_key};
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[derive(DeriveIden)]
+enum CalendarEvents {
+    Table,
+    CalendarId,
+}
+
+#[derive(DeriveIden)]
+enum Calendars {
+    Table,
+    Id,
+}
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(CalendarEvents::Table)
+                    .add_column_if_not_exists(ColumnDef::new(CalendarEvents::CalendarId).uuid())
+                    .to_owned(),
+            )
+            .await?;
+
+        add_foreign_key(
+            manager,
+            "fk-calendar_events-calendar_id",
+            (CalendarEvents::Table, CalendarEvents::CalendarId),
+            (Calendars::Table, Calendars::Id),
+            ForeignKeyAction::Cascade,
+        )
+        .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx-calendar_events-calendar_id")
+                    .table(CalendarEvents::Table)
+                    .col(CalendarEvents::CalendarId)
+                    .to_owned(),
+            )
+            .await?;
+
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        drop_foreign_key(manager, "fk-calendar_events-calendar_id", CalendarEvents::Table).await?;
+
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(CalendarEvents::Table)
+                    .drop_column(CalendarEvents::CalendarId)
+                    .to_owned(),
+            )
+            .await
+    }
+}
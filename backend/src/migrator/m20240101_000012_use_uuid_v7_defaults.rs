@@ -0,0 +1,77 @@
+use sea_orm_migration::prelude::*;
+use super::portable::is_sqlite;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+/// Tables whose `id` default moves to time-ordered UUIDv7; existing v4 ids are left untouched
+const TABLES: [&str; 6] = [
+    "auth.users",
+    "projects",
+    "can_do_list",
+    "calendars",
+    "calendar_events",
+    "deleted_records",
+];
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // SQLite has no UUID functions; the application generates every id there
+        if is_sqlite(manager) {
+            return Ok(());
+        }
+
+        let db = manager.get_connection();
+
+        // PostgreSQL only ships a native uuidv7() from version 18 on
+        db.execute_unprepared(
+            r#"
+            CREATE OR REPLACE FUNCTION uuid_generate_v7() RETURNS uuid AS $$
+            DECLARE
+                uuid_bytes bytea;
+            BEGIN
+                uuid_bytes = uuid_send(gen_random_uuid());
+                -- 48-bit big-endian unix timestamp in milliseconds
+                uuid_bytes = overlay(uuid_bytes placing substring(int8send(floor(extract(epoch from clock_timestamp()) * 1000)::bigint) from 3) from 1 for 6);
+                -- Version nibble 7; the variant bits are already set by gen_random_uuid()
+                uuid_bytes = set_byte(uuid_bytes, 6, (b'0111' || get_byte(uuid_bytes, 6)::bit(4))::bit(8)::int);
+                RETURN encode(uuid_bytes, 'hex')::uuid;
+            END
+            $$ LANGUAGE plpgsql VOLATILE;
+            "#,
+        )
+        .await?;
+
+        for table in TABLES {
+            db.execute_unprepared(&format!(
+                "ALTER TABLE {} ALTER COLUMN id SET DEFAULT uuid_generate_v7();",
+                table
+            ))
+            .await?;
+        }
+
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        if is_sqlite(manager) {
+            return Ok(());
+        }
+
+        let db = manager.get_connection();
+
+        for table in TABLES {
+            db.execute_unprepared(&format!(
+                "ALTER TABLE {} ALTER COLUMN id SET DEFAULT gen_random_uuid();",
+                table
+            ))
+            .await?;
+        }
+
+        db.execute_unprepared("DROP FUNCTION IF EXISTS uuid_generate_v7();")
+            .await?;
+
+        Ok(())
+    }
+}
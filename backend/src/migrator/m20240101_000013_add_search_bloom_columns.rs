@@ -0,0 +1,63 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[derive(DeriveIden)]
+enum CanDoList {
+    Table,
+    SearchBloom,
+}
+
+#[derive(DeriveIden)]
+enum CalendarEvents {
+    Table,
+    SearchBloom,
+}
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // Client-built bloom filter over hashed search tokens; lets the server narrow
+        // search candidates without ever seeing the plaintext
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(CanDoList::Table)
+                    .add_column_if_not_exists(ColumnDef::new(CanDoList::SearchBloom).text())
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(CalendarEvents::Table)
+                    .add_column_if_not_exists(ColumnDef::new(CalendarEvents::SearchBloom).text())
+                    .to_owned(),
+            )
+            .await?;
+
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(CalendarEvents::Table)
+                    .drop_column(CalendarEvents::SearchBloom)
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(CanDoList::Table)
+                    .drop_column(CanDoList::SearchBloom)
+                    .to_owned(),
+            )
+            .await
+    }
+}
@@ -0,0 +1,96 @@
+use sea_orm_migration::prelude::*;
+use super::portable::{PortableColumnDef, PortableTableCreate};
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[derive(DeriveIden)]
+enum AuditLog {
+    Table,
+    Id,
+    UserId,
+    ActorId,
+    Action,
+    Details,
+    CreatedAt,
+}
+
+#[derive(DeriveIden)]
+enum Users {
+    Table,
+    Id,
+}
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // Security-relevant actions taken on a user's account, shown in their activity feed
+        manager
+            .create_table(
+                Table::create()
+                    .table(AuditLog::Table)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(AuditLog::Id)
+                            .uuid()
+                            .not_null()
+                            .primary_key()
+                            .default_uuid(manager, "uuid_generate_v7()"),
+                    )
+                    .col(ColumnDef::new(AuditLog::UserId).uuid().not_null())
+                    .col(ColumnDef::new(AuditLog::ActorId).uuid())
+                    .col(ColumnDef::new(AuditLog::Action).string().not_null())
+                    .col(
+                        ColumnDef::new(AuditLog::Details)
+                            .json_binary()
+                            .not_null()
+                            .default_json(manager, "{}"),
+                    )
+                    .col(
+                        ColumnDef::new(AuditLog::CreatedAt)
+                            .timestamp_with_time_zone()
+                            .not_null()
+                            .default_now(manager),
+                    )
+                    .auth_foreign_key(
+                        manager,
+                        ForeignKey::create()
+                            .name("fk-audit_log-user_id")
+                            .from(AuditLog::Table, AuditLog::UserId)
+                            .to((Alias::new("auth"), Users::Table), Users::Id)
+                            .on_delete(ForeignKeyAction::Cascade)
+                            .on_update(ForeignKeyAction::Cascade),
+                    )
+                    .auth_foreign_key(
+                        manager,
+                        ForeignKey::create()
+                            .name("fk-audit_log-actor_id")
+                            .from(AuditLog::Table, AuditLog::ActorId)
+                            .to((Alias::new("auth"), Users::Table), Users::Id)
+                            .on_delete(ForeignKeyAction::SetNull)
+                            .on_update(ForeignKeyAction::Cascade),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx-audit_log-user_created_at")
+                    .table(AuditLog::Table)
+                    .col(AuditLog::UserId)
+                    .col(AuditLog::CreatedAt)
+                    .to_owned(),
+            )
+            .await?;
+
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(AuditLog::Table).if_exists().to_owned())
+            .await
+    }
+}
@@ -0,0 +1,105 @@
+use sea_orm_migration::prelude::*;
+use super::portable::{add_columns, add_foreign_key, drop_columns, drop_foreign_key, PortableColumnDef};
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[derive(DeriveIden)]
+enum CalendarEvents {
+    Table,
+    Id,
+    UserId,
+    Rrule,
+    RecurrenceExceptions,
+    RecurringEventId,
+    OriginalStartTime,
+}
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // Plaintext recurrence so the server can expand series into occurrences.
+        // An override of a single occurrence is its own event pointing at the series.
+        add_columns(
+            manager,
+            CalendarEvents::Table,
+            vec![
+                ColumnDef::new(CalendarEvents::Rrule).text().to_owned(),
+                ColumnDef::new(CalendarEvents::RecurrenceExceptions)
+                    .json_binary()
+                    .not_null()
+                    .default_json(manager, "[]")
+                    .to_owned(),
+                ColumnDef::new(CalendarEvents::RecurringEventId).uuid().to_owned(),
+                ColumnDef::new(CalendarEvents::OriginalStartTime).timestamp_with_time_zone().to_owned(),
+            ],
+        )
+        .await?;
+
+        add_foreign_key(
+            manager,
+            "fk-calendar_events-recurring_event_id",
+            (CalendarEvents::Table, CalendarEvents::RecurringEventId),
+            (CalendarEvents::Table, CalendarEvents::Id),
+            ForeignKeyAction::Cascade,
+        )
+        .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx-calendar_events-recurring_event_id")
+                    .table(CalendarEvents::Table)
+                    .col(CalendarEvents::RecurringEventId)
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx-calendar_events-user_rrule")
+                    .table(CalendarEvents::Table)
+                    .col(CalendarEvents::UserId)
+                    .col(CalendarEvents::Rrule)
+                    .to_owned(),
+            )
+            .await?;
+
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_index(
+                Index::drop()
+                    .name("idx-calendar_events-user_rrule")
+                    .table(CalendarEvents::Table)
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .drop_index(
+                Index::drop()
+                    .name("idx-calendar_events-recurring_event_id")
+                    .table(CalendarEvents::Table)
+                    .to_owned(),
+            )
+            .await?;
+
+        drop_foreign_key(manager, "fk-calendar_events-recurring_event_id", CalendarEvents::Table).await?;
+
+        drop_columns(
+            manager,
+            CalendarEvents::Table,
+            vec![
+                CalendarEvents::Rrule.into_iden(),
+                CalendarEvents::RecurrenceExceptions.into_iden(),
+                CalendarEvents::RecurringEventId.into_iden(),
+                CalendarEvents::OriginalStartTime.into_iden(),
+            ],
+        )
+        .await
+    }
+}
@@ -0,0 +1,58 @@
+use sea_orm_migration::prelude::*;
+use super::portable::PortableColumnDef;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[derive(DeriveIden)]
+enum SchemaBackfills {
+    Table,
+    Name,
+    TableName,
+    TotalRows,
+    ProcessedRows,
+    LastId,
+    StartedAt,
+    UpdatedAt,
+    CompletedAt,
+}
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // Progress of expand/contract backfills, so any replica can resume them and
+        // contract migrations can check they finished
+        manager
+            .create_table(
+                Table::create()
+                    .table(SchemaBackfills::Table)
+                    .if_not_exists()
+                    .col(ColumnDef::new(SchemaBackfills::Name).string().not_null().primary_key())
+                    .col(ColumnDef::new(SchemaBackfills::TableName).string().not_null())
+                    .col(ColumnDef::new(SchemaBackfills::TotalRows).big_integer().not_null().default(0))
+                    .col(ColumnDef::new(SchemaBackfills::ProcessedRows).big_integer().not_null().default(0))
+                    .col(ColumnDef::new(SchemaBackfills::LastId).uuid())
+                    .col(
+                        ColumnDef::new(SchemaBackfills::StartedAt)
+                            .timestamp_with_time_zone()
+                            .not_null()
+                            .default_now(manager),
+                    )
+                    .col(
+                        ColumnDef::new(SchemaBackfills::UpdatedAt)
+                            .timestamp_with_time_zone()
+                            .not_null()
+                            .default_now(manager),
+                    )
+                    .col(ColumnDef::new(SchemaBackfills::CompletedAt).timestamp_with_time_zone())
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(SchemaBackfills::Table).if_exists().to_owned())
+            .await
+    }
+}
@@ -0,0 +1,118 @@
+use sea_orm_migration::prelude::*;
+use super::portable::{PortableColumnDef, PortableTableCreate};
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[derive(DeriveIden)]
+enum Reminders {
+    Table,
+    Id,
+    UserId,
+    EventId,
+    OffsetMinutes,
+    RemindAt,
+    FireAt,
+    LastFiredAt,
+    CreatedAt,
+    UpdatedAt,
+}
+
+#[derive(DeriveIden)]
+enum Users {
+    Table,
+    Id,
+}
+
+#[derive(DeriveIden)]
+enum CalendarEvents {
+    Table,
+    Id,
+}
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // A reminder fires either a fixed number of minutes before its event starts or at
+        // an absolute time; `fire_at` is when it fires next, NULL once nothing is left to fire
+        manager
+            .create_table(
+                Table::create()
+                    .table(Reminders::Table)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(Reminders::Id)
+                            .uuid()
+                            .not_null()
+                            .primary_key()
+                            .default_uuid(manager, "uuid_generate_v7()"),
+                    )
+                    .col(ColumnDef::new(Reminders::UserId).uuid().not_null())
+                    .col(ColumnDef::new(Reminders::EventId).uuid().not_null())
+                    .col(ColumnDef::new(Reminders::OffsetMinutes).integer())
+                    .col(ColumnDef::new(Reminders::RemindAt).timestamp_with_time_zone())
+                    .col(ColumnDef::new(Reminders::FireAt).timestamp_with_time_zone())
+                    .col(ColumnDef::new(Reminders::LastFiredAt).timestamp_with_time_zone())
+                    .col(
+                        ColumnDef::new(Reminders::CreatedAt)
+                            .timestamp_with_time_zone()
+                            .not_null()
+                            .default_now(manager),
+                    )
+                    .col(
+                        ColumnDef::new(Reminders::UpdatedAt)
+                            .timestamp_with_time_zone()
+                            .not_null()
+                            .default_now(manager),
+                    )
+                    .check(Expr::cust("(offset_minutes IS NULL) <> (remind_at IS NULL)"))
+                    .auth_foreign_key(
+                        manager,
+                        ForeignKey::create()
+                            .name("fk-reminders-user_id")
+                            .from(Reminders::Table, Reminders::UserId)
+                            .to((Alias::new("auth"), Users::Table), Users::Id)
+                            .on_delete(ForeignKeyAction::Cascade)
+                            .on_update(ForeignKeyAction::Cascade),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk-reminders-event_id")
+                            .from(Reminders::Table, Reminders::EventId)
+                            .to(CalendarEvents::Table, CalendarEvents::Id)
+                            .on_delete(ForeignKeyAction::Cascade)
+                            .on_update(ForeignKeyAction::Cascade),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx-reminders-event_id")
+                    .table(Reminders::Table)
+                    .col(Reminders::EventId)
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx-reminders-fire_at")
+                    .table(Reminders::Table)
+                    .col(Reminders::FireAt)
+                    .to_owned(),
+            )
+            .await?;
+
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(Reminders::Table).if_exists().to_owned())
+            .await
+    }
+}
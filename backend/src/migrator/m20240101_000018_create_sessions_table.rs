@@ -0,0 +1,87 @@
+use sea_orm_migration::prelude::*;
+use super::portable::{create_auth_index, PortableColumnDef, PortableTableCreate};
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[derive(DeriveIden)]
+enum Sessions {
+    Table,
+    Id,
+    UserId,
+    UserAgent,
+    CreatedAt,
+    LastActiveAt,
+    ExpiresAt,
+    RevokedAt,
+}
+
+#[derive(DeriveIden)]
+enum Users {
+    Table,
+    Id,
+}
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // One row per login; tokens reference it so sessions can expire on inactivity or be revoked
+        manager
+            .create_table(
+                Table::create()
+                    .table((Alias::new("auth"), Sessions::Table))
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(Sessions::Id)
+                            .uuid()
+                            .not_null()
+                            .primary_key()
+                            .default_uuid(manager, "uuid_generate_v7()"),
+                    )
+                    .col(ColumnDef::new(Sessions::UserId).uuid().not_null())
+                    .col(ColumnDef::new(Sessions::UserAgent).text())
+                    .col(
+                        ColumnDef::new(Sessions::CreatedAt)
+                            .timestamp_with_time_zone()
+                            .not_null()
+                            .default_now(manager),
+                    )
+                    .col(
+                        ColumnDef::new(Sessions::LastActiveAt)
+                            .timestamp_with_time_zone()
+                            .not_null()
+                            .default_now(manager),
+                    )
+                    .col(ColumnDef::new(Sessions::ExpiresAt).timestamp_with_time_zone().not_null())
+                    .col(ColumnDef::new(Sessions::RevokedAt).timestamp_with_time_zone())
+                    .auth_foreign_key(
+                        manager,
+                        ForeignKey::create()
+                            .name("fk-sessions-user_id")
+                            .from((Alias::new("auth"), Sessions::Table), Sessions::UserId)
+                            .to((Alias::new("auth"), Users::Table), Users::Id)
+                            .on_delete(ForeignKeyAction::Cascade)
+                            .on_update(ForeignKeyAction::Cascade),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        create_auth_index(manager, "idx-sessions-user_id", Sessions::Table, &["user_id"]).await?;
+
+        create_auth_index(manager, "idx-sessions-expires_at", Sessions::Table, &["expires_at"]).await?;
+
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(
+                Table::drop()
+                    .table((Alias::new("auth"), Sessions::Table))
+                    .if_exists()
+                    .to_owned(),
+            )
+            .await
+    }
+}
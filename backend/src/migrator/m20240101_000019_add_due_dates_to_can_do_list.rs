@@ -0,0 +1,80 @@
+use sea_orm_migration::prelude::*;
+use super::portable::{add_columns, drop_columns};
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[derive(DeriveIden)]
+enum CanDoList {
+    Table,
+    UserId,
+    DueAt,
+    ScheduledAt,
+}
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // Plaintext deadlines so the server can filter and sort tasks without decrypting them
+        add_columns(
+            manager,
+            CanDoList::Table,
+            vec![
+                ColumnDef::new(CanDoList::DueAt).timestamp_with_time_zone().to_owned(),
+                ColumnDef::new(CanDoList::ScheduledAt).timestamp_with_time_zone().to_owned(),
+            ],
+        )
+        .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx-can_do_list-user_id-due_at")
+                    .table(CanDoList::Table)
+                    .col(CanDoList::UserId)
+                    .col(CanDoList::DueAt)
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx-can_do_list-user_id-scheduled_at")
+                    .table(CanDoList::Table)
+                    .col(CanDoList::UserId)
+                    .col(CanDoList::ScheduledAt)
+                    .to_owned(),
+            )
+            .await?;
+
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_index(
+                Index::drop()
+                    .name("idx-can_do_list-user_id-scheduled_at")
+                    .table(CanDoList::Table)
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .drop_index(
+                Index::drop()
+                    .name("idx-can_do_list-user_id-due_at")
+                    .table(CanDoList::Table)
+                    .to_owned(),
+            )
+            .await?;
+
+        drop_columns(
+            manager,
+            CanDoList::Table,
+            vec![CanDoList::DueAt.into_iden(), CanDoList::ScheduledAt.into_iden()],
+        )
+        .await
+    }
+}
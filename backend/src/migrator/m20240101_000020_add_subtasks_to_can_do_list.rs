@@ -0,0 +1,70 @@
+use sea_orm_migration::prelude::*;
+use super::portable::{add_columns, add_foreign_key, drop_columns, drop_foreign_key};
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[derive(DeriveIden)]
+enum CanDoList {
+    Table,
+    Id,
+    ParentTaskId,
+    CompletedAt,
+}
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // Tasks nest like projects; completion is plaintext so it can cascade through the hierarchy
+        add_columns(
+            manager,
+            CanDoList::Table,
+            vec![
+                ColumnDef::new(CanDoList::ParentTaskId).uuid().to_owned(),
+                ColumnDef::new(CanDoList::CompletedAt).timestamp_with_time_zone().to_owned(),
+            ],
+        )
+        .await?;
+
+        add_foreign_key(
+            manager,
+            "fk-can_do_list-parent_task_id",
+            (CanDoList::Table, CanDoList::ParentTaskId),
+            (CanDoList::Table, CanDoList::Id),
+            ForeignKeyAction::Cascade,
+        )
+        .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx-can_do_list-parent_task_id")
+                    .table(CanDoList::Table)
+                    .col(CanDoList::ParentTaskId)
+                    .to_owned(),
+            )
+            .await?;
+
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_index(
+                Index::drop()
+                    .name("idx-can_do_list-parent_task_id")
+                    .table(CanDoList::Table)
+                    .to_owned(),
+            )
+            .await?;
+
+        drop_foreign_key(manager, "fk-can_do_list-parent_task_id", CanDoList::Table).await?;
+
+        drop_columns(
+            manager,
+            CanDoList::Table,
+            vec![CanDoList::ParentTaskId.into_iden(), CanDoList::CompletedAt.into_iden()],
+        )
+        .await
+    }
+}
@@ -0,0 +1,65 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[derive(DeriveIden)]
+enum CalendarEvents {
+    Table,
+    UserId,
+    Status,
+}
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // iCalendar STATUS of an event, in plaintext so listings can filter on it
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(CalendarEvents::Table)
+                    .add_column_if_not_exists(
+                        ColumnDef::new(CalendarEvents::Status)
+                            .text()
+                            .not_null()
+                            .default("confirmed")
+                            .check(Expr::cust("status IN ('confirmed', 'tentative', 'cancelled')")),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx-calendar_events-user_id-status")
+                    .table(CalendarEvents::Table)
+                    .col(CalendarEvents::UserId)
+                    .col(CalendarEvents::Status)
+                    .to_owned(),
+            )
+            .await?;
+
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_index(
+                Index::drop()
+                    .name("idx-calendar_events-user_id-status")
+                    .table(CalendarEvents::Table)
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(CalendarEvents::Table)
+                    .drop_column(CalendarEvents::Status)
+                    .to_owned(),
+            )
+            .await
+    }
+}
@@ -0,0 +1,134 @@
+use sea_orm_migration::prelude::*;
+use super::portable::{PortableColumnDef, PortableTableCreate};
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[derive(DeriveIden)]
+enum TimeEntries {
+    Table,
+    Id,
+    UserId,
+    TaskId,
+    StartedAt,
+    StoppedAt,
+    EncryptedData,
+    Iv,
+    Salt,
+    CreatedAt,
+    UpdatedAt,
+}
+
+#[derive(DeriveIden)]
+enum Users {
+    Table,
+    Id,
+}
+
+#[derive(DeriveIden)]
+enum CanDoList {
+    Table,
+    Id,
+}
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // Time spent on a task; `stopped_at` is NULL while the timer runs. The optional
+        // note is client-side encrypted like every other payload.
+        manager
+            .create_table(
+                Table::create()
+                    .table(TimeEntries::Table)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(TimeEntries::Id)
+                            .uuid()
+                            .not_null()
+                            .primary_key()
+                            .default_uuid(manager, "uuid_generate_v7()"),
+                    )
+                    .col(ColumnDef::new(TimeEntries::UserId).uuid().not_null())
+                    .col(ColumnDef::new(TimeEntries::TaskId).uuid().not_null())
+                    .col(ColumnDef::new(TimeEntries::StartedAt).timestamp_with_time_zone().not_null())
+                    .col(ColumnDef::new(TimeEntries::StoppedAt).timestamp_with_time_zone())
+                    .col(ColumnDef::new(TimeEntries::EncryptedData).text())
+                    .col(ColumnDef::new(TimeEntries::Iv).text())
+                    .col(ColumnDef::new(TimeEntries::Salt).text())
+                    .col(
+                        ColumnDef::new(TimeEntries::CreatedAt)
+                            .timestamp_with_time_zone()
+                            .not_null()
+                            .default_now(manager),
+                    )
+                    .col(
+                        ColumnDef::new(TimeEntries::UpdatedAt)
+                            .timestamp_with_time_zone()
+                            .not_null()
+                            .default_now(manager),
+                    )
+                    .check(Expr::cust("stopped_at IS NULL OR stopped_at >= started_at"))
+                    .auth_foreign_key(
+                        manager,
+                        ForeignKey::create()
+                            .name("fk-time_entries-user_id")
+                            .from(TimeEntries::Table, TimeEntries::UserId)
+                            .to((Alias::new("auth"), Users::Table), Users::Id)
+                            .on_delete(ForeignKeyAction::Cascade)
+                            .on_update(ForeignKeyAction::Cascade),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk-time_entries-task_id")
+                            .from(TimeEntries::Table, TimeEntries::TaskId)
+                            .to(CanDoList::Table, CanDoList::Id)
+                            .on_delete(ForeignKeyAction::Cascade)
+                            .on_update(ForeignKeyAction::Cascade),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx-time_entries-user_id-started_at")
+                    .table(TimeEntries::Table)
+                    .col(TimeEntries::UserId)
+                    .col(TimeEntries::StartedAt)
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx-time_entries-task_id")
+                    .table(TimeEntries::Table)
+                    .col(TimeEntries::TaskId)
+                    .to_owned(),
+            )
+            .await?;
+
+        // At most one running timer per user
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx-time_entries-user_id-running")
+                    .table(TimeEntries::Table)
+                    .col(TimeEntries::UserId)
+                    .unique()
+                    .and_where(Expr::col(TimeEntries::StoppedAt).is_null())
+                    .to_owned(),
+            )
+            .await?;
+
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(TimeEntries::Table).if_exists().to_owned())
+            .await
+    }
+}
@@ -0,0 +1,36 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[derive(DeriveIden)]
+enum CanDoList {
+    Table,
+    Rrule,
+}
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // Plaintext so the server can create the next instance when a repeating task is completed
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(CanDoList::Table)
+                    .add_column_if_not_exists(ColumnDef::new(CanDoList::Rrule).text())
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(CanDoList::Table)
+                    .drop_column(CanDoList::Rrule)
+                    .to_owned(),
+            )
+            .await
+    }
+}
@@ -0,0 +1,115 @@
+use sea_orm_migration::prelude::*;
+use super::portable::{PortableColumnDef, PortableTableCreate};
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[derive(DeriveIden)]
+enum EventAttendees {
+    Table,
+    Id,
+    UserId,
+    EventId,
+    EncryptedData,
+    Iv,
+    Salt,
+    RsvpStatus,
+    RespondedAt,
+    CreatedAt,
+    UpdatedAt,
+}
+
+#[derive(DeriveIden)]
+enum Users {
+    Table,
+    Id,
+}
+
+#[derive(DeriveIden)]
+enum CalendarEvents {
+    Table,
+    Id,
+}
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // Who an attendee is stays encrypted; only their iCalendar PARTSTAT is plaintext
+        // so responses can be counted without decrypting anything
+        manager
+            .create_table(
+                Table::create()
+                    .table(EventAttendees::Table)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(EventAttendees::Id)
+                            .uuid()
+                            .not_null()
+                            .primary_key()
+                            .default_uuid(manager, "uuid_generate_v7()"),
+                    )
+                    .col(ColumnDef::new(EventAttendees::UserId).uuid().not_null())
+                    .col(ColumnDef::new(EventAttendees::EventId).uuid().not_null())
+                    .col(ColumnDef::new(EventAttendees::EncryptedData).text().not_null())
+                    .col(ColumnDef::new(EventAttendees::Iv).text().not_null())
+                    .col(ColumnDef::new(EventAttendees::Salt).text().not_null())
+                    .col(
+                        ColumnDef::new(EventAttendees::RsvpStatus)
+                            .text()
+                            .not_null()
+                            .default("needs_action")
+                            .check(Expr::cust("rsvp_status IN ('needs_action', 'accepted', 'declined', 'tentative')")),
+                    )
+                    .col(ColumnDef::new(EventAttendees::RespondedAt).timestamp_with_time_zone())
+                    .col(
+                        ColumnDef::new(EventAttendees::CreatedAt)
+                            .timestamp_with_time_zone()
+                            .not_null()
+                            .default_now(manager),
+                    )
+                    .col(
+                        ColumnDef::new(EventAttendees::UpdatedAt)
+                            .timestamp_with_time_zone()
+                            .not_null()
+                            .default_now(manager),
+                    )
+                    .auth_foreign_key(
+                        manager,
+                        ForeignKey::create()
+                            .name("fk-event_attendees-user_id")
+                            .from(EventAttendees::Table, EventAttendees::UserId)
+                            .to((Alias::new("auth"), Users::Table), Users::Id)
+                            .on_delete(ForeignKeyAction::Cascade)
+                            .on_update(ForeignKeyAction::Cascade),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk-event_attendees-event_id")
+                            .from(EventAttendees::Table, EventAttendees::EventId)
+                            .to(CalendarEvents::Table, CalendarEvents::Id)
+                            .on_delete(ForeignKeyAction::Cascade)
+                            .on_update(ForeignKeyAction::Cascade),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx-event_attendees-event_id")
+                    .table(EventAttendees::Table)
+                    .col(EventAttendees::EventId)
+                    .to_owned(),
+            )
+            .await?;
+
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(EventAttendees::Table).if_exists().to_owned())
+            .await
+    }
+}
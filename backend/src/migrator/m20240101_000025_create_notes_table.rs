@@ -0,0 +1,142 @@
+use sea_orm_migration::prelude::*;
+use super::portable::{PortableColumnDef, PortableTableCreate};
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[derive(DeriveIden)]
+enum Notes {
+    Table,
+    Id,
+    UserId,
+    ProjectId,
+    EventId,
+    EncryptedData,
+    Iv,
+    Salt,
+    CreatedAt,
+    UpdatedAt,
+    DeletedAt,
+}
+
+#[derive(DeriveIden)]
+enum Users {
+    Table,
+    Id,
+}
+
+#[derive(DeriveIden)]
+enum Projects {
+    Table,
+    Id,
+}
+
+#[derive(DeriveIden)]
+enum CalendarEvents {
+    Table,
+    Id,
+}
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // A note can be linked to a project and an event; it outlives both
+        manager
+            .create_table(
+                Table::create()
+                    .table(Notes::Table)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(Notes::Id)
+                            .uuid()
+                            .not_null()
+                            .primary_key()
+                            .default_uuid(manager, "uuid_generate_v7()"),
+                    )
+                    .col(ColumnDef::new(Notes::UserId).uuid().not_null())
+                    .col(ColumnDef::new(Notes::ProjectId).uuid())
+                    .col(ColumnDef::new(Notes::EventId).uuid())
+                    .col(ColumnDef::new(Notes::EncryptedData).text().not_null())
+                    .col(ColumnDef::new(Notes::Iv).text().not_null())
+                    .col(ColumnDef::new(Notes::Salt).text().not_null())
+                    .col(
+                        ColumnDef::new(Notes::CreatedAt)
+                            .timestamp_with_time_zone()
+                            .not_null()
+                            .default_now(manager),
+                    )
+                    .col(
+                        ColumnDef::new(Notes::UpdatedAt)
+                            .timestamp_with_time_zone()
+                            .not_null()
+                            .default_now(manager),
+                    )
+                    .col(ColumnDef::new(Notes::DeletedAt).timestamp_with_time_zone())
+                    .auth_foreign_key(
+                        manager,
+                        ForeignKey::create()
+                            .name("fk-notes-user_id")
+                            .from(Notes::Table, Notes::UserId)
+                            .to((Alias::new("auth"), Users::Table), Users::Id)
+                            .on_delete(ForeignKeyAction::Cascade)
+                            .on_update(ForeignKeyAction::Cascade),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk-notes-project_id")
+                            .from(Notes::Table, Notes::ProjectId)
+                            .to(Projects::Table, Projects::Id)
+                            .on_delete(ForeignKeyAction::SetNull)
+                            .on_update(ForeignKeyAction::Cascade),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk-notes-event_id")
+                            .from(Notes::Table, Notes::EventId)
+                            .to(CalendarEvents::Table, CalendarEvents::Id)
+                            .on_delete(ForeignKeyAction::SetNull)
+                            .on_update(ForeignKeyAction::Cascade),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx-notes-user_id")
+                    .table(Notes::Table)
+                    .col(Notes::UserId)
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx-notes-project_id")
+                    .table(Notes::Table)
+                    .col(Notes::ProjectId)
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx-notes-event_id")
+                    .table(Notes::Table)
+                    .col(Notes::EventId)
+                    .to_owned(),
+            )
+            .await?;
+
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(Notes::Table).if_exists().to_owned())
+            .await
+    }
+}
@@ -0,0 +1,67 @@
+use sea_orm_migration::prelude::*;
+use super::portable::{add_columns, drop_columns};
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[derive(DeriveIden)]
+enum CanDoList {
+    Table,
+    UserId,
+    Priority,
+    EstimatedMinutes,
+}
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // Plaintext so the server can sort by priority and plan tasks into free time without
+        // decrypting them; priority is a small integer so it sorts from low to urgent
+        add_columns(
+            manager,
+            CanDoList::Table,
+            vec![
+                ColumnDef::new(CanDoList::Priority)
+                    .small_integer()
+                    .check(Expr::cust("priority BETWEEN 1 AND 4"))
+                    .to_owned(),
+                ColumnDef::new(CanDoList::EstimatedMinutes)
+                    .integer()
+                    .check(Expr::cust("estimated_minutes > 0"))
+                    .to_owned(),
+            ],
+        )
+        .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx-can_do_list-user_id-priority")
+                    .table(CanDoList::Table)
+                    .col(CanDoList::UserId)
+                    .col(CanDoList::Priority)
+                    .to_owned(),
+            )
+            .await?;
+
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_index(
+                Index::drop()
+                    .name("idx-can_do_list-user_id-priority")
+                    .table(CanDoList::Table)
+                    .to_owned(),
+            )
+            .await?;
+
+        drop_columns(
+            manager,
+            CanDoList::Table,
+            vec![CanDoList::Priority.into_iden(), CanDoList::EstimatedMinutes.into_iden()],
+        )
+        .await
+    }
+}
@@ -0,0 +1,84 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[derive(DeriveIden)]
+enum Projects {
+    Table,
+    IsPinned,
+}
+
+#[derive(DeriveIden)]
+enum CanDoList {
+    Table,
+    IsPinned,
+}
+
+#[derive(DeriveIden)]
+enum Calendars {
+    Table,
+    IsPinned,
+}
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // Favorites, in plaintext so listings can filter on them
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Projects::Table)
+                    .add_column_if_not_exists(ColumnDef::new(Projects::IsPinned).boolean().not_null().default(false))
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(CanDoList::Table)
+                    .add_column_if_not_exists(ColumnDef::new(CanDoList::IsPinned).boolean().not_null().default(false))
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Calendars::Table)
+                    .add_column_if_not_exists(ColumnDef::new(Calendars::IsPinned).boolean().not_null().default(false))
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Calendars::Table)
+                    .drop_column(Calendars::IsPinned)
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(CanDoList::Table)
+                    .drop_column(CanDoList::IsPinned)
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Projects::Table)
+                    .drop_column(Projects::IsPinned)
+                    .to_owned(),
+            )
+            .await
+    }
+}
@@ -0,0 +1,47 @@
+use sea_orm_migration::prelude::*;
+use super::portable::is_sqlite;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // Data-only fixup for databases created before SQLite was supported
+        if is_sqlite(manager) {
+            return Ok(());
+        }
+
+        let db = manager.get_connection();
+
+        // Default projects are server-managed from now on; bring back trashed ones as root projects
+        db.execute_unprepared(
+            "UPDATE projects SET deleted_at = NULL, parent_id = NULL, updated_at = now() \
+             WHERE is_default = true AND deleted_at IS NOT NULL;",
+        )
+        .await?;
+
+        // Give every existing user one; the name is encrypted client-side, so it starts out empty
+        db.execute_unprepared(
+            "INSERT INTO projects (user_id, encrypted_data, iv, salt, is_default) \
+             SELECT u.id, '', '', '', true FROM auth.users u \
+             WHERE NOT EXISTS (SELECT 1 FROM projects p WHERE p.user_id = u.id AND p.is_default = true);",
+        )
+        .await?;
+
+        // Tasks without a project move to their owner's default project
+        db.execute_unprepared(
+            "UPDATE can_do_list c SET project_id = p.id, updated_at = now() \
+             FROM projects p \
+             WHERE c.project_id IS NULL AND p.user_id = c.user_id AND p.is_default = true;",
+        )
+        .await?;
+
+        Ok(())
+    }
+
+    async fn down(&self, _manager: &SchemaManager) -> Result<(), DbErr> {
+        // Data-only migration; the created projects are indistinguishable from user-made defaults
+        Ok(())
+    }
+}
@@ -0,0 +1,118 @@
+use sea_orm_migration::prelude::*;
+use super::portable::{PortableColumnDef, PortableTableCreate};
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[derive(DeriveIden)]
+enum ProjectMembers {
+    Table,
+    Id,
+    ProjectId,
+    UserId,
+    Role,
+    CreatedAt,
+    UpdatedAt,
+}
+
+#[derive(DeriveIden)]
+enum Users {
+    Table,
+    Id,
+}
+
+#[derive(DeriveIden)]
+enum Projects {
+    Table,
+    Id,
+}
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // Accounts a project is shared with; the project's own user is always its owner and has no row
+        manager
+            .create_table(
+                Table::create()
+                    .table(ProjectMembers::Table)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(ProjectMembers::Id)
+                            .uuid()
+                            .not_null()
+                            .primary_key()
+                            .default_uuid(manager, "uuid_generate_v7()"),
+                    )
+                    .col(ColumnDef::new(ProjectMembers::ProjectId).uuid().not_null())
+                    .col(ColumnDef::new(ProjectMembers::UserId).uuid().not_null())
+                    .col(
+                        ColumnDef::new(ProjectMembers::Role)
+                            .text()
+                            .not_null()
+                            .default("viewer")
+                            .check(Expr::cust("role IN ('owner', 'editor', 'viewer')")),
+                    )
+                    .col(
+                        ColumnDef::new(ProjectMembers::CreatedAt)
+                            .timestamp_with_time_zone()
+                            .not_null()
+                            .default_now(manager),
+                    )
+                    .col(
+                        ColumnDef::new(ProjectMembers::UpdatedAt)
+                            .timestamp_with_time_zone()
+                            .not_null()
+                            .default_now(manager),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk-project_members-project_id")
+                            .from(ProjectMembers::Table, ProjectMembers::ProjectId)
+                            .to(Projects::Table, Projects::Id)
+                            .on_delete(ForeignKeyAction::Cascade)
+                            .on_update(ForeignKeyAction::Cascade),
+                    )
+                    .auth_foreign_key(
+                        manager,
+                        ForeignKey::create()
+                            .name("fk-project_members-user_id")
+                            .from(ProjectMembers::Table, ProjectMembers::UserId)
+                            .to((Alias::new("auth"), Users::Table), Users::Id)
+                            .on_delete(ForeignKeyAction::Cascade)
+                            .on_update(ForeignKeyAction::Cascade),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx-project_members-project_id-user_id")
+                    .table(ProjectMembers::Table)
+                    .col(ProjectMembers::ProjectId)
+                    .col(ProjectMembers::UserId)
+                    .unique()
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx-project_members-user_id")
+                    .table(ProjectMembers::Table)
+                    .col(ProjectMembers::UserId)
+                    .to_owned(),
+            )
+            .await?;
+
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(ProjectMembers::Table).if_exists().to_owned())
+            .await
+    }
+}
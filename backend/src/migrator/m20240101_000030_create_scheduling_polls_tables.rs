@@ -0,0 +1,245 @@
+use sea_orm_migration::prelude::*;
+use super::portable::{PortableColumnDef, PortableTableCreate};
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[derive(DeriveIden)]
+enum SchedulingPolls {
+    Table,
+    Id,
+    UserId,
+    EncryptedData,
+    Iv,
+    Salt,
+    PublicToken,
+    Status,
+    FinalizedSlotId,
+    EventId,
+    CreatedAt,
+    UpdatedAt,
+}
+
+#[derive(DeriveIden)]
+enum PollSlots {
+    Table,
+    Id,
+    PollId,
+    StartTime,
+    EndTime,
+    CreatedAt,
+}
+
+#[derive(DeriveIden)]
+enum PollVotes {
+    Table,
+    Id,
+    PollId,
+    EncryptedData,
+    Iv,
+    Salt,
+    Answers,
+    CreatedAt,
+    UpdatedAt,
+}
+
+#[derive(DeriveIden)]
+enum Users {
+    Table,
+    Id,
+}
+
+#[derive(DeriveIden)]
+enum CalendarEvents {
+    Table,
+    Id,
+}
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(SchedulingPolls::Table)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(SchedulingPolls::Id)
+                            .uuid()
+                            .not_null()
+                            .primary_key()
+                            .default_uuid(manager, "uuid_generate_v7()"),
+                    )
+                    .col(ColumnDef::new(SchedulingPolls::UserId).uuid().not_null())
+                    .col(ColumnDef::new(SchedulingPolls::EncryptedData).text().not_null())
+                    .col(ColumnDef::new(SchedulingPolls::Iv).text().not_null())
+                    .col(ColumnDef::new(SchedulingPolls::Salt).text().not_null())
+                    .col(ColumnDef::new(SchedulingPolls::PublicToken).text().not_null().unique_key())
+                    .col(
+                        ColumnDef::new(SchedulingPolls::Status)
+                            .text()
+                            .not_null()
+                            .default("open")
+                            .check(Expr::cust("status IN ('open', 'finalized')")),
+                    )
+                    .col(ColumnDef::new(SchedulingPolls::FinalizedSlotId).uuid().null())
+                    .col(ColumnDef::new(SchedulingPolls::EventId).uuid().null())
+                    .col(
+                        ColumnDef::new(SchedulingPolls::CreatedAt)
+                            .timestamp_with_time_zone()
+                            .not_null()
+                            .default_now(manager),
+                    )
+                    .col(
+                        ColumnDef::new(SchedulingPolls::UpdatedAt)
+                            .timestamp_with_time_zone()
+                            .not_null()
+                            .default_now(manager),
+                    )
+                    .auth_foreign_key(
+                        manager,
+                        ForeignKey::create()
+                            .name("fk-scheduling_polls-user_id")
+                            .from(SchedulingPolls::Table, SchedulingPolls::UserId)
+                            .to((Alias::new("auth"), Users::Table), Users::Id)
+                            .on_delete(ForeignKeyAction::Cascade)
+                            .on_update(ForeignKeyAction::Cascade),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk-scheduling_polls-event_id")
+                            .from(SchedulingPolls::Table, SchedulingPolls::EventId)
+                            .to(CalendarEvents::Table, CalendarEvents::Id)
+                            .on_delete(ForeignKeyAction::SetNull)
+                            .on_update(ForeignKeyAction::Cascade),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx-scheduling_polls-user_id")
+                    .table(SchedulingPolls::Table)
+                    .col(SchedulingPolls::UserId)
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_table(
+                Table::create()
+                    .table(PollSlots::Table)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(PollSlots::Id)
+                            .uuid()
+                            .not_null()
+                            .primary_key()
+                            .default_uuid(manager, "uuid_generate_v7()"),
+                    )
+                    .col(ColumnDef::new(PollSlots::PollId).uuid().not_null())
+                    .col(ColumnDef::new(PollSlots::StartTime).timestamp_with_time_zone().not_null())
+                    .col(ColumnDef::new(PollSlots::EndTime).timestamp_with_time_zone().not_null())
+                    .col(
+                        ColumnDef::new(PollSlots::CreatedAt)
+                            .timestamp_with_time_zone()
+                            .not_null()
+                            .default_now(manager),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk-poll_slots-poll_id")
+                            .from(PollSlots::Table, PollSlots::PollId)
+                            .to(SchedulingPolls::Table, SchedulingPolls::Id)
+                            .on_delete(ForeignKeyAction::Cascade)
+                            .on_update(ForeignKeyAction::Cascade),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx-poll_slots-poll_id-start_time")
+                    .table(PollSlots::Table)
+                    .col(PollSlots::PollId)
+                    .col(PollSlots::StartTime)
+                    .to_owned(),
+            )
+            .await?;
+
+        // One ballot per invitee; the name is encrypted with the key shared in the invite link
+        manager
+            .create_table(
+                Table::create()
+                    .table(PollVotes::Table)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(PollVotes::Id)
+                            .uuid()
+                            .not_null()
+                            .primary_key()
+                            .default_uuid(manager, "uuid_generate_v7()"),
+                    )
+                    .col(ColumnDef::new(PollVotes::PollId).uuid().not_null())
+                    .col(ColumnDef::new(PollVotes::EncryptedData).text().not_null())
+                    .col(ColumnDef::new(PollVotes::Iv).text().not_null())
+                    .col(ColumnDef::new(PollVotes::Salt).text().not_null())
+                    .col(
+                        ColumnDef::new(PollVotes::Answers)
+                            .json_binary()
+                            .not_null()
+                            .default_json(manager, "{}"),
+                    )
+                    .col(
+                        ColumnDef::new(PollVotes::CreatedAt)
+                            .timestamp_with_time_zone()
+                            .not_null()
+                            .default_now(manager),
+                    )
+                    .col(
+                        ColumnDef::new(PollVotes::UpdatedAt)
+                            .timestamp_with_time_zone()
+                            .not_null()
+                            .default_now(manager),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk-poll_votes-poll_id")
+                            .from(PollVotes::Table, PollVotes::PollId)
+                            .to(SchedulingPolls::Table, SchedulingPolls::Id)
+                            .on_delete(ForeignKeyAction::Cascade)
+                            .on_update(ForeignKeyAction::Cascade),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx-poll_votes-poll_id")
+                    .table(PollVotes::Table)
+                    .col(PollVotes::PollId)
+                    .to_owned(),
+            )
+            .await?;
+
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(PollVotes::Table).if_exists().to_owned())
+            .await?;
+        manager
+            .drop_table(Table::drop().table(PollSlots::Table).if_exists().to_owned())
+            .await?;
+        manager
+            .drop_table(Table::drop().table(SchedulingPolls::Table).if_exists().to_owned())
+            .await
+    }
+}
@@ -0,0 +1,239 @@
+use sea_orm_migration::prelude::*;
+use super::portable::{PortableColumnDef, PortableTableCreate};
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[derive(DeriveIden)]
+enum BookingPages {
+    Table,
+    Id,
+    UserId,
+    CalendarId,
+    EncryptedData,
+    Iv,
+    Salt,
+    PublicToken,
+    TimeZone,
+    Availability,
+    SlotMinutes,
+    BufferBeforeMinutes,
+    BufferAfterMinutes,
+    MinNoticeMinutes,
+    MaxDaysAhead,
+    IsActive,
+    CreatedAt,
+    UpdatedAt,
+}
+
+#[derive(DeriveIden)]
+enum Bookings {
+    Table,
+    Id,
+    BookingPageId,
+    UserId,
+    EventId,
+    StartTime,
+    EndTime,
+    CreatedAt,
+}
+
+#[derive(DeriveIden)]
+enum Users {
+    Table,
+    Id,
+}
+
+#[derive(DeriveIden)]
+enum Calendars {
+    Table,
+    Id,
+}
+
+#[derive(DeriveIden)]
+enum CalendarEvents {
+    Table,
+    Id,
+}
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(BookingPages::Table)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(BookingPages::Id)
+                            .uuid()
+                            .not_null()
+                            .primary_key()
+                            .default_uuid(manager, "uuid_generate_v7()"),
+                    )
+                    .col(ColumnDef::new(BookingPages::UserId).uuid().not_null())
+                    .col(ColumnDef::new(BookingPages::CalendarId).uuid().null())
+                    .col(ColumnDef::new(BookingPages::EncryptedData).text().not_null())
+                    .col(ColumnDef::new(BookingPages::Iv).text().not_null())
+                    .col(ColumnDef::new(BookingPages::Salt).text().not_null())
+                    .col(ColumnDef::new(BookingPages::PublicToken).text().not_null().unique_key())
+                    .col(ColumnDef::new(BookingPages::TimeZone).text().not_null().default("UTC"))
+                    .col(
+                        ColumnDef::new(BookingPages::Availability)
+                            .json_binary()
+                            .not_null()
+                            .default_json(manager, "[]"),
+                    )
+                    .col(
+                        ColumnDef::new(BookingPages::SlotMinutes)
+                            .integer()
+                            .not_null()
+                            .check(Expr::cust("slot_minutes > 0")),
+                    )
+                    .col(
+                        ColumnDef::new(BookingPages::BufferBeforeMinutes)
+                            .integer()
+                            .not_null()
+                            .default(0)
+                            .check(Expr::cust("buffer_before_minutes >= 0")),
+                    )
+                    .col(
+                        ColumnDef::new(BookingPages::BufferAfterMinutes)
+                            .integer()
+                            .not_null()
+                            .default(0)
+                            .check(Expr::cust("buffer_after_minutes >= 0")),
+                    )
+                    .col(
+                        ColumnDef::new(BookingPages::MinNoticeMinutes)
+                            .integer()
+                            .not_null()
+                            .default(0)
+                            .check(Expr::cust("min_notice_minutes >= 0")),
+                    )
+                    .col(
+                        ColumnDef::new(BookingPages::MaxDaysAhead)
+                            .integer()
+                            .not_null()
+                            .default(60)
+                            .check(Expr::cust("max_days_ahead > 0")),
+                    )
+                    .col(ColumnDef::new(BookingPages::IsActive).boolean().not_null().default(true))
+                    .col(
+                        ColumnDef::new(BookingPages::CreatedAt)
+                            .timestamp_with_time_zone()
+                            .not_null()
+                            .default_now(manager),
+                    )
+                    .col(
+                        ColumnDef::new(BookingPages::UpdatedAt)
+                            .timestamp_with_time_zone()
+                            .not_null()
+                            .default_now(manager),
+                    )
+                    .auth_foreign_key(
+                        manager,
+                        ForeignKey::create()
+                            .name("fk-booking_pages-user_id")
+                            .from(BookingPages::Table, BookingPages::UserId)
+                            .to((Alias::new("auth"), Users::Table), Users::Id)
+                            .on_delete(ForeignKeyAction::Cascade)
+                            .on_update(ForeignKeyAction::Cascade),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk-booking_pages-calendar_id")
+                            .from(BookingPages::Table, BookingPages::CalendarId)
+                            .to(Calendars::Table, Calendars::Id)
+                            .on_delete(ForeignKeyAction::SetNull)
+                            .on_update(ForeignKeyAction::Cascade),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx-booking_pages-user_id")
+                    .table(BookingPages::Table)
+                    .col(BookingPages::UserId)
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_table(
+                Table::create()
+                    .table(Bookings::Table)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(Bookings::Id)
+                            .uuid()
+                            .not_null()
+                            .primary_key()
+                            .default_uuid(manager, "uuid_generate_v7()"),
+                    )
+                    .col(ColumnDef::new(Bookings::BookingPageId).uuid().not_null())
+                    .col(ColumnDef::new(Bookings::UserId).uuid().not_null())
+                    .col(ColumnDef::new(Bookings::EventId).uuid().null())
+                    .col(ColumnDef::new(Bookings::StartTime).timestamp_with_time_zone().not_null())
+                    .col(ColumnDef::new(Bookings::EndTime).timestamp_with_time_zone().not_null())
+                    .col(
+                        ColumnDef::new(Bookings::CreatedAt)
+                            .timestamp_with_time_zone()
+                            .not_null()
+                            .default_now(manager),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk-bookings-booking_page_id")
+                            .from(Bookings::Table, Bookings::BookingPageId)
+                            .to(BookingPages::Table, BookingPages::Id)
+                            .on_delete(ForeignKeyAction::Cascade)
+                            .on_update(ForeignKeyAction::Cascade),
+                    )
+                    .auth_foreign_key(
+                        manager,
+                        ForeignKey::create()
+                            .name("fk-bookings-user_id")
+                            .from(Bookings::Table, Bookings::UserId)
+                            .to((Alias::new("auth"), Users::Table), Users::Id)
+                            .on_delete(ForeignKeyAction::Cascade)
+                            .on_update(ForeignKeyAction::Cascade),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk-bookings-event_id")
+                            .from(Bookings::Table, Bookings::EventId)
+                            .to(CalendarEvents::Table, CalendarEvents::Id)
+                            .on_delete(ForeignKeyAction::SetNull)
+                            .on_update(ForeignKeyAction::Cascade),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx-bookings-booking_page_id-start_time")
+                    .table(Bookings::Table)
+                    .col(Bookings::BookingPageId)
+                    .col(Bookings::StartTime)
+                    .to_owned(),
+            )
+            .await?;
+
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(Bookings::Table).if_exists().to_owned())
+            .await?;
+        manager
+            .drop_table(Table::drop().table(BookingPages::Table).if_exists().to_owned())
+            .await
+    }
+}
@@ -0,0 +1,189 @@
+use sea_orm_migration::prelude::*;
+use super::portable::{add_columns, add_foreign_key, drop_columns, drop_foreign_key, PortableColumnDef, PortableTableCreate};
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[derive(DeriveIden)]
+enum CalendarSubscriptions {
+    Table,
+    Id,
+    UserId,
+    CalendarId,
+    Url,
+    RefreshIntervalMinutes,
+    Etag,
+    LastModified,
+    LastFetchedAt,
+    LastError,
+    NextFetchAt,
+    CreatedAt,
+    UpdatedAt,
+}
+
+#[derive(DeriveIden)]
+enum Calendars {
+    Table,
+    Id,
+    IsReadOnly,
+}
+
+#[derive(DeriveIden)]
+enum CalendarEvents {
+    Table,
+    SubscriptionId,
+    ExternalUid,
+    ExternalData,
+}
+
+#[derive(DeriveIden)]
+enum Users {
+    Table,
+    Id,
+}
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(CalendarSubscriptions::Table)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(CalendarSubscriptions::Id)
+                            .uuid()
+                            .not_null()
+                            .primary_key()
+                            .default_uuid(manager, "uuid_generate_v7()"),
+                    )
+                    .col(ColumnDef::new(CalendarSubscriptions::UserId).uuid().not_null())
+                    .col(ColumnDef::new(CalendarSubscriptions::CalendarId).uuid().not_null().unique_key())
+                    .col(ColumnDef::new(CalendarSubscriptions::Url).text().not_null())
+                    .col(
+                        ColumnDef::new(CalendarSubscriptions::RefreshIntervalMinutes)
+                            .integer()
+                            .not_null()
+                            .default(360)
+                            .check(Expr::cust("refresh_interval_minutes >= 15")),
+                    )
+                    .col(ColumnDef::new(CalendarSubscriptions::Etag).text().null())
+                    .col(ColumnDef::new(CalendarSubscriptions::LastModified).text().null())
+                    .col(ColumnDef::new(CalendarSubscriptions::LastFetchedAt).timestamp_with_time_zone().null())
+                    .col(ColumnDef::new(CalendarSubscriptions::LastError).text().null())
+                    .col(
+                        ColumnDef::new(CalendarSubscriptions::NextFetchAt)
+                            .timestamp_with_time_zone()
+                            .not_null()
+                            .default_now(manager),
+                    )
+                    .col(
+                        ColumnDef::new(CalendarSubscriptions::CreatedAt)
+                            .timestamp_with_time_zone()
+                            .not_null()
+                            .default_now(manager),
+                    )
+                    .col(
+                        ColumnDef::new(CalendarSubscriptions::UpdatedAt)
+                            .timestamp_with_time_zone()
+                            .not_null()
+                            .default_now(manager),
+                    )
+                    .auth_foreign_key(
+                        manager,
+                        ForeignKey::create()
+                            .name("fk-calendar_subscriptions-user_id")
+                            .from(CalendarSubscriptions::Table, CalendarSubscriptions::UserId)
+                            .to((Alias::new("auth"), Users::Table), Users::Id)
+                            .on_delete(ForeignKeyAction::Cascade)
+                            .on_update(ForeignKeyAction::Cascade),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk-calendar_subscriptions-calendar_id")
+                            .from(CalendarSubscriptions::Table, CalendarSubscriptions::CalendarId)
+                            .to(Calendars::Table, Calendars::Id)
+                            .on_delete(ForeignKeyAction::Cascade)
+                            .on_update(ForeignKeyAction::Cascade),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx-calendar_subscriptions-next_fetch_at")
+                    .table(CalendarSubscriptions::Table)
+                    .col(CalendarSubscriptions::NextFetchAt)
+                    .to_owned(),
+            )
+            .await?;
+
+        // Subscription calendars are filled by their feed only
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Calendars::Table)
+                    .add_column_if_not_exists(ColumnDef::new(Calendars::IsReadOnly).boolean().not_null().default(false))
+                    .to_owned(),
+            )
+            .await?;
+
+        // Feed events aren't encrypted; their details are kept as the feed published them
+        add_columns(
+            manager,
+            CalendarEvents::Table,
+            vec![
+                ColumnDef::new(CalendarEvents::SubscriptionId).uuid().null().to_owned(),
+                ColumnDef::new(CalendarEvents::ExternalUid).text().null().to_owned(),
+                ColumnDef::new(CalendarEvents::ExternalData).json_binary().null().to_owned(),
+            ],
+        )
+        .await?;
+
+        add_foreign_key(
+            manager,
+            "fk-calendar_events-subscription_id",
+            (CalendarEvents::Table, CalendarEvents::SubscriptionId),
+            (CalendarSubscriptions::Table, CalendarSubscriptions::Id),
+            ForeignKeyAction::Cascade,
+        )
+        .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx-calendar_events-subscription_id")
+                    .table(CalendarEvents::Table)
+                    .col(CalendarEvents::SubscriptionId)
+                    .to_owned(),
+            )
+            .await?;
+
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_index(Index::drop().name("idx-calendar_events-subscription_id").table(CalendarEvents::Table).to_owned())
+            .await?;
+        drop_foreign_key(manager, "fk-calendar_events-subscription_id", CalendarEvents::Table).await?;
+        drop_columns(
+            manager,
+            CalendarEvents::Table,
+            vec![
+                CalendarEvents::SubscriptionId.into_iden(),
+                CalendarEvents::ExternalUid.into_iden(),
+                CalendarEvents::ExternalData.into_iden(),
+            ],
+        )
+        .await?;
+        manager
+            .alter_table(Table::alter().table(Calendars::Table).drop_column(Calendars::IsReadOnly).to_owned())
+            .await?;
+        manager
+            .drop_table(Table::drop().table(CalendarSubscriptions::Table).if_exists().to_owned())
+            .await
+    }
+}
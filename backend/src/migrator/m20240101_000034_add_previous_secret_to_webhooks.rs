@@ -0,0 +1,37 @@
+use sea_orm_migration::prelude::*;
+use super::portable::{add_columns, drop_columns};
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[derive(DeriveIden)]
+enum Webhooks {
+    Table,
+    PreviousSecret,
+    PreviousSecretExpiresAt,
+}
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // After a rotation, deliveries are signed with the old secret too until it expires
+        add_columns(
+            manager,
+            Webhooks::Table,
+            vec![
+                ColumnDef::new(Webhooks::PreviousSecret).string().to_owned(),
+                ColumnDef::new(Webhooks::PreviousSecretExpiresAt).timestamp_with_time_zone().to_owned(),
+            ],
+        )
+        .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        drop_columns(
+            manager,
+            Webhooks::Table,
+            vec![Webhooks::PreviousSecret.into_iden(), Webhooks::PreviousSecretExpiresAt.into_iden()],
+        )
+        .await
+    }
+}
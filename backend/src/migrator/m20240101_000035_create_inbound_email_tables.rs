@@ -0,0 +1,84 @@
+use sea_orm_migration::prelude::*;
+use super::portable::{PortableColumnDef, PortableTableCreate};
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[derive(DeriveIden)]
+enum InboundEmailAddresses {
+    Table,
+    UserId,
+    Token,
+    CreatedAt,
+    UpdatedAt,
+}
+
+#[derive(DeriveIden)]
+enum CanDoList {
+    Table,
+    PendingPlaintext,
+}
+
+#[derive(DeriveIden)]
+enum Users {
+    Table,
+    Id,
+}
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(InboundEmailAddresses::Table)
+                    .if_not_exists()
+                    .col(ColumnDef::new(InboundEmailAddresses::UserId).uuid().not_null().primary_key())
+                    .col(ColumnDef::new(InboundEmailAddresses::Token).text().not_null().unique_key())
+                    .col(
+                        ColumnDef::new(InboundEmailAddresses::CreatedAt)
+                            .timestamp_with_time_zone()
+                            .not_null()
+                            .default_now(manager),
+                    )
+                    .col(
+                        ColumnDef::new(InboundEmailAddresses::UpdatedAt)
+                            .timestamp_with_time_zone()
+                            .not_null()
+                            .default_now(manager),
+                    )
+                    .auth_foreign_key(
+                        manager,
+                        ForeignKey::create()
+                            .name("fk-inbound_email_addresses-user_id")
+                            .from(InboundEmailAddresses::Table, InboundEmailAddresses::UserId)
+                            .to((Alias::new("auth"), Users::Table), Users::Id)
+                            .on_delete(ForeignKeyAction::Cascade)
+                            .on_update(ForeignKeyAction::Cascade),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        // Tasks created from emails hold the mail in plaintext until a client encrypts it
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(CanDoList::Table)
+                    .add_column_if_not_exists(ColumnDef::new(CanDoList::PendingPlaintext).json_binary().null())
+                    .to_owned(),
+            )
+            .await?;
+
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(Table::alter().table(CanDoList::Table).drop_column(CanDoList::PendingPlaintext).to_owned())
+            .await?;
+        manager
+            .drop_table(Table::drop().table(InboundEmailAddresses::Table).if_exists().to_owned())
+            .await
+    }
+}
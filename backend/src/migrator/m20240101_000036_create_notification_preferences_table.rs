@@ -0,0 +1,97 @@
+use sea_orm_migration::prelude::*;
+use super::portable::{PortableColumnDef, PortableTableCreate};
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[derive(DeriveIden)]
+enum NotificationPreferences {
+    Table,
+    UserId,
+    Timezone,
+    DigestFrequency,
+    DigestHour,
+    DigestWeekday,
+    NextDigestAt,
+    LastDigestSentAt,
+    CreatedAt,
+    UpdatedAt,
+}
+
+#[derive(DeriveIden)]
+enum Users {
+    Table,
+    Id,
+}
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(NotificationPreferences::Table)
+                    .if_not_exists()
+                    .col(ColumnDef::new(NotificationPreferences::UserId).uuid().not_null().primary_key())
+                    .col(ColumnDef::new(NotificationPreferences::Timezone).text().not_null().default("UTC"))
+                    .col(ColumnDef::new(NotificationPreferences::DigestFrequency).text().not_null().default("off"))
+                    .col(
+                        ColumnDef::new(NotificationPreferences::DigestHour)
+                            .small_integer()
+                            .not_null()
+                            .default(7)
+                            .check(Expr::cust("digest_hour BETWEEN 0 AND 23")),
+                    )
+                    .col(
+                        ColumnDef::new(NotificationPreferences::DigestWeekday)
+                            .small_integer()
+                            .not_null()
+                            .default(1)
+                            .check(Expr::cust("digest_weekday BETWEEN 1 AND 7")),
+                    )
+                    .col(ColumnDef::new(NotificationPreferences::NextDigestAt).timestamp_with_time_zone().null())
+                    .col(ColumnDef::new(NotificationPreferences::LastDigestSentAt).timestamp_with_time_zone().null())
+                    .col(
+                        ColumnDef::new(NotificationPreferences::CreatedAt)
+                            .timestamp_with_time_zone()
+                            .not_null()
+                            .default_now(manager),
+                    )
+                    .col(
+                        ColumnDef::new(NotificationPreferences::UpdatedAt)
+                            .timestamp_with_time_zone()
+                            .not_null()
+                            .default_now(manager),
+                    )
+                    .auth_foreign_key(
+                        manager,
+                        ForeignKey::create()
+                            .name("fk-notification_preferences-user_id")
+                            .from(NotificationPreferences::Table, NotificationPreferences::UserId)
+                            .to((Alias::new("auth"), Users::Table), Users::Id)
+                            .on_delete(ForeignKeyAction::Cascade)
+                            .on_update(ForeignKeyAction::Cascade),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx-notification_preferences-next_digest_at")
+                    .table(NotificationPreferences::Table)
+                    .col(NotificationPreferences::NextDigestAt)
+                    .to_owned(),
+            )
+            .await?;
+
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(NotificationPreferences::Table).if_exists().to_owned())
+            .await
+    }
+}
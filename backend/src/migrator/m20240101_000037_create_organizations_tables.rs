@@ -0,0 +1,202 @@
+use sea_orm_migration::prelude::*;
+use super::portable::{add_columns, add_foreign_key, drop_columns, drop_foreign_key, PortableColumnDef, PortableTableCreate};
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[derive(DeriveIden)]
+enum Organizations {
+    Table,
+    Id,
+    Name,
+    CreatedAt,
+    UpdatedAt,
+}
+
+#[derive(DeriveIden)]
+enum OrganizationMembers {
+    Table,
+    Id,
+    OrganizationId,
+    UserId,
+    Role,
+    CreatedAt,
+    UpdatedAt,
+}
+
+#[derive(DeriveIden)]
+enum Projects {
+    Table,
+    OrganizationId,
+}
+
+#[derive(DeriveIden)]
+enum Calendars {
+    Table,
+    OrganizationId,
+}
+
+#[derive(DeriveIden)]
+enum Users {
+    Table,
+    Id,
+}
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // Teams sharing one instance; the name is shown to every member, so it isn't encrypted
+        manager
+            .create_table(
+                Table::create()
+                    .table(Organizations::Table)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(Organizations::Id)
+                            .uuid()
+                            .not_null()
+                            .primary_key()
+                            .default_uuid(manager, "uuid_generate_v7()"),
+                    )
+                    .col(ColumnDef::new(Organizations::Name).text().not_null())
+                    .col(
+                        ColumnDef::new(Organizations::CreatedAt)
+                            .timestamp_with_time_zone()
+                            .not_null()
+                            .default_now(manager),
+                    )
+                    .col(
+                        ColumnDef::new(Organizations::UpdatedAt)
+                            .timestamp_with_time_zone()
+                            .not_null()
+                            .default_now(manager),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_table(
+                Table::create()
+                    .table(OrganizationMembers::Table)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(OrganizationMembers::Id)
+                            .uuid()
+                            .not_null()
+                            .primary_key()
+                            .default_uuid(manager, "uuid_generate_v7()"),
+                    )
+                    .col(ColumnDef::new(OrganizationMembers::OrganizationId).uuid().not_null())
+                    .col(ColumnDef::new(OrganizationMembers::UserId).uuid().not_null())
+                    .col(
+                        ColumnDef::new(OrganizationMembers::Role)
+                            .text()
+                            .not_null()
+                            .default("member")
+                            .check(Expr::cust("role IN ('owner', 'admin', 'member')")),
+                    )
+                    .col(
+                        ColumnDef::new(OrganizationMembers::CreatedAt)
+                            .timestamp_with_time_zone()
+                            .not_null()
+                            .default_now(manager),
+                    )
+                    .col(
+                        ColumnDef::new(OrganizationMembers::UpdatedAt)
+                            .timestamp_with_time_zone()
+                            .not_null()
+                            .default_now(manager),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk-organization_members-organization_id")
+                            .from(OrganizationMembers::Table, OrganizationMembers::OrganizationId)
+                            .to(Organizations::Table, Organizations::Id)
+                            .on_delete(ForeignKeyAction::Cascade)
+                            .on_update(ForeignKeyAction::Cascade),
+                    )
+                    .auth_foreign_key(
+                        manager,
+                        ForeignKey::create()
+                            .name("fk-organization_members-user_id")
+                            .from(OrganizationMembers::Table, OrganizationMembers::UserId)
+                            .to((Alias::new("auth"), Users::Table), Users::Id)
+                            .on_delete(ForeignKeyAction::Cascade)
+                            .on_update(ForeignKeyAction::Cascade),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx-organization_members-organization_id-user_id")
+                    .table(OrganizationMembers::Table)
+                    .col(OrganizationMembers::OrganizationId)
+                    .col(OrganizationMembers::UserId)
+                    .unique()
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx-organization_members-user_id")
+                    .table(OrganizationMembers::Table)
+                    .col(OrganizationMembers::UserId)
+                    .to_owned(),
+            )
+            .await?;
+
+        // Projects and calendars scoped to an organization stay with their user but are shared
+        // with every member; they fall back to private ones when the organization is deleted
+        for (table, column, name) in [
+            (Projects::Table.into_iden(), Projects::OrganizationId.into_iden(), "projects"),
+            (Calendars::Table.into_iden(), Calendars::OrganizationId.into_iden(), "calendars"),
+        ] {
+            add_columns(manager, table.clone(), vec![ColumnDef::new(column.clone()).uuid().null().to_owned()]).await?;
+            add_foreign_key(
+                manager,
+                &format!("fk-{}-organization_id", name),
+                (table.clone(), column.clone()),
+                (Organizations::Table, Organizations::Id),
+                ForeignKeyAction::SetNull,
+            )
+            .await?;
+            manager
+                .create_index(
+                    Index::create()
+                        .name(format!("idx-{}-organization_id", name))
+                        .table(table)
+                        .col(column)
+                        .to_owned(),
+                )
+                .await?;
+        }
+
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        for (table, column, name) in [
+            (Calendars::Table.into_iden(), Calendars::OrganizationId.into_iden(), "calendars"),
+            (Projects::Table.into_iden(), Projects::OrganizationId.into_iden(), "projects"),
+        ] {
+            manager
+                .drop_index(Index::drop().name(format!("idx-{}-organization_id", name)).table(table.clone()).to_owned())
+                .await?;
+            drop_foreign_key(manager, &format!("fk-{}-organization_id", name), table.clone()).await?;
+            drop_columns(manager, table, vec![column]).await?;
+        }
+
+        manager
+            .drop_table(Table::drop().table(OrganizationMembers::Table).if_exists().to_owned())
+            .await?;
+        manager
+            .drop_table(Table::drop().table(Organizations::Table).if_exists().to_owned())
+            .await
+    }
+}
@@ -0,0 +1,85 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[derive(DeriveIden)]
+enum CanDoList {
+    Table,
+    SearchTokens,
+}
+
+#[derive(DeriveIden)]
+enum CalendarEvents {
+    Table,
+    SearchTokens,
+}
+
+#[derive(DeriveIden)]
+enum Projects {
+    Table,
+    SearchTokens,
+}
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // Space-separated blind-index tokens (keyed hashes of the record's words) the client
+        // computes, so the server can match searches without seeing the plaintext
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(CanDoList::Table)
+                    .add_column_if_not_exists(ColumnDef::new(CanDoList::SearchTokens).text())
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(CalendarEvents::Table)
+                    .add_column_if_not_exists(ColumnDef::new(CalendarEvents::SearchTokens).text())
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Projects::Table)
+                    .add_column_if_not_exists(ColumnDef::new(Projects::SearchTokens).text())
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Projects::Table)
+                    .drop_column(Projects::SearchTokens)
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(CalendarEvents::Table)
+                    .drop_column(CalendarEvents::SearchTokens)
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(CanDoList::Table)
+                    .drop_column(CanDoList::SearchTokens)
+                    .to_owned(),
+            )
+            .await
+    }
+}
@@ -0,0 +1,49 @@
+use sea_orm_migration::prelude::*;
+use super::portable::{add_columns, drop_columns};
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[derive(DeriveIden)]
+enum SchemaVersion {
+    SchemaVersion,
+}
+
+/// Every table holding a client-encrypted payload
+const ENCRYPTED_TABLES: [&str; 11] = [
+    "projects",
+    "can_do_list",
+    "calendars",
+    "calendar_events",
+    "notes",
+    "time_entries",
+    "event_attendees",
+    "booking_pages",
+    "scheduling_polls",
+    "poll_votes",
+    "user_settings",
+];
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // Format version of the encrypted payload, chosen by the client that wrote it;
+        // existing records keep the original format, version 1
+        for table in ENCRYPTED_TABLES {
+            add_columns(
+                manager,
+                Alias::new(table),
+                vec![ColumnDef::new(SchemaVersion::SchemaVersion).small_integer().not_null().default(1).to_owned()],
+            )
+            .await?;
+        }
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        for table in ENCRYPTED_TABLES.iter().rev() {
+            drop_columns(manager, Alias::new(*table), vec![SchemaVersion::SchemaVersion.into_iden()]).await?;
+        }
+        Ok(())
+    }
+}
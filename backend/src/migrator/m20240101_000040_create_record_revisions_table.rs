@@ -0,0 +1,97 @@
+use sea_orm_migration::prelude::*;
+use super::portable::{PortableColumnDef, PortableTableCreate};
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[derive(DeriveIden)]
+enum RecordRevisions {
+    Table,
+    Id,
+    UserId,
+    ActorId,
+    TableName,
+    RecordId,
+    Action,
+    Snapshot,
+    CreatedAt,
+}
+
+#[derive(DeriveIden)]
+enum Users {
+    Table,
+    Id,
+}
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // Prior versions of records, taken before each update or delete; the snapshot holds
+        // the encrypted payload as stored, so the server still can't read it
+        manager
+            .create_table(
+                Table::create()
+                    .table(RecordRevisions::Table)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(RecordRevisions::Id)
+                            .uuid()
+                            .not_null()
+                            .primary_key()
+                            .default_uuid(manager, "uuid_generate_v7()"),
+                    )
+                    .col(ColumnDef::new(RecordRevisions::UserId).uuid().not_null())
+                    .col(ColumnDef::new(RecordRevisions::ActorId).uuid())
+                    .col(ColumnDef::new(RecordRevisions::TableName).string().not_null())
+                    .col(ColumnDef::new(RecordRevisions::RecordId).uuid().not_null())
+                    .col(ColumnDef::new(RecordRevisions::Action).string().not_null())
+                    .col(ColumnDef::new(RecordRevisions::Snapshot).json_binary().not_null())
+                    .col(
+                        ColumnDef::new(RecordRevisions::CreatedAt)
+                            .timestamp_with_time_zone()
+                            .not_null()
+                            .default_now(manager),
+                    )
+                    .auth_foreign_key(
+                        manager,
+                        ForeignKey::create()
+                            .name("fk-record_revisions-user_id")
+                            .from(RecordRevisions::Table, RecordRevisions::UserId)
+                            .to((Alias::new("auth"), Users::Table), Users::Id)
+                            .on_delete(ForeignKeyAction::Cascade)
+                            .on_update(ForeignKeyAction::Cascade),
+                    )
+                    .auth_foreign_key(
+                        manager,
+                        ForeignKey::create()
+                            .name("fk-record_revisions-actor_id")
+                            .from(RecordRevisions::Table, RecordRevisions::ActorId)
+                            .to((Alias::new("auth"), Users::Table), Users::Id)
+                            .on_delete(ForeignKeyAction::SetNull)
+                            .on_update(ForeignKeyAction::Cascade),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx-record_revisions-record")
+                    .table(RecordRevisions::Table)
+                    .col(RecordRevisions::TableName)
+                    .col(RecordRevisions::RecordId)
+                    .col(RecordRevisions::CreatedAt)
+                    .to_owned(),
+            )
+            .await?;
+
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(RecordRevisions::Table).if_exists().to_owned())
+            .await
+    }
+}
@@ -0,0 +1,63 @@
+use sea_orm_migration::prelude::*;
+use super::portable::{add_columns, drop_columns, is_sqlite};
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[derive(DeriveIden)]
+enum UserSettings {
+    Table,
+    Timezone,
+    WeekStart,
+}
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // Kept in plaintext beside the encrypted settings: the server expands recurring events,
+        // schedules reminders and sends digests in the user's local time
+        add_columns(
+            manager,
+            UserSettings::Table,
+            vec![
+                ColumnDef::new(UserSettings::Timezone).text().not_null().default("UTC").to_owned(),
+                ColumnDef::new(UserSettings::WeekStart)
+                    .small_integer()
+                    .not_null()
+                    .default(1)
+                    .check(Expr::cust("week_start BETWEEN 1 AND 7"))
+                    .to_owned(),
+            ],
+        )
+        .await?;
+
+        // The timezone moves over from the notification preferences, whose column stays
+        // until a later release so replicas of the previous one can still read it
+        let db = manager.get_connection();
+        let now = if is_sqlite(manager) { "strftime('%Y-%m-%dT%H:%M:%f+00:00', 'now')" } else { "now()" };
+        db.execute_unprepared(&format!(
+            "INSERT INTO user_settings (user_id, encrypted_data, iv, salt, created_at, updated_at) \
+             SELECT n.user_id, '{{}}', '', '', {now}, {now} FROM notification_preferences n \
+             WHERE n.timezone <> 'UTC' \
+             AND NOT EXISTS (SELECT 1 FROM user_settings s WHERE s.user_id = n.user_id);",
+        ))
+        .await?;
+        db.execute_unprepared(
+            "UPDATE user_settings SET timezone = \
+             (SELECT n.timezone FROM notification_preferences n WHERE n.user_id = user_settings.user_id) \
+             WHERE EXISTS (SELECT 1 FROM notification_preferences n WHERE n.user_id = user_settings.user_id);",
+        )
+        .await?;
+
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        drop_columns(
+            manager,
+            UserSettings::Table,
+            vec![UserSettings::Timezone.into_iden(), UserSettings::WeekStart.into_iden()],
+        )
+        .await
+    }
+}
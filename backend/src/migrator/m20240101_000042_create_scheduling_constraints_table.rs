@@ -0,0 +1,84 @@
+use sea_orm_migration::prelude::*;
+use super::portable::{PortableColumnDef, PortableTableCreate};
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[derive(DeriveIden)]
+enum SchedulingConstraints {
+    Table,
+    UserId,
+    WorkingHours,
+    ProtectedWindows,
+    MaxBlocksPerDay,
+    CreatedAt,
+    UpdatedAt,
+}
+
+#[derive(DeriveIden)]
+enum Users {
+    Table,
+    Id,
+}
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(SchedulingConstraints::Table)
+                    .if_not_exists()
+                    .col(ColumnDef::new(SchedulingConstraints::UserId).uuid().not_null().primary_key())
+                    .col(
+                        ColumnDef::new(SchedulingConstraints::WorkingHours)
+                            .json_binary()
+                            .not_null()
+                            .default_json(manager, "[]"),
+                    )
+                    .col(
+                        ColumnDef::new(SchedulingConstraints::ProtectedWindows)
+                            .json_binary()
+                            .not_null()
+                            .default_json(manager, "[]"),
+                    )
+                    .col(
+                        ColumnDef::new(SchedulingConstraints::MaxBlocksPerDay)
+                            .small_integer()
+                            .null()
+                            .check(Expr::cust("max_blocks_per_day > 0")),
+                    )
+                    .col(
+                        ColumnDef::new(SchedulingConstraints::CreatedAt)
+                            .timestamp_with_time_zone()
+                            .not_null()
+                            .default_now(manager),
+                    )
+                    .col(
+                        ColumnDef::new(SchedulingConstraints::UpdatedAt)
+                            .timestamp_with_time_zone()
+                            .not_null()
+                            .default_now(manager),
+                    )
+                    .auth_foreign_key(
+                        manager,
+                        ForeignKey::create()
+                            .name("fk-scheduling_constraints-user_id")
+                            .from(SchedulingConstraints::Table, SchedulingConstraints::UserId)
+                            .to((Alias::new("auth"), Users::Table), Users::Id)
+                            .on_delete(ForeignKeyAction::Cascade)
+                            .on_update(ForeignKeyAction::Cascade),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(SchedulingConstraints::Table).if_exists().to_owned())
+            .await
+    }
+}
@@ -0,0 +1,67 @@
+use sea_orm_migration::prelude::*;
+use super::portable::{add_columns, add_foreign_key, drop_columns, drop_foreign_key};
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[derive(DeriveIden)]
+enum CalendarEvents {
+    Table,
+    LinkedTaskId,
+}
+
+#[derive(DeriveIden)]
+enum CanDoList {
+    Table,
+    Id,
+}
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // An event can block out time for a task; the link is dropped rather than the event when
+        // the task is purged
+        add_columns(
+            manager,
+            CalendarEvents::Table,
+            vec![ColumnDef::new(CalendarEvents::LinkedTaskId).uuid().to_owned()],
+        )
+        .await?;
+
+        add_foreign_key(
+            manager,
+            "fk-calendar_events-linked_task_id",
+            (CalendarEvents::Table, CalendarEvents::LinkedTaskId),
+            (CanDoList::Table, CanDoList::Id),
+            ForeignKeyAction::SetNull,
+        )
+        .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx-calendar_events-linked_task_id")
+                    .table(CalendarEvents::Table)
+                    .col(CalendarEvents::LinkedTaskId)
+                    .to_owned(),
+            )
+            .await?;
+
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_index(
+                Index::drop()
+                    .name("idx-calendar_events-linked_task_id")
+                    .table(CalendarEvents::Table)
+                    .to_owned(),
+            )
+            .await?;
+
+        drop_foreign_key(manager, "fk-calendar_events-linked_task_id", CalendarEvents::Table).await?;
+
+        drop_columns(manager, CalendarEvents::Table, vec![CalendarEvents::LinkedTaskId.into_iden()]).await
+    }
+}
@@ -0,0 +1,130 @@
+use sea_orm_migration::prelude::*;
+use super::portable::{PortableColumnDef, PortableTableCreate};
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[derive(DeriveIden)]
+enum FocusSessions {
+    Table,
+    Id,
+    UserId,
+    TaskId,
+    Kind,
+    PlannedMinutes,
+    Status,
+    StartedAt,
+    EndedAt,
+    CreatedAt,
+    UpdatedAt,
+}
+
+#[derive(DeriveIden)]
+enum Users {
+    Table,
+    Id,
+}
+
+#[derive(DeriveIden)]
+enum CanDoList {
+    Table,
+    Id,
+}
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // A pomodoro-style focus or break period, optionally spent on a task. `ended_at` is
+        // NULL while the session runs.
+        manager
+            .create_table(
+                Table::create()
+                    .table(FocusSessions::Table)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(FocusSessions::Id)
+                            .uuid()
+                            .not_null()
+                            .primary_key()
+                            .default_uuid(manager, "uuid_generate_v7()"),
+                    )
+                    .col(ColumnDef::new(FocusSessions::UserId).uuid().not_null())
+                    .col(ColumnDef::new(FocusSessions::TaskId).uuid())
+                    .col(ColumnDef::new(FocusSessions::Kind).text().not_null().default("focus"))
+                    .col(
+                        ColumnDef::new(FocusSessions::PlannedMinutes)
+                            .integer()
+                            .not_null()
+                            .check(Expr::cust("planned_minutes > 0")),
+                    )
+                    .col(ColumnDef::new(FocusSessions::Status).text().not_null().default("running"))
+                    .col(ColumnDef::new(FocusSessions::StartedAt).timestamp_with_time_zone().not_null())
+                    .col(ColumnDef::new(FocusSessions::EndedAt).timestamp_with_time_zone())
+                    .col(
+                        ColumnDef::new(FocusSessions::CreatedAt)
+                            .timestamp_with_time_zone()
+                            .not_null()
+                            .default_now(manager),
+                    )
+                    .col(
+                        ColumnDef::new(FocusSessions::UpdatedAt)
+                            .timestamp_with_time_zone()
+                            .not_null()
+                            .default_now(manager),
+                    )
+                    .check(Expr::cust("(status = 'running') = (ended_at IS NULL)"))
+                    .check(Expr::cust("ended_at IS NULL OR ended_at >= started_at"))
+                    .auth_foreign_key(
+                        manager,
+                        ForeignKey::create()
+                            .name("fk-focus_sessions-user_id")
+                            .from(FocusSessions::Table, FocusSessions::UserId)
+                            .to((Alias::new("auth"), Users::Table), Users::Id)
+                            .on_delete(ForeignKeyAction::Cascade)
+                            .on_update(ForeignKeyAction::Cascade),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk-focus_sessions-task_id")
+                            .from(FocusSessions::Table, FocusSessions::TaskId)
+                            .to(CanDoList::Table, CanDoList::Id)
+                            .on_delete(ForeignKeyAction::SetNull)
+                            .on_update(ForeignKeyAction::Cascade),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx-focus_sessions-user_id-started_at")
+                    .table(FocusSessions::Table)
+                    .col(FocusSessions::UserId)
+                    .col(FocusSessions::StartedAt)
+                    .to_owned(),
+            )
+            .await?;
+
+        // At most one running session per user, so every device shows the same one
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx-focus_sessions-user_id-running")
+                    .table(FocusSessions::Table)
+                    .col(FocusSessions::UserId)
+                    .unique()
+                    .and_where(Expr::col(FocusSessions::EndedAt).is_null())
+                    .to_owned(),
+            )
+            .await?;
+
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(FocusSessions::Table).if_exists().to_owned())
+            .await
+    }
+}
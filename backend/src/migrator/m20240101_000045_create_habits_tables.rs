@@ -0,0 +1,184 @@
+use sea_orm_migration::prelude::*;
+use super::portable::{PortableColumnDef, PortableTableCreate};
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[derive(DeriveIden)]
+enum Habits {
+    Table,
+    Id,
+    UserId,
+    EncryptedData,
+    Iv,
+    Salt,
+    SchemaVersion,
+    Rrule,
+    StartsOn,
+    CurrentStreak,
+    LongestStreak,
+    StreakExpiresOn,
+    LastCheckinOn,
+    CreatedAt,
+    UpdatedAt,
+}
+
+#[derive(DeriveIden)]
+enum HabitCheckins {
+    Table,
+    Id,
+    HabitId,
+    UserId,
+    CheckedOn,
+    CreatedAt,
+}
+
+#[derive(DeriveIden)]
+enum Users {
+    Table,
+    Id,
+}
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // The habit's name and details are encrypted; its schedule and streaks stay plaintext
+        // so the server can keep the streaks up to date
+        manager
+            .create_table(
+                Table::create()
+                    .table(Habits::Table)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(Habits::Id)
+                            .uuid()
+                            .not_null()
+                            .primary_key()
+                            .default_uuid(manager, "uuid_generate_v7()"),
+                    )
+                    .col(ColumnDef::new(Habits::UserId).uuid().not_null())
+                    .col(ColumnDef::new(Habits::EncryptedData).text().not_null())
+                    .col(ColumnDef::new(Habits::Iv).text().not_null())
+                    .col(ColumnDef::new(Habits::Salt).text().not_null())
+                    .col(ColumnDef::new(Habits::SchemaVersion).small_integer().not_null().default(1))
+                    .col(ColumnDef::new(Habits::Rrule).text().not_null())
+                    .col(ColumnDef::new(Habits::StartsOn).date().not_null())
+                    .col(
+                        ColumnDef::new(Habits::CurrentStreak)
+                            .integer()
+                            .not_null()
+                            .default(0)
+                            .check(Expr::cust("current_streak >= 0")),
+                    )
+                    .col(
+                        ColumnDef::new(Habits::LongestStreak)
+                            .integer()
+                            .not_null()
+                            .default(0)
+                            .check(Expr::cust("longest_streak >= current_streak")),
+                    )
+                    .col(ColumnDef::new(Habits::StreakExpiresOn).date())
+                    .col(ColumnDef::new(Habits::LastCheckinOn).date())
+                    .col(
+                        ColumnDef::new(Habits::CreatedAt)
+                            .timestamp_with_time_zone()
+                            .not_null()
+                            .default_now(manager),
+                    )
+                    .col(
+                        ColumnDef::new(Habits::UpdatedAt)
+                            .timestamp_with_time_zone()
+                            .not_null()
+                            .default_now(manager),
+                    )
+                    .auth_foreign_key(
+                        manager,
+                        ForeignKey::create()
+                            .name("fk-habits-user_id")
+                            .from(Habits::Table, Habits::UserId)
+                            .to((Alias::new("auth"), Users::Table), Users::Id)
+                            .on_delete(ForeignKeyAction::Cascade)
+                            .on_update(ForeignKeyAction::Cascade),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx-habits-user_id-updated_at")
+                    .table(Habits::Table)
+                    .col(Habits::UserId)
+                    .col(Habits::UpdatedAt)
+                    .to_owned(),
+            )
+            .await?;
+
+        // One row per day the habit was done
+        manager
+            .create_table(
+                Table::create()
+                    .table(HabitCheckins::Table)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(HabitCheckins::Id)
+                            .uuid()
+                            .not_null()
+                            .primary_key()
+                            .default_uuid(manager, "uuid_generate_v7()"),
+                    )
+                    .col(ColumnDef::new(HabitCheckins::HabitId).uuid().not_null())
+                    .col(ColumnDef::new(HabitCheckins::UserId).uuid().not_null())
+                    .col(ColumnDef::new(HabitCheckins::CheckedOn).date().not_null())
+                    .col(
+                        ColumnDef::new(HabitCheckins::CreatedAt)
+                            .timestamp_with_time_zone()
+                            .not_null()
+                            .default_now(manager),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk-habit_checkins-habit_id")
+                            .from(HabitCheckins::Table, HabitCheckins::HabitId)
+                            .to(Habits::Table, Habits::Id)
+                            .on_delete(ForeignKeyAction::Cascade)
+                            .on_update(ForeignKeyAction::Cascade),
+                    )
+                    .auth_foreign_key(
+                        manager,
+                        ForeignKey::create()
+                            .name("fk-habit_checkins-user_id")
+                            .from(HabitCheckins::Table, HabitCheckins::UserId)
+                            .to((Alias::new("auth"), Users::Table), Users::Id)
+                            .on_delete(ForeignKeyAction::Cascade)
+                            .on_update(ForeignKeyAction::Cascade),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx-habit_checkins-habit_id-checked_on")
+                    .table(HabitCheckins::Table)
+                    .col(HabitCheckins::HabitId)
+                    .col(HabitCheckins::CheckedOn)
+                    .unique()
+                    .to_owned(),
+            )
+            .await?;
+
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(HabitCheckins::Table).if_exists().to_owned())
+            .await?;
+        manager
+            .drop_table(Table::drop().table(Habits::Table).if_exists().to_owned())
+            .await
+    }
+}
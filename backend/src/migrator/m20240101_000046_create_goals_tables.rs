@@ -0,0 +1,204 @@
+use sea_orm_migration::prelude::*;
+use super::portable::{PortableColumnDef, PortableTableCreate};
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[derive(DeriveIden)]
+enum Goals {
+    Table,
+    Id,
+    UserId,
+    EncryptedData,
+    Iv,
+    Salt,
+    SchemaVersion,
+    TargetDate,
+    AchievedAt,
+    CreatedAt,
+    UpdatedAt,
+}
+
+#[derive(DeriveIden)]
+enum GoalLinks {
+    Table,
+    Id,
+    GoalId,
+    UserId,
+    ProjectId,
+    TaskId,
+    CreatedAt,
+}
+
+#[derive(DeriveIden)]
+enum Users {
+    Table,
+    Id,
+}
+
+#[derive(DeriveIden)]
+enum Projects {
+    Table,
+    Id,
+}
+
+#[derive(DeriveIden)]
+enum CanDoList {
+    Table,
+    Id,
+}
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(Goals::Table)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(Goals::Id)
+                            .uuid()
+                            .not_null()
+                            .primary_key()
+                            .default_uuid(manager, "uuid_generate_v7()"),
+                    )
+                    .col(ColumnDef::new(Goals::UserId).uuid().not_null())
+                    .col(ColumnDef::new(Goals::EncryptedData).text().not_null())
+                    .col(ColumnDef::new(Goals::Iv).text().not_null())
+                    .col(ColumnDef::new(Goals::Salt).text().not_null())
+                    .col(ColumnDef::new(Goals::SchemaVersion).small_integer().not_null().default(1))
+                    .col(ColumnDef::new(Goals::TargetDate).date())
+                    .col(ColumnDef::new(Goals::AchievedAt).timestamp_with_time_zone())
+                    .col(
+                        ColumnDef::new(Goals::CreatedAt)
+                            .timestamp_with_time_zone()
+                            .not_null()
+                            .default_now(manager),
+                    )
+                    .col(
+                        ColumnDef::new(Goals::UpdatedAt)
+                            .timestamp_with_time_zone()
+                            .not_null()
+                            .default_now(manager),
+                    )
+                    .auth_foreign_key(
+                        manager,
+                        ForeignKey::create()
+                            .name("fk-goals-user_id")
+                            .from(Goals::Table, Goals::UserId)
+                            .to((Alias::new("auth"), Users::Table), Users::Id)
+                            .on_delete(ForeignKeyAction::Cascade)
+                            .on_update(ForeignKeyAction::Cascade),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx-goals-user_id")
+                    .table(Goals::Table)
+                    .col(Goals::UserId)
+                    .to_owned(),
+            )
+            .await?;
+
+        // A project or task counted towards a goal; purging it drops the link
+        manager
+            .create_table(
+                Table::create()
+                    .table(GoalLinks::Table)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(GoalLinks::Id)
+                            .uuid()
+                            .not_null()
+                            .primary_key()
+                            .default_uuid(manager, "uuid_generate_v7()"),
+                    )
+                    .col(ColumnDef::new(GoalLinks::GoalId).uuid().not_null())
+                    .col(ColumnDef::new(GoalLinks::UserId).uuid().not_null())
+                    .col(ColumnDef::new(GoalLinks::ProjectId).uuid())
+                    .col(ColumnDef::new(GoalLinks::TaskId).uuid())
+                    .col(
+                        ColumnDef::new(GoalLinks::CreatedAt)
+                            .timestamp_with_time_zone()
+                            .not_null()
+                            .default_now(manager),
+                    )
+                    .check(Expr::cust("(project_id IS NULL) <> (task_id IS NULL)"))
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk-goal_links-goal_id")
+                            .from(GoalLinks::Table, GoalLinks::GoalId)
+                            .to(Goals::Table, Goals::Id)
+                            .on_delete(ForeignKeyAction::Cascade)
+                            .on_update(ForeignKeyAction::Cascade),
+                    )
+                    .auth_foreign_key(
+                        manager,
+                        ForeignKey::create()
+                            .name("fk-goal_links-user_id")
+                            .from(GoalLinks::Table, GoalLinks::UserId)
+                            .to((Alias::new("auth"), Users::Table), Users::Id)
+                            .on_delete(ForeignKeyAction::Cascade)
+                            .on_update(ForeignKeyAction::Cascade),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk-goal_links-project_id")
+                            .from(GoalLinks::Table, GoalLinks::ProjectId)
+                            .to(Projects::Table, Projects::Id)
+                            .on_delete(ForeignKeyAction::Cascade)
+                            .on_update(ForeignKeyAction::Cascade),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk-goal_links-task_id")
+                            .from(GoalLinks::Table, GoalLinks::TaskId)
+                            .to(CanDoList::Table, CanDoList::Id)
+                            .on_delete(ForeignKeyAction::Cascade)
+                            .on_update(ForeignKeyAction::Cascade),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx-goal_links-goal_id-project_id")
+                    .table(GoalLinks::Table)
+                    .col(GoalLinks::GoalId)
+                    .col(GoalLinks::ProjectId)
+                    .unique()
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx-goal_links-goal_id-task_id")
+                    .table(GoalLinks::Table)
+                    .col(GoalLinks::GoalId)
+                    .col(GoalLinks::TaskId)
+                    .unique()
+                    .to_owned(),
+            )
+            .await?;
+
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(GoalLinks::Table).if_exists().to_owned())
+            .await?;
+        manager
+            .drop_table(Table::drop().table(Goals::Table).if_exists().to_owned())
+            .await
+    }
+}
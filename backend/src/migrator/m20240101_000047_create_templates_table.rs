@@ -0,0 +1,101 @@
+use sea_orm_migration::prelude::*;
+use super::portable::{PortableColumnDef, PortableTableCreate};
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[derive(DeriveIden)]
+enum Templates {
+    Table,
+    Id,
+    UserId,
+    Kind,
+    EncryptedData,
+    Iv,
+    Salt,
+    SchemaVersion,
+    Content,
+    CreatedAt,
+    UpdatedAt,
+}
+
+#[derive(DeriveIden)]
+enum Users {
+    Table,
+    Id,
+}
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // A saved project subtree or event. `content` holds copies of the records' encrypted
+        // payloads together with the rules that place their dates on instantiation.
+        manager
+            .create_table(
+                Table::create()
+                    .table(Templates::Table)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(Templates::Id)
+                            .uuid()
+                            .not_null()
+                            .primary_key()
+                            .default_uuid(manager, "uuid_generate_v7()"),
+                    )
+                    .col(ColumnDef::new(Templates::UserId).uuid().not_null())
+                    .col(
+                        ColumnDef::new(Templates::Kind)
+                            .text()
+                            .not_null()
+                            .check(Expr::cust("kind IN ('project', 'event')")),
+                    )
+                    .col(ColumnDef::new(Templates::EncryptedData).text().not_null())
+                    .col(ColumnDef::new(Templates::Iv).text().not_null())
+                    .col(ColumnDef::new(Templates::Salt).text().not_null())
+                    .col(ColumnDef::new(Templates::SchemaVersion).small_integer().not_null().default(1))
+                    .col(ColumnDef::new(Templates::Content).json_binary().not_null())
+                    .col(
+                        ColumnDef::new(Templates::CreatedAt)
+                            .timestamp_with_time_zone()
+                            .not_null()
+                            .default_now(manager),
+                    )
+                    .col(
+                        ColumnDef::new(Templates::UpdatedAt)
+                            .timestamp_with_time_zone()
+                            .not_null()
+                            .default_now(manager),
+                    )
+                    .auth_foreign_key(
+                        manager,
+                        ForeignKey::create()
+                            .name("fk-templates-user_id")
+                            .from(Templates::Table, Templates::UserId)
+                            .to((Alias::new("auth"), Users::Table), Users::Id)
+                            .on_delete(ForeignKeyAction::Cascade)
+                            .on_update(ForeignKeyAction::Cascade),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx-templates-user_id-kind")
+                    .table(Templates::Table)
+                    .col(Templates::UserId)
+                    .col(Templates::Kind)
+                    .to_owned(),
+            )
+            .await?;
+
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(Templates::Table).if_exists().to_owned())
+            .await
+    }
+}
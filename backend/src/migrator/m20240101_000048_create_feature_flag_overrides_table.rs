@@ -0,0 +1,88 @@
+use sea_orm_migration::prelude::*;
+use super::portable::{PortableColumnDef, PortableTableCreate};
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[derive(DeriveIden)]
+enum FeatureFlagOverrides {
+    Table,
+    Id,
+    UserId,
+    Feature,
+    Enabled,
+    CreatedAt,
+    UpdatedAt,
+}
+
+#[derive(DeriveIden)]
+enum Users {
+    Table,
+    Id,
+}
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // Switches a feature on or off for one user, whatever the instance-wide rollout says
+        manager
+            .create_table(
+                Table::create()
+                    .table(FeatureFlagOverrides::Table)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(FeatureFlagOverrides::Id)
+                            .uuid()
+                            .not_null()
+                            .primary_key()
+                            .default_uuid(manager, "uuid_generate_v7()"),
+                    )
+                    .col(ColumnDef::new(FeatureFlagOverrides::UserId).uuid().not_null())
+                    .col(ColumnDef::new(FeatureFlagOverrides::Feature).text().not_null())
+                    .col(ColumnDef::new(FeatureFlagOverrides::Enabled).boolean().not_null())
+                    .col(
+                        ColumnDef::new(FeatureFlagOverrides::CreatedAt)
+                            .timestamp_with_time_zone()
+                            .not_null()
+                            .default_now(manager),
+                    )
+                    .col(
+                        ColumnDef::new(FeatureFlagOverrides::UpdatedAt)
+                            .timestamp_with_time_zone()
+                            .not_null()
+                            .default_now(manager),
+                    )
+                    .auth_foreign_key(
+                        manager,
+                        ForeignKey::create()
+                            .name("fk-feature_flag_overrides-user_id")
+                            .from(FeatureFlagOverrides::Table, FeatureFlagOverrides::UserId)
+                            .to((Alias::new("auth"), Users::Table), Users::Id)
+                            .on_delete(ForeignKeyAction::Cascade)
+                            .on_update(ForeignKeyAction::Cascade),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx-feature_flag_overrides-user_id-feature")
+                    .table(FeatureFlagOverrides::Table)
+                    .col(FeatureFlagOverrides::UserId)
+                    .col(FeatureFlagOverrides::Feature)
+                    .unique()
+                    .to_owned(),
+            )
+            .await?;
+
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(FeatureFlagOverrides::Table).if_exists().to_owned())
+            .await
+    }
+}
@@ -0,0 +1,156 @@
+use sea_orm::ConnectionTrait;
+use sea_orm_migration::prelude::*;
+use uuid::Uuid;
+use super::portable::{PortableColumnDef, PortableTableCreate};
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[derive(DeriveIden)]
+enum Settings {
+    Table,
+    Id,
+    UserId,
+    Namespace,
+    EncryptedData,
+    Iv,
+    Salt,
+    SchemaVersion,
+    CreatedAt,
+    UpdatedAt,
+}
+
+#[derive(DeriveIden)]
+enum UserSettings {
+    Table,
+    UserId,
+    EncryptedData,
+    Iv,
+    Salt,
+    SchemaVersion,
+    CreatedAt,
+    UpdatedAt,
+}
+
+#[derive(DeriveIden)]
+enum Users {
+    Table,
+    Id,
+}
+
+/// Namespace the single settings blob of `user_settings` moves to
+const GENERAL_NAMESPACE: &str = "general";
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // Encrypted settings split into namespaces, so a toggle only rewrites its own part
+        manager
+            .create_table(
+                Table::create()
+                    .table(Settings::Table)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(Settings::Id)
+                            .uuid()
+                            .not_null()
+                            .primary_key()
+                            .default_uuid(manager, "uuid_generate_v7()"),
+                    )
+                    .col(ColumnDef::new(Settings::UserId).uuid().not_null())
+                    .col(ColumnDef::new(Settings::Namespace).text().not_null())
+                    .col(ColumnDef::new(Settings::EncryptedData).text().not_null())
+                    .col(ColumnDef::new(Settings::Iv).text().not_null())
+                    .col(ColumnDef::new(Settings::Salt).text().not_null())
+                    .col(ColumnDef::new(Settings::SchemaVersion).small_integer().not_null().default(1))
+                    .col(
+                        ColumnDef::new(Settings::CreatedAt)
+                            .timestamp_with_time_zone()
+                            .not_null()
+                            .default_now(manager),
+                    )
+                    .col(
+                        ColumnDef::new(Settings::UpdatedAt)
+                            .timestamp_with_time_zone()
+                            .not_null()
+                            .default_now(manager),
+                    )
+                    .auth_foreign_key(
+                        manager,
+                        ForeignKey::create()
+                            .name("fk-settings-user_id")
+                            .from(Settings::Table, Settings::UserId)
+                            .to((Alias::new("auth"), Users::Table), Users::Id)
+                            .on_delete(ForeignKeyAction::Cascade)
+                            .on_update(ForeignKeyAction::Cascade),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx-settings-user_id-namespace")
+                    .table(Settings::Table)
+                    .col(Settings::UserId)
+                    .col(Settings::Namespace)
+                    .unique()
+                    .to_owned(),
+            )
+            .await?;
+
+        // Saved blobs become the general namespace. Ids are made here as SQLite can't generate them.
+        let db = manager.get_connection();
+        let backend = manager.get_database_backend();
+        let select = Query::select()
+            .columns([
+                UserSettings::UserId,
+                UserSettings::EncryptedData,
+                UserSettings::Iv,
+                UserSettings::Salt,
+                UserSettings::SchemaVersion,
+                UserSettings::CreatedAt,
+                UserSettings::UpdatedAt,
+            ])
+            .from(UserSettings::Table)
+            .and_where(Expr::col(UserSettings::EncryptedData).ne("{}"))
+            .to_owned();
+        for row in db.query_all(backend.build(&select)).await? {
+            let insert = Query::insert()
+                .into_table(Settings::Table)
+                .columns([
+                    Settings::Id,
+                    Settings::UserId,
+                    Settings::Namespace,
+                    Settings::EncryptedData,
+                    Settings::Iv,
+                    Settings::Salt,
+                    Settings::SchemaVersion,
+                    Settings::CreatedAt,
+                    Settings::UpdatedAt,
+                ])
+                .values_panic([
+                    Uuid::now_v7().into(),
+                    row.try_get::<Uuid>("", "user_id")?.into(),
+                    GENERAL_NAMESPACE.into(),
+                    row.try_get::<String>("", "encrypted_data")?.into(),
+                    row.try_get::<String>("", "iv")?.into(),
+                    row.try_get::<String>("", "salt")?.into(),
+                    row.try_get::<i16>("", "schema_version")?.into(),
+                    row.try_get::<sea_orm::prelude::DateTimeWithTimeZone>("", "created_at")?.into(),
+                    row.try_get::<sea_orm::prelude::DateTimeWithTimeZone>("", "updated_at")?.into(),
+                ])
+                .to_owned();
+            db.execute(backend.build(&insert)).await?;
+        }
+
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(Settings::Table).if_exists().to_owned())
+            .await
+    }
+}
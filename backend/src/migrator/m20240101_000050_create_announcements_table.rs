@@ -0,0 +1,83 @@
+use sea_orm_migration::prelude::*;
+use super::portable::{PortableColumnDef, PortableTableCreate};
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[derive(DeriveIden)]
+enum Announcements {
+    Table,
+    Id,
+    Kind,
+    Message,
+    StartsAt,
+    EndsAt,
+    CreatedBy,
+    CreatedAt,
+}
+
+#[derive(DeriveIden)]
+enum Users {
+    Table,
+    Id,
+}
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // Notices super admins broadcast to everyone, kept for users who weren't connected
+        manager
+            .create_table(
+                Table::create()
+                    .table(Announcements::Table)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(Announcements::Id)
+                            .uuid()
+                            .not_null()
+                            .primary_key()
+                            .default_uuid(manager, "uuid_generate_v7()"),
+                    )
+                    .col(ColumnDef::new(Announcements::Kind).text().not_null())
+                    .col(ColumnDef::new(Announcements::Message).text().not_null())
+                    .col(ColumnDef::new(Announcements::StartsAt).timestamp_with_time_zone().null())
+                    .col(ColumnDef::new(Announcements::EndsAt).timestamp_with_time_zone().null())
+                    .col(ColumnDef::new(Announcements::CreatedBy).uuid().null())
+                    .col(
+                        ColumnDef::new(Announcements::CreatedAt)
+                            .timestamp_with_time_zone()
+                            .not_null()
+                            .default_now(manager),
+                    )
+                    .auth_foreign_key(
+                        manager,
+                        ForeignKey::create()
+                            .name("fk-announcements-created_by")
+                            .from(Announcements::Table, Announcements::CreatedBy)
+                            .to((Alias::new("auth"), Users::Table), Users::Id)
+                            .on_delete(ForeignKeyAction::SetNull)
+                            .on_update(ForeignKeyAction::Cascade),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx-announcements-created_at")
+                    .table(Announcements::Table)
+                    .col(Announcements::CreatedAt)
+                    .to_owned(),
+            )
+            .await?;
+
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(Announcements::Table).if_exists().to_owned())
+            .await
+    }
+}
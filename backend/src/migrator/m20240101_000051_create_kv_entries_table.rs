@@ -0,0 +1,94 @@
+use sea_orm_migration::prelude::*;
+use super::portable::{PortableColumnDef, PortableTableCreate};
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[derive(DeriveIden)]
+enum KvEntries {
+    Table,
+    Id,
+    UserId,
+    Key,
+    EncryptedData,
+    Iv,
+    Salt,
+    SchemaVersion,
+    CreatedAt,
+    UpdatedAt,
+}
+
+#[derive(DeriveIden)]
+enum Users {
+    Table,
+    Id,
+}
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // Small encrypted values under client-chosen keys, for UI state that needs no schema of its own
+        manager
+            .create_table(
+                Table::create()
+                    .table(KvEntries::Table)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(KvEntries::Id)
+                            .uuid()
+                            .not_null()
+                            .primary_key()
+                            .default_uuid(manager, "uuid_generate_v7()"),
+                    )
+                    .col(ColumnDef::new(KvEntries::UserId).uuid().not_null())
+                    .col(ColumnDef::new(KvEntries::Key).text().not_null())
+                    .col(ColumnDef::new(KvEntries::EncryptedData).text().not_null())
+                    .col(ColumnDef::new(KvEntries::Iv).text().not_null())
+                    .col(ColumnDef::new(KvEntries::Salt).text().not_null())
+                    .col(ColumnDef::new(KvEntries::SchemaVersion).small_integer().not_null().default(1))
+                    .col(
+                        ColumnDef::new(KvEntries::CreatedAt)
+                            .timestamp_with_time_zone()
+                            .not_null()
+                            .default_now(manager),
+                    )
+                    .col(
+                        ColumnDef::new(KvEntries::UpdatedAt)
+                            .timestamp_with_time_zone()
+                            .not_null()
+                            .default_now(manager),
+                    )
+                    .auth_foreign_key(
+                        manager,
+                        ForeignKey::create()
+                            .name("fk-kv_entries-user_id")
+                            .from(KvEntries::Table, KvEntries::UserId)
+                            .to((Alias::new("auth"), Users::Table), Users::Id)
+                            .on_delete(ForeignKeyAction::Cascade)
+                            .on_update(ForeignKeyAction::Cascade),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx-kv_entries-user_id-key")
+                    .table(KvEntries::Table)
+                    .col(KvEntries::UserId)
+                    .col(KvEntries::Key)
+                    .unique()
+                    .to_owned(),
+            )
+            .await?;
+
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(KvEntries::Table).if_exists().to_owned())
+            .await
+    }
+}
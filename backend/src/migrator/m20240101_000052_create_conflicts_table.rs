@@ -0,0 +1,92 @@
+use sea_orm_migration::prelude::*;
+use super::portable::{PortableColumnDef, PortableTableCreate};
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[derive(DeriveIden)]
+enum Conflicts {
+    Table,
+    Id,
+    UserId,
+    TableName,
+    RecordId,
+    Action,
+    BaseUpdatedAt,
+    ClientData,
+    ServerData,
+    Resolution,
+    ResolvedAt,
+    CreatedAt,
+}
+
+#[derive(DeriveIden)]
+enum Users {
+    Table,
+    Id,
+}
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // Offline writes that lost against a newer server version, kept with both sides until resolved
+        manager
+            .create_table(
+                Table::create()
+                    .table(Conflicts::Table)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(Conflicts::Id)
+                            .uuid()
+                            .not_null()
+                            .primary_key()
+                            .default_uuid(manager, "uuid_generate_v7()"),
+                    )
+                    .col(ColumnDef::new(Conflicts::UserId).uuid().not_null())
+                    .col(ColumnDef::new(Conflicts::TableName).text().not_null())
+                    .col(ColumnDef::new(Conflicts::RecordId).uuid().not_null())
+                    .col(ColumnDef::new(Conflicts::Action).text().not_null())
+                    .col(ColumnDef::new(Conflicts::BaseUpdatedAt).timestamp_with_time_zone().null())
+                    .col(ColumnDef::new(Conflicts::ClientData).json_binary().not_null())
+                    .col(ColumnDef::new(Conflicts::ServerData).json_binary().not_null())
+                    .col(ColumnDef::new(Conflicts::Resolution).text().null())
+                    .col(ColumnDef::new(Conflicts::ResolvedAt).timestamp_with_time_zone().null())
+                    .col(
+                        ColumnDef::new(Conflicts::CreatedAt)
+                            .timestamp_with_time_zone()
+                            .not_null()
+                            .default_now(manager),
+                    )
+                    .auth_foreign_key(
+                        manager,
+                        ForeignKey::create()
+                            .name("fk-conflicts-user_id")
+                            .from(Conflicts::Table, Conflicts::UserId)
+                            .to((Alias::new("auth"), Users::Table), Users::Id)
+                            .on_delete(ForeignKeyAction::Cascade)
+                            .on_update(ForeignKeyAction::Cascade),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx-conflicts-user_id-record_id")
+                    .table(Conflicts::Table)
+                    .col(Conflicts::UserId)
+                    .col(Conflicts::RecordId)
+                    .to_owned(),
+            )
+            .await?;
+
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(Conflicts::Table).if_exists().to_owned())
+            .await
+    }
+}
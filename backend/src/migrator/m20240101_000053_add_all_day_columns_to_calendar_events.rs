@@ -0,0 +1,43 @@
+use sea_orm_migration::prelude::*;
+use super::portable::{add_columns, drop_columns};
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[derive(DeriveIden)]
+enum CalendarEvents {
+    Table,
+    IsAllDay,
+    StartDate,
+    EndDate,
+}
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // All-day events span dates rather than times; the server derives their time bounds from them
+        add_columns(
+            manager,
+            CalendarEvents::Table,
+            vec![
+                ColumnDef::new(CalendarEvents::IsAllDay).boolean().not_null().default(false).to_owned(),
+                ColumnDef::new(CalendarEvents::StartDate).date().to_owned(),
+                ColumnDef::new(CalendarEvents::EndDate).date().to_owned(),
+            ],
+        )
+        .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        drop_columns(
+            manager,
+            CalendarEvents::Table,
+            vec![
+                CalendarEvents::IsAllDay.into_iden(),
+                CalendarEvents::StartDate.into_iden(),
+                CalendarEvents::EndDate.into_iden(),
+            ],
+        )
+        .await
+    }
+}
@@ -0,0 +1,38 @@
+use sea_orm_migration::prelude::*;
+use super::portable::{add_columns, drop_columns};
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[derive(DeriveIden)]
+enum Calendars {
+    Table,
+    Color,
+    DisplayOrder,
+}
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // Plaintext so members of a shared calendar can show and sort it without decrypting
+        // its payload; colors are `#rrggbb`
+        add_columns(
+            manager,
+            Calendars::Table,
+            vec![
+                ColumnDef::new(Calendars::Color).string_len(7).to_owned(),
+                ColumnDef::new(Calendars::DisplayOrder).integer().not_null().default(0).to_owned(),
+            ],
+        )
+        .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        drop_columns(
+            manager,
+            Calendars::Table,
+            vec![Calendars::Color.into_iden(), Calendars::DisplayOrder.into_iden()],
+        )
+        .await
+    }
+}
@@ -0,0 +1,66 @@
+use sea_orm_migration::prelude::*;
+use super::portable::{add_columns, drop_columns};
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[derive(DeriveIden)]
+enum EventAttendees {
+    Table,
+    Email,
+    InviteToken,
+    InvitedAt,
+}
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // Only set for attendees invited by email, who the server has to reach and recognize in
+        // replies; the token is part of the address replies are sent to
+        add_columns(
+            manager,
+            EventAttendees::Table,
+            vec![
+                ColumnDef::new(EventAttendees::Email).string().to_owned(),
+                ColumnDef::new(EventAttendees::InviteToken).string().to_owned(),
+                ColumnDef::new(EventAttendees::InvitedAt).timestamp_with_time_zone().to_owned(),
+            ],
+        )
+        .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx-event_attendees-invite_token")
+                    .table(EventAttendees::Table)
+                    .col(EventAttendees::InviteToken)
+                    .unique()
+                    .to_owned(),
+            )
+            .await?;
+
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_index(
+                Index::drop()
+                    .name("idx-event_attendees-invite_token")
+                    .table(EventAttendees::Table)
+                    .to_owned(),
+            )
+            .await?;
+
+        drop_columns(
+            manager,
+            EventAttendees::Table,
+            vec![
+                EventAttendees::Email.into_iden(),
+                EventAttendees::InviteToken.into_iden(),
+                EventAttendees::InvitedAt.into_iden(),
+            ],
+        )
+        .await
+    }
+}
@@ -0,0 +1,38 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[derive(DeriveIden)]
+enum Sessions {
+    Table,
+    ImpersonatorId,
+}
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // Set on sessions a super admin opened to impersonate the user, so they can be ended like
+        // any other session. No foreign key: impersonation tokens already stop working once the
+        // actor is gone or no longer a super admin.
+        manager
+            .alter_table(
+                Table::alter()
+                    .table((Alias::new("auth"), Sessions::Table))
+                    .add_column(ColumnDef::new(Sessions::ImpersonatorId).uuid())
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table((Alias::new("auth"), Sessions::Table))
+                    .drop_column(Sessions::ImpersonatorId)
+                    .to_owned(),
+            )
+            .await
+    }
+}
@@ -7,6 +7,59 @@ pub mod m20240101_000004_create_can_do_list_table;
 pub mod m20240101_000005_create_calendars_table;
 pub mod m20240101_000006_create_calendar_events_table;
 pub mod m20240101_000007_create_user_settings_table;
+pub mod m20240101_000008_create_deleted_records_table;
+pub mod m20240101_000009_add_soft_delete_columns;
+pub mod m20240101_000010_add_calendar_event_time_columns;
+pub mod m20240101_000011_add_calendar_id_to_calendar_events;
+pub mod m20240101_000012_use_uuid_v7_defaults;
+pub mod m20240101_000013_add_search_bloom_columns;
+pub mod m20240101_000014_create_audit_log_table;
+pub mod m20240101_000015_add_recurrence_to_calendar_events;
+pub mod m20240101_000016_create_schema_backfills_table;
+pub mod m20240101_000017_create_reminders_table;
+pub mod m20240101_000018_create_sessions_table;
+pub mod m20240101_000019_add_due_dates_to_can_do_list;
+pub mod m20240101_000020_add_subtasks_to_can_do_list;
+pub mod m20240101_000021_add_status_to_calendar_events;
+pub mod m20240101_000022_create_time_entries_table;
+pub mod m20240101_000023_add_rrule_to_can_do_list;
+pub mod m20240101_000024_create_event_attendees_table;
+pub mod m20240101_000025_create_notes_table;
+pub mod m20240101_000026_add_priority_to_can_do_list;
+pub mod m20240101_000027_add_is_pinned_columns;
+pub mod m20240101_000028_create_default_projects;
+pub mod m20240101_000029_create_project_members_table;
+pub mod m20240101_000030_create_scheduling_polls_tables;
+pub mod m20240101_000031_create_booking_pages_tables;
+pub mod m20240101_000032_create_calendar_subscriptions_table;
+pub mod m20240101_000033_create_webhooks_tables;
+pub mod m20240101_000034_add_previous_secret_to_webhooks;
+pub mod m20240101_000035_create_inbound_email_tables;
+pub mod m20240101_000036_create_notification_preferences_table;
+pub mod m20240101_000037_create_organizations_tables;
+pub mod m20240101_000038_add_search_tokens_columns;
+pub mod m20240101_000039_add_schema_version_columns;
+pub mod m20240101_000040_create_record_revisions_table;
+mod m20240101_000041_add_timezone_to_user_settings;
+mod m20240101_000042_create_scheduling_constraints_table;
+mod m20240101_000043_add_linked_task_id_to_calendar_events;
+mod m20240101_000044_create_focus_sessions_table;
+mod m20240101_000045_create_habits_tables;
+mod m20240101_000046_create_goals_tables;
+mod m20240101_000047_create_templates_table;
+mod m20240101_000048_create_feature_flag_overrides_table;
+mod m20240101_000049_create_settings_table;
+mod m20240101_000050_create_announcements_table;
+mod m20240101_000051_create_kv_entries_table;
+mod m20240101_000052_create_conflicts_table;
+mod m20240101_000053_add_all_day_columns_to_calendar_events;
+mod m20240101_000054_add_color_and_order_to_calendars;
+mod m20240101_000055_add_invitations_to_event_attendees;
+mod m20240101_000056_add_impersonator_id_to_sessions;
+
+pub mod expand_contract;
+pub mod portable;
+pub mod runner;
 
 pub struct Migrator;
 
@@ -21,6 +74,55 @@ impl MigratorTrait for Migrator {
             Box::new(m20240101_000005_create_calendars_table::Migration),
             Box::new(m20240101_000006_create_calendar_events_table::Migration),
             Box::new(m20240101_000007_create_user_settings_table::Migration),
+            Box::new(m20240101_000008_create_deleted_records_table::Migration),
+            Box::new(m20240101_000009_add_soft_delete_columns::Migration),
+            Box::new(m20240101_000010_add_calendar_event_time_columns::Migration),
+            Box::new(m20240101_000011_add_calendar_id_to_calendar_events::Migration),
+            Box::new(m20240101_000012_use_uuid_v7_defaults::Migration),
+            Box::new(m20240101_000013_add_search_bloom_columns::Migration),
+            Box::new(m20240101_000014_create_audit_log_table::Migration),
+            Box::new(m20240101_000015_add_recurrence_to_calendar_events::Migration),
+            Box::new(m20240101_000016_create_schema_backfills_table::Migration),
+            Box::new(m20240101_000017_create_reminders_table::Migration),
+            Box::new(m20240101_000018_create_sessions_table::Migration),
+            Box::new(m20240101_000019_add_due_dates_to_can_do_list::Migration),
+            Box::new(m20240101_000020_add_subtasks_to_can_do_list::Migration),
+            Box::new(m20240101_000021_add_status_to_calendar_events::Migration),
+            Box::new(m20240101_000022_create_time_entries_table::Migration),
+            Box::new(m20240101_000023_add_rrule_to_can_do_list::Migration),
+            Box::new(m20240101_000024_create_event_attendees_table::Migration),
+            Box::new(m20240101_000025_create_notes_table::Migration),
+            Box::new(m20240101_000026_add_priority_to_can_do_list::Migration),
+            Box::new(m20240101_000027_add_is_pinned_columns::Migration),
+            Box::new(m20240101_000028_create_default_projects::Migration),
+            Box::new(m20240101_000029_create_project_members_table::Migration),
+            Box::new(m20240101_000030_create_scheduling_polls_tables::Migration),
+            Box::new(m20240101_000031_create_booking_pages_tables::Migration),
+            Box::new(m20240101_000032_create_calendar_subscriptions_table::Migration),
+            Box::new(m20240101_000033_create_webhooks_tables::Migration),
+            Box::new(m20240101_000034_add_previous_secret_to_webhooks::Migration),
+            Box::new(m20240101_000035_create_inbound_email_tables::Migration),
+            Box::new(m20240101_000036_create_notification_preferences_table::Migration),
+            Box::new(m20240101_000037_create_organizations_tables::Migration),
+            Box::new(m20240101_000038_add_search_tokens_columns::Migration),
+            Box::new(m20240101_000039_add_schema_version_columns::Migration),
+            Box::new(m20240101_000040_create_record_revisions_table::Migration),
+            Box::new(m20240101_000041_add_timezone_to_user_settings::Migration),
+            Box::new(m20240101_000042_create_scheduling_constraints_table::Migration),
+            Box::new(m20240101_000043_add_linked_task_id_to_calendar_events::Migration),
+            Box::new(m20240101_000044_create_focus_sessions_table::Migration),
+            Box::new(m20240101_000045_create_habits_tables::Migration),
+            Box::new(m20240101_000046_create_goals_tables::Migration),
+            Box::new(m20240101_000047_create_templates_table::Migration),
+            Box::new(m20240101_000048_create_feature_flag_overrides_table::Migration),
+            Box::new(m20240101_000049_create_settings_table::Migration),
+            Box::new(m20240101_000050_create_announcements_table::Migration),
+            Box::new(m20240101_000051_create_kv_entries_table::Migration),
+            Box::new(m20240101_000052_create_conflicts_table::Migration),
+            Box::new(m20240101_000053_add_all_day_columns_to_calendar_events::Migration),
+            Box::new(m20240101_000054_add_color_and_order_to_calendars::Migration),
+            Box::new(m20240101_000055_add_invitations_to_event_attendees::Migration),
+            Box::new(m20240101_000056_add_impersonator_id_to_sessions::Migration),
         ]
     }
 }
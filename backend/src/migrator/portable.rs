@@ -0,0 +1,209 @@
+//! Helpers for migrations that have to run on both Postgres and SQLite.
+//!
+//! SQLite keeps the `auth` schema in an attached database file (see `db::sqlite_auth_path`)
+//! and lacks a few things Postgres migrations take for granted:
+//!
+//! - no UUID functions, so ids are always generated by the application there
+//! - no `jsonb`; JSON columns are text with plain string defaults
+//! - no foreign keys into another database, so references to `auth` tables are left out
+//! - one column per `ALTER TABLE`, and no constraints added to existing tables; foreign
+//!   keys added later are emulated with triggers
+//!
+//! Data-only migrations written before SQLite support existed are skipped on SQLite, as
+//! such a database always starts out empty.
+
+use sea_orm::{ConnectionTrait, DbBackend, DbErr};
+use sea_orm_migration::prelude::*;
+
+pub fn is_sqlite(manager: &SchemaManager) -> bool {
+    manager.get_database_backend() == DbBackend::Sqlite
+}
+
+pub trait PortableColumnDef {
+    /// `DEFAULT <function>()` on Postgres; SQLite relies on the application for ids
+    fn default_uuid(&mut self, manager: &SchemaManager, function: &str) -> &mut Self;
+    /// The current time; on SQLite in the RFC 3339 text the driver writes, so values compare as text
+    fn default_now(&mut self, manager: &SchemaManager) -> &mut Self;
+    /// A JSON literal, cast to `jsonb` on Postgres
+    fn default_json(&mut self, manager: &SchemaManager, json: &str) -> &mut Self;
+}
+
+impl PortableColumnDef for ColumnDef {
+    fn default_uuid(&mut self, manager: &SchemaManager, function: &str) -> &mut Self {
+        if is_sqlite(manager) {
+            return self;
+        }
+        self.extra(format!("DEFAULT {}", function))
+    }
+
+    fn default_now(&mut self, manager: &SchemaManager) -> &mut Self {
+        if is_sqlite(manager) {
+            return self.extra("DEFAULT (strftime('%Y-%m-%dT%H:%M:%f+00:00', 'now'))".to_string());
+        }
+        self.extra("DEFAULT NOW()".to_string())
+    }
+
+    fn default_json(&mut self, manager: &SchemaManager, json: &str) -> &mut Self {
+        let literal = json.replace('\'', "''");
+        if is_sqlite(manager) {
+            return self.extra(format!("DEFAULT '{}'", literal));
+        }
+        self.extra(format!("DEFAULT '{}'::jsonb", literal))
+    }
+}
+
+pub trait PortableTableCreate {
+    /// Foreign key from the new table to a table in the `auth` schema, left out on SQLite
+    fn auth_foreign_key(&mut self, manager: &SchemaManager, foreign_key: &mut ForeignKeyCreateStatement) -> &mut Self;
+}
+
+impl PortableTableCreate for TableCreateStatement {
+    fn auth_foreign_key(&mut self, manager: &SchemaManager, foreign_key: &mut ForeignKeyCreateStatement) -> &mut Self {
+        if is_sqlite(manager) {
+            return self;
+        }
+        self.foreign_key(foreign_key)
+    }
+}
+
+/// Index on a table in the `auth` schema; SQLite qualifies the index name instead of the table
+pub async fn create_auth_index(
+    manager: &SchemaManager<'_>,
+    name: &str,
+    table: impl IntoIden,
+    columns: &[&str],
+) -> Result<(), DbErr> {
+    let table = table.into_iden();
+    if is_sqlite(manager) {
+        let columns: Vec<String> = columns.iter().map(|column| format!("\"{}\"", column)).collect();
+        manager
+            .get_connection()
+            .execute_unprepared(&format!(
+                "CREATE INDEX IF NOT EXISTS \"auth\".\"{}\" ON \"{}\" ({})",
+                name,
+                table.to_string(),
+                columns.join(", ")
+            ))
+            .await?;
+        return Ok(());
+    }
+
+    let mut index = Index::create();
+    index.name(name).table((Alias::new("auth"), table));
+    for column in columns {
+        index.col(Alias::new(*column));
+    }
+    manager.create_index(index).await
+}
+
+/// Add columns to an existing table, in a single statement where the database allows it
+pub async fn add_columns(
+    manager: &SchemaManager<'_>,
+    table: impl IntoIden,
+    columns: Vec<ColumnDef>,
+) -> Result<(), DbErr> {
+    let table = table.into_iden();
+    if is_sqlite(manager) {
+        for mut column in columns {
+            manager
+                .alter_table(Table::alter().table(table.clone()).add_column(&mut column).to_owned())
+                .await?;
+        }
+        return Ok(());
+    }
+
+    let mut alter = Table::alter();
+    alter.table(table);
+    for mut column in columns {
+        alter.add_column_if_not_exists(&mut column);
+    }
+    manager.alter_table(alter).await
+}
+
+/// Drop columns from an existing table, in a single statement where the database allows it
+pub async fn drop_columns(
+    manager: &SchemaManager<'_>,
+    table: impl IntoIden,
+    columns: Vec<DynIden>,
+) -> Result<(), DbErr> {
+    let table = table.into_iden();
+    if is_sqlite(manager) {
+        for column in columns {
+            manager
+                .alter_table(Table::alter().table(table.clone()).drop_column(column).to_owned())
+                .await?;
+        }
+        return Ok(());
+    }
+
+    let mut alter = Table::alter();
+    alter.table(table);
+    for column in columns {
+        alter.drop_column(column);
+    }
+    manager.alter_table(alter).await
+}
+
+/// Foreign key on an existing table, cascading updates. SQLite can't add constraints to
+/// existing tables, so there its `ON DELETE` action is emulated by a trigger of the same name.
+pub async fn add_foreign_key(
+    manager: &SchemaManager<'_>,
+    name: &str,
+    (table, column): (impl IntoIden, impl IntoIden),
+    (ref_table, ref_column): (impl IntoIden, impl IntoIden),
+    on_delete: ForeignKeyAction,
+) -> Result<(), DbErr> {
+    let (table, column) = (table.into_iden(), column.into_iden());
+    let (ref_table, ref_column) = (ref_table.into_iden(), ref_column.into_iden());
+    if !is_sqlite(manager) {
+        return manager
+            .create_foreign_key(
+                ForeignKey::create()
+                    .name(name)
+                    .from(table, column)
+                    .to(ref_table, ref_column)
+                    .on_delete(on_delete)
+                    .on_update(ForeignKeyAction::Cascade)
+                    .to_owned(),
+            )
+            .await;
+    }
+
+    let (table, column) = (table.to_string(), column.to_string());
+    let matching = format!("\"{}\".\"{}\" = OLD.\"{}\"", table, column, ref_column.to_string());
+    let action = match on_delete {
+        ForeignKeyAction::Cascade => format!("DELETE FROM \"{}\" WHERE {};", table, matching),
+        ForeignKeyAction::SetNull => format!("UPDATE \"{}\" SET \"{}\" = NULL WHERE {};", table, column, matching),
+        _ => format!(
+            "SELECT RAISE(ABORT, 'FOREIGN KEY constraint failed') WHERE EXISTS (SELECT 1 FROM \"{}\" WHERE {});",
+            table, matching
+        ),
+    };
+
+    manager
+        .get_connection()
+        .execute_unprepared(&format!(
+            "CREATE TRIGGER IF NOT EXISTS \"{}\" BEFORE DELETE ON \"{}\" FOR EACH ROW BEGIN {} END",
+            name,
+            ref_table.to_string(),
+            action
+        ))
+        .await?;
+    Ok(())
+}
+
+/// Undo [`add_foreign_key`]
+pub async fn drop_foreign_key(manager: &SchemaManager<'_>, name: &str, table: impl IntoIden) -> Result<(), DbErr> {
+    let table = table.into_iden();
+    if is_sqlite(manager) {
+        manager
+            .get_connection()
+            .execute_unprepared(&format!("DROP TRIGGER IF EXISTS \"{}\"", name))
+            .await?;
+        return Ok(());
+    }
+
+    manager
+        .drop_foreign_key(ForeignKey::drop().name(name).table(table).to_owned())
+        .await
+}
@@ -0,0 +1,277 @@
+use sea_orm::{ConnectionTrait, DatabaseConnection, DatabaseTransaction, DbBackend, FromQueryResult, Statement, TransactionTrait};
+use sea_orm_migration::MigratorTrait;
+use std::collections::HashSet;
+use std::env;
+use std::time::Duration;
+
+use super::Migrator;
+use crate::errors::{AppError, Result};
+
+/// Arbitrary but fixed key for the advisory lock serializing migrations across replicas
+const MIGRATION_LOCK_KEY: i64 = 0x5354_524d_4d49_4752; // "STRMMIGR"
+const DEFAULT_WAIT_TIMEOUT_SECS: u64 = 300;
+const WAIT_POLL_INTERVAL: Duration = Duration::from_secs(2);
+
+/// How this replica treats pending migrations at startup (`MIGRATION_MODE`)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MigrationMode {
+    /// Apply pending migrations under an advisory lock (default)
+    Run,
+    /// Never write to the schema; wait until another replica has applied everything
+    Wait,
+    /// Leave the schema alone; migrations are run separately with `migrate up`
+    Skip,
+}
+
+impl MigrationMode {
+    pub fn from_env() -> Result<Self> {
+        match env::var("MIGRATION_MODE").as_deref() {
+            Err(_) | Ok("run") => Ok(MigrationMode::Run),
+            Ok("wait") => Ok(MigrationMode::Wait),
+            Ok("skip") => Ok(MigrationMode::Skip),
+            Ok(other) => Err(AppError::Internal(format!(
+                "Unknown MIGRATION_MODE '{}', expected 'run', 'wait' or 'skip'",
+                other
+            ))),
+        }
+    }
+}
+
+#[derive(Debug, FromQueryResult)]
+struct AppliedMigration {
+    version: String,
+}
+
+/// Versions recorded in the migration table, read without creating it
+pub(crate) async fn applied_versions<C: ConnectionTrait>(db: &C) -> Result<HashSet<String>> {
+    let backend = db.get_database_backend();
+    let table_exists_sql = match backend {
+        DbBackend::Sqlite => {
+            "SELECT COUNT(*) > 0 AS present FROM sqlite_master WHERE type = 'table' AND name = 'seaql_migrations'"
+        }
+        _ => "SELECT to_regclass('seaql_migrations') IS NOT NULL AS present",
+    };
+    let table_exists = db
+        .query_one(Statement::from_string(backend, table_exists_sql))
+        .await
+        .map_err(|e| AppError::Database(e.into()))?
+        .and_then(|row| row.try_get::<bool>("", "present").ok())
+        .unwrap_or(false);
+    if !table_exists {
+        return Ok(HashSet::new());
+    }
+
+    let rows = AppliedMigration::find_by_statement(Statement::from_string(
+        backend,
+        "SELECT version FROM seaql_migrations",
+    ))
+    .all(db)
+    .await
+    .map_err(|e| AppError::Database(e.into()))?;
+
+    Ok(rows.into_iter().map(|row| row.version).collect())
+}
+
+fn known_versions() -> HashSet<String> {
+    Migrator::migrations()
+        .iter()
+        .map(|migration| migration.name().to_string())
+        .collect()
+}
+
+/// Refuse to start against a schema migrated by a newer release; running old code
+/// on it could silently corrupt data the newer columns depend on
+fn ensure_schema_not_newer(applied: &HashSet<String>, known: &HashSet<String>) -> Result<()> {
+    let mut unknown: Vec<&String> = applied.difference(known).collect();
+    if unknown.is_empty() {
+        return Ok(());
+    }
+
+    unknown.sort();
+    Err(AppError::Internal(format!(
+        "Database schema is newer than this binary supports (unknown migrations: {}); deploy a newer release",
+        unknown.iter().map(|v| v.as_str()).collect::<Vec<_>>().join(", ")
+    )))
+}
+
+/// Migrations this binary knows about that haven't been applied yet, sorted by version
+pub async fn pending_migrations<C: ConnectionTrait>(db: &C) -> Result<Vec<String>> {
+    let applied = applied_versions(db).await?;
+    let mut pending: Vec<String> = known_versions().difference(&applied).cloned().collect();
+    pending.sort();
+    Ok(pending)
+}
+
+/// Whether a migration has been applied to the database
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MigrationState {
+    Applied,
+    Pending,
+    /// Applied by a newer release this binary doesn't know
+    Unknown,
+}
+
+/// Every migration this binary knows or the database has applied, sorted by version
+pub async fn status<C: ConnectionTrait>(db: &C) -> Result<Vec<(String, MigrationState)>> {
+    let applied = applied_versions(db).await?;
+    let known = known_versions();
+    let mut migrations: Vec<(String, MigrationState)> = known
+        .iter()
+        .map(|version| {
+            let state = if applied.contains(version) { MigrationState::Applied } else { MigrationState::Pending };
+            (version.clone(), state)
+        })
+        .chain(applied.difference(&known).map(|version| (version.clone(), MigrationState::Unknown)))
+        .collect();
+    migrations.sort_by(|(a, _), (b, _)| a.cmp(b));
+    Ok(migrations)
+}
+
+/// Bring the schema up to date according to `MIGRATION_MODE`
+pub async fn run(db: &DatabaseConnection) -> Result<()> {
+    match MigrationMode::from_env()? {
+        MigrationMode::Run => up(db, None).await,
+        MigrationMode::Wait => wait(db).await,
+        MigrationMode::Skip => skip(db).await,
+    }
+}
+
+/// Apply pending migrations, at most `steps` of them, while holding a transaction-scoped
+/// advisory lock, so replicas booting together run them one at a time and later ones find
+/// nothing left to do
+pub async fn up(db: &DatabaseConnection, steps: Option<u32>) -> Result<()> {
+    let txn = lock(db).await?;
+    ensure_schema_not_newer(&applied_versions(&txn).await?, &known_versions())?;
+
+    Migrator::up(&txn, steps).await?;
+
+    // Committing releases the lock
+    txn.commit().await
+        .map_err(|e| AppError::Database(e.into()))?;
+
+    Ok(())
+}
+
+/// Roll back the `steps` most recently applied migrations under the migration lock
+pub async fn down(db: &DatabaseConnection, steps: u32) -> Result<()> {
+    let txn = lock(db).await?;
+    ensure_schema_not_newer(&applied_versions(&txn).await?, &known_versions())?;
+
+    Migrator::down(&txn, Some(steps)).await?;
+
+    txn.commit().await
+        .map_err(|e| AppError::Database(e.into()))?;
+
+    Ok(())
+}
+
+/// Drop every table, in both schemas, and apply all migrations to the empty database
+pub async fn fresh(db: &DatabaseConnection) -> Result<()> {
+    if db.get_database_backend() == DbBackend::Sqlite {
+        drop_sqlite_tables(db).await?;
+        Migrator::up(db, None).await?;
+        return Ok(());
+    }
+
+    let txn = lock(db).await?;
+    txn.execute_unprepared("DROP SCHEMA IF EXISTS auth CASCADE")
+        .await
+        .map_err(|e| AppError::Database(e.into()))?;
+    Migrator::fresh(&txn).await?;
+    txn.commit().await
+        .map_err(|e| AppError::Database(e.into()))?;
+
+    Ok(())
+}
+
+/// Drop every trigger and table in both SQLite files on one connection, so switching off
+/// foreign key checks applies to all of the drops. `Migrator::fresh` can't: it leaves the
+/// attached auth file alone and runs each statement on whichever pooled connection is free.
+async fn drop_sqlite_tables(db: &DatabaseConnection) -> Result<()> {
+    let failed = |e: sqlx::Error| AppError::Database(e.into());
+    let mut conn = db.get_sqlite_connection_pool().acquire().await.map_err(failed)?;
+
+    sqlx::query("PRAGMA foreign_keys = OFF").execute(&mut *conn).await.map_err(failed)?;
+    for schema in ["main", "auth"] {
+        let objects: Vec<(String, String)> = sqlx::query_as(&format!(
+            "SELECT type, name FROM \"{}\".sqlite_master \
+             WHERE type IN ('trigger', 'table') AND name NOT LIKE 'sqlite_%' \
+             ORDER BY type = 'table', rowid DESC",
+            schema
+        ))
+        .fetch_all(&mut *conn)
+        .await
+        .map_err(failed)?;
+        for (kind, name) in objects {
+            sqlx::query(&format!("DROP {} IF EXISTS \"{}\".\"{}\"", kind.to_uppercase(), schema, name))
+                .execute(&mut *conn)
+                .await
+                .map_err(failed)?;
+        }
+    }
+    sqlx::query("PRAGMA foreign_keys = ON").execute(&mut *conn).await.map_err(failed)?;
+    Ok(())
+}
+
+/// Begin a transaction holding the migration lock
+async fn lock(db: &DatabaseConnection) -> Result<DatabaseTransaction> {
+    let txn = db.begin().await
+        .map_err(|e| AppError::Database(e.into()))?;
+
+    // A SQLite file has a single server and its transactions already lock the whole database
+    if txn.get_database_backend() == DbBackend::Postgres {
+        tracing::info!("Acquiring migration lock...");
+        txn.execute(Statement::from_sql_and_values(
+            DbBackend::Postgres,
+            "SELECT pg_advisory_xact_lock($1)",
+            [MIGRATION_LOCK_KEY.into()],
+        ))
+        .await
+        .map_err(|e| AppError::Database(e.into()))?;
+    }
+
+    Ok(txn)
+}
+
+/// Start without migrating, only refusing a schema from a newer release
+async fn skip(db: &DatabaseConnection) -> Result<()> {
+    let applied = applied_versions(db).await?;
+    let known = known_versions();
+    ensure_schema_not_newer(&applied, &known)?;
+
+    let pending = known.difference(&applied).count();
+    if pending > 0 {
+        tracing::warn!("Starting with {} pending migrations; apply them with `migrate up`", pending);
+    }
+    Ok(())
+}
+
+/// Poll until every migration this binary knows about has been applied elsewhere
+async fn wait(db: &DatabaseConnection) -> Result<()> {
+    let timeout_secs = env::var("MIGRATION_WAIT_TIMEOUT_SECS")
+        .ok()
+        .and_then(|v| v.parse().ok())
+        .unwrap_or(DEFAULT_WAIT_TIMEOUT_SECS);
+    let deadline = tokio::time::Instant::now() + Duration::from_secs(timeout_secs);
+    let known = known_versions();
+
+    loop {
+        let applied = applied_versions(db).await?;
+        ensure_schema_not_newer(&applied, &known)?;
+
+        let pending = known.difference(&applied).count();
+        if pending == 0 {
+            return Ok(());
+        }
+
+        if tokio::time::Instant::now() >= deadline {
+            return Err(AppError::Internal(format!(
+                "Timed out after {}s waiting for {} pending migrations",
+                timeout_secs, pending
+            )));
+        }
+
+        tracing::info!("Waiting for {} pending migrations to be applied by another replica", pending);
+        tokio::time::sleep(WAIT_POLL_INTERVAL).await;
+    }
+}
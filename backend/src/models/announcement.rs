@@ -0,0 +1,83 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::{
+    entities::announcements::{self, AnnouncementKind},
+    errors::FieldError,
+    models::validation::{field_error, validate_required, Validate},
+};
+
+/// Longest announcement message accepted
+pub const MAX_ANNOUNCEMENT_LENGTH: usize = 2000;
+
+#[derive(Debug, Deserialize)]
+pub struct CreateAnnouncementRequest {
+    /// Defaults to `info`
+    pub kind: Option<AnnouncementKind>,
+    pub message: String,
+    /// The window the announcement is about, e.g. planned downtime
+    pub starts_at: Option<DateTime<Utc>>,
+    /// After this the announcement is no longer listed
+    pub ends_at: Option<DateTime<Utc>>,
+}
+
+/// `?since=` only lists announcements sent after the given time, e.g. the last one the client saw
+#[derive(Debug, Deserialize)]
+pub struct AnnouncementQuery {
+    pub since: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct AnnouncementResponse {
+    pub id: Uuid,
+    pub kind: AnnouncementKind,
+    pub message: String,
+    pub starts_at: Option<DateTime<Utc>>,
+    pub ends_at: Option<DateTime<Utc>>,
+    pub created_at: DateTime<Utc>,
+}
+
+/// Frame pushed to every open WebSocket connection when an announcement is sent
+#[derive(Debug, Serialize)]
+pub struct AnnouncementFrame<'a> {
+    #[serde(rename = "type")]
+    pub frame_type: &'static str,
+    #[serde(flatten)]
+    pub announcement: &'a AnnouncementResponse,
+}
+
+impl AnnouncementResponse {
+    pub fn frame(&self) -> AnnouncementFrame<'_> {
+        AnnouncementFrame { frame_type: "announcement", announcement: self }
+    }
+}
+
+impl From<announcements::Model> for AnnouncementResponse {
+    fn from(announcement: announcements::Model) -> Self {
+        Self {
+            id: announcement.id,
+            kind: announcement.kind,
+            message: announcement.message,
+            starts_at: announcement.starts_at.map(|starts_at| starts_at.naive_utc().and_utc()),
+            ends_at: announcement.ends_at.map(|ends_at| ends_at.naive_utc().and_utc()),
+            created_at: announcement.created_at.naive_utc().and_utc(),
+        }
+    }
+}
+
+impl Validate for CreateAnnouncementRequest {
+    fn validate(&self, errors: &mut Vec<FieldError>) {
+        validate_required(errors, "message", &self.message);
+        if self.message.chars().count() > MAX_ANNOUNCEMENT_LENGTH {
+            field_error(errors, "message", format!("message must be at most {} characters", MAX_ANNOUNCEMENT_LENGTH));
+        }
+        if let Some(ends_at) = self.ends_at {
+            if self.starts_at.is_some_and(|starts_at| ends_at <= starts_at) {
+                field_error(errors, "ends_at", "ends_at must be after starts_at");
+            } else if ends_at <= Utc::now() {
+                field_error(errors, "ends_at", "ends_at must be in the future");
+            }
+        }
+    }
+}
@@ -0,0 +1,61 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use uuid::Uuid;
+use crate::{
+    entities::audit_log,
+    errors::FieldError,
+    models::user::AuthResponse,
+    models::validation::{field_error, validate_reference, validate_required, Validate},
+};
+
+/// Default and maximum lifetime of an impersonation token
+pub const DEFAULT_IMPERSONATION_MINUTES: i64 = 15;
+pub const MAX_IMPERSONATION_MINUTES: i64 = 60;
+
+#[derive(Debug, Deserialize)]
+pub struct ImpersonateRequest {
+    pub user_id: Uuid,
+    /// Why support needs access; shown to the affected user
+    pub reason: String,
+    pub ttl_minutes: Option<i64>,
+}
+
+/// Token for an impersonation session, with the session to end once support is done
+#[derive(Debug, Serialize)]
+pub struct ImpersonationResponse {
+    pub session_id: Uuid,
+    #[serde(flatten)]
+    pub auth: AuthResponse,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ActivityResponse {
+    pub id: Uuid,
+    pub action: String,
+    pub actor_id: Option<Uuid>,
+    pub details: Value,
+    pub created_at: DateTime<Utc>,
+}
+
+impl From<audit_log::Model> for ActivityResponse {
+    fn from(entry: audit_log::Model) -> Self {
+        Self {
+            id: entry.id,
+            action: entry.action,
+            actor_id: entry.actor_id,
+            details: entry.details,
+            created_at: entry.created_at.naive_utc().and_utc(),
+        }
+    }
+}
+
+impl Validate for ImpersonateRequest {
+    fn validate(&self, errors: &mut Vec<FieldError>) {
+        validate_reference(errors, "user_id", self.user_id);
+        validate_required(errors, "reason", &self.reason);
+        if self.ttl_minutes.is_some_and(|ttl_minutes| !(1..=MAX_IMPERSONATION_MINUTES).contains(&ttl_minutes)) {
+            field_error(errors, "ttl_minutes", format!("ttl_minutes must be between 1 and {}", MAX_IMPERSONATION_MINUTES));
+        }
+    }
+}
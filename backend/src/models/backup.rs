@@ -0,0 +1,41 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// Layout of the backup archive; bumped when an entry changes incompatibly
+pub const BACKUP_FORMAT_VERSION: u32 = 1;
+/// Largest archive accepted by the restore
+pub const MAX_RESTORE_BYTES: usize = 1024 * 1024 * 1024;
+/// Largest table or database file inflated from it
+pub const MAX_RESTORE_ENTRY_BYTES: u64 = 4 * 1024 * 1024 * 1024;
+
+pub const BACKUP_MANIFEST_ENTRY: &str = "manifest.json";
+
+/// How the tables are stored in the archive
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum BackupFormat {
+    /// Postgres: `<schema>/<table>.jsonl`, one JSON object per row
+    Postgres,
+    /// SQLite: `<schema>.sqlite`, a copy of each database file
+    Sqlite,
+}
+
+/// `manifest.json`: what the archive holds and the schema it was taken from
+#[derive(Debug, Serialize, Deserialize)]
+pub struct BackupManifest {
+    pub format_version: u32,
+    pub format: BackupFormat,
+    pub created_at: DateTime<Utc>,
+    /// Migrations the schema had; a backup only restores into the same schema
+    pub migrations: Vec<String>,
+    /// Rows per `<schema>.<table>`
+    pub counts: BTreeMap<String, u64>,
+}
+
+/// Rows per `<schema>.<table>` after a restore
+#[derive(Debug, Serialize)]
+pub struct RestoreSummary {
+    pub created_at: DateTime<Utc>,
+    pub restored: BTreeMap<String, u64>,
+}
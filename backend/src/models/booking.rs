@@ -0,0 +1,345 @@
+use chrono::{DateTime, NaiveTime, Utc};
+use chrono_tz::Tz;
+use sea_orm::{ActiveModelBehavior, Set};
+use serde::{Deserialize, Serialize};
+use streamline_models::DEFAULT_SCHEMA_VERSION;
+use uuid::Uuid;
+
+use super::{
+    double_option, validate_schema_version,
+    validation::{collect, Validate},
+};
+use crate::{
+    entities::{booking_pages, bookings},
+    errors::{AppError, FieldError, Result},
+};
+
+/// Longest slot and buffer a booking page can ask for
+pub const MAX_BOOKING_MINUTES: i32 = 24 * 60;
+/// Furthest a booking page can offer slots into the future
+pub const MAX_BOOKING_DAYS_AHEAD: i32 = 365;
+
+/// A recurring weekly window in the booking page's time zone
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AvailabilityWindow {
+    /// ISO weekday: 1 is Monday, 7 is Sunday
+    pub weekday: u32,
+    pub start: NaiveTime,
+    pub end: NaiveTime,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CreateBookingPageRequest {
+    pub calendar_id: Option<Uuid>,
+    pub encrypted_data: String,
+    pub iv: String,
+    pub salt: String,
+    /// 1 when omitted
+    pub schema_version: Option<i16>,
+    /// Defaults to `UTC`
+    pub time_zone: Option<String>,
+    pub availability: Vec<AvailabilityWindow>,
+    pub slot_minutes: i32,
+    pub buffer_before_minutes: Option<i32>,
+    pub buffer_after_minutes: Option<i32>,
+    pub min_notice_minutes: Option<i32>,
+    pub max_days_ahead: Option<i32>,
+    pub is_active: Option<bool>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct UpdateBookingPageRequest {
+    #[serde(default, deserialize_with = "double_option")]
+    pub calendar_id: Option<Option<Uuid>>,
+    pub encrypted_data: Option<String>,
+    pub iv: Option<String>,
+    pub salt: Option<String>,
+    /// A new `encrypted_data` without it is taken to be version 1
+    pub schema_version: Option<i16>,
+    pub time_zone: Option<String>,
+    pub availability: Option<Vec<AvailabilityWindow>>,
+    pub slot_minutes: Option<i32>,
+    pub buffer_before_minutes: Option<i32>,
+    pub buffer_after_minutes: Option<i32>,
+    pub min_notice_minutes: Option<i32>,
+    pub max_days_ahead: Option<i32>,
+    pub is_active: Option<bool>,
+}
+
+/// Book a slot through a booking link; the event payload is encrypted with the key from the link
+#[derive(Debug, Deserialize)]
+pub struct CreateBookingRequest {
+    pub start_time: DateTime<Utc>,
+    pub encrypted_data: String,
+    pub iv: String,
+    pub salt: String,
+    /// 1 when omitted
+    pub schema_version: Option<i16>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct BookingPageResponse {
+    pub id: Uuid,
+    pub user_id: Uuid,
+    pub calendar_id: Option<Uuid>,
+    pub encrypted_data: String,
+    pub iv: String,
+    pub salt: String,
+    pub schema_version: i16,
+    pub public_token: String,
+    pub time_zone: String,
+    pub availability: Vec<AvailabilityWindow>,
+    pub slot_minutes: i32,
+    pub buffer_before_minutes: i32,
+    pub buffer_after_minutes: i32,
+    pub min_notice_minutes: i32,
+    pub max_days_ahead: i32,
+    pub is_active: bool,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+/// A booking page as visitors see it through the booking link
+#[derive(Debug, Serialize)]
+pub struct PublicBookingPageResponse {
+    pub id: Uuid,
+    pub encrypted_data: String,
+    pub iv: String,
+    pub salt: String,
+    pub schema_version: i16,
+    pub time_zone: String,
+    pub slot_minutes: i32,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct BookingSlot {
+    pub start_time: DateTime<Utc>,
+    pub end_time: DateTime<Utc>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct BookingResponse {
+    pub id: Uuid,
+    pub booking_page_id: Uuid,
+    pub user_id: Uuid,
+    pub event_id: Option<Uuid>,
+    pub start_time: DateTime<Utc>,
+    pub end_time: DateTime<Utc>,
+    pub created_at: DateTime<Utc>,
+}
+
+/// Confirmation for the visitor; leaves out the owner's ids
+#[derive(Debug, Serialize)]
+pub struct PublicBookingResponse {
+    pub id: Uuid,
+    pub start_time: DateTime<Utc>,
+    pub end_time: DateTime<Utc>,
+}
+
+pub fn parse_time_zone(time_zone: &str) -> Result<Tz> {
+    time_zone
+        .parse()
+        .map_err(|_| AppError::Validation(format!("Unknown time zone {}", time_zone)))
+}
+
+/// Weekdays in range and every window ending after it starts
+pub fn validate_windows(field: &str, windows: &[AvailabilityWindow]) -> Result<()> {
+    for window in windows {
+        if !(1..=7).contains(&window.weekday) {
+            return Err(AppError::invalid_field(field, "weekday must be between 1 (Monday) and 7 (Sunday)"));
+        }
+        if window.end <= window.start {
+            return Err(AppError::invalid_field(field, "Each window must end after it starts"));
+        }
+    }
+    Ok(())
+}
+
+fn validate_minutes(field: &str, value: i32, min: i32, max: i32) -> Result<()> {
+    if !(min..=max).contains(&value) {
+        return Err(AppError::invalid_field(field, format!("{} must be between {} and {}", field, min, max)));
+    }
+    Ok(())
+}
+
+/// Check the rules a page ends up with, after defaults or an update are applied
+pub fn validate_booking_page(page: &booking_pages::ActiveModel) -> Result<()> {
+    let value = |field: &sea_orm::ActiveValue<i32>| *field.as_ref();
+    validate_minutes("slot_minutes", value(&page.slot_minutes), 5, MAX_BOOKING_MINUTES)?;
+    validate_minutes("buffer_before_minutes", value(&page.buffer_before_minutes), 0, MAX_BOOKING_MINUTES)?;
+    validate_minutes("buffer_after_minutes", value(&page.buffer_after_minutes), 0, MAX_BOOKING_MINUTES)?;
+    validate_minutes("min_notice_minutes", value(&page.min_notice_minutes), 0, MAX_BOOKING_DAYS_AHEAD * 24 * 60)?;
+    validate_minutes("max_days_ahead", value(&page.max_days_ahead), 1, MAX_BOOKING_DAYS_AHEAD)?;
+    parse_time_zone(page.time_zone.as_ref())?;
+    Ok(())
+}
+
+impl CreateBookingPageRequest {
+    pub fn into_active_model(self, user_id: Uuid, public_token: String) -> Result<booking_pages::ActiveModel> {
+        validate_windows("availability", &self.availability)?;
+        validate_schema_version("booking_pages", self.schema_version)?;
+        let mut page = booking_pages::ActiveModel::new();
+        page.user_id = Set(user_id);
+        page.calendar_id = Set(self.calendar_id);
+        page.encrypted_data = Set(self.encrypted_data);
+        page.iv = Set(self.iv);
+        page.salt = Set(self.salt);
+        page.schema_version = Set(self.schema_version.unwrap_or(DEFAULT_SCHEMA_VERSION));
+        page.public_token = Set(public_token);
+        if let Some(time_zone) = self.time_zone {
+            page.time_zone = Set(time_zone);
+        }
+        page.availability = Set(serde_json::json!(self.availability));
+        page.slot_minutes = Set(self.slot_minutes);
+        if let Some(minutes) = self.buffer_before_minutes {
+            page.buffer_before_minutes = Set(minutes);
+        }
+        if let Some(minutes) = self.buffer_after_minutes {
+            page.buffer_after_minutes = Set(minutes);
+        }
+        if let Some(minutes) = self.min_notice_minutes {
+            page.min_notice_minutes = Set(minutes);
+        }
+        if let Some(days) = self.max_days_ahead {
+            page.max_days_ahead = Set(days);
+        }
+        if let Some(is_active) = self.is_active {
+            page.is_active = Set(is_active);
+        }
+        validate_booking_page(&page)?;
+        Ok(page)
+    }
+}
+
+impl UpdateBookingPageRequest {
+    pub fn apply_to(self, page: &mut booking_pages::ActiveModel) -> Result<()> {
+        validate_schema_version("booking_pages", self.schema_version)?;
+        let schema_version = self.schema_version.or(self.encrypted_data.is_some().then_some(DEFAULT_SCHEMA_VERSION));
+        if let Some(calendar_id) = self.calendar_id {
+            page.calendar_id = Set(calendar_id);
+        }
+        if let Some(encrypted_data) = self.encrypted_data {
+            page.encrypted_data = Set(encrypted_data);
+        }
+        if let Some(iv) = self.iv {
+            page.iv = Set(iv);
+        }
+        if let Some(salt) = self.salt {
+            page.salt = Set(salt);
+        }
+        if let Some(schema_version) = schema_version {
+            page.schema_version = Set(schema_version);
+        }
+        if let Some(time_zone) = self.time_zone {
+            page.time_zone = Set(time_zone);
+        }
+        if let Some(availability) = self.availability {
+            validate_windows("availability", &availability)?;
+            page.availability = Set(serde_json::json!(availability));
+        }
+        if let Some(minutes) = self.slot_minutes {
+            page.slot_minutes = Set(minutes);
+        }
+        if let Some(minutes) = self.buffer_before_minutes {
+            page.buffer_before_minutes = Set(minutes);
+        }
+        if let Some(minutes) = self.buffer_after_minutes {
+            page.buffer_after_minutes = Set(minutes);
+        }
+        if let Some(minutes) = self.min_notice_minutes {
+            page.min_notice_minutes = Set(minutes);
+        }
+        if let Some(days) = self.max_days_ahead {
+            page.max_days_ahead = Set(days);
+        }
+        if let Some(is_active) = self.is_active {
+            page.is_active = Set(is_active);
+        }
+        validate_booking_page(page)
+    }
+}
+
+impl From<booking_pages::Model> for BookingPageResponse {
+    fn from(page: booking_pages::Model) -> Self {
+        Self {
+            id: page.id,
+            user_id: page.user_id,
+            calendar_id: page.calendar_id,
+            encrypted_data: page.encrypted_data,
+            iv: page.iv,
+            salt: page.salt,
+            schema_version: page.schema_version,
+            public_token: page.public_token,
+            time_zone: page.time_zone,
+            availability: serde_json::from_value(page.availability).unwrap_or_default(),
+            slot_minutes: page.slot_minutes,
+            buffer_before_minutes: page.buffer_before_minutes,
+            buffer_after_minutes: page.buffer_after_minutes,
+            min_notice_minutes: page.min_notice_minutes,
+            max_days_ahead: page.max_days_ahead,
+            is_active: page.is_active,
+            created_at: page.created_at.naive_utc().and_utc(),
+            updated_at: page.updated_at.naive_utc().and_utc(),
+        }
+    }
+}
+
+impl From<booking_pages::Model> for PublicBookingPageResponse {
+    fn from(page: booking_pages::Model) -> Self {
+        Self {
+            id: page.id,
+            encrypted_data: page.encrypted_data,
+            iv: page.iv,
+            salt: page.salt,
+            schema_version: page.schema_version,
+            time_zone: page.time_zone,
+            slot_minutes: page.slot_minutes,
+        }
+    }
+}
+
+impl From<bookings::Model> for BookingResponse {
+    fn from(booking: bookings::Model) -> Self {
+        Self {
+            id: booking.id,
+            booking_page_id: booking.booking_page_id,
+            user_id: booking.user_id,
+            event_id: booking.event_id,
+            start_time: booking.start_time.naive_utc().and_utc(),
+            end_time: booking.end_time.naive_utc().and_utc(),
+            created_at: booking.created_at.naive_utc().and_utc(),
+        }
+    }
+}
+
+impl From<bookings::Model> for PublicBookingResponse {
+    fn from(booking: bookings::Model) -> Self {
+        Self {
+            id: booking.id,
+            start_time: booking.start_time.naive_utc().and_utc(),
+            end_time: booking.end_time.naive_utc().and_utc(),
+        }
+    }
+}
+
+impl Validate for CreateBookingPageRequest {
+    fn validate(&self, errors: &mut Vec<FieldError>) {
+        if let Some(time_zone) = &self.time_zone {
+            collect(errors, "time_zone", parse_time_zone(time_zone).map(drop));
+        }
+        collect(errors, "availability", validate_windows("availability", &self.availability));
+    }
+}
+
+impl Validate for UpdateBookingPageRequest {
+    fn validate(&self, errors: &mut Vec<FieldError>) {
+        if let Some(time_zone) = &self.time_zone {
+            collect(errors, "time_zone", parse_time_zone(time_zone).map(drop));
+        }
+        if let Some(availability) = &self.availability {
+            collect(errors, "availability", validate_windows("availability", availability));
+        }
+    }
+}
+
+impl Validate for CreateBookingRequest {}
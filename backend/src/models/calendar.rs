@@ -1,33 +1,67 @@
-use chrono::{DateTime, Utc};
-use serde::{Deserialize, Serialize};
 use uuid::Uuid;
+use sea_orm::{ActiveModelBehavior, Set};
+use streamline_models::DEFAULT_SCHEMA_VERSION;
+use super::{
+    validate_client_id,
+    validation::{collect, field_error, validate_display_order, Validate},
+    ApplyTo, IntoRecord,
+};
+use crate::errors::FieldError;
 use crate::entities::calendars;
 
-#[derive(Debug, Deserialize)]
-pub struct CreateCalendarRequest {
-    pub encrypted_data: String,
-    pub iv: String,
-    pub salt: String,
-}
+pub use streamline_models::calendar::{CalendarResponse, CreateCalendarRequest, UpdateCalendarRequest};
 
-#[derive(Debug, Deserialize)]
-pub struct UpdateCalendarRequest {
-    pub encrypted_data: Option<String>,
-    pub iv: Option<String>,
-    pub salt: Option<String>,
-    pub is_default: Option<bool>,
+impl IntoRecord<calendars::ActiveModel> for CreateCalendarRequest {
+    fn into_active_model(self, user_id: Uuid) -> calendars::ActiveModel {
+        let mut calendar_active = calendars::ActiveModel::new();
+        if let Some(id) = self.id {
+            calendar_active.id = Set(id);
+        }
+        calendar_active.user_id = Set(user_id);
+        calendar_active.encrypted_data = Set(self.encrypted_data);
+        calendar_active.iv = Set(self.iv);
+        calendar_active.salt = Set(self.salt);
+        calendar_active.schema_version = Set(self.schema_version.unwrap_or(DEFAULT_SCHEMA_VERSION));
+        calendar_active.is_pinned = Set(self.is_pinned.unwrap_or(false));
+        calendar_active.color = Set(self.color.map(|color| color.to_ascii_lowercase()));
+        calendar_active.display_order = Set(self.display_order.unwrap_or(0));
+        calendar_active.organization_id = Set(self.organization_id);
+        calendar_active
+    }
 }
 
-#[derive(Debug, Serialize)]
-pub struct CalendarResponse {
-    pub id: Uuid,
-    pub user_id: Uuid,
-    pub encrypted_data: String,
-    pub iv: String,
-    pub salt: String,
-    pub is_default: bool,
-    pub created_at: DateTime<Utc>,
-    pub updated_at: DateTime<Utc>,
+impl ApplyTo<calendars::ActiveModel> for UpdateCalendarRequest {
+    fn apply_to(self, calendar_active: &mut calendars::ActiveModel) {
+        // A new payload without a version comes from a client predating versioning
+        let schema_version = self.schema_version.or(self.encrypted_data.is_some().then_some(DEFAULT_SCHEMA_VERSION));
+        if let Some(encrypted_data) = self.encrypted_data {
+            calendar_active.encrypted_data = Set(encrypted_data);
+        }
+        if let Some(iv) = self.iv {
+            calendar_active.iv = Set(iv);
+        }
+        if let Some(salt) = self.salt {
+            calendar_active.salt = Set(salt);
+        }
+        if let Some(schema_version) = schema_version {
+            calendar_active.schema_version = Set(schema_version);
+        }
+        if let Some(is_default) = self.is_default {
+            calendar_active.is_default = Set(is_default);
+        }
+        if let Some(is_pinned) = self.is_pinned {
+            calendar_active.is_pinned = Set(is_pinned);
+        }
+        if let Some(color) = self.color {
+            calendar_active.color = Set(color.map(|color| color.to_ascii_lowercase()));
+        }
+        if let Some(display_order) = self.display_order {
+            calendar_active.display_order = Set(display_order);
+        }
+        if let Some(organization_id) = self.organization_id {
+            calendar_active.organization_id = Set(organization_id);
+        }
+    }
 }
 
 impl From<calendars::Model> for CalendarResponse {
@@ -38,9 +72,44 @@ impl From<calendars::Model> for CalendarResponse {
             encrypted_data: calendar.encrypted_data,
             iv: calendar.iv,
             salt: calendar.salt,
+            schema_version: calendar.schema_version,
             is_default: calendar.is_default,
+            is_pinned: calendar.is_pinned,
+            is_read_only: calendar.is_read_only,
+            color: calendar.color,
+            display_order: calendar.display_order,
+            organization_id: calendar.organization_id,
             created_at: calendar.created_at.naive_utc().and_utc(),
             updated_at: calendar.updated_at.naive_utc().and_utc(),
+            deleted_at: calendar.deleted_at.map(|dt| dt.naive_utc().and_utc()),
         }
     }
 }
+
+/// Colors are `#rrggbb` hex, so every client can render them
+fn validate_color(errors: &mut Vec<FieldError>, color: Option<&str>) {
+    let Some(color) = color else {
+        return;
+    };
+    let is_hex = color.len() == 7
+        && color.starts_with('#')
+        && color[1..].chars().all(|c| c.is_ascii_hexdigit());
+    if !is_hex {
+        field_error(errors, "color", "color must be a hex color like #3b82f6");
+    }
+}
+
+impl Validate for CreateCalendarRequest {
+    fn validate(&self, errors: &mut Vec<FieldError>) {
+        collect(errors, "id", validate_client_id(self.id));
+        validate_color(errors, self.color.as_deref());
+        validate_display_order(errors, self.display_order);
+    }
+}
+
+impl Validate for UpdateCalendarRequest {
+    fn validate(&self, errors: &mut Vec<FieldError>) {
+        validate_color(errors, self.color.as_ref().and_then(Option::as_deref));
+        validate_display_order(errors, self.display_order);
+    }
+}
@@ -1,31 +1,167 @@
-use chrono::{DateTime, Utc};
-use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+
+use chrono::{DateTime, NaiveDate, NaiveTime, Utc};
 use uuid::Uuid;
-use crate::entities::calendar_events;
+use sea_orm::{ActiveModelBehavior, Set};
+use serde::Deserialize;
+use streamline_models::DEFAULT_SCHEMA_VERSION;
+use super::{
+    search::{join_search_tokens, validate_search_tokens},
+    user_settings::TimePreferences,
+    validate_client_id,
+    validate_schema_version,
+    validation::{collect, field_error, validate_reference, Validate},
+    ApplyTo, IntoRecord,
+};
+use crate::entities::calendar_events::{self, EventStatus};
+use crate::errors::{AppError, FieldError, Result};
+use crate::recurrence;
+
+pub use streamline_models::calendar_event::{CalendarEventResponse, CreateCalendarEventRequest, UpdateCalendarEventRequest};
+
+impl IntoRecord<calendar_events::ActiveModel> for CreateCalendarEventRequest {
+    fn into_active_model(self, user_id: Uuid) -> calendar_events::ActiveModel {
+        let mut event_active = calendar_events::ActiveModel::new();
+        if let Some(id) = self.id {
+            event_active.id = Set(id);
+        }
+        event_active.user_id = Set(user_id);
+        event_active.calendar_id = Set(self.calendar_id);
+        event_active.encrypted_data = Set(self.encrypted_data);
+        event_active.iv = Set(self.iv);
+        event_active.salt = Set(self.salt);
+        event_active.schema_version = Set(self.schema_version.unwrap_or(DEFAULT_SCHEMA_VERSION));
+        event_active.start_time = Set(self.start_time.map(Into::into));
+        event_active.end_time = Set(self.end_time.map(Into::into));
+        event_active.is_all_day = Set(self.is_all_day);
+        event_active.start_date = Set(self.start_date);
+        event_active.end_date = Set(self.end_date);
+        event_active.search_bloom = Set(self.search_bloom);
+        event_active.search_tokens = Set(self.search_tokens.map(join_search_tokens));
+        event_active.rrule = Set(self.rrule);
+        if let Some(exceptions) = self.recurrence_exceptions {
+            event_active.recurrence_exceptions = Set(serde_json::json!(exceptions));
+        }
+        if let Some(status) = self.status {
+            event_active.status = Set(status);
+        }
+        event_active
+    }
+}
+
+impl ApplyTo<calendar_events::ActiveModel> for UpdateCalendarEventRequest {
+    fn apply_to(self, event_active: &mut calendar_events::ActiveModel) {
+        // A new payload without a version comes from a client predating versioning
+        let schema_version = self.schema_version.or(self.encrypted_data.is_some().then_some(DEFAULT_SCHEMA_VERSION));
+        if let Some(calendar_id) = self.calendar_id {
+            event_active.calendar_id = Set(calendar_id);
+        }
+        if let Some(encrypted_data) = self.encrypted_data {
+            event_active.encrypted_data = Set(encrypted_data);
+        }
+        if let Some(iv) = self.iv {
+            event_active.iv = Set(iv);
+        }
+        if let Some(salt) = self.salt {
+            event_active.salt = Set(salt);
+        }
+        if let Some(schema_version) = schema_version {
+            event_active.schema_version = Set(schema_version);
+        }
+        if let Some(start_time) = self.start_time {
+            event_active.start_time = Set(start_time.map(Into::into));
+        }
+        if let Some(end_time) = self.end_time {
+            event_active.end_time = Set(end_time.map(Into::into));
+        }
+        if let Some(is_all_day) = self.is_all_day {
+            event_active.is_all_day = Set(is_all_day);
+        }
+        if let Some(start_date) = self.start_date {
+            event_active.start_date = Set(start_date);
+        }
+        if let Some(end_date) = self.end_date {
+            event_active.end_date = Set(end_date);
+        }
+        if let Some(search_bloom) = self.search_bloom {
+            event_active.search_bloom = Set(search_bloom);
+        }
+        if let Some(search_tokens) = self.search_tokens {
+            event_active.search_tokens = Set(search_tokens.map(join_search_tokens));
+        }
+        if let Some(rrule) = self.rrule {
+            event_active.rrule = Set(rrule);
+        }
+        if let Some(exceptions) = self.recurrence_exceptions {
+            event_active.recurrence_exceptions = Set(serde_json::json!(exceptions));
+        }
+        if let Some(status) = self.status {
+            event_active.status = Set(status);
+        }
+    }
+}
 
+/// Block out an event's time for a task
 #[derive(Debug, Deserialize)]
-pub struct CreateCalendarEventRequest {
-    pub encrypted_data: String,
-    pub iv: String,
-    pub salt: String,
+pub struct LinkTaskRequest {
+    pub task_id: Uuid,
 }
 
+/// Move or resize an event without resending its encrypted payload; omitted bounds stay as they are.
+/// All-day events move by date, timed events by time.
 #[derive(Debug, Deserialize)]
-pub struct UpdateCalendarEventRequest {
-    pub encrypted_data: Option<String>,
-    pub iv: Option<String>,
-    pub salt: Option<String>,
+pub struct UpdateEventTimeRequest {
+    pub start_time: Option<DateTime<Utc>>,
+    pub end_time: Option<DateTime<Utc>>,
+    pub start_date: Option<NaiveDate>,
+    pub end_date: Option<NaiveDate>,
 }
 
-#[derive(Debug, Serialize)]
-pub struct CalendarEventResponse {
-    pub id: Uuid,
-    pub user_id: Uuid,
-    pub encrypted_data: String,
-    pub iv: String,
-    pub salt: String,
-    pub created_at: DateTime<Utc>,
-    pub updated_at: DateTime<Utc>,
+/// The span an all-day event covers in its owner's timezone; `end_date` is inclusive
+pub fn all_day_bounds(
+    preferences: &TimePreferences,
+    start_date: NaiveDate,
+    end_date: NaiveDate,
+) -> (DateTime<Utc>, DateTime<Utc>) {
+    let day_after = end_date.succ_opt().unwrap_or(end_date);
+    (preferences.start_of_day(start_date), preferences.start_of_day(day_after))
+}
+
+/// The days from `start` to `end` in the owner's timezone, the reverse of [`all_day_bounds`]
+pub fn all_day_dates(
+    preferences: &TimePreferences,
+    start: DateTime<Utc>,
+    end: Option<DateTime<Utc>>,
+) -> (NaiveDate, NaiveDate) {
+    let start_date = start.with_timezone(&preferences.timezone).date_naive();
+    let end_date = end
+        .map(|end| end.with_timezone(&preferences.timezone).date_naive())
+        .filter(|day_after| *day_after > start_date)
+        .and_then(|day_after| day_after.pred_opt())
+        .unwrap_or(start_date);
+    (start_date, end_date)
+}
+
+/// Dates belong to all-day events only, which need at least a first day
+pub fn validate_all_day(
+    errors: &mut Vec<FieldError>,
+    is_all_day: bool,
+    start_date: Option<NaiveDate>,
+    end_date: Option<NaiveDate>,
+) {
+    if !is_all_day {
+        if start_date.is_some() || end_date.is_some() {
+            field_error(errors, "is_all_day", "start_date and end_date are only set on all-day events");
+        }
+        return;
+    }
+    match (start_date, end_date) {
+        (None, _) => field_error(errors, "start_date", "All-day events need a start_date"),
+        (Some(start_date), Some(end_date)) if end_date < start_date => {
+            field_error(errors, "end_date", "end_date must not be before start_date")
+        }
+        _ => {}
+    }
 }
 
 impl From<calendar_events::Model> for CalendarEventResponse {
@@ -33,11 +169,123 @@ impl From<calendar_events::Model> for CalendarEventResponse {
         Self {
             id: event.id,
             user_id: event.user_id,
+            calendar_id: event.calendar_id,
             encrypted_data: event.encrypted_data,
             iv: event.iv,
             salt: event.salt,
+            schema_version: event.schema_version,
+            start_time: event.start_time.map(|dt| dt.naive_utc().and_utc()),
+            end_time: event.end_time.map(|dt| dt.naive_utc().and_utc()),
+            is_all_day: event.is_all_day,
+            start_date: event.start_date,
+            end_date: event.end_date,
+            rrule: event.rrule,
+            recurrence_exceptions: serde_json::from_value(event.recurrence_exceptions).unwrap_or_default(),
+            recurring_event_id: event.recurring_event_id,
+            original_start_time: event.original_start_time.map(|dt| dt.naive_utc().and_utc()),
+            status: event.status,
+            subscription_id: event.subscription_id,
+            external_uid: event.external_uid,
+            external_data: event.external_data,
+            linked_task_id: event.linked_task_id,
+            attendees: None,
             created_at: event.created_at.naive_utc().and_utc(),
             updated_at: event.updated_at.naive_utc().and_utc(),
+            deleted_at: event.deleted_at.map(|dt| dt.naive_utc().and_utc()),
         }
     }
 }
+
+/// Most events a batch may create
+pub const MAX_BATCH_EVENTS: usize = 500;
+
+/// Events created together, e.g. from an imported `.ics` file or a series a client expanded
+#[derive(Debug, Deserialize)]
+pub struct BatchCreateCalendarEventsRequest {
+    pub events: Vec<CreateCalendarEventRequest>,
+}
+
+/// Parse a `?status=confirmed,tentative` filter
+pub fn parse_status_filter(status: Option<&str>) -> Result<Option<Vec<EventStatus>>> {
+    let Some(status) = status else {
+        return Ok(None);
+    };
+    status
+        .split(',')
+        .map(|value| match value.trim() {
+            "confirmed" => Ok(EventStatus::Confirmed),
+            "tentative" => Ok(EventStatus::Tentative),
+            "cancelled" => Ok(EventStatus::Cancelled),
+            other => Err(AppError::Validation(format!("Unknown event status '{}'", other))),
+        })
+        .collect::<Result<Vec<_>>>()
+        .map(Some)
+}
+
+impl Validate for CreateCalendarEventRequest {
+    fn validate(&self, errors: &mut Vec<FieldError>) {
+        collect(errors, "id", validate_client_id(self.id));
+        collect(errors, "search_tokens", validate_search_tokens(self.search_tokens.as_deref()));
+        validate_all_day(errors, self.is_all_day, self.start_date, self.end_date);
+    }
+}
+
+impl Validate for BatchCreateCalendarEventsRequest {
+    fn validate(&self, errors: &mut Vec<FieldError>) {
+        if self.events.is_empty() {
+            field_error(errors, "events", "At least one event is required");
+        } else if self.events.len() > MAX_BATCH_EVENTS {
+            field_error(errors, "events", format!("At most {} events can be created at once", MAX_BATCH_EVENTS));
+            return;
+        }
+
+        let mut ids = HashSet::with_capacity(self.events.len());
+        for (index, event) in self.events.iter().enumerate() {
+            let field = |name: &str| format!("events[{}].{}", index, name);
+            let mut event_errors = Vec::new();
+            event.validate(&mut event_errors);
+            errors.extend(event_errors.into_iter().map(|error| FieldError { field: field(&error.field), ..error }));
+            if let Some(id) = event.id
+                && !ids.insert(id)
+            {
+                field_error(errors, &field("id"), format!("Id {} is used twice", id));
+            }
+            // All-day events get their times from their dates once the owner's timezone is known
+            let start_time = match event.is_all_day {
+                true => event.start_date.map(|date| date.and_time(NaiveTime::MIN).and_utc()),
+                false => event.start_time,
+            };
+            if let (false, Some(start_time), Some(end_time)) = (event.is_all_day, start_time, event.end_time)
+                && end_time < start_time
+            {
+                field_error(errors, &field("end_time"), "end_time must not be before start_time");
+            }
+            collect(errors, &field("rrule"), recurrence::validate(event.rrule.as_deref(), start_time));
+            collect(errors, &field("schema_version"), validate_schema_version("calendar_events", event.schema_version));
+        }
+    }
+}
+
+impl Validate for UpdateCalendarEventRequest {
+    fn validate(&self, errors: &mut Vec<FieldError>) {
+        collect(errors, "search_tokens", validate_search_tokens(self.search_tokens.as_ref().and_then(Option::as_deref)));
+    }
+}
+
+impl Validate for UpdateEventTimeRequest {
+    fn validate(&self, errors: &mut Vec<FieldError>) {
+        let timed = self.start_time.is_some() || self.end_time.is_some();
+        let dated = self.start_date.is_some() || self.end_date.is_some();
+        if !timed && !dated {
+            field_error(errors, "start_time", "start_time, end_time, start_date or end_date is required");
+        } else if timed && dated {
+            field_error(errors, "start_date", "Move an event either by time or by date, not both");
+        }
+    }
+}
+
+impl Validate for LinkTaskRequest {
+    fn validate(&self, errors: &mut Vec<FieldError>) {
+        validate_reference(errors, "task_id", self.task_id);
+    }
+}
@@ -0,0 +1,143 @@
+use chrono::{DateTime, Utc};
+use sea_orm::{ActiveModelBehavior, Set};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::{
+    entities::calendar_subscriptions,
+    errors::{AppError, FieldError, Result},
+    models::validation::{collect, Validate},
+    outbound,
+};
+
+/// Shortest and longest time between two refreshes of a feed
+pub const MIN_REFRESH_INTERVAL_MINUTES: i32 = 15;
+pub const MAX_REFRESH_INTERVAL_MINUTES: i32 = 7 * 24 * 60;
+
+/// Subscribe to an ICS feed; the encrypted fields name the read-only calendar it fills
+#[derive(Debug, Deserialize)]
+pub struct CreateCalendarSubscriptionRequest {
+    pub url: String,
+    pub refresh_interval_minutes: Option<i32>,
+    pub encrypted_data: String,
+    pub iv: String,
+    pub salt: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct UpdateCalendarSubscriptionRequest {
+    pub url: Option<String>,
+    pub refresh_interval_minutes: Option<i32>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct CalendarSubscriptionResponse {
+    pub id: Uuid,
+    pub user_id: Uuid,
+    pub calendar_id: Uuid,
+    pub url: String,
+    pub refresh_interval_minutes: i32,
+    pub last_fetched_at: Option<DateTime<Utc>>,
+    pub last_error: Option<String>,
+    pub next_fetch_at: DateTime<Utc>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+/// Check a feed URL; `webcal://` links are fetched over HTTPS
+pub fn normalize_feed_url(url: &str) -> Result<String> {
+    let url = url.trim();
+    let url = match url.split_once("://") {
+        Some((scheme, rest)) if scheme.eq_ignore_ascii_case("webcal") || scheme.eq_ignore_ascii_case("webcals") => {
+            format!("https://{}", rest)
+        }
+        _ => url.to_string(),
+    };
+    let parsed = reqwest::Url::parse(&url)
+        .map_err(|_| AppError::invalid_field("url", "url must be a valid http(s) or webcal URL"))?;
+    if !matches!(parsed.scheme(), "http" | "https") || parsed.host_str().is_none() {
+        return Err(AppError::invalid_field("url", "url must be a valid http(s) or webcal URL"));
+    }
+    outbound::check_url(&parsed).map_err(|e| AppError::invalid_field("url", format!("url must be public: {}", e)))?;
+    Ok(parsed.to_string())
+}
+
+fn validate_refresh_interval(minutes: i32) -> Result<()> {
+    if !(MIN_REFRESH_INTERVAL_MINUTES..=MAX_REFRESH_INTERVAL_MINUTES).contains(&minutes) {
+        return Err(AppError::invalid_field(
+            "refresh_interval_minutes",
+            format!(
+                "refresh_interval_minutes must be between {} and {}",
+                MIN_REFRESH_INTERVAL_MINUTES, MAX_REFRESH_INTERVAL_MINUTES
+            ),
+        ));
+    }
+    Ok(())
+}
+
+impl Validate for CreateCalendarSubscriptionRequest {
+    fn validate(&self, errors: &mut Vec<FieldError>) {
+        collect(errors, "url", normalize_feed_url(&self.url).map(drop));
+        if let Some(minutes) = self.refresh_interval_minutes {
+            collect(errors, "refresh_interval_minutes", validate_refresh_interval(minutes));
+        }
+    }
+}
+
+impl Validate for UpdateCalendarSubscriptionRequest {
+    fn validate(&self, errors: &mut Vec<FieldError>) {
+        if let Some(url) = &self.url {
+            collect(errors, "url", normalize_feed_url(url).map(drop));
+        }
+        if let Some(minutes) = self.refresh_interval_minutes {
+            collect(errors, "refresh_interval_minutes", validate_refresh_interval(minutes));
+        }
+    }
+}
+
+impl CreateCalendarSubscriptionRequest {
+    pub fn subscription_active_model(&self, user_id: Uuid, calendar_id: Uuid) -> Result<calendar_subscriptions::ActiveModel> {
+        let mut subscription = calendar_subscriptions::ActiveModel::new();
+        subscription.user_id = Set(user_id);
+        subscription.calendar_id = Set(calendar_id);
+        subscription.url = Set(normalize_feed_url(&self.url)?);
+        if let Some(minutes) = self.refresh_interval_minutes {
+            subscription.refresh_interval_minutes = Set(minutes);
+        }
+        Ok(subscription)
+    }
+}
+
+impl UpdateCalendarSubscriptionRequest {
+    pub fn apply_to(self, subscription: &mut calendar_subscriptions::ActiveModel) -> Result<()> {
+        if let Some(url) = self.url {
+            subscription.url = Set(normalize_feed_url(&url)?);
+            // A different feed has nothing in common with the validators of the old one
+            subscription.etag = Set(None);
+            subscription.last_modified = Set(None);
+            subscription.next_fetch_at = Set(Utc::now().into());
+        }
+        if let Some(minutes) = self.refresh_interval_minutes {
+            validate_refresh_interval(minutes)?;
+            subscription.refresh_interval_minutes = Set(minutes);
+        }
+        Ok(())
+    }
+}
+
+impl From<calendar_subscriptions::Model> for CalendarSubscriptionResponse {
+    fn from(subscription: calendar_subscriptions::Model) -> Self {
+        Self {
+            id: subscription.id,
+            user_id: subscription.user_id,
+            calendar_id: subscription.calendar_id,
+            url: subscription.url,
+            refresh_interval_minutes: subscription.refresh_interval_minutes,
+            last_fetched_at: subscription.last_fetched_at.map(|dt| dt.naive_utc().and_utc()),
+            last_error: subscription.last_error,
+            next_fetch_at: subscription.next_fetch_at.naive_utc().and_utc(),
+            created_at: subscription.created_at.naive_utc().and_utc(),
+            updated_at: subscription.updated_at.naive_utc().and_utc(),
+        }
+    }
+}
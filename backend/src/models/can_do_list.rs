@@ -1,37 +1,100 @@
-use chrono::{DateTime, Utc};
-use serde::{Deserialize, Serialize};
 use uuid::Uuid;
-use crate::entities::can_do_list;
+use sea_orm::{ActiveModelBehavior, Set};
+use serde::Serialize;
+use streamline_models::DEFAULT_SCHEMA_VERSION;
+use super::{
+    search::{join_search_tokens, validate_search_tokens},
+    validate_client_id,
+    validation::{collect, validate_display_order, Validate},
+    ApplyTo, IntoRecord,
+};
+use crate::entities::can_do_list::{self, TaskPriority};
+use crate::errors::{AppError, FieldError, Result};
 
-#[derive(Debug, Deserialize)]
-pub struct CreateCanDoItemRequest {
-    pub project_id: Option<Uuid>,
-    pub encrypted_data: String,
-    pub iv: String,
-    pub salt: String,
-    pub display_order: Option<i32>,
-}
+pub use streamline_models::can_do_list::{CanDoItemResponse, CreateCanDoItemRequest, UpdateCanDoItemRequest};
 
-#[derive(Debug, Deserialize)]
-pub struct UpdateCanDoItemRequest {
-    pub project_id: Option<Uuid>,
-    pub encrypted_data: Option<String>,
-    pub iv: Option<String>,
-    pub salt: Option<String>,
-    pub display_order: Option<i32>,
+impl IntoRecord<can_do_list::ActiveModel> for CreateCanDoItemRequest {
+    fn into_active_model(self, user_id: Uuid) -> can_do_list::ActiveModel {
+        let mut item_active = can_do_list::ActiveModel::new();
+        if let Some(id) = self.id {
+            item_active.id = Set(id);
+        }
+        item_active.user_id = Set(user_id);
+        item_active.project_id = Set(self.project_id);
+        item_active.encrypted_data = Set(self.encrypted_data);
+        item_active.iv = Set(self.iv);
+        item_active.salt = Set(self.salt);
+        item_active.schema_version = Set(self.schema_version.unwrap_or(DEFAULT_SCHEMA_VERSION));
+        item_active.display_order = Set(self.display_order.unwrap_or(0));
+        item_active.search_bloom = Set(self.search_bloom);
+        item_active.search_tokens = Set(self.search_tokens.map(join_search_tokens));
+        item_active.due_at = Set(self.due_at.map(Into::into));
+        item_active.scheduled_at = Set(self.scheduled_at.map(Into::into));
+        item_active.parent_task_id = Set(self.parent_task_id);
+        item_active.completed_at = Set(self.completed_at.map(Into::into));
+        item_active.rrule = Set(self.rrule);
+        item_active.priority = Set(self.priority);
+        item_active.estimated_minutes = Set(self.estimated_minutes);
+        item_active.is_pinned = Set(self.is_pinned.unwrap_or(false));
+        item_active
+    }
 }
 
-#[derive(Debug, Serialize)]
-pub struct CanDoItemResponse {
-    pub id: Uuid,
-    pub user_id: Uuid,
-    pub project_id: Option<Uuid>,
-    pub encrypted_data: String,
-    pub iv: String,
-    pub salt: String,
-    pub display_order: i32,
-    pub created_at: DateTime<Utc>,
-    pub updated_at: DateTime<Utc>,
+impl ApplyTo<can_do_list::ActiveModel> for UpdateCanDoItemRequest {
+    fn apply_to(self, item_active: &mut can_do_list::ActiveModel) {
+        // A new payload without a version comes from a client predating versioning
+        let schema_version = self.schema_version.or(self.encrypted_data.is_some().then_some(DEFAULT_SCHEMA_VERSION));
+        if let Some(project_id) = self.project_id {
+            item_active.project_id = Set(project_id);
+        }
+        if let Some(encrypted_data) = self.encrypted_data {
+            item_active.encrypted_data = Set(encrypted_data);
+            // Once encrypted, the plaintext of an emailed task is dropped
+            item_active.pending_plaintext = Set(None);
+        }
+        if let Some(iv) = self.iv {
+            item_active.iv = Set(iv);
+        }
+        if let Some(salt) = self.salt {
+            item_active.salt = Set(salt);
+        }
+        if let Some(schema_version) = schema_version {
+            item_active.schema_version = Set(schema_version);
+        }
+        if let Some(display_order) = self.display_order {
+            item_active.display_order = Set(display_order);
+        }
+        if let Some(search_bloom) = self.search_bloom {
+            item_active.search_bloom = Set(search_bloom);
+        }
+        if let Some(search_tokens) = self.search_tokens {
+            item_active.search_tokens = Set(search_tokens.map(join_search_tokens));
+        }
+        if let Some(due_at) = self.due_at {
+            item_active.due_at = Set(due_at.map(Into::into));
+        }
+        if let Some(scheduled_at) = self.scheduled_at {
+            item_active.scheduled_at = Set(scheduled_at.map(Into::into));
+        }
+        if let Some(parent_task_id) = self.parent_task_id {
+            item_active.parent_task_id = Set(parent_task_id);
+        }
+        if let Some(completed_at) = self.completed_at {
+            item_active.completed_at = Set(completed_at.map(Into::into));
+        }
+        if let Some(rrule) = self.rrule {
+            item_active.rrule = Set(rrule);
+        }
+        if let Some(priority) = self.priority {
+            item_active.priority = Set(priority);
+        }
+        if let Some(estimated_minutes) = self.estimated_minutes {
+            item_active.estimated_minutes = Set(estimated_minutes);
+        }
+        if let Some(is_pinned) = self.is_pinned {
+            item_active.is_pinned = Set(is_pinned);
+        }
+    }
 }
 
 impl From<can_do_list::Model> for CanDoItemResponse {
@@ -43,9 +106,71 @@ impl From<can_do_list::Model> for CanDoItemResponse {
             encrypted_data: item.encrypted_data,
             iv: item.iv,
             salt: item.salt,
+            schema_version: item.schema_version,
             display_order: item.display_order,
+            due_at: item.due_at.map(|dt| dt.naive_utc().and_utc()),
+            scheduled_at: item.scheduled_at.map(|dt| dt.naive_utc().and_utc()),
+            parent_task_id: item.parent_task_id,
+            completed_at: item.completed_at.map(|dt| dt.naive_utc().and_utc()),
+            rrule: item.rrule,
+            priority: item.priority,
+            estimated_minutes: item.estimated_minutes,
+            is_pinned: item.is_pinned,
+            pending_plaintext: item.pending_plaintext,
             created_at: item.created_at.naive_utc().and_utc(),
             updated_at: item.updated_at.naive_utc().and_utc(),
+            deleted_at: item.deleted_at.map(|dt| dt.naive_utc().and_utc()),
         }
     }
 }
+
+/// Events that block out time for a task
+#[derive(Debug, Serialize)]
+pub struct LinkedEventsResponse {
+    pub task_id: Uuid,
+    /// In order of their start time
+    pub linked_event_ids: Vec<Uuid>,
+}
+
+/// Parse a `?priority=high,urgent` filter
+pub fn parse_priority_filter(priority: Option<&str>) -> Result<Option<Vec<TaskPriority>>> {
+    let Some(priority) = priority else {
+        return Ok(None);
+    };
+    priority
+        .split(',')
+        .map(|value| match value.trim() {
+            "low" => Ok(TaskPriority::Low),
+            "medium" => Ok(TaskPriority::Medium),
+            "high" => Ok(TaskPriority::High),
+            "urgent" => Ok(TaskPriority::Urgent),
+            other => Err(AppError::Validation(format!("Unknown priority '{}'", other))),
+        })
+        .collect::<Result<Vec<_>>>()
+        .map(Some)
+}
+
+/// Estimates must be positive; checked before insert so clients get a validation error
+pub fn validate_estimated_minutes(estimated_minutes: Option<i32>) -> Result<()> {
+    match estimated_minutes {
+        Some(minutes) if minutes <= 0 => Err(AppError::invalid_field("estimated_minutes", "estimated_minutes must be positive")),
+        _ => Ok(()),
+    }
+}
+
+impl Validate for CreateCanDoItemRequest {
+    fn validate(&self, errors: &mut Vec<FieldError>) {
+        collect(errors, "id", validate_client_id(self.id));
+        validate_display_order(errors, self.display_order);
+        collect(errors, "estimated_minutes", validate_estimated_minutes(self.estimated_minutes));
+        collect(errors, "search_tokens", validate_search_tokens(self.search_tokens.as_deref()));
+    }
+}
+
+impl Validate for UpdateCanDoItemRequest {
+    fn validate(&self, errors: &mut Vec<FieldError>) {
+        validate_display_order(errors, self.display_order);
+        collect(errors, "estimated_minutes", validate_estimated_minutes(self.estimated_minutes.flatten()));
+        collect(errors, "search_tokens", validate_search_tokens(self.search_tokens.as_ref().and_then(Option::as_deref)));
+    }
+}
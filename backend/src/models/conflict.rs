@@ -0,0 +1,76 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::{
+    entities::conflicts::{self, ConflictAction, ConflictResolution},
+    errors::FieldError,
+    models::validation::{field_error, Validate},
+};
+
+/// `?resolved=true` lists settled conflicts instead of open ones
+#[derive(Debug, Deserialize)]
+pub struct ConflictQuery {
+    #[serde(default)]
+    pub resolved: bool,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ResolveConflictRequest {
+    pub resolution: ConflictResolution,
+    /// Update request body for the record, required for `merged`
+    pub data: Option<serde_json::Value>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ConflictResponse {
+    pub id: Uuid,
+    pub table: String,
+    pub record_id: Uuid,
+    pub action: ConflictAction,
+    pub base_updated_at: Option<DateTime<Utc>>,
+    /// What the client pushed
+    pub client_data: serde_json::Value,
+    /// The server's copy of the record when the push was rejected
+    pub server_data: serde_json::Value,
+    pub resolution: Option<ConflictResolution>,
+    pub resolved_at: Option<DateTime<Utc>>,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ResolveConflictResponse {
+    pub conflict: ConflictResponse,
+    /// The record as saved by the resolution; absent when the server version was kept or the
+    /// record was deleted
+    pub record: Option<serde_json::Value>,
+}
+
+impl From<conflicts::Model> for ConflictResponse {
+    fn from(conflict: conflicts::Model) -> Self {
+        Self {
+            id: conflict.id,
+            table: conflict.table_name,
+            record_id: conflict.record_id,
+            action: conflict.action,
+            base_updated_at: conflict.base_updated_at.map(|at| at.naive_utc().and_utc()),
+            client_data: conflict.client_data,
+            server_data: conflict.server_data,
+            resolution: conflict.resolution,
+            resolved_at: conflict.resolved_at.map(|at| at.naive_utc().and_utc()),
+            created_at: conflict.created_at.naive_utc().and_utc(),
+        }
+    }
+}
+
+impl Validate for ResolveConflictRequest {
+    fn validate(&self, errors: &mut Vec<FieldError>) {
+        match (self.resolution, &self.data) {
+            (ConflictResolution::Merged, None) => field_error(errors, "data", "data is required to save a merged version"),
+            (ConflictResolution::KeepMine | ConflictResolution::KeepTheirs, Some(_)) => {
+                field_error(errors, "data", "data is only accepted with the merged resolution")
+            }
+            _ => {}
+        }
+    }
+}
@@ -0,0 +1,110 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::{
+    entities::event_attendees::{self, RsvpStatus},
+    errors::FieldError,
+    models::validation::{field_error, validate_email, validate_reference, validate_required, Validate},
+};
+
+pub use streamline_models::event_attendee::AttendeeSummary;
+
+/// Longest summary or location an invitation takes
+const MAX_INVITE_TEXT_CHARS: usize = 4000;
+
+#[derive(Debug, Deserialize)]
+pub struct CreateAttendeeRequest {
+    pub event_id: Uuid,
+    pub encrypted_data: String,
+    pub iv: String,
+    pub salt: String,
+    /// 1 when omitted
+    pub schema_version: Option<i16>,
+    /// Defaults to `needs_action`
+    pub rsvp_status: Option<RsvpStatus>,
+    /// Email the attendee an invitation they can answer from their calendar app
+    pub invite: Option<InviteRequest>,
+}
+
+/// What an emailed invitation discloses; the event's times are added from the event. There is
+/// no free-text message, so the server can't be used to mail arbitrary text to any address.
+#[derive(Debug, Deserialize)]
+pub struct InviteRequest {
+    pub email: String,
+    pub name: Option<String>,
+    /// How the invitation names the organizer; their account's address when omitted
+    pub organizer_name: Option<String>,
+    pub summary: String,
+    pub location: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct RsvpRequest {
+    pub rsvp_status: RsvpStatus,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct AttendeeQuery {
+    pub event_id: Option<Uuid>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct AttendeeResponse {
+    pub id: Uuid,
+    pub user_id: Uuid,
+    pub event_id: Uuid,
+    pub encrypted_data: String,
+    pub iv: String,
+    pub salt: String,
+    pub schema_version: i16,
+    pub rsvp_status: RsvpStatus,
+    /// When the attendee last responded; `null` while the response is `needs_action`
+    pub responded_at: Option<DateTime<Utc>>,
+    /// Where an invitation was emailed to, if one was
+    pub email: Option<String>,
+    pub invited_at: Option<DateTime<Utc>>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+impl From<event_attendees::Model> for AttendeeResponse {
+    fn from(attendee: event_attendees::Model) -> Self {
+        Self {
+            id: attendee.id,
+            user_id: attendee.user_id,
+            event_id: attendee.event_id,
+            encrypted_data: attendee.encrypted_data,
+            iv: attendee.iv,
+            salt: attendee.salt,
+            schema_version: attendee.schema_version,
+            rsvp_status: attendee.rsvp_status,
+            responded_at: attendee.responded_at.map(|dt| dt.naive_utc().and_utc()),
+            email: attendee.email,
+            invited_at: attendee.invited_at.map(|dt| dt.naive_utc().and_utc()),
+            created_at: attendee.created_at.naive_utc().and_utc(),
+            updated_at: attendee.updated_at.naive_utc().and_utc(),
+        }
+    }
+}
+
+impl Validate for CreateAttendeeRequest {
+    fn validate(&self, errors: &mut Vec<FieldError>) {
+        validate_reference(errors, "event_id", self.event_id);
+        if let Some(invite) = &self.invite {
+            validate_email(errors, "invite.email", invite.email.trim());
+            validate_required(errors, "invite.summary", &invite.summary);
+            let texts = [
+                ("invite.summary", Some(&invite.summary)),
+                ("invite.location", invite.location.as_ref()),
+            ];
+            for (field, text) in texts {
+                if text.is_some_and(|text| text.chars().count() > MAX_INVITE_TEXT_CHARS) {
+                    field_error(errors, field, format!("{} must be at most {} characters", field, MAX_INVITE_TEXT_CHARS));
+                }
+            }
+        }
+    }
+}
+
+impl Validate for RsvpRequest {}
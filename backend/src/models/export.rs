@@ -0,0 +1,40 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use uuid::Uuid;
+
+/// Layout of the export archive; bumped when an entry changes incompatibly
+pub const EXPORT_FORMAT_VERSION: u32 = 1;
+/// Largest archive accepted by the import
+pub const MAX_IMPORT_BYTES: usize = 64 * 1024 * 1024;
+/// Largest uncompressed entry read from an archive
+pub const MAX_IMPORT_ENTRY_BYTES: u64 = 256 * 1024 * 1024;
+
+pub const MANIFEST_ENTRY: &str = "manifest.json";
+
+/// `manifest.json`: what the archive holds. Every other entry is `<table>.json`,
+/// an array of the table's rows as stored, so encrypted fields stay encrypted.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ExportManifest {
+    pub format_version: u32,
+    pub exported_at: DateTime<Utc>,
+    /// Rows per table
+    pub counts: BTreeMap<String, usize>,
+}
+
+/// `manifest.json` of an organization export, which can't be imported
+#[derive(Debug, Serialize)]
+pub struct OrganizationExportManifest {
+    pub format_version: u32,
+    pub exported_at: DateTime<Utc>,
+    pub organization_id: Uuid,
+    /// Rows per table
+    pub counts: BTreeMap<String, usize>,
+}
+
+/// Rows created per table by an import, and rows left out because what they belong to was missing
+#[derive(Debug, Default, Serialize)]
+pub struct ImportSummary {
+    pub imported: BTreeMap<String, usize>,
+    pub skipped: BTreeMap<String, usize>,
+}
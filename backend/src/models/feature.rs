@@ -0,0 +1,11 @@
+use serde::Deserialize;
+
+use crate::models::validation::Validate;
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct SetFeatureOverrideRequest {
+    /// Whether the feature is on for the user, whatever the rollout says
+    pub enabled: bool,
+}
+
+impl Validate for SetFeatureOverrideRequest {}
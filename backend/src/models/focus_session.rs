@@ -0,0 +1,93 @@
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::{
+    entities::focus_sessions::{self, FocusKind, FocusStatus},
+    errors::{AppError, FieldError, Result},
+    models::validation::{collect, Validate},
+};
+
+/// Length of a focus session started without `planned_minutes`
+pub const DEFAULT_FOCUS_MINUTES: i32 = 25;
+/// Length of a break started without `planned_minutes`
+pub const DEFAULT_BREAK_MINUTES: i32 = 5;
+/// Longest session that can be planned
+pub const MAX_FOCUS_MINUTES: i32 = 240;
+
+#[derive(Debug, Default, Deserialize)]
+pub struct StartFocusSessionRequest {
+    /// Task to work on; must be an active task of the user
+    pub task_id: Option<Uuid>,
+    #[serde(default)]
+    pub kind: FocusKind,
+    /// 25 for focus and 5 for breaks when omitted
+    pub planned_minutes: Option<i32>,
+}
+
+impl Validate for StartFocusSessionRequest {
+    fn validate(&self, errors: &mut Vec<FieldError>) {
+        collect(errors, "planned_minutes", self.planned_minutes().map(drop));
+    }
+}
+
+impl StartFocusSessionRequest {
+    pub fn planned_minutes(&self) -> Result<i32> {
+        let minutes = self.planned_minutes.unwrap_or(match self.kind {
+            FocusKind::Focus => DEFAULT_FOCUS_MINUTES,
+            FocusKind::Break => DEFAULT_BREAK_MINUTES,
+        });
+        if !(1..=MAX_FOCUS_MINUTES).contains(&minutes) {
+            return Err(AppError::invalid_field(
+                "planned_minutes",
+                format!("planned_minutes must be between 1 and {}", MAX_FOCUS_MINUTES),
+            ));
+        }
+        Ok(minutes)
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct FocusSessionQuery {
+    /// Only sessions still running or ending after this instant
+    pub start: Option<DateTime<Utc>>,
+    /// Only sessions starting before this instant
+    pub end: Option<DateTime<Utc>>,
+    pub task_id: Option<Uuid>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct FocusSessionResponse {
+    pub id: Uuid,
+    pub user_id: Uuid,
+    pub task_id: Option<Uuid>,
+    pub kind: FocusKind,
+    pub planned_minutes: i32,
+    pub status: FocusStatus,
+    pub started_at: DateTime<Utc>,
+    /// When the planned time runs out, so every device counts down to the same instant
+    pub planned_end_at: DateTime<Utc>,
+    /// `null` while the session is running
+    pub ended_at: Option<DateTime<Utc>>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+impl From<focus_sessions::Model> for FocusSessionResponse {
+    fn from(session: focus_sessions::Model) -> Self {
+        let started_at = session.started_at.naive_utc().and_utc();
+        Self {
+            id: session.id,
+            user_id: session.user_id,
+            task_id: session.task_id,
+            kind: session.kind,
+            planned_minutes: session.planned_minutes,
+            status: session.status,
+            started_at,
+            planned_end_at: started_at + Duration::minutes(session.planned_minutes as i64),
+            ended_at: session.ended_at.map(|dt| dt.naive_utc().and_utc()),
+            created_at: session.created_at.naive_utc().and_utc(),
+            updated_at: session.updated_at.naive_utc().and_utc(),
+        }
+    }
+}
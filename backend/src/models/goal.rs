@@ -0,0 +1,150 @@
+use chrono::{DateTime, NaiveDate, Utc};
+use sea_orm::{ActiveModelBehavior, Set};
+use serde::{Deserialize, Serialize};
+use streamline_models::{double_option, DEFAULT_SCHEMA_VERSION};
+use uuid::Uuid;
+
+use crate::{
+    entities::goals,
+    errors::FieldError,
+    models::{
+        validate_client_id,
+        validation::{collect, Validate},
+    },
+};
+
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct CreateGoalRequest {
+    /// Optional client-generated id so offline-created records keep their identity
+    pub id: Option<Uuid>,
+    pub encrypted_data: String,
+    pub iv: String,
+    pub salt: String,
+    /// Format version of `encrypted_data`; 1 when omitted
+    pub schema_version: Option<i16>,
+    pub target_date: Option<NaiveDate>,
+}
+
+impl CreateGoalRequest {
+    pub fn into_active_model(self, user_id: Uuid) -> goals::ActiveModel {
+        let mut goal_active = goals::ActiveModel::new();
+        if let Some(id) = self.id {
+            goal_active.id = Set(id);
+        }
+        goal_active.user_id = Set(user_id);
+        goal_active.encrypted_data = Set(self.encrypted_data);
+        goal_active.iv = Set(self.iv);
+        goal_active.salt = Set(self.salt);
+        goal_active.schema_version = Set(self.schema_version.unwrap_or(DEFAULT_SCHEMA_VERSION));
+        goal_active.target_date = Set(self.target_date);
+        goal_active
+    }
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct UpdateGoalRequest {
+    pub encrypted_data: Option<String>,
+    pub iv: Option<String>,
+    pub salt: Option<String>,
+    /// Format version of `encrypted_data`; a new `encrypted_data` without it is taken to be version 1
+    pub schema_version: Option<i16>,
+    /// `null` clears the target date
+    #[serde(default, deserialize_with = "double_option")]
+    pub target_date: Option<Option<NaiveDate>>,
+    /// Mark the goal as reached, or open it again
+    pub achieved: Option<bool>,
+}
+
+impl UpdateGoalRequest {
+    pub fn apply_to(self, goal_active: &mut goals::ActiveModel) {
+        // A new payload without a version comes from a client predating versioning
+        let schema_version = self.schema_version.or(self.encrypted_data.is_some().then_some(DEFAULT_SCHEMA_VERSION));
+        if let Some(encrypted_data) = self.encrypted_data {
+            goal_active.encrypted_data = Set(encrypted_data);
+        }
+        if let Some(iv) = self.iv {
+            goal_active.iv = Set(iv);
+        }
+        if let Some(salt) = self.salt {
+            goal_active.salt = Set(salt);
+        }
+        if let Some(schema_version) = schema_version {
+            goal_active.schema_version = Set(schema_version);
+        }
+        if let Some(target_date) = self.target_date {
+            goal_active.target_date = Set(target_date);
+        }
+        match self.achieved {
+            // Marking a reached goal again keeps when it was first reached
+            Some(true) if goal_active.achieved_at.as_ref().is_none() => {
+                goal_active.achieved_at = Set(Some(Utc::now().into()));
+            }
+            Some(false) => goal_active.achieved_at = Set(None),
+            _ => {}
+        }
+    }
+}
+
+/// How far along a goal is, counted over the active tasks linked to it directly or through a project
+#[derive(Debug, Clone, Copy, Default, Serialize)]
+pub struct GoalProgress {
+    pub total_tasks: u64,
+    pub completed_tasks: u64,
+    /// Share of `total_tasks` completed, rounded down; 0 without tasks
+    pub percent: u8,
+}
+
+impl GoalProgress {
+    pub fn new(total_tasks: u64, completed_tasks: u64) -> Self {
+        let percent = (completed_tasks * 100).checked_div(total_tasks).unwrap_or(0) as u8;
+        Self { total_tasks, completed_tasks, percent }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct GoalResponse {
+    pub id: Uuid,
+    pub user_id: Uuid,
+    pub encrypted_data: String,
+    pub iv: String,
+    pub salt: String,
+    /// Format version of `encrypted_data`
+    pub schema_version: i16,
+    pub target_date: Option<NaiveDate>,
+    pub achieved_at: Option<DateTime<Utc>>,
+    /// Linked projects; all of their tasks count towards the goal
+    pub project_ids: Vec<Uuid>,
+    /// Tasks linked on their own
+    pub task_ids: Vec<Uuid>,
+    pub progress: GoalProgress,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+impl GoalResponse {
+    pub fn new(goal: goals::Model, project_ids: Vec<Uuid>, task_ids: Vec<Uuid>, progress: GoalProgress) -> Self {
+        Self {
+            id: goal.id,
+            user_id: goal.user_id,
+            encrypted_data: goal.encrypted_data,
+            iv: goal.iv,
+            salt: goal.salt,
+            schema_version: goal.schema_version,
+            target_date: goal.target_date,
+            achieved_at: goal.achieved_at.map(|dt| dt.naive_utc().and_utc()),
+            project_ids,
+            task_ids,
+            progress,
+            created_at: goal.created_at.naive_utc().and_utc(),
+            updated_at: goal.updated_at.naive_utc().and_utc(),
+        }
+    }
+}
+
+impl Validate for CreateGoalRequest {
+    fn validate(&self, errors: &mut Vec<FieldError>) {
+        collect(errors, "id", validate_client_id(self.id));
+    }
+}
+
+impl Validate for UpdateGoalRequest {}
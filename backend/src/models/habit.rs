@@ -0,0 +1,193 @@
+use chrono::{DateTime, NaiveDate, Utc};
+use sea_orm::{ActiveModelBehavior, Set};
+use serde::{Deserialize, Serialize};
+use streamline_models::DEFAULT_SCHEMA_VERSION;
+use uuid::Uuid;
+
+use crate::{
+    entities::{habit_checkins, habits},
+    errors::FieldError,
+    models::{
+        validate_client_id,
+        validation::{collect, Validate},
+    },
+};
+
+/// Schedule of habits created without an `rrule`
+pub const DEFAULT_HABIT_RRULE: &str = "FREQ=DAILY";
+/// Widest range of check-ins listed in one request
+pub const MAX_CHECKIN_WINDOW_DAYS: i64 = 366;
+
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct CreateHabitRequest {
+    /// Optional client-generated id so offline-created records keep their identity
+    pub id: Option<Uuid>,
+    pub encrypted_data: String,
+    pub iv: String,
+    pub salt: String,
+    /// Format version of `encrypted_data`; 1 when omitted
+    pub schema_version: Option<i16>,
+    /// Daily when omitted
+    pub rrule: Option<String>,
+    /// The user's current date when omitted
+    pub starts_on: Option<NaiveDate>,
+}
+
+impl CreateHabitRequest {
+    pub fn rrule(&self) -> &str {
+        self.rrule.as_deref().unwrap_or(DEFAULT_HABIT_RRULE)
+    }
+
+    pub fn into_active_model(self, user_id: Uuid, today: NaiveDate) -> habits::ActiveModel {
+        let mut habit_active = habits::ActiveModel::new();
+        if let Some(id) = self.id {
+            habit_active.id = Set(id);
+        }
+        habit_active.user_id = Set(user_id);
+        habit_active.rrule = Set(self.rrule().to_string());
+        habit_active.starts_on = Set(self.starts_on.unwrap_or(today));
+        habit_active.encrypted_data = Set(self.encrypted_data);
+        habit_active.iv = Set(self.iv);
+        habit_active.salt = Set(self.salt);
+        habit_active.schema_version = Set(self.schema_version.unwrap_or(DEFAULT_SCHEMA_VERSION));
+        habit_active
+    }
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct UpdateHabitRequest {
+    pub encrypted_data: Option<String>,
+    pub iv: Option<String>,
+    pub salt: Option<String>,
+    /// Format version of `encrypted_data`; a new `encrypted_data` without it is taken to be version 1
+    pub schema_version: Option<i16>,
+    pub rrule: Option<String>,
+    pub starts_on: Option<NaiveDate>,
+}
+
+impl UpdateHabitRequest {
+    /// Whether the change moves the days the habit is due, so its streaks need recounting
+    pub fn reschedules(&self) -> bool {
+        self.rrule.is_some() || self.starts_on.is_some()
+    }
+
+    pub fn apply_to(self, habit_active: &mut habits::ActiveModel) {
+        // A new payload without a version comes from a client predating versioning
+        let schema_version = self.schema_version.or(self.encrypted_data.is_some().then_some(DEFAULT_SCHEMA_VERSION));
+        if let Some(encrypted_data) = self.encrypted_data {
+            habit_active.encrypted_data = Set(encrypted_data);
+        }
+        if let Some(iv) = self.iv {
+            habit_active.iv = Set(iv);
+        }
+        if let Some(salt) = self.salt {
+            habit_active.salt = Set(salt);
+        }
+        if let Some(schema_version) = schema_version {
+            habit_active.schema_version = Set(schema_version);
+        }
+        if let Some(rrule) = self.rrule {
+            habit_active.rrule = Set(rrule);
+        }
+        if let Some(starts_on) = self.starts_on {
+            habit_active.starts_on = Set(starts_on);
+        }
+    }
+}
+
+/// The day a check-in is for
+#[derive(Debug, Default, Deserialize)]
+pub struct CheckinRequest {
+    /// The user's current date when omitted
+    pub date: Option<NaiveDate>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CheckinListQuery {
+    /// Defaults to a year before `end`
+    pub start: Option<NaiveDate>,
+    /// Defaults to the user's current date
+    pub end: Option<NaiveDate>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct HabitResponse {
+    pub id: Uuid,
+    pub user_id: Uuid,
+    pub encrypted_data: String,
+    pub iv: String,
+    pub salt: String,
+    /// Format version of `encrypted_data`
+    pub schema_version: i16,
+    pub rrule: String,
+    pub starts_on: NaiveDate,
+    /// Periods in a row the habit was done, ending with the latest one that is over or done
+    pub current_streak: i32,
+    pub longest_streak: i32,
+    /// Local date from which the current streak is broken unless the habit is checked in first
+    pub streak_expires_on: Option<NaiveDate>,
+    pub last_checkin_on: Option<NaiveDate>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+impl HabitResponse {
+    /// The streak as it stands on `today`; a streak that ran out since the last check-in reads as 0
+    pub fn on(mut self, today: NaiveDate) -> Self {
+        if self.streak_expires_on.is_some_and(|expires_on| expires_on <= today) {
+            self.current_streak = 0;
+            self.streak_expires_on = None;
+        }
+        self
+    }
+}
+
+impl From<habits::Model> for HabitResponse {
+    fn from(habit: habits::Model) -> Self {
+        Self {
+            id: habit.id,
+            user_id: habit.user_id,
+            encrypted_data: habit.encrypted_data,
+            iv: habit.iv,
+            salt: habit.salt,
+            schema_version: habit.schema_version,
+            rrule: habit.rrule,
+            starts_on: habit.starts_on,
+            current_streak: habit.current_streak,
+            longest_streak: habit.longest_streak,
+            streak_expires_on: habit.streak_expires_on,
+            last_checkin_on: habit.last_checkin_on,
+            created_at: habit.created_at.naive_utc().and_utc(),
+            updated_at: habit.updated_at.naive_utc().and_utc(),
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct HabitCheckinResponse {
+    pub id: Uuid,
+    pub habit_id: Uuid,
+    pub checked_on: NaiveDate,
+    pub created_at: DateTime<Utc>,
+}
+
+impl From<habit_checkins::Model> for HabitCheckinResponse {
+    fn from(checkin: habit_checkins::Model) -> Self {
+        Self {
+            id: checkin.id,
+            habit_id: checkin.habit_id,
+            checked_on: checkin.checked_on,
+            created_at: checkin.created_at.naive_utc().and_utc(),
+        }
+    }
+}
+
+impl Validate for CreateHabitRequest {
+    fn validate(&self, errors: &mut Vec<FieldError>) {
+        collect(errors, "id", validate_client_id(self.id));
+    }
+}
+
+impl Validate for UpdateHabitRequest {}
+
+impl Validate for CheckinRequest {}
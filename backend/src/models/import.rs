@@ -0,0 +1,182 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use uuid::Uuid;
+
+use crate::{
+    entities::can_do_list::TaskPriority,
+    errors::{AppError, FieldError, Result},
+    models::{
+        can_do_list::validate_estimated_minutes,
+        search::validate_search_tokens,
+        validate_schema_version,
+        validation::{collect, field_error, Validate},
+    },
+};
+
+/// Largest export accepted from another app, and largest encrypted commit
+pub const MAX_IMPORT_UPLOAD_BYTES: usize = 32 * 1024 * 1024;
+/// Most records created by one import
+pub const MAX_IMPORT_PROJECTS: usize = 1_000;
+pub const MAX_IMPORT_TASKS: usize = 20_000;
+const MAX_KEY_LENGTH: usize = 64;
+
+/// Phase one of an import: what another app's export holds, as plaintext the client encrypts.
+/// Keys name the records so the encrypted versions sent back can refer to each other.
+#[derive(Debug, Default, Serialize)]
+pub struct ImportPlan {
+    pub projects: Vec<PlannedProject>,
+    pub tasks: Vec<PlannedTask>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct PlannedProject {
+    pub key: String,
+    pub parent_key: Option<String>,
+    pub name: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct PlannedTask {
+    pub key: String,
+    pub project_key: String,
+    pub parent_key: Option<String>,
+    pub content: String,
+    pub description: Option<String>,
+    /// Comments left on the task, oldest first
+    pub comments: Vec<String>,
+    pub priority: Option<TaskPriority>,
+    pub due_at: Option<DateTime<Utc>>,
+    /// The due date as the export wrote it, such as "every monday"; left for the client to
+    /// interpret when `due_at` is missing or the date repeats
+    pub date_string: Option<String>,
+    pub estimated_minutes: Option<i32>,
+}
+
+/// Phase two: the planned records, encrypted by the client. Links use the plan's keys and
+/// must point at a record earlier in the same list.
+#[derive(Debug, Deserialize)]
+pub struct CommitImportRequest {
+    #[serde(default)]
+    pub projects: Vec<ImportedProject>,
+    #[serde(default)]
+    pub tasks: Vec<ImportedTask>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ImportedProject {
+    pub key: String,
+    pub parent_key: Option<String>,
+    pub encrypted_data: String,
+    pub iv: String,
+    pub salt: String,
+    pub schema_version: Option<i16>,
+    pub search_tokens: Option<Vec<String>>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ImportedTask {
+    pub key: String,
+    /// Tasks without a project go to the default project
+    pub project_key: Option<String>,
+    pub parent_key: Option<String>,
+    pub encrypted_data: String,
+    pub iv: String,
+    pub salt: String,
+    pub schema_version: Option<i16>,
+    pub search_bloom: Option<String>,
+    pub search_tokens: Option<Vec<String>>,
+    pub due_at: Option<DateTime<Utc>>,
+    pub rrule: Option<String>,
+    pub priority: Option<TaskPriority>,
+    pub estimated_minutes: Option<i32>,
+}
+
+impl Validate for CommitImportRequest {
+    fn validate(&self, errors: &mut Vec<FieldError>) {
+        if self.projects.is_empty() && self.tasks.is_empty() {
+            field_error(errors, "tasks", "Nothing to import");
+        }
+        if self.projects.len() > MAX_IMPORT_PROJECTS {
+            field_error(errors, "projects", format!("At most {} projects can be imported at once", MAX_IMPORT_PROJECTS));
+            return;
+        }
+        if self.tasks.len() > MAX_IMPORT_TASKS {
+            field_error(errors, "tasks", format!("At most {} tasks can be imported at once", MAX_IMPORT_TASKS));
+            return;
+        }
+
+        let mut project_keys = HashSet::with_capacity(self.projects.len());
+        for (index, project) in self.projects.iter().enumerate() {
+            let field = |name: &str| format!("projects[{}].{}", index, name);
+            collect(errors, &field("parent_key"), validate_link(&project_keys, "parent_key", project.parent_key.as_deref()));
+            collect(errors, &field("schema_version"), validate_schema_version("projects", project.schema_version));
+            collect(errors, &field("search_tokens"), validate_search_tokens(project.search_tokens.as_deref()));
+            collect(errors, &field("key"), validate_key(&mut project_keys, &project.key));
+        }
+
+        let mut task_keys = HashSet::with_capacity(self.tasks.len());
+        for (index, task) in self.tasks.iter().enumerate() {
+            let field = |name: &str| format!("tasks[{}].{}", index, name);
+            collect(errors, &field("project_key"), validate_link(&project_keys, "project_key", task.project_key.as_deref()));
+            collect(errors, &field("parent_key"), validate_link(&task_keys, "parent_key", task.parent_key.as_deref()));
+            collect(errors, &field("estimated_minutes"), validate_estimated_minutes(task.estimated_minutes));
+            collect(errors, &field("schema_version"), validate_schema_version("can_do_list", task.schema_version));
+            collect(errors, &field("search_tokens"), validate_search_tokens(task.search_tokens.as_deref()));
+            collect(errors, &field("rrule"), crate::recurrence::validate_task(task.rrule.as_deref(), task.due_at));
+            collect(errors, &field("key"), validate_key(&mut task_keys, &task.key));
+        }
+    }
+}
+
+impl CommitImportRequest {
+    pub fn total(&self) -> usize {
+        self.projects.len() + self.tasks.len()
+    }
+}
+
+fn validate_key<'a>(keys: &mut HashSet<&'a str>, key: &'a str) -> Result<()> {
+    if key.is_empty() || key.len() > MAX_KEY_LENGTH {
+        return Err(AppError::Validation(format!("Keys must be 1 to {} characters long", MAX_KEY_LENGTH)));
+    }
+    if !keys.insert(key) {
+        return Err(AppError::Validation(format!("Key '{}' is used twice", key)));
+    }
+    Ok(())
+}
+
+fn validate_link(earlier: &HashSet<&str>, field: &str, key: Option<&str>) -> Result<()> {
+    match key {
+        Some(key) if !earlier.contains(key) => Err(AppError::Validation(format!(
+            "{} '{}' must name a record listed before it",
+            field, key
+        ))),
+        _ => Ok(()),
+    }
+}
+
+/// Returned when the encrypted records are accepted; progress follows over the WebSocket
+#[derive(Debug, Serialize)]
+pub struct ImportStarted {
+    pub import_id: Uuid,
+    pub total: usize,
+}
+
+/// Payload of `IMPORT_PROGRESS` WebSocket messages
+#[derive(Debug, Serialize)]
+pub struct ImportProgress {
+    pub source: &'static str,
+    pub status: ImportStatus,
+    pub processed: usize,
+    pub total: usize,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ImportStatus {
+    Running,
+    Completed,
+    Failed,
+}
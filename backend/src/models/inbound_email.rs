@@ -0,0 +1,117 @@
+use chrono::{DateTime, Utc};
+use hmac::{Hmac, Mac};
+use serde::{Deserialize, Serialize};
+use sha2::Sha256;
+use std::env;
+
+use crate::{entities::inbound_email_addresses, models::validation::Validate};
+
+/// Longest subject and body kept from an email; the rest is cut off
+const MAX_SUBJECT_CHARS: usize = 1000;
+const MAX_BODY_CHARS: usize = 64 * 1024;
+/// Largest request the inbound mail hook accepts
+pub const MAX_INBOUND_EMAIL_BYTES: usize = 1024 * 1024;
+
+/// Domain inbound mail is received on, e.g. `in.example.com`. Email-to-task is disabled unless
+/// it and [`inbound_email_secret`] are both set, since the hook can't be called without the secret.
+pub fn inbound_email_domain() -> Option<String> {
+    inbound_email_secret()?;
+    env::var("INBOUND_EMAIL_DOMAIN")
+        .ok()
+        .map(|domain| domain.trim().to_ascii_lowercase())
+        .filter(|domain| !domain.is_empty())
+}
+
+/// Bearer token the mail server sends with every call to the inbound hook
+pub fn inbound_email_secret() -> Option<String> {
+    env::var("INBOUND_EMAIL_SECRET")
+        .ok()
+        .map(|secret| secret.trim().to_string())
+        .filter(|secret| !secret.is_empty())
+}
+
+/// Compare a presented token with the secret in constant time, so response timing doesn't
+/// reveal how much of it was right. Both are hashed first, which also hides the secret's length.
+pub fn inbound_secret_matches(presented: &str, secret: &str) -> bool {
+    let digest = |value: &str| {
+        let mut mac = Hmac::<Sha256>::new_from_slice(b"streamline-inbound-email").expect("HMAC takes keys of any size");
+        mac.update(value.as_bytes());
+        mac
+    };
+    digest(presented).verify_slice(&digest(secret).finalize().into_bytes()).is_ok()
+}
+
+/// Lowercase hex, as some mail servers don't preserve the case of the local part
+pub fn generate_inbound_token() -> String {
+    hex::encode(rand::random::<[u8; 16]>())
+}
+
+#[derive(Debug, Serialize)]
+pub struct InboundEmailAddressResponse {
+    /// `<token>@<INBOUND_EMAIL_DOMAIN>`
+    pub address: String,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+impl InboundEmailAddressResponse {
+    pub fn new(address: inbound_email_addresses::Model, domain: &str) -> Self {
+        Self {
+            address: format!("{}@{}", address.token, domain),
+            created_at: address.created_at.naive_utc().and_utc(),
+            updated_at: address.updated_at.naive_utc().and_utc(),
+        }
+    }
+}
+
+/// A received email as the mail server's hook posts it
+#[derive(Debug, Deserialize)]
+pub struct InboundEmailRequest {
+    /// Recipients, e.g. `Tasks <3f2a...@in.example.com>, other@example.com`
+    pub to: String,
+    pub from: Option<String>,
+    pub subject: Option<String>,
+    /// Plain text body
+    pub text: Option<String>,
+    /// The `text/calendar` part, which replies to invitations carry
+    pub calendar: Option<String>,
+}
+
+/// What the server learns from an email; the client encrypts it into the task's `encrypted_data`
+#[derive(Debug, Serialize)]
+pub struct PendingEmailTask {
+    pub subject: String,
+    pub body: String,
+    pub from: Option<String>,
+    pub received_at: DateTime<Utc>,
+}
+
+impl InboundEmailRequest {
+    /// Tokens of the recipients on `domain`; other recipients are ignored
+    pub fn recipient_tokens(&self, domain: &str) -> Vec<String> {
+        self.to
+            .split(',')
+            .filter_map(|recipient| {
+                let recipient = recipient.trim();
+                let address = match (recipient.rfind('<'), recipient.rfind('>')) {
+                    (Some(start), Some(end)) if start < end => &recipient[start + 1..end],
+                    _ => recipient,
+                };
+                let (token, recipient_domain) = address.trim().rsplit_once('@')?;
+                recipient_domain.eq_ignore_ascii_case(domain).then(|| token.to_ascii_lowercase())
+            })
+            .collect()
+    }
+
+    pub fn into_pending_task(self) -> PendingEmailTask {
+        let subject = self.subject.as_deref().map(str::trim).filter(|subject| !subject.is_empty()).unwrap_or("(no subject)");
+        PendingEmailTask {
+            subject: subject.chars().take(MAX_SUBJECT_CHARS).collect(),
+            body: self.text.unwrap_or_default().trim().chars().take(MAX_BODY_CHARS).collect(),
+            from: self.from.map(|from| from.trim().to_string()).filter(|from| !from.is_empty()),
+            received_at: Utc::now(),
+        }
+    }
+}
+
+impl Validate for InboundEmailRequest {}
@@ -0,0 +1,81 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::{
+    entities::kv_entries,
+    errors::{AppError, FieldError, Result},
+    models::validation::{field_error, Validate},
+};
+
+/// Longest key accepted, in characters
+pub const MAX_KV_KEY_LENGTH: usize = 128;
+/// Entries a user may have at once
+pub const MAX_KV_ENTRIES: u64 = 1000;
+/// Largest `encrypted_data` accepted; the store is meant for small bits of UI state
+pub const MAX_KV_VALUE_BYTES: usize = 64 * 1024;
+
+/// Keys are ASCII letters, digits, `_`, `-`, `.` and `:`, e.g. `calendar.last_view`
+pub fn validate_kv_key(key: &str) -> Result<()> {
+    let valid = !key.is_empty()
+        && key.len() <= MAX_KV_KEY_LENGTH
+        && key.bytes().all(|byte| byte.is_ascii_alphanumeric() || b"_-.:".contains(&byte));
+    if !valid {
+        return Err(AppError::invalid_field(
+            "key",
+            format!("key must be 1 to {} letters, digits, '_', '-', '.' or ':'", MAX_KV_KEY_LENGTH),
+        ));
+    }
+    Ok(())
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct SaveKvEntryRequest {
+    pub encrypted_data: String,
+    pub iv: String,
+    pub salt: String,
+    /// Format version of `encrypted_data`; 1 when omitted
+    pub schema_version: Option<i16>,
+}
+
+/// `?prefix=calendar.` only lists the keys starting with it
+#[derive(Debug, Deserialize)]
+pub struct KvQuery {
+    pub prefix: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct KvEntryResponse {
+    pub id: Uuid,
+    pub key: String,
+    pub encrypted_data: String,
+    pub iv: String,
+    pub salt: String,
+    /// Format version of `encrypted_data`
+    pub schema_version: i16,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+impl From<kv_entries::Model> for KvEntryResponse {
+    fn from(entry: kv_entries::Model) -> Self {
+        Self {
+            id: entry.id,
+            key: entry.key,
+            encrypted_data: entry.encrypted_data,
+            iv: entry.iv,
+            salt: entry.salt,
+            schema_version: entry.schema_version,
+            created_at: entry.created_at.naive_utc().and_utc(),
+            updated_at: entry.updated_at.naive_utc().and_utc(),
+        }
+    }
+}
+
+impl Validate for SaveKvEntryRequest {
+    fn validate(&self, errors: &mut Vec<FieldError>) {
+        if self.encrypted_data.len() > MAX_KV_VALUE_BYTES {
+            field_error(errors, "encrypted_data", format!("encrypted_data must be at most {} bytes", MAX_KV_VALUE_BYTES));
+        }
+    }
+}
@@ -1,12 +1,58 @@
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine as _};
 use chrono::{DateTime, Utc};
+use sea_orm::{ConnectionTrait, EntityTrait, PaginatorTrait, QuerySelect, Select};
 use serde::{Deserialize, Serialize};
+use std::{collections::HashSet, env, sync::LazyLock};
+use uuid::Uuid;
+
+// Wire types shared with `streamline-client`; the server-only conversions live in the modules below
+pub use streamline_models::{double_option, ApiResponse, ListMeta};
+pub use validation::Validate;
 
 pub mod user;
 pub mod project;
 pub mod can_do_list;
 pub mod calendar;
 pub mod calendar_event;
+pub mod sync;
+pub mod audit;
+pub mod recurrence;
+pub mod reminder;
+pub mod search;
+pub mod trash;
+pub mod usage;
+pub mod time_entry;
+pub mod event_attendee;
+pub mod note;
+pub mod project_member;
+pub mod poll;
+pub mod booking;
+pub mod calendar_subscription;
+pub mod export;
+pub mod backup;
+pub mod import;
+pub mod webhook;
+pub mod inbound_email;
+pub mod notification_preferences;
+pub mod organization;
+pub mod revision;
+pub mod report;
+pub mod user_settings;
+pub mod schedule;
+pub mod scheduling_constraints;
+pub mod focus_session;
+pub mod habit;
+pub mod goal;
+pub mod template;
+pub mod quick_add;
+pub mod feature;
+pub mod setting;
+pub mod announcement;
+pub mod kv;
+pub mod conflict;
+pub mod validation;
 
+#[allow(dead_code)]
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct EncryptedData {
     pub encrypted_data: String,
@@ -14,37 +60,224 @@ pub struct EncryptedData {
     pub salt: String,
 }
 
+#[allow(dead_code)]
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct TimestampFields {
     pub created_at: DateTime<Utc>,
     pub updated_at: DateTime<Utc>,
 }
 
-// Common response types
-#[derive(Debug, Serialize)]
-pub struct ApiResponse<T> {
-    pub data: T,
-    pub message: Option<String>,
+/// Spacing between reordered positions, leaving room to insert items without renumbering
+pub const DISPLAY_ORDER_GAP: i32 = 1000;
+/// Upper bound on ids in one reorder request, keeping positions well within `i32`
+pub const MAX_REORDER_IDS: usize = 10_000;
+
+/// Ids in their new display order
+#[derive(Debug, Deserialize)]
+pub struct ReorderRequest {
+    pub ids: Vec<Uuid>,
 }
 
-#[derive(Debug, Serialize)]
-pub struct ErrorResponse {
-    pub error: String,
-    pub details: Option<String>,
+impl Validate for ReorderRequest {
+    fn validate(&self, errors: &mut Vec<crate::errors::FieldError>) {
+        if self.ids.is_empty() {
+            validation::field_error(errors, "ids", "ids must not be empty");
+        } else if self.ids.len() > MAX_REORDER_IDS {
+            validation::field_error(errors, "ids", format!("At most {} ids can be reordered at once", MAX_REORDER_IDS));
+        } else if self.ids.iter().collect::<HashSet<&Uuid>>().len() != self.ids.len() {
+            validation::field_error(errors, "ids", "ids must not contain duplicates");
+        }
+    }
+}
+
+impl ReorderRequest {
+    /// `display_order` for the id at `index`
+    pub fn position(index: usize) -> i32 {
+        (index as i32 + 1) * DISPLAY_ORDER_GAP
+    }
 }
 
-impl<T> ApiResponse<T> {
-    pub fn new(data: T) -> Self {
-        Self {
-            data,
-            message: None,
+/// Builds the row for a create request of a shared record type, owned by `user_id`
+pub trait IntoRecord<A> {
+    fn into_active_model(self, user_id: Uuid) -> A;
+}
+
+/// Copies the fields present in an update request of a shared record type onto a row
+pub trait ApplyTo<A> {
+    fn apply_to(self, active: &mut A);
+}
+
+/// `?fields=id,updated_at,display_order` limits list responses to the named fields
+#[derive(Debug, Deserialize)]
+pub struct FieldsQuery {
+    pub fields: Option<String>,
+}
+
+impl FieldsQuery {
+    /// Serialize records, keeping only the requested fields; `id` is always included.
+    /// Unknown field names are ignored so clients can ask for fields newer servers add.
+    pub fn select<T: Serialize>(&self, records: Vec<T>) -> crate::errors::Result<Vec<serde_json::Value>> {
+        let fields: Option<HashSet<&str>> = self.fields.as_deref().map(|fields| {
+            fields
+                .split(',')
+                .map(str::trim)
+                .filter(|field| !field.is_empty())
+                .chain(std::iter::once("id"))
+                .collect()
+        });
+
+        records
+            .into_iter()
+            .map(|record| {
+                let mut value = serde_json::to_value(record)?;
+                if let (Some(fields), Some(object)) = (&fields, value.as_object_mut()) {
+                    object.retain(|key, _| fields.contains(key.as_str()));
+                }
+                Ok(value)
+            })
+            .collect()
+    }
+}
+
+/// Largest request body accepted by routes without a limit of their own
+pub const MAX_BODY_BYTES: usize = 2 * 1024 * 1024;
+
+/// Largest page a list endpoint returns for `?limit=`
+pub const MAX_PAGE_LIMIT: u64 = 1000;
+
+/// `?limit=50&cursor=...` pages through a list response; without `limit` the whole list is returned
+#[derive(Debug, Deserialize)]
+pub struct PageQuery {
+    pub limit: Option<u64>,
+    /// `next_cursor` of the previous page
+    pub cursor: Option<String>,
+    /// Taken when the query is extracted, before the handler reads anything
+    #[serde(skip, default = "Utc::now")]
+    pub generated_at: DateTime<Utc>,
+}
+
+impl PageQuery {
+    /// Records to skip and the page size
+    fn bounds(&self) -> crate::errors::Result<(u64, Option<u64>)> {
+        let mut errors = Vec::new();
+        if self.limit.is_some_and(|limit| !(1..=MAX_PAGE_LIMIT).contains(&limit)) {
+            validation::field_error(&mut errors, "limit", format!("limit must be between 1 and {}", MAX_PAGE_LIMIT));
+        }
+        let offset = match &self.cursor {
+            Some(cursor) => URL_SAFE_NO_PAD
+                .decode(cursor)
+                .ok()
+                .and_then(|bytes| String::from_utf8(bytes).ok())
+                .and_then(|offset| offset.parse().ok())
+                .unwrap_or_else(|| {
+                    validation::field_error(&mut errors, "cursor", "cursor must be a next_cursor of this list");
+                    0
+                }),
+            None => 0,
+        };
+        if !errors.is_empty() {
+            return Err(crate::errors::AppError::InvalidFields(errors));
         }
+        Ok((offset, self.limit))
     }
 
-    pub fn with_message(data: T, message: impl Into<String>) -> Self {
-        Self {
-            data,
-            message: Some(message.into()),
+    fn meta(&self, total_count: u64, offset: u64, returned: usize, limit: Option<u64>) -> ListMeta {
+        let end = offset + returned as u64;
+        ListMeta {
+            total_count,
+            limit,
+            next_cursor: limit.filter(|_| end < total_count).map(|_| URL_SAFE_NO_PAD.encode(end.to_string())),
+            generated_at: self.generated_at,
         }
     }
+
+    /// The requested page of a list that was read in full
+    pub fn page<T>(&self, items: Vec<T>) -> crate::errors::Result<(Vec<T>, ListMeta)> {
+        let (offset, limit) = self.bounds()?;
+        let total_count = items.len() as u64;
+        let items: Vec<T> = items
+            .into_iter()
+            .skip(offset as usize)
+            .take(limit.map_or(usize::MAX, |limit| limit as usize))
+            .collect();
+        let meta = self.meta(total_count, offset, items.len(), limit);
+        Ok((items, meta))
+    }
+
+    /// Read only the requested page of `select`, which must already be ordered
+    pub async fn fetch<E, C>(&self, db: &C, select: Select<E>) -> crate::errors::Result<(Vec<E::Model>, ListMeta)>
+    where
+        E: EntityTrait,
+        E::Model: Sync,
+        C: ConnectionTrait,
+    {
+        let (offset, limit) = self.bounds()?;
+        let Some(limit) = limit else {
+            let items = select.all(db).await.map_err(|e| crate::errors::AppError::Database(e.into()))?;
+            let meta = self.meta(items.len() as u64, 0, items.len(), None);
+            return Ok((items, meta));
+        };
+        let total_count = select.clone().count(db).await.map_err(|e| crate::errors::AppError::Database(e.into()))?;
+        let items = select
+            .offset(offset)
+            .limit(limit)
+            .all(db)
+            .await
+            .map_err(|e| crate::errors::AppError::Database(e.into()))?;
+        let meta = self.meta(total_count, offset, items.len(), Some(limit));
+        Ok((items, meta))
+    }
+}
+
+/// Client-supplied ids must be random (v4) or time-ordered (v7) so they can't collide by construction
+pub fn validate_client_id(id: Option<Uuid>) -> crate::errors::Result<()> {
+    match id.map(|id| id.get_version_num()) {
+        None | Some(4) | Some(7) => Ok(()),
+        Some(_) => Err(crate::errors::AppError::invalid_field("id", "id must be a version 4 or version 7 UUID")),
+    }
+}
+
+/// `table:version` pairs from `REJECTED_SCHEMA_VERSIONS`, e.g. `can_do_list:3,projects:2`: payload
+/// formats a client release is known to have written corrupt, refused so they don't spread
+static REJECTED_SCHEMA_VERSIONS: LazyLock<HashSet<(String, i16)>> = LazyLock::new(|| {
+    env::var("REJECTED_SCHEMA_VERSIONS")
+        .unwrap_or_default()
+        .split(',')
+        .filter_map(|entry| {
+            let (table, version) = entry.trim().split_once(':')?;
+            Some((table.trim().to_string(), version.trim().parse().ok()?))
+        })
+        .collect()
+});
+
+/// The `schema_version` a client sent for a record of `table`, if any
+pub fn validate_schema_version(table: &str, schema_version: Option<i16>) -> crate::errors::Result<()> {
+    let Some(version) = schema_version else {
+        return Ok(());
+    };
+    if version < 1 {
+        return Err(crate::errors::AppError::invalid_field("schema_version", "schema_version must be positive"));
+    }
+    if REJECTED_SCHEMA_VERSIONS.contains(&(table.to_string(), version)) {
+        return Err(crate::errors::AppError::Coded(
+            crate::errors::ErrorCode::ValidationSchemaVersionRejected,
+            format!("{} schema version {} is no longer accepted; update the app", table, version),
+        ));
+    }
+    Ok(())
+}
+
+/// Unguessable token for links shared outside the app, like poll invites and booking pages
+pub fn generate_public_token() -> String {
+    URL_SAFE_NO_PAD.encode(rand::random::<[u8; 32]>())
+}
+
+// Common response types
+#[derive(Debug, Serialize)]
+pub struct ErrorResponse {
+    pub error: String,
+    pub code: crate::errors::ErrorCode,
+    pub details: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub fields: Option<Vec<crate::errors::FieldError>>,
 }
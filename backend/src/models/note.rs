@@ -0,0 +1,80 @@
+use uuid::Uuid;
+use sea_orm::{ActiveModelBehavior, Set};
+use streamline_models::DEFAULT_SCHEMA_VERSION;
+use super::{
+    validate_client_id,
+    validation::{collect, Validate},
+    ApplyTo, IntoRecord,
+};
+use crate::errors::FieldError;
+use crate::entities::notes;
+
+pub use streamline_models::note::{CreateNoteRequest, NoteQuery, NoteResponse, UpdateNoteRequest};
+
+impl IntoRecord<notes::ActiveModel> for CreateNoteRequest {
+    fn into_active_model(self, user_id: Uuid) -> notes::ActiveModel {
+        let mut note_active = notes::ActiveModel::new();
+        if let Some(id) = self.id {
+            note_active.id = Set(id);
+        }
+        note_active.user_id = Set(user_id);
+        note_active.project_id = Set(self.project_id);
+        note_active.event_id = Set(self.event_id);
+        note_active.encrypted_data = Set(self.encrypted_data);
+        note_active.iv = Set(self.iv);
+        note_active.salt = Set(self.salt);
+        note_active.schema_version = Set(self.schema_version.unwrap_or(DEFAULT_SCHEMA_VERSION));
+        note_active
+    }
+}
+
+impl ApplyTo<notes::ActiveModel> for UpdateNoteRequest {
+    fn apply_to(self, note_active: &mut notes::ActiveModel) {
+        // A new payload without a version comes from a client predating versioning
+        let schema_version = self.schema_version.or(self.encrypted_data.is_some().then_some(DEFAULT_SCHEMA_VERSION));
+        if let Some(project_id) = self.project_id {
+            note_active.project_id = Set(project_id);
+        }
+        if let Some(event_id) = self.event_id {
+            note_active.event_id = Set(event_id);
+        }
+        if let Some(encrypted_data) = self.encrypted_data {
+            note_active.encrypted_data = Set(encrypted_data);
+        }
+        if let Some(iv) = self.iv {
+            note_active.iv = Set(iv);
+        }
+        if let Some(salt) = self.salt {
+            note_active.salt = Set(salt);
+        }
+        if let Some(schema_version) = schema_version {
+            note_active.schema_version = Set(schema_version);
+        }
+    }
+}
+
+impl From<notes::Model> for NoteResponse {
+    fn from(note: notes::Model) -> Self {
+        Self {
+            id: note.id,
+            user_id: note.user_id,
+            project_id: note.project_id,
+            event_id: note.event_id,
+            encrypted_data: note.encrypted_data,
+            iv: note.iv,
+            salt: note.salt,
+            schema_version: note.schema_version,
+            created_at: note.created_at.naive_utc().and_utc(),
+            updated_at: note.updated_at.naive_utc().and_utc(),
+            deleted_at: note.deleted_at.map(|dt| dt.naive_utc().and_utc()),
+        }
+    }
+}
+
+impl Validate for CreateNoteRequest {
+    fn validate(&self, errors: &mut Vec<FieldError>) {
+        collect(errors, "id", validate_client_id(self.id));
+    }
+}
+
+impl Validate for UpdateNoteRequest {}
@@ -0,0 +1,130 @@
+use chrono::{DateTime, Datelike, Duration, TimeZone, Utc};
+use chrono_tz::Tz;
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::{
+    entities::notification_preferences::{self, DigestFrequency},
+    errors::{AppError, FieldError, Result},
+    models::{user_settings::validate_timezone, validation::Validate},
+};
+
+#[derive(Debug, Deserialize)]
+pub struct UpdateNotificationPreferencesRequest {
+    /// Sets the timezone of the user settings
+    pub timezone: Option<String>,
+    pub digest_frequency: Option<DigestFrequency>,
+    pub digest_hour: Option<i16>,
+    pub digest_weekday: Option<i16>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct NotificationPreferencesResponse {
+    /// The timezone of the user settings
+    pub timezone: String,
+    pub digest_frequency: DigestFrequency,
+    pub digest_hour: i16,
+    pub digest_weekday: i16,
+    pub next_digest_at: Option<DateTime<Utc>>,
+    pub last_digest_sent_at: Option<DateTime<Utc>>,
+    pub updated_at: DateTime<Utc>,
+}
+
+/// Preferences of a user who hasn't changed any: UTC, no digests
+pub fn default_preferences(user_id: Uuid) -> notification_preferences::Model {
+    let now = Utc::now().into();
+    notification_preferences::Model {
+        user_id,
+        timezone: "UTC".to_string(),
+        digest_frequency: DigestFrequency::Off,
+        digest_hour: 7,
+        digest_weekday: 1,
+        next_digest_at: None,
+        last_digest_sent_at: None,
+        created_at: now,
+        updated_at: now,
+    }
+}
+
+impl UpdateNotificationPreferencesRequest {
+    /// Every invalid field is reported, not just the first
+    pub fn apply_to(self, preferences: &mut notification_preferences::Model) -> Result<()> {
+        let mut errors = Vec::new();
+        if let Some(timezone) = self.timezone
+            && let Some(timezone) = validate_timezone("timezone", &timezone, &mut errors)
+        {
+            preferences.timezone = timezone;
+        }
+        if let Some(digest_frequency) = self.digest_frequency {
+            preferences.digest_frequency = digest_frequency;
+        }
+        if let Some(digest_hour) = self.digest_hour {
+            if (0..=23).contains(&digest_hour) {
+                preferences.digest_hour = digest_hour;
+            } else {
+                errors.push(FieldError {
+                    field: "digest_hour".to_string(),
+                    message: "digest_hour must be between 0 and 23".to_string(),
+                });
+            }
+        }
+        if let Some(digest_weekday) = self.digest_weekday {
+            if (1..=7).contains(&digest_weekday) {
+                preferences.digest_weekday = digest_weekday;
+            } else {
+                errors.push(FieldError {
+                    field: "digest_weekday".to_string(),
+                    message: "digest_weekday must be between 1 (Monday) and 7 (Sunday)".to_string(),
+                });
+            }
+        }
+        if !errors.is_empty() {
+            return Err(AppError::InvalidFields(errors));
+        }
+        Ok(())
+    }
+}
+
+/// When the next digest is due after `after`: the next `digest_hour` in `timezone`,
+/// on `digest_weekday` for weekly digests. `None` while digests are off.
+pub fn next_digest_at(preferences: &notification_preferences::Model, timezone: Tz, after: DateTime<Utc>) -> Option<DateTime<Utc>> {
+    if preferences.digest_frequency == DigestFrequency::Off {
+        return None;
+    }
+    let mut day = after.with_timezone(&timezone).date_naive();
+    // A week and a day covers every weekday, including today's hour having passed
+    for _ in 0..=7 {
+        let scheduled = preferences.digest_frequency == DigestFrequency::Daily
+            || day.weekday().number_from_monday() == preferences.digest_weekday as u32;
+        if scheduled {
+            let local = day.and_hms_opt(preferences.digest_hour as u32, 0, 0)?;
+            // An hour skipped by a DST change is sent an hour later
+            let at = timezone
+                .from_local_datetime(&local)
+                .earliest()
+                .or_else(|| timezone.from_local_datetime(&(local + Duration::hours(1))).earliest())
+                .map(|at| at.with_timezone(&Utc));
+            if let Some(at) = at.filter(|at| *at > after) {
+                return Some(at);
+            }
+        }
+        day = day.succ_opt()?;
+    }
+    None
+}
+
+impl From<notification_preferences::Model> for NotificationPreferencesResponse {
+    fn from(preferences: notification_preferences::Model) -> Self {
+        Self {
+            timezone: preferences.timezone,
+            digest_frequency: preferences.digest_frequency,
+            digest_hour: preferences.digest_hour,
+            digest_weekday: preferences.digest_weekday,
+            next_digest_at: preferences.next_digest_at.map(|dt| dt.naive_utc().and_utc()),
+            last_digest_sent_at: preferences.last_digest_sent_at.map(|dt| dt.naive_utc().and_utc()),
+            updated_at: preferences.updated_at.naive_utc().and_utc(),
+        }
+    }
+}
+
+impl Validate for UpdateNotificationPreferencesRequest {}
@@ -0,0 +1,118 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::{
+    entities::{
+        organization_members::{self, OrganizationRole},
+        organizations,
+    },
+    errors::{AppError, FieldError, Result},
+    models::validation::{collect, validate_email, Validate},
+};
+
+pub const MAX_ORGANIZATION_NAME_LENGTH: usize = 100;
+
+#[derive(Debug, Deserialize)]
+pub struct CreateOrganizationRequest {
+    pub name: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct UpdateOrganizationRequest {
+    pub name: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct AddOrganizationMemberRequest {
+    /// Email of the account to add
+    pub email: String,
+    /// Defaults to `member`
+    pub role: Option<OrganizationRole>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct UpdateOrganizationMemberRequest {
+    pub role: OrganizationRole,
+}
+
+/// An organization the user belongs to, with the user's role in it
+#[derive(Debug, Serialize)]
+pub struct OrganizationResponse {
+    pub id: Uuid,
+    pub name: String,
+    pub role: OrganizationRole,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct OrganizationMemberResponse {
+    pub id: Uuid,
+    pub organization_id: Uuid,
+    pub user_id: Uuid,
+    pub email: String,
+    pub role: OrganizationRole,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+/// Trimmed name, between 1 and [`MAX_ORGANIZATION_NAME_LENGTH`] characters
+pub fn normalize_organization_name(name: &str) -> Result<String> {
+    let name = name.trim();
+    if name.is_empty() || name.chars().count() > MAX_ORGANIZATION_NAME_LENGTH {
+        return Err(AppError::invalid_field(
+            "name",
+            format!("name must be between 1 and {} characters", MAX_ORGANIZATION_NAME_LENGTH),
+        ));
+    }
+    Ok(name.to_string())
+}
+
+impl OrganizationResponse {
+    pub fn new(organization: organizations::Model, role: OrganizationRole) -> Self {
+        Self {
+            id: organization.id,
+            name: organization.name,
+            role,
+            created_at: organization.created_at.naive_utc().and_utc(),
+            updated_at: organization.updated_at.naive_utc().and_utc(),
+        }
+    }
+}
+
+impl OrganizationMemberResponse {
+    pub fn new(member: organization_members::Model, email: String) -> Self {
+        Self {
+            id: member.id,
+            organization_id: member.organization_id,
+            user_id: member.user_id,
+            email,
+            role: member.role,
+            created_at: member.created_at.naive_utc().and_utc(),
+            updated_at: member.updated_at.naive_utc().and_utc(),
+        }
+    }
+}
+
+impl Validate for CreateOrganizationRequest {
+    fn validate(&self, errors: &mut Vec<FieldError>) {
+        collect(errors, "name", normalize_organization_name(&self.name).map(drop));
+    }
+}
+
+impl Validate for UpdateOrganizationRequest {
+    fn validate(&self, errors: &mut Vec<FieldError>) {
+        if let Some(name) = &self.name {
+            collect(errors, "name", normalize_organization_name(name).map(drop));
+        }
+    }
+}
+
+impl Validate for AddOrganizationMemberRequest {
+    fn validate(&self, errors: &mut Vec<FieldError>) {
+        validate_email(errors, "email", self.email.trim());
+    }
+}
+
+impl Validate for UpdateOrganizationMemberRequest {}
@@ -0,0 +1,216 @@
+use std::collections::HashMap;
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::models::validation::{field_error, validate_reference, Validate};
+use crate::errors::FieldError;
+use crate::entities::{
+    poll_slots,
+    poll_votes::{self, PollAnswer},
+    scheduling_polls::{self, PollStatus},
+};
+
+/// Upper bound on candidate slots in one poll
+pub const MAX_POLL_SLOTS: usize = 100;
+
+/// Upper bound on ballots in one poll, since anyone with the invite link can vote
+pub const MAX_POLL_VOTES: u64 = 500;
+
+#[derive(Debug, Deserialize)]
+pub struct PollSlotRequest {
+    pub start_time: DateTime<Utc>,
+    pub end_time: DateTime<Utc>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CreatePollRequest {
+    pub encrypted_data: String,
+    pub iv: String,
+    pub salt: String,
+    /// 1 when omitted
+    pub schema_version: Option<i16>,
+    pub slots: Vec<PollSlotRequest>,
+}
+
+/// Pick the winning slot; the event is created from its times and the given encrypted payload
+#[derive(Debug, Deserialize)]
+pub struct FinalizePollRequest {
+    pub slot_id: Uuid,
+    pub calendar_id: Option<Uuid>,
+    pub encrypted_data: String,
+    pub iv: String,
+    pub salt: String,
+    /// 1 when omitted
+    pub schema_version: Option<i16>,
+}
+
+/// An invitee's ballot, sent without an account
+#[derive(Debug, Deserialize)]
+pub struct SubmitVoteRequest {
+    /// Invitee's name, encrypted with the key from the invite link
+    pub encrypted_data: String,
+    pub iv: String,
+    pub salt: String,
+    /// 1 when omitted
+    pub schema_version: Option<i16>,
+    pub answers: HashMap<Uuid, PollAnswer>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct PollSlotResponse {
+    pub id: Uuid,
+    pub start_time: DateTime<Utc>,
+    pub end_time: DateTime<Utc>,
+    pub yes: i64,
+    pub maybe: i64,
+    pub no: i64,
+}
+
+#[derive(Debug, Serialize)]
+pub struct PollVoteResponse {
+    pub id: Uuid,
+    pub poll_id: Uuid,
+    pub encrypted_data: String,
+    pub iv: String,
+    pub salt: String,
+    pub schema_version: i16,
+    pub answers: HashMap<Uuid, PollAnswer>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+/// A poll as its organizer sees it, with per-slot tallies and every ballot
+#[derive(Debug, Serialize)]
+pub struct PollResponse {
+    pub id: Uuid,
+    pub user_id: Uuid,
+    pub encrypted_data: String,
+    pub iv: String,
+    pub salt: String,
+    pub schema_version: i16,
+    pub public_token: String,
+    pub status: PollStatus,
+    pub finalized_slot_id: Option<Uuid>,
+    pub event_id: Option<Uuid>,
+    pub slots: Vec<PollSlotResponse>,
+    pub votes: Vec<PollVoteResponse>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+/// A poll as invitees see it through the invite link
+#[derive(Debug, Serialize)]
+pub struct PublicPollResponse {
+    pub id: Uuid,
+    pub encrypted_data: String,
+    pub iv: String,
+    pub salt: String,
+    pub schema_version: i16,
+    pub status: PollStatus,
+    pub finalized_slot_id: Option<Uuid>,
+    pub slots: Vec<PollSlotResponse>,
+    pub votes: Vec<PollVoteResponse>,
+}
+
+impl PollSlotResponse {
+    /// Slot with its answer counts over the given ballots
+    pub fn new(slot: poll_slots::Model, votes: &[PollVoteResponse]) -> Self {
+        let count = |answer: PollAnswer| {
+            votes.iter().filter(|vote| vote.answers.get(&slot.id) == Some(&answer)).count() as i64
+        };
+        Self {
+            id: slot.id,
+            yes: count(PollAnswer::Yes),
+            maybe: count(PollAnswer::Maybe),
+            no: count(PollAnswer::No),
+            start_time: slot.start_time.naive_utc().and_utc(),
+            end_time: slot.end_time.naive_utc().and_utc(),
+        }
+    }
+}
+
+impl From<poll_votes::Model> for PollVoteResponse {
+    fn from(vote: poll_votes::Model) -> Self {
+        Self {
+            id: vote.id,
+            poll_id: vote.poll_id,
+            encrypted_data: vote.encrypted_data,
+            iv: vote.iv,
+            salt: vote.salt,
+            schema_version: vote.schema_version,
+            answers: serde_json::from_value(vote.answers).unwrap_or_default(),
+            created_at: vote.created_at.naive_utc().and_utc(),
+            updated_at: vote.updated_at.naive_utc().and_utc(),
+        }
+    }
+}
+
+impl PollResponse {
+    pub fn new(poll: scheduling_polls::Model, slots: Vec<poll_slots::Model>, votes: Vec<poll_votes::Model>) -> Self {
+        let votes: Vec<PollVoteResponse> = votes.into_iter().map(Into::into).collect();
+        Self {
+            id: poll.id,
+            user_id: poll.user_id,
+            encrypted_data: poll.encrypted_data,
+            iv: poll.iv,
+            salt: poll.salt,
+            schema_version: poll.schema_version,
+            public_token: poll.public_token,
+            status: poll.status,
+            finalized_slot_id: poll.finalized_slot_id,
+            event_id: poll.event_id,
+            slots: slots.into_iter().map(|slot| PollSlotResponse::new(slot, &votes)).collect(),
+            votes,
+            created_at: poll.created_at.naive_utc().and_utc(),
+            updated_at: poll.updated_at.naive_utc().and_utc(),
+        }
+    }
+}
+
+impl From<PollResponse> for PublicPollResponse {
+    fn from(poll: PollResponse) -> Self {
+        Self {
+            id: poll.id,
+            encrypted_data: poll.encrypted_data,
+            iv: poll.iv,
+            salt: poll.salt,
+            schema_version: poll.schema_version,
+            status: poll.status,
+            finalized_slot_id: poll.finalized_slot_id,
+            slots: poll.slots,
+            votes: poll.votes,
+        }
+    }
+}
+
+impl Validate for CreatePollRequest {
+    fn validate(&self, errors: &mut Vec<FieldError>) {
+        if self.slots.is_empty() {
+            field_error(errors, "slots", "A poll needs at least one slot");
+        } else if self.slots.len() > MAX_POLL_SLOTS {
+            field_error(errors, "slots", format!("A poll can have at most {} slots", MAX_POLL_SLOTS));
+            return;
+        }
+        for (index, slot) in self.slots.iter().enumerate() {
+            if slot.end_time <= slot.start_time {
+                field_error(errors, &format!("slots[{}].end_time", index), "end_time must be after start_time");
+            }
+        }
+    }
+}
+
+impl Validate for FinalizePollRequest {
+    fn validate(&self, errors: &mut Vec<FieldError>) {
+        validate_reference(errors, "slot_id", self.slot_id);
+    }
+}
+
+impl Validate for SubmitVoteRequest {
+    fn validate(&self, errors: &mut Vec<FieldError>) {
+        if self.answers.is_empty() {
+            field_error(errors, "answers", "answers must not be empty");
+        }
+    }
+}
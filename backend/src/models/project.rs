@@ -1,43 +1,78 @@
-use chrono::{DateTime, Utc};
-use serde::{Deserialize, Serialize};
 use uuid::Uuid;
+use sea_orm::{ActiveModelBehavior, Set};
+use streamline_models::DEFAULT_SCHEMA_VERSION;
+use super::{
+    search::{join_search_tokens, validate_search_tokens},
+    validate_client_id,
+    validation::{collect, validate_display_order, Validate},
+    ApplyTo, IntoRecord,
+};
+use crate::errors::FieldError;
 use crate::entities::projects;
 
+pub use streamline_models::project::{
+    CreateProjectRequest, DuplicateProjectResponse, MoveProjectRequest, ProjectResponse, UpdateProjectRequest,
+};
 
-#[derive(Debug, Deserialize)]
-pub struct CreateProjectRequest {
-    pub encrypted_data: String,
-    pub iv: String,
-    pub salt: String,
-    pub parent_id: Option<Uuid>,
-    pub display_order: Option<i32>,
-    pub is_collapsed: Option<bool>,
-}
-
-#[derive(Debug, Deserialize)]
-pub struct UpdateProjectRequest {
-    pub encrypted_data: Option<String>,
-    pub iv: Option<String>,
-    pub salt: Option<String>,
-    pub is_default: Option<bool>,
-    pub parent_id: Option<Uuid>,
-    pub display_order: Option<i32>,
-    pub is_collapsed: Option<bool>,
+impl IntoRecord<projects::ActiveModel> for CreateProjectRequest {
+    fn into_active_model(self, user_id: Uuid) -> projects::ActiveModel {
+        let mut project_active = projects::ActiveModel::new();
+        if let Some(id) = self.id {
+            project_active.id = Set(id);
+        }
+        project_active.user_id = Set(user_id);
+        project_active.encrypted_data = Set(self.encrypted_data);
+        project_active.iv = Set(self.iv);
+        project_active.salt = Set(self.salt);
+        project_active.schema_version = Set(self.schema_version.unwrap_or(DEFAULT_SCHEMA_VERSION));
+        project_active.parent_id = Set(self.parent_id);
+        project_active.display_order = Set(self.display_order.unwrap_or(0));
+        project_active.is_collapsed = Set(self.is_collapsed.unwrap_or(false));
+        project_active.is_pinned = Set(self.is_pinned.unwrap_or(false));
+        project_active.organization_id = Set(self.organization_id);
+        project_active.search_tokens = Set(self.search_tokens.map(join_search_tokens));
+        project_active
+    }
 }
 
-#[derive(Debug, Serialize)]
-pub struct ProjectResponse {
-    pub id: Uuid,
-    pub user_id: Uuid,
-    pub encrypted_data: String,
-    pub iv: String,
-    pub salt: String,
-    pub is_default: bool,
-    pub parent_id: Option<Uuid>,
-    pub display_order: i32,
-    pub is_collapsed: bool,
-    pub created_at: DateTime<Utc>,
-    pub updated_at: DateTime<Utc>,
+impl ApplyTo<projects::ActiveModel> for UpdateProjectRequest {
+    fn apply_to(self, project_active: &mut projects::ActiveModel) {
+        // A new payload without a version comes from a client predating versioning
+        let schema_version = self.schema_version.or(self.encrypted_data.is_some().then_some(DEFAULT_SCHEMA_VERSION));
+        if let Some(encrypted_data) = self.encrypted_data {
+            project_active.encrypted_data = Set(encrypted_data);
+        }
+        if let Some(iv) = self.iv {
+            project_active.iv = Set(iv);
+        }
+        if let Some(salt) = self.salt {
+            project_active.salt = Set(salt);
+        }
+        if let Some(schema_version) = schema_version {
+            project_active.schema_version = Set(schema_version);
+        }
+        if let Some(is_default) = self.is_default {
+            project_active.is_default = Set(is_default);
+        }
+        if let Some(parent_id) = self.parent_id {
+            project_active.parent_id = Set(parent_id);
+        }
+        if let Some(display_order) = self.display_order {
+            project_active.display_order = Set(display_order);
+        }
+        if let Some(is_collapsed) = self.is_collapsed {
+            project_active.is_collapsed = Set(is_collapsed);
+        }
+        if let Some(is_pinned) = self.is_pinned {
+            project_active.is_pinned = Set(is_pinned);
+        }
+        if let Some(organization_id) = self.organization_id {
+            project_active.organization_id = Set(organization_id);
+        }
+        if let Some(search_tokens) = self.search_tokens {
+            project_active.search_tokens = Set(search_tokens.map(join_search_tokens));
+        }
+    }
 }
 
 impl From<projects::Model> for ProjectResponse {
@@ -48,12 +83,33 @@ impl From<projects::Model> for ProjectResponse {
             encrypted_data: project.encrypted_data,
             iv: project.iv,
             salt: project.salt,
+            schema_version: project.schema_version,
             is_default: project.is_default,
             parent_id: project.parent_id,
             display_order: project.display_order,
             is_collapsed: project.is_collapsed,
+            is_pinned: project.is_pinned,
+            organization_id: project.organization_id,
             created_at: project.created_at.naive_utc().and_utc(),
             updated_at: project.updated_at.naive_utc().and_utc(),
+            deleted_at: project.deleted_at.map(|dt| dt.naive_utc().and_utc()),
         }
     }
 }
+
+impl Validate for CreateProjectRequest {
+    fn validate(&self, errors: &mut Vec<FieldError>) {
+        collect(errors, "id", validate_client_id(self.id));
+        validate_display_order(errors, self.display_order);
+        collect(errors, "search_tokens", validate_search_tokens(self.search_tokens.as_deref()));
+    }
+}
+
+impl Validate for UpdateProjectRequest {
+    fn validate(&self, errors: &mut Vec<FieldError>) {
+        validate_display_order(errors, self.display_order);
+        collect(errors, "search_tokens", validate_search_tokens(self.search_tokens.as_ref().and_then(Option::as_deref)));
+    }
+}
+
+impl Validate for MoveProjectRequest {}
@@ -0,0 +1,63 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::{entities::project_members::{self, ProjectRole}, errors::FieldError};
+use super::{
+    project::ProjectResponse,
+    validation::{validate_email, Validate},
+};
+
+#[derive(Debug, Deserialize)]
+pub struct AddMemberRequest {
+    /// Email of the account to share the project with
+    pub email: String,
+    /// Defaults to `viewer`
+    pub role: Option<ProjectRole>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct UpdateMemberRequest {
+    pub role: ProjectRole,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ProjectMemberResponse {
+    pub id: Uuid,
+    pub project_id: Uuid,
+    pub user_id: Uuid,
+    pub email: String,
+    pub role: ProjectRole,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+/// A project shared with the user, with the user's role in it
+#[derive(Debug, Serialize)]
+pub struct SharedProjectResponse {
+    #[serde(flatten)]
+    pub project: ProjectResponse,
+    pub role: ProjectRole,
+}
+
+impl ProjectMemberResponse {
+    pub fn new(member: project_members::Model, email: String) -> Self {
+        Self {
+            id: member.id,
+            project_id: member.project_id,
+            user_id: member.user_id,
+            email,
+            role: member.role,
+            created_at: member.created_at.naive_utc().and_utc(),
+            updated_at: member.updated_at.naive_utc().and_utc(),
+        }
+    }
+}
+
+impl Validate for AddMemberRequest {
+    fn validate(&self, errors: &mut Vec<FieldError>) {
+        validate_email(errors, "email", self.email.trim());
+    }
+}
+
+impl Validate for UpdateMemberRequest {}
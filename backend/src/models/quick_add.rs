@@ -0,0 +1,60 @@
+use chrono::{DateTime, NaiveDate, NaiveTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    entities::can_do_list::TaskPriority,
+    errors::FieldError,
+    models::validation::{field_error, Validate},
+};
+
+/// Longest quick-add line accepted, in characters
+pub const MAX_QUICK_ADD_LENGTH: usize = 500;
+
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct QuickAddRequest {
+    pub text: String,
+    /// Language of `text` as a BCP 47 tag such as `de-AT`; English when omitted or not supported
+    pub locale: Option<String>,
+}
+
+impl Validate for QuickAddRequest {
+    fn validate(&self, errors: &mut Vec<FieldError>) {
+        if self.text.trim().is_empty() {
+            field_error(errors, "text", "text must not be empty");
+        } else if self.text.chars().count() > MAX_QUICK_ADD_LENGTH {
+            field_error(errors, "text", format!("text must be at most {} characters", MAX_QUICK_ADD_LENGTH));
+        }
+    }
+}
+
+/// What a quick-add line most likely describes
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum QuickAddKind {
+    #[default]
+    Task,
+    /// Lines with a time of day, such as "Dentist tomorrow 3pm"
+    Event,
+}
+
+/// A quick-add line taken apart; the client encrypts the title and creates the record itself
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct QuickAddResponse {
+    /// The text left over once the recognised parts are taken out
+    pub title: String,
+    pub kind: QuickAddKind,
+    /// Local date, also set when only a time was given
+    pub date: Option<NaiveDate>,
+    /// Local time of day
+    pub time: Option<NaiveTime>,
+    /// `date` and `time` in the user's timezone, when a time was given
+    pub at: Option<DateTime<Utc>>,
+    pub duration_minutes: Option<i64>,
+    /// Name after `/`, for the client to match against its decrypted project names
+    pub project_hint: Option<String>,
+    /// Names after `#`, in order and without duplicates
+    pub tags: Vec<String>,
+    pub priority: Option<TaskPriority>,
+    /// Language the text was read in
+    pub locale: &'static str,
+}
@@ -0,0 +1,61 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use super::{calendar_event::UpdateCalendarEventRequest, validation::Validate};
+use crate::errors::FieldError;
+use crate::entities::calendar_events::EventStatus;
+
+#[derive(Debug, Deserialize)]
+pub struct OccurrenceQuery {
+    pub start: DateTime<Utc>,
+    pub end: DateTime<Utc>,
+    pub calendar_id: Option<Uuid>,
+    /// Comma-separated statuses to include
+    pub status: Option<String>,
+}
+
+/// One slot on the timeline; clients decrypt the details from `event_id`
+#[derive(Debug, Serialize)]
+pub struct OccurrenceResponse {
+    /// The record holding this occurrence's data: the series itself or an override
+    pub event_id: Uuid,
+    pub start_time: DateTime<Utc>,
+    pub end_time: Option<DateTime<Utc>>,
+    /// The series this occurrence belongs to, if any
+    pub recurring_event_id: Option<Uuid>,
+    /// Where the series originally placed this occurrence
+    pub original_start_time: Option<DateTime<Utc>>,
+    pub status: EventStatus,
+}
+
+/// Which part of a series an edit applies to
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum OccurrenceScope {
+    This,
+    ThisAndFuture,
+    All,
+}
+
+/// Changes to a series as seen from one of its occurrences. Times are the new
+/// times of that occurrence; other occurrences in scope move by the same offset.
+#[derive(Debug, Deserialize)]
+pub struct UpdateOccurrenceRequest {
+    pub occurrence_start: DateTime<Utc>,
+    pub scope: OccurrenceScope,
+    #[serde(flatten)]
+    pub changes: UpdateCalendarEventRequest,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct DeleteOccurrenceQuery {
+    pub occurrence_start: DateTime<Utc>,
+    pub scope: OccurrenceScope,
+}
+
+impl Validate for UpdateOccurrenceRequest {
+    fn validate(&self, errors: &mut Vec<FieldError>) {
+        self.changes.validate(errors);
+    }
+}
@@ -0,0 +1,71 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::{
+    entities::reminders,
+    errors::FieldError,
+    models::validation::{field_error, validate_reference, Validate},
+};
+
+/// Longest offset a reminder may have before its event, four weeks
+pub const MAX_REMINDER_OFFSET_MINUTES: i32 = 4 * 7 * 24 * 60;
+
+#[derive(Debug, Deserialize)]
+pub struct CreateReminderRequest {
+    pub event_id: Uuid,
+    /// Minutes before each occurrence of the event; exclusive with `remind_at`
+    pub offset_minutes: Option<i32>,
+    /// Absolute time to fire once; exclusive with `offset_minutes`
+    pub remind_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ReminderQuery {
+    pub event_id: Option<Uuid>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ReminderResponse {
+    pub id: Uuid,
+    pub user_id: Uuid,
+    pub event_id: Uuid,
+    pub offset_minutes: Option<i32>,
+    pub remind_at: Option<DateTime<Utc>>,
+    /// When the reminder fires next; `null` once nothing is left to fire
+    pub fire_at: Option<DateTime<Utc>>,
+    pub last_fired_at: Option<DateTime<Utc>>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+impl From<reminders::Model> for ReminderResponse {
+    fn from(reminder: reminders::Model) -> Self {
+        Self {
+            id: reminder.id,
+            user_id: reminder.user_id,
+            event_id: reminder.event_id,
+            offset_minutes: reminder.offset_minutes,
+            remind_at: reminder.remind_at.map(|dt| dt.naive_utc().and_utc()),
+            fire_at: reminder.fire_at.map(|dt| dt.naive_utc().and_utc()),
+            last_fired_at: reminder.last_fired_at.map(|dt| dt.naive_utc().and_utc()),
+            created_at: reminder.created_at.naive_utc().and_utc(),
+            updated_at: reminder.updated_at.naive_utc().and_utc(),
+        }
+    }
+}
+
+impl Validate for CreateReminderRequest {
+    fn validate(&self, errors: &mut Vec<FieldError>) {
+        validate_reference(errors, "event_id", self.event_id);
+        match (self.offset_minutes, self.remind_at) {
+            (Some(offset), None) if !(0..=MAX_REMINDER_OFFSET_MINUTES).contains(&offset) => field_error(
+                errors,
+                "offset_minutes",
+                format!("offset_minutes must be between 0 and {}", MAX_REMINDER_OFFSET_MINUTES),
+            ),
+            (Some(_), None) | (None, Some(_)) => {}
+            _ => field_error(errors, "offset_minutes", "Exactly one of offset_minutes and remind_at is required"),
+        }
+    }
+}
@@ -0,0 +1,133 @@
+use chrono::{DateTime, NaiveDate, Utc, Weekday};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::{
+    errors::{AppError, Result},
+    models::time_entry::ProjectTimeTotal,
+};
+
+/// Days covered when `range` is omitted
+pub const DEFAULT_REPORT_DAYS: i64 = 30;
+/// Widest report, the same as the occurrence window events are expanded in
+pub const MAX_REPORT_DAYS: i64 = 366;
+
+#[derive(Debug, Deserialize)]
+pub struct ReportQuery {
+    /// `week`, `month`, `quarter`, `year` or a number of days such as `14d`; 30 days when omitted
+    pub range: Option<String>,
+}
+
+impl ReportQuery {
+    /// Number of days the report covers, today included
+    pub fn days(&self) -> Result<i64> {
+        let Some(range) = self.range.as_deref().map(str::trim) else {
+            return Ok(DEFAULT_REPORT_DAYS);
+        };
+        let days = match range {
+            "week" => 7,
+            "month" => 30,
+            "quarter" => 90,
+            "year" => 365,
+            other => other
+                .strip_suffix('d')
+                .and_then(|days| days.parse::<i64>().ok())
+                .ok_or_else(|| AppError::invalid_field("range", "range must be week, month, quarter, year or a number of days like 14d"))?,
+        };
+        if !(1..=MAX_REPORT_DAYS).contains(&days) {
+            return Err(AppError::invalid_field(
+                "range",
+                format!("range must cover between 1 and {} days", MAX_REPORT_DAYS),
+            ));
+        }
+        Ok(days)
+    }
+}
+
+/// Activity over the report window, computed from plaintext metadata only; days are UTC days
+#[derive(Debug, Serialize)]
+pub struct ReportSummary {
+    /// Start of the first day in the report
+    pub start: DateTime<Utc>,
+    /// When the report was computed
+    pub end: DateTime<Utc>,
+    /// One entry per day in the window, oldest first, days without completions included
+    pub tasks_completed: Vec<DailyCount>,
+    /// Event occurrences per calendar, busiest first; cancelled events are left out
+    pub events_per_calendar: Vec<CalendarEventCount>,
+    /// Monday through Sunday
+    pub busiest_weekdays: Vec<WeekdayActivity>,
+    pub time_tracked: TimeTracked,
+    /// Time in focus sessions; breaks and cancelled sessions are left out
+    pub focus: FocusTime,
+}
+
+#[derive(Debug, Serialize)]
+pub struct DailyCount {
+    pub date: NaiveDate,
+    pub count: u64,
+}
+
+#[derive(Debug, Serialize)]
+pub struct CalendarEventCount {
+    /// `null` for events without a calendar
+    pub calendar_id: Option<Uuid>,
+    pub events: u64,
+    /// Scheduled time of the events with an end time
+    pub minutes: i64,
+}
+
+#[derive(Debug, Serialize)]
+pub struct WeekdayActivity {
+    #[serde(serialize_with = "serialize_weekday")]
+    pub weekday: Weekday,
+    pub events: u64,
+    pub event_minutes: i64,
+    pub tasks_completed: u64,
+}
+
+#[derive(Debug, Serialize)]
+pub struct TimeTracked {
+    pub total_seconds: i64,
+    /// One entry per day in the window, oldest first; running timers count up to now
+    pub per_day: Vec<DailySeconds>,
+    /// The same totals as `GET /api/time-entries/totals` for the window
+    pub per_project: Vec<ProjectTimeTotal>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct DailySeconds {
+    pub date: NaiveDate,
+    pub seconds: i64,
+}
+
+#[derive(Debug, Serialize)]
+pub struct FocusTime {
+    pub total_seconds: i64,
+    /// Sessions started in the window and stopped rather than cancelled
+    pub sessions_completed: u64,
+    /// One entry per day in the window, oldest first; a running session counts up to now
+    pub per_day: Vec<DailyFocus>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct DailyFocus {
+    pub date: NaiveDate,
+    pub seconds: i64,
+    /// Completed sessions started that day
+    pub sessions: u64,
+}
+
+/// Lowercase English names, e.g. `monday`
+fn serialize_weekday<S: serde::Serializer>(weekday: &Weekday, serializer: S) -> std::result::Result<S::Ok, S::Error> {
+    let name = match weekday {
+        Weekday::Mon => "monday",
+        Weekday::Tue => "tuesday",
+        Weekday::Wed => "wednesday",
+        Weekday::Thu => "thursday",
+        Weekday::Fri => "friday",
+        Weekday::Sat => "saturday",
+        Weekday::Sun => "sunday",
+    };
+    serializer.serialize_str(name)
+}
@@ -0,0 +1,184 @@
+use chrono::{DateTime, Utc};
+use sea_orm::Set;
+use serde::{de::DeserializeOwned, Serialize};
+use uuid::Uuid;
+
+use crate::{
+    entities::{
+        calendar_events, calendars, can_do_list, notes, projects,
+        record_revisions::{self, RevisionAction},
+    },
+    errors::{AppError, Result},
+};
+
+/// Revisions kept per record when `RECORD_HISTORY_LIMIT` isn't set
+pub const DEFAULT_HISTORY_LIMIT: u64 = 50;
+
+/// Records whose prior versions are kept in `record_revisions`
+pub trait Revisioned: Clone + Serialize + DeserializeOwned {
+    const TABLE: &'static str;
+    type ActiveModel;
+
+    fn id(&self) -> Uuid;
+    fn user_id(&self) -> Uuid;
+
+    /// What a revision stores: the record as it is in the database, encrypted payload included
+    fn snapshot(&self) -> Result<serde_json::Value> {
+        Ok(serde_json::to_value(self)?)
+    }
+
+    /// Copy a prior version's content onto the record: its encrypted payload and the plaintext
+    /// fields that describe it. Where the record sits (parent, project, calendar, order, sharing) is kept.
+    fn revert(active: &mut Self::ActiveModel, prior: Self);
+}
+
+impl Revisioned for projects::Model {
+    const TABLE: &'static str = "projects";
+    type ActiveModel = projects::ActiveModel;
+
+    fn id(&self) -> Uuid {
+        self.id
+    }
+
+    fn user_id(&self) -> Uuid {
+        self.user_id
+    }
+
+    fn revert(project: &mut projects::ActiveModel, prior: Self) {
+        project.encrypted_data = Set(prior.encrypted_data);
+        project.iv = Set(prior.iv);
+        project.salt = Set(prior.salt);
+        project.schema_version = Set(prior.schema_version);
+        project.search_tokens = Set(prior.search_tokens);
+    }
+}
+
+impl Revisioned for can_do_list::Model {
+    const TABLE: &'static str = "can_do_list";
+    type ActiveModel = can_do_list::ActiveModel;
+
+    fn id(&self) -> Uuid {
+        self.id
+    }
+
+    fn user_id(&self) -> Uuid {
+        self.user_id
+    }
+
+    /// Leaves out the plaintext of a task created by email, so history stays encrypted
+    fn snapshot(&self) -> Result<serde_json::Value> {
+        Ok(serde_json::to_value(can_do_list::Model { pending_plaintext: None, ..self.clone() })?)
+    }
+
+    fn revert(item: &mut can_do_list::ActiveModel, prior: Self) {
+        item.encrypted_data = Set(prior.encrypted_data);
+        item.iv = Set(prior.iv);
+        item.salt = Set(prior.salt);
+        item.schema_version = Set(prior.schema_version);
+        item.search_bloom = Set(prior.search_bloom);
+        item.search_tokens = Set(prior.search_tokens);
+        item.due_at = Set(prior.due_at);
+        item.scheduled_at = Set(prior.scheduled_at);
+        item.completed_at = Set(prior.completed_at);
+        item.rrule = Set(prior.rrule);
+        item.priority = Set(prior.priority);
+        item.estimated_minutes = Set(prior.estimated_minutes);
+    }
+}
+
+impl Revisioned for calendars::Model {
+    const TABLE: &'static str = "calendars";
+    type ActiveModel = calendars::ActiveModel;
+
+    fn id(&self) -> Uuid {
+        self.id
+    }
+
+    fn user_id(&self) -> Uuid {
+        self.user_id
+    }
+
+    fn revert(calendar: &mut calendars::ActiveModel, prior: Self) {
+        calendar.encrypted_data = Set(prior.encrypted_data);
+        calendar.iv = Set(prior.iv);
+        calendar.salt = Set(prior.salt);
+        calendar.schema_version = Set(prior.schema_version);
+        calendar.color = Set(prior.color);
+    }
+}
+
+impl Revisioned for calendar_events::Model {
+    const TABLE: &'static str = "calendar_events";
+    type ActiveModel = calendar_events::ActiveModel;
+
+    fn id(&self) -> Uuid {
+        self.id
+    }
+
+    fn user_id(&self) -> Uuid {
+        self.user_id
+    }
+
+    fn revert(event: &mut calendar_events::ActiveModel, prior: Self) {
+        event.encrypted_data = Set(prior.encrypted_data);
+        event.iv = Set(prior.iv);
+        event.salt = Set(prior.salt);
+        event.schema_version = Set(prior.schema_version);
+        event.search_bloom = Set(prior.search_bloom);
+        event.search_tokens = Set(prior.search_tokens);
+        event.start_time = Set(prior.start_time);
+        event.end_time = Set(prior.end_time);
+        event.rrule = Set(prior.rrule);
+        event.recurrence_exceptions = Set(prior.recurrence_exceptions);
+        event.status = Set(prior.status);
+    }
+}
+
+impl Revisioned for notes::Model {
+    const TABLE: &'static str = "notes";
+    type ActiveModel = notes::ActiveModel;
+
+    fn id(&self) -> Uuid {
+        self.id
+    }
+
+    fn user_id(&self) -> Uuid {
+        self.user_id
+    }
+
+    fn revert(note: &mut notes::ActiveModel, prior: Self) {
+        note.encrypted_data = Set(prior.encrypted_data);
+        note.iv = Set(prior.iv);
+        note.salt = Set(prior.salt);
+        note.schema_version = Set(prior.schema_version);
+    }
+}
+
+/// A prior version of a record, newest first in listings
+#[derive(Debug, Serialize)]
+pub struct RevisionResponse<T> {
+    pub id: Uuid,
+    pub record_id: Uuid,
+    /// `null` once the user who made the change has deleted their account
+    pub actor_id: Option<Uuid>,
+    /// The change that replaced this version
+    pub action: RevisionAction,
+    /// The record as it was before `action`, shaped like the resource's own responses
+    pub record: T,
+    pub created_at: DateTime<Utc>,
+}
+
+impl<T> RevisionResponse<T> {
+    pub fn new<M: Revisioned + Into<T>>(revision: record_revisions::Model) -> Result<Self> {
+        let record: M = serde_json::from_value(revision.snapshot)
+            .map_err(|e| AppError::Internal(format!("Unreadable revision {}: {}", revision.id, e)))?;
+        Ok(Self {
+            id: revision.id,
+            record_id: revision.record_id,
+            actor_id: revision.actor_id,
+            action: revision.action,
+            record: record.into(),
+            created_at: revision.created_at.naive_utc().and_utc(),
+        })
+    }
+}
@@ -0,0 +1,201 @@
+use chrono::{DateTime, Duration, NaiveTime, Utc};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::{
+    errors::{AppError, FieldError, Result},
+    models::{
+        calendar_event::CalendarEventResponse,
+        can_do_list::CanDoItemResponse,
+        search::validate_search_tokens,
+        user_settings::iso_weekday,
+        validate_schema_version,
+        validation::{collect, field_error, Validate},
+    },
+    scheduler::WeeklyWindow,
+};
+
+/// Days planned when `end` is omitted
+pub const DEFAULT_PLAN_DAYS: i64 = 7;
+/// Widest window one plan covers
+pub const MAX_PLAN_DAYS: i64 = 31;
+/// Most tasks one plan considers
+pub const MAX_PLAN_TASKS: u64 = 500;
+/// Length of tasks without an estimate
+pub const DEFAULT_TASK_MINUTES: i32 = 30;
+/// Shortest block a task is split into
+pub const DEFAULT_MIN_BLOCK_MINUTES: i32 = 15;
+/// Most blocks applied at once
+pub const MAX_APPLY_BLOCKS: usize = 200;
+
+#[derive(Debug, Deserialize)]
+pub struct PlanScheduleRequest {
+    /// Defaults to now
+    pub start: Option<DateTime<Utc>>,
+    /// Defaults to a week after `start`
+    pub end: Option<DateTime<Utc>>,
+    /// Tasks to plan; by default the user's open tasks that aren't scheduled yet
+    pub task_ids: Option<Vec<Uuid>>,
+    /// Overrides the working hours of the user's scheduling constraints for this plan
+    pub working_hours: Option<WorkingHoursRequest>,
+    /// Minutes planned for tasks without `estimated_minutes`
+    pub default_minutes: Option<i32>,
+    /// Shortest block a task is split into when it doesn't fit in one piece
+    pub min_block_minutes: Option<i32>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct WorkingHoursRequest {
+    pub start: NaiveTime,
+    pub end: NaiveTime,
+    /// ISO weekdays: 1 is Monday, 7 is Sunday
+    #[serde(default = "default_workdays")]
+    pub weekdays: Vec<i16>,
+}
+
+fn default_workdays() -> Vec<i16> {
+    (1..=5).collect()
+}
+
+impl WorkingHoursRequest {
+    /// The same hours on each of the weekdays
+    pub fn parse(&self) -> Result<Vec<WeeklyWindow>> {
+        if self.end <= self.start {
+            return Err(AppError::invalid_field("working_hours", "Working hours must end after they start"));
+        }
+        self.weekdays
+            .iter()
+            .map(|&weekday| iso_weekday(weekday).map(|weekday| WeeklyWindow { weekday, start: self.start, end: self.end }))
+            .collect::<Option<_>>()
+            .ok_or_else(|| AppError::invalid_field("working_hours", "weekdays must be between 1 (Monday) and 7 (Sunday)"))
+    }
+}
+
+fn validate_minutes(field: &str, value: i32) -> Result<Duration> {
+    if !(1..=24 * 60).contains(&value) {
+        return Err(AppError::invalid_field(field, format!("{} must be between 1 and {}", field, 24 * 60)));
+    }
+    Ok(Duration::minutes(value as i64))
+}
+
+impl PlanScheduleRequest {
+    /// The window to plan in, starting no earlier than `now`
+    pub fn window(&self, now: DateTime<Utc>) -> Result<(DateTime<Utc>, DateTime<Utc>)> {
+        let start = self.start.unwrap_or(now).max(now);
+        let end = self.end.unwrap_or(start + Duration::days(DEFAULT_PLAN_DAYS));
+        if end <= start {
+            return Err(AppError::invalid_field("end", "end must be after start and in the future"));
+        }
+        if end - start > Duration::days(MAX_PLAN_DAYS) {
+            return Err(AppError::invalid_field("end", format!("A plan must not cover more than {} days", MAX_PLAN_DAYS)));
+        }
+        Ok((start, end))
+    }
+
+    pub fn default_duration(&self) -> Result<Duration> {
+        validate_minutes("default_minutes", self.default_minutes.unwrap_or(DEFAULT_TASK_MINUTES))
+    }
+
+    pub fn min_block(&self) -> Result<Duration> {
+        validate_minutes("min_block_minutes", self.min_block_minutes.unwrap_or(DEFAULT_MIN_BLOCK_MINUTES))
+    }
+}
+
+impl Validate for PlanScheduleRequest {
+    fn validate(&self, errors: &mut Vec<FieldError>) {
+        collect(errors, "default_minutes", self.default_duration().map(drop));
+        collect(errors, "min_block_minutes", self.min_block().map(drop));
+        if let Some(hours) = &self.working_hours {
+            collect(errors, "working_hours", hours.parse().map(drop));
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct PlanResponse {
+    pub start: DateTime<Utc>,
+    pub end: DateTime<Utc>,
+    /// Timezone the working hours were read in
+    pub timezone: String,
+    /// Proposed blocks in time order; a task that was split has several
+    pub blocks: Vec<PlannedBlock>,
+    /// Tasks that didn't fit into the window
+    pub unscheduled: Vec<UnscheduledTask>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct PlannedBlock {
+    pub task_id: Uuid,
+    pub start_time: DateTime<Utc>,
+    pub end_time: DateTime<Utc>,
+    /// The block ends after the task is due
+    pub after_due: bool,
+}
+
+#[derive(Debug, Serialize)]
+pub struct UnscheduledTask {
+    pub task_id: Uuid,
+    pub minutes: i64,
+}
+
+/// Turn planned blocks into calendar events; each event payload is encrypted by the client
+#[derive(Debug, Deserialize)]
+pub struct ApplyScheduleRequest {
+    /// Calendar the events go into; none when omitted
+    pub calendar_id: Option<Uuid>,
+    pub blocks: Vec<ScheduleBlockRequest>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ScheduleBlockRequest {
+    pub task_id: Uuid,
+    pub start_time: DateTime<Utc>,
+    pub end_time: DateTime<Utc>,
+    pub encrypted_data: String,
+    pub iv: String,
+    pub salt: String,
+    /// 1 when omitted
+    pub schema_version: Option<i16>,
+    /// Blind-index tokens of the event, see `GET /api/search`
+    pub search_tokens: Option<Vec<String>>,
+}
+
+impl Validate for ApplyScheduleRequest {
+    fn validate(&self, errors: &mut Vec<FieldError>) {
+        if self.blocks.is_empty() {
+            field_error(errors, "blocks", "At least one block is required");
+        } else if self.blocks.len() > MAX_APPLY_BLOCKS {
+            field_error(errors, "blocks", format!("At most {} blocks can be applied at once", MAX_APPLY_BLOCKS));
+            return;
+        }
+        for (index, block) in self.blocks.iter().enumerate() {
+            if block.end_time <= block.start_time {
+                field_error(errors, &format!("blocks[{}].end_time", index), "Each block must end after it starts");
+            }
+            collect(
+                errors,
+                &format!("blocks[{}].schema_version", index),
+                validate_schema_version("calendar_events", block.schema_version),
+            );
+            collect(
+                errors,
+                &format!("blocks[{}].search_tokens", index),
+                validate_search_tokens(block.search_tokens.as_deref()),
+            );
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct ApplyScheduleResponse {
+    /// The created events, in the order of the request's blocks
+    pub events: Vec<ScheduledEvent>,
+    /// The tasks with `scheduled_at` moved to their first block
+    pub tasks: Vec<CanDoItemResponse>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ScheduledEvent {
+    pub task_id: Uuid,
+    pub event: CalendarEventResponse,
+}
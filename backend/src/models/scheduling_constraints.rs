@@ -0,0 +1,126 @@
+use chrono::{DateTime, NaiveTime, Utc};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use super::double_option;
+use crate::{
+    entities::scheduling_constraints,
+    errors::{AppError, Result},
+    models::{
+        booking::{validate_windows, AvailabilityWindow},
+        user_settings::iso_weekday,
+        validation::Validate,
+    },
+    scheduler::{Constraints, WeeklyWindow},
+};
+
+/// Most windows in each list
+pub const MAX_CONSTRAINT_WINDOWS: usize = 50;
+
+#[derive(Debug, Deserialize)]
+pub struct UpdateSchedulingConstraintsRequest {
+    pub working_hours: Option<Vec<AvailabilityWindow>>,
+    pub protected_windows: Option<Vec<AvailabilityWindow>>,
+    /// `null` removes the limit
+    #[serde(default, deserialize_with = "double_option")]
+    pub max_blocks_per_day: Option<Option<i16>>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct SchedulingConstraintsResponse {
+    /// Weekly windows tasks are scheduled in, in the user's timezone
+    pub working_hours: Vec<AvailabilityWindow>,
+    /// Weekly windows kept free of scheduled tasks and bookings
+    pub protected_windows: Vec<AvailabilityWindow>,
+    pub max_blocks_per_day: Option<i16>,
+    pub updated_at: DateTime<Utc>,
+}
+
+/// 09:00 to 17:00, Monday to Friday
+pub fn default_working_hours() -> Vec<AvailabilityWindow> {
+    let (start, end) = (
+        NaiveTime::from_hms_opt(9, 0, 0).unwrap_or_default(),
+        NaiveTime::from_hms_opt(17, 0, 0).unwrap_or_default(),
+    );
+    (1..=5).map(|weekday| AvailabilityWindow { weekday, start, end }).collect()
+}
+
+/// Constraints of a user who never set any: default working hours, nothing protected, no limit
+pub fn default_constraints(user_id: Uuid) -> scheduling_constraints::Model {
+    let now = Utc::now().into();
+    scheduling_constraints::Model {
+        user_id,
+        working_hours: serde_json::json!(default_working_hours()),
+        protected_windows: serde_json::json!([]),
+        max_blocks_per_day: None,
+        created_at: now,
+        updated_at: now,
+    }
+}
+
+fn validate_list(field: &str, windows: &[AvailabilityWindow]) -> Result<()> {
+    if windows.len() > MAX_CONSTRAINT_WINDOWS {
+        return Err(AppError::invalid_field(field, format!("At most {} windows are allowed", MAX_CONSTRAINT_WINDOWS)));
+    }
+    validate_windows(field, windows)
+}
+
+fn windows_of(value: &serde_json::Value) -> Vec<AvailabilityWindow> {
+    serde_json::from_value(value.clone()).unwrap_or_default()
+}
+
+fn weekly_windows(windows: Vec<AvailabilityWindow>) -> Vec<WeeklyWindow> {
+    windows
+        .into_iter()
+        .filter_map(|window| {
+            let weekday = iso_weekday(i16::try_from(window.weekday).ok()?)?;
+            Some(WeeklyWindow { weekday, start: window.start, end: window.end })
+        })
+        .collect()
+}
+
+impl UpdateSchedulingConstraintsRequest {
+    pub fn apply_to(self, constraints: &mut scheduling_constraints::Model) -> Result<()> {
+        if let Some(working_hours) = self.working_hours {
+            validate_list("working_hours", &working_hours)?;
+            if working_hours.is_empty() {
+                return Err(AppError::invalid_field("working_hours", "At least one working hours window is required"));
+            }
+            constraints.working_hours = serde_json::json!(working_hours);
+        }
+        if let Some(protected_windows) = self.protected_windows {
+            validate_list("protected_windows", &protected_windows)?;
+            constraints.protected_windows = serde_json::json!(protected_windows);
+        }
+        if let Some(max_blocks_per_day) = self.max_blocks_per_day {
+            if max_blocks_per_day.is_some_and(|max| max < 1) {
+                return Err(AppError::invalid_field("max_blocks_per_day", "max_blocks_per_day must be positive"));
+            }
+            constraints.max_blocks_per_day = max_blocks_per_day;
+        }
+        Ok(())
+    }
+}
+
+impl From<&scheduling_constraints::Model> for Constraints {
+    fn from(constraints: &scheduling_constraints::Model) -> Self {
+        Self {
+            working_hours: weekly_windows(windows_of(&constraints.working_hours)),
+            protected_windows: weekly_windows(windows_of(&constraints.protected_windows)),
+            max_blocks_per_day: constraints.max_blocks_per_day.map(|max| max as usize),
+        }
+    }
+}
+
+impl From<scheduling_constraints::Model> for SchedulingConstraintsResponse {
+    fn from(constraints: scheduling_constraints::Model) -> Self {
+        Self {
+            working_hours: windows_of(&constraints.working_hours),
+            protected_windows: windows_of(&constraints.protected_windows),
+            max_blocks_per_day: constraints.max_blocks_per_day,
+            updated_at: constraints.updated_at.naive_utc().and_utc(),
+        }
+    }
+}
+
+impl Validate for UpdateSchedulingConstraintsRequest {}
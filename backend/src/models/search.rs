@@ -0,0 +1,147 @@
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::{
+    errors::{AppError, FieldError, Result},
+    models::validation::{field_error, Validate},
+};
+
+/// Upper bound on bit positions in one prefilter query
+pub const MAX_PREFILTER_BITS: usize = 1024;
+/// Upper bound on blind-index tokens stored for one record
+pub const MAX_RECORD_TOKENS: usize = 512;
+/// Upper bound on blind-index tokens in one search query
+pub const MAX_QUERY_TOKENS: usize = 32;
+/// Tokens are encoded hashes; long enough for a hex SHA-512
+const MAX_TOKEN_LENGTH: usize = 128;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SearchTable {
+    CanDoList,
+    CalendarEvents,
+    Projects,
+}
+
+impl SearchTable {
+    fn parse(table: &str) -> Result<Self> {
+        match table {
+            "can_do_list" => Ok(SearchTable::CanDoList),
+            "calendar_events" => Ok(SearchTable::CalendarEvents),
+            "projects" => Ok(SearchTable::Projects),
+            other => Err(AppError::invalid_field("tables", format!("Unknown table '{}'", other))),
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct PrefilterRequest {
+    /// Tables to search; all searchable tables when omitted
+    pub tables: Option<Vec<SearchTable>>,
+    /// Bloom filter bit positions of the hashed query tokens; a record is a
+    /// candidate only if every position is set in its filter
+    pub bits: Vec<u32>,
+}
+
+#[derive(Debug, Default, Serialize)]
+pub struct PrefilterResponse {
+    pub can_do_list: Vec<Uuid>,
+    pub calendar_events: Vec<Uuid>,
+}
+
+impl PrefilterRequest {
+    pub fn includes(&self, table: SearchTable) -> bool {
+        self.tables.as_ref().is_none_or(|tables| tables.contains(&table))
+    }
+}
+
+/// `?q_tokens=<token>,<token>&tables=can_do_list,projects`
+#[derive(Debug, Deserialize)]
+pub struct SearchQuery {
+    /// Blind-index tokens of the query's words; a record matches if it has all of them
+    pub q_tokens: String,
+    /// Tables to search; all searchable tables when omitted
+    pub tables: Option<String>,
+}
+
+impl SearchQuery {
+    /// The distinct query tokens
+    pub fn tokens(&self) -> Result<Vec<&str>> {
+        let mut tokens: Vec<&str> = self.q_tokens.split(',').map(str::trim).filter(|token| !token.is_empty()).collect();
+        tokens.sort_unstable();
+        tokens.dedup();
+        if tokens.is_empty() {
+            return Err(AppError::invalid_field("q_tokens", "At least one token is required"));
+        }
+        if tokens.len() > MAX_QUERY_TOKENS {
+            return Err(AppError::invalid_field(
+                "q_tokens",
+                format!("At most {} tokens can be searched at once", MAX_QUERY_TOKENS),
+            ));
+        }
+        if !tokens.iter().all(|token| is_valid_token(token)) {
+            return Err(AppError::invalid_field("q_tokens", "Tokens must be hex or base64 strings"));
+        }
+        Ok(tokens)
+    }
+
+    pub fn tables(&self) -> Result<Vec<SearchTable>> {
+        match self.tables.as_deref() {
+            None => Ok(vec![SearchTable::CanDoList, SearchTable::CalendarEvents, SearchTable::Projects]),
+            Some(tables) => tables.split(',').map(|table| SearchTable::parse(table.trim())).collect(),
+        }
+    }
+}
+
+/// Ids of the active records having every query token
+#[derive(Debug, Default, Serialize)]
+pub struct SearchResponse {
+    pub can_do_list: Vec<Uuid>,
+    pub calendar_events: Vec<Uuid>,
+    pub projects: Vec<Uuid>,
+}
+
+fn is_valid_token(token: &str) -> bool {
+    token.len() <= MAX_TOKEN_LENGTH
+        && token.bytes().all(|byte| byte.is_ascii_alphanumeric() || matches!(byte, b'+' | b'/' | b'=' | b'-' | b'_'))
+}
+
+/// Checked before insert so clients get a validation error instead of a truncated index
+pub fn validate_search_tokens(tokens: Option<&[String]>) -> Result<()> {
+    let Some(tokens) = tokens else {
+        return Ok(());
+    };
+    if tokens.len() > MAX_RECORD_TOKENS {
+        return Err(AppError::invalid_field(
+            "search_tokens",
+            format!("At most {} search tokens can be stored per record", MAX_RECORD_TOKENS),
+        ));
+    }
+    if !tokens.iter().all(|token| !token.is_empty() && is_valid_token(token)) {
+        return Err(AppError::invalid_field("search_tokens", "Search tokens must be hex or base64 strings"));
+    }
+    Ok(())
+}
+
+/// The `search_tokens` column: distinct tokens separated by spaces, which no token contains
+pub fn join_search_tokens(mut tokens: Vec<String>) -> String {
+    tokens.sort_unstable();
+    tokens.dedup();
+    tokens.join(" ")
+}
+
+/// Whether the stored `search_tokens` contain every (sorted, distinct) query token
+pub fn has_all_tokens(search_tokens: &str, query: &[&str]) -> bool {
+    let stored: std::collections::HashSet<&str> = search_tokens.split(' ').collect();
+    query.iter().all(|token| stored.contains(token))
+}
+
+impl Validate for PrefilterRequest {
+    fn validate(&self, errors: &mut Vec<FieldError>) {
+        if self.bits.is_empty() {
+            field_error(errors, "bits", "At least one bit position is required");
+        } else if self.bits.len() > MAX_PREFILTER_BITS {
+            field_error(errors, "bits", format!("At most {} bit positions can be queried at once", MAX_PREFILTER_BITS));
+        }
+    }
+}
@@ -0,0 +1,73 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::{
+    entities::settings,
+    errors::{AppError, Result},
+    models::validation::Validate,
+};
+
+/// Namespace holding what used to be the single settings blob; `/api/user-settings` reads and
+/// writes it
+pub const GENERAL_NAMESPACE: &str = "general";
+/// Longest namespace accepted, in characters
+pub const MAX_NAMESPACE_LENGTH: usize = 64;
+/// Namespaces a user may have at once
+pub const MAX_NAMESPACES: u64 = 100;
+
+/// Namespaces are lowercase ASCII letters, digits, `_`, `-` and `.`, e.g. `calendar.view`
+pub fn validate_namespace(namespace: &str) -> Result<()> {
+    let valid = !namespace.is_empty()
+        && namespace.len() <= MAX_NAMESPACE_LENGTH
+        && namespace.bytes().all(|byte| byte.is_ascii_lowercase() || byte.is_ascii_digit() || b"_-.".contains(&byte));
+    if !valid {
+        return Err(AppError::invalid_field(
+            "namespace",
+            format!(
+                "namespace must be 1 to {} lowercase letters, digits, '_', '-' or '.'",
+                MAX_NAMESPACE_LENGTH
+            ),
+        ));
+    }
+    Ok(())
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct SaveSettingRequest {
+    pub encrypted_data: String,
+    pub iv: String,
+    pub salt: String,
+    /// Format version of `encrypted_data`; 1 when omitted
+    pub schema_version: Option<i16>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct SettingResponse {
+    pub id: Uuid,
+    pub namespace: String,
+    pub encrypted_data: String,
+    pub iv: String,
+    pub salt: String,
+    /// Format version of `encrypted_data`
+    pub schema_version: i16,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+impl From<settings::Model> for SettingResponse {
+    fn from(setting: settings::Model) -> Self {
+        Self {
+            id: setting.id,
+            namespace: setting.namespace,
+            encrypted_data: setting.encrypted_data,
+            iv: setting.iv,
+            salt: setting.salt,
+            schema_version: setting.schema_version,
+            created_at: setting.created_at.naive_utc().and_utc(),
+            updated_at: setting.updated_at.naive_utc().and_utc(),
+        }
+    }
+}
+
+impl Validate for SaveSettingRequest {}
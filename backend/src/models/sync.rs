@@ -0,0 +1,118 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+use crate::entities::deleted_records;
+use crate::models::validation::Validate;
+use crate::handlers::user_settings::UserSettingsResponse;
+use super::{
+    calendar::CalendarResponse,
+    calendar_event::CalendarEventResponse,
+    can_do_list::CanDoItemResponse,
+    habit::HabitResponse,
+    kv::KvEntryResponse,
+    note::NoteResponse,
+    project::ProjectResponse,
+    setting::SettingResponse,
+};
+
+#[derive(Debug, Deserialize)]
+pub struct SyncQuery {
+    pub since: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SyncAction {
+    Create,
+    Update,
+    Delete,
+}
+
+/// One change a client queued while offline
+#[derive(Debug, Deserialize)]
+pub struct SyncOperation {
+    /// Client-chosen id echoed back in the matching result
+    pub op_id: String,
+    pub table: String,
+    pub action: SyncAction,
+    /// Client-generated for creates, so retried pushes don't create duplicates
+    pub record_id: Uuid,
+    /// `updated_at` of the record version the client edited; omit to overwrite unconditionally
+    pub base_updated_at: Option<DateTime<Utc>>,
+    /// Create or update request body for the table
+    #[serde(default)]
+    pub data: serde_json::Value,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct SyncPushRequest {
+    pub operations: Vec<SyncOperation>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SyncOperationStatus {
+    Applied,
+    Conflict,
+    NotFound,
+    Invalid,
+}
+
+#[derive(Debug, Serialize)]
+pub struct SyncOperationResult {
+    pub op_id: String,
+    pub record_id: Uuid,
+    pub status: SyncOperationStatus,
+    /// Authoritative server copy of the record; absent after deletes
+    pub record: Option<serde_json::Value>,
+    pub error: Option<String>,
+    /// Entry in the conflict log holding both versions, set for conflicts
+    pub conflict_id: Option<Uuid>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct SyncPushResponse {
+    pub results: Vec<SyncOperationResult>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct DeletedRecordResponse {
+    pub table: String,
+    pub record_id: Uuid,
+    pub deleted_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct SyncResponse {
+    pub projects: Vec<ProjectResponse>,
+    pub can_do_list: Vec<CanDoItemResponse>,
+    pub calendars: Vec<CalendarResponse>,
+    pub calendar_events: Vec<CalendarEventResponse>,
+    pub notes: Vec<NoteResponse>,
+    /// Streaks as they stand on the user's current date
+    pub habits: Vec<HabitResponse>,
+    /// Time preferences and the general settings namespace
+    pub user_settings: Option<UserSettingsResponse>,
+    /// Settings namespaces, the general one included
+    pub settings: Vec<SettingResponse>,
+    /// Entries of the key-value store
+    pub kv: Vec<KvEntryResponse>,
+    pub deleted: Vec<DeletedRecordResponse>,
+    /// Every task still holding the plaintext of the email it was created from, whether or not it
+    /// changed since `since`; clients encrypt these and clear it with an update
+    pub pending_encryption: Vec<Uuid>,
+    /// Watermark to pass as `since` on the next sync request
+    pub server_time: DateTime<Utc>,
+}
+
+impl From<deleted_records::Model> for DeletedRecordResponse {
+    fn from(record: deleted_records::Model) -> Self {
+        Self {
+            table: record.table_name,
+            record_id: record.record_id,
+            deleted_at: record.deleted_at.naive_utc().and_utc(),
+        }
+    }
+}
+
+impl Validate for SyncPushRequest {}
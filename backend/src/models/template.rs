@@ -0,0 +1,353 @@
+use chrono::{DateTime, Duration, NaiveDate, NaiveTime, TimeZone, Utc};
+use chrono_tz::Tz;
+use sea_orm::{prelude::DateTimeWithTimeZone, ActiveModelBehavior, Set};
+use serde::{Deserialize, Serialize};
+use streamline_models::{calendar_event::EventStatus, can_do_list::TaskPriority, DEFAULT_SCHEMA_VERSION};
+use uuid::Uuid;
+
+use crate::entities::{
+    calendar_events, can_do_list, projects,
+    templates::{self, TemplateKind},
+};
+use crate::errors::FieldError;
+use crate::models::{
+    calendar_event::CalendarEventResponse,
+    can_do_list::CanDoItemResponse,
+    project::ProjectResponse,
+    validate_client_id,
+    validation::{collect, field_error, Validate},
+};
+
+/// Where a date lands relative to the day a template is instantiated, e.g. "3 days later at 15:00",
+/// in the user's timezone
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DateShift {
+    pub days: i64,
+    pub time: NaiveTime,
+}
+
+impl DateShift {
+    /// The shift that takes `anchor` to `at`
+    pub fn between(anchor: NaiveDate, at: DateTimeWithTimeZone, timezone: Tz) -> Self {
+        let local = at.with_timezone(&timezone);
+        Self { days: (local.date_naive() - anchor).num_days(), time: local.time() }
+    }
+
+    /// The point in time the shift lands on from `start`; a time skipped by a DST change
+    /// moves an hour later
+    pub fn resolve(self, start: NaiveDate, timezone: Tz) -> Option<DateTime<Utc>> {
+        let local = start.checked_add_signed(Duration::try_days(self.days)?)?.and_time(self.time);
+        timezone
+            .from_local_datetime(&local)
+            .earliest()
+            .or_else(|| timezone.from_local_datetime(&(local + Duration::hours(1))).earliest())
+            .map(|at| at.with_timezone(&Utc))
+    }
+}
+
+/// Encrypted payload copied from the record a template entry was saved from
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TemplatePayload {
+    pub encrypted_data: String,
+    pub iv: String,
+    pub salt: String,
+    pub schema_version: i16,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TemplateProject {
+    /// Index of the parent in `ProjectTemplate::projects`; `None` only for the root
+    pub parent: Option<usize>,
+    #[serde(flatten)]
+    pub payload: TemplatePayload,
+    pub display_order: i32,
+    pub is_collapsed: bool,
+    pub search_tokens: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TemplateTask {
+    /// Index of the task's project in `ProjectTemplate::projects`
+    pub project: usize,
+    /// Index of the parent task in `ProjectTemplate::tasks`
+    pub parent: Option<usize>,
+    #[serde(flatten)]
+    pub payload: TemplatePayload,
+    pub display_order: i32,
+    pub search_bloom: Option<String>,
+    pub search_tokens: Option<String>,
+    pub due: Option<DateShift>,
+    pub scheduled: Option<DateShift>,
+    pub rrule: Option<String>,
+    pub priority: Option<TaskPriority>,
+    pub estimated_minutes: Option<i32>,
+    pub is_pinned: bool,
+}
+
+/// A project subtree; the root comes first and every project after its parent
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ProjectTemplate {
+    pub projects: Vec<TemplateProject>,
+    pub tasks: Vec<TemplateTask>,
+}
+
+impl ProjectTemplate {
+    /// Save `projects`, the root first and every parent before its children, with their tasks.
+    /// Completion isn't kept, so every instance starts out open.
+    pub fn capture(projects: &[projects::Model], tasks: &[can_do_list::Model], anchor: NaiveDate, timezone: Tz) -> Self {
+        let project_index = |id: Uuid| projects.iter().position(|project| project.id == id);
+        let task_index = |id: Uuid| tasks.iter().position(|task| task.id == id);
+        let projects_out = projects
+            .iter()
+            .enumerate()
+            .map(|(index, project)| TemplateProject {
+                parent: if index == 0 { None } else { project.parent_id.and_then(project_index) },
+                payload: TemplatePayload {
+                    encrypted_data: project.encrypted_data.clone(),
+                    iv: project.iv.clone(),
+                    salt: project.salt.clone(),
+                    schema_version: project.schema_version,
+                },
+                display_order: project.display_order,
+                is_collapsed: project.is_collapsed,
+                search_tokens: project.search_tokens.clone(),
+            })
+            .collect();
+        let tasks_out = tasks
+            .iter()
+            .filter_map(|task| {
+                Some(TemplateTask {
+                    project: task.project_id.and_then(project_index)?,
+                    parent: task.parent_task_id.and_then(task_index),
+                    payload: TemplatePayload {
+                        encrypted_data: task.encrypted_data.clone(),
+                        iv: task.iv.clone(),
+                        salt: task.salt.clone(),
+                        schema_version: task.schema_version,
+                    },
+                    display_order: task.display_order,
+                    search_bloom: task.search_bloom.clone(),
+                    search_tokens: task.search_tokens.clone(),
+                    due: task.due_at.map(|at| DateShift::between(anchor, at, timezone)),
+                    scheduled: task.scheduled_at.map(|at| DateShift::between(anchor, at, timezone)),
+                    rrule: task.rrule.clone(),
+                    priority: task.priority,
+                    estimated_minutes: task.estimated_minutes,
+                    is_pinned: task.is_pinned,
+                })
+            })
+            .collect();
+        Self { projects: projects_out, tasks: tasks_out }
+    }
+}
+
+/// A single event; the instance starts `start` after the instantiation date
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EventTemplate {
+    #[serde(flatten)]
+    pub payload: TemplatePayload,
+    /// Calendar new instances go to unless another one is asked for
+    pub calendar_id: Option<Uuid>,
+    pub start: DateShift,
+    pub duration_minutes: Option<i64>,
+    /// Days an all-day event covers; timed events have none
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub all_day_span: Option<i64>,
+    pub search_bloom: Option<String>,
+    pub search_tokens: Option<String>,
+    pub rrule: Option<String>,
+    pub status: EventStatus,
+}
+
+impl EventTemplate {
+    pub fn capture(event: &calendar_events::Model, start_time: DateTimeWithTimeZone, anchor: NaiveDate, timezone: Tz) -> Self {
+        Self {
+            payload: TemplatePayload {
+                encrypted_data: event.encrypted_data.clone(),
+                iv: event.iv.clone(),
+                salt: event.salt.clone(),
+                schema_version: event.schema_version,
+            },
+            calendar_id: event.calendar_id,
+            // An all-day event starts on its first day, whatever timezone it was created in
+            start: match event.start_date.filter(|_| event.is_all_day) {
+                Some(first_day) => DateShift { days: (first_day - anchor).num_days(), time: NaiveTime::MIN },
+                None => DateShift::between(anchor, start_time, timezone),
+            },
+            duration_minutes: event.end_time.map(|end_time| (end_time - start_time).num_minutes()),
+            all_day_span: event
+                .start_date
+                .filter(|_| event.is_all_day)
+                .map(|start_date| (event.end_date.unwrap_or(start_date) - start_date).num_days() + 1),
+            search_bloom: event.search_bloom.clone(),
+            search_tokens: event.search_tokens.clone(),
+            rrule: event.rrule.clone(),
+            status: event.status,
+        }
+    }
+}
+
+/// What a template holds, depending on its kind
+#[derive(Debug, Clone)]
+pub enum TemplateContent {
+    Project(ProjectTemplate),
+    Event(EventTemplate),
+}
+
+impl TemplateContent {
+    pub fn parse(template: &templates::Model) -> Result<Self, serde_json::Error> {
+        let content = template.content.clone();
+        Ok(match template.kind {
+            TemplateKind::Project => Self::Project(serde_json::from_value(content)?),
+            TemplateKind::Event => Self::Event(serde_json::from_value(content)?),
+        })
+    }
+
+    pub fn kind(&self) -> TemplateKind {
+        match self {
+            Self::Project(_) => TemplateKind::Project,
+            Self::Event(_) => TemplateKind::Event,
+        }
+    }
+
+    pub fn to_json(&self) -> serde_json::Value {
+        match self {
+            Self::Project(content) => serde_json::to_value(content),
+            Self::Event(content) => serde_json::to_value(content),
+        }
+        .unwrap_or_default()
+    }
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct CreateTemplateRequest {
+    /// Optional client-generated id so offline-created records keep their identity
+    pub id: Option<Uuid>,
+    /// The template's own name and description
+    pub encrypted_data: String,
+    pub iv: String,
+    pub salt: String,
+    /// Format version of `encrypted_data`; 1 when omitted
+    pub schema_version: Option<i16>,
+    /// Save this project with its sub-projects and tasks; exclusive with `event_id`
+    pub project_id: Option<Uuid>,
+    /// Save this event; exclusive with `project_id`
+    pub event_id: Option<Uuid>,
+    /// Date the saved dates are taken relative to; the user's current date for projects and the
+    /// event's own date for events when omitted
+    pub anchor_date: Option<NaiveDate>,
+}
+
+impl CreateTemplateRequest {
+    pub fn into_active_model(self, user_id: Uuid, content: &TemplateContent) -> templates::ActiveModel {
+        let mut template_active = templates::ActiveModel::new();
+        if let Some(id) = self.id {
+            template_active.id = Set(id);
+        }
+        template_active.user_id = Set(user_id);
+        template_active.kind = Set(content.kind());
+        template_active.encrypted_data = Set(self.encrypted_data);
+        template_active.iv = Set(self.iv);
+        template_active.salt = Set(self.salt);
+        template_active.schema_version = Set(self.schema_version.unwrap_or(DEFAULT_SCHEMA_VERSION));
+        template_active.content = Set(content.to_json());
+        template_active
+    }
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct UpdateTemplateRequest {
+    pub encrypted_data: Option<String>,
+    pub iv: Option<String>,
+    pub salt: Option<String>,
+    /// Format version of `encrypted_data`; a new `encrypted_data` without it is taken to be version 1
+    pub schema_version: Option<i16>,
+}
+
+impl UpdateTemplateRequest {
+    pub fn apply_to(self, template_active: &mut templates::ActiveModel) {
+        // A new payload without a version comes from a client predating versioning
+        let schema_version = self.schema_version.or(self.encrypted_data.is_some().then_some(DEFAULT_SCHEMA_VERSION));
+        if let Some(encrypted_data) = self.encrypted_data {
+            template_active.encrypted_data = Set(encrypted_data);
+        }
+        if let Some(iv) = self.iv {
+            template_active.iv = Set(iv);
+        }
+        if let Some(salt) = self.salt {
+            template_active.salt = Set(salt);
+        }
+        if let Some(schema_version) = schema_version {
+            template_active.schema_version = Set(schema_version);
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct TemplateListQuery {
+    pub kind: Option<TemplateKind>,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct InstantiateTemplateRequest {
+    /// Date the saved date shifts count from; the user's current date when omitted
+    pub start_date: Option<NaiveDate>,
+    /// Project templates: place the new project under this project instead of at the top level
+    pub parent_id: Option<Uuid>,
+    /// Event templates: calendar for the new event instead of the saved one
+    pub calendar_id: Option<Uuid>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct TemplateResponse {
+    pub id: Uuid,
+    pub user_id: Uuid,
+    pub kind: TemplateKind,
+    pub encrypted_data: String,
+    pub iv: String,
+    pub salt: String,
+    /// Format version of `encrypted_data`
+    pub schema_version: i16,
+    /// The saved records, shaped by `kind`
+    pub content: serde_json::Value,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+impl From<templates::Model> for TemplateResponse {
+    fn from(template: templates::Model) -> Self {
+        Self {
+            id: template.id,
+            user_id: template.user_id,
+            kind: template.kind,
+            encrypted_data: template.encrypted_data,
+            iv: template.iv,
+            salt: template.salt,
+            schema_version: template.schema_version,
+            content: template.content,
+            created_at: template.created_at.naive_utc().and_utc(),
+            updated_at: template.updated_at.naive_utc().and_utc(),
+        }
+    }
+}
+
+/// Records created from a template; only the lists matching its kind are filled
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct InstantiateTemplateResponse {
+    pub projects: Vec<ProjectResponse>,
+    pub can_do_list: Vec<CanDoItemResponse>,
+    pub calendar_events: Vec<CalendarEventResponse>,
+}
+
+impl Validate for CreateTemplateRequest {
+    fn validate(&self, errors: &mut Vec<FieldError>) {
+        collect(errors, "id", validate_client_id(self.id));
+        if self.project_id.is_some() == self.event_id.is_some() {
+            field_error(errors, "project_id", "Exactly one of project_id and event_id is required");
+        }
+    }
+}
+
+impl Validate for UpdateTemplateRequest {}
+
+impl Validate for InstantiateTemplateRequest {}
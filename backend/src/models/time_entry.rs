@@ -0,0 +1,76 @@
+use chrono::{DateTime, Utc};
+use sea_orm::FromQueryResult;
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::{entities::time_entries, models::validation::Validate};
+
+/// Optional encrypted note saved with the entry when a timer stops
+#[derive(Debug, Deserialize)]
+pub struct StopTimerRequest {
+    pub encrypted_data: String,
+    pub iv: String,
+    pub salt: String,
+    /// 1 when omitted
+    pub schema_version: Option<i16>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct TimeEntryQuery {
+    /// Only entries still running or ending after this instant
+    pub start: Option<DateTime<Utc>>,
+    /// Only entries starting before this instant
+    pub end: Option<DateTime<Utc>>,
+    pub task_id: Option<Uuid>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct TimeTotalsQuery {
+    pub start: DateTime<Utc>,
+    pub end: DateTime<Utc>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct TimeEntryResponse {
+    pub id: Uuid,
+    pub user_id: Uuid,
+    pub task_id: Uuid,
+    pub started_at: DateTime<Utc>,
+    /// `null` while the timer is running
+    pub stopped_at: Option<DateTime<Utc>>,
+    pub encrypted_data: Option<String>,
+    pub iv: Option<String>,
+    pub salt: Option<String>,
+    pub schema_version: i16,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+/// Time tracked on a project's tasks within the requested window
+#[derive(Debug, Serialize, FromQueryResult)]
+pub struct ProjectTimeTotal {
+    /// `null` for tasks in the inbox
+    pub project_id: Option<Uuid>,
+    pub total_seconds: i64,
+    pub entry_count: i64,
+}
+
+impl From<time_entries::Model> for TimeEntryResponse {
+    fn from(entry: time_entries::Model) -> Self {
+        Self {
+            id: entry.id,
+            user_id: entry.user_id,
+            task_id: entry.task_id,
+            started_at: entry.started_at.naive_utc().and_utc(),
+            stopped_at: entry.stopped_at.map(|dt| dt.naive_utc().and_utc()),
+            encrypted_data: entry.encrypted_data,
+            iv: entry.iv,
+            salt: entry.salt,
+            schema_version: entry.schema_version,
+            created_at: entry.created_at.naive_utc().and_utc(),
+            updated_at: entry.updated_at.naive_utc().and_utc(),
+        }
+    }
+}
+
+impl Validate for StopTimerRequest {}
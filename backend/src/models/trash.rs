@@ -0,0 +1,17 @@
+use serde::Serialize;
+use super::{
+    calendar::CalendarResponse,
+    calendar_event::CalendarEventResponse,
+    can_do_list::CanDoItemResponse,
+    note::NoteResponse,
+    project::ProjectResponse,
+};
+
+#[derive(Debug, Serialize)]
+pub struct TrashResponse {
+    pub projects: Vec<ProjectResponse>,
+    pub can_do_list: Vec<CanDoItemResponse>,
+    pub calendars: Vec<CalendarResponse>,
+    pub calendar_events: Vec<CalendarEventResponse>,
+    pub notes: Vec<NoteResponse>,
+}
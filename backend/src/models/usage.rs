@@ -0,0 +1,19 @@
+use serde::Serialize;
+
+#[derive(Debug, Serialize)]
+pub struct TableUsage {
+    pub records: u64,
+    pub trashed: u64,
+    pub bytes: u64,
+    pub quota: Option<u64>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct UsageResponse {
+    pub projects: TableUsage,
+    pub can_do_list: TableUsage,
+    pub calendars: TableUsage,
+    pub calendar_events: TableUsage,
+    pub notes: TableUsage,
+    pub total_bytes: u64,
+}
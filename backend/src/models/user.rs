@@ -1,39 +1,10 @@
-use chrono::{DateTime, Utc};
-use serde::{Deserialize, Serialize};
-use serde_json::Value;
-use uuid::Uuid;
-use crate::entities::users;
+use crate::{
+    entities::users,
+    errors::FieldError,
+    models::validation::{validate_email, validate_password, validate_required, Validate},
+};
 
-#[derive(Debug, Deserialize)]
-pub struct CreateUserRequest {
-    pub email: String,
-    pub password: String,
-}
-
-#[derive(Debug, Deserialize)]
-pub struct LoginRequest {
-    pub email: String,
-    pub password: String,
-}
-
-#[derive(Debug, Serialize)]
-pub struct UserResponse {
-    pub id: Uuid,
-    pub email: String,
-    pub email_confirmed_at: Option<DateTime<Utc>>,
-    pub created_at: DateTime<Utc>,
-    pub updated_at: DateTime<Utc>,
-    pub app_metadata: Value,
-    pub user_metadata: Value,
-}
-
-#[derive(Debug, Serialize)]
-pub struct AuthResponse {
-    pub access_token: String,
-    pub token_type: String,
-    pub expires_in: i64,
-    pub user: UserResponse,
-}
+pub use streamline_models::auth::{AuthResponse, CreateUserRequest, LoginRequest, SessionResponse, UserResponse};
 
 impl From<users::Model> for UserResponse {
     fn from(user: users::Model) -> Self {
@@ -48,3 +19,18 @@ impl From<users::Model> for UserResponse {
         }
     }
 }
+
+impl Validate for CreateUserRequest {
+    fn validate(&self, errors: &mut Vec<FieldError>) {
+        validate_email(errors, "email", &self.email);
+        validate_password(errors, "password", &self.password);
+    }
+}
+
+/// Only presence is checked, so accounts made before the rules were tightened can still log in
+impl Validate for LoginRequest {
+    fn validate(&self, errors: &mut Vec<FieldError>) {
+        validate_required(errors, "email", &self.email);
+        validate_required(errors, "password", &self.password);
+    }
+}
@@ -0,0 +1,93 @@
+use chrono::{DateTime, Duration, NaiveDate, NaiveTime, TimeZone, Utc, Weekday};
+use chrono_tz::Tz;
+
+use crate::{
+    entities::user_settings,
+    errors::FieldError,
+};
+
+/// Timezone of users who haven't chosen one
+pub const DEFAULT_TIMEZONE: &str = "UTC";
+/// Weeks start on Monday unless the user chose otherwise
+pub const DEFAULT_WEEK_START: i16 = 1;
+
+pub fn default_timezone() -> String {
+    DEFAULT_TIMEZONE.to_string()
+}
+
+pub fn default_week_start() -> i16 {
+    DEFAULT_WEEK_START
+}
+
+/// The user's local time: what days, weeks and recurring schedules are counted in
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TimePreferences {
+    pub timezone: Tz,
+    pub week_start: Weekday,
+}
+
+impl Default for TimePreferences {
+    fn default() -> Self {
+        Self { timezone: Tz::UTC, week_start: Weekday::Mon }
+    }
+}
+
+impl TimePreferences {
+    /// The user's current local date
+    pub fn today(&self) -> NaiveDate {
+        Utc::now().with_timezone(&self.timezone).date_naive()
+    }
+
+    /// When `date` begins for the user; where a DST change skips midnight, the end of the gap
+    pub fn start_of_day(&self, date: NaiveDate) -> DateTime<Utc> {
+        let midnight = date.and_time(NaiveTime::MIN);
+        self.timezone
+            .from_local_datetime(&midnight)
+            .earliest()
+            .or_else(|| self.timezone.from_local_datetime(&(midnight + Duration::hours(1))).earliest())
+            .map(|at| at.with_timezone(&Utc))
+            .unwrap_or_else(|| midnight.and_utc())
+    }
+}
+
+impl From<&user_settings::Model> for TimePreferences {
+    /// Values that no longer parse fall back to the defaults
+    fn from(settings: &user_settings::Model) -> Self {
+        let defaults = Self::default();
+        Self {
+            timezone: settings.timezone.parse().unwrap_or(defaults.timezone),
+            week_start: iso_weekday(settings.week_start).unwrap_or(defaults.week_start),
+        }
+    }
+}
+
+/// ISO weekday, 1 (Monday) to 7 (Sunday)
+pub fn iso_weekday(number: i16) -> Option<Weekday> {
+    match number {
+        1..=7 => Weekday::try_from(number as u8 - 1).ok(),
+        _ => None,
+    }
+}
+
+/// Check an IANA timezone name, returning it trimmed
+pub fn validate_timezone(field: &str, timezone: &str, errors: &mut Vec<FieldError>) -> Option<String> {
+    let timezone = timezone.trim();
+    match timezone.parse::<Tz>() {
+        Ok(_) => Some(timezone.to_string()),
+        Err(_) => {
+            errors.push(FieldError { field: field.to_string(), message: format!("Unknown timezone '{}'", timezone) });
+            None
+        }
+    }
+}
+
+pub fn validate_week_start(week_start: i16, errors: &mut Vec<FieldError>) -> Option<i16> {
+    if iso_weekday(week_start).is_some() {
+        return Some(week_start);
+    }
+    errors.push(FieldError {
+        field: "week_start".to_string(),
+        message: "week_start must be between 1 (Monday) and 7 (Sunday)".to_string(),
+    });
+    None
+}
@@ -0,0 +1,101 @@
+//! Checks of request bodies, run by the `ValidatedJson` extractor before a handler sees the
+//! request. Every invalid field is reported at once, each with its own message.
+
+use uuid::Uuid;
+
+use crate::errors::{AppError, FieldError, Result};
+
+/// Longest email address accepted, per RFC 5321
+pub const MAX_EMAIL_LENGTH: usize = 254;
+/// Shortest password accepted. Clients send a key derived from the password rather than the
+/// password itself, so this only stops trivial values; strength is up to the client.
+pub const MIN_PASSWORD_LENGTH: usize = 8;
+/// Longest password accepted, bounding the cost of hashing it
+pub const MAX_PASSWORD_LENGTH: usize = 1024;
+
+/// A request body that can check its own fields
+pub trait Validate {
+    /// Add one entry to `errors` per invalid field; bodies without constraints add none
+    fn validate(&self, _errors: &mut Vec<FieldError>) {}
+
+    /// All invalid fields as one error
+    fn check(&self) -> Result<()> {
+        let mut errors = Vec::new();
+        self.validate(&mut errors);
+        if errors.is_empty() {
+            return Ok(());
+        }
+        Err(AppError::InvalidFields(errors))
+    }
+}
+
+impl<T: Validate> Validate for Vec<T> {
+    fn validate(&self, errors: &mut Vec<FieldError>) {
+        for item in self {
+            item.validate(errors);
+        }
+    }
+}
+
+pub fn field_error(errors: &mut Vec<FieldError>, field: &str, message: impl Into<String>) {
+    errors.push(FieldError { field: field.to_string(), message: message.into() });
+}
+
+/// Keep the errors of a check that stops at the first problem; errors not tied to a field are
+/// reported under `field`
+pub fn collect(errors: &mut Vec<FieldError>, field: &str, result: Result<()>) {
+    match result {
+        Ok(()) => {}
+        Err(AppError::InvalidFields(fields)) => errors.extend(fields),
+        Err(AppError::Validation(message)) => field_error(errors, field, message),
+        Err(error) => field_error(errors, field, error.to_string()),
+    }
+}
+
+/// A plausible address: one `@` with something before it and a dotted domain after it, no
+/// whitespace. Whether it receives mail is only known by sending some.
+pub fn validate_email(errors: &mut Vec<FieldError>, field: &str, email: &str) {
+    let valid = email.len() <= MAX_EMAIL_LENGTH
+        && !email.chars().any(char::is_whitespace)
+        && email.split_once('@').is_some_and(|(local, domain)| {
+            !local.is_empty()
+                && !domain.contains('@')
+                && domain.split('.').count() >= 2
+                && domain.split('.').all(|label| !label.is_empty())
+        });
+    if !valid {
+        field_error(errors, field, format!("{} must be a valid email address", field));
+    }
+}
+
+/// At least `MIN_PASSWORD_LENGTH` characters mixing letters with digits or symbols
+pub fn validate_password(errors: &mut Vec<FieldError>, field: &str, password: &str) {
+    let length = password.chars().count();
+    if length < MIN_PASSWORD_LENGTH {
+        field_error(errors, field, format!("{} must be at least {} characters", field, MIN_PASSWORD_LENGTH));
+    } else if length > MAX_PASSWORD_LENGTH {
+        field_error(errors, field, format!("{} must be at most {} characters", field, MAX_PASSWORD_LENGTH));
+    } else if !password.chars().any(char::is_alphabetic) || password.chars().all(char::is_alphabetic) {
+        field_error(errors, field, format!("{} must contain letters and digits or symbols", field));
+    }
+}
+
+pub fn validate_required(errors: &mut Vec<FieldError>, field: &str, value: &str) {
+    if value.trim().is_empty() {
+        field_error(errors, field, format!("{} must not be empty", field));
+    }
+}
+
+/// Positions are counted from 0
+pub fn validate_display_order(errors: &mut Vec<FieldError>, display_order: Option<i32>) {
+    if display_order.is_some_and(|display_order| display_order < 0) {
+        field_error(errors, "display_order", "display_order must not be negative");
+    }
+}
+
+/// A reference to a record; the nil UUID is what a client sends for a missing id
+pub fn validate_reference(errors: &mut Vec<FieldError>, field: &str, id: Uuid) {
+    if id.is_nil() {
+        field_error(errors, field, format!("{} must be set", field));
+    }
+}
@@ -0,0 +1,258 @@
+use chrono::{DateTime, Utc};
+use sea_orm::{ActiveModelBehavior, Set};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::{
+    entities::{webhook_deliveries, webhooks},
+    errors::{AppError, FieldError, Result},
+    models::{
+        generate_public_token,
+        validation::{collect, field_error, Validate},
+    },
+    outbound,
+};
+
+/// Most event patterns a webhook can subscribe to
+const MAX_EVENT_PATTERNS: usize = 50;
+/// How long the previous secret keeps signing deliveries after a rotation, unless asked otherwise
+pub const DEFAULT_GRACE_PERIOD_MINUTES: i64 = 24 * 60;
+const MAX_GRACE_PERIOD_MINUTES: i64 = 7 * 24 * 60;
+
+#[derive(Debug, Deserialize)]
+pub struct CreateWebhookRequest {
+    pub url: String,
+    /// Patterns such as `can_do_list.*`, `*.delete` or `calendar_events.update`; all changes when omitted
+    pub events: Option<Vec<String>>,
+    pub is_active: Option<bool>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct UpdateWebhookRequest {
+    pub url: Option<String>,
+    pub events: Option<Vec<String>>,
+    pub is_active: Option<bool>,
+    /// Replace the signing secret; the new one is returned once in the response
+    #[serde(default)]
+    pub rotate_secret: bool,
+}
+
+#[derive(Debug, Default, Deserialize)]
+pub struct RotateWebhookSecretRequest {
+    /// Minutes deliveries stay signed with the old secret as well; 0 drops it right away
+    pub grace_period_minutes: Option<i64>,
+}
+
+/// A delivery as a receiver got it, to check the receiver's signature verification against
+#[derive(Debug, Deserialize)]
+pub struct VerifyWebhookSignatureRequest {
+    /// The raw request body
+    pub payload: String,
+    /// The `X-Streamline-Signature` header
+    pub signature: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct WebhookSignatureResponse {
+    pub valid: bool,
+    /// `t` of the header
+    pub signed_at: Option<DateTime<Utc>>,
+    /// `current` or `previous`, the secret a valid signature was made with
+    pub secret: Option<&'static str>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct WebhookResponse {
+    pub id: Uuid,
+    pub user_id: Uuid,
+    pub url: String,
+    /// Only returned when the webhook is created or its secret rotated
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub secret: Option<String>,
+    pub events: Vec<String>,
+    pub is_active: bool,
+    /// Until when deliveries are also signed with the secret before the last rotation
+    pub previous_secret_expires_at: Option<DateTime<Utc>>,
+    pub last_delivered_at: Option<DateTime<Utc>>,
+    pub last_error: Option<String>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct WebhookDeliveryResponse {
+    pub id: Uuid,
+    pub webhook_id: Uuid,
+    pub event: String,
+    pub payload: serde_json::Value,
+    pub attempts: i32,
+    pub next_attempt_at: Option<DateTime<Utc>>,
+    pub delivered_at: Option<DateTime<Utc>>,
+    pub last_status: Option<i32>,
+    pub last_error: Option<String>,
+    pub created_at: DateTime<Utc>,
+}
+
+/// Check a webhook URL; only http(s) targets are posted to
+fn normalize_webhook_url(url: &str) -> Result<String> {
+    let parsed = reqwest::Url::parse(url.trim())
+        .map_err(|_| AppError::invalid_field("url", "url must be a valid http(s) URL"))?;
+    if !matches!(parsed.scheme(), "http" | "https") || parsed.host_str().is_none() {
+        return Err(AppError::invalid_field("url", "url must be a valid http(s) URL"));
+    }
+    outbound::check_url(&parsed).map_err(|e| AppError::invalid_field("url", format!("url must be public: {}", e)))?;
+    Ok(parsed.to_string())
+}
+
+/// Each pattern is `*` or `<table>.<action>`, where either part may be `*`
+fn validate_events(events: &[String]) -> Result<()> {
+    if events.is_empty() || events.len() > MAX_EVENT_PATTERNS {
+        return Err(AppError::invalid_field("events", format!("events must list between 1 and {} patterns", MAX_EVENT_PATTERNS)));
+    }
+    for pattern in events {
+        if pattern == "*" {
+            continue;
+        }
+        let valid = pattern.split_once('.').is_some_and(|(table, action)| {
+            (table == "*" || (!table.is_empty() && table.chars().all(|c| c.is_ascii_lowercase() || c == '_')))
+                && matches!(action, "*" | "insert" | "update" | "delete")
+        });
+        if !valid {
+            return Err(AppError::invalid_field(
+                "events",
+                format!("Invalid event pattern '{}': expected '*' or '<table>.<insert|update|delete|*>'", pattern),
+            ));
+        }
+    }
+    Ok(())
+}
+
+impl Validate for CreateWebhookRequest {
+    fn validate(&self, errors: &mut Vec<FieldError>) {
+        collect(errors, "url", normalize_webhook_url(&self.url).map(drop));
+        if let Some(events) = &self.events {
+            collect(errors, "events", validate_events(events));
+        }
+    }
+}
+
+impl Validate for UpdateWebhookRequest {
+    fn validate(&self, errors: &mut Vec<FieldError>) {
+        if let Some(url) = &self.url {
+            collect(errors, "url", normalize_webhook_url(url).map(drop));
+        }
+        if let Some(events) = &self.events {
+            collect(errors, "events", validate_events(events));
+        }
+    }
+}
+
+impl Validate for RotateWebhookSecretRequest {
+    fn validate(&self, errors: &mut Vec<FieldError>) {
+        if self.grace_period_minutes.is_some_and(|minutes| !(0..=MAX_GRACE_PERIOD_MINUTES).contains(&minutes)) {
+            field_error(
+                errors,
+                "grace_period_minutes",
+                format!("grace_period_minutes must be between 0 and {}", MAX_GRACE_PERIOD_MINUTES),
+            );
+        }
+    }
+}
+
+impl Validate for VerifyWebhookSignatureRequest {
+    fn validate(&self, errors: &mut Vec<FieldError>) {
+        if self.signature.trim().is_empty() {
+            field_error(errors, "signature", "signature must not be empty");
+        }
+    }
+}
+
+impl CreateWebhookRequest {
+    pub fn webhook_active_model(&self, user_id: Uuid) -> Result<webhooks::ActiveModel> {
+        let mut webhook = webhooks::ActiveModel::new();
+        webhook.user_id = Set(user_id);
+        webhook.url = Set(normalize_webhook_url(&self.url)?);
+        webhook.secret = Set(generate_public_token());
+        if let Some(events) = &self.events {
+            validate_events(events)?;
+            webhook.events = Set(serde_json::json!(events));
+        }
+        if let Some(is_active) = self.is_active {
+            webhook.is_active = Set(is_active);
+        }
+        Ok(webhook)
+    }
+}
+
+impl UpdateWebhookRequest {
+    pub fn apply_to(self, webhook: &mut webhooks::ActiveModel) -> Result<()> {
+        if let Some(url) = self.url {
+            webhook.url = Set(normalize_webhook_url(&url)?);
+        }
+        if let Some(events) = self.events {
+            validate_events(&events)?;
+            webhook.events = Set(serde_json::json!(events));
+        }
+        if let Some(is_active) = self.is_active {
+            webhook.is_active = Set(is_active);
+        }
+        if self.rotate_secret {
+            rotate_secret(webhook, None);
+        }
+        Ok(())
+    }
+}
+
+/// Replace the signing secret; `previous` is a secret that keeps signing until it expires
+pub fn rotate_secret(webhook: &mut webhooks::ActiveModel, previous: Option<(String, DateTime<Utc>)>) {
+    let (previous_secret, expires_at) = previous.unzip();
+    webhook.previous_secret = Set(previous_secret);
+    webhook.previous_secret_expires_at = Set(expires_at.map(Into::into));
+    webhook.secret = Set(generate_public_token());
+}
+
+impl WebhookResponse {
+    /// Response that reveals the signing secret, for the one time the client needs it
+    pub fn with_secret(webhook: webhooks::Model) -> Self {
+        let secret = webhook.secret.clone();
+        Self { secret: Some(secret), ..webhook.into() }
+    }
+}
+
+impl From<webhooks::Model> for WebhookResponse {
+    fn from(webhook: webhooks::Model) -> Self {
+        Self {
+            id: webhook.id,
+            user_id: webhook.user_id,
+            url: webhook.url,
+            secret: None,
+            events: serde_json::from_value(webhook.events).unwrap_or_default(),
+            is_active: webhook.is_active,
+            previous_secret_expires_at: webhook
+                .previous_secret_expires_at
+                .map(|dt| dt.naive_utc().and_utc())
+                .filter(|expires_at| *expires_at > Utc::now()),
+            last_delivered_at: webhook.last_delivered_at.map(|dt| dt.naive_utc().and_utc()),
+            last_error: webhook.last_error,
+            created_at: webhook.created_at.naive_utc().and_utc(),
+            updated_at: webhook.updated_at.naive_utc().and_utc(),
+        }
+    }
+}
+
+impl From<webhook_deliveries::Model> for WebhookDeliveryResponse {
+    fn from(delivery: webhook_deliveries::Model) -> Self {
+        Self {
+            id: delivery.id,
+            webhook_id: delivery.webhook_id,
+            event: delivery.event,
+            payload: delivery.payload,
+            attempts: delivery.attempts,
+            next_attempt_at: delivery.next_attempt_at.map(|dt| dt.naive_utc().and_utc()),
+            delivered_at: delivery.delivered_at.map(|dt| dt.naive_utc().and_utc()),
+            last_status: delivery.last_status,
+            last_error: delivery.last_error,
+            created_at: delivery.created_at.naive_utc().and_utc(),
+        }
+    }
+}
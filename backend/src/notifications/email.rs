@@ -0,0 +1,63 @@
+use chrono::{DateTime, Utc};
+use chrono_tz::Tz;
+use sea_orm::EntityTrait;
+
+use super::{Notification, NotificationChannel};
+use crate::{
+    db::Database,
+    entities::prelude::*,
+    errors::{AppError, Result},
+    mailer::{format_local, templates, user_timezone, Email, Mailer},
+};
+
+/// Tables whose notifications are emailed; others are only pushed to the app
+const EMAILED_TABLES: &[&str] = &["bookings"];
+
+/// Email the user about notifications that matter outside the app. Record
+/// details are encrypted, so mails only say what happened and when.
+pub struct EmailChannel {
+    db: Database,
+    mailer: Mailer,
+}
+
+impl EmailChannel {
+    pub fn new(db: Database, mailer: Mailer) -> Self {
+        Self { db, mailer }
+    }
+
+    /// The email for a notification, or `None` when it isn't emailed
+    fn render(notification: &Notification, timezone: Tz) -> Option<Email> {
+        let time = |key: &str| {
+            notification.payload.get(key)
+                .and_then(|value| serde_json::from_value::<DateTime<Utc>>(value.clone()).ok())
+                .map(|dt| format_local(dt, timezone))
+        };
+        match notification.table {
+            "bookings" => Some(templates::BOOKING.render(&[("start_time", &time("start_time")?)])),
+            _ => None,
+        }
+    }
+}
+
+#[async_trait::async_trait]
+impl NotificationChannel for EmailChannel {
+    fn name(&self) -> &'static str {
+        "email"
+    }
+
+    async fn deliver(&self, notification: &Notification) -> Result<()> {
+        if !EMAILED_TABLES.contains(&notification.table) {
+            return Ok(());
+        }
+        let timezone = user_timezone(&self.db.connection, notification.user_id).await?;
+        let Some(email) = Self::render(notification, timezone) else {
+            return Ok(());
+        };
+        let user = Users::find_by_id(notification.user_id)
+            .one(&self.db.connection)
+            .await
+            .map_err(|e| AppError::Database(e.into()))?
+            .ok_or_else(|| AppError::NotFound("User not found".to_string()))?;
+        self.mailer.send(&user.email, email).await
+    }
+}
@@ -0,0 +1,75 @@
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use std::sync::Arc;
+use uuid::Uuid;
+
+use crate::errors::Result;
+
+pub mod email;
+pub mod websocket;
+
+/// Something a user should be told about. Event details stay encrypted, so a
+/// notification only carries ids and times; clients look up the rest themselves.
+#[derive(Debug, Clone, Serialize)]
+pub struct Notification {
+    pub user_id: Uuid,
+    /// Table of the record that triggered it, e.g. `reminders`
+    pub table: &'static str,
+    pub record_id: Uuid,
+    pub payload: serde_json::Value,
+}
+
+/// A way of reaching users, e.g. WebSocket push today and email or mobile push later
+#[async_trait::async_trait]
+pub trait NotificationChannel: Send + Sync {
+    fn name(&self) -> &'static str;
+
+    async fn deliver(&self, notification: &Notification) -> Result<()>;
+}
+
+/// Fans a notification out to every configured channel; one failing channel
+/// doesn't keep the others from delivering
+#[derive(Clone, Default)]
+pub struct Notifier {
+    channels: Vec<Arc<dyn NotificationChannel>>,
+}
+
+impl Notifier {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_channel(mut self, channel: impl NotificationChannel + 'static) -> Self {
+        self.channels.push(Arc::new(channel));
+        self
+    }
+
+    pub async fn notify(&self, notification: &Notification) {
+        for channel in &self.channels {
+            if let Err(e) = channel.deliver(notification).await {
+                tracing::error!(
+                    "Failed to deliver {} notification {} via {}: {}",
+                    notification.table, notification.record_id, channel.name(), e
+                );
+            }
+        }
+    }
+}
+
+/// Payload of a fired reminder
+#[derive(Debug, Serialize)]
+pub struct ReminderPayload {
+    pub event_id: Uuid,
+    /// Start of the occurrence the reminder is for, if the event has a start time
+    pub occurrence_start: Option<DateTime<Utc>>,
+    pub fire_at: DateTime<Utc>,
+}
+
+/// Payload of a booking made through a booking page
+#[derive(Debug, Serialize)]
+pub struct BookingPayload {
+    pub booking_page_id: Uuid,
+    pub event_id: Uuid,
+    pub start_time: DateTime<Utc>,
+    pub end_time: DateTime<Utc>,
+}
@@ -0,0 +1,35 @@
+use super::{Notification, NotificationChannel};
+use crate::{
+    errors::Result,
+    websocket::{WebSocketMessage, WebSocketState},
+};
+
+/// Push notifications to the user's open WebSocket connections on this instance
+pub struct WebSocketChannel {
+    ws_state: WebSocketState,
+}
+
+impl WebSocketChannel {
+    pub fn new(ws_state: WebSocketState) -> Self {
+        Self { ws_state }
+    }
+}
+
+#[async_trait::async_trait]
+impl NotificationChannel for WebSocketChannel {
+    fn name(&self) -> &'static str {
+        "websocket"
+    }
+
+    async fn deliver(&self, notification: &Notification) -> Result<()> {
+        let ws_message = WebSocketMessage {
+            event_type: "NOTIFICATION".to_string(),
+            table: notification.table.to_string(),
+            user_id: notification.user_id,
+            record_id: Some(notification.record_id),
+            data: Some(notification.payload.clone()),
+        };
+        self.ws_state.broadcast_to_user(&notification.user_id, ws_message, None).await;
+        Ok(())
+    }
+}
@@ -0,0 +1,156 @@
+//! HTTP requests to URLs chosen by users, such as calendar feeds. They must not reach the
+//! server's own network: hosts are resolved when each connection is made and only public
+//! addresses are used, so a name can't be pointed at an internal address after it was saved.
+
+use std::{
+    net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr},
+    sync::Arc,
+};
+
+use reqwest::{
+    dns::{Addrs, Name, Resolve, Resolving},
+    redirect::Policy,
+    Url,
+};
+
+/// Most redirects followed before a request fails
+const MAX_REDIRECTS: usize = 10;
+
+/// Whether `ip` is on the public internet; loopback, private, link-local, unique-local,
+/// shared (CGNAT), multicast and unspecified addresses are not
+pub fn is_public(ip: IpAddr) -> bool {
+    match ip {
+        IpAddr::V4(ip) => is_public_v4(ip),
+        IpAddr::V6(ip) => match ip.to_ipv4_mapped() {
+            Some(mapped) => is_public_v4(mapped),
+            None => is_public_v6(ip),
+        },
+    }
+}
+
+fn is_public_v4(ip: Ipv4Addr) -> bool {
+    let [first, second, ..] = ip.octets();
+    !(ip.is_loopback()
+        || ip.is_private()
+        || ip.is_link_local()
+        || ip.is_broadcast()
+        || ip.is_multicast()
+        || ip.is_unspecified()
+        || first == 0
+        || (first == 100 && (64..128).contains(&second)))
+}
+
+fn is_public_v6(ip: Ipv6Addr) -> bool {
+    let first = ip.segments()[0];
+    !(ip.is_loopback()
+        || ip.is_multicast()
+        || ip.is_unspecified()
+        || (first & 0xfe00) == 0xfc00
+        || (first & 0xffc0) == 0xfe80)
+}
+
+/// Reject a URL whose host is a non-public IP address. Names are checked when they are
+/// resolved; this catches addresses written into the URL, which skip resolution.
+pub fn check_url(url: &Url) -> Result<(), String> {
+    let host = url.host_str().ok_or("URL has no host")?;
+    let Ok(ip) = host.trim_start_matches('[').trim_end_matches(']').parse::<IpAddr>() else {
+        return Ok(());
+    };
+    if !is_public(ip) {
+        return Err(format!("{} is not a public address", ip));
+    }
+    Ok(())
+}
+
+/// Resolves names to their public addresses only
+struct PublicResolver;
+
+impl Resolve for PublicResolver {
+    fn resolve(&self, name: Name) -> Resolving {
+        Box::pin(async move {
+            let host = name.as_str().to_string();
+            let addrs: Vec<SocketAddr> = tokio::net::lookup_host((host.as_str(), 0)).await?.collect();
+            let public: Vec<SocketAddr> = addrs.into_iter().filter(|addr| is_public(addr.ip())).collect();
+            if public.is_empty() {
+                return Err(format!("{} has no public address", host).into());
+            }
+            Ok(Box::new(public.into_iter()) as Addrs)
+        })
+    }
+}
+
+/// A client builder for user-chosen URLs. Redirects are followed only to public hosts.
+pub fn client_builder() -> reqwest::ClientBuilder {
+    reqwest::Client::builder()
+        .dns_resolver(Arc::new(PublicResolver))
+        .redirect(Policy::custom(|attempt| {
+            if attempt.previous().len() >= MAX_REDIRECTS {
+                return attempt.error("too many redirects");
+            }
+            match check_url(attempt.url()) {
+                Ok(()) => attempt.follow(),
+                Err(e) => attempt.error(e),
+            }
+        }))
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::{response::Redirect, routing::get, Router};
+    use reqwest::Url;
+
+    use super::{check_url, client_builder};
+
+    fn check(url: &str) -> Result<(), String> {
+        check_url(&Url::parse(url).unwrap())
+    }
+
+    #[test]
+    fn addresses_outside_the_public_internet_are_rejected() {
+        for url in [
+            "http://127.0.0.1/feed.ics",
+            "http://10.0.0.5/",
+            "http://172.16.0.1/",
+            "http://192.168.1.1:8080/",
+            "http://169.254.169.254/latest/meta-data/",
+            "http://100.64.0.1/",
+            "http://0.0.0.0/",
+            "http://[::1]/",
+            "http://[fe80::1]/",
+            "http://[fd00::1]/",
+            "http://[::ffff:127.0.0.1]/",
+        ] {
+            assert!(check(url).is_err(), "{} was allowed", url);
+        }
+    }
+
+    #[test]
+    fn public_addresses_and_names_are_allowed() {
+        assert_eq!(check("https://93.184.215.14/feed.ics"), Ok(()));
+        assert_eq!(check("https://[2606:2800:21f:cb07:6820:80da:af6b:8b2c]/"), Ok(()));
+        // Checked when resolved instead
+        assert_eq!(check("https://localhost/"), Ok(()));
+    }
+
+    /// Serve a redirect to `location` on a loopback port, standing in for a public server; the
+    /// client reaches it because literal addresses skip the resolver, which is why callers run
+    /// `check_url` first
+    async fn redirecting_to(location: &'static str) -> String {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = listener.local_addr().unwrap();
+        let app = Router::new().route("/", get(move || async move { Redirect::temporary(location) }));
+        tokio::spawn(async move { axum::serve(listener, app).await });
+        format!("http://{}/", address)
+    }
+
+    #[tokio::test]
+    async fn redirects_to_internal_hosts_are_not_followed() {
+        let client = client_builder().build().unwrap();
+        for location in ["http://169.254.169.254/latest/meta-data/", "http://[::1]:1/", "http://localhost:1/"] {
+            let error = client.get(redirecting_to(location).await).send().await.unwrap_err();
+            assert!(error.is_redirect() || error.is_connect(), "{}: {:?}", location, error);
+            let message = format!("{:?}", error);
+            assert!(message.contains("not a public address") || message.contains("no public address"), "{}", message);
+        }
+    }
+}
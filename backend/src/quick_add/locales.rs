@@ -0,0 +1,128 @@
+use chrono::Weekday;
+
+use crate::entities::can_do_list::TaskPriority;
+
+/// Words of one language the rule parser understands. All words are lowercase; phrases are
+/// matched word by word, longest first.
+pub struct Vocabulary {
+    /// BCP 47 primary language subtag, e.g. `en`
+    pub code: &'static str,
+    /// Days from today, e.g. "tomorrow" is 1
+    pub relative_days: &'static [(&'static [&'static str], i64)],
+    /// Full weekday names and abbreviations that aren't everyday words
+    pub weekdays: &'static [(&'static str, Weekday)],
+    /// Full and abbreviated month names
+    pub months: &'static [(&'static str, u32)],
+    /// Words that may stand before a weekday without changing it, e.g. "next monday"
+    pub next: &'static [&'static str],
+    /// Word introducing "in 3 days"
+    pub within: &'static [&'static str],
+    pub day_units: &'static [&'static str],
+    pub week_units: &'static [&'static str],
+    pub month_units: &'static [&'static str],
+    /// Words that may stand before a date, e.g. "on monday"; dropped with the date
+    pub date_prepositions: &'static [&'static str],
+    /// Words that may stand before a time, e.g. "at 3pm"; dropped with the time
+    pub time_prepositions: &'static [&'static str],
+    /// Words following an hour, e.g. "15 uhr"
+    pub hour_suffixes: &'static [&'static str],
+    /// Named times of day, as (hour, minute)
+    pub named_times: &'static [(&'static str, (u32, u32))],
+    /// Whether "3pm" style 12-hour times are understood
+    pub twelve_hour: bool,
+    /// Whether numeric dates put the day first, e.g. "20.10." rather than "10/20"
+    pub day_first: bool,
+    /// Word introducing a duration, e.g. "for 1h"
+    pub durations: &'static [&'static str],
+    pub hour_units: &'static [&'static str],
+    pub minute_units: &'static [&'static str],
+    /// Names usable after `!`, in addition to the English ones every locale accepts
+    pub priorities: &'static [(&'static str, TaskPriority)],
+}
+
+pub static ENGLISH: Vocabulary = Vocabulary {
+    code: "en",
+    relative_days: &[
+        (&["day", "after", "tomorrow"], 2),
+        (&["tomorrow"], 1),
+        (&["tmrw"], 1),
+        (&["today"], 0),
+        (&["tonight"], 0),
+    ],
+    weekdays: &[
+        ("monday", Weekday::Mon), ("mon", Weekday::Mon),
+        ("tuesday", Weekday::Tue), ("tue", Weekday::Tue), ("tues", Weekday::Tue),
+        ("wednesday", Weekday::Wed), ("wed", Weekday::Wed),
+        ("thursday", Weekday::Thu), ("thu", Weekday::Thu), ("thurs", Weekday::Thu),
+        ("friday", Weekday::Fri), ("fri", Weekday::Fri),
+        ("saturday", Weekday::Sat),
+        ("sunday", Weekday::Sun),
+    ],
+    months: &[
+        ("january", 1), ("jan", 1), ("february", 2), ("feb", 2), ("march", 3), ("mar", 3),
+        ("april", 4), ("apr", 4), ("may", 5), ("june", 6), ("jun", 6), ("july", 7), ("jul", 7),
+        ("august", 8), ("aug", 8), ("september", 9), ("sep", 9), ("sept", 9), ("october", 10),
+        ("oct", 10), ("november", 11), ("nov", 11), ("december", 12), ("dec", 12),
+    ],
+    next: &["next"],
+    within: &["in"],
+    day_units: &["day", "days"],
+    week_units: &["week", "weeks"],
+    month_units: &["month", "months"],
+    date_prepositions: &["on"],
+    time_prepositions: &["at", "@"],
+    hour_suffixes: &["o'clock"],
+    named_times: &[("noon", (12, 0)), ("midnight", (0, 0))],
+    twelve_hour: true,
+    day_first: false,
+    durations: &["for"],
+    hour_units: &["h", "hr", "hrs", "hour", "hours"],
+    minute_units: &["m", "min", "mins", "minute", "minutes"],
+    priorities: &[],
+};
+
+pub static GERMAN: Vocabulary = Vocabulary {
+    code: "de",
+    relative_days: &[
+        (&["übermorgen"], 2),
+        (&["morgen"], 1),
+        (&["heute", "morgen"], 0),
+        (&["heute", "abend"], 0),
+        (&["heute"], 0),
+    ],
+    weekdays: &[
+        ("montag", Weekday::Mon),
+        ("dienstag", Weekday::Tue),
+        ("mittwoch", Weekday::Wed),
+        ("donnerstag", Weekday::Thu),
+        ("freitag", Weekday::Fri),
+        ("samstag", Weekday::Sat), ("sonnabend", Weekday::Sat),
+        ("sonntag", Weekday::Sun),
+    ],
+    months: &[
+        ("januar", 1), ("jan", 1), ("jänner", 1), ("februar", 2), ("feb", 2), ("märz", 3),
+        ("mär", 3), ("april", 4), ("apr", 4), ("mai", 5), ("juni", 6), ("jun", 6), ("juli", 7),
+        ("jul", 7), ("august", 8), ("aug", 8), ("september", 9), ("sep", 9), ("sept", 9),
+        ("oktober", 10), ("okt", 10), ("november", 11), ("nov", 11), ("dezember", 12), ("dez", 12),
+    ],
+    next: &["nächsten", "nächste", "nächster", "kommenden", "kommende"],
+    within: &["in"],
+    day_units: &["tag", "tagen"],
+    week_units: &["woche", "wochen"],
+    month_units: &["monat", "monaten"],
+    date_prepositions: &["am"],
+    time_prepositions: &["um"],
+    hour_suffixes: &["uhr"],
+    named_times: &[("mittag", (12, 0)), ("mitternacht", (0, 0))],
+    twelve_hour: false,
+    day_first: true,
+    durations: &["für"],
+    hour_units: &["h", "std", "stunde", "stunden"],
+    minute_units: &["m", "min", "minute", "minuten"],
+    priorities: &[
+        ("niedrig", TaskPriority::Low),
+        ("mittel", TaskPriority::Medium),
+        ("hoch", TaskPriority::High),
+        ("dringend", TaskPriority::Urgent),
+    ],
+};
@@ -0,0 +1,51 @@
+//! Quick-add parsing: turns a line such as "Dentist tomorrow 3pm /health #errand" into a title
+//! and the dates, project hint and tags around it. The text is only read, never stored; the
+//! client encrypts the result before it creates the task or event.
+
+mod locales;
+mod rules;
+
+use chrono::{DateTime, NaiveDate, Utc};
+use chrono_tz::Tz;
+
+use crate::models::quick_add::QuickAddResponse;
+use rules::RuleParser;
+
+/// When a line is read, so relative dates such as "tomorrow" can be resolved
+#[derive(Debug, Clone, Copy)]
+pub struct ParseContext {
+    pub now: DateTime<Utc>,
+    pub timezone: Tz,
+}
+
+impl ParseContext {
+    pub fn today(&self) -> NaiveDate {
+        self.now.with_timezone(&self.timezone).date_naive()
+    }
+}
+
+/// Reads quick-add lines in one language
+pub trait QuickAddParser: Send + Sync {
+    /// BCP 47 primary language subtag, e.g. `en`
+    fn locale(&self) -> &'static str;
+
+    fn parse(&self, text: &str, context: &ParseContext) -> QuickAddResponse;
+}
+
+/// Available parsers; the first one is the fallback
+static PARSERS: &[&dyn QuickAddParser] = &[
+    &RuleParser::new(&locales::ENGLISH),
+    &RuleParser::new(&locales::GERMAN),
+];
+
+/// The parser for a locale such as `de-AT`, chosen by its language; English when the locale is
+/// missing or not supported
+pub fn parser_for(locale: Option<&str>) -> &'static dyn QuickAddParser {
+    let language = locale
+        .and_then(|locale| locale.split(['-', '_']).next())
+        .map(|language| language.trim().to_lowercase());
+    language
+        .and_then(|language| PARSERS.iter().find(|parser| parser.locale() == language))
+        .copied()
+        .unwrap_or(PARSERS[0])
+}
@@ -0,0 +1,449 @@
+use chrono::{Datelike, Days, Duration, Months, NaiveDate, NaiveTime, TimeZone, Utc, Weekday};
+
+use super::{locales::Vocabulary, ParseContext, QuickAddParser};
+use crate::{
+    entities::can_do_list::TaskPriority,
+    models::quick_add::{QuickAddKind, QuickAddResponse},
+};
+
+/// Longest duration understood, a week
+const MAX_DURATION_MINUTES: f64 = 7.0 * 24.0 * 60.0;
+/// Largest "in 3 days" style offset understood
+const MAX_OFFSET: u32 = 3650;
+/// Punctuation that may trail a word without being part of it
+const TRAILING_PUNCTUATION: [char; 4] = [',', ';', '?', '!'];
+
+/// Reads dates, times and durations word by word with one language's `Vocabulary`; `#tags`,
+/// `/project` hints and `!priority` markers work the same in every language
+pub struct RuleParser {
+    vocabulary: &'static Vocabulary,
+}
+
+impl RuleParser {
+    pub const fn new(vocabulary: &'static Vocabulary) -> Self {
+        Self { vocabulary }
+    }
+
+    /// A date at the start of `words` and how many words it takes
+    fn date_at(&self, words: &[String], today: NaiveDate) -> Option<(usize, NaiveDate)> {
+        if let Some(first) = words.first()
+            && self.vocabulary.date_prepositions.contains(&bare(first))
+            && let Some((consumed, date)) = self.date_phrase(&words[1..], today)
+        {
+            return Some((consumed + 1, date));
+        }
+        self.date_phrase(words, today)
+    }
+
+    fn date_phrase(&self, words: &[String], today: NaiveDate) -> Option<(usize, NaiveDate)> {
+        let vocabulary = self.vocabulary;
+        // "day after tomorrow" wins over "tomorrow"
+        if let Some((phrase, days)) = vocabulary
+            .relative_days
+            .iter()
+            .filter(|(phrase, _)| starts_with_phrase(words, phrase))
+            .max_by_key(|(phrase, _)| phrase.len())
+        {
+            return Some((phrase.len(), today + Duration::days(*days)));
+        }
+
+        let skip = usize::from(words.first().is_some_and(|word| vocabulary.next.contains(&bare(word))));
+        if let Some(word) = words.get(skip)
+            && let Some(weekday) = lookup(vocabulary.weekdays, bare(word))
+        {
+            return Some((skip + 1, coming_weekday(today, weekday)));
+        }
+
+        if let [within, amount, unit, ..] = words
+            && vocabulary.within.contains(&bare(within))
+            && let Some(amount) = number(amount).filter(|amount| *amount <= MAX_OFFSET)
+        {
+            let unit = bare(unit);
+            let date = if vocabulary.day_units.contains(&unit) {
+                today.checked_add_days(Days::new(amount as u64))
+            } else if vocabulary.week_units.contains(&unit) {
+                today.checked_add_days(Days::new(amount as u64 * 7))
+            } else if vocabulary.month_units.contains(&unit) {
+                today.checked_add_months(Months::new(amount))
+            } else {
+                None
+            };
+            if let Some(date) = date {
+                return Some((3, date));
+            }
+        }
+
+        let first = words.first()?;
+        if let Ok(date) = NaiveDate::parse_from_str(first, "%Y-%m-%d") {
+            return Some((1, date));
+        }
+        if let Some(date) = self.numeric_date(first, today) {
+            return Some((1, date));
+        }
+        self.named_month_date(words, today)
+    }
+
+    /// "20.10." and "20.10.2025" for day-first languages, "10/20" and "10/20/2025" otherwise
+    fn numeric_date(&self, word: &str, today: NaiveDate) -> Option<NaiveDate> {
+        let separator = if self.vocabulary.day_first { '.' } else { '/' };
+        let parts: Vec<u32> = word
+            .trim_end_matches(separator)
+            .split(separator)
+            .map(number)
+            .collect::<Option<_>>()?;
+        let (day, month, year) = match (parts.as_slice(), self.vocabulary.day_first) {
+            ([day, month], true) | ([month, day], false) => (*day, *month, None),
+            ([day, month, year], true) | ([month, day, year], false) => (*day, *month, Some(*year)),
+            _ => return None,
+        };
+        // Two-digit years are this century's
+        let year = year.map(|year| if year < 100 { 2000 + year } else { year });
+        day_in_year(today, day, month, year)
+    }
+
+    /// "oct 20", "october 20th, 2025", "20 oct" and "20. oktober"
+    fn named_month_date(&self, words: &[String], today: NaiveDate) -> Option<(usize, NaiveDate)> {
+        let [first, second, rest @ ..] = words else {
+            return None;
+        };
+        let months = self.vocabulary.months;
+        let (day, month) = match (lookup(months, bare(first)), lookup(months, bare(second))) {
+            (Some(month), None) => (day_number(second)?, month),
+            (None, Some(month)) => (day_number(first)?, month),
+            _ => return None,
+        };
+        let year = rest.first().and_then(|word| number(bare(word))).filter(|year| (1000..=9999).contains(year));
+        let date = day_in_year(today, day, month, year)?;
+        Some((2 + usize::from(year.is_some()), date))
+    }
+
+    /// A time of day at the start of `words` and how many words it takes
+    fn time_at(&self, words: &[String]) -> Option<(usize, NaiveTime)> {
+        if let Some(first) = words.first()
+            && self.vocabulary.time_prepositions.contains(&bare(first))
+            && let Some((consumed, time)) = self.time_phrase(&words[1..])
+        {
+            return Some((consumed + 1, time));
+        }
+        self.time_phrase(words)
+    }
+
+    fn time_phrase(&self, words: &[String]) -> Option<(usize, NaiveTime)> {
+        let vocabulary = self.vocabulary;
+        let first = bare(words.first()?);
+        let next = words.get(1).map(|word| bare(word));
+        if let Some((hour, minute)) = lookup(vocabulary.named_times, first) {
+            return Some((1, NaiveTime::from_hms_opt(hour, minute, 0)?));
+        }
+
+        // "3pm", "3:30pm" and "3 pm"
+        if vocabulary.twelve_hour {
+            for (marker, pm) in [("am", false), ("pm", true)] {
+                if let Some(clock) = first.strip_suffix(marker)
+                    && let Some(time) = twelve_hour_time(clock, pm)
+                {
+                    return Some((1, time));
+                }
+                if next == Some(marker)
+                    && let Some(time) = twelve_hour_time(first, pm)
+                {
+                    return Some((2, time));
+                }
+            }
+        }
+
+        let has_suffix = next.is_some_and(|next| vocabulary.hour_suffixes.contains(&next));
+        // "15:30" and "15:30 uhr"
+        if let Some(time) = clock_time(first) {
+            return Some((1 + usize::from(has_suffix), time));
+        }
+        // "15 uhr" and "15uhr"
+        if let Some(hour) = number(first).filter(|_| has_suffix) {
+            return Some((2, NaiveTime::from_hms_opt(hour, 0, 0)?));
+        }
+        vocabulary.hour_suffixes.iter().find_map(|suffix| {
+            let hour = number(first.strip_suffix(suffix)?)?;
+            Some((1, NaiveTime::from_hms_opt(hour, 0, 0)?))
+        })
+    }
+
+    /// A duration such as "for 1h30m" or "for 90 minutes", in minutes
+    fn duration_at(&self, words: &[String]) -> Option<(usize, i64)> {
+        let (first, rest) = words.split_first()?;
+        if !self.vocabulary.durations.contains(&bare(first)) {
+            return None;
+        }
+        let first = bare(rest.first()?);
+        let (consumed, minutes) = match self.compact_duration(first) {
+            Some(minutes) => (2, minutes),
+            None => {
+                let unit = bare(rest.get(1)?);
+                (3, amount(first)? * self.unit_minutes(unit)?)
+            }
+        };
+        let minutes = minutes.round();
+        (1.0..=MAX_DURATION_MINUTES).contains(&minutes).then_some((consumed, minutes as i64))
+    }
+
+    /// "90min", "1h", "1.5h" and "1h30m"
+    fn compact_duration(&self, word: &str) -> Option<f64> {
+        let mut minutes = 0.0;
+        let mut rest = word;
+        while !rest.is_empty() {
+            let amount_end = rest.find(|c: char| !(c.is_ascii_digit() || c == '.' || c == ',')).unwrap_or(rest.len());
+            let unit_end = rest[amount_end..]
+                .find(|c: char| !c.is_alphabetic())
+                .map_or(rest.len(), |end| amount_end + end);
+            minutes += amount(&rest[..amount_end])? * self.unit_minutes(&rest[amount_end..unit_end])?;
+            rest = &rest[unit_end..];
+        }
+        Some(minutes)
+    }
+
+    fn unit_minutes(&self, unit: &str) -> Option<f64> {
+        if self.vocabulary.hour_units.contains(&unit) {
+            Some(60.0)
+        } else if self.vocabulary.minute_units.contains(&unit) {
+            Some(1.0)
+        } else {
+            None
+        }
+    }
+
+    /// `!1` to `!4`, the English names and the language's own
+    fn priority(&self, name: &str) -> Option<TaskPriority> {
+        let name = name.to_lowercase();
+        match name.as_str() {
+            "1" | "low" => Some(TaskPriority::Low),
+            "2" | "medium" => Some(TaskPriority::Medium),
+            "3" | "high" => Some(TaskPriority::High),
+            "4" | "urgent" => Some(TaskPriority::Urgent),
+            name => lookup(self.vocabulary.priorities, name),
+        }
+    }
+}
+
+impl QuickAddParser for RuleParser {
+    fn locale(&self) -> &'static str {
+        self.vocabulary.code
+    }
+
+    /// Only the first date, time, duration, project hint and priority count; later ones stay in
+    /// the title
+    fn parse(&self, text: &str, context: &ParseContext) -> QuickAddResponse {
+        let today = context.today();
+        let tokens: Vec<&str> = text.split_whitespace().collect();
+        let words: Vec<String> = tokens
+            .iter()
+            .map(|token| token.to_lowercase().trim_end_matches(TRAILING_PUNCTUATION).to_string())
+            .collect();
+
+        let mut response = QuickAddResponse { locale: self.locale(), ..Default::default() };
+        let mut title = Vec::new();
+        let mut index = 0;
+        while index < tokens.len() {
+            let token = tokens[index];
+            let rest = &words[index..];
+            let consumed = if let Some(tag) = marked(token, '#') {
+                if !response.tags.iter().any(|existing| existing.to_lowercase() == tag.to_lowercase()) {
+                    response.tags.push(tag.to_string());
+                }
+                1
+            } else if let Some(project) = marked(token, '/').filter(|_| response.project_hint.is_none()) {
+                response.project_hint = Some(project.to_string());
+                1
+            } else if let Some(priority) = marked(token, '!')
+                .filter(|_| response.priority.is_none())
+                .and_then(|name| self.priority(name))
+            {
+                response.priority = Some(priority);
+                1
+            } else if let Some((consumed, date)) = self.date_at(rest, today).filter(|_| response.date.is_none()) {
+                response.date = Some(date);
+                consumed
+            } else if let Some((consumed, time)) = self.time_at(rest).filter(|_| response.time.is_none()) {
+                response.time = Some(time);
+                consumed
+            } else if let Some((consumed, minutes)) = self.duration_at(rest).filter(|_| response.duration_minutes.is_none()) {
+                response.duration_minutes = Some(minutes);
+                consumed
+            } else {
+                title.push(token);
+                1
+            };
+            index += consumed;
+        }
+        response.title = title.join(" ");
+
+        if let Some(time) = response.time {
+            // A time without a date is the next time the clock shows it
+            let date = *response.date.get_or_insert_with(|| {
+                let local_now = context.now.with_timezone(&context.timezone).time();
+                if time > local_now { today } else { today + Duration::days(1) }
+            });
+            let local = date.and_time(time);
+            // A time skipped by a DST change moves an hour later
+            response.at = context
+                .timezone
+                .from_local_datetime(&local)
+                .earliest()
+                .or_else(|| context.timezone.from_local_datetime(&(local + Duration::hours(1))).earliest())
+                .map(|at| at.with_timezone(&Utc));
+            response.kind = QuickAddKind::Event;
+        }
+        response
+    }
+}
+
+/// The word without a trailing full stop, for dictionary lookups
+fn bare(word: &str) -> &str {
+    word.trim_end_matches('.')
+}
+
+fn lookup<T: Copy>(entries: &[(&str, T)], word: &str) -> Option<T> {
+    entries.iter().find(|(name, _)| *name == word).map(|(_, value)| *value)
+}
+
+fn starts_with_phrase(words: &[String], phrase: &[&str]) -> bool {
+    words.len() >= phrase.len() && words.iter().zip(phrase).all(|(word, part)| bare(word) == *part)
+}
+
+/// The name after a `#`, `/` or `!` marker
+fn marked(token: &str, marker: char) -> Option<&str> {
+    token
+        .strip_prefix(marker)
+        .map(|name| name.trim_end_matches(TRAILING_PUNCTUATION))
+        .filter(|name| !name.is_empty() && !name.starts_with(marker))
+}
+
+/// Up to four plain digits
+fn number(word: &str) -> Option<u32> {
+    (!word.is_empty() && word.len() <= 4 && word.bytes().all(|b| b.is_ascii_digit()))
+        .then(|| word.parse().ok())
+        .flatten()
+}
+
+/// A positive amount such as "90", "1.5" or "1,5"
+fn amount(word: &str) -> Option<f64> {
+    word.replace(',', ".").parse::<f64>().ok().filter(|amount| amount.is_finite() && *amount > 0.0)
+}
+
+/// A day of the month such as "20", "20." or "20th"
+fn day_number(word: &str) -> Option<u32> {
+    let word = bare(word);
+    let digits = ["st", "nd", "rd", "th"].iter().find_map(|suffix| word.strip_suffix(suffix)).unwrap_or(word);
+    number(digits).filter(|day| (1..=31).contains(day))
+}
+
+/// The date in `year`, or without one the next time the day comes round
+fn day_in_year(today: NaiveDate, day: u32, month: u32, year: Option<u32>) -> Option<NaiveDate> {
+    if let Some(year) = year {
+        return NaiveDate::from_ymd_opt(year as i32, month, day);
+    }
+    NaiveDate::from_ymd_opt(today.year(), month, day)
+        .filter(|date| *date >= today)
+        .or_else(|| NaiveDate::from_ymd_opt(today.year() + 1, month, day))
+}
+
+/// The next `weekday` after today, never today itself
+fn coming_weekday(today: NaiveDate, weekday: Weekday) -> NaiveDate {
+    let days = (weekday.num_days_from_monday() + 7 - today.weekday().num_days_from_monday()) % 7;
+    today + Duration::days(if days == 0 { 7 } else { days as i64 })
+}
+
+/// "3" or "3:30" on a 12-hour clock
+fn twelve_hour_time(clock: &str, pm: bool) -> Option<NaiveTime> {
+    let (hour, minute) = match clock.split_once(':') {
+        Some((hour, minute)) if minute.len() == 2 => (number(hour)?, number(minute)?),
+        Some(_) => return None,
+        None => (number(clock)?, 0),
+    };
+    if !(1..=12).contains(&hour) {
+        return None;
+    }
+    NaiveTime::from_hms_opt(hour % 12 + if pm { 12 } else { 0 }, minute, 0)
+}
+
+/// "15:30" on a 24-hour clock
+fn clock_time(word: &str) -> Option<NaiveTime> {
+    let (hour, minute) = word.split_once(':')?;
+    if minute.len() != 2 {
+        return None;
+    }
+    NaiveTime::from_hms_opt(number(hour)?, number(minute)?, 0)
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{DateTime, NaiveDate, Utc};
+    use chrono_tz::Tz;
+
+    use super::RuleParser;
+    use crate::{
+        models::quick_add::QuickAddResponse,
+        quick_add::{locales::ENGLISH, ParseContext, QuickAddParser},
+    };
+
+    fn parse_at(text: &str, now: &str, timezone: Tz) -> QuickAddResponse {
+        let now = now.parse::<DateTime<Utc>>().unwrap();
+        RuleParser::new(&ENGLISH).parse(text, &ParseContext { now, timezone })
+    }
+
+    /// Parse English text in UTC on New Year's Eve, a Wednesday
+    fn parse(text: &str) -> QuickAddResponse {
+        parse_at(text, "2025-12-31T10:00:00Z", Tz::UTC)
+    }
+
+    fn date(year: i32, month: u32, day: u32) -> Option<NaiveDate> {
+        NaiveDate::from_ymd_opt(year, month, day)
+    }
+
+    #[test]
+    fn relative_dates_roll_over_into_the_next_year() {
+        let response = parse("Pay rent tomorrow");
+        assert_eq!(response.title, "Pay rent");
+        assert_eq!(response.date, date(2026, 1, 1));
+
+        assert_eq!(parse("Call mom day after tomorrow").date, date(2026, 1, 2));
+        assert_eq!(parse("Renew passport in 2 weeks").date, date(2026, 1, 14));
+        assert_eq!(parse("Dentist in 2 months").date, date(2026, 2, 28));
+    }
+
+    #[test]
+    fn weekdays_are_always_in_the_future() {
+        assert_eq!(parse("Review friday").date, date(2026, 1, 2));
+        // Today is a Wednesday, so "wednesday" means next week's
+        assert_eq!(parse("Review wednesday").date, date(2026, 1, 7));
+        assert_eq!(parse("Standup next monday").date, date(2026, 1, 5));
+        assert_eq!(parse("Standup on mon").title, "Standup");
+    }
+
+    #[test]
+    fn dates_without_a_year_are_the_next_time_they_come_round() {
+        assert_eq!(parse("Party dec 31").date, date(2025, 12, 31));
+        assert_eq!(parse("Gift shopping dec 20").date, date(2026, 12, 20));
+        assert_eq!(parse("Taxes 4/15").date, date(2026, 4, 15));
+    }
+
+    #[test]
+    fn times_without_a_date_are_the_next_time_the_clock_shows_them() {
+        let response = parse("Call 11am");
+        assert_eq!(response.date, date(2025, 12, 31));
+        assert_eq!(response.at, Some("2025-12-31T11:00:00Z".parse().unwrap()));
+
+        let response = parse("Call 9am");
+        assert_eq!(response.date, date(2026, 1, 1));
+        assert_eq!(response.at, Some("2026-01-01T09:00:00Z".parse().unwrap()));
+    }
+
+    #[test]
+    fn today_is_the_users_local_date() {
+        // Already New Year's Day in Berlin
+        let response = parse_at("Brunch tomorrow 11am", "2025-12-31T23:30:00Z", chrono_tz::Europe::Berlin);
+        assert_eq!(response.date, date(2026, 1, 2));
+        assert_eq!(response.at, Some("2026-01-02T10:00:00Z".parse().unwrap()));
+
+        let response = parse_at("Brunch 11am", "2025-12-31T23:30:00Z", chrono_tz::Europe::Berlin);
+        assert_eq!(response.date, date(2026, 1, 1));
+    }
+}
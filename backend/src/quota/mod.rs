@@ -0,0 +1,136 @@
+use sea_orm::{ConnectionTrait, DbBackend, FromQueryResult, Statement};
+use std::env;
+use uuid::Uuid;
+
+use crate::errors::{AppError, Result};
+
+/// User-owned tables that count towards storage usage
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum QuotaTable {
+    Projects,
+    CanDoList,
+    Calendars,
+    CalendarEvents,
+    Notes,
+}
+
+impl QuotaTable {
+    pub fn table_name(self) -> &'static str {
+        match self {
+            QuotaTable::Projects => "projects",
+            QuotaTable::CanDoList => "can_do_list",
+            QuotaTable::Calendars => "calendars",
+            QuotaTable::CalendarEvents => "calendar_events",
+            QuotaTable::Notes => "notes",
+        }
+    }
+
+    fn env_key(self) -> &'static str {
+        match self {
+            QuotaTable::Projects => "QUOTA_MAX_PROJECTS",
+            QuotaTable::CanDoList => "QUOTA_MAX_CAN_DO_ITEMS",
+            QuotaTable::Calendars => "QUOTA_MAX_CALENDARS",
+            QuotaTable::CalendarEvents => "QUOTA_MAX_CALENDAR_EVENTS",
+            QuotaTable::Notes => "QUOTA_MAX_NOTES",
+        }
+    }
+}
+
+/// Row count and on-disk size of one user's records in a table
+#[derive(Debug, FromQueryResult)]
+pub struct TableStats {
+    pub records: i64,
+    pub trashed: i64,
+    pub bytes: i64,
+}
+
+impl TableStats {
+    pub async fn load<C: ConnectionTrait>(db: &C, user_id: Uuid, table: QuotaTable) -> Result<Self> {
+        // Table names come from the fixed enum above, never from user input
+        let backend = db.get_database_backend();
+        let sql = match backend {
+            // SQLite has no per-row size; the encrypted payload makes up nearly all of it
+            DbBackend::Sqlite => format!(
+                "SELECT COUNT(*) AS records, COUNT(t.deleted_at) AS trashed, \
+                 COALESCE(SUM(LENGTH(t.encrypted_data) + LENGTH(t.iv) + LENGTH(t.salt)), 0) AS bytes \
+                 FROM {} t WHERE t.user_id = $1",
+                table.table_name()
+            ),
+            _ => format!(
+                "SELECT COUNT(*)::bigint AS records, COUNT(t.deleted_at)::bigint AS trashed, \
+                 COALESCE(SUM(pg_column_size(t.*)), 0)::bigint AS bytes \
+                 FROM {} t WHERE t.user_id = $1",
+                table.table_name()
+            ),
+        };
+
+        Self::find_by_statement(Statement::from_sql_and_values(backend, sql, [user_id.into()]))
+            .one(db)
+            .await
+            .map_err(|e| AppError::Database(e.into()))?
+            .ok_or_else(|| AppError::Internal("Usage query returned no rows".to_string()))
+    }
+}
+
+/// Per-user record limits for shared instances; a table without a limit is unbounded.
+/// Trashed records count towards the limit until they are purged.
+#[derive(Debug, Clone, Default)]
+pub struct Quotas {
+    pub projects: Option<u64>,
+    pub can_do_list: Option<u64>,
+    pub calendars: Option<u64>,
+    pub calendar_events: Option<u64>,
+    pub notes: Option<u64>,
+}
+
+impl Quotas {
+    pub fn from_env() -> Self {
+        let limit = |table: QuotaTable| {
+            env::var(table.env_key())
+                .ok()
+                .and_then(|v| v.parse().ok())
+        };
+
+        Self {
+            projects: limit(QuotaTable::Projects),
+            can_do_list: limit(QuotaTable::CanDoList),
+            calendars: limit(QuotaTable::Calendars),
+            calendar_events: limit(QuotaTable::CalendarEvents),
+            notes: limit(QuotaTable::Notes),
+        }
+    }
+
+    pub fn limit(&self, table: QuotaTable) -> Option<u64> {
+        match table {
+            QuotaTable::Projects => self.projects,
+            QuotaTable::CanDoList => self.can_do_list,
+            QuotaTable::Calendars => self.calendars,
+            QuotaTable::CalendarEvents => self.calendar_events,
+            QuotaTable::Notes => self.notes,
+        }
+    }
+
+    /// Fail with `QuotaExceeded` if creating `additional` records would push the user past the limit
+    pub async fn enforce<C: ConnectionTrait>(
+        &self,
+        db: &C,
+        user_id: Uuid,
+        table: QuotaTable,
+        additional: u64,
+    ) -> Result<()> {
+        let Some(limit) = self.limit(table) else {
+            return Ok(());
+        };
+
+        let stats = TableStats::load(db, user_id, table).await?;
+        if stats.records as u64 + additional > limit {
+            return Err(AppError::QuotaExceeded(format!(
+                "The limit of {} records in {} has been reached",
+                limit,
+                table.table_name()
+            )));
+        }
+
+        Ok(())
+    }
+}
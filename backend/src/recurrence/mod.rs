@@ -0,0 +1,228 @@
+use chrono::{DateTime, Duration, NaiveDate, Utc};
+use rrule::{Frequency, RRule, RRuleSet, Tz, Unvalidated};
+
+use crate::{
+    entities::calendar_events,
+    errors::{AppError, Result},
+    models::user_settings::TimePreferences,
+};
+
+/// Widest range the occurrences endpoint will expand in one request
+pub const MAX_OCCURRENCE_WINDOW_DAYS: i64 = 366;
+/// Most occurrences returned for a single series in one request
+const MAX_OCCURRENCES_PER_SERIES: u16 = 2_000;
+/// Upper bound when counting a series' occurrences before a split point
+const MAX_COUNTED_OCCURRENCES: u16 = u16::MAX;
+
+/// A recurring event's schedule, expanded in its owner's timezone so occurrences keep their
+/// local time across daylight saving changes
+#[derive(Debug, Clone)]
+pub struct Series {
+    pub rrule: String,
+    pub start: DateTime<Utc>,
+    pub duration: Duration,
+    pub exceptions: Vec<DateTime<Utc>>,
+    pub time: TimePreferences,
+}
+
+impl Series {
+    /// The schedule of an event in its owner's local time, or `None` when it doesn't recur
+    /// or has no start time
+    pub fn from_event(event: &calendar_events::Model, time: &TimePreferences) -> Option<Self> {
+        let rrule = event.rrule.clone()?;
+        let start = event.start_time?.naive_utc().and_utc();
+        let duration = event
+            .end_time
+            .map(|end| end.naive_utc().and_utc() - start)
+            .unwrap_or_default();
+        Some(Self {
+            rrule,
+            start,
+            duration,
+            exceptions: exceptions_of(event),
+            time: *time,
+        })
+    }
+
+    fn timezone(&self) -> Tz {
+        self.time.timezone.into()
+    }
+
+    fn rule_set(&self, with_exceptions: bool) -> Result<RRuleSet> {
+        let set = parse(&self.rrule, self.start, &self.time)?;
+        if !with_exceptions {
+            return Ok(set);
+        }
+        Ok(set.set_exdates(self.exceptions.iter().map(|dt| dt.with_timezone(&self.timezone())).collect()))
+    }
+
+    /// Start times of occurrences that overlap `[start, end)`
+    pub fn occurrences(&self, start: DateTime<Utc>, end: DateTime<Utc>) -> Result<Vec<DateTime<Utc>>> {
+        let dates = self
+            .rule_set(true)?
+            .after((start - self.duration).with_timezone(&self.timezone()))
+            .before(end.with_timezone(&self.timezone()))
+            .all(MAX_OCCURRENCES_PER_SERIES)
+            .dates;
+
+        Ok(dates
+            .into_iter()
+            .map(|dt| dt.with_timezone(&Utc))
+            .filter(|&occurrence| {
+                occurrence < end
+                    && (occurrence + self.duration > start || (self.duration.is_zero() && occurrence >= start))
+            })
+            .collect())
+    }
+
+    /// Whether an occurrence starts exactly at `at` and hasn't been excluded
+    pub fn contains(&self, at: DateTime<Utc>) -> Result<bool> {
+        let at_tz = at.with_timezone(&self.timezone());
+        let dates = self.rule_set(true)?.after(at_tz).before(at_tz).all(1).dates;
+        Ok(!dates.is_empty())
+    }
+
+    /// Start of the first occurrence strictly after `at`
+    pub fn next_after(&self, at: DateTime<Utc>) -> Result<Option<DateTime<Utc>>> {
+        let dates = self.rule_set(true)?.after(at.with_timezone(&self.timezone())).all(2).dates;
+        Ok(dates.into_iter().map(|dt| dt.with_timezone(&Utc)).find(|dt| *dt > at))
+    }
+
+    /// Occurrences generated before `at`, counting excluded ones as COUNT does
+    pub fn count_before(&self, at: DateTime<Utc>) -> Result<u32> {
+        let dates = self
+            .rule_set(false)?
+            .before(at.with_timezone(&self.timezone()))
+            .all(MAX_COUNTED_OCCURRENCES)
+            .dates;
+        Ok(dates.into_iter().filter(|dt| dt.with_timezone(&Utc) < at).count() as u32)
+    }
+}
+
+/// Exception dates stored on an event; malformed entries are ignored
+pub fn exceptions_of(event: &calendar_events::Model) -> Vec<DateTime<Utc>> {
+    serde_json::from_value(event.recurrence_exceptions.clone()).unwrap_or_default()
+}
+
+/// A rule anchored at `start` in the user's timezone; rules without a WKST count weeks from
+/// the user's first day of the week
+fn parse(rrule: &str, start: DateTime<Utc>, time: &TimePreferences) -> Result<RRuleSet> {
+    let has_week_start = rrule.to_ascii_uppercase().contains("WKST=");
+    rrule
+        .parse::<RRule<Unvalidated>>()
+        .map(|rule| if has_week_start { rule } else { rule.week_start(time.week_start) })
+        .and_then(|rule| rule.build(start.with_timezone(&Tz::from(time.timezone))))
+        .map_err(|e| AppError::Validation(format!("Invalid rrule: {}", e)))
+}
+
+/// Check a rule before it is stored; a recurring event needs a start time to anchor it
+pub fn validate(rrule: Option<&str>, start_time: Option<DateTime<Utc>>) -> Result<()> {
+    let Some(rrule) = rrule else {
+        return Ok(());
+    };
+    let Some(start_time) = start_time else {
+        return Err(AppError::Validation("A recurring event requires a start_time".to_string()));
+    };
+    parse(rrule, start_time, &TimePreferences::default()).map(|_| ())
+}
+
+/// Check a repeating task's rule; it repeats from its due or scheduled time
+pub fn validate_task(rrule: Option<&str>, anchor: Option<DateTime<Utc>>) -> Result<()> {
+    let Some(rrule) = rrule else {
+        return Ok(());
+    };
+    let Some(anchor) = anchor else {
+        return Err(AppError::Validation("A repeating task requires a due_at or scheduled_at".to_string()));
+    };
+    parse(rrule, anchor, &TimePreferences::default()).map(|_| ())
+}
+
+/// A habit's schedule, in whole local days. The time of day doesn't matter, so days are
+/// counted in UTC and the rule's start is midnight of `starts_on`.
+fn habit_rule(rrule: &str, starts_on: NaiveDate, time: &TimePreferences) -> Result<RRuleSet> {
+    let start = starts_on.and_time(chrono::NaiveTime::MIN).and_utc();
+    let set = parse(rrule, start, &TimePreferences { timezone: chrono_tz::Tz::UTC, ..*time })?;
+    if set.get_rrule().iter().any(|rule| matches!(rule.get_freq(), Frequency::Hourly | Frequency::Minutely | Frequency::Secondly)) {
+        return Err(AppError::invalid_field("rrule", "A habit repeats daily at most"));
+    }
+    Ok(set)
+}
+
+/// Check a habit's rule before it is stored
+pub fn validate_habit(rrule: &str, starts_on: NaiveDate) -> Result<()> {
+    habit_rule(rrule, starts_on, &TimePreferences::default()).map(|_| ())
+}
+
+/// Dates a habit is due on up to and including `through`, followed by the next two after it
+/// if the rule goes on that long
+pub fn habit_due_dates(rrule: &str, starts_on: NaiveDate, through: NaiveDate, time: &TimePreferences) -> Result<Vec<NaiveDate>> {
+    let set = habit_rule(rrule, starts_on, time)?;
+    let day_after = (through + Duration::days(1)).and_time(chrono::NaiveTime::MIN).and_utc().with_timezone(&Tz::UTC);
+    let mut dates: Vec<NaiveDate> = set
+        .clone()
+        .before(day_after - Duration::seconds(1))
+        .all(MAX_COUNTED_OCCURRENCES)
+        .dates
+        .into_iter()
+        .chain(set.after(day_after).all(2).dates)
+        .map(|dt| dt.date_naive())
+        .collect();
+    dates.dedup();
+    Ok(dates)
+}
+
+/// When the next instance of a repeating task is due after the one anchored at `anchor`, in its
+/// owner's local time, together with the rule that instance carries on with; `None` once the rule has ended
+pub fn next_task(rrule: &str, anchor: DateTime<Utc>, time: &TimePreferences) -> Result<Option<(DateTime<Utc>, String)>> {
+    let series = Series {
+        rrule: rrule.to_string(),
+        start: anchor,
+        duration: Duration::zero(),
+        exceptions: Vec::new(),
+        time: *time,
+    };
+    Ok(series.next_after(anchor)?.map(|next| (next, continued(rrule, 1))))
+}
+
+/// Rule parts other than COUNT and UNTIL, which bound the series
+fn unbounded_parts(rrule: &str) -> impl Iterator<Item = &str> {
+    rrule
+        .trim()
+        .trim_start_matches("RRULE:")
+        .split(';')
+        .filter(|part| {
+            let key = part.split('=').next().unwrap_or_default().to_ascii_uppercase();
+            !part.is_empty() && key != "COUNT" && key != "UNTIL"
+        })
+}
+
+/// The rule ended just before `at`, for the part of a series that stays behind on a split
+pub fn ended_before(rrule: &str, at: DateTime<Utc>) -> String {
+    let until = (at - Duration::seconds(1)).format("%Y%m%dT%H%M%SZ");
+    let mut parts: Vec<String> = unbounded_parts(rrule).map(str::to_string).collect();
+    parts.push(format!("UNTIL={}", until));
+    parts.join(";")
+}
+
+/// The rule for the part of a series that continues from a split, where `elapsed`
+/// occurrences were already generated; an UNTIL end carries over unchanged
+pub fn continued(rrule: &str, elapsed: u32) -> String {
+    let Some(count) = count_of(rrule) else {
+        return rrule.trim().trim_start_matches("RRULE:").to_string();
+    };
+    let mut parts: Vec<String> = unbounded_parts(rrule).map(str::to_string).collect();
+    parts.push(format!("COUNT={}", count.saturating_sub(elapsed).max(1)));
+    parts.join(";")
+}
+
+/// The COUNT of a rule, if it is bounded by one
+fn count_of(rrule: &str) -> Option<u32> {
+    rrule
+        .trim()
+        .trim_start_matches("RRULE:")
+        .split(';')
+        .find_map(|part| {
+            let (key, value) = part.split_once('=')?;
+            key.eq_ignore_ascii_case("COUNT").then(|| value.parse().ok()).flatten()
+        })
+}
@@ -0,0 +1,363 @@
+//! The auto-scheduler: packs open tasks into the free time of a calendar. Everything here works
+//! on plain intervals; the handlers load tasks and busy time and persist the blocks a user accepts.
+
+use std::collections::HashMap;
+
+use chrono::{DateTime, Datelike, Duration, NaiveDate, NaiveTime, TimeZone, Utc, Weekday};
+use chrono_tz::Tz;
+use uuid::Uuid;
+
+use crate::entities::can_do_list::TaskPriority;
+
+/// A span of time, `[start, end)`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Interval {
+    pub start: DateTime<Utc>,
+    pub end: DateTime<Utc>,
+}
+
+impl Interval {
+    fn length(&self) -> Duration {
+        self.end - self.start
+    }
+}
+
+/// A window of local time repeating every week, such as working hours or a lunch break
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct WeeklyWindow {
+    pub weekday: Weekday,
+    pub start: NaiveTime,
+    pub end: NaiveTime,
+}
+
+/// When a user wants work scheduled
+#[derive(Debug, Clone)]
+pub struct Constraints {
+    pub working_hours: Vec<WeeklyWindow>,
+    /// Kept free of scheduled tasks
+    pub protected_windows: Vec<WeeklyWindow>,
+    pub max_blocks_per_day: Option<usize>,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct PlanOptions {
+    /// Shortest block a task is split into
+    pub min_block: Duration,
+    /// Most blocks on one local day
+    pub max_blocks_per_day: Option<usize>,
+    /// Timezone days are counted in
+    pub timezone: Tz,
+}
+
+/// What the scheduler needs to know about a task
+#[derive(Debug, Clone)]
+pub struct SchedulableTask {
+    pub id: Uuid,
+    pub duration: Duration,
+    pub due_at: Option<DateTime<Utc>>,
+    pub priority: Option<TaskPriority>,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Block {
+    pub task_id: Uuid,
+    pub start: DateTime<Utc>,
+    pub end: DateTime<Utc>,
+}
+
+#[derive(Debug, Default)]
+pub struct Plan {
+    pub blocks: Vec<Block>,
+    /// Tasks that didn't fit, with the time they needed
+    pub unscheduled: Vec<(Uuid, Duration)>,
+}
+
+/// The occurrences of weekly windows within `[from, to)`, in order; windows whose times a DST
+/// change skips are left out
+pub fn weekly_intervals(windows: &[WeeklyWindow], timezone: Tz, from: DateTime<Utc>, to: DateTime<Utc>) -> Vec<Interval> {
+    let mut intervals = Vec::new();
+    let mut date = from.with_timezone(&timezone).date_naive();
+    let last_date = to.with_timezone(&timezone).date_naive();
+    while date <= last_date {
+        for window in windows.iter().filter(|window| window.weekday == date.weekday()) {
+            let (Some(start), Some(end)) = (
+                timezone.from_local_datetime(&date.and_time(window.start)).earliest(),
+                timezone.from_local_datetime(&date.and_time(window.end)).earliest(),
+            ) else {
+                continue;
+            };
+            let interval = Interval { start: start.with_timezone(&Utc).max(from), end: end.with_timezone(&Utc).min(to) };
+            if interval.start < interval.end {
+                intervals.push(interval);
+            }
+        }
+        let Some(next) = date.succ_opt() else {
+            break;
+        };
+        date = next;
+    }
+    intervals.sort_by_key(|interval| interval.start);
+    intervals
+}
+
+/// `available` with the `busy` time taken out, in order
+pub fn free_intervals(available: Vec<Interval>, busy: &[Interval]) -> Vec<Interval> {
+    let mut free = available;
+    for taken in busy {
+        free = free
+            .into_iter()
+            .flat_map(|interval| {
+                if taken.end <= interval.start || taken.start >= interval.end {
+                    return vec![interval];
+                }
+                [
+                    Interval { start: interval.start, end: taken.start },
+                    Interval { start: taken.end, end: interval.end },
+                ]
+                .into_iter()
+                .filter(|part| part.start < part.end)
+                .collect()
+            })
+            .collect();
+    }
+    free.sort_by_key(|interval| interval.start);
+    free
+}
+
+/// Higher is more urgent; tasks without a priority come last
+fn urgency(priority: Option<TaskPriority>) -> u8 {
+    match priority {
+        Some(TaskPriority::Urgent) => 4,
+        Some(TaskPriority::High) => 3,
+        Some(TaskPriority::Medium) => 2,
+        Some(TaskPriority::Low) => 1,
+        None => 0,
+    }
+}
+
+/// Earliest due first, then most urgent, then oldest. Each task goes into the earliest free slot
+/// that holds it whole while still meeting its due date; otherwise it is split across the
+/// earliest slots in blocks of at least `min_block`. Days that already hold `max_blocks_per_day`
+/// blocks are skipped. A task that doesn't fit at all is left out rather than partly scheduled.
+pub fn plan(tasks: &[SchedulableTask], free: Vec<Interval>, options: &PlanOptions) -> Plan {
+    let mut order: Vec<&SchedulableTask> = tasks.iter().collect();
+    order.sort_by(|a, b| {
+        (a.due_at.is_none(), a.due_at)
+            .cmp(&(b.due_at.is_none(), b.due_at))
+            .then(urgency(b.priority).cmp(&urgency(a.priority)))
+            .then(a.created_at.cmp(&b.created_at))
+            .then(a.id.cmp(&b.id))
+    });
+
+    let mut free = free;
+    let mut plan = Plan::default();
+    let mut blocks_per_day = HashMap::new();
+    for task in order {
+        match place(task, &free, &blocks_per_day, options) {
+            Some((blocks, remaining)) => {
+                for block in &blocks {
+                    *blocks_per_day.entry(local_date(block.start, options.timezone)).or_insert(0) += 1;
+                }
+                plan.blocks.extend(blocks);
+                free = remaining;
+            }
+            None => plan.unscheduled.push((task.id, task.duration)),
+        }
+    }
+    plan.blocks.sort_by_key(|block| block.start);
+    plan
+}
+
+fn local_date(at: DateTime<Utc>, timezone: Tz) -> NaiveDate {
+    at.with_timezone(&timezone).date_naive()
+}
+
+/// The blocks of one task and the free time left after them
+fn place(
+    task: &SchedulableTask,
+    free: &[Interval],
+    blocks_per_day: &HashMap<NaiveDate, usize>,
+    options: &PlanOptions,
+) -> Option<(Vec<Block>, Vec<Interval>)> {
+    if task.duration <= Duration::zero() {
+        return Some((Vec::new(), free.to_vec()));
+    }
+    let has_room = |index: usize, added: &HashMap<NaiveDate, usize>| {
+        let date = local_date(free[index].start, options.timezone);
+        let used = blocks_per_day.get(&date).copied().unwrap_or(0) + added.get(&date).copied().unwrap_or(0);
+        options.max_blocks_per_day.is_none_or(|max| used < max)
+    };
+
+    let whole = (0..free.len()).find(|&index| free[index].length() >= task.duration && has_room(index, &HashMap::new()));
+    let meets_due = |index: usize| task.due_at.is_none_or(|due_at| free[index].start + task.duration <= due_at);
+    if let Some(index) = whole.filter(|&index| meets_due(index)) {
+        return Some(take(task.id, free, &[(index, task.duration)]));
+    }
+
+    let mut parts = Vec::new();
+    let mut added = HashMap::new();
+    let mut remaining = task.duration;
+    for (index, interval) in free.iter().enumerate() {
+        if remaining <= Duration::zero() {
+            break;
+        }
+        let length = interval.length().min(remaining);
+        // Leftovers shorter than a block are only used for the last piece of a task
+        if (length < options.min_block && length < remaining) || !has_room(index, &added) {
+            continue;
+        }
+        parts.push((index, length));
+        *added.entry(local_date(interval.start, options.timezone)).or_insert(0) += 1;
+        remaining -= length;
+    }
+    if remaining > Duration::zero() {
+        // Late is better than not at all
+        return whole.map(|index| take(task.id, free, &[(index, task.duration)]));
+    }
+    Some(take(task.id, free, &parts))
+}
+
+/// Take `length` from the start of each listed free interval
+fn take(task_id: Uuid, free: &[Interval], parts: &[(usize, Duration)]) -> (Vec<Block>, Vec<Interval>) {
+    let mut free = free.to_vec();
+    let blocks = parts
+        .iter()
+        .map(|&(index, length)| {
+            let start = free[index].start;
+            free[index].start += length;
+            Block { task_id, start, end: start + length }
+        })
+        .collect();
+    free.retain(|interval| interval.start < interval.end);
+    (blocks, free)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn at(time: &str) -> DateTime<Utc> {
+        time.parse().unwrap()
+    }
+
+    fn interval(start: &str, end: &str) -> Interval {
+        Interval { start: at(start), end: at(end) }
+    }
+
+    fn nine_to_five(weekdays: &[Weekday]) -> Vec<WeeklyWindow> {
+        weekdays
+            .iter()
+            .map(|&weekday| WeeklyWindow {
+                weekday,
+                start: NaiveTime::from_hms_opt(9, 0, 0).unwrap(),
+                end: NaiveTime::from_hms_opt(17, 0, 0).unwrap(),
+            })
+            .collect()
+    }
+
+    fn task(minutes: i64, due_at: Option<&str>, priority: Option<TaskPriority>) -> SchedulableTask {
+        SchedulableTask {
+            id: Uuid::now_v7(),
+            duration: Duration::minutes(minutes),
+            due_at: due_at.map(at),
+            priority,
+            created_at: at("2025-01-01T00:00:00Z"),
+        }
+    }
+
+    fn options(max_blocks_per_day: Option<usize>) -> PlanOptions {
+        PlanOptions { min_block: Duration::minutes(30), max_blocks_per_day, timezone: Tz::UTC }
+    }
+
+    /// Monday, January 6th 2025, from 9 to 5 in UTC, around a meeting running into the morning,
+    /// one at 10 and lunch
+    fn monday_free_time() -> Vec<Interval> {
+        let working = weekly_intervals(&nine_to_five(&[Weekday::Mon]), Tz::UTC, at("2025-01-06T00:00:00Z"), at("2025-01-07T00:00:00Z"));
+        let busy = [
+            interval("2025-01-06T08:00:00Z", "2025-01-06T09:30:00Z"),
+            interval("2025-01-06T10:00:00Z", "2025-01-06T11:00:00Z"),
+            interval("2025-01-06T12:30:00Z", "2025-01-06T13:00:00Z"),
+        ];
+        free_intervals(working, &busy)
+    }
+
+    #[test]
+    fn working_hours_are_local_time() {
+        let windows = nine_to_five(&[Weekday::Mon, Weekday::Tue, Weekday::Wed, Weekday::Thu, Weekday::Fri]);
+        let intervals = weekly_intervals(&windows, chrono_tz::Europe::Berlin, at("2025-01-06T00:00:00Z"), at("2025-01-13T00:00:00Z"));
+        assert_eq!(intervals.len(), 5);
+        assert_eq!(intervals[0], interval("2025-01-06T08:00:00Z", "2025-01-06T16:00:00Z"));
+        assert_eq!(intervals[4], interval("2025-01-10T08:00:00Z", "2025-01-10T16:00:00Z"));
+
+        // Cut to the range asked for
+        let intervals = weekly_intervals(&windows, chrono_tz::Europe::Berlin, at("2025-01-06T12:00:00Z"), at("2025-01-06T14:00:00Z"));
+        assert_eq!(intervals, [interval("2025-01-06T12:00:00Z", "2025-01-06T14:00:00Z")]);
+    }
+
+    #[test]
+    fn windows_skipped_by_a_dst_change_are_left_out() {
+        // Berlin's clocks jump from 2:00 to 3:00 on Sunday, March 30th 2025
+        let window = WeeklyWindow {
+            weekday: Weekday::Sun,
+            start: NaiveTime::from_hms_opt(2, 0, 0).unwrap(),
+            end: NaiveTime::from_hms_opt(2, 30, 0).unwrap(),
+        };
+        let intervals = weekly_intervals(&[window], chrono_tz::Europe::Berlin, at("2025-03-29T00:00:00Z"), at("2025-04-07T00:00:00Z"));
+        assert_eq!(intervals, [interval("2025-04-06T00:00:00Z", "2025-04-06T00:30:00Z")]);
+    }
+
+    #[test]
+    fn busy_time_is_taken_out_of_working_hours() {
+        assert_eq!(
+            monday_free_time(),
+            [
+                interval("2025-01-06T09:30:00Z", "2025-01-06T10:00:00Z"),
+                interval("2025-01-06T11:00:00Z", "2025-01-06T12:30:00Z"),
+                interval("2025-01-06T13:00:00Z", "2025-01-06T17:00:00Z"),
+            ]
+        );
+    }
+
+    #[test]
+    fn tasks_go_whole_into_the_earliest_slot_that_holds_them() {
+        let due = task(60, Some("2025-01-06T12:00:00Z"), None);
+        let urgent = task(120, None, Some(TaskPriority::Urgent));
+        let short = task(30, None, None);
+        let plan = plan(&[short.clone(), urgent.clone(), due.clone()], monday_free_time(), &options(None));
+
+        assert!(plan.unscheduled.is_empty());
+        assert_eq!(
+            plan.blocks,
+            [
+                Block { task_id: short.id, start: at("2025-01-06T09:30:00Z"), end: at("2025-01-06T10:00:00Z") },
+                Block { task_id: due.id, start: at("2025-01-06T11:00:00Z"), end: at("2025-01-06T12:00:00Z") },
+                Block { task_id: urgent.id, start: at("2025-01-06T13:00:00Z"), end: at("2025-01-06T15:00:00Z") },
+            ]
+        );
+    }
+
+    #[test]
+    fn tasks_are_split_around_events_to_meet_their_due_date() {
+        let due = task(120, Some("2025-01-06T12:30:00Z"), None);
+        let plan = plan(std::slice::from_ref(&due), monday_free_time(), &options(None));
+        assert_eq!(
+            plan.blocks,
+            [
+                Block { task_id: due.id, start: at("2025-01-06T09:30:00Z"), end: at("2025-01-06T10:00:00Z") },
+                Block { task_id: due.id, start: at("2025-01-06T11:00:00Z"), end: at("2025-01-06T12:30:00Z") },
+            ]
+        );
+    }
+
+    #[test]
+    fn full_days_are_skipped_and_leftover_tasks_reported() {
+        let free = weekly_intervals(&nine_to_five(&[Weekday::Mon, Weekday::Tue]), Tz::UTC, at("2025-01-06T00:00:00Z"), at("2025-01-08T00:00:00Z"));
+        let tasks = [task(60, None, None), task(60, None, None), task(60, None, None)];
+        let plan = plan(&tasks, free, &options(Some(1)));
+
+        let starts: Vec<_> = plan.blocks.iter().map(|block| block.start).collect();
+        assert_eq!(starts, [at("2025-01-06T09:00:00Z"), at("2025-01-07T09:00:00Z")]);
+        assert_eq!(plan.unscheduled, [(tasks[2].id, Duration::minutes(60))]);
+    }
+}
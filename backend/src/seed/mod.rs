@@ -0,0 +1,350 @@
+//! Demo data for load testing and frontend development: a user with nested projects,
+//! hundreds of tasks and a few months of events, encrypted the way the web client encrypts
+//! them so the account reads like a real one after signing in.
+
+use chrono::{DateTime, Datelike, Duration, NaiveTime, Utc, Weekday};
+use rand::{rngs::StdRng, seq::IndexedRandom, Rng, SeedableRng};
+use sea_orm::*;
+use serde_json::json;
+use std::collections::BTreeMap;
+use streamline_client::crypto::{auth_password, ClientKey, EncryptedFields};
+use streamline_models::DEFAULT_SCHEMA_VERSION;
+use uuid::Uuid;
+
+use crate::{
+    auth::AuthService,
+    db::Database,
+    entities::{calendar_events, calendars, can_do_list, projects},
+    errors::{AppError, Result},
+    handlers::{export::insert_rows, projects::ensure_default_project},
+    models::{search::join_search_tokens, user::CreateUserRequest, DISPLAY_ORDER_GAP},
+};
+
+/// What to create; see `streamline_backend seed-demo --help` for the defaults
+#[derive(Debug, Clone)]
+pub struct DemoOptions {
+    pub email: String,
+    pub password: String,
+    pub tasks: usize,
+    /// Months of past events; one more month of upcoming ones is added
+    pub months: u32,
+    /// Seed for the generator, so runs with the same options create the same content
+    pub seed: u64,
+}
+
+/// Rows created per table
+#[derive(Debug)]
+pub struct DemoSummary {
+    pub user_id: Uuid,
+    pub created: BTreeMap<&'static str, usize>,
+}
+
+/// Name, color and the index of the parent project
+const PROJECTS: &[(&str, &str, Option<usize>)] = &[
+    ("Work", "#3b82f6", None),
+    ("Q3 launch", "#60a5fa", Some(0)),
+    ("Marketing site", "#93c5fd", Some(1)),
+    ("Beta feedback", "#93c5fd", Some(1)),
+    ("Hiring", "#60a5fa", Some(0)),
+    ("Personal", "#10b981", None),
+    ("Travel", "#34d399", Some(5)),
+    ("Finances", "#34d399", Some(5)),
+    ("Home", "#f59e0b", None),
+    ("Garden", "#fbbf24", Some(8)),
+    ("Renovation", "#fbbf24", Some(8)),
+    ("Learning", "#8b5cf6", None),
+    ("Rust", "#a78bfa", Some(11)),
+    ("Spanish", "#a78bfa", Some(11)),
+    ("Health", "#ef4444", None),
+];
+
+const TASK_VERBS: &[&str] = &[
+    "Review", "Draft", "Update", "Email", "Schedule", "Fix", "Plan", "Research", "Call about", "Clean up",
+    "Prepare", "Book", "Pay", "Order", "Write", "Organize", "Follow up on", "Test", "Sketch", "Read up on",
+];
+const TASK_OBJECTS: &[&str] = &[
+    "the quarterly report", "the onboarding checklist", "the team offsite", "the dentist appointment",
+    "flight tickets", "the garden beds", "the budget spreadsheet", "release notes", "interview questions",
+    "the conference talk", "the grocery list", "the insurance renewal", "the bike repair", "a blog post",
+    "the backup strategy", "design mockups", "the API docs", "tax documents", "a birthday gift", "the weekly review",
+];
+const TASK_TAGS: &[&str] = &["errand", "deep-work", "quick", "waiting", "call", "review"];
+
+/// Name, color and whether it is the default calendar
+const CALENDARS: &[(&str, &str, bool)] = &[
+    ("Personal", "#10b981", true),
+    ("Work", "#3b82f6", false),
+    ("Family", "#f59e0b", false),
+];
+const EVENT_TITLES: &[&str] = &[
+    "Standup", "1:1 with Alex", "Design review", "Lunch with Sam", "Gym", "Dentist", "Sprint planning",
+    "Team retro", "Yoga", "Coffee chat", "Focus time", "Client call", "Doctor's appointment",
+    "Dinner with friends", "Book club", "Piano lesson",
+];
+const EVENT_LOCATIONS: &[&str] = &["Room 4B", "Zoom", "Café Central", "Home office", "City gym"];
+const ALL_DAY_TITLES: &[&str] = &["Conference", "Day off", "Team offsite", "Public holiday"];
+/// Title, rule, first weekday of the rule and start time of the recurring events
+const RECURRING_EVENTS: &[(&str, &str, Weekday, u32)] = &[
+    ("Weekly team sync", "FREQ=WEEKLY;BYDAY=MO", Weekday::Mon, 9),
+    ("Running club", "FREQ=WEEKLY;BYDAY=TU,TH", Weekday::Tue, 18),
+];
+
+/// Create the demo user and fill the account. Fails if a user with that email exists.
+pub async fn seed_demo(db: &Database, options: &DemoOptions) -> Result<DemoSummary> {
+    let mut rng = StdRng::seed_from_u64(options.seed);
+    let key = ClientKey::from_password(&options.password);
+    let now = Utc::now();
+
+    // Registered like any other user, with the password the web client would send
+    let user = AuthService::new(db.clone())
+        .register(
+            CreateUserRequest {
+                email: options.email.clone(),
+                password: auth_password(&options.password),
+            },
+            Some("seed-demo".to_string()),
+        )
+        .await?
+        .user;
+    let user_id = user.id;
+    let mut created = BTreeMap::new();
+
+    let txn = db.connection.begin().await.map_err(|e| AppError::Database(e.into()))?;
+
+    let (inbox, _) = ensure_default_project(&txn, user_id).await?;
+    let mut project_rows: Vec<projects::Model> = Vec::with_capacity(PROJECTS.len());
+    for (index, (name, color, parent)) in PROJECTS.iter().enumerate() {
+        let fields = key.encrypt(&mut rng, &json!({ "name": name, "color": color }))?;
+        project_rows.push(projects::Model {
+            id: Uuid::now_v7(),
+            user_id,
+            encrypted_data: fields.encrypted_data,
+            iv: fields.iv,
+            salt: fields.salt,
+            schema_version: DEFAULT_SCHEMA_VERSION,
+            is_default: false,
+            parent_id: parent.map(|parent| project_rows[parent].id),
+            display_order: (index as i32 + 1) * DISPLAY_ORDER_GAP,
+            is_collapsed: false,
+            is_pinned: index == 1,
+            organization_id: None,
+            search_tokens: Some(join_search_tokens(key.search_tokens(name))),
+            created_at: now.into(),
+            updated_at: now.into(),
+            deleted_at: None,
+        });
+    }
+    insert_rows(&txn, &project_rows).await?;
+    created.insert("projects", project_rows.len());
+
+    let project_ids: Vec<Uuid> = std::iter::once(inbox.id).chain(project_rows.iter().map(|project| project.id)).collect();
+    let task_rows = demo_tasks(&mut rng, &key, user_id, &project_ids, options.tasks, now)?;
+    insert_rows(&txn, &task_rows).await?;
+    created.insert("can_do_list", task_rows.len());
+
+    let mut calendar_rows = Vec::with_capacity(CALENDARS.len());
+    for (index, (name, color, is_default)) in CALENDARS.iter().enumerate() {
+        let fields = key.encrypt(&mut rng, &json!({ "name": name, "color": color, "is_visible": true, "type": "regular" }))?;
+        calendar_rows.push(calendars::Model {
+            id: Uuid::now_v7(),
+            user_id,
+            encrypted_data: fields.encrypted_data,
+            iv: fields.iv,
+            salt: fields.salt,
+            schema_version: DEFAULT_SCHEMA_VERSION,
+            is_default: *is_default,
+            is_pinned: false,
+            is_read_only: false,
+            color: Some((*color).to_owned()),
+            display_order: (index as i32 + 1) * DISPLAY_ORDER_GAP,
+            organization_id: None,
+            created_at: now.into(),
+            updated_at: now.into(),
+            deleted_at: None,
+        });
+    }
+    insert_rows(&txn, &calendar_rows).await?;
+    created.insert("calendars", calendar_rows.len());
+
+    let calendar_ids: Vec<Uuid> = calendar_rows.iter().map(|calendar| calendar.id).collect();
+    let event_rows = demo_events(&mut rng, &key, user_id, &calendar_ids, options.months, now)?;
+    insert_rows(&txn, &event_rows).await?;
+    created.insert("calendar_events", event_rows.len());
+
+    txn.commit().await.map_err(|e| AppError::Database(e.into()))?;
+    Ok(DemoSummary { user_id, created })
+}
+
+fn demo_tasks(
+    rng: &mut StdRng,
+    key: &ClientKey,
+    user_id: Uuid,
+    project_ids: &[Uuid],
+    count: usize,
+    now: DateTime<Utc>,
+) -> Result<Vec<can_do_list::Model>> {
+    let mut rows: Vec<can_do_list::Model> = Vec::with_capacity(count);
+    for index in 0..count {
+        // Every tenth task is a subtask of a recent top-level one in the same project
+        let parent = (index % 10 == 9)
+            .then(|| rows.iter().rev().take(9).find(|task| task.parent_task_id.is_none()))
+            .flatten();
+        let project_id = match parent {
+            Some(parent) => parent.project_id,
+            None => project_ids.choose(rng).copied(),
+        };
+
+        let completed = rng.random_bool(0.3);
+        let due_at = rng
+            .random_bool(0.4)
+            .then(|| at_hour(now + Duration::days(rng.random_range(-14..45)), 17));
+        let completed_at = completed.then(|| now - Duration::hours(rng.random_range(1..24 * 30)));
+        let impact: u8 = rng.random_range(1..=5);
+        let urgency: u8 = rng.random_range(1..=5);
+        let duration_minutes = rng.random_bool(0.5).then(|| *[15, 30, 45, 60, 90, 120].choose(rng).unwrap_or(&30));
+        let tag_count = rng.random_range(0..=2);
+        let tags: Vec<&str> = TASK_TAGS.choose_multiple(rng, tag_count).copied().collect();
+        let my_day = !completed && rng.random_bool(0.05);
+        let content = format!(
+            "{} {}",
+            TASK_VERBS.choose(rng).unwrap_or(&"Review"),
+            TASK_OBJECTS.choose(rng).unwrap_or(&"the weekly review")
+        );
+
+        let fields = key.encrypt(
+            rng,
+            &json!({
+                "content": content,
+                "completed": completed,
+                "due_date": due_at.map(|due| due.to_rfc3339()),
+                "impact": impact,
+                "urgency": urgency,
+                "tags": tags,
+                "duration_minutes": duration_minutes,
+                "my_day": my_day,
+                "parent_task_id": parent.map(|parent| parent.id),
+            }),
+        )?;
+        let created_at = now - Duration::hours(rng.random_range(24..24 * 90));
+        rows.push(can_do_list::Model {
+            id: Uuid::now_v7(),
+            user_id,
+            project_id,
+            encrypted_data: fields.encrypted_data,
+            iv: fields.iv,
+            salt: fields.salt,
+            schema_version: DEFAULT_SCHEMA_VERSION,
+            display_order: (index as i32 + 1) * DISPLAY_ORDER_GAP,
+            search_bloom: None,
+            search_tokens: Some(join_search_tokens(key.search_tokens(&format!("{} {}", content, tags.join(" "))))),
+            due_at: due_at.map(Into::into),
+            scheduled_at: None,
+            parent_task_id: parent.map(|parent| parent.id),
+            completed_at: completed_at.map(Into::into),
+            rrule: None,
+            priority: match (impact + urgency) / 2 {
+                5 => Some(can_do_list::TaskPriority::Urgent),
+                4 => Some(can_do_list::TaskPriority::High),
+                3 => Some(can_do_list::TaskPriority::Medium),
+                _ => None,
+            },
+            estimated_minutes: duration_minutes,
+            is_pinned: false,
+            pending_plaintext: None,
+            created_at: created_at.into(),
+            updated_at: created_at.into(),
+            deleted_at: None,
+        });
+    }
+    Ok(rows)
+}
+
+fn demo_events(
+    rng: &mut StdRng,
+    key: &ClientKey,
+    user_id: Uuid,
+    calendar_ids: &[Uuid],
+    months: u32,
+    now: DateTime<Utc>,
+) -> Result<Vec<calendar_events::Model>> {
+    let first_day = at_hour(now - Duration::days(30 * months as i64), 0);
+    let last_day = at_hour(now + Duration::days(30), 0);
+    let mut rows = Vec::new();
+    let mut event = |rng: &mut StdRng, title: &str, start: DateTime<Utc>, end: DateTime<Utc>, all_day: bool, rrule: Option<&str>| {
+        let calendar_id = *calendar_ids.choose(rng).unwrap_or(&calendar_ids[0]);
+        let location = (!all_day && rng.random_bool(0.5)).then(|| *EVENT_LOCATIONS.choose(rng).unwrap_or(&"Zoom"));
+        let fields: EncryptedFields = key.encrypt(
+            rng,
+            &json!({
+                "title": title,
+                "location": location,
+                "start_time": start.to_rfc3339(),
+                "end_time": end.to_rfc3339(),
+                "all_day": all_day,
+                "calendar_id": calendar_id,
+                "recurrence_rule": rrule,
+            }),
+        )?;
+        rows.push(calendar_events::Model {
+            id: Uuid::now_v7(),
+            user_id,
+            calendar_id: Some(calendar_id),
+            encrypted_data: fields.encrypted_data,
+            iv: fields.iv,
+            salt: fields.salt,
+            schema_version: DEFAULT_SCHEMA_VERSION,
+            start_time: Some(start.into()),
+            end_time: Some(end.into()),
+            is_all_day: all_day,
+            start_date: all_day.then(|| start.date_naive()),
+            end_date: all_day.then(|| (end - Duration::days(1)).date_naive()),
+            search_bloom: None,
+            search_tokens: Some(join_search_tokens(key.search_tokens(&format!("{} {}", title, location.unwrap_or(""))))),
+            rrule: rrule.map(str::to_string),
+            recurrence_exceptions: json!([]),
+            recurring_event_id: None,
+            original_start_time: None,
+            status: calendar_events::EventStatus::Confirmed,
+            subscription_id: None,
+            external_uid: None,
+            external_data: None,
+            linked_task_id: None,
+            created_at: now.into(),
+            updated_at: now.into(),
+            deleted_at: None,
+        });
+        Ok::<_, AppError>(())
+    };
+
+    for (title, rrule, weekday, hour) in RECURRING_EVENTS {
+        // The series starts on an occurrence of its rule
+        let offset = (7 + weekday.num_days_from_monday() - first_day.weekday().num_days_from_monday()) % 7;
+        let start = at_hour(first_day + Duration::days(offset as i64), *hour);
+        event(rng, title, start, start + Duration::hours(1), false, Some(rrule))?;
+    }
+
+    let mut day = first_day;
+    while day < last_day {
+        let weekend = matches!(day.weekday(), Weekday::Sat | Weekday::Sun);
+        if rng.random_bool(0.07) {
+            let title = *ALL_DAY_TITLES.choose(rng).unwrap_or(&"Day off");
+            event(rng, title, day, day + Duration::days(1), true, None)?;
+        }
+        let count = if weekend { rng.random_range(0..=1) } else { rng.random_range(1..=3) };
+        for _ in 0..count {
+            let start = at_hour(day, rng.random_range(8..18)) + Duration::minutes(*[0, 30].choose(rng).unwrap_or(&0));
+            let end = start + Duration::minutes(*[30, 60, 90].choose(rng).unwrap_or(&60));
+            let title = *EVENT_TITLES.choose(rng).unwrap_or(&"Focus time");
+            event(rng, title, start, end, false, None)?;
+        }
+        day += Duration::days(1);
+    }
+    Ok(rows)
+}
+
+/// `hour` o'clock on the day of `day`
+fn at_hour(day: DateTime<Utc>, hour: u32) -> DateTime<Utc> {
+    day.date_naive()
+        .and_time(NaiveTime::from_hms_opt(hour, 0, 0).unwrap_or_default())
+        .and_utc()
+}
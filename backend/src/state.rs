@@ -1,5 +1,14 @@
 use axum::extract::FromRef;
-use crate::{auth::AuthService, db::Database, websocket::WebSocketState};
+use crate::{
+    auth::AuthService,
+    db::Database,
+    features::FeatureFlags,
+    mailer::Mailer,
+    middleware::{rate_limit::RateLimiter, versioning::ApiVersions},
+    notifications::Notifier,
+    quota::Quotas,
+    websocket::WebSocketState,
+};
 
 // Define the shared application state
 #[derive(Clone)]
@@ -7,6 +16,13 @@ pub struct AppState {
     pub db: Database,
     pub auth_service: AuthService,
     pub ws_state: WebSocketState,
+    pub quotas: Quotas,
+    pub features: FeatureFlags,
+    pub notifier: Notifier,
+    /// `None` when `SMTP_URL` is unset
+    pub mailer: Option<Mailer>,
+    pub rate_limiter: RateLimiter,
+    pub api_versions: ApiVersions,
 }
 
 // Implement FromRef so that individual services can be extracted from AppState
@@ -0,0 +1,189 @@
+//! Outbound webhooks: every record change a user's clients are told about over the
+//! WebSocket is also queued as a signed POST to the user's webhook URLs, delivered
+//! by `jobs::webhooks`.
+
+use chrono::{DateTime, Utc};
+use hmac::{Hmac, Mac};
+use sea_orm::*;
+use serde_json::json;
+use sha2::Sha256;
+use tokio::sync::mpsc;
+use uuid::Uuid;
+
+use crate::{
+    db::Database,
+    entities::{prelude::*, webhook_deliveries, webhooks},
+    errors::{AppError, Result},
+    websocket::WebSocketMessage,
+};
+
+/// Header carrying `t=<unix time>,v1=<hex HMAC-SHA256 of "<t>.<body>">`
+pub const SIGNATURE_HEADER: &str = "X-Streamline-Signature";
+pub const EVENT_HEADER: &str = "X-Streamline-Event";
+pub const DELIVERY_HEADER: &str = "X-Streamline-Delivery";
+
+/// Changes waiting to be matched against webhooks; beyond this they are dropped
+const QUEUE_CAPACITY: usize = 1024;
+
+/// Tables whose changes are never delivered, so webhook settings don't echo to the webhooks themselves
+const EXCLUDED_TABLES: &[&str] = &["webhooks"];
+
+/// Hands record changes to a background task that queues a delivery for every matching webhook,
+/// keeping the database lookups off the request that made the change
+#[derive(Clone)]
+pub struct WebhookDispatcher {
+    tx: mpsc::Sender<(Uuid, Vec<WebSocketMessage>)>,
+}
+
+impl WebhookDispatcher {
+    pub fn spawn(db: Database) -> Self {
+        let (tx, mut rx) = mpsc::channel::<(Uuid, Vec<WebSocketMessage>)>(QUEUE_CAPACITY);
+        tokio::spawn(async move {
+            while let Some((user_id, messages)) = rx.recv().await {
+                if let Err(e) = enqueue_deliveries(&db, user_id, messages).await {
+                    tracing::error!("Queueing webhook deliveries for user {} failed: {}", user_id, e);
+                }
+            }
+        });
+        Self { tx }
+    }
+
+    /// Queue the inserts, updates and deletes among `messages` for the user's webhooks
+    pub fn dispatch(&self, user_id: Uuid, messages: &[WebSocketMessage]) {
+        let changes: Vec<_> = messages
+            .iter()
+            .filter(|message| event_name(message).is_some())
+            .cloned()
+            .collect();
+        if changes.is_empty() {
+            return;
+        }
+        if let Err(e) = self.tx.try_send((user_id, changes)) {
+            tracing::warn!("Dropping webhook events for user {}: {}", user_id, e);
+        }
+    }
+}
+
+/// `<table>.<action>` of a record change, or `None` for other messages such as notifications
+pub fn event_name(message: &WebSocketMessage) -> Option<String> {
+    let action = match message.event_type.as_str() {
+        "INSERT" => "insert",
+        "UPDATE" => "update",
+        "DELETE" => "delete",
+        _ => return None,
+    };
+    if EXCLUDED_TABLES.contains(&message.table.as_str()) {
+        return None;
+    }
+    Some(format!("{}.{}", message.table, action))
+}
+
+/// Whether an event such as `can_do_list.update` matches a pattern such as `*`, `can_do_list.*` or `*.update`
+pub fn event_matches(pattern: &str, event: &str) -> bool {
+    if pattern == "*" {
+        return true;
+    }
+    let (Some((pattern_table, pattern_action)), Some((table, action))) = (pattern.split_once('.'), event.split_once('.')) else {
+        return false;
+    };
+    (pattern_table == "*" || pattern_table == table) && (pattern_action == "*" || pattern_action == action)
+}
+
+/// Secrets deliveries are signed with: the current one, and the previous one until it expires
+pub fn signing_secrets(webhook: &webhooks::Model, now: DateTime<Utc>) -> Vec<&str> {
+    let previous = webhook
+        .previous_secret
+        .as_deref()
+        .filter(|_| webhook.previous_secret_expires_at.is_some_and(|expires_at| expires_at > now));
+    std::iter::once(webhook.secret.as_str()).chain(previous).collect()
+}
+
+fn mac(secret: &str, timestamp: i64, body: &[u8]) -> Hmac<Sha256> {
+    let mut mac = Hmac::<Sha256>::new_from_slice(secret.as_bytes()).expect("HMAC accepts keys of any length");
+    mac.update(timestamp.to_string().as_bytes());
+    mac.update(b".");
+    mac.update(body);
+    mac
+}
+
+/// Signature header value for a body sent at `timestamp`, with a `v1` for each secret
+pub fn sign(secrets: &[&str], timestamp: i64, body: &[u8]) -> String {
+    let mut header = format!("t={}", timestamp);
+    for secret in secrets {
+        header.push_str(",v1=");
+        header.push_str(&hex::encode(mac(secret, timestamp, body).finalize().into_bytes()));
+    }
+    header
+}
+
+/// The timestamp of a signature header and the index of the first secret one of its `v1`
+/// signatures was made with, if any was
+pub fn verify(secrets: &[&str], header: &str, body: &[u8]) -> Option<(i64, Option<usize>)> {
+    let mut timestamp = None;
+    let mut signatures = Vec::new();
+    for part in header.split(',') {
+        match part.trim().split_once('=') {
+            Some(("t", value)) => timestamp = value.parse::<i64>().ok(),
+            Some(("v1", value)) => signatures.extend(hex::decode(value).ok()),
+            _ => {}
+        }
+    }
+    let timestamp = timestamp?;
+    let matched = secrets.iter().position(|secret| {
+        signatures.iter().any(|signature| mac(secret, timestamp, body).verify_slice(signature).is_ok())
+    });
+    Some((timestamp, matched))
+}
+
+async fn enqueue_deliveries(db: &Database, user_id: Uuid, messages: Vec<WebSocketMessage>) -> Result<()> {
+    let hooks = Webhooks::find()
+        .filter(webhooks::Column::UserId.eq(user_id))
+        .filter(webhooks::Column::IsActive.eq(true))
+        .all(&db.connection)
+        .await
+        .map_err(|e| AppError::Database(e.into()))?;
+    if hooks.is_empty() {
+        return Ok(());
+    }
+
+    let now = Utc::now();
+    let mut deliveries = Vec::new();
+    for message in &messages {
+        let Some(event) = event_name(message) else {
+            continue;
+        };
+        for hook in &hooks {
+            let subscribed = hook.events.as_array().is_some_and(|patterns| {
+                patterns.iter().filter_map(|pattern| pattern.as_str()).any(|pattern| event_matches(pattern, &event))
+            });
+            if !subscribed {
+                continue;
+            }
+            let id = Uuid::now_v7();
+            let mut delivery = webhook_deliveries::ActiveModel::new();
+            delivery.id = Set(id);
+            delivery.webhook_id = Set(hook.id);
+            delivery.user_id = Set(user_id);
+            delivery.event = Set(event.clone());
+            delivery.payload = Set(json!({
+                "id": id,
+                "webhook_id": hook.id,
+                "event": event,
+                "table": message.table,
+                "record_id": message.record_id,
+                "occurred_at": now,
+                "data": message.data,
+            }));
+            deliveries.push(delivery);
+        }
+    }
+    if deliveries.is_empty() {
+        return Ok(());
+    }
+
+    WebhookDeliveries::insert_many(deliveries)
+        .exec(&db.connection)
+        .await
+        .map_err(|e| AppError::Database(e.into()))?;
+    Ok(())
+}
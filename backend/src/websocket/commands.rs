@@ -0,0 +1,104 @@
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use uuid::Uuid;
+
+use super::replay::FrameCheck;
+
+/// Answer to a frame a client sent after authenticating. It carries the frame's `request_id`
+/// so the client can tell which of its frames went through.
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ReplyFrame {
+    Ack {
+        request_id: Option<Value>,
+    },
+    Error {
+        request_id: Option<Value>,
+        code: CommandErrorCode,
+        message: String,
+    },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum CommandErrorCode {
+    /// The frame isn't a JSON object with a `type`
+    MalformedFrame,
+    /// The `type` isn't a command the server knows
+    UnknownCommand,
+    /// A known command is missing a field or has one of the wrong type
+    InvalidCommand,
+    /// The frame's `nonce` was already processed, possibly on an earlier connection
+    DuplicateFrame,
+    /// The frame's `seq` isn't higher than the previous one on the connection
+    OutOfOrderFrame,
+}
+
+impl ReplyFrame {
+    pub fn error(request_id: Option<Value>, code: CommandErrorCode, message: impl Into<String>) -> Self {
+        Self::Error { request_id, code, message: message.into() }
+    }
+
+    /// The error for a frame replay protection dropped, `None` for fresh frames
+    pub fn dropped(request_id: Option<Value>, check: FrameCheck) -> Option<Self> {
+        match check {
+            FrameCheck::Fresh => None,
+            FrameCheck::Duplicate => Some(Self::error(
+                request_id,
+                CommandErrorCode::DuplicateFrame,
+                "A frame with this nonce was already processed",
+            )),
+            FrameCheck::OutOfOrder => Some(Self::error(
+                request_id,
+                CommandErrorCode::OutOfOrderFrame,
+                "seq must increase with every frame",
+            )),
+        }
+    }
+}
+
+/// Commands a client can send after authenticating
+#[derive(Debug, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum Command {
+    /// Round trip to check the connection is alive end to end
+    Ping,
+    /// Changes to every table the user can see are delivered anyway, so subscriptions only
+    /// need confirming
+    Subscribe { table: String },
+    Unsubscribe { table: String },
+}
+
+const COMMANDS: [&str; 3] = ["ping", "subscribe", "unsubscribe"];
+
+/// The `request_id` of a frame, echoed back unchanged in the reply
+pub fn request_id(frame: &Value) -> Option<Value> {
+    frame.get("request_id").filter(|id| !id.is_null()).cloned()
+}
+
+/// Run a fresh frame's command and acknowledge it, or say why it was rejected
+pub fn handle(frame: &Value, connection_id: Uuid) -> ReplyFrame {
+    let request_id = request_id(frame);
+    let Some(command_type) = frame.get("type").and_then(Value::as_str) else {
+        return ReplyFrame::error(request_id, CommandErrorCode::MalformedFrame, "Frames must be JSON objects with a type");
+    };
+    if !COMMANDS.contains(&command_type) {
+        return ReplyFrame::error(
+            request_id,
+            CommandErrorCode::UnknownCommand,
+            format!("Unknown command {}", command_type),
+        );
+    }
+
+    match serde_json::from_value::<Command>(frame.clone()) {
+        Ok(Command::Ping) => {}
+        Ok(Command::Subscribe { table }) => {
+            tracing::debug!("Connection {} subscribed to {}", connection_id, table);
+        }
+        Ok(Command::Unsubscribe { table }) => {
+            tracing::debug!("Connection {} unsubscribed from {}", connection_id, table);
+        }
+        Err(error) => return ReplyFrame::error(request_id, CommandErrorCode::InvalidCommand, error.to_string()),
+    }
+    ReplyFrame::Ack { request_id }
+}
@@ -0,0 +1,69 @@
+use std::collections::HashMap;
+use std::env;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::Mutex;
+use uuid::Uuid;
+
+use super::WebSocketMessage;
+
+/// Default coalescing window for repeated updates of one record
+const DEFAULT_DEBOUNCE_MS: u64 = 150;
+
+/// (user, table, record) an update broadcast is coalesced under
+pub type DebounceKey = (Uuid, String, Uuid);
+
+pub struct PendingBroadcast {
+    pub message: WebSocketMessage,
+    pub exclude_connection_id: Option<Uuid>,
+}
+
+/// Holds back UPDATE broadcasts for a short window so rapid successive saves of the
+/// same record (e.g. autosave while dragging) reach clients as one message with the latest state
+#[derive(Clone)]
+pub struct BroadcastDebouncer {
+    pub window: Duration,
+    pending: Arc<Mutex<HashMap<DebounceKey, PendingBroadcast>>>,
+}
+
+impl BroadcastDebouncer {
+    pub fn from_env() -> Self {
+        let window_ms = env::var("WS_BROADCAST_DEBOUNCE_MS")
+            .ok()
+            .and_then(|v| v.parse().ok())
+            .unwrap_or(DEFAULT_DEBOUNCE_MS);
+
+        Self {
+            window: Duration::from_millis(window_ms),
+            pending: Arc::new(Mutex::new(HashMap::new())),
+        }
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        !self.window.is_zero()
+    }
+
+    /// Queue an update; returns true when this starts a new window and the caller must schedule the flush
+    pub async fn push(&self, key: DebounceKey, message: WebSocketMessage, exclude_connection_id: Option<Uuid>) -> bool {
+        let mut pending = self.pending.lock().await;
+        match pending.get_mut(&key) {
+            Some(existing) => {
+                // Coalesced writes from different connections must reach all of them
+                if existing.exclude_connection_id != exclude_connection_id {
+                    existing.exclude_connection_id = None;
+                }
+                existing.message = message;
+                false
+            }
+            None => {
+                pending.insert(key, PendingBroadcast { message, exclude_connection_id });
+                true
+            }
+        }
+    }
+
+    /// Remove and return the pending update for `key`, if any
+    pub async fn take(&self, key: &DebounceKey) -> Option<PendingBroadcast> {
+        self.pending.lock().await.remove(key)
+    }
+}
@@ -0,0 +1,32 @@
+use std::env;
+use std::time::Duration;
+
+/// Default time between pings from the server
+const DEFAULT_INTERVAL_MS: u64 = 30_000;
+/// Default time a client may stay silent, pongs included, before its socket is closed
+const DEFAULT_TIMEOUT_MS: u64 = 90_000;
+
+/// Keeps idle sockets in check: the server pings every `interval` and closes connections it
+/// hasn't heard from within `timeout`, so sockets of clients that went away without closing them
+/// (a laptop put to sleep, a dropped network) don't linger. The auth frame must arrive within
+/// `timeout` as well.
+#[derive(Debug, Clone, Copy)]
+pub struct Heartbeat {
+    pub interval: Duration,
+    pub timeout: Duration,
+}
+
+impl Heartbeat {
+    pub fn from_env() -> Self {
+        fn millis(key: &str, default: u64) -> Duration {
+            Duration::from_millis(env::var(key).ok().and_then(|v| v.parse().ok()).unwrap_or(default))
+        }
+
+        let interval = millis("WS_HEARTBEAT_INTERVAL_MS", DEFAULT_INTERVAL_MS).max(Duration::from_millis(10));
+        Self {
+            interval,
+            // A client gets at least one ping to answer before it counts as gone
+            timeout: millis("WS_HEARTBEAT_TIMEOUT_MS", DEFAULT_TIMEOUT_MS).max(interval * 2),
+        }
+    }
+}
@@ -6,44 +6,100 @@ use axum::{
     response::Response,
 };
 use futures_util::{sink::SinkExt, stream::StreamExt};
-use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
-use std::sync::Arc;
-use tokio::sync::{broadcast, RwLock};
+use std::sync::{atomic::{AtomicU64, Ordering}, Arc};
+use std::time::Instant;
+use tokio::sync::{mpsc, watch, RwLock};
 use uuid::Uuid;
 
-use crate::auth::AuthService;
+use crate::{auth::AuthService, models::announcement::AnnouncementResponse, webhooks::WebhookDispatcher};
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct WebSocketMessage {
-    pub event_type: String,
-    pub table: String,
-    pub user_id: Uuid,
-    pub record_id: Option<Uuid>,
-    pub data: Option<serde_json::Value>,
+pub mod commands;
+pub mod debounce;
+pub mod heartbeat;
+pub mod protocol;
+pub mod replay;
+pub mod shutdown;
+
+use commands::{CommandErrorCode, ReplyFrame};
+use debounce::BroadcastDebouncer;
+use heartbeat::Heartbeat;
+use protocol::{encode, negotiate_version, ClientCapabilities, CURRENT_PROTOCOL_VERSION, MIN_PROTOCOL_VERSION};
+use replay::{ReplayGuard, SequenceTracker};
+use shutdown::ShutdownNotice;
+
+pub use streamline_models::websocket::WebSocketMessage;
+
+/// Frames waiting to be written to one socket. A connection that falls this far behind is
+/// closed rather than silently missing frames, and the client resyncs after reconnecting.
+const CONNECTION_QUEUE_SIZE: usize = 100;
+
+/// Frame queued for one connection
+#[derive(Debug, Clone)]
+pub enum OutgoingFrame {
+    /// A change event, encoded in the connection's protocol version
+    Change(WebSocketMessage),
+    /// The answer to a frame the client sent
+    Reply(ReplyFrame),
+    /// A notice from the operators, sent to every connection
+    Announcement(AnnouncementResponse),
 }
 
 #[derive(Clone)]
 pub struct WebSocketConnection {
-    pub tx: broadcast::Sender<WebSocketMessage>,
+    /// The only sender of the connection's queue; removing the connection closes its socket
+    pub tx: mpsc::Sender<OutgoingFrame>,
     pub connection_id: Uuid,
+    pub capabilities: ClientCapabilities,
 }
 
 #[derive(Clone)]
 pub struct WebSocketState {
     pub connections: Arc<RwLock<HashMap<Uuid, Vec<WebSocketConnection>>>>,
+    pub replay_guard: ReplayGuard,
+    pub debouncer: BroadcastDebouncer,
+    pub heartbeat: Heartbeat,
+    shutdown: Arc<watch::Sender<Option<ShutdownNotice>>>,
+    webhooks: Option<WebhookDispatcher>,
 }
 
 impl WebSocketState {
     pub fn new() -> Self {
         Self {
             connections: Arc::new(RwLock::new(HashMap::new())),
+            replay_guard: ReplayGuard::new(),
+            debouncer: BroadcastDebouncer::from_env(),
+            heartbeat: Heartbeat::from_env(),
+            shutdown: Arc::new(watch::channel(None).0),
+            webhooks: None,
         }
     }
 
-    pub async fn add_connection(&self, user_id: Uuid, connection_id: Uuid, tx: broadcast::Sender<WebSocketMessage>) {
+    /// Also hand every broadcast change to the user's webhooks
+    pub fn with_webhooks(mut self, dispatcher: WebhookDispatcher) -> Self {
+        self.webhooks = Some(dispatcher);
+        self
+    }
+
+    /// Tell every open socket the server is going away, then close them
+    pub fn notify_shutdown(&self, notice: ShutdownNotice) {
+        tracing::info!("Notifying WebSocket clients of {} with a {}ms reconnect spread", notice.reason, notice.reconnect_spread_ms);
+        self.shutdown.send_replace(Some(notice));
+    }
+
+    /// Whether a shutdown notice went out; sockets opened from now on are closed right away
+    pub fn is_shutting_down(&self) -> bool {
+        self.shutdown.borrow().is_some()
+    }
+
+    /// Open sockets across all users
+    pub async fn connection_count(&self) -> usize {
+        self.connections.read().await.values().map(Vec::len).sum()
+    }
+
+    pub async fn add_connection(&self, user_id: Uuid, connection_id: Uuid, capabilities: ClientCapabilities, tx: mpsc::Sender<OutgoingFrame>) {
         let mut connections = self.connections.write().await;
-        let conn = WebSocketConnection { tx, connection_id };
+        let conn = WebSocketConnection { tx, connection_id, capabilities };
         connections.entry(user_id).or_insert_with(Vec::new).push(conn);
     }
 
@@ -57,31 +113,172 @@ impl WebSocketState {
         }
     }
 
+    /// Queue a frame for one connection of the user, e.g. the reply to a frame it sent.
+    /// Returns `false` once the connection is gone.
+    pub async fn send_to_connection(&self, user_id: &Uuid, connection_id: &Uuid, frame: OutgoingFrame) -> bool {
+        let sent = {
+            let connections = self.connections.read().await;
+            let Some(conn) = connections
+                .get(user_id)
+                .and_then(|user_conns| user_conns.iter().find(|conn| &conn.connection_id == connection_id))
+            else {
+                return false;
+            };
+            conn.tx.try_send(frame)
+        };
+        match sent {
+            Ok(()) => true,
+            Err(e) => {
+                tracing::warn!("Closing WebSocket connection {} that can't take more frames: {}", connection_id, e);
+                self.remove_connection(user_id, connection_id).await;
+                false
+            }
+        }
+    }
+
+    /// Queue a frame for every open connection, whoever it belongs to; returns how many were
+    /// reached. Connections whose queue is full are closed, as for change events.
+    pub async fn send_to_all(&self, frame: OutgoingFrame) -> usize {
+        let mut stalled = Vec::new();
+        let mut sent_count = 0;
+        {
+            let connections = self.connections.read().await;
+            for (user_id, user_conns) in connections.iter() {
+                for conn in user_conns {
+                    match conn.tx.try_send(frame.clone()) {
+                        Ok(()) => sent_count += 1,
+                        Err(_) => stalled.push((*user_id, conn.connection_id)),
+                    }
+                }
+            }
+        }
+        for (user_id, connection_id) in stalled {
+            tracing::warn!("Closing WebSocket connection {} that fell {} frames behind", connection_id, CONNECTION_QUEUE_SIZE);
+            self.remove_connection(&user_id, &connection_id).await;
+        }
+        sent_count
+    }
+
     pub async fn broadcast_to_user(&self, user_id: &Uuid, message: WebSocketMessage, exclude_connection_id: Option<Uuid>) {
+        self.broadcast_many_to_user(user_id, vec![message], exclude_connection_id).await;
+    }
+
+    /// Broadcast several changes from one request, all skipping the originating connection.
+    /// Updates are debounced per record; inserts and deletes go out immediately and supersede a pending update.
+    pub async fn broadcast_many_to_user(&self, user_id: &Uuid, messages: Vec<WebSocketMessage>, exclude_connection_id: Option<Uuid>) {
+        // Webhooks see every change, including the updates debouncing would collapse
+        if let Some(webhooks) = &self.webhooks {
+            webhooks.dispatch(*user_id, &messages);
+        }
+
+        if !self.debouncer.is_enabled() {
+            self.send_to_user(user_id, messages, exclude_connection_id).await;
+            return;
+        }
+
+        let mut immediate = Vec::with_capacity(messages.len());
+        for message in messages {
+            let Some(record_id) = message.record_id else {
+                immediate.push(message);
+                continue;
+            };
+            let key = (*user_id, message.table.clone(), record_id);
+
+            if message.event_type != "UPDATE" {
+                self.debouncer.take(&key).await;
+                immediate.push(message);
+                continue;
+            }
+
+            if self.debouncer.push(key.clone(), message, exclude_connection_id).await {
+                let ws_state = self.clone();
+                tokio::spawn(async move {
+                    tokio::time::sleep(ws_state.debouncer.window).await;
+                    if let Some(pending) = ws_state.debouncer.take(&key).await {
+                        ws_state.send_to_user(&key.0, vec![pending.message], pending.exclude_connection_id).await;
+                    }
+                });
+            }
+        }
+
+        self.send_to_user(user_id, immediate, exclude_connection_id).await;
+    }
+
+    /// Broadcast the same changes to several users, e.g. everyone a project is shared with
+    pub async fn broadcast_many_to_users(&self, user_ids: &[Uuid], messages: Vec<WebSocketMessage>, exclude_connection_id: Option<Uuid>) {
+        for user_id in user_ids {
+            self.broadcast_many_to_user(user_id, messages.clone(), exclude_connection_id).await;
+        }
+    }
+
+    /// Deliver messages to every connection of the user under a single lock. Connections whose
+    /// queue is full are closed afterwards rather than silently missing changes.
+    async fn send_to_user(&self, user_id: &Uuid, messages: Vec<WebSocketMessage>, exclude_connection_id: Option<Uuid>) {
+        if messages.is_empty() {
+            return;
+        }
+
+        let mut stalled = Vec::new();
+        self.queue_for_user(user_id, messages, exclude_connection_id, &mut stalled).await;
+        for connection_id in stalled {
+            tracing::warn!("Closing WebSocket connection {} that fell {} frames behind", connection_id, CONNECTION_QUEUE_SIZE);
+            self.remove_connection(user_id, &connection_id).await;
+        }
+    }
+
+    /// Queue messages for the user's connections, collecting those whose queue is full; split
+    /// out so the read lock is released before they're removed
+    async fn queue_for_user(
+        &self,
+        user_id: &Uuid,
+        messages: Vec<WebSocketMessage>,
+        exclude_connection_id: Option<Uuid>,
+        stalled: &mut Vec<Uuid>,
+    ) {
         let connections = self.connections.read().await;
-        tracing::info!("Broadcasting WebSocket message to user {}: {:?}, excluding connection: {:?}", user_id, message, exclude_connection_id);
         
-        if let Some(user_conns) = connections.get(user_id) {
+        let Some(user_conns) = connections.get(user_id) else {
+            tracing::warn!("No WebSocket connections found for user {}", user_id);
+            tracing::info!("Active connections: {:?}", connections.keys().collect::<Vec<_>>());
+            return;
+        };
+
+        for message in messages {
+            tracing::info!("Broadcasting WebSocket message to user {}: {:?}, excluding connection: {:?}", user_id, message, exclude_connection_id);
+
+            // Only measure the payload when some connection declared a size limit
+            let payload_size = if user_conns.iter().any(|conn| conn.capabilities.max_payload_size.is_some()) {
+                serde_json::to_vec(&message).map(|bytes| bytes.len()).unwrap_or(0)
+            } else {
+                0
+            };
             let mut sent_count = 0;
             for conn in user_conns {
-                // Skip the connection that initiated the update
-                if let Some(exclude_id) = exclude_connection_id {
-                    if conn.connection_id == exclude_id {
-                        tracing::info!("Skipping connection {} (initiator of the update)", exclude_id);
-                        continue;
-                    }
+                // Skip the connection that initiated the update, and those already falling behind
+                if let Some(exclude_id) = exclude_connection_id
+                    && conn.connection_id == exclude_id
+                {
+                    tracing::info!("Skipping connection {} (initiator of the update)", exclude_id);
+                    continue;
                 }
+                if stalled.contains(&conn.connection_id) {
+                    continue;
+                }
+                
+                // Oversized payloads are sent without `data`; the client refetches the record over REST
+                let outgoing = match conn.capabilities.max_payload_size {
+                    Some(max_size) if payload_size > max_size => WebSocketMessage { data: None, ..message.clone() },
+                    _ => message.clone(),
+                };
                 
-                if let Err(e) = conn.tx.send(message.clone()) {
+                if let Err(e) = conn.tx.try_send(OutgoingFrame::Change(outgoing)) {
                     tracing::warn!("Failed to send WebSocket message to connection {}: {}", conn.connection_id, e);
+                    stalled.push(conn.connection_id);
                 } else {
                     sent_count += 1;
                 }
             }
             tracing::info!("Successfully sent WebSocket message to {} out of {} connections for user {}", sent_count, user_conns.len(), user_id);
-        } else {
-            tracing::warn!("No WebSocket connections found for user {}", user_id);
-            tracing::info!("Active connections: {:?}", connections.keys().collect::<Vec<_>>());
         }
     }
 }
@@ -101,39 +298,64 @@ async fn websocket_connection(
     ws_state: WebSocketState,
 ) {
     let (mut sender, mut receiver) = socket.split();
-    let (tx, mut rx) = broadcast::channel::<WebSocketMessage>(100);
+    let (tx, mut rx) = mpsc::channel::<OutgoingFrame>(CONNECTION_QUEUE_SIZE);
     
     // Generate a unique connection ID for this WebSocket
     let connection_id = Uuid::new_v4();
     
     // Handle authentication
     let mut user_id: Option<Uuid> = None;
+    let mut protocol_version = MIN_PROTOCOL_VERSION;
+    let mut auth_error_message = "Authentication failed".to_string();
     
-    // Authentication flow
-    if let Some(msg) = receiver.next().await {
-        if let Ok(Message::Text(text)) = msg {
-            if let Ok(auth_msg) = serde_json::from_str::<serde_json::Value>(&text) {
-                if let Some(token) = auth_msg.get("token").and_then(|t| t.as_str()) {
-                    if let Ok(user) = auth_service.get_user_from_token(token).await {
-                        user_id = Some(user.id);
-                        tracing::info!("WebSocket authentication successful for user: {} with connection_id: {}", user.id, connection_id);
-                        ws_state.add_connection(user.id, connection_id, tx.clone()).await;
-                        
-                        // Send authentication success with connection_id
-                        let auth_response = serde_json::json!({
-                            "type": "auth_success",
-                            "user_id": user.id,
-                            "connection_id": connection_id
-                        });
-                        
-                        if sender.send(Message::Text(auth_response.to_string().into())).await.is_err() {
-                            tracing::error!("Failed to send auth success message to user: {}", user.id);
-                            return;
-                        }
-                        tracing::info!("Sent auth success message to user: {} with connection_id: {}", user.id, connection_id);
-                    } else {
-                        tracing::warn!("WebSocket authentication failed for token");
+    // Authentication flow; a client that never sends its auth frame is dropped like an idle one
+    let heartbeat = ws_state.heartbeat;
+    let first_frame = match tokio::time::timeout(heartbeat.timeout, receiver.next()).await {
+        Ok(frame) => frame,
+        Err(_) => {
+            auth_error_message = "Authentication timed out".to_string();
+            None
+        }
+    };
+    if let Some(Ok(Message::Text(text))) = first_frame
+        && let Ok(auth_msg) = serde_json::from_str::<serde_json::Value>(&text)
+        && let Some(token) = auth_msg.get("token").and_then(|t| t.as_str())
+    {
+        let requested_version = auth_msg.get("version").and_then(|v| v.as_u64());
+        match negotiate_version(requested_version) {
+            None => {
+                tracing::warn!("WebSocket client requested unsupported protocol version {:?}", requested_version);
+                auth_error_message = format!(
+                    "Unsupported protocol version, server supports {} to {}",
+                    MIN_PROTOCOL_VERSION, CURRENT_PROTOCOL_VERSION
+                );
+            }
+            Some(version) => {
+                if let Ok(user) = auth_service.get_user_from_token(token).await {
+                    user_id = Some(user.id);
+                    protocol_version = version;
+                    let capabilities = ClientCapabilities::from_auth_message(&auth_msg);
+                    tracing::info!("WebSocket authentication successful for user: {} with connection_id: {} (protocol v{}, {:?})", user.id, connection_id, version, capabilities);
+                    ws_state.add_connection(user.id, connection_id, capabilities.clone(), tx).await;
+                    
+                    // Send authentication success with connection_id, the negotiated protocol version and accepted capabilities
+                    let auth_response = serde_json::json!({
+                        "type": "auth_success",
+                        "user_id": user.id,
+                        "connection_id": connection_id,
+                        "version": version,
+                        "server_version": CURRENT_PROTOCOL_VERSION,
+                        "capabilities": capabilities
+                    });
+                    
+                    if sender.send(Message::Text(auth_response.to_string().into())).await.is_err() {
+                        tracing::error!("Failed to send auth success message to user: {}", user.id);
+                        ws_state.remove_connection(&user.id, &connection_id).await;
+                        return;
                     }
+                    tracing::info!("Sent auth success message to user: {} with connection_id: {}", user.id, connection_id);
+                } else {
+                    tracing::warn!("WebSocket authentication failed for token");
                 }
             }
         }
@@ -143,7 +365,9 @@ async fn websocket_connection(
         // Authentication failed
         let auth_error = serde_json::json!({
             "type": "auth_error",
-            "message": "Authentication failed"
+            "message": auth_error_message,
+            "min_version": MIN_PROTOCOL_VERSION,
+            "server_version": CURRENT_PROTOCOL_VERSION
         });
         
         let _ = sender.send(Message::Text(auth_error.to_string().into())).await;
@@ -151,30 +375,110 @@ async fn websocket_connection(
     }
     
     let user_id = user_id.unwrap();
+
+    // Milliseconds after `connected_at` at which the client was last heard from
+    let connected_at = Instant::now();
+    let last_seen = Arc::new(AtomicU64::new(0));
     
     // Spawn task to handle outgoing messages
+    let mut shutdown = ws_state.shutdown.subscribe();
+    if shutdown.borrow().is_some() {
+        // Connected while the server is already shutting down
+        shutdown.mark_changed();
+    }
+    let send_last_seen = last_seen.clone();
     let mut send_task = tokio::spawn(async move {
-        while let Ok(msg) = rx.recv().await {
-            if let Ok(json) = serde_json::to_string(&msg) {
-                if sender.send(Message::Text(json.into())).await.is_err() {
-                    break;
+        let mut ping = tokio::time::interval_at(tokio::time::Instant::now() + heartbeat.interval, heartbeat.interval);
+        loop {
+            tokio::select! {
+                _ = ping.tick() => {
+                    let silent_ms = (connected_at.elapsed().as_millis() as u64)
+                        .saturating_sub(send_last_seen.load(Ordering::Relaxed));
+                    if silent_ms > heartbeat.timeout.as_millis() as u64 {
+                        tracing::info!("Closing WebSocket connection {} after {}ms without a frame from the client", connection_id, silent_ms);
+                        let _ = sender.send(Message::Close(None)).await;
+                        break;
+                    }
+                    if sender.send(Message::Ping(Vec::new().into())).await.is_err() {
+                        break;
+                    }
+                }
+                frame = rx.recv() => {
+                    // The connection was removed from the state, e.g. for falling behind
+                    let Some(frame) = frame else {
+                        let _ = sender.send(Message::Close(None)).await;
+                        break;
+                    };
+                    let json = match &frame {
+                        OutgoingFrame::Change(msg) => encode(msg, protocol_version),
+                        OutgoingFrame::Reply(reply) => serde_json::to_string(reply),
+                        OutgoingFrame::Announcement(announcement) => serde_json::to_string(&announcement.frame()),
+                    };
+                    if let Ok(json) = json
+                        && sender.send(Message::Text(json.into())).await.is_err()
+                    {
+                        break;
+                    }
+                }
+                Ok(()) = shutdown.changed() => {
+                    let notice = shutdown.borrow_and_update().clone();
+                    if let Some(notice) = notice {
+                        if let Ok(json) = serde_json::to_string(&notice.frame()) {
+                            let _ = sender.send(Message::Text(json.into())).await;
+                        }
+                        let _ = sender.send(Message::Close(None)).await;
+                        break;
+                    }
                 }
             }
         }
     });
     
     // Handle incoming messages
+    let recv_state = ws_state.clone();
     let mut recv_task = tokio::spawn(async move {
+        let mut sequence = SequenceTracker::default();
         while let Some(msg) = receiver.next().await {
-            match msg {
+            // Any frame counts as a sign of life, pongs to the heartbeat included
+            if msg.is_ok() {
+                last_seen.store(connected_at.elapsed().as_millis() as u64, Ordering::Relaxed);
+            }
+            let reply = match msg {
                 Ok(Message::Text(text)) => {
-                    // Handle incoming messages (e.g., subscriptions)
-                    tracing::debug!("Received WebSocket message: {}", text);
+                    let Ok(frame) = serde_json::from_str::<serde_json::Value>(&text) else {
+                        tracing::warn!("Rejecting malformed WebSocket message on connection {}", connection_id);
+                        let reply = ReplyFrame::error(None, CommandErrorCode::MalformedFrame, "Frames must be JSON objects with a type");
+                        if !recv_state.send_to_connection(&user_id, &connection_id, OutgoingFrame::Reply(reply)).await {
+                            break;
+                        }
+                        continue;
+                    };
+
+                    // Drop replayed frames before they reach any handler
+                    let seq = frame.get("seq").and_then(|v| v.as_u64());
+                    let nonce = frame.get("nonce").and_then(|v| v.as_str());
+                    let check = recv_state.replay_guard.check(user_id, &mut sequence, seq, nonce).await;
+                    match ReplyFrame::dropped(commands::request_id(&frame), check) {
+                        Some(reply) => {
+                            tracing::warn!("Dropping {:?} WebSocket frame on connection {} (seq {:?}, nonce {:?})", check, connection_id, seq, nonce);
+                            reply
+                        }
+                        None => {
+                            tracing::debug!("Received WebSocket message: {}", text);
+                            commands::handle(&frame, connection_id)
+                        }
+                    }
+                },
+                Ok(Message::Binary(_)) => {
+                    ReplyFrame::error(None, CommandErrorCode::MalformedFrame, "Frames must be JSON text")
                 },
                 Ok(Message::Close(_)) => {
                     break;
                 },
-                _ => {}
+                _ => continue,
+            };
+            if !recv_state.send_to_connection(&user_id, &connection_id, OutgoingFrame::Reply(reply)).await {
+                break;
             }
         }
     });
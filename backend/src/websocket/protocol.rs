@@ -0,0 +1,63 @@
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use super::WebSocketMessage;
+
+/// Oldest protocol version the server still speaks
+pub const MIN_PROTOCOL_VERSION: u32 = 1;
+/// Newest protocol version the server speaks
+pub const CURRENT_PROTOCOL_VERSION: u32 = 2;
+
+/// Smallest `max_payload_size` a client may declare; anything lower is raised to this
+pub const MIN_MAX_PAYLOAD_SIZE: usize = 1024;
+
+/// Features a client declares in its auth message so the server can tailor delivery per connection
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ClientCapabilities {
+    /// Client can process several change events delivered in one frame
+    pub supports_batching: bool,
+    /// Client can resume a dropped connection from its last seen event
+    pub supports_resume: bool,
+    /// Largest frame the client wants to receive, in bytes
+    pub max_payload_size: Option<usize>,
+}
+
+impl ClientCapabilities {
+    /// Read capabilities from the auth message, keeping only what the server can honor
+    pub fn from_auth_message(auth_msg: &Value) -> Self {
+        let requested: ClientCapabilities = auth_msg
+            .get("capabilities")
+            .and_then(|c| serde_json::from_value(c.clone()).ok())
+            .unwrap_or_default();
+
+        Self {
+            // Batched delivery and resume are not implemented server-side yet
+            supports_batching: false,
+            supports_resume: false,
+            max_payload_size: requested.max_payload_size.map(|size| size.max(MIN_MAX_PAYLOAD_SIZE)),
+        }
+    }
+}
+
+/// Pick the version to speak on a connection from the one requested in the auth message.
+/// Clients that predate negotiation send no version and get the legacy format.
+pub fn negotiate_version(requested: Option<u64>) -> Option<u32> {
+    let requested = requested.unwrap_or(MIN_PROTOCOL_VERSION as u64);
+    if requested < MIN_PROTOCOL_VERSION as u64 {
+        return None;
+    }
+    Some(requested.min(CURRENT_PROTOCOL_VERSION as u64) as u32)
+}
+
+/// Serialize a message in the wire format of the given protocol version
+pub fn encode(message: &WebSocketMessage, version: u32) -> serde_json::Result<String> {
+    let mut value = serde_json::to_value(message)?;
+    // v2: every frame carries a `type` discriminator so change events can be told apart from control frames
+    if version >= 2
+        && let Value::Object(ref mut fields) = value
+    {
+        fields.insert("type".to_string(), Value::String("change".to_string()));
+    }
+    serde_json::to_string(&value)
+}
@@ -0,0 +1,88 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::sync::Mutex;
+use uuid::Uuid;
+
+/// How long a nonce is remembered; retries after a reconnect must arrive within this window
+const NONCE_TTL: Duration = Duration::from_secs(10 * 60);
+/// Upper bound on remembered nonces per user, oldest are evicted first
+const MAX_NONCES_PER_USER: usize = 1000;
+
+/// Outcome of checking an inbound frame against replay protection
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FrameCheck {
+    Fresh,
+    /// The nonce was already processed, possibly on an earlier connection
+    Duplicate,
+    /// The sequence number did not increase on this connection
+    OutOfOrder,
+}
+
+/// Per-connection sequence tracking; a reconnect starts a new sequence
+#[derive(Debug, Default)]
+pub struct SequenceTracker {
+    last_seq: Option<u64>,
+}
+
+impl SequenceTracker {
+    pub fn accept(&mut self, seq: u64) -> bool {
+        if self.last_seq.is_some_and(|last| seq <= last) {
+            return false;
+        }
+        self.last_seq = Some(seq);
+        true
+    }
+}
+
+/// Remembers recently processed nonces per user so frames retried on a new connection are not applied twice
+#[derive(Clone, Default)]
+pub struct ReplayGuard {
+    seen: Arc<Mutex<HashMap<Uuid, HashMap<String, Instant>>>>,
+}
+
+impl ReplayGuard {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Validate an inbound frame's `seq` and `nonce`; both are optional
+    pub async fn check(
+        &self,
+        user_id: Uuid,
+        sequence: &mut SequenceTracker,
+        seq: Option<u64>,
+        nonce: Option<&str>,
+    ) -> FrameCheck {
+        if let Some(seq) = seq
+            && !sequence.accept(seq)
+        {
+            return FrameCheck::OutOfOrder;
+        }
+
+        let Some(nonce) = nonce else {
+            return FrameCheck::Fresh;
+        };
+
+        let mut seen = self.seen.lock().await;
+        let user_nonces = seen.entry(user_id).or_default();
+        let now = Instant::now();
+        user_nonces.retain(|_, seen_at| now.duration_since(*seen_at) < NONCE_TTL);
+
+        if user_nonces.contains_key(nonce) {
+            return FrameCheck::Duplicate;
+        }
+
+        if user_nonces.len() >= MAX_NONCES_PER_USER
+            && let Some(oldest) = user_nonces
+                .iter()
+                .min_by_key(|(_, seen_at)| **seen_at)
+                .map(|(key, _)| key.clone())
+        {
+            user_nonces.remove(&oldest);
+        }
+        user_nonces.insert(nonce.to_string(), now);
+
+        FrameCheck::Fresh
+    }
+}
@@ -0,0 +1,56 @@
+use serde::Serialize;
+use std::env;
+use std::time::Duration;
+
+/// Default window over which clients spread their reconnects after a restart notice
+const DEFAULT_RECONNECT_SPREAD_MS: u64 = 30_000;
+/// Shortest delay suggested to any client, giving the new instance time to come up
+const MIN_RECONNECT_DELAY_MS: u64 = 1_000;
+/// How long to let restart notices flush before the process exits
+pub const SHUTDOWN_DRAIN: Duration = Duration::from_millis(500);
+
+/// Broadcast to every socket before the server closes them
+#[derive(Debug, Clone)]
+pub struct ShutdownNotice {
+    /// Why the server is going away, e.g. `shutdown` or `maintenance`
+    pub reason: String,
+    pub reconnect_spread_ms: u64,
+}
+
+/// Frame sent to one connection; each gets its own randomized delay so clients
+/// don't all reconnect to the new instance at the same moment
+#[derive(Debug, Serialize)]
+pub struct ServerRestartingFrame<'a> {
+    #[serde(rename = "type")]
+    pub frame_type: &'static str,
+    pub reason: &'a str,
+    pub reconnect_after_ms: u64,
+}
+
+impl ShutdownNotice {
+    pub fn new(reason: impl Into<String>) -> Self {
+        let reconnect_spread_ms = env::var("WS_RECONNECT_SPREAD_MS")
+            .ok()
+            .and_then(|v| v.parse().ok())
+            .unwrap_or(DEFAULT_RECONNECT_SPREAD_MS);
+
+        Self {
+            reason: reason.into(),
+            reconnect_spread_ms,
+        }
+    }
+
+    pub fn frame(&self) -> ServerRestartingFrame<'_> {
+        let jitter = if self.reconnect_spread_ms == 0 {
+            0
+        } else {
+            rand::random_range(0..self.reconnect_spread_ms)
+        };
+
+        ServerRestartingFrame {
+            frame_type: "server_restarting",
+            reason: &self.reason,
+            reconnect_after_ms: MIN_RECONNECT_DELAY_MS + jitter,
+        }
+    }
+}
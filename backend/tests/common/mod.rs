@@ -0,0 +1,158 @@
+//! Harness shared by the integration tests: runs the server binary against `TEST_DATABASE_URL`
+//! (a Postgres database the tests may write to), or against a throwaway SQLite file when it isn't
+//! set, and registers users on it.
+
+#![allow(dead_code)]
+
+use std::{
+    net::TcpListener,
+    path::PathBuf,
+    process::{Child, Command, Stdio},
+    time::Duration,
+};
+
+use futures_util::StreamExt;
+use serde_json::{json, Value};
+use streamline_client::{routes, Client};
+use tokio_tungstenite::tungstenite::{self, Message};
+use uuid::Uuid;
+
+pub struct Server {
+    child: Child,
+    pub base_url: String,
+    port: u16,
+    database_url: String,
+    env: Vec<(String, String)>,
+    sqlite_dir: Option<PathBuf>,
+}
+
+impl Server {
+    pub async fn start() -> Self {
+        Self::start_with_env(&[]).await
+    }
+
+    /// Start with extra environment variables, e.g. shorter timeouts
+    pub async fn start_with_env(env: &[(&str, &str)]) -> Self {
+        let port = TcpListener::bind("127.0.0.1:0").unwrap().local_addr().unwrap().port();
+        let (database_url, sqlite_dir) = match std::env::var("TEST_DATABASE_URL") {
+            Ok(url) => (url, None),
+            Err(_) => {
+                let dir = std::env::temp_dir().join(format!("streamline-test-{}", Uuid::new_v4()));
+                (format!("sqlite://{}/streamline.db", dir.display()), Some(dir))
+            }
+        };
+        let env: Vec<(String, String)> = env.iter().map(|(key, value)| (key.to_string(), value.to_string())).collect();
+
+        let child = Self::spawn(port, &database_url, &env);
+        let server = Self { child, base_url: format!("http://127.0.0.1:{}", port), port, database_url, env, sqlite_dir };
+        server.wait_until_up().await;
+        server
+    }
+
+    fn spawn(port: u16, database_url: &str, env: &[(String, String)]) -> Child {
+        Command::new(env!("CARGO_BIN_EXE_streamline_backend"))
+            .env("DATABASE_URL", database_url)
+            .env("JWT_SECRET", "integration-test-secret-with-at-least-32-characters")
+            .env("PORT", port.to_string())
+            .env("RATE_LIMIT_PER_MINUTE", "0")
+            .env("RUST_LOG", "warn")
+            .envs(env.iter().map(|(key, value)| (key, value)))
+            .stdout(Stdio::null())
+            .spawn()
+            .expect("server binary should start")
+    }
+
+    async fn wait_until_up(&self) {
+        let http = reqwest::Client::new();
+        for _ in 0..300 {
+            if http.get(format!("{}{}", self.base_url, routes::HEALTH_LIVE)).send().await.is_ok() {
+                return;
+            }
+            tokio::time::sleep(Duration::from_millis(100)).await;
+        }
+        panic!("server did not come up on {}", self.base_url);
+    }
+
+    /// Stop the server gracefully, as a deploy would, and start it again on the same port and database
+    pub async fn restart(&mut self) {
+        let status = Command::new("kill")
+            .args(["-TERM", &self.child.id().to_string()])
+            .status()
+            .expect("kill should run");
+        assert!(status.success(), "the server should receive SIGTERM");
+        self.child.wait().unwrap();
+
+        self.child = Self::spawn(self.port, &self.database_url, &self.env);
+        self.wait_until_up().await;
+    }
+
+    pub fn ws_url(&self) -> String {
+        format!("{}{}", self.base_url.replacen("http", "ws", 1), routes::WEBSOCKET)
+    }
+
+    /// Register a new user and return their access token
+    pub async fn access_token(&self) -> String {
+        self.register_as(&format!("test-{}@example.com", Uuid::new_v4())).await
+    }
+
+    /// Register a user with the given email and return their access token
+    pub async fn register_as(&self, email: &str) -> String {
+        let body = json!({ "email": email, "password": "Integration-Test-1" });
+        let response = Client::new(&self.base_url)
+            .post::<_, Value>(routes::AUTH_REGISTER, &body)
+            .await
+            .expect("registration should succeed");
+        response.data["access_token"].as_str().unwrap().to_string()
+    }
+
+    pub fn client(&self, token: &str) -> Client {
+        Client::new(&self.base_url).with_token(token)
+    }
+
+    /// A client signed in as a new user
+    pub async fn register(&self) -> Client {
+        self.client(&self.access_token().await)
+    }
+}
+
+impl Drop for Server {
+    fn drop(&mut self) {
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+        if let Some(dir) = &self.sqlite_dir {
+            let _ = std::fs::remove_dir_all(dir);
+        }
+    }
+}
+
+/// A create body with placeholder ciphertext plus the plaintext fields in `extra`
+pub fn encrypted(extra: Value) -> Value {
+    let mut body = json!({ "encrypted_data": "ciphertext", "iv": "iv", "salt": "salt" });
+    body.as_object_mut().unwrap().extend(extra.as_object().unwrap().clone());
+    body
+}
+
+pub fn id_of(record: &Value) -> Uuid {
+    record["id"].as_str().unwrap().parse().unwrap()
+}
+
+/// Read text frames until a change event for `record_id` arrives
+pub async fn next_change<S>(socket: &mut S, record_id: Uuid) -> Value
+where
+    S: StreamExt<Item = Result<Message, tungstenite::Error>> + Unpin,
+{
+    let wait = async {
+        while let Some(frame) = socket.next().await {
+            if let Message::Text(text) = frame.unwrap() {
+                let message: Value = serde_json::from_str(&text).unwrap();
+                if message["record_id"] == record_id.to_string() {
+                    return message;
+                }
+            }
+        }
+        panic!("socket closed before the change for {} arrived", record_id);
+    };
+    tokio::time::timeout(Duration::from_secs(5), wait)
+        .await
+        .unwrap_or_else(|_| panic!("no change for {} within 5 seconds", record_id))
+}
@@ -0,0 +1,162 @@
+//! Clients reordering and moving the same siblings at once must leave one consistent order behind:
+//! every sibling at its own position, numbered from the first one without gaps.
+
+mod common;
+
+use rand::seq::SliceRandom;
+use serde_json::{json, Value};
+use streamline_client::{routes, Client};
+use uuid::Uuid;
+
+use common::{encrypted, id_of, Server};
+
+const CLIENTS: usize = 8;
+const ROUNDS: usize = 5;
+const SIBLINGS: usize = 6;
+
+/// The siblings' ids by `display_order`, failing unless they take the first positions, one each
+fn order_of(records: &[Value], ids: &[Uuid]) -> Vec<Uuid> {
+    let mut siblings: Vec<(i64, Uuid)> = records
+        .iter()
+        .filter(|record| ids.contains(&id_of(record)))
+        .map(|record| (record["display_order"].as_i64().unwrap(), id_of(record)))
+        .collect();
+    assert_eq!(siblings.len(), ids.len(), "every sibling should be listed");
+    siblings.sort();
+    let step = siblings[0].0;
+    assert!(step > 0);
+    let positions: Vec<i64> = siblings.iter().map(|(position, _)| *position).collect();
+    assert_eq!(
+        positions,
+        (1..=ids.len() as i64).map(|n| n * step).collect::<Vec<_>>(),
+        "siblings should be numbered once each: {:?}",
+        siblings
+    );
+    siblings.into_iter().map(|(_, id)| id).collect()
+}
+
+/// Reorder all or some of `ids` from several clients at once
+async fn reorder_concurrently(client: &Client, route: &'static str, ids: &[Uuid]) {
+    let mut tasks = Vec::new();
+    for _ in 0..CLIENTS {
+        let client = client.clone();
+        let ids = ids.to_vec();
+        tasks.push(tokio::spawn(async move {
+            for _ in 0..ROUNDS {
+                let mut order = ids.clone();
+                order.shuffle(&mut rand::rng());
+                order.truncate(rand::random_range(1..=ids.len()));
+                let response = client
+                    .post::<_, Vec<Value>>(route, &json!({ "ids": order }))
+                    .await
+                    .expect("reorder should succeed");
+                // A reorder sees the positions it wrote, not a mix with another one's, and puts
+                // the listed siblings first
+                let result = order_of(&response.data, &ids);
+                assert_eq!(result[..order.len()], order[..]);
+            }
+        }));
+    }
+    for task in tasks {
+        task.await.unwrap();
+    }
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn concurrent_reorders_leave_a_consistent_order() {
+    let server = Server::start().await;
+    let client = server.register().await;
+
+    // Projects nested in a folder of their own, so no other project shares their positions
+    let folder = client.post::<_, Value>(routes::PROJECTS, &encrypted(json!({}))).await.unwrap().data;
+    let mut project_ids = Vec::new();
+    for _ in 0..SIBLINGS {
+        let project = client
+            .post::<_, Value>(routes::PROJECTS, &encrypted(json!({ "parent_id": folder["id"] })))
+            .await
+            .unwrap()
+            .data;
+        project_ids.push(id_of(&project));
+    }
+
+    reorder_concurrently(&client, routes::PROJECTS_REORDER, &project_ids).await;
+    let projects = client
+        .get::<Vec<Value>>(&format!("{}?parent_id={}", routes::PROJECTS, folder["id"].as_str().unwrap()))
+        .await
+        .unwrap()
+        .data;
+    order_of(&projects, &project_ids);
+
+    let mut task_ids = Vec::new();
+    for _ in 0..SIBLINGS {
+        let task = client
+            .post::<_, Value>(routes::CAN_DO_LIST, &encrypted(json!({ "project_id": folder["id"] })))
+            .await
+            .unwrap()
+            .data;
+        task_ids.push(id_of(&task));
+    }
+
+    reorder_concurrently(&client, routes::CAN_DO_LIST_REORDER, &task_ids).await;
+    let tasks = client
+        .get::<Vec<Value>>(&format!("{}?project_id={}", routes::CAN_DO_LIST, folder["id"].as_str().unwrap()))
+        .await
+        .unwrap()
+        .data;
+    order_of(&tasks, &task_ids);
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn concurrent_moves_and_reorders_renumber_siblings_once() {
+    let server = Server::start().await;
+    let client = server.register().await;
+
+    let folder = client.post::<_, Value>(routes::PROJECTS, &encrypted(json!({}))).await.unwrap().data;
+    let folder_id = id_of(&folder);
+    let mut project_ids = Vec::new();
+    for _ in 0..SIBLINGS {
+        let project = client
+            .post::<_, Value>(routes::PROJECTS, &encrypted(json!({ "parent_id": folder_id })))
+            .await
+            .unwrap()
+            .data;
+        project_ids.push(id_of(&project));
+    }
+
+    // Half the clients move a project to a random spot among its siblings while the rest reorder them
+    let mut tasks = Vec::new();
+    for index in 0..CLIENTS {
+        let client = client.clone();
+        let ids = project_ids.clone();
+        tasks.push(tokio::spawn(async move {
+            for round in 0..ROUNDS {
+                if index % 2 == 0 {
+                    let id = ids[(index + round) % ids.len()];
+                    let body = json!({ "parent_id": folder_id, "position": (index * round) % ids.len() });
+                    client
+                        .post::<_, Value>(&routes::project_move(id), &body)
+                        .await
+                        .expect("move should succeed");
+                } else {
+                    let mut order = ids.clone();
+                    order.shuffle(&mut rand::rng());
+                    client
+                        .post::<_, Vec<Value>>(routes::PROJECTS_REORDER, &json!({ "ids": order }))
+                        .await
+                        .expect("reorder should succeed");
+                }
+            }
+        }));
+    }
+    for task in tasks {
+        task.await.unwrap();
+    }
+
+    // Both renumber every sibling, so none is left behind or doubled up
+    let projects = client
+        .get::<Vec<Value>>(&format!("{}?parent_id={}", routes::PROJECTS, folder_id))
+        .await
+        .unwrap()
+        .data;
+    order_of(&projects, &project_ids);
+}
@@ -0,0 +1,66 @@
+//! `If-None-Match` must only answer `304` while the body is the one the client has, even when a
+//! change doesn't touch the record's `updated_at`, and the same tag must still work as `If-Match`.
+
+mod common;
+
+use reqwest::{header, StatusCode};
+use serde_json::{json, Value};
+use streamline_client::routes;
+
+use common::{encrypted, id_of, Server};
+
+/// Status and `ETag` of a `GET`, sent with `If-None-Match` when a tag is given
+async fn get(server: &Server, token: &str, path: &str, if_none_match: Option<&str>) -> (StatusCode, String) {
+    let mut request = reqwest::Client::new().get(format!("{}{}", server.base_url, path)).bearer_auth(token);
+    if let Some(etag) = if_none_match {
+        request = request.header(header::IF_NONE_MATCH, etag);
+    }
+    let response = request.send().await.unwrap();
+    let etag = response.headers()[header::ETAG].to_str().unwrap().to_string();
+    (response.status(), etag)
+}
+
+#[tokio::test]
+async fn checking_in_a_habit_invalidates_its_tag() {
+    let server = Server::start().await;
+    let token = server.access_token().await;
+    let client = server.client(&token);
+
+    let habit = client.post::<_, Value>(routes::HABITS, &encrypted(json!({}))).await.unwrap().data;
+    let path = routes::habit(id_of(&habit));
+    let (status, etag) = get(&server, &token, &path, None).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(get(&server, &token, &path, Some(&etag)).await.0, StatusCode::NOT_MODIFIED);
+
+    client.post::<_, Value>(&routes::habit_checkin(id_of(&habit)), &json!({})).await.unwrap();
+    let (status, etag) = get(&server, &token, &path, Some(&etag)).await;
+    assert_eq!(status, StatusCode::OK, "the check-in changed the streak");
+
+    let response = reqwest::Client::new()
+        .put(format!("{}{}", server.base_url, path))
+        .bearer_auth(&token)
+        .header(header::IF_MATCH, &etag)
+        .json(&json!({ "encrypted_data": "changed" }))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK, "a read's tag is accepted as If-Match");
+}
+
+#[tokio::test]
+async fn completing_a_linked_task_invalidates_the_goals_tag() {
+    let server = Server::start().await;
+    let token = server.access_token().await;
+    let client = server.client(&token);
+
+    let goal = client.post::<_, Value>(routes::GOALS, &encrypted(json!({}))).await.unwrap().data;
+    let task = client.post::<_, Value>(routes::CAN_DO_LIST, &encrypted(json!({}))).await.unwrap().data;
+    client.put::<_, Value>(&routes::goal_task(id_of(&goal), id_of(&task)), &json!({})).await.unwrap();
+    let path = routes::goal(id_of(&goal));
+    let (_, etag) = get(&server, &token, &path, None).await;
+
+    let task_path = format!("{}/{}", routes::CAN_DO_LIST, id_of(&task));
+    client.put::<_, Value>(&task_path, &json!({ "completed_at": "2030-01-01T09:00:00Z" })).await.unwrap();
+    let (status, _) = get(&server, &token, &path, Some(&etag)).await;
+    assert_eq!(status, StatusCode::OK, "the goal's progress changed without its updated_at");
+}
@@ -0,0 +1,44 @@
+//! The inbound email hook creates tasks for whoever owns the recipient address, so only the mail
+//! server may call it, and a leaked address can only add so many tasks.
+
+mod common;
+
+use reqwest::StatusCode;
+use serde_json::{json, Value};
+use streamline_client::routes;
+
+use common::Server;
+
+const SECRET: &str = "integration-test-inbound-secret";
+
+async fn receive(server: &Server, address: &str, secret: Option<&str>) -> StatusCode {
+    let mut request = reqwest::Client::new()
+        .post(format!("{}{}", server.base_url, routes::INBOUND_EMAIL_RECEIVE))
+        .json(&json!({ "to": address, "subject": "Buy milk" }));
+    if let Some(secret) = secret {
+        request = request.bearer_auth(secret);
+    }
+    request.send().await.unwrap().status()
+}
+
+#[tokio::test]
+async fn the_hook_needs_the_secret_and_limits_each_address() {
+    let server = Server::start_with_env(&[("INBOUND_EMAIL_DOMAIN", "in.example.com"), ("INBOUND_EMAIL_SECRET", SECRET)]).await;
+    let client = server.register().await;
+    let address = client.post::<_, Value>(routes::INBOUND_EMAIL, &json!({})).await.unwrap().data;
+    let address = address["address"].as_str().unwrap();
+
+    assert_eq!(receive(&server, address, None).await, StatusCode::UNAUTHORIZED);
+    assert_eq!(receive(&server, address, Some("wrong-secret")).await, StatusCode::UNAUTHORIZED);
+    let tasks = client.get::<Vec<Value>>(routes::CAN_DO_LIST).await.unwrap().data;
+    assert!(tasks.is_empty(), "unauthenticated mail created tasks");
+
+    let mut statuses = Vec::new();
+    for _ in 0..11 {
+        statuses.push(receive(&server, address, Some(SECRET)).await);
+    }
+    assert!(statuses[..10].iter().all(|status| *status == StatusCode::OK), "{:?}", statuses);
+    assert_eq!(statuses[10], StatusCode::TOO_MANY_REQUESTS);
+    let tasks = client.get::<Vec<Value>>(routes::CAN_DO_LIST).await.unwrap().data;
+    assert_eq!(tasks.len(), 10);
+}
@@ -0,0 +1,66 @@
+//! The organization export hands an owner the records their members share, so it must leave out
+//! what the API itself wouldn't show: trashed records and columns kept for the server's own use.
+
+mod common;
+
+use async_zip::base::read::mem::ZipFileReader;
+use futures_util::AsyncReadExt;
+use serde_json::{json, Value};
+use streamline_client::routes;
+
+use common::{encrypted, id_of, Server};
+
+async fn read_table(zip: &ZipFileReader, table: &str) -> Vec<Value> {
+    let name = format!("{}.json", table);
+    let index = zip
+        .file()
+        .entries()
+        .iter()
+        .position(|entry| entry.filename().as_str().is_ok_and(|filename| filename == name))
+        .unwrap_or_else(|| panic!("the archive has no {}", name));
+    let mut data = Vec::new();
+    zip.reader_with_entry(index).await.unwrap().read_to_end(&mut data).await.unwrap();
+    serde_json::from_slice(&data).unwrap()
+}
+
+#[tokio::test]
+async fn trashed_records_are_left_out_of_the_organization_export() {
+    let server = Server::start().await;
+    let token = server.access_token().await;
+    let owner = server.client(&token);
+
+    let organization = owner.post::<_, Value>(routes::ORGANIZATIONS, &json!({ "name": "Acme" })).await.unwrap().data;
+    let organization_id = id_of(&organization);
+    let project = owner
+        .post::<_, Value>(routes::PROJECTS, &encrypted(json!({ "organization_id": organization_id })))
+        .await
+        .unwrap()
+        .data;
+    let project_id = id_of(&project);
+    let task = json!({ "project_id": project_id, "search_tokens": ["abcd"] });
+    let kept = owner.post::<_, Value>(routes::CAN_DO_LIST, &encrypted(task.clone())).await.unwrap().data;
+    let trashed = owner.post::<_, Value>(routes::CAN_DO_LIST, &encrypted(task)).await.unwrap().data;
+    owner.delete::<Value>(&format!("{}/{}", routes::CAN_DO_LIST, id_of(&trashed))).await.unwrap();
+    let trashed_project = owner
+        .post::<_, Value>(routes::PROJECTS, &encrypted(json!({ "organization_id": organization_id })))
+        .await
+        .unwrap()
+        .data;
+    owner.delete::<Value>(&routes::project(id_of(&trashed_project))).await.unwrap();
+
+    let response = reqwest::Client::new()
+        .get(format!("{}{}", server.base_url, routes::organization_export(organization_id)))
+        .bearer_auth(&token)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), reqwest::StatusCode::OK);
+    let zip = ZipFileReader::new(response.bytes().await.unwrap().to_vec()).await.unwrap();
+
+    let projects = read_table(&zip, "projects").await;
+    assert_eq!(projects.iter().map(|project| &project["id"]).collect::<Vec<_>>(), [&project["id"]]);
+    let tasks = read_table(&zip, "can_do_list").await;
+    assert_eq!(tasks.iter().map(|task| &task["id"]).collect::<Vec<_>>(), [&kept["id"]]);
+    assert!(tasks[0].get("search_tokens").is_none(), "internal columns are exported: {}", tasks[0]);
+    assert!(tasks[0]["pending_plaintext"].is_null());
+}
@@ -0,0 +1,170 @@
+//! The route table in `streamline-client` is the contract between the server and its clients: every
+//! route in it must be served, and the core record types must behave alike, including the
+//! WebSocket broadcasts that keep other devices in sync.
+
+mod common;
+
+use futures_util::{SinkExt, StreamExt};
+use serde_json::{json, Value};
+use streamline_client::routes;
+use tokio_tungstenite::{connect_async, tungstenite::Message};
+use uuid::Uuid;
+
+use common::{encrypted, id_of, next_change, Server};
+
+/// `(name, pattern)` of every route constant in the client's route table
+fn route_table() -> Vec<(&'static str, &'static str)> {
+    include_str!("../client/src/routes.rs")
+        .lines()
+        .filter_map(|line| line.strip_prefix("pub const "))
+        .filter_map(|line| {
+            let (name, rest) = line.split_once(": &str = \"")?;
+            Some((name, rest.strip_suffix("\";")?))
+        })
+        .collect()
+}
+
+#[tokio::test]
+async fn every_client_route_is_served() {
+    let server = Server::start().await;
+    let token = server.access_token().await;
+    let http = reqwest::Client::new();
+
+    let table = route_table();
+    assert!(table.len() > 50, "the route table should be parsed, got {} routes", table.len());
+    for (name, pattern) in table {
+        let path = pattern
+            .replace("{id}", &Uuid::new_v4().to_string())
+            .replace("{user_id}", &Uuid::new_v4().to_string())
+            .replace("{vote_id}", &Uuid::new_v4().to_string())
+            .replace("{revision}", &Uuid::new_v4().to_string())
+            .replace("{token}", "parity-check");
+        let response = http
+            .get(format!("{}{}", server.base_url, path))
+            .bearer_auth(&token)
+            .send()
+            .await
+            .unwrap();
+
+        // The router answers unknown paths with a bare 404; a handler's 404 carries an error code
+        let status = response.status();
+        let body: Value = response.json().await.unwrap_or(Value::Null);
+        assert!(
+            status != reqwest::StatusCode::NOT_FOUND || body["code"] == "NOT_FOUND",
+            "{} ({}) is not served",
+            name,
+            pattern
+        );
+    }
+}
+
+#[tokio::test]
+async fn routes_are_served_under_each_api_version() {
+    let server = Server::start().await;
+    let token = server.access_token().await;
+    let http = reqwest::Client::new();
+    let get = async |path: &str, version: Option<&str>| {
+        let mut request = http.get(format!("{}{}", server.base_url, path)).bearer_auth(&token);
+        if let Some(version) = version {
+            request = request.header("api-version", version);
+        }
+        let response = request.send().await.unwrap();
+        let served_in = response.headers().get("api-version").map(|value| value.to_str().unwrap().to_string());
+        let body: Value = response.json().await.unwrap();
+        (served_in, body)
+    };
+
+    let versioned = routes::PROJECTS.replacen("/api", "/api/v1", 1);
+    let (served_in, body) = get(&versioned, None).await;
+    assert_eq!(served_in.as_deref(), Some("1"));
+    assert_eq!(body["data"], get(routes::PROJECTS, None).await.1["data"]);
+    assert_eq!(get(routes::PROJECTS, Some("1")).await.0.as_deref(), Some("1"));
+
+    for (path, version) in [(routes::PROJECTS.replacen("/api", "/api/v99", 1), None), (routes::PROJECTS.to_string(), Some("0"))] {
+        let (_, body) = get(&path, version).await;
+        assert_eq!(body["code"], "UNSUPPORTED_API_VERSION", "{} {:?}", path, version);
+    }
+}
+
+#[tokio::test]
+async fn user_settings_are_stored_per_user() {
+    let server = Server::start().await;
+    let alice = server.register().await;
+    let bob = server.register().await;
+
+    let settings = json!({ "encrypted_data": "alice-settings", "iv": "iv", "salt": "salt" });
+    alice.put::<_, Value>(routes::USER_SETTINGS, &settings).await.unwrap();
+    let updated = json!({
+        "encrypted_data": "alice-settings-2",
+        "iv": "iv-2",
+        "salt": "salt-2",
+        "schema_version": 2,
+        "timezone": "Europe/Berlin",
+        "week_start": 7
+    });
+    alice.put::<_, Value>(routes::USER_SETTINGS, &updated).await.unwrap();
+
+    let mut stored = alice.get::<Value>(routes::USER_SETTINGS).await.unwrap().data;
+    assert!(stored.as_object_mut().unwrap().remove("updated_at").is_some());
+    assert_eq!(stored, updated);
+    let others = bob.get::<Value>(routes::USER_SETTINGS).await.unwrap().data;
+    assert_eq!(others["encrypted_data"], "{}");
+    assert_eq!(others["timezone"], "UTC");
+}
+
+#[tokio::test]
+async fn rest_writes_are_broadcast_over_websocket() {
+    let server = Server::start().await;
+    let token = server.access_token().await;
+    let client = server.client(&token);
+
+    let (mut socket, _) = connect_async(server.ws_url()).await.unwrap();
+    socket
+        .send(Message::text(json!({ "token": token, "version": 2 }).to_string()))
+        .await
+        .unwrap();
+    let Some(Ok(Message::Text(reply))) = socket.next().await else {
+        panic!("expected an auth reply");
+    };
+    let reply: Value = serde_json::from_str(&reply).unwrap();
+    assert_eq!(reply["type"], "auth_success");
+
+    let calendar = client.post::<_, Value>(routes::CALENDARS, &encrypted(json!({}))).await.unwrap().data;
+    let calendar_id = id_of(&calendar);
+    let creates = [
+        ("projects", routes::PROJECTS, json!({})),
+        ("can_do_list", routes::CAN_DO_LIST, json!({})),
+        (
+            "calendar_events",
+            routes::CALENDAR_EVENTS,
+            json!({
+                "calendar_id": calendar_id,
+                "start_time": "2030-01-01T09:00:00Z",
+                "end_time": "2030-01-01T10:00:00Z",
+            }),
+        ),
+        ("notes", routes::NOTES, json!({})),
+    ];
+
+    let change = next_change(&mut socket, calendar_id).await;
+    assert_eq!((change["table"].as_str(), change["event_type"].as_str()), (Some("calendars"), Some("INSERT")));
+
+    for (table, route, extra) in creates {
+        let record = client.post::<_, Value>(route, &encrypted(extra)).await.unwrap().data;
+        let id = id_of(&record);
+        let change = next_change(&mut socket, id).await;
+        assert_eq!(change["type"], "change", "{} insert", table);
+        assert_eq!((change["table"].as_str(), change["event_type"].as_str()), (Some(table), Some("INSERT")));
+        assert_eq!(change["data"]["id"], record["id"], "{} insert carries the record", table);
+
+        let path = format!("{}/{}", route, id);
+        client.put::<_, Value>(&path, &json!({ "encrypted_data": "changed" })).await.unwrap();
+        let change = next_change(&mut socket, id).await;
+        assert_eq!((change["table"].as_str(), change["event_type"].as_str()), (Some(table), Some("UPDATE")));
+        assert_eq!(change["data"]["encrypted_data"], "changed", "{} update carries the new state", table);
+
+        client.delete::<Value>(&path).await.unwrap();
+        let change = next_change(&mut socket, id).await;
+        assert_eq!((change["table"].as_str(), change["event_type"].as_str()), (Some(table), Some("DELETE")));
+    }
+}
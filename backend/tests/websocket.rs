@@ -0,0 +1,303 @@
+//! Conformance of the `/ws` protocol described in API.md: the auth handshake, who receives which
+//! change events, the heartbeat and what clients see across a server restart.
+
+mod common;
+
+use std::time::Duration;
+
+use futures_util::{SinkExt, StreamExt};
+use serde_json::{json, Value};
+use streamline_client::{models::project::CreateProjectRequest, routes, Subscriber, SubscriberEvent};
+use tokio::net::TcpStream;
+use tokio_tungstenite::{connect_async, tungstenite::Message, MaybeTlsStream, WebSocketStream};
+use uuid::Uuid;
+
+use common::{encrypted, id_of, next_change, Server};
+
+type Socket = WebSocketStream<MaybeTlsStream<TcpStream>>;
+
+/// Open a socket, send `auth` as its first frame and return the server's reply
+async fn connect(server: &Server, auth: Value) -> (Socket, Value) {
+    let (mut socket, _) = connect_async(server.ws_url()).await.unwrap();
+    socket.send(Message::text(auth.to_string())).await.unwrap();
+    let reply = next_frame(&mut socket).await.expect("the server should reply to the auth frame");
+    (socket, reply)
+}
+
+/// A socket authenticated with protocol version 2, and its connection id
+async fn authenticated(server: &Server, token: &str) -> (Socket, Uuid) {
+    let (socket, reply) = connect(server, json!({ "token": token, "version": 2 })).await;
+    assert_eq!(reply["type"], "auth_success", "{}", reply);
+    (socket, reply["connection_id"].as_str().unwrap().parse().unwrap())
+}
+
+/// The next text frame, or `None` once the server closed the socket
+async fn next_frame(socket: &mut Socket) -> Option<Value> {
+    let wait = async {
+        while let Some(frame) = socket.next().await {
+            match frame {
+                Ok(Message::Text(text)) => return Some(serde_json::from_str(&text).unwrap()),
+                Ok(Message::Close(_)) | Err(_) => return None,
+                Ok(_) => {}
+            }
+        }
+        None
+    };
+    tokio::time::timeout(Duration::from_secs(5), wait).await.expect("no frame within 5 seconds")
+}
+
+async fn next_event(subscriber: &mut Subscriber) -> Option<SubscriberEvent> {
+    tokio::time::timeout(Duration::from_secs(5), subscriber.next()).await.expect("no event within 5 seconds")
+}
+
+/// Fail if a text frame arrives within `window`
+async fn assert_silent(socket: &mut Socket, window: Duration) {
+    let wait = async {
+        while let Some(frame) = socket.next().await {
+            if let Ok(Message::Text(text)) = frame {
+                return text;
+            }
+        }
+        panic!("socket closed");
+    };
+    if let Ok(text) = tokio::time::timeout(window, wait).await {
+        panic!("expected no frame, got {}", text);
+    }
+}
+
+#[tokio::test]
+async fn auth_success_negotiates_version_and_capabilities() {
+    let server = Server::start().await;
+    let token = server.access_token().await;
+
+    let auth = json!({
+        "token": token,
+        "version": 99,
+        "capabilities": { "supports_batching": true, "supports_resume": true, "max_payload_size": 10 },
+    });
+    let (_socket, reply) = connect(&server, auth).await;
+    assert_eq!(reply["type"], "auth_success");
+    assert!(reply["connection_id"].as_str().unwrap().parse::<Uuid>().is_ok());
+    assert_eq!(reply["version"], 2, "a newer client is talked to in the server's newest version");
+    assert_eq!(reply["server_version"], 2);
+    // Only what the server implements is accepted, and tiny payload limits are raised
+    assert_eq!(
+        reply["capabilities"],
+        json!({ "supports_batching": false, "supports_resume": false, "max_payload_size": 1024 })
+    );
+
+    // Clients that predate negotiation get version 1 frames, without a `type`
+    let (mut legacy, reply) = connect(&server, json!({ "token": token })).await;
+    assert_eq!(reply["version"], 1);
+    let project = server.client(&token).post::<_, Value>(routes::PROJECTS, &encrypted(json!({}))).await.unwrap().data;
+    let change = next_change(&mut legacy, id_of(&project)).await;
+    assert_eq!(change["event_type"], "INSERT");
+    assert!(change.get("type").is_none(), "{}", change);
+}
+
+#[tokio::test]
+async fn auth_failures_are_reported_and_close_the_socket() {
+    let server = Server::start_with_env(&[("WS_HEARTBEAT_INTERVAL_MS", "200"), ("WS_HEARTBEAT_TIMEOUT_MS", "600")]).await;
+    let token = server.access_token().await;
+
+    for (auth, message) in [
+        (json!({ "token": "not-a-jwt", "version": 2 }), "Authentication failed"),
+        (json!({ "version": 2 }), "Authentication failed"),
+        (json!({ "token": token, "version": 0 }), "Unsupported protocol version, server supports 1 to 2"),
+    ] {
+        let (mut socket, reply) = connect(&server, auth.clone()).await;
+        assert_eq!(reply["type"], "auth_error", "{}", auth);
+        assert_eq!(reply["message"], message);
+        assert_eq!((reply["min_version"].as_u64(), reply["server_version"].as_u64()), (Some(1), Some(2)));
+        assert!(next_frame(&mut socket).await.is_none(), "the socket should close after {}", auth);
+    }
+
+    // A socket that never authenticates is dropped once the heartbeat timeout passes
+    let (mut socket, _) = connect_async(server.ws_url()).await.unwrap();
+    let reply = next_frame(&mut socket).await.unwrap();
+    assert_eq!((reply["type"].as_str(), reply["message"].as_str()), (Some("auth_error"), Some("Authentication timed out")));
+    assert!(next_frame(&mut socket).await.is_none());
+}
+
+#[tokio::test]
+async fn writes_skip_the_connection_that_made_them() {
+    let server = Server::start().await;
+    let token = server.access_token().await;
+    let (mut origin, origin_id) = authenticated(&server, &token).await;
+    let (mut other, _) = authenticated(&server, &token).await;
+
+    let tagged = server.client(&token).with_connection_id(origin_id);
+    let project = tagged.post::<_, Value>(routes::PROJECTS, &encrypted(json!({}))).await.unwrap().data;
+    let id = id_of(&project);
+    assert_eq!(next_change(&mut other, id).await["event_type"], "INSERT");
+    assert_silent(&mut origin, Duration::from_millis(500)).await;
+
+    // Batch endpoints skip it too
+    let reordered = json!({ "ids": [id] });
+    tagged.post::<_, Value>(routes::PROJECTS_REORDER, &reordered).await.unwrap();
+    tagged.post::<_, Value>(&routes::project_duplicate(id), &json!({})).await.unwrap();
+    assert_eq!(next_change(&mut other, id).await["event_type"], "UPDATE");
+    assert_silent(&mut origin, Duration::from_millis(500)).await;
+
+    // Untagged writes reach every connection
+    server.client(&token).delete::<Value>(&routes::project(id)).await.unwrap();
+    assert_eq!(next_change(&mut origin, id).await["event_type"], "DELETE");
+    assert_eq!(next_change(&mut other, id).await["event_type"], "DELETE");
+}
+
+#[tokio::test]
+async fn changes_reach_only_users_who_can_see_the_record() {
+    let server = Server::start().await;
+    let alice_token = server.access_token().await;
+    let bob_email = format!("bob-{}@example.com", Uuid::new_v4());
+    let bob_token = server.register_as(&bob_email).await;
+    let alice = server.client(&alice_token);
+    let (mut alice_socket, _) = authenticated(&server, &alice_token).await;
+    let (mut bob_socket, _) = authenticated(&server, &bob_token).await;
+
+    let project = alice.post::<_, Value>(routes::PROJECTS, &encrypted(json!({}))).await.unwrap().data;
+    let project_id = id_of(&project);
+    next_change(&mut alice_socket, project_id).await;
+    assert_silent(&mut bob_socket, Duration::from_millis(500)).await;
+
+    // Once the project is shared, its tasks reach Bob as well
+    let member = json!({ "email": bob_email, "role": "viewer" });
+    alice.post::<_, Value>(&routes::project_members(project_id), &member).await.unwrap();
+    let task = alice
+        .post::<_, Value>(routes::CAN_DO_LIST, &encrypted(json!({ "project_id": project_id })))
+        .await
+        .unwrap()
+        .data;
+    let change = next_change(&mut bob_socket, id_of(&task)).await;
+    assert_eq!((change["table"].as_str(), change["event_type"].as_str()), (Some("can_do_list"), Some("INSERT")));
+
+    // Alice's other records stay private
+    let private = alice.post::<_, Value>(routes::NOTES, &encrypted(json!({}))).await.unwrap().data;
+    next_change(&mut alice_socket, id_of(&private)).await;
+    assert_silent(&mut bob_socket, Duration::from_millis(500)).await;
+}
+
+#[tokio::test]
+async fn oversized_changes_are_sent_without_data() {
+    let server = Server::start().await;
+    let token = server.access_token().await;
+    let auth = json!({ "token": token, "version": 2, "capabilities": { "max_payload_size": 1024 } });
+    let (mut limited, _) = connect(&server, auth).await;
+    let (mut unlimited, _) = authenticated(&server, &token).await;
+
+    let body = json!({ "encrypted_data": "x".repeat(4096), "iv": "iv", "salt": "salt" });
+    let note = server.client(&token).post::<_, Value>(routes::NOTES, &body).await.unwrap().data;
+    let id = id_of(&note);
+    assert!(next_change(&mut limited, id).await["data"].is_null());
+    assert_eq!(next_change(&mut unlimited, id).await["data"]["encrypted_data"], note["encrypted_data"]);
+}
+
+#[tokio::test]
+async fn client_frames_are_answered_with_ack_or_error() {
+    let server = Server::start().await;
+    let token = server.access_token().await;
+    let (mut socket, _) = authenticated(&server, &token).await;
+
+    let mut reply_to = async |frame: String| {
+        socket.send(Message::text(frame)).await.unwrap();
+        next_frame(&mut socket).await.expect("a reply")
+    };
+    let frame = json!({ "type": "subscribe", "table": "projects", "request_id": "a", "seq": 1, "nonce": "n-1" });
+    assert_eq!(reply_to(frame.to_string()).await, json!({ "type": "ack", "request_id": "a" }));
+    assert_eq!(reply_to(json!({ "type": "ping", "request_id": 7, "seq": 2 }).to_string()).await, json!({ "type": "ack", "request_id": 7 }));
+
+    for (frame, code) in [
+        ("not json".to_string(), "MALFORMED_FRAME"),
+        (json!({ "request_id": "b" }).to_string(), "MALFORMED_FRAME"),
+        (json!({ "type": "teleport", "request_id": "b" }).to_string(), "UNKNOWN_COMMAND"),
+        (json!({ "type": "subscribe", "request_id": "b" }).to_string(), "INVALID_COMMAND"),
+        (json!({ "type": "ping", "request_id": "b", "seq": 2 }).to_string(), "OUT_OF_ORDER_FRAME"),
+        (json!({ "type": "ping", "request_id": "b", "nonce": "n-1" }).to_string(), "DUPLICATE_FRAME"),
+    ] {
+        let reply = reply_to(frame.clone()).await;
+        assert_eq!((reply["type"].as_str(), reply["code"].as_str()), (Some("error"), Some(code)), "{}: {}", frame, reply);
+        let request_id = if frame == "not json" { Value::Null } else { json!("b") };
+        assert_eq!(reply["request_id"], request_id, "{}", frame);
+        assert!(reply["message"].is_string());
+    }
+}
+
+#[tokio::test]
+async fn heartbeat_keeps_live_sockets_and_drops_silent_ones() {
+    let server = Server::start_with_env(&[("WS_HEARTBEAT_INTERVAL_MS", "200"), ("WS_HEARTBEAT_TIMEOUT_MS", "600")]).await;
+    let token = server.access_token().await;
+
+    // Reading the socket answers the server's pings, so it stays open well past the timeout
+    let (mut live, _) = authenticated(&server, &token).await;
+    let mut pings = 0;
+    let deadline = tokio::time::Instant::now() + Duration::from_millis(1500);
+    while let Ok(frame) = tokio::time::timeout_at(deadline, live.next()).await {
+        match frame {
+            Some(Ok(Message::Ping(_))) => pings += 1,
+            other => panic!("expected only pings, got {:?}", other),
+        }
+    }
+    assert!(pings >= 3, "expected a ping every 200ms, got {}", pings);
+    let project = server.client(&token).post::<_, Value>(routes::PROJECTS, &encrypted(json!({}))).await.unwrap().data;
+    next_change(&mut live, id_of(&project)).await;
+
+    // A client that stops answering is disconnected
+    let (mut silent, _) = authenticated(&server, &token).await;
+    tokio::time::sleep(Duration::from_millis(1500)).await;
+    assert!(next_frame(&mut silent).await.is_none(), "the silent socket should be closed");
+}
+
+#[tokio::test]
+async fn clients_are_told_to_reconnect_before_a_restart() {
+    let mut server = Server::start_with_env(&[("WS_RECONNECT_SPREAD_MS", "0")]).await;
+    let token = server.access_token().await;
+    let (mut socket, _) = authenticated(&server, &token).await;
+
+    server.restart().await;
+    let notice = next_frame(&mut socket).await.expect("a restart notice");
+    assert_eq!(notice, json!({ "type": "server_restarting", "reason": "shutdown", "reconnect_after_ms": 1000 }));
+    assert!(next_frame(&mut socket).await.is_none(), "the socket should close after the notice");
+
+    // The session outlives the restart: the same token reconnects and receives changes again.
+    // Resume isn't supported, so changes missed in between are fetched from /api/sync.
+    let (mut socket, _) = authenticated(&server, &token).await;
+    let project = server.client(&token).post::<_, Value>(routes::PROJECTS, &encrypted(json!({}))).await.unwrap().data;
+    assert_eq!(next_change(&mut socket, id_of(&project)).await["event_type"], "INSERT");
+}
+
+#[tokio::test]
+async fn subscriber_reconnects_after_a_restart() {
+    let mut server = Server::start_with_env(&[("WS_RECONNECT_SPREAD_MS", "0")]).await;
+    let client = server.register().await;
+    let mut subscriber = client.subscribe();
+    assert!(matches!(next_event(&mut subscriber).await, Some(SubscriberEvent::Connected { .. })));
+
+    server.restart().await;
+    assert!(matches!(next_event(&mut subscriber).await, Some(SubscriberEvent::Disconnected)));
+    let Some(SubscriberEvent::Connected { connection_id }) = next_event(&mut subscriber).await else {
+        panic!("the subscriber should reconnect");
+    };
+
+    let request = CreateProjectRequest { encrypted_data: "ciphertext".into(), ..Default::default() };
+    let project = client.create_project(&request).await.unwrap();
+    let Some(SubscriberEvent::Change(change)) = next_event(&mut subscriber).await else {
+        panic!("expected the project's change event");
+    };
+    assert_eq!((change.table.as_str(), change.record_id), ("projects", Some(project.id)));
+
+    // Writes tagged with the subscriber's connection aren't echoed back to it
+    let tagged = client.with_connection_id(connection_id);
+    tagged.delete_project(project.id).await.unwrap();
+    assert!(tokio::time::timeout(Duration::from_millis(500), subscriber.next()).await.is_err());
+}
+
+#[tokio::test]
+async fn subscriber_stops_when_the_token_is_rejected() {
+    let server = Server::start().await;
+    let mut subscriber = server.client("not-a-jwt").subscribe();
+    let Some(SubscriberEvent::AuthFailed { message }) = next_event(&mut subscriber).await else {
+        panic!("expected the token to be rejected");
+    };
+    assert_eq!(message, "Authentication failed");
+    assert!(next_event(&mut subscriber).await.is_none());
+}